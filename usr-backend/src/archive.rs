@@ -0,0 +1,154 @@
+use std::io::{Cursor, Write};
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::Local;
+use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection, QueryResult, Statement};
+use tracing::error;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{manifest, schema, UsrState};
+
+/// Tables that belong to a season, and how their rows are narrowed to it.
+/// Every other table is archived whole.
+const SEASON_FILTERS: [(&str, &str); 2] = [
+    ("orders", "season = ?"),
+    (
+        "order_status",
+        "order_id IN (SELECT id FROM orders WHERE season = ?)",
+    ),
+];
+
+/// Renders a cell of any SQLite type as text, leaving NULLs empty
+fn cell(row: &QueryResult, i: usize) -> String {
+    if let Ok(value) = row.try_get_by_index::<Option<i64>>(i) {
+        value.map(|x| x.to_string()).unwrap_or_default()
+    } else if let Ok(value) = row.try_get_by_index::<Option<f64>>(i) {
+        value.map(|x| x.to_string()).unwrap_or_default()
+    } else if let Ok(value) = row.try_get_by_index::<Option<String>>(i) {
+        value.unwrap_or_default()
+    } else if let Ok(value) = row.try_get_by_index::<Option<Vec<u8>>>(i) {
+        value
+            .unwrap_or_default()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    } else {
+        String::new()
+    }
+}
+
+/// What to select from `table`. Postgres only decodes its columns as the
+/// types they were created with, so there every column is read as text.
+async fn select(db: &DatabaseConnection, table: &str) -> anyhow::Result<String> {
+    let backend = db.get_database_backend();
+    if backend != DatabaseBackend::Postgres {
+        return Ok("*".to_string());
+    }
+    let columns: Vec<String> = db
+        .query_all(Statement::from_sql_and_values(
+            backend,
+            "SELECT column_name AS name FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1 ORDER BY ordinal_position",
+            [table.into()],
+        ))
+        .await?
+        .into_iter()
+        .map(|row| row.try_get("", "name"))
+        .collect::<Result<_, _>>()?;
+    Ok(columns
+        .iter()
+        .map(|column| format!("CAST(\"{column}\" AS TEXT) AS \"{column}\""))
+        .collect::<Vec<_>>()
+        .join(", "))
+}
+
+async fn table_csv(db: &DatabaseConnection, table: &str, season: u16) -> anyhow::Result<Vec<u8>> {
+    let backend = db.get_database_backend();
+    let select = select(db, table).await?;
+    let statement = match SEASON_FILTERS.iter().find(|(name, _)| *name == table) {
+        Some((_, filter)) => Statement::from_sql_and_values(
+            backend,
+            schema::placeholders(
+                backend,
+                &format!("SELECT {select} FROM \"{table}\" WHERE {filter}"),
+            ),
+            [season.into()],
+        ),
+        None => Statement::from_string(backend, format!("SELECT {select} FROM \"{table}\"")),
+    };
+    let rows = db.query_all(statement).await?;
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    if let Some(first) = rows.first() {
+        let columns = first.column_names();
+        writer.write_record(&columns)?;
+        for row in &rows {
+            writer.write_record((0..columns.len()).map(|i| cell(row, i)))?;
+        }
+    }
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+/// Everything a season leaves behind, zipped up for next year's officers and
+/// the university archive: every table as CSV, with orders and their statuses
+/// narrowed to the season, and every manifest report for the season.
+async fn build(db: &DatabaseConnection, season: u16) -> anyhow::Result<Vec<u8>> {
+    let tables = schema::tables(db).await?;
+
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("README.txt", options)?;
+    write!(
+        zip,
+        "Utah Student Robotics, {season} season archive\n\
+         Generated {}\n\n\
+         tables/   Every table in the database as CSV. orders and order_status\n          \
+         only hold the orders placed in {season}.\n\
+         reports/  The manifest's funding, status history and tax exemption\n          \
+         exports for the orders placed in {season}.\n",
+        Local::now().naive_local().format("%Y-%m-%d %H:%M")
+    )?;
+
+    for table in tables {
+        let data = table_csv(db, &table, season).await?;
+        zip.start_file(format!("tables/{table}.csv"), options)?;
+        zip.write_all(&data)?;
+    }
+    for (name, data) in manifest::season_reports(db, season).await? {
+        zip.start_file(format!("reports/{name}"), options)?;
+        zip.write_all(&data)?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+#[axum::debug_handler]
+async fn get_archive(State(state): State<&'static UsrState>, Path(season): Path<u16>) -> Response {
+    match build(&state.db, season).await {
+        Ok(data) => (
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"usr-{season}-archive.zip\""),
+                ),
+            ],
+            data,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to build season archive: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new().route("/archive/{season}", get(get_archive))
+}
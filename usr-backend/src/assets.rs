@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{Datelike, Local, NaiveDateTime};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    auth::{Caller, Role},
+    backup::backup_db,
+    manifest, schema, UsrState,
+};
+
+mod asset;
+mod verification;
+
+/// Registers each unit of `order` as an asset if it cost at least
+/// `threshold`, returning how many were registered. An order that was already
+/// registered, such as one put back in storage after a hold, isn't again.
+pub async fn register(
+    db: &impl ConnectionTrait,
+    order: &manifest::Order,
+    threshold: sea_orm::prelude::Decimal,
+    now: NaiveDateTime,
+) -> Result<i64, sea_orm::DbErr> {
+    if order.unit_cost < threshold {
+        return Ok(0);
+    }
+    let registered = asset::Entity::find()
+        .filter(asset::Column::OrderId.eq(order.id))
+        .count(db)
+        .await?;
+    if registered > 0 {
+        return Ok(0);
+    }
+    for _ in 0..order.count {
+        asset::ActiveModel {
+            id: ActiveValue::NotSet,
+            order_id: ActiveValue::Set(order.id),
+            name: ActiveValue::Set(order.name.clone()),
+            cost: ActiveValue::Set(order.unit_cost),
+            serial_number: ActiveValue::Set(None),
+            custodian: ActiveValue::Set(None),
+            location: ActiveValue::Set(order.store_in.clone()),
+            depreciation_note: ActiveValue::Set(String::new()),
+            acquired: ActiveValue::Set(now),
+        }
+        .insert(db)
+        .await?;
+    }
+    Ok(order.count)
+}
+
+/// The year inventory is being verified for
+fn current_year() -> u16 {
+    Local::now().year() as u16
+}
+
+#[derive(Deserialize)]
+struct ListAssets {
+    #[serde(default)]
+    custodian: Option<String>,
+    #[serde(default)]
+    location: Option<String>,
+    /// Whose verification is included, the current year by default
+    #[serde(default)]
+    year: Option<u16>,
+}
+
+#[derive(Serialize)]
+struct AssetEntry {
+    #[serde(flatten)]
+    asset: asset::Model,
+    verification: Option<verification::Model>,
+}
+
+#[axum::debug_handler]
+async fn get_assets(
+    State(state): State<&'static UsrState>,
+    Query(ListAssets {
+        custodian,
+        location,
+        year,
+    }): Query<ListAssets>,
+) -> Response {
+    let year = year.unwrap_or_else(current_year);
+    let mut query = asset::Entity::find().order_by_asc(asset::Column::Id);
+    if let Some(custodian) = custodian {
+        query = query.filter(asset::Column::Custodian.eq(custodian));
+    }
+    if let Some(location) = location {
+        query = query.filter(asset::Column::Location.eq(location));
+    }
+    let (assets, verifications) = tokio::join!(
+        query.all(&state.db),
+        verification::Entity::find()
+            .filter(verification::Column::Year.eq(year))
+            .all(&state.db),
+    );
+    match (assets, verifications) {
+        (Ok(assets), Ok(verifications)) => {
+            let mut verifications: HashMap<_, _> = verifications
+                .into_iter()
+                .map(|model| (model.asset_id, model))
+                .collect();
+            Json(
+                assets
+                    .into_iter()
+                    .map(|asset| AssetEntry {
+                        verification: verifications.remove(&asset.id),
+                        asset,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .into_response()
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get assets: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UpdateAsset {
+    id: u32,
+    #[serde(default)]
+    serial_number: Option<String>,
+    #[serde(default)]
+    custodian: Option<String>,
+    #[serde(default)]
+    location: Option<String>,
+    #[serde(default)]
+    depreciation_note: Option<String>,
+}
+
+#[axum::debug_handler]
+async fn update_asset(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(update_asset): Json<UpdateAsset>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot change assets", caller.role),
+        )
+            .into_response();
+    }
+    let active_model = asset::ActiveModel {
+        id: ActiveValue::Unchanged(update_asset.id.into()),
+        order_id: ActiveValue::NotSet,
+        name: ActiveValue::NotSet,
+        cost: ActiveValue::NotSet,
+        serial_number: update_asset
+            .serial_number
+            .map_or(ActiveValue::NotSet, |serial| ActiveValue::Set(Some(serial))),
+        custodian: update_asset
+            .custodian
+            .map_or(ActiveValue::NotSet, |custodian| {
+                ActiveValue::Set(Some(custodian))
+            }),
+        location: update_asset
+            .location
+            .map_or(ActiveValue::NotSet, ActiveValue::Set),
+        depreciation_note: update_asset
+            .depreciation_note
+            .map_or(ActiveValue::NotSet, ActiveValue::Set),
+        acquired: ActiveValue::NotSet,
+    };
+
+    match active_model.update(&state.db).await {
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(sea_orm::DbErr::RecordNotUpdated) => (StatusCode::BAD_REQUEST, "Asset not found").into_response(),
+        Err(e) => {
+            error!("Failed to update asset: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct VerifyAsset {
+    id: u32,
+    verified_by: String,
+    /// Where it was found, if not where it was last recorded
+    #[serde(default)]
+    location: Option<String>,
+    #[serde(default)]
+    note: String,
+}
+
+/// Records an asset as found for this year's inventory, moving it if it was
+/// found somewhere else
+#[axum::debug_handler]
+async fn verify_asset(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(verify_asset): Json<VerifyAsset>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot verify assets", caller.role),
+        )
+            .into_response();
+    }
+    if verify_asset.verified_by.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "Verifier is required").into_response();
+    }
+    let asset = match asset::Entity::find_by_id(verify_asset.id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(asset)) => asset,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Asset not found").into_response(),
+        Err(e) => {
+            error!("Failed to find asset: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let location = verify_asset.location.unwrap_or(asset.location.clone());
+    let result = async {
+        if location != asset.location {
+            let mut active_model: asset::ActiveModel = asset.into();
+            active_model.location = ActiveValue::Set(location.clone());
+            active_model.update(&state.db).await?;
+        }
+        verification::Entity::insert(verification::ActiveModel {
+            asset_id: ActiveValue::Set(verify_asset.id.into()),
+            year: ActiveValue::Set(current_year().into()),
+            verified_by: ActiveValue::Set(verify_asset.verified_by.trim().to_string()),
+            location: ActiveValue::Set(location),
+            note: ActiveValue::Set(verify_asset.note),
+            date: ActiveValue::Set(Local::now().naive_local()),
+        })
+        .on_conflict(
+            OnConflict::columns([verification::Column::AssetId, verification::Column::Year])
+                .update_columns([
+                    verification::Column::VerifiedBy,
+                    verification::Column::Location,
+                    verification::Column::Note,
+                    verification::Column::Date,
+                ])
+                .to_owned(),
+        )
+        .exec(&state.db)
+        .await?;
+        Result::<_, sea_orm::DbErr>::Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to verify asset: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct VerificationQuery {
+    #[serde(default)]
+    year: Option<u16>,
+}
+
+#[derive(Serialize)]
+struct VerificationReport {
+    year: u16,
+    verified: usize,
+    /// Assets acquired before the year ended that nobody has found yet
+    missing: Vec<asset::Model>,
+}
+
+/// How far along a year's inventory verification is
+#[axum::debug_handler]
+async fn get_verification_report(
+    State(state): State<&'static UsrState>,
+    Query(VerificationQuery { year }): Query<VerificationQuery>,
+) -> Response {
+    let year = year.unwrap_or_else(current_year);
+    let (assets, verifications) = tokio::join!(
+        asset::Entity::find()
+            .order_by_asc(asset::Column::Id)
+            .all(&state.db),
+        verification::Entity::find()
+            .filter(verification::Column::Year.eq(year))
+            .all(&state.db),
+    );
+    match (assets, verifications) {
+        (Ok(assets), Ok(verifications)) => {
+            let verified: Vec<_> = verifications.iter().map(|model| model.asset_id).collect();
+            let missing = assets
+                .into_iter()
+                .filter(|asset| {
+                    asset.acquired.year() <= year as i32 && !verified.contains(&asset.id)
+                })
+                .collect();
+            Json(VerificationReport {
+                year,
+                verified: verified.len(),
+                missing,
+            })
+            .into_response()
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get asset verifications: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/list/asset", get(get_assets))
+        .route("/update/asset", post(update_asset))
+        .route("/verify/asset", post(verify_asset))
+        .route("/report/verification", get(get_verification_report))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, asset::Entity).await?);
+    problems.extend(schema::verify(db, verification::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::{auth::Role, UsrState};
+
+    #[tokio::test]
+    async fn members_cannot_change_or_verify_assets() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [
+            (
+                Method::POST,
+                "/update/asset",
+                json!({ "id": 1, "serial_number": null, "custodian": null, "location": "Shop", "depreciation_note": null }),
+            ),
+            (
+                Method::POST,
+                "/verify/asset",
+                json!({ "id": 1, "verified_by": "a", "location": null, "note": "" }),
+            ),
+        ];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
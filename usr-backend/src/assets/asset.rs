@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// One unit of capital equipment, registered as the order that bought it was
+/// put in storage
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "assets")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub order_id: i64,
+    pub name: String,
+    /// What the unit cost in dollars
+    pub cost: Decimal,
+    #[sea_orm(nullable)]
+    pub serial_number: Option<String>,
+    /// Who is responsible for it
+    #[sea_orm(nullable)]
+    pub custodian: Option<String>,
+    pub location: String,
+    /// How the department depreciates it, eg. five year straight line
+    pub depreciation_note: String,
+    pub acquired: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
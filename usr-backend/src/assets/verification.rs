@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// An asset being found during the department's yearly inventory
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "asset_verifications")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub asset_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub year: i64,
+    pub verified_by: String,
+    /// Where it was found
+    pub location: String,
+    pub note: String,
+    pub date: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -2,14 +2,15 @@ use axum::{
     extract::State, http::StatusCode, routing::post, Form, Router
 };
 use sea_orm::{
-    sea_query::Table, sqlx::types::chrono::Local, ActiveModelTrait, ActiveValue,
-    ConnectionTrait, DatabaseConnection, Schema,
+    sqlx::types::chrono::{Local, NaiveDateTime},
+    ActiveModelTrait, ActiveValue, ConnectionTrait,
 };
 use serde::Deserialize;
 use tracing::error;
 
-use crate::{backup::backup_db, UsrState};
+use crate::{backup::backup_db, schema, UsrState};
 
+#[allow(clippy::module_inception)]
 mod attendance;
 
 #[derive(Deserialize)]
@@ -17,19 +18,43 @@ struct CheckIn {
     uid: String,
 }
 
+/// The number in a university id, eg. u1234567
+fn parse_uid(uid: &str) -> Option<u32> {
+    uid.strip_prefix('u')
+        .or_else(|| uid.strip_prefix('U'))?
+        .parse()
+        .ok()
+}
+
+/// Records a check in made at `date`, for check ins made while the kiosk was
+/// offline, or why it can't be
+pub async fn check_in_at(
+    db: &impl ConnectionTrait,
+    uid: &str,
+    date: NaiveDateTime,
+) -> Result<Result<(), &'static str>, sea_orm::DbErr> {
+    let Some(uid) = parse_uid(uid) else {
+        return Ok(Err("Invalid uid"));
+    };
+    attendance::ActiveModel {
+        uid: ActiveValue::Set(uid.into()),
+        date: ActiveValue::Set(date),
+    }
+    .insert(db)
+    .await?;
+    Ok(Ok(()))
+}
+
 #[axum::debug_handler]
 async fn add_attendance(
     State(state): State<&'static UsrState>,
     Form(CheckIn { uid }): Form<CheckIn>,
 ) -> (StatusCode, &'static str) {
-    let Some(uid) = uid.strip_prefix('u').or_else(|| uid.strip_prefix('U')) else {
-        return (StatusCode::BAD_REQUEST, "");
-    };
-    let Ok(uid) = uid.parse::<u32>() else {
+    let Some(uid) = parse_uid(&uid) else {
         return (StatusCode::BAD_REQUEST, "");
     };
     let active_model = attendance::ActiveModel {
-        uid: ActiveValue::Set(uid),
+        uid: ActiveValue::Set(uid.into()),
         date: ActiveValue::Set(Local::now().naive_local()),
     };
 
@@ -50,14 +75,10 @@ pub fn router() -> Router<&'static UsrState> {
         .route("/add/attendance", post(add_attendance))
 }
 
-pub async fn reset_tables(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
-    let builder = db.get_database_backend();
-    let schema = Schema::new(builder);
-
-    db.execute(builder.build(Table::drop().table(attendance::Entity).if_exists()))
-        .await?;
-    db.execute(builder.build(&schema.create_table_from_entity(attendance::Entity)))
-        .await?;
-
-    Ok(())
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, attendance::Entity).await?);
+    Ok(problems)
 }
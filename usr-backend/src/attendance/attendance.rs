@@ -6,7 +6,7 @@ use serde::Serialize;
 #[sea_orm(table_name = "attendance")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub uid: u32,
+    pub uid: i64,
     #[sea_orm(primary_key)]
     pub date: DateTime,
 }
@@ -0,0 +1,583 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+
+use crate::{backup::backup_db, schema, UsrState};
+
+mod grant;
+mod user;
+
+pub use user::Role;
+
+/// Who is making a request, put in its extensions by [`authenticate`]
+#[derive(Clone, Debug, Serialize)]
+pub struct Caller {
+    /// `None` when sign in isn't required and no token was given
+    pub name: Option<String>,
+    pub role: Role,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Caller {
+    type Rejection = Infallible;
+
+    /// Requests that weren't authenticated are only trusted like a member
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Caller>().cloned().unwrap_or(Caller {
+            name: None,
+            role: Role::Member,
+        }))
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Role {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Caller::from_request_parts(parts, state)
+            .await
+            .map(|caller| caller.role)
+    }
+}
+
+/// The longest a role can be granted for at once
+const MAX_GRANT_HOURS: u32 = 14 * 24;
+
+/// The highest role `user` holds through grants that haven't ended by `now`
+async fn granted_role(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    now: NaiveDateTime,
+) -> Result<Option<Role>, sea_orm::DbErr> {
+    Ok(grant::Entity::find()
+        .filter(grant::Column::UserId.eq(user.id))
+        .filter(grant::Column::Expires.gt(now))
+        .filter(grant::Column::Revoked.is_null())
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| model.role)
+        .max())
+}
+
+/// Who `name` is trusted as, as if they had signed in with their own token,
+/// if they are a user. For callers identified some other way, eg. by their
+/// Discord account.
+pub async fn caller_named(
+    db: &DatabaseConnection,
+    name: &str,
+) -> Result<Option<Caller>, sea_orm::DbErr> {
+    let Some(user) = user::Entity::find()
+        .filter(user::Column::Name.eq(name))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let granted = granted_role(db, &user, Local::now().naive_local()).await?;
+    Ok(Some(Caller {
+        role: granted.map_or(user.role, |role| role.max(user.role)),
+        name: Some(user.name),
+    }))
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn new_token() -> String {
+    rand::random::<[u8; 32]>()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Identifies the caller from the `Authorization: Bearer <token>` header.
+/// Without one, the request is turned away if `require_auth` is set, and
+/// otherwise only trusted like a member. Admins sign in with a token made by
+/// `usr-backend add-user`.
+pub async fn authenticate(
+    State(state): State<&'static UsrState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let caller = match token {
+        Some(token) => match user::Entity::find()
+            .filter(user::Column::TokenHash.eq(hash_token(token.trim())))
+            .one(&state.db)
+            .await
+        {
+            Ok(Some(user)) => match granted_role(&state.db, &user, Local::now().naive_local())
+                .await
+            {
+                Ok(granted) => Caller {
+                    role: granted.map_or(user.role, |role| role.max(user.role)),
+                    name: Some(user.name),
+                },
+                Err(e) => {
+                    error!("Failed to find grants: {e}");
+                    return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+                }
+            },
+            Ok(None) => return (StatusCode::UNAUTHORIZED, "Invalid token").into_response(),
+            Err(e) => {
+                error!("Failed to find user: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+            }
+        },
+        None if state.require_auth => {
+            return (StatusCode::UNAUTHORIZED, "Sign in required").into_response()
+        }
+        None => Caller {
+            name: None,
+            role: Role::Member,
+        },
+    };
+    request.extensions_mut().insert(caller);
+    next.run(request).await
+}
+
+/// Turns away anyone below a lead, for the admin routes
+pub async fn require_lead(role: Role, request: Request, next: Next) -> Response {
+    if role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{role} cannot use admin routes"),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Adds a user, returning their token, or why they can't be added
+pub async fn create_user(
+    db: &DatabaseConnection,
+    name: &str,
+    role: Role,
+) -> Result<Result<String, &'static str>, sea_orm::DbErr> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(Err("Name is empty"));
+    }
+    if user::Entity::find()
+        .filter(user::Column::Name.eq(name))
+        .one(db)
+        .await?
+        .is_some()
+    {
+        return Ok(Err("User already exists"));
+    }
+    let token = new_token();
+    user::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(name.to_string()),
+        role: ActiveValue::Set(role),
+        token_hash: ActiveValue::Set(hash_token(&token)),
+        created: ActiveValue::Set(Local::now().naive_local()),
+    }
+    .insert(db)
+    .await?;
+    Ok(Ok(token))
+}
+
+#[derive(Serialize)]
+struct IssuedToken {
+    name: String,
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct NewUser {
+    name: String,
+    role: Role,
+}
+
+#[axum::debug_handler]
+async fn new_user(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(NewUser { name, role }): Json<NewUser>,
+) -> Response {
+    if role > caller.role {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot add a user with the {role} role", caller.role),
+        )
+            .into_response();
+    }
+    match create_user(&state.db, &name, role).await {
+        Ok(Ok(token)) => {
+            backup_db(state);
+            Json(IssuedToken {
+                name: name.trim().to_string(),
+                token,
+            })
+            .into_response()
+        }
+        Ok(Err(msg)) => (StatusCode::BAD_REQUEST, msg).into_response(),
+        Err(e) => {
+            error!("Failed to create user: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UserRef {
+    name: String,
+}
+
+/// Finds the user named `name` that `caller` is allowed to manage, which is
+/// anyone with a lower role, or anyone at all for admins
+async fn managed_user(
+    db: &DatabaseConnection,
+    caller: &Caller,
+    name: &str,
+) -> Result<user::Model, Response> {
+    match user::Entity::find()
+        .filter(user::Column::Name.eq(name))
+        .one(db)
+        .await
+    {
+        Ok(Some(model)) if model.role >= caller.role && caller.role < Role::Admin => Err((
+            StatusCode::FORBIDDEN,
+            format!(
+                "{} cannot manage a user with the {} role",
+                caller.role, model.role
+            ),
+        )
+            .into_response()),
+        Ok(Some(model)) => Ok(model),
+        Ok(None) => Err((StatusCode::BAD_REQUEST, "User not found").into_response()),
+        Err(e) => {
+            error!("Failed to find user: {e}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "").into_response())
+        }
+    }
+}
+
+/// Issues a new token for a user, such as when theirs was lost or leaked
+#[axum::debug_handler]
+async fn rotate_token(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(UserRef { name }): Json<UserRef>,
+) -> Response {
+    let model = match managed_user(&state.db, &caller, &name).await {
+        Ok(model) => model,
+        Err(response) => return response,
+    };
+    let token = new_token();
+    let mut active_model: user::ActiveModel = model.into();
+    active_model.token_hash = ActiveValue::Set(hash_token(&token));
+    match active_model.update(&state.db).await {
+        Ok(model) => {
+            backup_db(state);
+            Json(IssuedToken {
+                name: model.name,
+                token,
+            })
+            .into_response()
+        }
+        Err(e) => {
+            error!("Failed to rotate token: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn del_user(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(UserRef { name }): Json<UserRef>,
+) -> Response {
+    let model = match managed_user(&state.db, &caller, &name).await {
+        Ok(model) => model,
+        Err(response) => return response,
+    };
+    match user::Entity::delete_by_id(model.id).exec(&state.db).await {
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete user: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NewGrant {
+    name: String,
+    role: Role,
+    /// How long the grant lasts, up to two weeks
+    hours: u32,
+    /// Why it is needed, eg. the trip it is for
+    reason: String,
+}
+
+/// Lets a user act with `role` for a while, such as to buy parts on a
+/// competition trip. Only admins can grant roles.
+#[axum::debug_handler]
+async fn new_grant(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(NewGrant {
+        name,
+        role,
+        hours,
+        reason,
+    }): Json<NewGrant>,
+) -> Response {
+    if caller.role < Role::Admin {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot grant roles", caller.role),
+        )
+            .into_response();
+    }
+    if hours == 0 || hours > MAX_GRANT_HOURS {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("A grant must last between 1 and {MAX_GRANT_HOURS} hours"),
+        )
+            .into_response();
+    }
+    if reason.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "A reason is required").into_response();
+    }
+    let model = match managed_user(&state.db, &caller, &name).await {
+        Ok(model) => model,
+        Err(response) => return response,
+    };
+    if role <= model.role {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("{} already has the {} role", model.name, model.role),
+        )
+            .into_response();
+    }
+    let granted_by = caller.name.unwrap_or_else(|| caller.role.to_string());
+    let now = Local::now().naive_local();
+    let result = grant::ActiveModel {
+        id: ActiveValue::NotSet,
+        user_id: ActiveValue::Set(model.id),
+        role: ActiveValue::Set(role),
+        reason: ActiveValue::Set(reason.trim().to_string()),
+        granted_by: ActiveValue::Set(granted_by.clone()),
+        granted: ActiveValue::Set(now),
+        expires: ActiveValue::Set(now + TimeDelta::hours(hours.into())),
+        revoked: ActiveValue::Set(None),
+        revoked_by: ActiveValue::Set(None),
+    }
+    .insert(&state.db)
+    .await;
+    match result {
+        Ok(grant) => {
+            info!(
+                "{granted_by} granted {} the {role} role until {}",
+                model.name, grant.expires
+            );
+            backup_db(state);
+            Json(grant).into_response()
+        }
+        Err(e) => {
+            error!("Failed to grant role: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GrantRef {
+    id: u32,
+}
+
+/// Ends a grant before it expires, such as when the trip is over early
+#[axum::debug_handler]
+async fn del_grant(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(GrantRef { id }): Json<GrantRef>,
+) -> Response {
+    if caller.role < Role::Admin {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot revoke roles", caller.role),
+        )
+            .into_response();
+    }
+    let model = match grant::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(model)) => model,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Grant not found").into_response(),
+        Err(e) => {
+            error!("Failed to find grant: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let now = Local::now().naive_local();
+    if model.revoked.is_some() || model.expires <= now {
+        return (StatusCode::BAD_REQUEST, "Grant has already ended").into_response();
+    }
+    let revoked_by = caller.name.unwrap_or_else(|| caller.role.to_string());
+    let mut active_model: grant::ActiveModel = model.into();
+    active_model.revoked = ActiveValue::Set(Some(now));
+    active_model.revoked_by = ActiveValue::Set(Some(revoked_by.clone()));
+    match active_model.update(&state.db).await {
+        Ok(grant) => {
+            info!("{revoked_by} revoked grant {}", grant.id);
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to revoke grant: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct GrantEntry {
+    name: String,
+    #[serde(flatten)]
+    grant: grant::Model,
+    /// Whether it still elevates the user
+    active: bool,
+}
+
+/// Every grant, the latest first, including those that have ended
+#[axum::debug_handler]
+async fn get_grants(State(state): State<&'static UsrState>) -> Response {
+    let (grants, users) = tokio::join!(
+        grant::Entity::find()
+            .order_by_desc(grant::Column::Granted)
+            .all(&state.db),
+        user::Entity::find().all(&state.db),
+    );
+    let (grants, users) = match (grants, users) {
+        (Ok(grants), Ok(users)) => (grants, users),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get grants: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let now = Local::now().naive_local();
+    let entries: Vec<_> = grants
+        .into_iter()
+        .map(|grant| GrantEntry {
+            // Grants outlive the users they were for
+            name: users
+                .iter()
+                .find(|user| user.id == grant.user_id)
+                .map(|user| user.name.clone())
+                .unwrap_or_default(),
+            active: grant.revoked.is_none() && grant.expires > now,
+            grant,
+        })
+        .collect();
+    Json(entries).into_response()
+}
+
+#[axum::debug_handler]
+async fn get_users(State(state): State<&'static UsrState>) -> Response {
+    match user::Entity::find()
+        .order_by_asc(user::Column::Name)
+        .all(&state.db)
+        .await
+    {
+        Ok(users) => Json(users).into_response(),
+        Err(e) => {
+            error!("Failed to get users: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn get_me(caller: Caller) -> Response {
+    Json(caller).into_response()
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new().route("/me", get(get_me))
+}
+
+pub fn admin_router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/new/user", post(new_user))
+        .route("/rotate/user", post(rotate_token))
+        .route("/del/user", delete(del_user))
+        .route("/list/user", get(get_users))
+        .route("/new/grant", post(new_grant))
+        .route("/del/grant", delete(del_grant))
+        .route("/list/grant", get(get_grants))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, user::Entity).await?);
+    problems.extend(schema::verify(db, grant::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::UsrState;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn only_higher_roles_manage_users() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        for (name, role) in [
+            ("member", Role::Member),
+            ("lead", Role::Lead),
+            ("admin", Role::Admin),
+        ] {
+            create_user(&state.db, name, role).await.unwrap().unwrap();
+        }
+        let caller = |role| Caller {
+            name: Some("tester".to_string()),
+            role,
+        };
+
+        assert!(managed_user(&state.db, &caller(Role::Lead), "member")
+            .await
+            .is_ok());
+        assert!(managed_user(&state.db, &caller(Role::Lead), "lead")
+            .await
+            .is_err());
+        assert!(managed_user(&state.db, &caller(Role::Lead), "admin")
+            .await
+            .is_err());
+        assert!(managed_user(&state.db, &caller(Role::Member), "member")
+            .await
+            .is_err());
+        assert!(managed_user(&state.db, &caller(Role::Admin), "admin")
+            .await
+            .is_ok());
+    }
+}
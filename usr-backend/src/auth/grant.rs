@@ -0,0 +1,34 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+use super::Role;
+
+/// A role a user holds above their own until `expires`, such as a member
+/// buying for the team during a competition trip. Grants are kept after
+/// they end, as a record of who was trusted with what.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "role_grants")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[serde(skip)]
+    pub user_id: i64,
+    pub role: Role,
+    pub reason: String,
+    pub granted_by: String,
+    pub granted: DateTime,
+    pub expires: DateTime,
+    /// Set when the grant was ended before it expired
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked: Option<DateTime>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_by: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
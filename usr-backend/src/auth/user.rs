@@ -0,0 +1,46 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Someone who can sign in with an API token
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "users")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip)]
+    pub id: i64,
+    /// Also who the user's orders are requested by
+    #[sea_orm(unique)]
+    pub name: String,
+    pub role: Role,
+    /// SHA-256 of the user's token, which is only shown when it is issued
+    #[sea_orm(unique)]
+    #[serde(skip)]
+    pub token_hash: String,
+    pub created: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// What a user is trusted with. Members place and change their own orders,
+/// leads (including treasurers) also process everyone's orders and use the
+/// admin routes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Role {
+    #[sea_orm(string_value = "M")]
+    Member,
+    #[sea_orm(string_value = "L")]
+    Lead,
+    #[sea_orm(string_value = "A")]
+    Admin,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
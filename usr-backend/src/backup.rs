@@ -1,22 +1,80 @@
-use std::{process::Command, sync::atomic::Ordering, time::Duration};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
 
-use crate::UsrState;
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{Local, NaiveDateTime};
+use sea_orm::{ConnectionTrait, Database, DatabaseBackend, DatabaseConnection, Statement};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{
+    jobs::{self, Retry, Schedule},
+    notify::Topic,
+    schema, UsrState,
+};
+
+mod remote;
+mod snapshot;
+
+pub use remote::{Remote, RemoteConfig};
+pub use snapshot::{spawn as spawn_snapshots, SnapshotConfig};
+
+/// A restored backup may have at most this fraction fewer orders than the
+/// live database before it is considered suspicious
+const MAX_ORDER_SHORTFALL: f64 = 0.1;
+
+#[derive(Serialize, Clone)]
+pub struct Verification {
+    date: NaiveDateTime,
+    ok: bool,
+    row_counts: BTreeMap<String, i64>,
+    problems: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct BackupStatus {
+    last_backup: Option<NaiveDateTime>,
+    last_error: Option<String>,
+    last_verification: Option<Verification>,
+    /// When the last scheduled snapshot was taken
+    last_snapshot: Option<NaiveDateTime>,
+    /// The last backup that was uploaded and read back intact
+    last_remote_upload: Option<remote::RemoteUpload>,
+    last_remote_error: Option<String>,
+}
 
 pub fn backup_db(state: &'static UsrState) {
+    if state.sandbox {
+        return;
+    }
     if state.backup_task_running.swap(true, Ordering::Relaxed) {
         return;
     }
-    tokio::spawn(async move {
-        tokio::time::sleep(Duration::from_secs(60 * 10)).await;
+    // Changes made within the wait are backed up together
+    let wait = Schedule::Once(Duration::from_secs(60 * 10));
+    jobs::spawn(state, "backup", wait, Retry::NEVER, |state| async move {
         state.backup_task_running.store(false, Ordering::Relaxed);
-        if let Err(e) = std::fs::copy("usr-db.sqlite", "../usr-db-backup/usr-db.sqlite") {
-            tracing::error!("Failed to copy database: {}", e);
-            return;
+        let start = Instant::now();
+        if let Err(e) = write_backup(state) {
+            state.backup_status.lock().last_error = Some(format!("{e:#}"));
+            return Err(e);
         }
         if let Err(e) = Command::new("git")
             .arg("add")
-            .arg("usr-db.sqlite")
-            .current_dir("../usr-db-backup")
+            .arg(backup_file(state))
+            .current_dir(&state.backup_dir)
             .output()
         {
             tracing::error!("Failed to add files to git: {}", e);
@@ -25,18 +83,480 @@ pub fn backup_db(state: &'static UsrState) {
             .arg("commit")
             .arg("-m")
             .arg("Automated backup")
-            .current_dir("../usr-db-backup")
+            .current_dir(&state.backup_dir)
             .output()
         {
             tracing::error!("Failed to commit files to git: {}", e);
         }
         if let Err(e) = Command::new("git")
             .arg("push")
-            .current_dir("../usr-db-backup")
+            .current_dir(&state.backup_dir)
             .output()
         {
             tracing::error!("Failed to push files to git: {}", e);
         }
+        state.metrics.observe_backup(start.elapsed());
+        let mut status = state.backup_status.lock();
+        status.last_backup = Some(Local::now().naive_local());
+        status.last_error = None;
+        drop(status);
+        remote::spawn_upload(state);
+        Ok(())
     });
-    
-}
\ No newline at end of file
+}
+
+/// The file in `backup_dir` that backups are written to. Postgres databases
+/// are backed up as the SQL script `pg_dump` writes, to be restored with
+/// `psql`.
+fn backup_file(state: &UsrState) -> &'static str {
+    match state.db.get_database_backend() {
+        DatabaseBackend::Postgres => "usr-db.sql",
+        _ => "usr-db.sqlite",
+    }
+}
+
+fn write_backup(state: &UsrState) -> anyhow::Result<()> {
+    let path = Path::new(&state.backup_dir).join(backup_file(state));
+    if state.db.get_database_backend() != DatabaseBackend::Postgres {
+        std::fs::copy(&state.db_path, path).context("Failed to copy database")?;
+        return Ok(());
+    }
+    let output = Command::new("pg_dump")
+        .arg("--no-owner")
+        .arg("--file")
+        .arg(&path)
+        .arg("--dbname")
+        .arg(&state.database_url)
+        .output()
+        .context("Failed to run pg_dump")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "pg_dump failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn git(state: &'static UsrState, args: &[&str]) -> std::io::Result<std::process::Output> {
+    Command::new("git")
+        .args(args)
+        .current_dir(&state.backup_dir)
+        .output()
+}
+
+#[derive(Serialize)]
+struct Snapshot {
+    rev: String,
+    date: String,
+}
+
+/// Backup commits, newest first.
+#[axum::debug_handler]
+async fn get_snapshots(State(state): State<&'static UsrState>) -> Response {
+    let output = tokio::task::spawn_blocking(|| {
+        git(state, &["log", "--format=%H %cI", "--", backup_file(state)])
+    })
+    .await
+    .unwrap();
+    match output {
+        Ok(output) if output.status.success() => {
+            let snapshots: Vec<_> = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| line.split_once(' '))
+                .map(|(rev, date)| Snapshot {
+                    rev: rev.to_string(),
+                    date: date.to_string(),
+                })
+                .collect();
+            Json(snapshots).into_response()
+        }
+        Ok(output) => {
+            error!(
+                "Failed to list backups: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to list backups: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Writes the database as of backup commit `rev` to a temporary file and
+/// opens it read only. Returns the path so it can be cleaned up afterwards.
+async fn open_snapshot(
+    state: &'static UsrState,
+    rev: String,
+) -> Result<(DatabaseConnection, PathBuf), String> {
+    let path = std::env::temp_dir().join(format!("usr-db-{rev}.sqlite"));
+    let spec = format!("{rev}:usr-db.sqlite");
+    let output = tokio::task::spawn_blocking(move || git(state, &["show", &spec]))
+        .await
+        .unwrap()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+    }
+    std::fs::write(&path, output.stdout).map_err(|e| e.to_string())?;
+    let db = Database::connect(format!("sqlite://{}?mode=ro", path.display()))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((db, path))
+}
+
+/// Rows of `table` keyed by id, with every value as text so that snapshots
+/// taken before a column was added can still be compared.
+async fn load_rows(
+    db: &DatabaseConnection,
+    table: &str,
+) -> Result<BTreeMap<String, BTreeMap<String, Option<String>>>, sea_orm::DbErr> {
+    let backend = db.get_database_backend();
+    let columns: Vec<String> = db
+        .query_all(Statement::from_sql_and_values(
+            backend,
+            "SELECT name FROM pragma_table_info(?)",
+            [table.into()],
+        ))
+        .await?
+        .into_iter()
+        .map(|row| row.try_get("", "name"))
+        .collect::<Result<_, _>>()?;
+    if columns.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+
+    let select = columns
+        .iter()
+        .map(|column| format!("CAST(\"{column}\" AS TEXT) AS \"{column}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let rows = db
+        .query_all(Statement::from_string(
+            backend,
+            format!("SELECT {select} FROM \"{table}\""),
+        ))
+        .await?;
+
+    let mut out = BTreeMap::new();
+    for row in rows {
+        let mut values = BTreeMap::new();
+        for column in &columns {
+            values.insert(column.clone(), row.try_get::<Option<String>>("", column)?);
+        }
+        let id = values.get("id").cloned().flatten().unwrap_or_default();
+        out.insert(id, values);
+    }
+    Ok(out)
+}
+
+#[derive(Deserialize)]
+struct DiffQuery {
+    from: String,
+    /// Another backup commit, or `live` for the current database
+    #[serde(default = "default_diff_to")]
+    to: String,
+}
+
+fn default_diff_to() -> String {
+    "live".to_string()
+}
+
+#[derive(Serialize)]
+struct ChangedField {
+    before: Option<String>,
+    after: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ChangedOrder {
+    id: String,
+    fields: BTreeMap<String, ChangedField>,
+}
+
+#[derive(Serialize, Default)]
+struct OrderDiff {
+    added: Vec<BTreeMap<String, Option<String>>>,
+    changed: Vec<ChangedOrder>,
+    deleted: Vec<BTreeMap<String, Option<String>>>,
+}
+
+/// Orders added, changed and deleted between two backups, or between a
+/// backup and the live database.
+#[axum::debug_handler]
+async fn diff_snapshots(
+    State(state): State<&'static UsrState>,
+    Query(DiffQuery { from, to }): Query<DiffQuery>,
+) -> Response {
+    let is_rev = |rev: &str| rev.len() >= 4 && rev.chars().all(|c| c.is_ascii_hexdigit());
+    if !is_rev(&from) || (to != "live" && !is_rev(&to)) {
+        return (StatusCode::BAD_REQUEST, "Expected a backup commit hash").into_response();
+    }
+    if state.db.get_database_backend() == DatabaseBackend::Postgres {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Backups of a Postgres database can't be compared",
+        )
+            .into_response();
+    }
+
+    let mut temp_files = vec![];
+    let mut load = async |rev: String| {
+        if rev == "live" {
+            return load_rows(&state.db, "orders")
+                .await
+                .map_err(|e| e.to_string());
+        }
+        let (db, path) = open_snapshot(state, rev).await?;
+        temp_files.push(path);
+        let rows = load_rows(&db, "orders").await.map_err(|e| e.to_string());
+        let _ = db.close().await;
+        rows
+    };
+    let before = load(from).await;
+    let after = load(to).await;
+    for path in temp_files {
+        let _ = std::fs::remove_file(path);
+    }
+    let (mut before, after) = match (before, after) {
+        (Ok(before), Ok(after)) => (before, after),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to load backup: {e}");
+            return (StatusCode::BAD_REQUEST, "Failed to load backup").into_response();
+        }
+    };
+
+    let mut diff = OrderDiff::default();
+    for (id, after) in after {
+        let Some(before) = before.remove(&id) else {
+            diff.added.push(after);
+            continue;
+        };
+        let mut fields = BTreeMap::new();
+        for (column, value) in &after {
+            let old = before.get(column).cloned().flatten();
+            if old != *value {
+                fields.insert(
+                    column.clone(),
+                    ChangedField {
+                        before: old,
+                        after: value.clone(),
+                    },
+                );
+            }
+        }
+        if !fields.is_empty() {
+            diff.changed.push(ChangedOrder { id, fields });
+        }
+    }
+    diff.deleted = before.into_values().collect();
+
+    Json(diff).into_response()
+}
+
+async fn count_rows(db: &DatabaseConnection) -> Result<BTreeMap<String, i64>, sea_orm::DbErr> {
+    let backend = db.get_database_backend();
+    let tables = schema::tables(db).await?;
+
+    let mut counts = BTreeMap::new();
+    for table in tables {
+        let count = db
+            .query_one(Statement::from_string(
+                backend,
+                format!("SELECT COUNT(*) AS count FROM \"{table}\""),
+            ))
+            .await?
+            .map(|row| row.try_get("", "count"))
+            .transpose()?
+            .unwrap_or_default();
+        counts.insert(table, count);
+    }
+    Ok(counts)
+}
+
+/// Problems found in a restored copy of the database.
+async fn check_snapshot(
+    db: &DatabaseConnection,
+    row_counts: &BTreeMap<String, i64>,
+    live_orders: i64,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let backend = db.get_database_backend();
+    let mut problems = vec![];
+
+    let integrity: Vec<String> = db
+        .query_all(Statement::from_string(backend, "PRAGMA integrity_check"))
+        .await?
+        .into_iter()
+        .map(|row| row.try_get_by_index(0))
+        .collect::<Result<_, _>>()?;
+    if integrity != ["ok"] {
+        problems.extend(
+            integrity
+                .into_iter()
+                .map(|e| format!("Integrity check: {e}")),
+        );
+    }
+
+    let violations = db
+        .query_all(Statement::from_string(backend, "PRAGMA foreign_key_check"))
+        .await?;
+    if !violations.is_empty() {
+        problems.push(format!("{} foreign key violation(s)", violations.len()));
+    }
+
+    match row_counts.get("orders") {
+        None => problems.push("The orders table is missing".to_string()),
+        Some(&orders) if (orders as f64) < live_orders as f64 * (1.0 - MAX_ORDER_SHORTFALL) => {
+            problems.push(format!(
+                "The backup has {orders} orders but the live database has {live_orders}"
+            ));
+        }
+        Some(_) => {}
+    }
+
+    Ok(problems)
+}
+
+/// Checks that the latest `pg_dump` backup was written to the end, since
+/// restoring it would need a second Postgres database
+fn verify_dump(state: &'static UsrState) -> Vec<String> {
+    let spec = format!("HEAD:{}", backup_file(state));
+    match git(state, &["show", &spec]) {
+        Ok(output) if output.status.success() => {
+            if String::from_utf8_lossy(&output.stdout).contains("PostgreSQL database dump complete")
+            {
+                vec![]
+            } else {
+                vec!["The backup was cut off before the end of the dump".to_string()]
+            }
+        }
+        Ok(output) => vec![format!(
+            "Failed to read backup: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )],
+        Err(e) => vec![format!("Failed to read backup: {e}")],
+    }
+}
+
+/// Restores the latest backup into a temporary database and sanity checks it.
+async fn verify_latest(state: &'static UsrState) -> Verification {
+    let date = Local::now().naive_local();
+    if state.db.get_database_backend() == DatabaseBackend::Postgres {
+        let problems = tokio::task::spawn_blocking(move || verify_dump(state))
+            .await
+            .unwrap();
+        return Verification {
+            date,
+            ok: problems.is_empty(),
+            row_counts: BTreeMap::new(),
+            problems,
+        };
+    }
+    let (db, path) = match open_snapshot(state, "HEAD".to_string()).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            return Verification {
+                date,
+                ok: false,
+                row_counts: BTreeMap::new(),
+                problems: vec![format!("Failed to restore backup: {e}")],
+            }
+        }
+    };
+
+    let result = async {
+        let row_counts = count_rows(&db).await?;
+        let live_orders = count_rows(&state.db)
+            .await?
+            .get("orders")
+            .copied()
+            .unwrap_or_default();
+        let problems = check_snapshot(&db, &row_counts, live_orders).await?;
+        Result::<_, sea_orm::DbErr>::Ok((row_counts, problems))
+    }
+    .await;
+    let _ = db.close().await;
+    let _ = std::fs::remove_file(path);
+
+    let (row_counts, problems) = match result {
+        Ok(result) => result,
+        Err(e) => (
+            BTreeMap::new(),
+            vec![format!("Failed to query restored backup: {e}")],
+        ),
+    };
+    Verification {
+        date,
+        ok: problems.is_empty(),
+        row_counts,
+        problems,
+    }
+}
+
+/// Verifies the latest backup once a day, reporting the result to the
+/// maintenance webhook and the backup status endpoint.
+pub fn spawn_verification(state: &'static UsrState) {
+    if state.sandbox {
+        return;
+    }
+    jobs::spawn(
+        state,
+        "backup_verification",
+        Schedule::Every(Duration::from_secs(60 * 60 * 24)),
+        Retry::NEVER,
+        |state| async move {
+            let verification = verify_latest(state).await;
+            let (msg, result) = if verification.ok {
+                info!("Backup verified");
+                let rows: i64 = verification.row_counts.values().sum();
+                (format!("**Backup Verified**\n**Rows:** {rows}"), Ok(()))
+            } else {
+                let problems = verification.problems.join("\n");
+                (
+                    format!("**Backup Verification Failed!**\n{problems}"),
+                    Err(anyhow::anyhow!("Backup verification failed: {problems}")),
+                )
+            };
+            // Keyed far away from equipment ids so it doesn't replace a reminder
+            state.notifier.send(Topic::Maintenance, u32::MAX, msg);
+            state.backup_status.lock().last_verification = Some(verification);
+            result
+        },
+    );
+}
+
+#[axum::debug_handler]
+async fn get_status(State(state): State<&'static UsrState>) -> Response {
+    Json(state.backup_status.lock().clone()).into_response()
+}
+
+#[derive(Serialize)]
+struct RemoteStatus {
+    /// Where backups are uploaded to, if anywhere
+    target: Option<String>,
+    last_upload: Option<remote::RemoteUpload>,
+    last_error: Option<String>,
+}
+
+#[axum::debug_handler]
+async fn get_remote_status(State(state): State<&'static UsrState>) -> Response {
+    let status = state.backup_status.lock();
+    Json(RemoteStatus {
+        target: state.remote_backup.as_ref().map(Remote::target),
+        last_upload: status.last_remote_upload.clone(),
+        last_error: status.last_remote_error.clone(),
+    })
+    .into_response()
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/list/backup", get(get_snapshots))
+        .route("/diff/backup", get(diff_snapshots))
+        .route("/status/backup", get(get_status))
+        .route("/backup/status", get(get_remote_status))
+        .route("/backup/list", get(snapshot::get_snapshots))
+        .route("/backup/restore", post(snapshot::restore_snapshot))
+}
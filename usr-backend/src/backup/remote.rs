@@ -0,0 +1,294 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::{
+    jobs::{self, Retry, Schedule},
+    storage::{Storage, StorageConfig},
+    UsrState,
+};
+
+/// Uploads are one at a time, so a retry can't race the next backup's upload
+static UPLOADING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Somewhere off this server that each backup is also uploaded to, so that
+/// losing the SD card doesn't lose the database with it
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum RemoteConfig {
+    /// An S3 compatible bucket, eg. on AWS, Backblaze or a MinIO server
+    S3 {
+        /// eg. https://s3.us-west-2.amazonaws.com
+        endpoint: String,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+        /// The object each backup replaces
+        #[serde(default = "default_file")]
+        key: String,
+    },
+    /// A machine reachable over SFTP with an SSH key, eg. a NAS
+    Sftp {
+        /// eg. backup@nas.local
+        host: String,
+        #[serde(default)]
+        port: Option<u16>,
+        /// The key to log in with, ssh's default one if left out
+        #[serde(default)]
+        identity_file: Option<String>,
+        /// The file each backup replaces, relative to the login directory
+        #[serde(default = "default_file")]
+        path: String,
+    },
+}
+
+fn default_file() -> String {
+    "usr-db.sqlite".to_string()
+}
+
+impl RemoteConfig {
+    pub fn validate(&self, key: &str, problems: &mut Vec<String>) {
+        match self {
+            Self::S3 {
+                endpoint,
+                bucket,
+                region,
+                access_key,
+                secret_key,
+                key: object,
+            } => {
+                StorageConfig::S3 {
+                    endpoint: endpoint.clone(),
+                    bucket: bucket.clone(),
+                    region: region.clone(),
+                    access_key: access_key.clone(),
+                    secret_key: secret_key.clone(),
+                }
+                .validate(key, problems);
+                if object.trim().is_empty() {
+                    problems.push(format!("{key}.key: is empty"));
+                }
+            }
+            Self::Sftp {
+                host,
+                identity_file,
+                path,
+                ..
+            } => {
+                if host.trim().is_empty() {
+                    problems.push(format!("{key}.host: is empty"));
+                }
+                if let Some(identity_file) = identity_file {
+                    if !Path::new(identity_file).is_file() {
+                        problems.push(format!(
+                            "{key}.identity_file: {identity_file:?} is not a file"
+                        ));
+                    }
+                }
+                if path.trim().is_empty() {
+                    problems.push(format!("{key}.path: is empty"));
+                } else if path.contains(['"', '\n']) {
+                    problems.push(format!(
+                        "{key}.path: {path:?} can't contain quotes or line breaks"
+                    ));
+                }
+            }
+        }
+    }
+}
+
+pub enum Remote {
+    S3 {
+        bucket: Storage,
+        /// eg. s3://usr-backups/usr-db.sqlite
+        target: String,
+        key: String,
+    },
+    Sftp {
+        host: String,
+        port: Option<u16>,
+        identity_file: Option<String>,
+        path: String,
+    },
+}
+
+impl Remote {
+    pub fn new(config: RemoteConfig) -> Self {
+        match config {
+            RemoteConfig::S3 {
+                endpoint,
+                bucket,
+                region,
+                access_key,
+                secret_key,
+                key,
+            } => Self::S3 {
+                target: format!("s3://{bucket}/{key}"),
+                bucket: Storage::new(StorageConfig::S3 {
+                    endpoint,
+                    bucket,
+                    region,
+                    access_key,
+                    secret_key,
+                }),
+                key,
+            },
+            RemoteConfig::Sftp {
+                host,
+                port,
+                identity_file,
+                path,
+            } => Self::Sftp {
+                host,
+                port,
+                identity_file,
+                path,
+            },
+        }
+    }
+
+    /// Where backups are uploaded to
+    pub fn target(&self) -> String {
+        match self {
+            Self::S3 { target, .. } => target.clone(),
+            Self::Sftp {
+                host, port, path, ..
+            } => match port {
+                Some(port) => format!("sftp://{host}:{port}/{path}"),
+                None => format!("sftp://{host}/{path}"),
+            },
+        }
+    }
+
+    /// Replaces the remote copy with `data`, read from `local`, and reads
+    /// the remote copy back so it can be checked
+    async fn put(&self, local: &Path, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::S3 { bucket, key, .. } => {
+                bucket.put(key, data).await?;
+                bucket.get(key).await
+            }
+            Self::Sftp {
+                host,
+                port,
+                identity_file,
+                path,
+            } => {
+                let mut command = Command::new("sftp");
+                command.args(["-b", "-", "-o", "BatchMode=yes"]);
+                if let Some(port) = port {
+                    command.arg("-P").arg(port.to_string());
+                }
+                if let Some(identity_file) = identity_file {
+                    command.arg("-i").arg(identity_file);
+                }
+                command.arg(host);
+                let copy = std::env::temp_dir().join("usr-db-remote.sqlite");
+                let batch = format!(
+                    "put \"{}\" \"{path}\"\nget \"{path}\" \"{}\"\n",
+                    local.display(),
+                    copy.display()
+                );
+                tokio::task::spawn_blocking(move || sftp(command, &batch, copy)).await?
+            }
+        }
+    }
+}
+
+/// Runs `batch` through sftp, then takes the file it downloaded to `copy`
+fn sftp(mut command: Command, batch: &str, copy: PathBuf) -> anyhow::Result<Vec<u8>> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(batch.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "sftp failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let data = std::fs::read(&copy)?;
+    let _ = std::fs::remove_file(copy);
+    Ok(data)
+}
+
+#[derive(Serialize, Clone)]
+pub struct RemoteUpload {
+    date: NaiveDateTime,
+    /// Of the backup, which the remote copy was read back and matched
+    sha256: String,
+    bytes: u64,
+}
+
+/// Uploads the latest backup and checks that the remote copy reads back
+/// with the same checksum
+async fn upload(state: &'static UsrState) -> anyhow::Result<()> {
+    let Some(remote) = &state.remote_backup else {
+        return Ok(());
+    };
+    let _uploading = UPLOADING.lock().await;
+    let result = async {
+        let local = Path::new(&state.backup_dir).join(super::backup_file(state));
+        let data = tokio::fs::read(&local).await?;
+        let bytes = data.len() as u64;
+        let sha256 = hex::encode(Sha256::digest(&data));
+        let copy = hex::encode(Sha256::digest(remote.put(&local, data).await?));
+        if copy != sha256 {
+            anyhow::bail!(
+                "{} reads back with checksum {copy} instead of {sha256}",
+                remote.target()
+            );
+        }
+        anyhow::Ok(RemoteUpload {
+            date: Local::now().naive_local(),
+            sha256,
+            bytes,
+        })
+    }
+    .await;
+
+    let mut status = state.backup_status.lock();
+    match result {
+        Ok(upload) => {
+            info!("Backup uploaded to {}", remote.target());
+            status.last_remote_upload = Some(upload);
+            status.last_remote_error = None;
+            Ok(())
+        }
+        Err(e) => {
+            status.last_remote_error = Some(format!("{e:#}"));
+            Err(e.context("Failed to upload backup"))
+        }
+    }
+}
+
+/// Uploads the backup that was just taken in the background, if there is
+/// somewhere to upload it to
+pub fn spawn_upload(state: &'static UsrState) {
+    if state.remote_backup.is_none() {
+        return;
+    }
+    jobs::spawn(
+        state,
+        "remote_backup",
+        Schedule::Once(Duration::ZERO),
+        Retry::times(3, Duration::from_secs(5 * 60)),
+        upload,
+    );
+}
@@ -0,0 +1,463 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Datelike, Local, NaiveDateTime, TimeDelta};
+use sea_orm::{
+    ConnectOptions, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection, Statement,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{
+    auth::{Caller, Role},
+    jobs::{self, Retry, Schedule},
+    manifest,
+    notify::Topic,
+    webhook, UsrState,
+};
+
+use super::backup_db;
+
+const NAME_FORMAT: &str = "usr-db-%Y%m%dT%H%M%S.sqlite";
+/// Tables left as they are by a restore: the migration lock and instance
+/// heartbeats belong to the servers running now
+const KEPT_TABLES: &[&str] = &["schema_lock", "schema_instances"];
+/// Tables emptied by a restore rather than rolled back. The responses kept
+/// for idempotency keys describe writes that may no longer be there, so a
+/// retry after a restore is handled again instead of replayed.
+const CLEARED_TABLES: &[&str] = &["idempotency_keys"];
+
+/// Copies of the database taken on a schedule and kept on this server, so
+/// that a bad bulk edit can be rolled back without digging through git
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    /// Where the copies are kept, created if it doesn't exist
+    pub dir: String,
+    /// Hours between copies, counted from midnight, eg. 6 for midnight, 6am,
+    /// noon and 6pm. 0 takes none.
+    pub every_hours: u32,
+    /// How many days back the last copy of each day is kept
+    pub keep_daily: usize,
+    /// How many weeks back the last copy of each week is kept
+    pub keep_weekly: usize,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            dir: "snapshots".to_string(),
+            every_hours: 24,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+impl SnapshotConfig {
+    pub fn validate(&self, key: &str, problems: &mut Vec<String>) {
+        let path = Path::new(&self.dir);
+        if path.exists() && !path.is_dir() {
+            problems.push(format!("{key}.dir: {:?} is not a directory", self.dir));
+        }
+        if self.every_hours > 0 && self.keep_daily == 0 && self.keep_weekly == 0 {
+            problems.push(format!(
+                "{key}: keep_daily or keep_weekly must be above 0 to keep the copies taken"
+            ));
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Snapshot {
+    name: String,
+    date: NaiveDateTime,
+    bytes: u64,
+}
+
+/// The copies in `dir`, newest first. Files that aren't copies are ignored.
+fn list(dir: &Path) -> std::io::Result<Vec<Snapshot>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut snapshots = vec![];
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Ok(date) = NaiveDateTime::parse_from_str(&name, NAME_FORMAT) else {
+            continue;
+        };
+        snapshots.push(Snapshot {
+            name,
+            date,
+            bytes: entry.metadata()?.len(),
+        });
+    }
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.date));
+    Ok(snapshots)
+}
+
+/// Copies the live database into `dir`. `VACUUM INTO` gives a consistent
+/// copy even while requests are writing to it.
+async fn take(db: &DatabaseConnection, dir: &Path) -> anyhow::Result<PathBuf> {
+    tokio::fs::create_dir_all(dir).await?;
+    let now = Local::now().naive_local();
+    let path = dir.join(now.format(NAME_FORMAT).to_string());
+    db.execute(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        "VACUUM INTO ?",
+        [path.to_string_lossy().into_owned().into()],
+    ))
+    .await?;
+    Ok(path)
+}
+
+/// Which copies to delete, keeping the newest of each of the last
+/// `keep_daily` days and `keep_weekly` weeks that have one. The newest copy
+/// is always kept.
+fn expired(snapshots: &[Snapshot], keep_daily: usize, keep_weekly: usize) -> Vec<&Snapshot> {
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    snapshots
+        .iter()
+        .enumerate()
+        .filter(|(i, snapshot)| {
+            let date = snapshot.date.date();
+            let week = date.iso_week();
+            let new_day = days.len() < keep_daily && days.insert(date);
+            let new_week = weeks.len() < keep_weekly && weeks.insert((week.year(), week.week()));
+            *i > 0 && !new_day && !new_week
+        })
+        .map(|(_, snapshot)| snapshot)
+        .collect()
+}
+
+fn prune(config: &SnapshotConfig) -> std::io::Result<()> {
+    let dir = Path::new(&config.dir);
+    let snapshots = list(dir)?;
+    for snapshot in expired(&snapshots, config.keep_daily, config.keep_weekly) {
+        std::fs::remove_file(dir.join(&snapshot.name))?;
+    }
+    Ok(())
+}
+
+/// The start of the interval that `now` is in
+fn slot_start(now: NaiveDateTime, every_hours: u32) -> NaiveDateTime {
+    let epoch = NaiveDateTime::default();
+    let hours = (now - epoch).num_hours();
+    let every_hours = i64::from(every_hours);
+    epoch + TimeDelta::hours(hours - hours.rem_euclid(every_hours))
+}
+
+/// When the next copy is due: now if the current interval doesn't have one
+/// yet, otherwise at the start of the next interval
+fn next_snapshot(state: &'static UsrState, now: NaiveDateTime) -> NaiveDateTime {
+    let config = &state.snapshots;
+    let slot = slot_start(now, config.every_hours);
+    match list(Path::new(&config.dir)) {
+        Ok(snapshots) if snapshots.first().is_some_and(|latest| latest.date >= slot) => {
+            slot + TimeDelta::hours(config.every_hours.into())
+        }
+        // Listing again is left to the snapshot, which reports the failure
+        _ => now,
+    }
+}
+
+/// Takes a copy at the start of each interval, and deletes the copies that
+/// are no longer kept. A copy is taken at startup if the current interval
+/// doesn't have one yet. Copies are SQLite files, so none are taken of a
+/// Postgres database, which is left to its own tooling.
+pub fn spawn(state: &'static UsrState) {
+    if state.sandbox
+        || state.snapshots.every_hours == 0
+        || state.db.get_database_backend() == DatabaseBackend::Postgres
+    {
+        return;
+    }
+    jobs::spawn(
+        state,
+        "snapshots",
+        Schedule::At(next_snapshot),
+        // Failures are reported, so don't retry and report them every minute
+        Retry::times(0, Duration::from_secs(60 * 60)),
+        |state| async move {
+            let config = &state.snapshots;
+            match take(&state.db, Path::new(&config.dir)).await {
+                Ok(path) => {
+                    info!("Took database snapshot {}", path.display());
+                    state.backup_status.lock().last_snapshot = Some(Local::now().naive_local());
+                    if let Err(e) = prune(config) {
+                        error!("Failed to remove old database snapshots: {e}");
+                    }
+                    Ok(())
+                }
+                Err(e) => {
+                    state.notifier.send(
+                        Topic::Maintenance,
+                        u32::MAX - 1,
+                        format!("**Database Snapshot Failed!**\n{e:#}"),
+                    );
+                    Err(e.context("Failed to take database snapshot"))
+                }
+            }
+        },
+    );
+}
+
+/// The copies kept on this server, newest first
+#[axum::debug_handler]
+pub async fn get_snapshots(State(state): State<&'static UsrState>) -> Response {
+    match list(Path::new(&state.snapshots.dir)) {
+        Ok(snapshots) => Json(snapshots).into_response(),
+        Err(e) => {
+            error!("Failed to list database snapshots: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// The names of the tables in `schema` that hold data, rather than
+/// SQLite's or the migrator's own bookkeeping
+async fn tables(db: &DatabaseConnection, schema: &str) -> Result<Vec<String>, sea_orm::DbErr> {
+    db.query_all(Statement::from_string(
+        db.get_database_backend(),
+        format!(
+            "SELECT name FROM \"{schema}\".sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name != 'seaql_migrations'"
+        ),
+    ))
+    .await?
+    .into_iter()
+    .map(|row| row.try_get("", "name"))
+    .collect()
+}
+
+async fn columns(
+    db: &DatabaseConnection,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    db.query_all(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        format!("SELECT name FROM pragma_table_info(?, '{schema}')"),
+        [table.into()],
+    ))
+    .await?
+    .into_iter()
+    .map(|row| row.try_get("", "name"))
+    .collect()
+}
+
+/// Replaces the contents of every table with the copy's, other than
+/// [`KEPT_TABLES`] and [`CLEARED_TABLES`]. Only the columns
+/// both have are copied, so copies taken before a migration can still be
+/// restored, leaving newer columns to their defaults.
+async fn copy_tables(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
+    let snapshot_tables: HashSet<String> = tables(db, "snapshot").await?.into_iter().collect();
+    for table in tables(db, "main").await? {
+        if KEPT_TABLES.contains(&table.as_str()) {
+            continue;
+        }
+        db.execute_unprepared(&format!("DELETE FROM main.\"{table}\""))
+            .await?;
+        if !snapshot_tables.contains(&table) || CLEARED_TABLES.contains(&table.as_str()) {
+            continue;
+        }
+        let snapshot_columns: HashSet<String> =
+            columns(db, "snapshot", &table).await?.into_iter().collect();
+        let shared = columns(db, "main", &table)
+            .await?
+            .into_iter()
+            .filter(|column| snapshot_columns.contains(column))
+            .map(|column| format!("\"{column}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        db.execute_unprepared(&format!(
+            "INSERT INTO main.\"{table}\" ({shared}) SELECT {shared} FROM snapshot.\"{table}\""
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+/// Restores the copy at `path` over the live database, in one transaction.
+/// The copy is attached to a connection of its own, since attached databases
+/// are only visible to the connection that attached them.
+async fn restore(state: &'static UsrState, path: &Path) -> Result<(), sea_orm::DbErr> {
+    let mut options = ConnectOptions::new(format!("sqlite://{}?mode=rw", state.db_path));
+    options.max_connections(1).sqlx_logging(false);
+    let db = Database::connect(options).await?;
+    let backend = db.get_database_backend();
+    db.execute(Statement::from_sql_and_values(
+        backend,
+        "ATTACH DATABASE ? AS snapshot",
+        [path.to_string_lossy().into_owned().into()],
+    ))
+    .await?;
+    db.execute_unprepared("BEGIN IMMEDIATE").await?;
+    let result = match copy_tables(&db).await {
+        Ok(()) => db.execute_unprepared("COMMIT").await.map(|_| ()),
+        Err(e) => {
+            let _ = db.execute_unprepared("ROLLBACK").await;
+            Err(e)
+        }
+    };
+    let _ = db.execute_unprepared("DETACH DATABASE snapshot").await;
+    let _ = db.close().await;
+    result
+}
+
+/// Reloads everything read from the database once and kept in memory, so that
+/// none of it is left describing the database from before a restore
+async fn reload_caches(state: &'static UsrState) -> anyhow::Result<()> {
+    state.typeahead.invalidate();
+    state.dashboard.invalidate();
+    state.flags.reload(&state.db).await?;
+    state.flag_changed(manifest::COMPETITION_FLAG);
+    manifest::load_seasons(&state.db).await?;
+    state.rollups.refresh(&state.db, None).await?;
+    state.notifier.clear_team_webhooks();
+    webhook::load_team_webhooks(&state.notifier, &state.db).await?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct RestoreSnapshot {
+    name: String,
+}
+
+/// Rolls the database back to a copy, after first taking a copy of the
+/// database as it is, so that the restore can itself be undone. Responds
+/// with the name of that copy.
+#[axum::debug_handler]
+pub async fn restore_snapshot(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(RestoreSnapshot { name }): Json<RestoreSnapshot>,
+) -> Response {
+    if caller.role < Role::Admin {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot restore the database", caller.role),
+        )
+            .into_response();
+    }
+    if state.db.get_database_backend() == DatabaseBackend::Postgres {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Snapshots aren't taken of a Postgres database",
+        )
+            .into_response();
+    }
+    // Only names that were listed, so that no other file can be restored
+    if NaiveDateTime::parse_from_str(&name, NAME_FORMAT).is_err() {
+        return (StatusCode::BAD_REQUEST, "Not the name of a snapshot").into_response();
+    }
+    let dir = Path::new(&state.snapshots.dir);
+    let path = dir.join(&name);
+    if !path.is_file() {
+        return (StatusCode::NOT_FOUND, "Snapshot not found").into_response();
+    }
+    let before = match take(&state.db, dir).await {
+        Ok(before) => before,
+        Err(e) => {
+            error!("Failed to take database snapshot before restoring: {e:#}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if let Err(e) = restore(state, &path).await {
+        error!("Failed to restore database snapshot {name}: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+    }
+    info!(
+        "{} restored database snapshot {name}",
+        caller.name.as_deref().unwrap_or("An admin")
+    );
+    if let Err(e) = reload_caches(state).await {
+        error!("Failed to reload caches after restoring database snapshot {name}: {e:#}");
+    }
+    backup_db(state);
+    state.notifier.send(
+        Topic::Maintenance,
+        u32::MAX - 1,
+        format!(
+            "**Database Restored**\n**Snapshot:** {name}\n**By:** {}",
+            caller.name.as_deref().unwrap_or("An admin")
+        ),
+    );
+    // The copy to restore to undo this
+    let undo = before.file_name().unwrap_or_default().to_string_lossy();
+    Json(serde_json::json!({ "undo": undo })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn count(db: &DatabaseConnection, sql: &str) -> i64 {
+        db.query_one(Statement::from_string(db.get_database_backend(), sql))
+            .await
+            .unwrap()
+            .unwrap()
+            .try_get_by_index(0)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn restores_clear_idempotency_keys_and_reload_flags() {
+        let dir = std::env::temp_dir().join(format!("usr-snapshot-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let url = format!("sqlite://{}?mode=rwc", dir.join("usr.sqlite").display());
+        let state = UsrState::for_tests(&url).await;
+        let db = &state.db;
+
+        db.execute_unprepared(
+            "INSERT INTO feature_flags (name, enabled) VALUES ('restored', true)",
+        )
+        .await
+        .unwrap();
+        db.execute_unprepared(
+            "INSERT INTO idempotency_keys (key, request_hash, status, body, created) VALUES ('a', '', 200, '', '2026-10-16 00:00:00')",
+        )
+        .await
+        .unwrap();
+        let snapshot = take(db, &dir.join("snapshots")).await.unwrap();
+        db.execute_unprepared("UPDATE feature_flags SET enabled = false WHERE name = 'restored'")
+            .await
+            .unwrap();
+        db.execute_unprepared(
+            "INSERT INTO idempotency_keys (key, request_hash, status, body, created) VALUES ('c', '', 200, '', '2026-10-16 00:00:00')",
+        )
+        .await
+        .unwrap();
+        db.execute_unprepared(
+            "INSERT INTO schema_instances (id, schema, started, last_seen) VALUES ('b', 32, '2026-10-16 00:00:00', '2026-10-16 00:00:00')",
+        )
+        .await
+        .unwrap();
+        state.flags.reload(db).await.unwrap();
+        assert!(!state.flag_enabled("restored"));
+
+        restore(state, &snapshot).await.unwrap();
+        reload_caches(state).await.unwrap();
+
+        assert!(state.flag_enabled("restored"));
+        // Neither the responses kept since the copy nor those in it are
+        // replayed
+        assert_eq!(count(db, "SELECT COUNT(*) FROM idempotency_keys").await, 0);
+        // The heartbeats of the instances running now are left alone
+        assert_eq!(count(db, "SELECT COUNT(*) FROM schema_instances").await, 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{Local, NaiveDateTime};
+use parking_lot::RwLock;
+use serde::Serialize;
+use tokio::sync::Notify;
+use tracing::error;
+
+use crate::{manifest, scheduler, UsrState};
+
+/// How long changes are gathered before the dashboard is rebuilt, so that a
+/// burst of them, eg. a cart of orders, rebuilds it once
+const SETTLE: Duration = Duration::from_secs(1);
+/// How often the dashboard is rebuilt even if nothing has said it changed,
+/// so that today's schedule and arrivals roll over
+const REFRESH: Duration = Duration::from_secs(60);
+
+/// Everything the wall display shows, as one payload
+#[derive(Serialize)]
+struct Payload {
+    generated: NaiveDateTime,
+    #[serde(flatten)]
+    manifest: manifest::DashboardSection,
+    /// Who is in the shop during each of today's slots
+    schedule: Vec<scheduler::Slot>,
+}
+
+/// The wall dashboard, rebuilt in the background as orders and schedules
+/// change so that serving it is only a copy of the latest payload
+#[derive(Default)]
+pub struct Dashboard {
+    payload: RwLock<Option<Bytes>>,
+    changed: Notify,
+}
+
+impl Dashboard {
+    /// Asks for the dashboard to be rebuilt soon
+    pub fn invalidate(&self) {
+        self.changed.notify_one();
+    }
+}
+
+async fn build(state: &'static UsrState) -> Result<Bytes, sea_orm::DbErr> {
+    let now = Local::now().naive_local();
+    let payload = Payload {
+        generated: now,
+        manifest: manifest::dashboard_section(state).await?,
+        schedule: scheduler::day(&state.db, now.date()).await?,
+    };
+    Ok(serde_json::to_vec(&payload).unwrap_or_default().into())
+}
+
+/// Keeps the dashboard up to date for as long as the server runs
+pub fn spawn(state: &'static UsrState) {
+    tokio::spawn(async move {
+        loop {
+            match build(state).await {
+                Ok(payload) => *state.dashboard.payload.write() = Some(payload),
+                Err(e) => error!("Failed to build dashboard: {e}"),
+            }
+            let _ = tokio::time::timeout(REFRESH, state.dashboard.changed.notified()).await;
+            tokio::time::sleep(SETTLE).await;
+        }
+    });
+}
+
+#[axum::debug_handler]
+async fn get_dashboard(State(state): State<&'static UsrState>) -> Response {
+    match state.dashboard.payload.read().clone() {
+        Some(payload) => ([(header::CONTENT_TYPE, "application/json")], payload).into_response(),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The dashboard hasn't been built yet",
+        )
+            .into_response(),
+    }
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new().route("/dashboard", get(get_dashboard))
+}
@@ -0,0 +1,463 @@
+use std::{collections::HashMap, time::Duration};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{Local, NaiveDate, TimeDelta, Timelike};
+use parking_lot::Mutex;
+use sea_orm::{
+    sea_query::OnConflict, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    EntityTrait, QueryFilter,
+};
+use serde::Deserialize;
+use tracing::{error, warn};
+
+use crate::{
+    auth::{Caller, Role},
+    backup::backup_db,
+    jobs::{self, Retry, Schedule},
+    locale::Locale,
+    maintenance, manifest, scheduler, schema, UsrState,
+};
+
+mod member;
+mod preference;
+
+const DISCORD_API: &str = "https://discord.com/api/v10";
+/// How far ahead of a shift its reminder is sent
+const SHIFT_LEAD: TimeDelta = TimeDelta::minutes(30);
+/// How long equipment can stay checked out before its member is nagged
+const CHECKOUT_LIMIT: TimeDelta = TimeDelta::hours(24);
+/// The hour of the day that order digests are sent at
+const DIGEST_HOUR: u32 = 18;
+
+/// Sends direct messages to members through a Discord bot, for notices that
+/// only concern one person.
+pub struct Dm {
+    bot: Option<(reqwest::Client, String)>,
+    /// The last day each overdue checkout was nagged about, so that it
+    /// happens at most once a day
+    last_nagged: Mutex<HashMap<i64, NaiveDate>>,
+    /// The last shift slot that reminders were sent for
+    last_shift: Mutex<Option<(NaiveDate, u16)>>,
+    /// The last day and hour order digests were sent
+    last_digest: Mutex<Option<(NaiveDate, u32)>>,
+}
+
+impl Dm {
+    pub fn new(bot_token: Option<String>) -> Self {
+        Self {
+            bot: bot_token.map(|token| (reqwest::Client::new(), token)),
+            last_nagged: Mutex::default(),
+            last_shift: Mutex::default(),
+            last_digest: Mutex::default(),
+        }
+    }
+}
+
+/// Whether a bot is configured to send direct messages
+pub fn enabled(state: &UsrState) -> bool {
+    state.dm.bot.is_some()
+}
+
+/// The Discord id of `member`, if it is known
+pub async fn discord_id(
+    db: &DatabaseConnection,
+    member: &str,
+) -> Result<Option<String>, sea_orm::DbErr> {
+    Ok(member::Entity::find_by_id(member)
+        .one(db)
+        .await?
+        .map(|model| model.discord_id))
+}
+
+/// The name of the member whose Discord id is `discord_id`, if it is known
+pub async fn member_name(
+    db: &DatabaseConnection,
+    discord_id: &str,
+) -> Result<Option<String>, sea_orm::DbErr> {
+    Ok(member::Entity::find()
+        .filter(member::Column::DiscordId.eq(discord_id))
+        .one(db)
+        .await?
+        .map(|model| model.name))
+}
+
+/// Each member's language, for those who chose one
+pub async fn locales(db: &DatabaseConnection) -> Result<HashMap<String, Locale>, sea_orm::DbErr> {
+    Ok(member::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|model| Some((model.name, model.locale?)))
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct Channel {
+    id: String,
+}
+
+/// Sends `content` to `member` as a direct message. Returns `false` if it
+/// couldn't be sent because there is no bot or the member's Discord id isn't
+/// known, so that callers can fall back to posting somewhere else.
+pub async fn send_dm(
+    state: &'static UsrState,
+    member: &str,
+    content: &str,
+) -> anyhow::Result<bool> {
+    let Some((client, token)) = &state.dm.bot else {
+        return Ok(false);
+    };
+    let Some(member) = member::Entity::find_by_id(member).one(&state.db).await? else {
+        return Ok(false);
+    };
+    let auth = format!("Bot {token}");
+
+    let channel: Channel = client
+        .post(format!("{DISCORD_API}/users/@me/channels"))
+        .header("Authorization", &auth)
+        .json(&serde_json::json!({ "recipient_id": member.discord_id }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    client
+        .post(format!("{DISCORD_API}/channels/{}/messages", channel.id))
+        .header("Authorization", &auth)
+        .json(&serde_json::json!({ "content": content }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(true)
+}
+
+/// Replaces the slash commands of the bot's application `application_id`
+/// with `commands`. Returns `false` if there is no bot to register them with.
+pub async fn register_commands(
+    state: &'static UsrState,
+    application_id: &str,
+    commands: &serde_json::Value,
+) -> anyhow::Result<bool> {
+    let Some((client, token)) = &state.dm.bot else {
+        return Ok(false);
+    };
+    client
+        .put(format!("{DISCORD_API}/applications/{application_id}/commands"))
+        .header("Authorization", format!("Bot {token}"))
+        .json(commands)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(true)
+}
+
+async fn remind_shifts(state: &'static UsrState) -> anyhow::Result<()> {
+    let now = Local::now().naive_local();
+    let Some(slot) = scheduler::slot_at(now + SHIFT_LEAD) else {
+        return Ok(());
+    };
+    {
+        let mut last_shift = state.dm.last_shift.lock();
+        if *last_shift == Some((now.date(), slot)) {
+            return Ok(());
+        }
+        *last_shift = Some((now.date(), slot));
+    }
+
+    let start = now + SHIFT_LEAD;
+    let start = format!("{}:{:0>2}", start.hour(), start.minute() / 15 * 15);
+    let locales = locales(&state.db).await?;
+    for name in scheduler::shifts_starting(&state.db, slot).await? {
+        let locale = locales.get(&name).copied().unwrap_or_default();
+        let content = locale.shift_reminder(&start);
+        if let Err(e) = send_dm(state, &name, &content).await {
+            warn!("Failed to DM shift reminder to {name}: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn nag_checkouts(state: &'static UsrState) -> anyhow::Result<()> {
+    let now = Local::now().naive_local();
+    let today = now.date();
+    let locales = locales(&state.db).await?;
+    for (id, name, equipment) in
+        maintenance::overdue_checkouts(&state.db, now - CHECKOUT_LIMIT).await?
+    {
+        if state.dm.last_nagged.lock().get(&id) == Some(&today) {
+            continue;
+        }
+        let locale = locales.get(&name).copied().unwrap_or_default();
+        let content = locale.checkout_reminder(&equipment);
+        match send_dm(state, &name, &content).await {
+            Ok(_) => {
+                state.dm.last_nagged.lock().insert(id, today);
+            }
+            Err(e) => warn!("Failed to DM checkout reminder to {name}: {e}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends each member who requested orders a summary of how they moved along
+/// over the last day, unless they opted out. Competition mode sends them
+/// every few hours instead, covering the hours since the last.
+async fn send_order_digests(state: &'static UsrState) -> anyhow::Result<()> {
+    let now = Local::now().naive_local();
+    let every = manifest::competition_digest_hours(state);
+    let due = match every {
+        Some(hours) => now.hour().is_multiple_of(hours),
+        None => now.hour() == DIGEST_HOUR,
+    };
+    if !due {
+        return Ok(());
+    }
+    {
+        let mut last_digest = state.dm.last_digest.lock();
+        if *last_digest == Some((now.date(), now.hour())) {
+            return Ok(());
+        }
+        *last_digest = Some((now.date(), now.hour()));
+    }
+
+    let opted_out: Vec<_> = preference::Entity::find()
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .filter(|model| !model.order_digest)
+        .map(|model| model.name)
+        .collect();
+    let since = now - TimeDelta::hours(every.unwrap_or(24).into());
+    let locales = locales(&state.db).await?;
+    for (name, content) in manifest::requester_digests(&state.db, since, &locales).await? {
+        if opted_out.contains(&name) {
+            continue;
+        }
+        if let Err(e) = send_dm(state, &name, &content).await {
+            warn!("Failed to DM order digest to {name}: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Periodically DMs members about their upcoming shifts, equipment they've
+/// kept checked out for too long, and their orders.
+pub fn spawn_reminders(state: &'static UsrState) {
+    if state.dm.bot.is_none() {
+        return;
+    }
+    // They run every few minutes, so failures wait for the next run
+    let every = Schedule::Every(Duration::from_secs(60 * 5));
+    jobs::spawn(state, "shift_reminders", every, Retry::NEVER, remind_shifts);
+    jobs::spawn(state, "checkout_reminders", every, Retry::NEVER, nag_checkouts);
+    jobs::spawn(state, "order_digests", every, Retry::NEVER, send_order_digests);
+}
+
+#[derive(Deserialize)]
+struct SetMember {
+    name: String,
+    discord_id: String,
+    #[serde(default)]
+    locale: Option<Locale>,
+}
+
+#[axum::debug_handler]
+async fn set_member(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(SetMember {
+        name,
+        discord_id,
+        locale,
+    }): Json<SetMember>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot link Discord members", caller.role),
+        )
+            .into_response();
+    }
+    if name.is_empty() || discord_id.is_empty() || !discord_id.bytes().all(|b| b.is_ascii_digit()) {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let result = member::Entity::insert(member::ActiveModel {
+        name: ActiveValue::Set(name),
+        discord_id: ActiveValue::Set(discord_id),
+        locale: ActiveValue::Set(locale),
+    })
+    .on_conflict(
+        OnConflict::column(member::Column::Name)
+            .update_columns([member::Column::DiscordId, member::Column::Locale])
+            .to_owned(),
+    )
+    .exec(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to set member discord id: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteMember {
+    name: String,
+}
+
+#[axum::debug_handler]
+async fn del_member(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(DeleteMember { name }): Json<DeleteMember>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot unlink Discord members", caller.role),
+        )
+            .into_response();
+    }
+    match member::Entity::delete_by_id(name).exec(&state.db).await {
+        Ok(result) if result.rows_affected == 0 => (StatusCode::BAD_REQUEST, "Member not found").into_response(),
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete member discord id: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn get_members(State(state): State<&'static UsrState>) -> Response {
+    match member::Entity::find().all(&state.db).await {
+        Ok(members) => Json(members).into_response(),
+        Err(e) => {
+            error!("Failed to get member discord ids: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SetPreference {
+    name: String,
+    order_digest: bool,
+}
+
+#[axum::debug_handler]
+async fn set_preference(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(SetPreference { name, order_digest }): Json<SetPreference>,
+) -> Response {
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    if caller.role < Role::Lead && caller.name.as_deref() != Some(name.as_str()) {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} can only change their own preferences", caller.role),
+        )
+            .into_response();
+    }
+    let result = preference::Entity::insert(preference::ActiveModel {
+        name: ActiveValue::Set(name),
+        order_digest: ActiveValue::Set(order_digest),
+    })
+    .on_conflict(
+        OnConflict::column(preference::Column::Name)
+            .update_column(preference::Column::OrderDigest)
+            .to_owned(),
+    )
+    .exec(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to set dm preference: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[axum::debug_handler]
+async fn get_preferences(State(state): State<&'static UsrState>) -> Response {
+    match preference::Entity::find().all(&state.db).await {
+        Ok(preferences) => Json(preferences).into_response(),
+        Err(e) => {
+            error!("Failed to get dm preferences: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/set/member", post(set_member))
+        .route("/del/member", delete(del_member))
+        .route("/list/member", get(get_members))
+        .route("/set/preference", post(set_preference))
+        .route("/list/preference", get(get_preferences))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, member::Entity).await?);
+    problems.extend(schema::verify(db, preference::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::{auth::Role, UsrState};
+
+    #[tokio::test]
+    async fn members_cannot_link_members_or_change_others_preferences() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [
+            (
+                Method::POST,
+                "/set/member",
+                json!({ "name": "a", "discord_id": "1", "locale": null }),
+            ),
+            (Method::DELETE, "/del/member", json!({ "name": "a" })),
+            (
+                Method::POST,
+                "/set/preference",
+                json!({ "name": "someone", "order_digest": true }),
+            ),
+        ];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
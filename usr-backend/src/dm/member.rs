@@ -0,0 +1,22 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+use crate::locale::Locale;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "member_discord_ids")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    /// Discord user id, a snowflake kept as text so that it survives JSON intact
+    pub discord_id: String,
+    /// The language the member's direct messages are written in, English if
+    /// not set
+    #[sea_orm(nullable)]
+    pub locale: Option<Locale>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
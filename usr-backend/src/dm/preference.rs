@@ -0,0 +1,18 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// Which direct messages a member wants. Members without a row get all of
+/// them.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "dm_preferences")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    /// A daily summary of how the orders the member requested moved along
+    pub order_digest: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
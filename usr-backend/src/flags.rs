@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use parking_lot::RwLock;
+use sea_orm::{
+    sea_query::OnConflict, ActiveValue, ConnectionTrait, DatabaseConnection, EntityTrait,
+};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::{backup::backup_db, schema, UsrState};
+
+mod flag;
+
+async fn read(db: &DatabaseConnection) -> Result<HashMap<String, bool>, sea_orm::DbErr> {
+    Ok(flag::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| (model.name, model.enabled))
+        .collect())
+}
+
+/// Feature flags, cached in memory so that checking one doesn't need a query.
+/// Flags that have never been set are disabled.
+pub struct Flags {
+    cache: RwLock<HashMap<String, bool>>,
+}
+
+impl Flags {
+    pub async fn load(db: &DatabaseConnection) -> Result<Self, sea_orm::DbErr> {
+        Ok(Self {
+            cache: RwLock::new(read(db).await?),
+        })
+    }
+
+    /// Reads every flag again, eg. after the database was restored
+    pub async fn reload(&self, db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
+        *self.cache.write() = read(db).await?;
+        Ok(())
+    }
+
+    pub fn enabled(&self, name: &str) -> bool {
+        self.cache.read().get(name).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize)]
+struct SetFlag {
+    name: String,
+    enabled: bool,
+}
+
+#[axum::debug_handler]
+async fn set_flag(
+    State(state): State<&'static UsrState>,
+    Json(SetFlag { name, enabled }): Json<SetFlag>,
+) -> (StatusCode, &'static str) {
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "");
+    }
+    let result = flag::Entity::insert(flag::ActiveModel {
+        name: ActiveValue::Set(name.clone()),
+        enabled: ActiveValue::Set(enabled),
+    })
+    .on_conflict(
+        OnConflict::column(flag::Column::Name)
+            .update_column(flag::Column::Enabled)
+            .to_owned(),
+    )
+    .exec(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to set flag: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "")
+    } else {
+        state.flags.cache.write().insert(name.clone(), enabled);
+        state.flag_changed(&name);
+        backup_db(state);
+        (StatusCode::OK, "")
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteFlag {
+    name: String,
+}
+
+#[axum::debug_handler]
+async fn del_flag(
+    State(state): State<&'static UsrState>,
+    Json(DeleteFlag { name }): Json<DeleteFlag>,
+) -> (StatusCode, &'static str) {
+    match flag::Entity::delete_by_id(name.clone())
+        .exec(&state.db)
+        .await
+    {
+        Ok(result) if result.rows_affected == 0 => (StatusCode::BAD_REQUEST, "Flag not found"),
+        Ok(_) => {
+            state.flags.cache.write().remove(&name);
+            state.flag_changed(&name);
+            backup_db(state);
+            (StatusCode::OK, "")
+        }
+        Err(e) => {
+            error!("Failed to delete flag: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn get_flags(State(state): State<&'static UsrState>) -> Response {
+    Json(state.flags.cache.read().clone()).into_response()
+}
+
+#[axum::debug_handler]
+async fn get_flag(State(state): State<&'static UsrState>, Path(name): Path<String>) -> Response {
+    Json(state.flag_enabled(&name)).into_response()
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/set/flag", post(set_flag))
+        .route("/del/flag", delete(del_flag))
+        .route("/list/flag", get(get_flags))
+        .route("/get/flag/{name}", get(get_flag))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, flag::Entity).await?);
+    Ok(problems)
+}
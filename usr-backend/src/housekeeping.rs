@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{Days, Local};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseBackend,
+    DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Statement,
+};
+use tracing::{error, info};
+
+use crate::{
+    jobs::{self, Retry, Schedule},
+    notify::Topic,
+    schema, UsrState,
+};
+
+mod size_sample;
+
+/// The database is compared against the newest sample at least this old
+const GROWTH_WINDOW_DAYS: u64 = 7;
+/// Growth over the window by more than this factor is considered abnormal
+const GROWTH_ALERT_FACTOR: f64 = 1.5;
+/// Small databases can easily double in a week, so ignore growth below this
+const GROWTH_ALERT_MIN_BYTES: i64 = 10 * 1024 * 1024;
+
+pub struct Report {
+    pub sample: size_sample::Model,
+    pub previous: Option<size_sample::Model>,
+}
+
+impl Report {
+    pub fn abnormal_growth(&self) -> bool {
+        let Some(previous) = &self.previous else {
+            return false;
+        };
+        let growth = self.sample.bytes - previous.bytes;
+        growth > GROWTH_ALERT_MIN_BYTES
+            && self.sample.bytes as f64 > previous.bytes as f64 * GROWTH_ALERT_FACTOR
+    }
+
+    pub fn message(&self) -> String {
+        let mut msg = format!(
+            "**Database Maintenance**\n**Size:** {}\n**Reclaimed:** {}",
+            format_bytes(self.sample.bytes),
+            format_bytes(self.sample.freed_bytes)
+        );
+        if let Some(previous) = &self.previous {
+            msg.push_str(&format!(
+                "\n**Size on {}:** {}",
+                previous.date.date(),
+                format_bytes(previous.bytes)
+            ));
+        }
+        msg
+    }
+}
+
+fn format_bytes(bytes: i64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+async fn database_size(db: &DatabaseConnection) -> Result<i64, sea_orm::DbErr> {
+    let backend = db.get_database_backend();
+    let sql = match backend {
+        DatabaseBackend::Sqlite => {
+            "SELECT page_count * page_size AS size FROM pragma_page_count(), pragma_page_size()"
+        }
+        DatabaseBackend::Postgres => "SELECT pg_database_size(current_database()) AS size",
+        DatabaseBackend::MySql => {
+            "SELECT SUM(data_length + index_length) AS size FROM information_schema.tables WHERE table_schema = DATABASE()"
+        }
+    };
+    let row = db
+        .query_one(Statement::from_string(backend, sql))
+        .await?
+        .ok_or_else(|| sea_orm::DbErr::Custom("Database size query returned no rows".into()))?;
+    row.try_get("", "size")
+}
+
+/// Vacuums and analyzes the database, then records its size so that growth
+/// can be tracked over time.
+pub async fn run(db: &DatabaseConnection) -> Result<Report, sea_orm::DbErr> {
+    let before = database_size(db).await?;
+    match db.get_database_backend() {
+        DatabaseBackend::Sqlite => {
+            db.execute_unprepared("VACUUM").await?;
+            db.execute_unprepared("ANALYZE").await?;
+        }
+        DatabaseBackend::Postgres => {
+            db.execute_unprepared("VACUUM ANALYZE").await?;
+        }
+        DatabaseBackend::MySql => {}
+    }
+    let after = database_size(db).await?;
+
+    let now = Local::now().naive_local();
+    let previous = size_sample::Entity::find()
+        .filter(size_sample::Column::Date.lte(now - Days::new(GROWTH_WINDOW_DAYS)))
+        .order_by_desc(size_sample::Column::Date)
+        .one(db)
+        .await?;
+    let sample = size_sample::ActiveModel {
+        id: ActiveValue::NotSet,
+        date: ActiveValue::Set(now),
+        bytes: ActiveValue::Set(after),
+        freed_bytes: ActiveValue::Set((before - after).max(0)),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(Report { sample, previous })
+}
+
+/// Runs database maintenance once a day, alerting the maintenance webhook if
+/// the database has grown abnormally fast.
+pub fn spawn_task(state: &'static UsrState) {
+    jobs::spawn(
+        state,
+        "db_maintenance",
+        Schedule::Every(Duration::from_secs(60 * 60 * 24)),
+        Retry::times(2, Duration::from_secs(15 * 60)),
+        |state| async move {
+            let report = run(&state.db).await?;
+            info!("{}", report.message());
+            if report.abnormal_growth() {
+                state.notifier.send(
+                    Topic::Maintenance,
+                    report.sample.id as u32,
+                    format!("**Database Growing Quickly!**\n{}", report.message()),
+                );
+            }
+            Ok(())
+        },
+    );
+}
+
+#[axum::debug_handler]
+async fn get_sizes(State(state): State<&'static UsrState>) -> Response {
+    match size_sample::Entity::find()
+        .order_by_asc(size_sample::Column::Date)
+        .all(&state.db)
+        .await
+    {
+        Ok(samples) => Json(samples).into_response(),
+        Err(e) => {
+            error!("Failed to enumerate database size samples: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new().route("/list/size", get(get_sizes))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, size_sample::Entity).await?);
+    Ok(problems)
+}
@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "db_size_samples")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub date: DateTime,
+    /// Size of the database after vacuuming
+    pub bytes: i64,
+    /// How much the vacuum reclaimed
+    pub freed_bytes: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,305 @@
+use std::{collections::HashSet, time::Duration};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Local, TimeDelta};
+use parking_lot::Mutex;
+use sea_orm::{sea_query::OnConflict, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set};
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+use crate::{
+    auth::Caller,
+    jobs::{self, Retry, Schedule},
+    schema, UsrState,
+};
+
+mod key;
+
+const HEADER: &str = "idempotency-key";
+/// Set on responses that were replayed rather than made again
+const REPLAYED_HEADER: &str = "idempotent-replayed";
+const MAX_KEY_LEN: usize = 255;
+/// Requests and responses larger than this aren't made idempotent
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Remembers what requests made with an `Idempotency-Key` were answered with,
+/// for `ttl`, so that a client retrying one over a flaky connection doesn't
+/// place the same order twice
+pub struct Idempotency {
+    ttl: TimeDelta,
+    /// Keys of requests that are still being handled
+    in_flight: Mutex<HashSet<String>>,
+}
+
+impl Idempotency {
+    pub fn new(ttl_hours: u32) -> Self {
+        Self {
+            ttl: TimeDelta::hours(ttl_hours.into()),
+            in_flight: Mutex::default(),
+        }
+    }
+}
+
+/// Releases a key once its request has been handled, or abandoned
+struct InFlight<'a> {
+    idempotency: &'a Idempotency,
+    key: String,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.idempotency.in_flight.lock().remove(&self.key);
+    }
+}
+
+fn stored_response(model: key::Model) -> Response {
+    let mut response = (
+        StatusCode::from_u16(model.status as u16).unwrap_or(StatusCode::OK),
+        model.body,
+    )
+        .into_response();
+    let headers = response.headers_mut();
+    match model
+        .content_type
+        .and_then(|content_type| HeaderValue::from_str(&content_type).ok())
+    {
+        Some(content_type) => {
+            headers.insert(header::CONTENT_TYPE, content_type);
+        }
+        None => {
+            headers.remove(header::CONTENT_TYPE);
+        }
+    }
+    headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// Answers a request made again with the same `Idempotency-Key` with the
+/// response it got the first time, instead of handling it again. Requests
+/// without the header are handled as usual, and failures on our end aren't
+/// remembered so that they can be retried. Each caller has keys of their
+/// own, so that reusing someone else's key can't replay their response.
+pub async fn replay(
+    State(state): State<&'static UsrState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(key) = request.headers().get(HEADER) else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.trim().is_empty() && key.len() <= MAX_KEY_LEN => key.trim().to_string(),
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("Idempotency-Key must be between 1 and {MAX_KEY_LEN} visible characters"),
+            )
+                .into_response()
+        }
+    };
+    // Keys can't hold a newline, so one after the key can't be mistaken for
+    // part of it. Callers without a token share the keys after a bare one.
+    let caller = request
+        .extensions()
+        .get::<Caller>()
+        .and_then(|caller| caller.name.as_deref())
+        .unwrap_or_default();
+    let key = format!("{key}\n{caller}");
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, MAX_BODY_BYTES).await else {
+        return (StatusCode::PAYLOAD_TOO_LARGE, "Request is too large").into_response();
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(parts.method.as_str());
+    hasher.update(parts.uri.to_string());
+    hasher.update(&body);
+    let request_hash = format!("{:x}", hasher.finalize());
+
+    let idempotency = &state.idempotency;
+    let now = Local::now().naive_local();
+    match key::Entity::find_by_id(&key)
+        .filter(key::Column::Created.gt(now - idempotency.ttl))
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(model)) if model.request_hash != request_hash => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "This Idempotency-Key was already used for a different request",
+            )
+                .into_response()
+        }
+        Ok(Some(model)) => return stored_response(model),
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to find idempotency key: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    }
+    if !idempotency.in_flight.lock().insert(key.clone()) {
+        return (
+            StatusCode::CONFLICT,
+            "A request with this Idempotency-Key is still being handled",
+        )
+            .into_response();
+    }
+    let _in_flight = InFlight {
+        idempotency,
+        key: key.clone(),
+    };
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if response.status().is_server_error() {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            error!("Failed to read response to remember it: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    // Only text is remembered, which is all these routes answer with
+    if let Ok(text) = std::str::from_utf8(&body) {
+        let content_type = parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        // An expired key with the same value is replaced
+        let result = key::Entity::insert(key::ActiveModel {
+            key: Set(key),
+            request_hash: Set(request_hash),
+            status: Set(parts.status.as_u16().into()),
+            content_type: Set(content_type),
+            body: Set(text.to_string()),
+            created: Set(now),
+        })
+        .on_conflict(
+            OnConflict::column(key::Column::Key)
+                .update_columns([
+                    key::Column::RequestHash,
+                    key::Column::Status,
+                    key::Column::ContentType,
+                    key::Column::Body,
+                    key::Column::Created,
+                ])
+                .to_owned(),
+        )
+        .exec(&state.db)
+        .await;
+        if let Err(e) = result {
+            error!("Failed to remember idempotency key: {e}");
+        }
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Hourly, forgets the keys that have expired
+pub fn spawn_expiry(state: &'static UsrState) {
+    jobs::spawn(
+        state,
+        "idempotency_expiry",
+        Schedule::Every(Duration::from_secs(60 * 60)),
+        Retry::NEVER,
+        |state| async move {
+            key::Entity::delete_many()
+                .filter(
+                    key::Column::Created.lte(Local::now().naive_local() - state.idempotency.ttl),
+                )
+                .exec(&state.db)
+                .await?;
+            Ok(())
+        },
+    );
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, key::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use axum::{middleware, routing::post, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::auth::Role;
+
+    /// Answers with how many requests it has handled
+    fn app(state: &'static UsrState) -> Router {
+        let handled: &'static AtomicU32 = Box::leak(Box::default());
+        Router::new()
+            .route(
+                "/new/order",
+                post(move || async move { handled.fetch_add(1, Ordering::Relaxed).to_string() }),
+            )
+            .layer(middleware::from_fn_with_state(state, replay))
+    }
+
+    async fn call(app: &Router, key: &str, caller: &str, body: &str) -> (StatusCode, bool, String) {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/new/order")
+            .header(HEADER, key)
+            .extension(Caller {
+                name: Some(caller.to_string()),
+                role: Role::Member,
+            })
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let replayed = response.headers().contains_key(REPLAYED_HEADER);
+        let body = to_bytes(response.into_body(), MAX_BODY_BYTES)
+            .await
+            .unwrap();
+        (status, replayed, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn retries_are_replayed() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let app = app(state);
+        assert_eq!(
+            call(&app, "a", "alice", "{}").await,
+            (StatusCode::OK, false, "0".to_string())
+        );
+        assert_eq!(
+            call(&app, "a", "alice", "{}").await,
+            (StatusCode::OK, true, "0".to_string())
+        );
+        // Another key is another request
+        assert_eq!(
+            call(&app, "b", "alice", "{}").await,
+            (StatusCode::OK, false, "1".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn keys_are_per_caller_and_request() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let app = app(state);
+        assert_eq!(call(&app, "a", "alice", "{}").await.2, "0");
+        // Someone else's key doesn't replay their response
+        assert_eq!(
+            call(&app, "a", "bob", "{}").await,
+            (StatusCode::OK, false, "1".to_string())
+        );
+        let (status, _, _) = call(&app, "a", "alice", r#"{"count":2}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
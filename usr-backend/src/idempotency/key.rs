@@ -0,0 +1,26 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// A request that was made with an `Idempotency-Key`, and what it was
+/// answered with, so that a retry of it is answered the same way
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "idempotency_keys")]
+pub struct Model {
+    /// The `Idempotency-Key` and the name of the caller that sent it, on
+    /// separate lines
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub key: String,
+    /// SHA-256 of the request's method, uri and body, so that the key can't
+    /// be reused for a different request
+    pub request_hash: String,
+    pub status: i64,
+    #[sea_orm(nullable)]
+    pub content_type: Option<String>,
+    pub body: String,
+    pub created: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
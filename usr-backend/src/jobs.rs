@@ -0,0 +1,358 @@
+use std::{collections::BTreeMap, future::Future, time::Duration};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use parking_lot::Mutex;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::Serialize;
+use tracing::{error, warn};
+
+use crate::{schema, UsrState};
+
+mod run;
+
+/// How many of each job's latest runs are kept
+const HISTORY_RUNS: u64 = 100;
+/// How many failed runs `/jobs` lists
+const RECENT_FAILURES: u64 = 20;
+
+/// When a job runs
+#[derive(Clone, Copy, Debug)]
+pub enum Schedule {
+    /// Every so often, starting right away
+    Every(Duration),
+    /// At the next time the function gives from now, asked again after each
+    /// run, eg. every Monday morning
+    At(fn(&'static UsrState, NaiveDateTime) -> NaiveDateTime),
+    /// Once, after waiting this long
+    Once(Duration),
+}
+
+/// How a failed run is retried
+#[derive(Clone, Copy, Debug)]
+pub struct Retry {
+    /// Retries after the first attempt, before the job waits for its next
+    /// scheduled run
+    pub attempts: u32,
+    /// The wait before the first retry, doubling after each one. A job that
+    /// has run out of retries also waits this long before its next run.
+    pub backoff: Duration,
+}
+
+impl Retry {
+    /// Failed runs wait for the next scheduled run
+    pub const NEVER: Retry = Retry {
+        attempts: 0,
+        backoff: Duration::ZERO,
+    };
+
+    pub const fn times(attempts: u32, backoff: Duration) -> Self {
+        Self { attempts, backoff }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Waiting for `next_run`
+    Queued,
+    Running,
+    /// The last attempt failed, and it is retried at `next_run`
+    Retrying,
+    /// Every attempt of the last run failed. It runs again at `next_run`.
+    Failed,
+    /// A job that only runs once has run
+    Done,
+}
+
+/// What a job is doing, as `/jobs` shows it
+#[derive(Serialize, Clone, Debug)]
+pub struct JobStatus {
+    pub name: &'static str,
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_run: Option<NaiveDateTime>,
+    /// The attempt of the current or last run
+    pub attempt: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_started: Option<NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_finished: Option<NaiveDateTime>,
+    /// Why the last run failed, until one succeeds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Since the server started
+    pub runs: u64,
+    pub failures: u64,
+}
+
+/// The background jobs this server runs, by name
+#[derive(Default)]
+pub struct Jobs {
+    jobs: Mutex<BTreeMap<&'static str, JobStatus>>,
+}
+
+impl Jobs {
+    fn update(&self, name: &'static str, f: impl FnOnce(&mut JobStatus)) {
+        let mut jobs = self.jobs.lock();
+        let job = jobs.entry(name).or_insert_with(|| JobStatus {
+            name,
+            status: Status::Queued,
+            next_run: None,
+            attempt: 0,
+            last_started: None,
+            last_finished: None,
+            last_error: None,
+            runs: 0,
+            failures: 0,
+        });
+        f(job);
+    }
+
+    fn queue(&self, name: &'static str, status: Status, wait: Duration) {
+        let next_run = Local::now().naive_local() + TimeDelta::from_std(wait).unwrap_or_default();
+        self.update(name, |job| {
+            job.status = status;
+            job.next_run = Some(next_run);
+        });
+    }
+
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        self.jobs.lock().values().cloned().collect()
+    }
+
+    pub fn status(&self, name: &str) -> Option<JobStatus> {
+        self.jobs.lock().get(name).cloned()
+    }
+}
+
+/// Records that a run started, returning its id
+async fn start_run(
+    db: &DatabaseConnection,
+    name: &str,
+    attempt: u32,
+    started: NaiveDateTime,
+) -> Option<i64> {
+    let result = run::ActiveModel {
+        id: ActiveValue::NotSet,
+        job: ActiveValue::Set(name.to_string()),
+        attempt: ActiveValue::Set(attempt.into()),
+        started: ActiveValue::Set(started),
+        finished: ActiveValue::Set(None),
+        ok: ActiveValue::Set(false),
+        error: ActiveValue::Set(None),
+    }
+    .insert(db)
+    .await;
+    match result {
+        Ok(model) => Some(model.id),
+        Err(e) => {
+            warn!("Failed to record start of job {name}: {e}");
+            None
+        }
+    }
+}
+
+/// Records how a run ended, and forgets the job's runs past the latest
+/// [HISTORY_RUNS]
+async fn finish_run(db: &DatabaseConnection, name: &str, id: Option<i64>, error: Option<String>) {
+    let Some(id) = id else {
+        return;
+    };
+    let result = run::ActiveModel {
+        id: ActiveValue::Unchanged(id),
+        job: ActiveValue::NotSet,
+        attempt: ActiveValue::NotSet,
+        started: ActiveValue::NotSet,
+        finished: ActiveValue::Set(Some(Local::now().naive_local())),
+        ok: ActiveValue::Set(error.is_none()),
+        error: ActiveValue::Set(error),
+    }
+    .update(db)
+    .await;
+    if let Err(e) = result {
+        warn!("Failed to record end of job run {id}: {e}");
+    }
+    let oldest_kept = run::Entity::find()
+        .select_only()
+        .column(run::Column::Id)
+        .filter(run::Column::Job.eq(name))
+        .order_by_desc(run::Column::Id)
+        .offset(HISTORY_RUNS - 1)
+        .into_tuple::<i64>()
+        .one(db)
+        .await;
+    let result = match oldest_kept {
+        Ok(Some(oldest_kept)) => run::Entity::delete_many()
+            .filter(run::Column::Job.eq(name))
+            .filter(run::Column::Id.lt(oldest_kept))
+            .exec(db)
+            .await
+            .map(|_| ()),
+        Ok(None) => Ok(()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("Failed to remove old runs of job {name}: {e}");
+    }
+}
+
+/// Runs `job` on `schedule` in the background, retrying failed runs following
+/// `retry`. Every run is recorded, and `/admin/jobs` shows what each job is
+/// doing.
+pub fn spawn<F, Fut>(
+    state: &'static UsrState,
+    name: &'static str,
+    schedule: Schedule,
+    retry: Retry,
+    job: F,
+) where
+    F: Fn(&'static UsrState) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<()>> + Send,
+{
+    let first_wait = match schedule {
+        Schedule::Every(_) => Duration::ZERO,
+        Schedule::At(next) => {
+            let now = Local::now().naive_local();
+            (next(state, now) - now).to_std().unwrap_or_default()
+        }
+        Schedule::Once(delay) => delay,
+    };
+    state.jobs.queue(name, Status::Queued, first_wait);
+    tokio::spawn(async move {
+        let mut wait = first_wait;
+        loop {
+            tokio::time::sleep(wait).await;
+            let period_start = tokio::time::Instant::now();
+            let mut attempt = 0;
+            let failed = loop {
+                attempt += 1;
+                let started = Local::now().naive_local();
+                state.jobs.update(name, |job| {
+                    job.status = Status::Running;
+                    job.next_run = None;
+                    job.attempt = attempt;
+                    job.last_started = Some(started);
+                });
+                let id = start_run(&state.db, name, attempt, started).await;
+                let error = job(state).await.err().map(|e| format!("{e:#}"));
+                let finished = Local::now().naive_local();
+                state.jobs.update(name, |job| {
+                    job.runs += 1;
+                    job.last_finished = Some(finished);
+                    if error.is_some() {
+                        job.failures += 1;
+                    }
+                    job.last_error.clone_from(&error);
+                });
+                finish_run(&state.db, name, id, error.clone()).await;
+                let Some(error) = error else {
+                    break false;
+                };
+                error!("Job {name} failed on attempt {attempt}: {error}");
+                if attempt > retry.attempts {
+                    break true;
+                }
+                let backoff = retry.backoff * 2u32.saturating_pow(attempt - 1);
+                state.jobs.queue(name, Status::Retrying, backoff);
+                tokio::time::sleep(backoff).await;
+            };
+            wait = match schedule {
+                Schedule::Every(period) => period.saturating_sub(period_start.elapsed()),
+                Schedule::At(next) => {
+                    let now = Local::now().naive_local();
+                    (next(state, now) - now).to_std().unwrap_or_default()
+                }
+                Schedule::Once(_) => {
+                    let status = if failed { Status::Failed } else { Status::Done };
+                    state.jobs.update(name, |job| {
+                        job.status = status;
+                        job.next_run = None;
+                    });
+                    return;
+                }
+            };
+            if failed {
+                wait = wait.max(retry.backoff);
+            }
+            let status = if failed {
+                Status::Failed
+            } else {
+                Status::Queued
+            };
+            state.jobs.queue(name, status, wait);
+        }
+    });
+}
+
+/// Marks the runs that were cut short the last time the server stopped as
+/// failed
+pub async fn load(db: &DatabaseConnection) -> Result<Jobs, sea_orm::DbErr> {
+    run::Entity::update_many()
+        .col_expr(
+            run::Column::Finished,
+            sea_orm::sea_query::Expr::col(run::Column::Started).into(),
+        )
+        .col_expr(
+            run::Column::Error,
+            sea_orm::sea_query::Expr::value("Interrupted by the server stopping"),
+        )
+        .filter(run::Column::Finished.is_null())
+        .exec(db)
+        .await?;
+    Ok(Jobs::default())
+}
+
+#[derive(Serialize)]
+struct JobsReport {
+    jobs: Vec<JobStatus>,
+    /// The latest failed runs, including those from before a restart
+    recent_failures: Vec<run::Model>,
+}
+
+/// What each background job is doing, and the runs that failed lately
+#[axum::debug_handler]
+async fn get_jobs(State(state): State<&'static UsrState>) -> Response {
+    let result = run::Entity::find()
+        .filter(run::Column::Ok.eq(false))
+        .filter(run::Column::Finished.is_not_null())
+        .order_by_desc(run::Column::Id)
+        .limit(RECENT_FAILURES)
+        .all(&state.db)
+        .await;
+    match result {
+        Ok(recent_failures) => Json(JobsReport {
+            jobs: state.jobs.statuses(),
+            recent_failures,
+        })
+        .into_response(),
+        Err(e) => {
+            error!("Failed to get job runs: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new().route("/jobs", get(get_jobs))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, run::Entity).await?);
+    if problems.is_empty() {
+        schema::ensure_index(db, run::Entity, run::Column::Job).await?;
+    }
+    Ok(problems)
+}
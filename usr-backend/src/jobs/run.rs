@@ -0,0 +1,26 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// One run of a background job, kept so that failures can be looked into
+/// after the fact and survive restarts
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "job_runs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub job: String,
+    /// 1 for the scheduled run, and one more for each retry of it
+    pub attempt: i64,
+    pub started: DateTime,
+    /// Unset while the job is running
+    #[sea_orm(nullable)]
+    pub finished: Option<DateTime>,
+    pub ok: bool,
+    #[sea_orm(nullable)]
+    pub error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,360 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{Local, NaiveDateTime};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, TransactionError, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    attendance,
+    auth::{Caller, Role},
+    backup::backup_db,
+    maintenance, schema, UsrState,
+};
+
+mod change;
+mod mutation;
+mod session;
+
+/// Notes that the state of `equipment_id` changed, so that kiosks download
+/// it on their next sync
+pub async fn equipment_changed(db: &impl ConnectionTrait, equipment_id: i64) {
+    let result = change::ActiveModel {
+        seq: ActiveValue::NotSet,
+        equipment_id: ActiveValue::Set(equipment_id),
+    }
+    .insert(db)
+    .await;
+    if let Err(e) = result {
+        error!("Failed to record equipment change: {e}");
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenSession {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct OpenedSession {
+    session_id: i64,
+}
+
+#[axum::debug_handler]
+async fn open_session(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(OpenSession { name }): Json<OpenSession>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot open kiosk sessions", caller.role),
+        )
+            .into_response();
+    }
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Name is required").into_response();
+    }
+    let result = session::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(name),
+        opened: ActiveValue::Set(Local::now().naive_local()),
+        last_sync: ActiveValue::Set(None),
+    }
+    .insert(&state.db)
+    .await;
+
+    match result {
+        Ok(model) => Json(OpenedSession {
+            session_id: model.id,
+        })
+        .into_response(),
+        Err(e) => {
+            error!("Failed to open kiosk session: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Marks the session as having synced, or responds with why it can't sync
+async fn touch_session(
+    db: &DatabaseConnection,
+    session_id: u32,
+) -> Result<(), (StatusCode, &'static str)> {
+    let result = session::ActiveModel {
+        id: ActiveValue::Unchanged(session_id.into()),
+        name: ActiveValue::NotSet,
+        opened: ActiveValue::NotSet,
+        last_sync: ActiveValue::Set(Some(Local::now().naive_local())),
+    }
+    .update(db)
+    .await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(sea_orm::DbErr::RecordNotUpdated) => {
+            Err((StatusCode::BAD_REQUEST, "Session not found"))
+        }
+        Err(e) => {
+            error!("Failed to update kiosk session: {e}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, ""))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Download {
+    session_id: u32,
+    /// The `cursor` of the last download, or nothing to download everything
+    #[serde(default)]
+    cursor: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct Changes {
+    /// Pass as `cursor` on the next download
+    cursor: i64,
+    /// Whether `equipment` is everything, rather than what changed
+    full: bool,
+    equipment: Vec<maintenance::EquipmentState>,
+}
+
+/// The equipment whose state changed since `cursor`
+async fn changes_since(
+    db: &DatabaseConnection,
+    cursor: Option<u32>,
+) -> Result<Changes, sea_orm::DbErr> {
+    let latest = change::Entity::find()
+        .order_by_desc(change::Column::Seq)
+        .one(db)
+        .await?
+        .map_or(0, |model| model.seq);
+    let Some(cursor) = cursor else {
+        return Ok(Changes {
+            cursor: latest,
+            full: true,
+            equipment: maintenance::equipment_states(db, None).await?,
+        });
+    };
+    let changed: Vec<i64> = change::Entity::find()
+        .select_only()
+        .column(change::Column::EquipmentId)
+        .distinct()
+        .filter(change::Column::Seq.gt(cursor))
+        .filter(change::Column::Seq.lte(latest))
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(Changes {
+        cursor: latest,
+        full: false,
+        equipment: maintenance::equipment_states(db, Some(changed)).await?,
+    })
+}
+
+#[axum::debug_handler]
+async fn download(
+    State(state): State<&'static UsrState>,
+    Query(Download { session_id, cursor }): Query<Download>,
+) -> Response {
+    if let Err(response) = touch_session(&state.db, session_id).await {
+        return response.into_response();
+    }
+    match changes_since(&state.db, cursor).await {
+        Ok(changes) => Json(changes).into_response(),
+        Err(e) => {
+            error!("Failed to get kiosk changes: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Action {
+    CheckIn { uid: String },
+    Checkout { equipment_id: u32, member: String },
+    Return { equipment_id: u32 },
+}
+
+#[derive(Deserialize)]
+struct QueuedMutation {
+    /// Numbered by the kiosk, unique within its session
+    id: u32,
+    /// When it happened at the kiosk
+    date: NaiveDateTime,
+    #[serde(flatten)]
+    action: Action,
+}
+
+#[derive(Deserialize)]
+struct Upload {
+    session_id: u32,
+    mutations: Vec<QueuedMutation>,
+}
+
+#[derive(Serialize)]
+struct Conflict {
+    id: u32,
+    reason: String,
+}
+
+#[derive(Serialize, Default)]
+struct UploadReport {
+    applied: Vec<u32>,
+    /// Mutations that no longer make sense given what happened while the
+    /// kiosk was offline, such as checking out equipment that someone else
+    /// checked out first. The kiosk should drop them and tell whoever is
+    /// around.
+    conflicts: Vec<Conflict>,
+}
+
+/// Applies `mutation`, recording its outcome, unless the session already
+/// uploaded it, in which case its recorded outcome is returned
+async fn apply(
+    db: &DatabaseConnection,
+    session_id: u32,
+    mutation: QueuedMutation,
+) -> Result<Result<(), String>, TransactionError<sea_orm::DbErr>> {
+    let now = Local::now().naive_local();
+    db.transaction(|tx| {
+        Box::pin(async move {
+            if let Some(model) = mutation::Entity::find_by_id((i64::from(session_id), i64::from(mutation.id)))
+                .one(tx)
+                .await?
+            {
+                return Ok(model.conflict.map_or(Ok(()), Err));
+            }
+            // Kiosk clocks drift, and nothing can happen in the future
+            let date = mutation.date.min(now);
+            let outcome = match mutation.action {
+                Action::CheckIn { uid } => attendance::check_in_at(tx, &uid, date).await?,
+                Action::Checkout {
+                    equipment_id,
+                    member,
+                } => {
+                    let outcome = maintenance::checkout_at(tx, equipment_id, member, date).await?;
+                    if outcome.is_ok() {
+                        equipment_changed(tx, equipment_id.into()).await;
+                    }
+                    outcome
+                }
+                Action::Return { equipment_id } => {
+                    let outcome = maintenance::return_at(tx, equipment_id, date).await?;
+                    if outcome.is_ok() {
+                        equipment_changed(tx, equipment_id.into()).await;
+                    }
+                    outcome
+                }
+            };
+            mutation::ActiveModel {
+                session_id: ActiveValue::Set(session_id.into()),
+                mutation_id: ActiveValue::Set(mutation.id.into()),
+                date: ActiveValue::Set(date),
+                uploaded: ActiveValue::Set(now),
+                conflict: ActiveValue::Set(outcome.err().map(str::to_string)),
+            }
+            .insert(tx)
+            .await?;
+            Ok(outcome.map_err(str::to_string))
+        })
+    })
+    .await
+}
+
+/// Applies mutations queued while the kiosk was offline, in the order they
+/// happened, reporting which of them conflict with what happened meanwhile.
+#[axum::debug_handler]
+async fn upload(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(Upload {
+        session_id,
+        mut mutations,
+    }): Json<Upload>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot upload kiosk changes", caller.role),
+        )
+            .into_response();
+    }
+    if let Err(response) = touch_session(&state.db, session_id).await {
+        return response.into_response();
+    }
+    mutations.sort_by_key(|mutation| (mutation.date, mutation.id));
+
+    let mut report = UploadReport::default();
+    for mutation in mutations {
+        let id = mutation.id;
+        match apply(&state.db, session_id, mutation).await {
+            Ok(Ok(())) => report.applied.push(id),
+            Ok(Err(reason)) => report.conflicts.push(Conflict { id, reason }),
+            Err(e) => {
+                error!("Failed to apply kiosk mutation: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+            }
+        }
+    }
+    if !report.applied.is_empty() {
+        backup_db(state);
+    }
+    Json(report).into_response()
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/open/session", post(open_session))
+        .route("/changes", get(download))
+        .route("/upload", post(upload))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, session::Entity).await?);
+    problems.extend(schema::verify(db, mutation::Entity).await?);
+    problems.extend(schema::verify(db, change::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::{auth::Role, UsrState};
+
+    #[tokio::test]
+    async fn members_cannot_open_sessions_or_upload() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [
+            (Method::POST, "/open/session", json!({ "name": "Shop" })),
+            (
+                Method::POST,
+                "/upload",
+                json!({ "session_id": 1, "mutations": [] }),
+            ),
+        ];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
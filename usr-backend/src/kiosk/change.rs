@@ -0,0 +1,18 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// Equipment whose state changed, in the order it happened. Kiosks download
+/// the equipment changed after the last change they have seen.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "kiosk_changes")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub seq: i64,
+    pub equipment_id: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,26 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// A queued mutation that a session has uploaded, kept so that uploading it
+/// again, eg. when the Wi-Fi drops before the response arrives, reports the
+/// same outcome instead of applying it twice.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "kiosk_mutations")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub session_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub mutation_id: i64,
+    /// When the mutation happened at the kiosk
+    pub date: DateTime,
+    pub uploaded: DateTime,
+    /// Why the mutation wasn't applied, if it wasn't
+    #[sea_orm(nullable)]
+    pub conflict: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
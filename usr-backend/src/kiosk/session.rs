@@ -0,0 +1,21 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// A kiosk that syncs with the server. Queued mutations are numbered per
+/// session, so a new session is opened whenever a kiosk starts fresh.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "kiosk_sessions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    pub opened: DateTime,
+    #[sea_orm(nullable)]
+    pub last_sync: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
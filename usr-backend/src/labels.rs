@@ -0,0 +1,236 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Local;
+use serde::Deserialize;
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+use tracing::error;
+
+use crate::{
+    auth::{Caller, Role},
+    manifest, UsrState,
+};
+
+const DEFAULT_ORDER_TEMPLATE: &str = "^XA
+^CF0,40
+^FO30,30^FD{name}^FS
+^CF0,30
+^FO30,90^FDOrder {number}   Team: {team}^FS
+^FO30,130^FDStore in: {store_in}^FS
+^FO30,170^FDReceived: {date}^FS
+^BY2,2,60
+^FO30,210^BCN,60,Y,N,N^FD{id}^FS
+^XZ
+";
+
+const DEFAULT_BIN_TEMPLATE: &str = "^XA
+^CF0,50
+^FO30,30^FD{location}^FS
+^CF0,30
+^FO30,100^FD{contents}^FS
+^FO30,150^BQN,2,5^FDQA,{location}^FS
+^XZ
+";
+
+#[derive(Deserialize, Default)]
+pub struct LabelConfig {
+    /// Address of a networked Zebra printer, eg. `10.0.0.20:9100`
+    printer: Option<String>,
+    /// Path to a ZPL file overriding the order-received label
+    order_template: Option<String>,
+    /// Path to a ZPL file overriding the bin label
+    bin_template: Option<String>,
+}
+
+impl LabelConfig {
+    pub fn validate(&self, problems: &mut Vec<String>) {
+        if let Some(printer) = &self.printer {
+            let valid = printer
+                .rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+            if !valid {
+                problems.push(format!(
+                    "labels.printer: {printer:?} should be a host and port, eg. 10.0.0.20:9100"
+                ));
+            }
+        }
+        for (key, path) in [
+            ("labels.order_template", &self.order_template),
+            ("labels.bin_template", &self.bin_template),
+        ] {
+            if let Some(path) = path {
+                if !std::path::Path::new(path).is_file() {
+                    problems.push(format!("{key}: {path:?} does not exist"));
+                }
+            }
+        }
+    }
+}
+
+pub struct Labels {
+    printer: Option<String>,
+    order_template: String,
+    bin_template: String,
+}
+
+impl Labels {
+    pub fn load(config: LabelConfig) -> std::io::Result<Self> {
+        Ok(Self {
+            printer: config.printer,
+            order_template: match config.order_template {
+                Some(path) => std::fs::read_to_string(path)?,
+                None => DEFAULT_ORDER_TEMPLATE.to_string(),
+            },
+            bin_template: match config.bin_template {
+                Some(path) => std::fs::read_to_string(path)?,
+                None => DEFAULT_BIN_TEMPLATE.to_string(),
+            },
+        })
+    }
+}
+
+/// Fills `{key}` placeholders in a template. `^` and `~` are ZPL command
+/// prefixes, so they are stripped from values to keep user input from
+/// injecting commands.
+fn render(template: &str, values: &[(&str, String)]) -> String {
+    let mut out = template.to_string();
+    for (key, value) in values {
+        let value: String = value
+            .chars()
+            .map(|c| if c == '^' || c == '~' { ' ' } else { c })
+            .collect();
+        out = out.replace(&format!("{{{key}}}"), &value);
+    }
+    out
+}
+
+#[derive(Deserialize)]
+struct PrintQuery {
+    #[serde(default)]
+    print: bool,
+}
+
+/// Answers with the label, or sends it to the label printer if `print`,
+/// which only leads can
+async fn respond(state: &'static UsrState, caller: Caller, zpl: String, print: bool) -> Response {
+    if !print {
+        return ([(header::CONTENT_TYPE, "application/zpl")], zpl).into_response();
+    }
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot print labels", caller.role),
+        )
+            .into_response();
+    }
+    let Some(printer) = &state.labels.printer else {
+        return (StatusCode::BAD_REQUEST, "No label printer configured").into_response();
+    };
+    let result = async {
+        let mut stream = TcpStream::connect(printer).await?;
+        stream.write_all(zpl.as_bytes()).await?;
+        stream.shutdown().await
+    }
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to send label to printer: {e}");
+        (StatusCode::BAD_GATEWAY, "Failed to reach label printer").into_response()
+    } else {
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[axum::debug_handler]
+async fn order_label(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Path(id): Path<manifest::OrderRef>,
+    Query(PrintQuery { print }): Query<PrintQuery>,
+) -> Response {
+    let id = match manifest::resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let order = match manifest::get_order(&state.db, id).await {
+        Ok(Some(model)) => model,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let zpl = render(
+        &state.labels.order_template,
+        &[
+            ("id", order.id.to_string()),
+            ("number", order.number()),
+            ("name", order.name),
+            ("team", order.team.to_string()),
+            ("store_in", order.store_in),
+            ("vendor", order.vendor),
+            ("count", order.count.to_string()),
+            ("date", Local::now().date_naive().to_string()),
+        ],
+    );
+
+    respond(state, caller, zpl, print).await
+}
+
+#[derive(Deserialize)]
+struct BinLabel {
+    location: String,
+    #[serde(default)]
+    contents: String,
+}
+
+#[axum::debug_handler]
+async fn bin_label(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Query(PrintQuery { print }): Query<PrintQuery>,
+    Json(BinLabel { location, contents }): Json<BinLabel>,
+) -> Response {
+    if location.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let zpl = render(
+        &state.labels.bin_template,
+        &[("location", location), ("contents", contents)],
+    );
+
+    respond(state, caller, zpl, print).await
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/order/{id}", get(order_label))
+        .route("/bin", post(bin_label))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::{auth::Role, UsrState};
+
+    #[tokio::test]
+    async fn members_cannot_print_labels() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [(Method::POST, "/bin?print=true", json!({ "location": "A1" }))];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
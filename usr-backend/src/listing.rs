@@ -0,0 +1,73 @@
+use sea_orm::{sea_query::SimpleExpr, EntityTrait, Order, QueryOrder, QuerySelect, Select};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+/// The most rows a single page can hold
+const MAX_PER_PAGE: u64 = 500;
+
+/// The keys a listing can be sorted by, and the expression each sorts on
+pub type SortKeys = [(&'static str, fn() -> SimpleExpr)];
+
+/// Parses a `?sort=` parameter such as `unit_cost.desc,date.asc`, where each
+/// key must be one of `allowed`. The direction defaults to ascending.
+pub fn parse_sort(sort: &str, allowed: &SortKeys) -> Result<Vec<(SimpleExpr, Order)>, String> {
+    sort.split(',')
+        .filter(|key| !key.trim().is_empty())
+        .map(|key| {
+            let (column, direction) = key.trim().split_once('.').unwrap_or((key.trim(), "asc"));
+            let direction = match direction {
+                "asc" => Order::Asc,
+                "desc" => Order::Desc,
+                _ => {
+                    return Err(format!(
+                        "Invalid sort direction {direction:?}, expected asc or desc"
+                    ))
+                }
+            };
+            let Some((_, expr)) = allowed.iter().find(|(name, _)| *name == column) else {
+                let names: Vec<_> = allowed.iter().map(|(name, _)| *name).collect();
+                return Err(format!(
+                    "Cannot sort by {column:?}, expected one of {}",
+                    names.join(", ")
+                ));
+            };
+            Ok((expr(), direction))
+        })
+        .collect()
+}
+
+/// Optional `?page=&per_page=` parameters. Listings are unpaged when
+/// `per_page` is missing, for older clients.
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Page {
+    #[serde(default)]
+    pub page: u64,
+    /// Left out, every row is listed
+    #[param(minimum = 1)]
+    pub per_page: Option<u64>,
+}
+
+impl Page {
+    pub fn apply<E: EntityTrait>(&self, select: Select<E>) -> Select<E> {
+        match self.per_page {
+            Some(per_page) => {
+                let per_page = per_page.clamp(1, MAX_PER_PAGE);
+                select.offset(self.page * per_page).limit(per_page)
+            }
+            None => select,
+        }
+    }
+}
+
+/// Sorts `select` by `sort`, then by `tiebreak` so that pages don't overlap
+pub fn apply_sort<E: EntityTrait>(
+    mut select: Select<E>,
+    sort: Vec<(SimpleExpr, Order)>,
+    tiebreak: SimpleExpr,
+) -> Select<E> {
+    for (expr, order) in sort {
+        select = select.order_by(expr, order);
+    }
+    select.order_by(tiebreak, Order::Asc)
+}
@@ -0,0 +1,87 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::Status;
+
+/// The language a member's notifications are written in. Messages posted to
+/// a channel through a webhook are read by everyone, so they stay in English.
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize, Default)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(2))")]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    #[sea_orm(string_value = "en")]
+    En,
+    #[sea_orm(string_value = "es")]
+    Es,
+}
+
+impl Locale {
+    pub fn shift_reminder(self, start: &str) -> String {
+        match self {
+            Locale::En => format!("Reminder: your shift starts at {start}"),
+            Locale::Es => format!("Recordatorio: tu turno empieza a las {start}"),
+        }
+    }
+
+    pub fn checkout_reminder(self, equipment: &str) -> String {
+        match self {
+            Locale::En => format!(
+                "**{equipment}** is still checked out to you, please return it when you're done"
+            ),
+            Locale::Es => format!(
+                "**{equipment}** sigue prestado a tu nombre, por favor devuélvelo cuando termines"
+            ),
+        }
+    }
+
+    pub fn order_digest_heading(self) -> &'static str {
+        match self {
+            Locale::En => "**Your Orders Today**",
+            Locale::Es => "**Tus pedidos de hoy**",
+        }
+    }
+
+    pub fn pickup_ready(self, order: &str, name: &str, location: &str) -> String {
+        match (self, location.is_empty()) {
+            (Locale::En, true) => format!("**{order}** {name} is ready to pick up"),
+            (Locale::En, false) => {
+                format!("**{order}** {name} is ready to pick up from **{location}**")
+            }
+            (Locale::Es, true) => format!("**{order}** {name} está listo para recoger"),
+            (Locale::Es, false) => {
+                format!("**{order}** {name} está listo para recoger en **{location}**")
+            }
+        }
+    }
+
+    pub fn pickup_digest_heading(self) -> &'static str {
+        match self {
+            Locale::En => "**Still Waiting for You to Pick Up**",
+            Locale::Es => "**Todavía esperan a que los recojas**",
+        }
+    }
+
+    pub fn status(self, status: Status) -> &'static str {
+        match (self, status) {
+            (Locale::En, Status::New) => "New",
+            (Locale::En, Status::Submitted) => "Submitted",
+            (Locale::En, Status::Shipped) => "Shipped",
+            (Locale::En, Status::PartiallyReceived) => "Partially Received",
+            (Locale::En, Status::Delivered) => "Delivered",
+            (Locale::En, Status::InStorage) => "In Storage",
+            (Locale::En, Status::OnHold) => "On Hold",
+            (Locale::En, Status::Returned) => "Returned",
+            (Locale::En, Status::Cancelled) => "Cancelled",
+            (Locale::Es, Status::New) => "Nuevo",
+            (Locale::Es, Status::Submitted) => "Solicitado",
+            (Locale::Es, Status::Shipped) => "Enviado",
+            (Locale::Es, Status::PartiallyReceived) => "Recibido en parte",
+            (Locale::Es, Status::Delivered) => "Entregado",
+            (Locale::Es, Status::InStorage) => "Almacenado",
+            (Locale::Es, Status::OnHold) => "En espera",
+            (Locale::Es, Status::Returned) => "Devuelto",
+            (Locale::Es, Status::Cancelled) => "Cancelado",
+        }
+    }
+}
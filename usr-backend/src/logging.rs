@@ -0,0 +1,256 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{LineWriter, Write},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, OriginalUri},
+    http::{Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use serde::Deserialize;
+use tower_http::request_id::RequestId;
+use tracing::{info, Span};
+
+use crate::UsrState;
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Multi-line, human readable output
+    #[default]
+    Pretty,
+    /// One JSON object per line, for ingestion by Loki, Elastic, etc.
+    Json,
+}
+
+/// The span every request is handled in, so that everything logged while
+/// handling it can be correlated. `actor` is recorded once the caller has
+/// been identified.
+pub fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or_else(|| request.uri().path());
+
+    tracing::info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        route,
+        actor = tracing::field::Empty,
+    )
+}
+
+/// Requests bigger than this are rejected by the HTTP log rather than buffered
+const MAX_LOGGED_BODY: usize = 2 * 1024 * 1024;
+/// Body summaries are cut off after this many characters
+const BODY_SUMMARY_LEN: usize = 512;
+/// Values of JSON fields whose name contains any of these are never logged
+const REDACTED_FIELDS: [&str; 5] = ["password", "token", "secret", "key", "auth"];
+
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (name, value) in map.iter_mut() {
+                let name = name.to_lowercase();
+                if REDACTED_FIELDS.iter().any(|field| name.contains(field)) {
+                    *value = serde_json::Value::String("<redacted>".into());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn body_summary(body: &[u8]) -> String {
+    if body.is_empty() {
+        return String::new();
+    }
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return format!("<{} bytes>", body.len());
+    };
+    redact(&mut value);
+    let mut summary = value.to_string();
+    if let Some((cut, _)) = summary.char_indices().nth(BODY_SUMMARY_LEN) {
+        summary.truncate(cut);
+        summary.push_str("...");
+    }
+    summary
+}
+
+/// Logs the method, path, status, latency and a redacted summary of the body
+/// of every request. Headers are left out entirely so that credentials never
+/// end up in the logs.
+async fn log_request(request: Request<Body>, next: Next) -> Response {
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_LOGGED_BODY).await {
+        Ok(body) => body,
+        Err(_) => return (StatusCode::PAYLOAD_TOO_LARGE, "").into_response(),
+    };
+    let method = parts.method.clone();
+    let path = parts
+        .extensions
+        .get::<OriginalUri>()
+        .map_or(&parts.uri, |uri| &uri.0)
+        .path()
+        .to_string();
+    let summary = body_summary(&body);
+
+    let start = Instant::now();
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    info!(
+        %method,
+        path,
+        status = response.status().as_u16(),
+        latency_ms = start.elapsed().as_millis() as u64,
+        body = summary,
+        "HTTP request"
+    );
+
+    response
+}
+
+/// Adds the HTTP log to a route group if it is one of the groups enabled in
+/// `http_log` in the config.
+pub fn http_log(
+    enabled_groups: &[String],
+    group: &str,
+    router: Router<&'static UsrState>,
+) -> Router<&'static UsrState> {
+    if enabled_groups.iter().any(|enabled| enabled == group) {
+        router.layer(middleware::from_fn(log_request))
+    } else {
+        router
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LogFileConfig {
+    #[serde(default = "default_log_path")]
+    path: PathBuf,
+    /// Rotate once the log grows past this many bytes
+    max_bytes: Option<u64>,
+    /// Rotate once the log has been written to for this many hours
+    max_age_hours: Option<u64>,
+    /// How many rotated logs to keep, as `usr-backend.log.1` (newest) and up
+    #[serde(default = "default_retain")]
+    retain: usize,
+}
+
+fn default_log_path() -> PathBuf {
+    PathBuf::from("usr-backend.log")
+}
+
+fn default_retain() -> usize {
+    5
+}
+
+impl Default for LogFileConfig {
+    fn default() -> Self {
+        Self {
+            path: default_log_path(),
+            max_bytes: None,
+            max_age_hours: None,
+            retain: default_retain(),
+        }
+    }
+}
+
+/// A log file that is appended to across restarts and rotated by size and age.
+pub struct RotatingFile {
+    config: LogFileConfig,
+    file: LineWriter<File>,
+    written: u64,
+    opened: SystemTime,
+}
+
+impl RotatingFile {
+    pub fn open(config: LogFileConfig) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        let metadata = file.metadata()?;
+        Ok(Self {
+            written: metadata.len(),
+            opened: metadata.created().unwrap_or_else(|_| SystemTime::now()),
+            file: LineWriter::new(file),
+            config,
+        })
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.config.path.clone().into_os_string();
+        path.push(format!(".{n}"));
+        path.into()
+    }
+
+    fn should_rotate(&self) -> bool {
+        if self
+            .config
+            .max_bytes
+            .is_some_and(|max_bytes| self.written >= max_bytes)
+        {
+            return true;
+        }
+        self.config.max_age_hours.is_some_and(|hours| {
+            self.opened
+                .elapsed()
+                .is_ok_and(|age| age >= Duration::from_secs(hours * 60 * 60))
+        })
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        if self.config.retain == 0 {
+            std::fs::remove_file(&self.config.path)?;
+        } else {
+            let _ = std::fs::remove_file(self.rotated_path(self.config.retain));
+            for n in (1..self.config.retain).rev() {
+                let _ = std::fs::rename(self.rotated_path(n), self.rotated_path(n + 1));
+            }
+            std::fs::rename(&self.config.path, self.rotated_path(1))?;
+        }
+        self.reopen()
+    }
+
+    fn reopen(&mut self) -> std::io::Result<()> {
+        self.file = LineWriter::new(File::create(&self.config.path)?);
+        self.written = 0;
+        self.opened = SystemTime::now();
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if !self.config.path.exists() {
+            // Someone deleted the log out from under us
+            self.reopen()?;
+        } else if self.should_rotate() {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
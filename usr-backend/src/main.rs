@@ -1,27 +1,73 @@
 use std::{
-    backtrace::Backtrace, io::{LineWriter, Write}, net::SocketAddr, panic::set_hook, path::Path, sync::atomic::AtomicBool
+    backtrace::Backtrace, collections::HashMap, io::Write, net::SocketAddr, panic::set_hook, path::Path, sync::atomic::AtomicBool,
+    time::Duration,
 };
 
-use axum::{routing::get, Router};
+use axum::{
+    extract::{DefaultBodyLimit, State},
+    http::HeaderValue,
+    middleware,
+    response::Response,
+    routing::get,
+    Router,
+};
 use discord_webhook2::webhook::DiscordWebhook;
 use parking_lot::Mutex;
 use rustls::crypto::ring::default_provider;
-use sea_orm::{Database, DatabaseConnection};
+use sea_orm::{
+    prelude::Decimal, ConnectionTrait, Database, DatabaseConnection, TransactionTrait,
+};
 use serde::Deserialize;
 use tower::ServiceBuilder;
-use tower_http::cors::Any;
-use tracing::{error, info};
-use tracing_subscriber::FmtSubscriber;
+use tower_http::{
+    cors::Any,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{error, info, warn};
+use sentry::integrations::tower::{NewSentryLayer, SentryHttpLayer};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, FmtSubscriber};
 use webhook::BatchedWebhook;
 
 mod scheduler;
 mod manifest;
+mod auth;
+mod metrics;
 mod webhook;
 mod backup;
+mod api_error;
+mod archive;
+mod assets;
 mod attendance;
+mod dashboard;
+mod dm;
+mod flags;
+mod housekeeping;
+mod idempotency;
+mod jobs;
+mod kiosk;
+mod labels;
+mod listing;
+mod locale;
+mod logging;
+mod maintenance;
+mod migration;
+mod money;
+mod multipart;
+mod notify;
+mod packing;
+mod printing;
+mod ratelimit;
+mod readiness;
+mod registry;
+mod safety;
+mod schema;
+mod sponsorship;
+mod storage;
+mod travel;
 
 struct LogWriter {
-    inner: &'static Mutex<LineWriter<std::fs::File>>,
+    inner: &'static Mutex<logging::RotatingFile>,
 }
 
 impl Write for LogWriter {
@@ -40,99 +86,874 @@ impl Write for LogWriter {
 
 #[derive(Deserialize)]
 struct Config {
+    /// A SQLite database file, or a Postgres database, which is backed up
+    /// with `pg_dump` instead of being copied
+    #[serde(default = "default_database_url")]
+    database_url: String,
+    /// Git repository that the database is copied into and pushed from
+    #[serde(default = "default_backup_dir")]
+    backup_dir: String,
     new_orders_webhook: Option<String>,
     order_updates_webhook: Option<String>,
+    maintenance_webhook: Option<String>,
+    low_stock_webhook: Option<String>,
+    /// Reminders posted to the new orders webhook as an order waits for
+    /// approval, each mentioning someone further up the chain, eg. the team's
+    /// leads after a day and the admins after three
+    #[serde(default)]
+    approval_escalation: Vec<manifest::EscalationStep>,
+    /// Requirements new orders have to meet, eg. a reason of at least 20
+    /// characters, or a link on orders over $200
+    #[serde(default)]
+    intake_rules: Vec<manifest::IntakeRule>,
+    /// Which statuses orders can be moved to from each status, eg.
+    /// `{ "Shipped": ["Delivered", "InStorage"] }`. Left out, orders only
+    /// move forward, and can be put on hold or returned once they arrive.
+    #[serde(default)]
+    status_transitions: manifest::StatusTransitions,
+    /// How many days before an order's `needed_by` date the order updates
+    /// webhook is reminded of it if it hasn't been bought, and again once it
+    /// is overdue. Left out, nobody is reminded.
+    #[serde(default = "default_needed_by_reminder_days")]
+    needed_by_reminder_days: Option<u32>,
+    /// How many days an order can sit in storage before its requester is
+    /// reminded each morning to pick it up, until they confirm they have.
+    /// Left out, nobody is reminded.
+    #[serde(default = "default_pickup_reminder_days")]
+    pickup_reminder_days: Option<u32>,
+    /// Base url of the web UI, eg. https://usr.example.org, that webhook
+    /// messages link orders to
+    web_url: Option<String>,
+    /// Receives the weekly summary of spending per team, posted Monday mornings
+    spending_webhook: Option<String>,
+    /// Each team's budget for a budget period, shown as bars in the weekly
+    /// summary
+    #[serde(default)]
+    team_budgets: HashMap<scheduler::Team, Decimal>,
+    /// Percentages of a team's budget at which the spending webhook is
+    /// alerted, as orders push the team's spending past them
+    #[serde(default = "default_budget_thresholds")]
+    budget_thresholds: Vec<u8>,
+    /// Discord role id of each team's leads, mentioned in budget alerts
+    #[serde(default)]
+    team_lead_roles: HashMap<scheduler::Team, u64>,
+    /// Whether each team's balance, surplus or deficit, is added to its budget
+    /// when a budget period is closed, instead of starting over
+    #[serde(default)]
+    carry_over_budgets: bool,
+    /// Unit cost at or above which an order's units are added to the asset
+    /// register as it is put in storage. Leave out to not keep a register.
+    asset_threshold: Option<Decimal>,
+    /// Dollars per unit of each other currency that orders can be priced in,
+    /// captured by each order as it is placed
+    #[serde(default)]
+    exchange_rates: HashMap<manifest::Currency, Decimal>,
+    /// How subtotals and totals are rounded to the cent
+    #[serde(default)]
+    rounding: money::Rounding,
+    #[serde(default)]
+    labels: labels::LabelConfig,
+    #[serde(default)]
+    log_format: logging::LogFormat,
+    #[serde(default)]
+    log_file: logging::LogFileConfig,
+    /// Errors logged while handling requests, and panics, are reported here
+    sentry_dsn: Option<String>,
+    /// Route groups (eg. `manifest`, `admin`) whose requests are logged
+    #[serde(default)]
+    http_log: Vec<String>,
+    /// For staging instances running against a copy of production data.
+    /// Webhooks and backups are disabled and every response is marked.
+    #[serde(default)]
+    sandbox: bool,
+    /// Sends every webhook to `/dev/webhook-sink` on this server instead of
+    /// Discord. Only available in debug builds.
+    #[serde(default)]
+    webhook_sink: bool,
+    /// How long a Discord message is remembered, so that the same message
+    /// sent again within this many seconds, eg. from a change submitted
+    /// twice, is dropped. 0 sends every message.
+    #[serde(default = "default_webhook_dedupe_secs")]
+    webhook_dedupe_secs: u64,
+    /// How long a request made with an `Idempotency-Key` header is
+    /// remembered, so that a retry of it within this many hours gets the
+    /// same response instead of, eg. placing the order again
+    #[serde(default = "default_idempotency_ttl_hours")]
+    idempotency_ttl_hours: u32,
+    /// Token of the Discord bot that sends members direct messages, such as
+    /// shift reminders
+    discord_bot_token: Option<String>,
+    /// Serves `/api/public/manifest`, a sanitized view of the orders for the
+    /// team's website to embed
+    #[serde(default)]
+    public_manifest: bool,
+    /// Turns away requests without a user's token. Otherwise they are trusted
+    /// like a member, so that the web UI can still place and follow orders
+    /// before it signs in.
+    #[serde(default)]
+    require_auth: bool,
+    /// Accepts orders emailed to a shared mailbox, posted here by the mail
+    /// provider
+    email: Option<manifest::EmailConfig>,
+    /// Answers the `/order` slash command in Discord, registered through
+    /// `discord_bot_token`
+    interactions: Option<manifest::InteractionsConfig>,
+    /// Watches shipments with a tracking provider and marks them delivered
+    tracking: Option<manifest::TrackingConfig>,
+    /// How things change while the `competition_mode` flag is on: a waiver
+    /// on approvals, a channel of its own and more frequent digests
+    #[serde(default)]
+    competition: manifest::CompetitionConfig,
+    /// Slack, email or more Discord webhooks that notifications are sent
+    /// to, by topic
+    #[serde(default)]
+    notifications: Vec<notify::BackendConfig>,
+    /// The most each member can order in a calendar month, unless a lead
+    /// has set them a quota of their own. Orders leads place aren't limited.
+    #[serde(default)]
+    member_quota: manifest::Quota,
+    /// Where receipts and invoices uploaded to orders are kept, the
+    /// `attachments` directory by default
+    #[serde(default)]
+    attachments: storage::StorageConfig,
+    /// Copies of the database taken on a schedule and rotated, kept in the
+    /// `snapshots` directory by default
+    #[serde(default)]
+    snapshots: backup::SnapshotConfig,
+    /// An S3 bucket or SFTP server that each backup is also uploaded to.
+    /// Left out, backups are only pushed to `backup_dir`'s git remote.
+    #[serde(default)]
+    remote_backup: Option<backup::RemoteConfig>,
+    /// How often each client can change something, and how large a request
+    /// can be
+    #[serde(default)]
+    rate_limit: ratelimit::RateLimitConfig,
+    /// Sends order updates as a summary every few minutes instead of one by
+    /// one. Left out, each is sent as it happens.
+    #[serde(default)]
+    notification_digest: Option<notify::DigestConfig>,
+}
+
+fn default_database_url() -> String {
+    "sqlite://usr-db.sqlite?mode=rwc".to_string()
+}
+
+fn default_budget_thresholds() -> Vec<u8> {
+    vec![80, 95, 100]
+}
+
+fn default_needed_by_reminder_days() -> Option<u32> {
+    Some(3)
+}
+
+fn default_pickup_reminder_days() -> Option<u32> {
+    Some(7)
+}
+
+fn default_webhook_dedupe_secs() -> u64 {
+    10 * 60
+}
+
+fn default_idempotency_ttl_hours() -> u32 {
+    24
+}
+
+fn default_backup_dir() -> String {
+    "../usr-db-backup".to_string()
+}
+
+/// The file behind a SQLite database url, which is what gets backed up
+fn sqlite_path(url: &str) -> Option<&str> {
+    let rest = url
+        .strip_prefix("sqlite://")
+        .or_else(|| url.strip_prefix("sqlite:"))?;
+    let path = rest.split('?').next().unwrap_or_default();
+    if path.is_empty() || path == ":memory:" {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+/// Whether `url` points at a Postgres database, whose backups are taken with
+/// `pg_dump` instead of copying a file
+fn is_postgres_url(url: &str) -> bool {
+    url.starts_with("postgres://") || url.starts_with("postgresql://")
+}
+
+impl Config {
+    /// Checks every setting up front so that all of the problems can be
+    /// reported at once, instead of failing later inside a handler.
+    fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+
+        if sqlite_path(&self.database_url).is_none() && !is_postgres_url(&self.database_url) {
+            problems.push(format!(
+                "database_url: {:?} is neither a SQLite database file url, eg. sqlite://usr-db.sqlite?mode=rwc, nor a Postgres url, eg. postgres://usr@db.example.edu/usr",
+                self.database_url
+            ));
+        }
+
+        let backup_dir = Path::new(&self.backup_dir);
+        if !backup_dir.is_dir() {
+            problems.push(format!(
+                "backup_dir: {:?} is not a directory",
+                self.backup_dir
+            ));
+        } else if !backup_dir.join(".git").exists() {
+            problems.push(format!(
+                "backup_dir: {:?} is not a git repository",
+                self.backup_dir
+            ));
+        }
+
+        for (key, url) in [
+            ("new_orders_webhook", &self.new_orders_webhook),
+            ("order_updates_webhook", &self.order_updates_webhook),
+            ("maintenance_webhook", &self.maintenance_webhook),
+            ("low_stock_webhook", &self.low_stock_webhook),
+            ("spending_webhook", &self.spending_webhook),
+            ("competition.webhook", &self.competition.webhook),
+        ] {
+            let Some(url) = url else {
+                continue;
+            };
+            if let Err(msg) = webhook::check_url(url) {
+                problems.push(format!("{key}: {msg}"));
+            }
+        }
+
+        if let Some(url) = &self.web_url {
+            match url.parse::<axum::http::Uri>() {
+                Ok(uri) if matches!(uri.scheme_str(), Some("http" | "https")) => {}
+                Ok(_) => problems.push(format!(
+                    "web_url: {url:?} is not an http(s) url, eg. https://usr.example.org"
+                )),
+                Err(e) => problems.push(format!("web_url: {url:?} is not a valid url: {e}")),
+            }
+        }
+
+        for (team, budget) in &self.team_budgets {
+            if budget.is_sign_negative() {
+                problems.push(format!("team_budgets: {team} has a negative budget"));
+            }
+        }
+
+        if !self
+            .approval_escalation
+            .is_sorted_by_key(|step| step.after_hours)
+        {
+            problems.push(
+                "approval_escalation: steps must be in order of after_hours".to_string(),
+            );
+        }
+
+        self.status_transitions.validate(&mut problems);
+
+        for (index, rule) in self.intake_rules.iter().enumerate() {
+            rule.validate(index, &mut problems);
+        }
+
+        if self.budget_thresholds.contains(&0) {
+            problems.push("budget_thresholds: thresholds must be above 0%".to_string());
+        }
+
+        if self.asset_threshold.is_some_and(|threshold| threshold <= Decimal::ZERO) {
+            problems.push("asset_threshold: must be positive".to_string());
+        }
+
+        for (currency, rate) in &self.exchange_rates {
+            if *currency == manifest::Currency::Usd {
+                problems.push("exchange_rates: USD is what everything is converted to".to_string());
+            } else if *rate <= Decimal::ZERO {
+                problems.push(format!("exchange_rates: the rate for {currency} must be positive"));
+            }
+        }
+
+        if self.idempotency_ttl_hours == 0 {
+            problems.push("idempotency_ttl_hours: must be at least 1".to_string());
+        }
+
+        if self.webhook_sink && !cfg!(debug_assertions) {
+            problems.push("webhook_sink: only available in debug builds".to_string());
+        }
+
+        self.competition.validate(&mut problems);
+
+        if self.discord_bot_token.as_ref().is_some_and(|token| token.trim().is_empty()) {
+            problems.push("discord_bot_token: is empty".to_string());
+        }
+
+        if let Some(dsn) = &self.sentry_dsn {
+            if let Err(e) = dsn.parse::<sentry::types::Dsn>() {
+                problems.push(format!("sentry_dsn: {dsn:?} is not a valid DSN: {e}"));
+            }
+        }
+
+        self.labels.validate(&mut problems);
+        if let Some(interactions) = &self.interactions {
+            interactions.validate(&mut problems);
+        }
+        if let Some(email) = &self.email {
+            email.validate(&mut problems);
+        }
+        if let Some(tracking) = &self.tracking {
+            tracking.validate(&mut problems);
+        }
+        for backend in &self.notifications {
+            backend.validate(&mut problems);
+        }
+        self.attachments.validate("attachments", &mut problems);
+        self.snapshots.validate("snapshots", &mut problems);
+        if let Some(remote_backup) = &self.remote_backup {
+            remote_backup.validate("remote_backup", &mut problems);
+        }
+        self.member_quota.validate("member_quota", &mut problems);
+        self.rate_limit.validate("rate_limit", &mut problems);
+        if let Some(digest) = &self.notification_digest {
+            digest.validate("notification_digest", &mut problems);
+        }
+
+        problems
+    }
+}
+
+/// Checks every module's tables against its entities, returning each
+/// mismatch
+async fn verify_schema(
+    db: &(impl ConnectionTrait + TransactionTrait),
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(scheduler::verify_tables(db).await?);
+    problems.extend(manifest::verify_tables(db).await?);
+    problems.extend(attendance::verify_tables(db).await?);
+    problems.extend(sponsorship::verify_tables(db).await?);
+    problems.extend(travel::verify_tables(db).await?);
+    problems.extend(packing::verify_tables(db).await?);
+    problems.extend(registry::verify_tables(db).await?);
+    problems.extend(maintenance::verify_tables(db).await?);
+    problems.extend(safety::verify_tables(db).await?);
+    problems.extend(printing::verify_tables(db).await?);
+    problems.extend(webhook::verify_tables(db).await?);
+    problems.extend(housekeeping::verify_tables(db).await?);
+    problems.extend(idempotency::verify_tables(db).await?);
+    problems.extend(flags::verify_tables(db).await?);
+    problems.extend(assets::verify_tables(db).await?);
+    problems.extend(auth::verify_tables(db).await?);
+    problems.extend(dm::verify_tables(db).await?);
+    problems.extend(kiosk::verify_tables(db).await?);
+    problems.extend(jobs::verify_tables(db).await?);
+    Ok(problems)
 }
 
 struct UsrState {
     db: DatabaseConnection,
-    new_orders_webhook: Option<BatchedWebhook>,
-    order_updates_webhook: Option<BatchedWebhook>,
-    backup_task_running: AtomicBool
+    notifier: notify::Notifier,
+    team_budgets: HashMap<scheduler::Team, Decimal>,
+    carry_over_budgets: bool,
+    budget_thresholds: Vec<u8>,
+    team_lead_roles: HashMap<scheduler::Team, u64>,
+    approval_escalation: Vec<manifest::EscalationStep>,
+    intake_rules: Vec<manifest::IntakeRule>,
+    status_transitions: manifest::StatusTransitions,
+    needed_by_reminder_days: Option<u32>,
+    pickup_reminder_days: Option<u32>,
+    exchange_rates: HashMap<manifest::Currency, Decimal>,
+    asset_threshold: Option<Decimal>,
+    labels: labels::Labels,
+    flags: flags::Flags,
+    rollups: manifest::Rollups,
+    typeahead: manifest::Typeahead,
+    order_events: manifest::OrderEvents,
+    jobs: jobs::Jobs,
+    dashboard: dashboard::Dashboard,
+    metrics: metrics::Metrics,
+    idempotency: idempotency::Idempotency,
+    rate_limiter: ratelimit::RateLimiter,
+    db_path: String,
+    database_url: String,
+    backup_dir: String,
+    sandbox: bool,
+    require_auth: bool,
+    email_intake: Option<manifest::EmailIntake>,
+    interactions: Option<manifest::Interactions>,
+    tracking: Option<manifest::Tracking>,
+    webhook_sink: webhook::Sink,
+    backup_status: Mutex<backup::BackupStatus>,
+    backup_task_running: AtomicBool,
+    dm: dm::Dm,
+    attachments: storage::Storage,
+    snapshots: backup::SnapshotConfig,
+    remote_backup: Option<backup::Remote>,
+    member_quota: manifest::Quota,
+    competition: manifest::CompetitionConfig,
+}
+
+impl UsrState {
+    /// Whether the runtime feature flag `name` has been enabled for this deployment
+    fn flag_enabled(&self, name: &str) -> bool {
+        self.flags.enabled(name)
+    }
+
+    /// Applies whatever else follows from the flag `name`, once it is set or
+    /// deleted
+    fn flag_changed(&self, name: &str) {
+        if name == manifest::COMPETITION_FLAG {
+            self.notifier.set_diverted(self.flag_enabled(name));
+        }
+    }
+}
+
+#[cfg(test)]
+impl UsrState {
+    /// A sandbox with the default config, on `database_url` once it has been
+    /// migrated and checked like it is at startup, eg. `sqlite::memory:`
+    async fn for_tests(database_url: &str) -> &'static Self {
+        let config: Config = serde_json::from_str("{}").unwrap();
+        let mut options = sea_orm::ConnectOptions::new(database_url);
+        options.max_connections(1).sqlx_logging(false);
+        let db = Database::connect(options).await.unwrap();
+        migration::migrate(&db).await.unwrap();
+        let problems = verify_schema(&db).await.unwrap();
+        assert!(problems.is_empty(), "{problems:#?}");
+        Box::leak(Box::new(UsrState {
+            notifier: notify::Notifier::default(),
+            team_budgets: config.team_budgets,
+            carry_over_budgets: config.carry_over_budgets,
+            budget_thresholds: config.budget_thresholds,
+            team_lead_roles: config.team_lead_roles,
+            approval_escalation: config.approval_escalation,
+            intake_rules: config.intake_rules,
+            status_transitions: config.status_transitions,
+            needed_by_reminder_days: config.needed_by_reminder_days,
+            pickup_reminder_days: config.pickup_reminder_days,
+            exchange_rates: config.exchange_rates,
+            asset_threshold: config.asset_threshold,
+            labels: labels::Labels::load(config.labels).unwrap(),
+            flags: flags::Flags::load(&db).await.unwrap(),
+            rollups: manifest::Rollups::load(&db).await.unwrap(),
+            typeahead: manifest::Typeahead::default(),
+            order_events: manifest::OrderEvents::default(),
+            jobs: jobs::load(&db).await.unwrap(),
+            dashboard: dashboard::Dashboard::default(),
+            metrics: metrics::Metrics::default(),
+            idempotency: idempotency::Idempotency::new(config.idempotency_ttl_hours),
+            rate_limiter: ratelimit::RateLimiter::new(config.rate_limit),
+            db_path: sqlite_path(database_url).unwrap_or_default().to_string(),
+            database_url: database_url.to_string(),
+            backup_dir: config.backup_dir,
+            sandbox: true,
+            require_auth: config.require_auth,
+            email_intake: None,
+            interactions: None,
+            tracking: None,
+            webhook_sink: webhook::Sink::default(),
+            backup_status: Mutex::default(),
+            backup_task_running: AtomicBool::new(false),
+            dm: dm::Dm::new(None),
+            attachments: storage::Storage::new(config.attachments),
+            snapshots: config.snapshots,
+            remote_backup: None,
+            member_quota: config.member_quota,
+            competition: config.competition,
+            db,
+        }))
+    }
+
+    /// Sends `body` to `uri` on `router` as `role`, returning the status
+    async fn call_as(
+        &'static self,
+        router: Router<&'static Self>,
+        role: auth::Role,
+        method: axum::http::Method,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> axum::http::StatusCode {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .extension(auth::Caller {
+                name: Some("tester".to_string()),
+                role,
+            })
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        router
+            .with_state(self)
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+}
+
+/// Marks every response from a sandbox instance so that it can't be mistaken
+/// for production.
+async fn watermark_sandbox(State(state): State<&'static UsrState>, mut response: Response) -> Response {
+    if state.sandbox {
+        response
+            .headers_mut()
+            .insert("x-usr-sandbox", HeaderValue::from_static("true"));
+    }
+    response
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let log_file = Mutex::new(LineWriter::new(std::fs::File::create("usr-backend.log")?));
+    let mut config: Config = serde_json::from_reader(std::fs::File::open("config.json")?)?;
+    let log_file = Mutex::new(logging::RotatingFile::open(std::mem::take(&mut config.log_file))?);
     let log_file: &_ = Box::leak(Box::new(log_file));
 
-    FmtSubscriber::builder()
+    let builder = FmtSubscriber::builder()
         .with_file(true)
         .with_level(true)
         .with_line_number(true)
         .with_target(true)
         .with_thread_names(true)
         .with_timer(tracing_subscriber::fmt::time::ChronoLocal::rfc_3339())
-        .pretty()
         .with_ansi(false)
-        .with_writer(|| {
-            let mut lock = log_file.lock();
-
-            if lock.get_ref().metadata().is_err() {
-                println!("Error");
-                if let Ok(file) = std::fs::File::create("usr-backend.log") {
-                    *lock.get_mut() = file;
-                }
-            }
-
-            LogWriter { inner: log_file }
-        })
-        .init();
+        .with_writer(|| LogWriter { inner: log_file });
+    match config.log_format {
+        logging::LogFormat::Pretty => builder
+            .pretty()
+            .finish()
+            .with(sentry::integrations::tracing::layer())
+            .init(),
+        logging::LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .finish()
+            .with(sentry::integrations::tracing::layer())
+            .init(),
+    }
 
     set_hook(Box::new(|info| {
         let backtrace = Backtrace::capture();
         error!("{}\n{backtrace}", info);
     }));
 
-    let db = Database::connect("sqlite://usr-db.sqlite?mode=rwc").await?;
-    let config: Config = serde_json::from_reader(std::fs::File::open("config.json")?)?;
+    let problems = config.validate();
+    if !problems.is_empty() {
+        for problem in &problems {
+            error!("{problem}");
+        }
+        anyhow::bail!(
+            "config.json has {} problem(s):\n{}",
+            problems.len(),
+            problems.join("\n")
+        );
+    }
 
-    if Path::new(".reset-db").exists() {
-        info!("Resetting DB");
-        let directive = std::fs::read_to_string(".reset-db")?;
+    // Installed after our panic hook so that Sentry's hook chains onto it
+    let _sentry = config.sentry_dsn.as_deref().map(|dsn| {
+        sentry::init((
+            dsn,
+            sentry::ClientOptions {
+                release: sentry::release_name!(),
+                ..Default::default()
+            },
+        ))
+    });
 
-        match directive.as_str() {
-            "scheduler" => {
-                scheduler::reset_tables(&db).await?;
-                info!("Reset scheduler tables");
-            }
-            "manifest" => {
-                manifest::reset_tables(&db).await?;
-                info!("Reset manifest tables");
-            }
-            "attendance" => {
-                attendance::reset_tables(&db).await?;
-                info!("Reset attendance tables");
-            }
-            "all" => {
-                scheduler::reset_tables(&db).await?;
-                manifest::reset_tables(&db).await?;
-                attendance::reset_tables(&db).await?;
-                info!("Reset all tables");
-            }
-            _ => {
-                error!("Invalid directive in .reset-db");
-                return Ok(());
-            }
+    money::init(config.rounding);
+    if let Some(url) = &config.web_url {
+        manifest::init_permalinks(url);
+    }
+
+    default_provider()
+        .install_default()
+        .map_err(|_| anyhow::anyhow!("Failed to install ring CryptoProvider"))?;
+
+    // The port is taken before migrating so that the gate can say this
+    // instance isn't ready yet. The other commands don't serve at all.
+    let serving = !matches!(
+        std::env::args().nth(1).as_deref(),
+        Some("generate-load" | "import-sheet" | "db-maintenance" | "add-user" | "seed")
+    );
+    let listener = if serving {
+        Some(std::net::TcpListener::bind(listen_addr())?)
+    } else {
+        None
+    };
+    let gate = listener.as_ref().map(readiness::Gate::open).transpose()?;
+
+    let db = Database::connect(&config.database_url).await?;
+
+    migration::migrate(&db).await?;
+    let problems = verify_schema(&db).await?;
+    if !problems.is_empty() {
+        for problem in &problems {
+            error!("{problem}");
         }
+        anyhow::bail!(
+            "The database schema does not match this version of usr-backend:\n{}\nAn entity was changed without a migration",
+            problems.join("\n")
+        );
+    }
+    manifest::load_seasons(&db).await?;
+
+    if std::env::args().nth(1).as_deref() == Some("generate-load") {
+        let count = match std::env::args().nth(2) {
+            Some(count) => count.parse()?,
+            None => 20_000,
+        };
+        manifest::generate_load(&db, count).await?;
+        println!("Generated {count} orders");
+        return Ok(());
+    }
 
-        std::fs::remove_file(".reset-db")?;
+    if std::env::args().nth(1).as_deref() == Some("seed") {
+        let count = manifest::seed(&db).await?;
+        println!("Seeded {count} orders");
+        return Ok(());
     }
 
+    if std::env::args().nth(1).as_deref() == Some("import-sheet") {
+        let Some(path) = std::env::args().nth(2) else {
+            anyhow::bail!("Usage: usr-backend import-sheet <manifest.csv>");
+        };
+        let count = manifest::import_sheet(&db, std::fs::File::open(path)?).await?;
+        println!("Imported {count} orders");
+        return Ok(());
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("db-maintenance") {
+        let report = housekeeping::run(&db).await?;
+        println!("{}", report.message().replace("**", ""));
+        if report.abnormal_growth() {
+            println!("Warning: the database is growing abnormally fast");
+        }
+        return Ok(());
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("add-user") {
+        let (Some(name), Some(role)) = (std::env::args().nth(2), std::env::args().nth(3)) else {
+            anyhow::bail!("Usage: usr-backend add-user <name> <Member|Lead|Admin>");
+        };
+        let role: auth::Role = serde_json::from_value(serde_json::Value::String(role))?;
+        match auth::create_user(&db, &name, role).await? {
+            Ok(token) => println!("Token for {}: {token}", name.trim()),
+            Err(msg) => anyhow::bail!(msg),
+        }
+        return Ok(());
+    }
+
+    let sandbox = config.sandbox;
+    let sink_url = |destination: &str| {
+        config
+            .webhook_sink
+            .then(|| format!("http://127.0.0.1/dev/webhook-sink/{destination}"))
+    };
+    if sandbox {
+        warn!("Running in sandbox mode, webhooks and backups are disabled");
+    }
+    let mut notifier = notify::Notifier::default();
+    let dedupe_window = Duration::from_secs(config.webhook_dedupe_secs);
+    if !sandbox {
+        for (destination, url, topics) in [
+            (
+                "new_orders",
+                config.new_orders_webhook,
+                &[notify::Topic::NewOrder, notify::Topic::OrderReminder][..],
+            ),
+            (
+                "order_updates",
+                config.order_updates_webhook,
+                &[notify::Topic::OrderUpdate, notify::Topic::OrderApproval],
+            ),
+            ("maintenance", config.maintenance_webhook, &[notify::Topic::Maintenance]),
+            ("low_stock", config.low_stock_webhook, &[notify::Topic::LowStock]),
+            ("spending", config.spending_webhook, &[notify::Topic::Spending]),
+        ] {
+            if let Some(url) = sink_url(destination).or(url) {
+                notifier.register(
+                    topics.iter().copied(),
+                    BatchedWebhook::new(
+                        destination,
+                        DiscordWebhook::new(url)?,
+                        db.clone(),
+                        dedupe_window,
+                    ),
+                );
+            }
+        }
+        for backend in config.notifications {
+            notifier.register_config(backend, &db, sink_url, dedupe_window)?;
+        }
+        if let Some(url) = sink_url("competition").or(config.competition.webhook.take()) {
+            notifier.register_divert(
+                config.competition.topics.iter().copied(),
+                BatchedWebhook::new(
+                    "competition",
+                    DiscordWebhook::new(url)?,
+                    db.clone(),
+                    dedupe_window,
+                ),
+            );
+        }
+        notifier.enable_team_webhooks(notify::TeamWebhookSettings {
+            db: db.clone(),
+            dedupe_window,
+            sink: config.webhook_sink,
+        });
+        webhook::load_team_webhooks(&notifier, &db).await?;
+    }
+    if let Some(digest) = config.notification_digest {
+        notifier.enable_digest(digest);
+    }
+    let state: &'static UsrState = Box::leak(Box::new(UsrState {
+        notifier,
+        team_budgets: config.team_budgets,
+        carry_over_budgets: config.carry_over_budgets,
+        budget_thresholds: config.budget_thresholds,
+        team_lead_roles: config.team_lead_roles,
+        approval_escalation: config.approval_escalation,
+        intake_rules: config.intake_rules,
+        status_transitions: config.status_transitions,
+        needed_by_reminder_days: config.needed_by_reminder_days,
+        pickup_reminder_days: config.pickup_reminder_days,
+        exchange_rates: config.exchange_rates,
+        asset_threshold: config.asset_threshold,
+        labels: labels::Labels::load(config.labels)?,
+        flags: flags::Flags::load(&db).await?,
+        rollups: manifest::Rollups::load(&db).await?,
+        typeahead: manifest::Typeahead::default(),
+        order_events: manifest::OrderEvents::default(),
+        jobs: jobs::load(&db).await?,
+        dashboard: dashboard::Dashboard::default(),
+        metrics: metrics::Metrics::default(),
+        idempotency: idempotency::Idempotency::new(config.idempotency_ttl_hours),
+        rate_limiter: ratelimit::RateLimiter::new(config.rate_limit),
+        db_path: sqlite_path(&config.database_url).unwrap_or_default().to_string(),
+        database_url: config.database_url,
+        backup_dir: config.backup_dir,
+        sandbox: config.sandbox,
+        require_auth: config.require_auth,
+        email_intake: config
+            .email
+            .map(|email| manifest::EmailIntake::new(email, sandbox)),
+        interactions: config.interactions.map(manifest::Interactions::new),
+        tracking: config
+            .tracking
+            .map(|tracking| manifest::Tracking::new(tracking, sandbox)),
+        webhook_sink: webhook::Sink::default(),
+        backup_status: Mutex::default(),
+        backup_task_running: AtomicBool::new(false),
+        dm: dm::Dm::new(config.discord_bot_token.filter(|_| !sandbox)),
+        attachments: storage::Storage::new(config.attachments),
+        snapshots: config.snapshots,
+        remote_backup: config.remote_backup.map(backup::Remote::new),
+        member_quota: config.member_quota,
+        competition: config.competition,
+        db,
+    }));
+    state.flag_changed(manifest::COMPETITION_FLAG);
+
+    maintenance::spawn_reminders(state);
+    housekeeping::spawn_task(state);
+    backup::spawn_verification(state);
+    backup::spawn_snapshots(state);
+    webhook::spawn_retries(state);
+    idempotency::spawn_expiry(state);
+    dm::spawn_reminders(state);
+    manifest::spawn_weekly_post(state);
+    manifest::spawn_approval_reminders(state);
+    manifest::spawn_deadline_reminders(state);
+    manifest::spawn_pickup_reminders(state);
+    manifest::spawn_return_reminders(state);
+    manifest::spawn_recurring_orders(state);
+    manifest::spawn_tracking(state);
+    manifest::spawn_command_registration(state);
+    dashboard::spawn(state);
+    migration::spawn_heartbeat(state);
+    notify::spawn_digest(state);
+
+    let http_log = |group, router| logging::http_log(&config.http_log, group, router);
     let app = Router::new()
         .route(
             "/",
-            get(|| async { format!("Version: {}", env!("CARGO_PKG_VERSION")) }),
+            get(|State(state): State<&'static UsrState>| async move {
+                if state.sandbox {
+                    format!("Version: {} (sandbox)", env!("CARGO_PKG_VERSION"))
+                } else {
+                    format!("Version: {}", env!("CARGO_PKG_VERSION"))
+                }
+            }),
         )
+        .route("/metrics", get(metrics::get_metrics))
+        .route("/healthz", get(readiness::get_live))
+        .route("/readyz", get(readiness::get_readyz))
+        .merge(manifest::openapi_router())
+        .merge(if cfg!(debug_assertions) {
+            Router::new().nest("/dev", webhook::dev_router())
+        } else {
+            Router::new()
+        })
         .nest(
             "/api",
             Router::new()
-                .nest("/scheduler", scheduler::router())
-                .nest("/manifest", manifest::router())
-                .nest("/attendance", attendance::router()),
+                .nest("/scheduler", http_log("scheduler", scheduler::router()))
+                .nest("/manifest", http_log("manifest", manifest::router(state)))
+                .nest("/attendance", http_log("attendance", attendance::router()))
+                .nest("/sponsorship", http_log("sponsorship", sponsorship::router()))
+                .nest("/travel", http_log("travel", travel::router()))
+                .nest("/packing", http_log("packing", packing::router()))
+                .nest("/registry", http_log("registry", registry::router()))
+                .nest("/maintenance", http_log("maintenance", maintenance::router()))
+                .nest("/safety", http_log("safety", safety::router()))
+                .nest("/printing", http_log("printing", printing::router()))
+                .nest("/labels", http_log("labels", labels::router()))
+                .nest("/dm", http_log("dm", dm::router()))
+                .nest("/kiosk", http_log("kiosk", kiosk::router()))
+                .nest("/assets", http_log("assets", assets::router()))
+                .nest("/auth", http_log("auth", auth::router()))
+                .nest("/notifications", http_log("notifications", notify::router()))
+                .merge(http_log("dashboard", dashboard::router()))
+                .nest(
+                    "/admin",
+                    http_log(
+                        "admin",
+                        manifest::admin_router()
+                            .merge(flags::router())
+                            .merge(backup::router())
+                            .merge(jobs::router())
+                            .merge(archive::router())
+                            .merge(auth::admin_router())
+                            .merge(notify::admin_router())
+                            .nest("/webhooks", webhook::router())
+                            .nest("/db", housekeeping::router())
+                            .layer(middleware::from_fn(auth::require_lead)),
+                    ),
+                )
+                .layer(middleware::from_fn_with_state(state, ratelimit::limit))
+                .layer(DefaultBodyLimit::max(state.rate_limiter.max_body_bytes()))
+                .layer(middleware::from_fn_with_state(state, auth::authenticate))
+                // The mail provider signs in with the key in its url instead
+                .nest("/email", http_log("email", manifest::email_router()))
+                // So does the tracking provider
+                .nest("/tracking", http_log("tracking", manifest::tracking_router()))
+                // And Discord signs each interaction
+                .nest("/discord", http_log("discord", manifest::interactions_router()))
+                // The public manifest is for anyone, so it is added after
+                // authentication
+                .merge(if config.public_manifest {
+                    Router::new().nest("/public", http_log("public", manifest::public_router()))
+                } else {
+                    Router::new()
+                }),
         )
         .layer(
             ServiceBuilder::new()
+                .layer(NewSentryLayer::<axum::extract::Request>::new_from_top())
+                .layer(SentryHttpLayer::new())
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(TraceLayer::new_for_http().make_span_with(logging::request_span))
+                .layer(middleware::map_response_with_state(state, watermark_sandbox))
+                .layer(middleware::from_fn_with_state(state, metrics::track_latency))
+                .layer(PropagateRequestIdLayer::x_request_id())
                 .layer({
                     let mut layer = tower_http::cors::CorsLayer::new();
                     #[cfg(debug_assertions)]
@@ -151,46 +972,52 @@ async fn main() -> anyhow::Result<()> {
                 })
                 .layer(tower_http::compression::CompressionLayer::new())
         )
-        .with_state(Box::leak(Box::new(UsrState {
-            db,
-            new_orders_webhook: {
-                if let Some(new_orders_webhook) = config.new_orders_webhook {
-                    Some(DiscordWebhook::new(new_orders_webhook)?.into())
-                } else {
-                    None
-                }
-            },
-            order_updates_webhook: {
-                if let Some(order_updates_webhook) = config.order_updates_webhook {
-                    Some(DiscordWebhook::new(order_updates_webhook)?.into())
-                } else {
-                    None
-                }
-            },
-            backup_task_running: AtomicBool::new(false),
-        })));
-
-    default_provider()
-        .install_default()
-        .map_err(|_| anyhow::anyhow!("Failed to install ring CryptoProvider"))?;
+        .with_state(state);
 
     info!("Starting server");
+    if let Some(gate) = gate {
+        gate.close().await;
+    }
+    let listener = match listener {
+        Some(listener) => listener,
+        None => std::net::TcpListener::bind(listen_addr())?,
+    };
+    let handle = axum_server::Handle::new();
+    readiness::shut_down_on_signal(handle.clone());
+    serve(listener, app, handle).await?;
+
+    // Batched messages would otherwise be lost with the process
+    info!("Sending queued webhook messages");
+    state.notifier.flush().await;
+    info!("Stopped");
+    Ok(())
+}
+
+fn listen_addr() -> SocketAddr {
+    if cfg!(debug_assertions) {
+        SocketAddr::from(([0, 0, 0, 0], 80))
+    } else {
+        SocketAddr::from(([0, 0, 0, 0], 443))
+    }
+}
+
+/// Serves `app` on `listener` until `handle` shuts it down, over TLS in
+/// release builds
+async fn serve(listener: std::net::TcpListener, app: Router, handle: axum_server::Handle) -> std::io::Result<()> {
     #[cfg(not(debug_assertions))]
     {
         use axum_server::tls_rustls::RustlsConfig;
         let config = RustlsConfig::from_pem_file("cert.pem", "key.pem").await?;
-        let addr = SocketAddr::from(([0, 0, 0, 0], 443));
-        axum_server::bind_rustls(addr, config)
-            .serve(app.into_make_service())
+        axum_server::from_tcp_rustls(listener, config)
+            .handle(handle)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await
-            .map_err(Into::into)
     }
     #[cfg(debug_assertions)]
     {
-        let addr = SocketAddr::from(([0, 0, 0, 0], 80));
-        axum_server::bind(addr)
-            .serve(app.into_make_service())
+        axum_server::from_tcp(listener)
+            .handle(handle)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await
-            .map_err(Into::into)
     }
 }
@@ -0,0 +1,724 @@
+use std::{collections::HashMap, time::Duration};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{Days, Local, NaiveDateTime};
+use sea_orm::{
+    prelude::Date,
+    sea_query::{Condition, Expr},
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::{
+    auth::{Caller, Role},
+    backup::backup_db,
+    jobs::{self, Retry, Schedule},
+    kiosk,
+    notify::Topic,
+    safety, schema, UsrState,
+};
+
+mod checkout;
+mod equipment;
+mod service_record;
+
+fn next_due(from: Date, interval_days: i64) -> Date {
+    from.checked_add_days(Days::new(interval_days.unsigned_abs()))
+        .unwrap_or(Date::MAX)
+}
+
+#[derive(Deserialize)]
+struct PendingEquipment {
+    name: String,
+    #[serde(default)]
+    location: String,
+    interval_days: u32,
+    #[serde(default)]
+    required_certification: Option<String>,
+}
+
+#[axum::debug_handler]
+async fn new_equipment(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(pending_equipment): Json<PendingEquipment>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot add equipment", caller.role),
+        )
+            .into_response();
+    }
+    if pending_equipment.name.is_empty() || pending_equipment.interval_days == 0 {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let active_model = equipment::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(pending_equipment.name),
+        location: ActiveValue::Set(pending_equipment.location),
+        interval_days: ActiveValue::Set(pending_equipment.interval_days.into()),
+        next_due: ActiveValue::Set(next_due(
+            Local::now().date_naive(),
+            pending_equipment.interval_days.into(),
+        )),
+        required_certification: ActiveValue::Set(
+            pending_equipment
+                .required_certification
+                .filter(|x| !x.is_empty()),
+        ),
+        last_reminded: ActiveValue::Set(None),
+    };
+
+    match active_model.insert(&state.db).await {
+        Ok(model) => {
+            kiosk::equipment_changed(&state.db, model.id).await;
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to add equipment: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EquipmentStatus {
+    #[serde(flatten)]
+    equipment: equipment::Model,
+    overdue: bool,
+}
+
+#[axum::debug_handler]
+async fn get_equipment(State(state): State<&'static UsrState>) -> Response {
+    match equipment::Entity::find()
+        .order_by_asc(equipment::Column::NextDue)
+        .all(&state.db)
+        .await
+    {
+        Ok(equipment) => {
+            let today = Local::now().date_naive();
+            Json(
+                equipment
+                    .into_iter()
+                    .map(|equipment| EquipmentStatus {
+                        overdue: equipment.next_due < today,
+                        equipment,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .into_response()
+        }
+        Err(e) => {
+            error!("Failed to enumerate equipment: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PendingService {
+    equipment_id: u32,
+    performed_by: String,
+    #[serde(default)]
+    notes: String,
+}
+
+#[axum::debug_handler]
+async fn new_service(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(pending_service): Json<PendingService>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot record equipment service", caller.role),
+        )
+            .into_response();
+    }
+    if pending_service.performed_by.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let equipment = match equipment::Entity::find_by_id(pending_service.equipment_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Equipment not found").into_response(),
+        Err(e) => {
+            error!("Failed to find equipment: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let equipment_id = equipment.id;
+    let now = Local::now().naive_local();
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                service_record::ActiveModel {
+                    id: ActiveValue::NotSet,
+                    equipment_id: ActiveValue::Set(equipment.id),
+                    date: ActiveValue::Set(now),
+                    performed_by: ActiveValue::Set(pending_service.performed_by),
+                    notes: ActiveValue::Set(pending_service.notes),
+                }
+                .insert(tx)
+                .await?;
+
+                equipment::ActiveModel {
+                    id: ActiveValue::Unchanged(equipment.id),
+                    name: ActiveValue::NotSet,
+                    location: ActiveValue::NotSet,
+                    interval_days: ActiveValue::NotSet,
+                    next_due: ActiveValue::Set(next_due(now.date(), equipment.interval_days)),
+                    required_certification: ActiveValue::NotSet,
+                    last_reminded: ActiveValue::Set(None),
+                }
+                .update(tx)
+                .await?;
+
+                Result::<_, sea_orm::DbErr>::Ok(())
+            })
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to record service: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        kiosk::equipment_changed(&state.db, equipment_id).await;
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[axum::debug_handler]
+async fn get_service_records(
+    State(state): State<&'static UsrState>,
+    Path(equipment_id): Path<u32>,
+) -> Response {
+    match service_record::Entity::find()
+        .filter(service_record::Column::EquipmentId.eq(equipment_id))
+        .order_by_desc(service_record::Column::Date)
+        .all(&state.db)
+        .await
+    {
+        Ok(records) => Json(records).into_response(),
+        Err(e) => {
+            error!("Failed to enumerate service records: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CheckoutEquipment {
+    equipment_id: u32,
+    member: String,
+    /// Why the signed in supervisor is letting an uncertified member through
+    /// the safety gate
+    #[serde(default)]
+    override_reason: Option<String>,
+}
+
+#[axum::debug_handler]
+async fn checkout_equipment(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(checkout_equipment): Json<CheckoutEquipment>,
+) -> Response {
+    if checkout_equipment.member.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let equipment = match equipment::Entity::find_by_id(checkout_equipment.equipment_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Equipment not found").into_response(),
+        Err(e) => {
+            error!("Failed to find equipment: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    match checkout::Entity::find()
+        .filter(checkout::Column::EquipmentId.eq(equipment.id))
+        .filter(checkout::Column::Returned.is_null())
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(_)) => {
+            return (StatusCode::BAD_REQUEST, "Equipment is already checked out").into_response();
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to find checkout: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    }
+
+    let mut supervisor = None;
+    if let Some(certification) = &equipment.required_certification {
+        match safety::is_certified(&state.db, &checkout_equipment.member, certification, false)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                if checkout_equipment.override_reason.is_none() {
+                    return (
+                        StatusCode::FORBIDDEN,
+                        "Member lacks the required certification",
+                    )
+                        .into_response();
+                }
+                let (Some(name), true) = (&caller.name, caller.role >= Role::Lead) else {
+                    return (
+                        StatusCode::FORBIDDEN,
+                        format!("{} cannot override the safety gate", caller.role),
+                    )
+                        .into_response();
+                };
+                if checkout_equipment
+                    .override_reason
+                    .as_ref()
+                    .is_none_or(|x| x.is_empty())
+                {
+                    return (StatusCode::BAD_REQUEST, "Overrides require a reason").into_response();
+                }
+                match safety::is_certified(&state.db, name, certification, true).await {
+                    Ok(true) => supervisor = Some(name.clone()),
+                    Ok(false) => {
+                        return (
+                            StatusCode::FORBIDDEN,
+                            "Supervisor cannot override this certification",
+                        )
+                            .into_response();
+                    }
+                    Err(e) => {
+                        error!("Failed to find certification: {e}");
+                        return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to find certification: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+            }
+        }
+    }
+
+    let override_reason = supervisor
+        .is_some()
+        .then_some(checkout_equipment.override_reason)
+        .flatten();
+    let active_model = checkout::ActiveModel {
+        id: ActiveValue::NotSet,
+        equipment_id: ActiveValue::Set(equipment.id),
+        member: ActiveValue::Set(checkout_equipment.member),
+        date: ActiveValue::Set(Local::now().naive_local()),
+        returned: ActiveValue::Set(None),
+        override_by: ActiveValue::Set(supervisor),
+        override_reason: ActiveValue::Set(override_reason),
+    };
+
+    match active_model.insert(&state.db).await {
+        Ok(model) => {
+            kiosk::equipment_changed(&state.db, model.equipment_id).await;
+            backup_db(state);
+            if let (Some(supervisor), Some(reason)) = (&model.override_by, &model.override_reason) {
+                warn!(
+                    "{supervisor} overrode the safety gate for {} on {}: {reason}",
+                    model.member, equipment.name
+                );
+                state.notifier.send(
+                    Topic::Maintenance,
+                    model.id as u32,
+                    format!(
+                        "**Safety Override**\n**Equipment:** {}\n**Member:** {}\n**Supervisor:** {supervisor}\n**Reason:** {reason}",
+                        equipment.name, model.member
+                    ),
+                );
+            }
+            // Hazardous equipment comes with its handling notes so the kiosk can show them
+            match safety::hazard_for(&state.db, &equipment.name).await {
+                Ok(Some(hazard)) => Json(hazard).into_response(),
+                Ok(None) => (StatusCode::OK, "").into_response(),
+                Err(e) => {
+                    error!("Failed to find hazard: {e}");
+                    (StatusCode::OK, "").into_response()
+                }
+            }
+        }
+        Err(e) => {
+            error!("Failed to check out equipment: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ReturnEquipment {
+    equipment_id: u32,
+}
+
+#[axum::debug_handler]
+async fn return_equipment(
+    State(state): State<&'static UsrState>,
+    Json(ReturnEquipment { equipment_id }): Json<ReturnEquipment>,
+) -> (StatusCode, &'static str) {
+    let result = checkout::Entity::update_many()
+        .col_expr(
+            checkout::Column::Returned,
+            Expr::value(Local::now().naive_local()),
+        )
+        .filter(checkout::Column::EquipmentId.eq(equipment_id))
+        .filter(checkout::Column::Returned.is_null())
+        .exec(&state.db)
+        .await;
+
+    match result {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Equipment is not checked out")
+        }
+        Ok(_) => {
+            kiosk::equipment_changed(&state.db, equipment_id.into()).await;
+            backup_db(state);
+            (StatusCode::OK, "")
+        }
+        Err(e) => {
+            error!("Failed to return equipment: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn get_checkouts(State(state): State<&'static UsrState>) -> Response {
+    match checkout::Entity::find()
+        .order_by_desc(checkout::Column::Date)
+        .all(&state.db)
+        .await
+    {
+        Ok(checkouts) => Json(checkouts).into_response(),
+        Err(e) => {
+            error!("Failed to enumerate checkouts: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Checks `equipment_id` out to `member` as of `date`, for check outs made
+/// while the kiosk was offline, or why it can't be. There is no supervisor on
+/// hand to override the safety gate, so uncertified members are turned away.
+pub async fn checkout_at(
+    db: &impl ConnectionTrait,
+    equipment_id: u32,
+    member: String,
+    date: NaiveDateTime,
+) -> Result<Result<(), &'static str>, sea_orm::DbErr> {
+    if member.is_empty() {
+        return Ok(Err("Member is required"));
+    }
+    let Some(equipment) = equipment::Entity::find_by_id(equipment_id).one(db).await? else {
+        return Ok(Err("Equipment not found"));
+    };
+    if checkout::Entity::find()
+        .filter(checkout::Column::EquipmentId.eq(equipment.id))
+        .filter(checkout::Column::Returned.is_null())
+        .one(db)
+        .await?
+        .is_some()
+    {
+        return Ok(Err("Equipment is already checked out"));
+    }
+    if let Some(certification) = &equipment.required_certification {
+        if !safety::is_certified(db, &member, certification, false).await? {
+            return Ok(Err("Member lacks the required certification"));
+        }
+    }
+    checkout::ActiveModel {
+        id: ActiveValue::NotSet,
+        equipment_id: ActiveValue::Set(equipment.id),
+        member: ActiveValue::Set(member),
+        date: ActiveValue::Set(date),
+        returned: ActiveValue::Set(None),
+        override_by: ActiveValue::Set(None),
+        override_reason: ActiveValue::Set(None),
+    }
+    .insert(db)
+    .await?;
+    Ok(Ok(()))
+}
+
+/// Returns `equipment_id` as of `date`, or why it can't be
+pub async fn return_at(
+    db: &impl ConnectionTrait,
+    equipment_id: u32,
+    date: NaiveDateTime,
+) -> Result<Result<(), &'static str>, sea_orm::DbErr> {
+    let Some(model) = checkout::Entity::find()
+        .filter(checkout::Column::EquipmentId.eq(equipment_id))
+        .filter(checkout::Column::Returned.is_null())
+        .one(db)
+        .await?
+    else {
+        return Ok(Err("Equipment is not checked out"));
+    };
+    checkout::ActiveModel {
+        id: ActiveValue::Unchanged(model.id),
+        equipment_id: ActiveValue::NotSet,
+        member: ActiveValue::NotSet,
+        date: ActiveValue::NotSet,
+        // Kiosk clocks drift, and an item can't come back before it left
+        returned: ActiveValue::Set(Some(date.max(model.date))),
+        override_by: ActiveValue::NotSet,
+        override_reason: ActiveValue::NotSet,
+    }
+    .update(db)
+    .await?;
+    Ok(Ok(()))
+}
+
+/// What the kiosk shows for a piece of equipment
+#[derive(Serialize)]
+pub struct EquipmentState {
+    #[serde(flatten)]
+    equipment: equipment::Model,
+    /// The checkout that hasn't been returned, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checkout: Option<checkout::Model>,
+}
+
+/// The state of `ids`, or of every piece of equipment if `None`
+pub async fn equipment_states(
+    db: &DatabaseConnection,
+    ids: Option<Vec<i64>>,
+) -> Result<Vec<EquipmentState>, sea_orm::DbErr> {
+    let mut equipment = equipment::Entity::find();
+    let mut checkouts = checkout::Entity::find().filter(checkout::Column::Returned.is_null());
+    if let Some(ids) = ids {
+        equipment = equipment.filter(equipment::Column::Id.is_in(ids.clone()));
+        checkouts = checkouts.filter(checkout::Column::EquipmentId.is_in(ids));
+    }
+    let mut checkouts: HashMap<i64, checkout::Model> = checkouts
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| (model.equipment_id, model))
+        .collect();
+    Ok(equipment
+        .order_by_asc(equipment::Column::Id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|equipment| EquipmentState {
+            checkout: checkouts.remove(&equipment.id),
+            equipment,
+        })
+        .collect())
+}
+
+/// Checkouts that still haven't been returned after being checked out before
+/// `checked_out_before`, as the checkout id, member, and equipment name.
+pub async fn overdue_checkouts(
+    db: &DatabaseConnection,
+    checked_out_before: NaiveDateTime,
+) -> Result<Vec<(i64, String, String)>, sea_orm::DbErr> {
+    let checkouts = checkout::Entity::find()
+        .filter(checkout::Column::Returned.is_null())
+        .filter(checkout::Column::Date.lt(checked_out_before))
+        .all(db)
+        .await?;
+    let equipment: HashMap<i64, String> = equipment::Entity::find()
+        .filter(equipment::Column::Id.is_in(checkouts.iter().map(|model| model.equipment_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| (model.id, model.name))
+        .collect();
+
+    Ok(checkouts
+        .into_iter()
+        .map(|model| {
+            let name = equipment
+                .get(&model.equipment_id)
+                .cloned()
+                .unwrap_or_default();
+            (model.id, model.member, name)
+        })
+        .collect())
+}
+
+async fn remind_overdue(state: &'static UsrState) -> Result<(), sea_orm::DbErr> {
+    let today = Local::now().date_naive();
+    let overdue = equipment::Entity::find()
+        .filter(equipment::Column::NextDue.lt(today))
+        .filter(
+            Condition::any()
+                .add(equipment::Column::LastReminded.is_null())
+                .add(equipment::Column::LastReminded.lt(today)),
+        )
+        .all(&state.db)
+        .await?;
+
+    for model in overdue {
+        let days = (today - model.next_due).num_days();
+        state.notifier.send(
+            Topic::Maintenance,
+            model.id as u32,
+            format!(
+                "**Maintenance Overdue!**\n**Equipment:** {}\n**Location:** {}\n**Due:** {} ({days} days ago)",
+                model.name, model.location, model.next_due
+            ),
+        );
+        equipment::ActiveModel {
+            id: ActiveValue::Unchanged(model.id),
+            name: ActiveValue::NotSet,
+            location: ActiveValue::NotSet,
+            interval_days: ActiveValue::NotSet,
+            next_due: ActiveValue::NotSet,
+            required_certification: ActiveValue::NotSet,
+            last_reminded: ActiveValue::Set(Some(today)),
+        }
+        .update(&state.db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Periodically posts reminders for overdue equipment to the maintenance webhook.
+pub fn spawn_reminders(state: &'static UsrState) {
+    if !state.notifier.routes(Topic::Maintenance) {
+        return;
+    }
+    jobs::spawn(
+        state,
+        "maintenance_reminders",
+        Schedule::Every(Duration::from_secs(60 * 60)),
+        Retry::times(2, Duration::from_secs(5 * 60)),
+        |state| async move { Ok(remind_overdue(state).await?) },
+    );
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/new/equipment", post(new_equipment))
+        .route("/list/equipment", get(get_equipment))
+        .route("/new/service", post(new_service))
+        .route("/list/service/{equipment_id}", get(get_service_records))
+        .route("/checkout/equipment", post(checkout_equipment))
+        .route("/return/equipment", post(return_equipment))
+        .route("/list/checkout", get(get_checkouts))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, equipment::Entity).await?);
+    problems.extend(schema::verify(db, service_record::Entity).await?);
+    problems.extend(schema::verify(db, checkout::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use sea_orm::EntityTrait;
+    use serde_json::json;
+
+    use crate::{auth::Role, safety, UsrState};
+
+    use super::checkout;
+
+    #[tokio::test]
+    async fn members_cannot_change_equipment_or_service_records() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [
+            (
+                Method::POST,
+                "/new/equipment",
+                json!({ "name": "Mill", "location": "Shop", "interval_days": 30, "required_certification": null }),
+            ),
+            (
+                Method::POST,
+                "/new/service",
+                json!({ "equipment_id": 1, "performed_by": "a", "notes": "" }),
+            ),
+        ];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn overrides_are_made_by_the_signed_in_lead() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let setup = [
+            (
+                safety::router(),
+                "/set/certification",
+                json!({ "member": "tester", "certification": "Mill", "granted_by": "a", "supervisor": true }),
+            ),
+            (
+                super::router(),
+                "/new/equipment",
+                json!({ "name": "Mill", "location": "Shop", "interval_days": 30, "required_certification": "Mill" }),
+            ),
+        ];
+        for (router, uri, body) in setup {
+            let status = state
+                .call_as(router, Role::Lead, Method::POST, uri, body)
+                .await;
+            assert_eq!(status, StatusCode::OK, "{uri}");
+        }
+        let checkout =
+            json!({ "equipment_id": 1, "member": "newbie", "override_reason": "Supervised" });
+
+        let status = state
+            .call_as(
+                super::router(),
+                Role::Member,
+                Method::POST,
+                "/checkout/equipment",
+                checkout.clone(),
+            )
+            .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let status = state
+            .call_as(
+                super::router(),
+                Role::Lead,
+                Method::POST,
+                "/checkout/equipment",
+                checkout,
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let model = checkout::Entity::find()
+            .one(&state.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(model.override_by.as_deref(), Some("tester"));
+    }
+}
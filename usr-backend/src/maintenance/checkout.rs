@@ -0,0 +1,27 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "equipment_checkouts")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub equipment_id: i64,
+    pub member: String,
+    pub date: DateTime,
+    #[sea_orm(nullable)]
+    pub returned: Option<DateTime>,
+    /// The supervisor who let an uncertified member check this out
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub override_by: Option<String>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub override_reason: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
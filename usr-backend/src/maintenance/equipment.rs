@@ -0,0 +1,28 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "equipment")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    pub location: String,
+    /// Days between required services
+    pub interval_days: i64,
+    pub next_due: Date,
+    /// The safety certification members need before checking this out
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_certification: Option<String>,
+    /// The last day an overdue reminder was posted, so we only nag once a day
+    #[sea_orm(nullable)]
+    #[serde(skip)]
+    pub last_reminded: Option<Date>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,419 +1,6199 @@
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
-    routing::{delete, get, post},
+    routing::{delete, get, patch, post},
     Json, Router,
 };
+use chrono::Datelike;
 use sea_orm::{
-    prelude::Decimal, sea_query::Table, sqlx::types::chrono::Local, ActiveModelTrait, ActiveValue,
-    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Schema,
-    TransactionTrait,
+    prelude::Decimal,
+    sea_query::{Expr, OnConflict, SimpleExpr},
+    sqlx::types::chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime},
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, Iterable, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    api_error::{self, ApiError},
+    backup::backup_db,
+    dm, idempotency, listing, money,
+    notify::Topic,
+    registry, scheduler, schema, UsrState,
+};
+
+mod analytics;
+mod approval;
+mod arrivals;
+mod attachment;
+mod audit;
+mod batch;
+mod budget;
+mod budget_period;
+mod checkout;
+mod comment;
+mod competition;
+mod cost_split;
+mod countdown;
+mod custom_field;
+mod dashboard;
+mod current;
+mod deadline;
+mod digest;
+mod discrepancy;
+mod email;
+mod escalation;
+mod events;
+mod field_value;
+mod fixture;
+mod freeze;
+mod funding;
+mod import;
+mod intake;
+mod interactions;
+mod inventory;
+mod lead_time;
+mod loadgen;
+mod location;
+mod openapi;
+mod order;
+mod order_status;
+mod period_total;
+mod permalink;
+mod pickup;
+mod policy;
+mod price;
+mod public;
+mod quota;
+mod recurring;
+mod reimbursement;
+mod reminder;
+mod returns;
+mod revision;
+mod rollup;
+mod search;
+mod season;
+mod season_budget;
+mod service;
+mod sheet;
+mod shipment;
+mod shipment_order;
+mod spend;
+mod stock;
+mod tax;
+mod template;
+#[cfg(test)]
+mod tests;
+mod tracking;
+mod transfer;
+mod typeahead;
+mod vendor;
+mod vendor_policy;
+mod watch;
+mod weekly;
+mod wishlist;
+mod xlsx;
+
+pub use fixture::seed;
+pub use loadgen::generate as generate_load;
+pub use competition::{
+    digest_hours as competition_digest_hours, CompetitionConfig, FLAG as COMPETITION_FLAG,
 };
-use serde::Deserialize;
-use tracing::error;
+pub use digest::requester_digests;
+pub use dashboard::{section as dashboard_section, Section as DashboardSection};
+pub use deadline::spawn as spawn_deadline_reminders;
+pub use pickup::spawn as spawn_pickup_reminders;
+pub use returns::spawn as spawn_return_reminders;
+pub use email::{router as email_router, EmailConfig, EmailIntake};
+pub use interactions::{
+    router as interactions_router, spawn as spawn_command_registration, Interactions,
+    InteractionsConfig,
+};
+pub use tracking::{
+    router as tracking_router, spawn as spawn_tracking, Tracking, TrackingConfig,
+};
+pub use escalation::{spawn as spawn_approval_reminders, Step as EscalationStep};
+pub use intake::IntakeRule;
+pub use events::OrderEvents;
+pub use openapi::router as openapi_router;
+pub use order::{Currency, Model as Order};
+pub use order_status::{Status, Transitions as StatusTransitions};
+pub use permalink::init as init_permalinks;
+pub use public::router as public_router;
+pub use quota::Quota;
+pub use recurring::spawn as spawn_recurring_orders;
+pub use season::load as load_seasons;
+pub use rollup::Rollups;
+pub use sheet::import as import_sheet;
+pub use typeahead::Typeahead;
+pub use weekly::spawn as spawn_weekly_post;
+
+#[derive(Deserialize, ToSchema)]
+pub struct PendingOrder {
+    pub name: String,
+    pub count: u32,
+    pub unit_cost: Decimal,
+    pub store_in: String,
+    pub team: scheduler::Team,
+    pub reason: String,
+    pub vendor: String,
+    pub link: String,
+    #[serde(default)]
+    pub funding_source: funding::Source,
+    #[serde(default)]
+    pub component_id: Option<u32>,
+    /// The member asking for the order, if it isn't being placed for someone
+    /// else's benefit
+    #[serde(default)]
+    pub requester: Option<String>,
+    /// The currency `unit_cost` is in
+    #[serde(default)]
+    pub currency: order::Currency,
+    /// Dollars per unit of `currency`, if not the configured rate
+    #[serde(default)]
+    pub exchange_rate: Option<Decimal>,
+    /// Values for the custom fields admins have defined, by key
+    #[serde(default)]
+    pub fields: HashMap<String, serde_json::Value>,
+    /// The day the order has to be bought by
+    #[serde(default)]
+    pub needed_by: Option<NaiveDate>,
+    /// Places the order even if the same thing was ordered in the last 30
+    /// days
+    #[serde(default)]
+    pub allow_duplicate: bool,
+    /// What the vendor charges on top of the units, if it is already known,
+    /// eg. from a quote
+    #[serde(default)]
+    pub shipping_cost: Option<Decimal>,
+    #[serde(default)]
+    pub tax: Option<Decimal>,
+    #[serde(default)]
+    pub fees: Option<Decimal>,
+}
+
+impl PendingOrder {
+    /// Signed in members always request their own orders, while leads can
+    /// place them on someone else's behalf
+    fn request_as(&mut self, caller: &policy::Caller) {
+        let Some(name) = &caller.name else {
+            return;
+        };
+        self.requester = non_blank(self.requester.take());
+        if caller.role < policy::Role::Lead || self.requester.is_none() {
+            self.requester = Some(name.clone());
+        }
+    }
+
+    /// Settles the exchange rate the order will be converted into dollars at,
+    /// falling back to `fallback` if no rate is given or configured
+    fn capture_rate(
+        &mut self,
+        state: &UsrState,
+        fallback: Option<Decimal>,
+    ) -> Result<(), &'static str> {
+        if self.currency == order::Currency::Usd {
+            if self.exchange_rate.is_some() {
+                return Err("Orders in dollars don't take an exchange rate");
+            }
+            return Ok(());
+        }
+        let Some(rate) = self
+            .exchange_rate
+            .or_else(|| state.exchange_rates.get(&self.currency).copied())
+            .or(fallback)
+        else {
+            return Err("No exchange rate is configured for that currency");
+        };
+        if rate <= Decimal::ZERO {
+            return Err("Exchange rate must be positive");
+        }
+        self.exchange_rate = Some(rate);
+        Ok(())
+    }
+
+    /// The unit cost in dollars
+    fn subtotal(&self) -> Decimal {
+        money::subtotal(self.count.into(), self.dollar_unit_cost())
+    }
+
+    fn dollar_unit_cost(&self) -> Decimal {
+        match self.exchange_rate {
+            Some(rate) if self.currency != order::Currency::Usd => {
+                money::convert(self.unit_cost, rate)
+            }
+            _ => self.unit_cost,
+        }
+    }
+}
+
+/// The unit cost as shown in webhook messages, with the price the order was
+/// placed at if it wasn't in dollars
+fn unit_cost_text(order: &order::Model) -> String {
+    match (order.currency, order.original_unit_cost, order.exchange_rate) {
+        (Some(currency), Some(original), Some(rate)) => {
+            format!(
+                "{} ({original} {currency} at {rate})",
+                money::dollars(order.unit_cost)
+            )
+        }
+        _ => money::dollars(order.unit_cost),
+    }
+}
+
+/// The shipping, tax and fees recorded on `order` and the total they come
+/// to, as lines of a message, if any have been recorded
+fn charges_text(order: &order::Model) -> String {
+    if !order.has_charges() {
+        return String::new();
+    }
+    let mut text = String::new();
+    for (label, amount) in [
+        ("Shipping", order.shipping_cost),
+        ("Tax", order.tax),
+        ("Fees", order.fees),
+    ] {
+        if let Some(amount) = amount {
+            text.push_str(&format!("\n**{label}:** {}", money::dollars(amount)));
+        }
+    }
+    text.push_str(&format!("\n**Total:** {}", money::dollars(order.total())));
+    text
+}
+
+/// `hold` is why the order was put on hold as soon as it was placed, if it was,
+/// and `standing` is where its team stands against its budget afterwards
+fn new_order_webhook_msg(
+    order: &order::Model,
+    hold: Option<&str>,
+    warning: Option<&str>,
+    standing: Option<&budget::Standing>,
+) -> String {
+    format!(
+        "**New Order!**\n**Order:** {}\n**Name:** {}\n**Vendor:** {}\n**Link:** {}\n**Count:** {}\n**Unit Cost:** {}\n**Subtotal:** {}{}\n**Team:** {}\n**Funding:** {}\n**Reason:** {}{}{}{}{}{}",
+        order.number(),
+        order.name,
+        order.vendor,
+        order.link,
+        order.count,
+        unit_cost_text(order),
+        money::dollars(money::subtotal(order.count, order.unit_cost)),
+        charges_text(order),
+        order.team,
+        order.funding_source,
+        order.reason,
+        order
+            .needed_by
+            .map(|needed_by| format!("\n**Needed By:** {needed_by}"))
+            .unwrap_or_default(),
+        hold.map(|reason| format!("\n**On Hold:** {reason}"))
+            .unwrap_or_default(),
+        warning
+            .map(|warning| format!("\n**Warning:** {warning}"))
+            .unwrap_or_default(),
+        standing.map(budget::Standing::line).unwrap_or_default(),
+        permalink::line(order)
+    )
+}
+
+/// The message posted when an order moves to `status`. `submitted_at` is only
+/// used to predict an ETA when the order has been submitted.
+async fn order_update_webhook_msg(
+    db: &DatabaseConnection,
+    order: &order::Model,
+    status: order_status::Status,
+    submitted_at: NaiveDateTime,
+) -> String {
+    match status {
+        order_status::Status::InStorage => {
+            if order.store_in.is_empty() {
+                format!(
+                    "**Order Complete!**\n**Order:** {}\n**Name:** {}\n**Team:** {}",
+                    order.number(),
+                    order.name,
+                    order.team
+                )
+            } else {
+                format!(
+                    "**Order Complete!**\n**Order:** {}\n**Name:** {}\n**Team:** {}\n**Location:** {}",
+                    order.number(),
+                    order.name,
+                    order.team,
+                    order.store_in
+                )
+            }
+        }
+        order_status::Status::Returned => {
+            let rma = match returns::rma_lines(db, order).await {
+                Ok(rma) => rma,
+                Err(e) => {
+                    error!("Failed to find vendor's return details: {e}");
+                    String::new()
+                }
+            };
+            format!(
+                "**Order Returned**\n**Order:** {}\n**Name:** {}\n**Team:** {}\n**Vendor:** {}{rma}",
+                order.number(),
+                order.name,
+                order.team,
+                order.vendor
+            )
+        }
+        order_status::Status::Submitted => {
+            let eta = match vendor_lead_times(db).await {
+                Ok(lead_times) => {
+                    lead_times
+                        .get(&lead_time::vendor_key(&order.vendor))
+                        .map(|lead_time| {
+                            let mut eta =
+                                format!("\n**ETA:** {}", lead_time.predict(submitted_at).date());
+                            if lead_time.degraded {
+                                eta.push_str(" (vendor has been slower than usual)");
+                            }
+                            eta
+                        })
+                }
+                Err(e) => {
+                    error!("Failed to compute vendor lead times: {e}");
+                    None
+                }
+            };
+            format!(
+                "**Order Update!**\n**Order:** {}\n**Name:** {}\n**Team:** {}\n**Status:** {}{}",
+                order.number(),
+                order.name,
+                order.team,
+                status,
+                eta.unwrap_or_default()
+            )
+        }
+        _ => format!(
+            "**Order Update!**\n**Order:** {}\n**Name:** {}\n**Team:** {}\n**Status:** {}",
+            order.number(),
+            order.name,
+            order.team,
+            status
+        ),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DryRun {
+    /// Checks the request and reports what would happen without doing it
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// What an order mutation would have done. Returned instead of making the
+/// change when `?dry_run=true` is passed, once every check has passed.
+#[derive(Serialize, ToSchema)]
+struct DryRunReport {
+    order_id: Option<i64>,
+    /// The status the order would be left in
+    status: order_status::Status,
+    /// The message that would be posted, if any
+    webhook: Option<String>,
+}
+
+/// The next number in `season`, for display numbers like USR-2025-0042
+async fn next_season_number(tx: &DatabaseTransaction, season: u16) -> Result<i64, sea_orm::DbErr> {
+    let last: Option<Option<i64>> = order::Entity::find()
+        .select_only()
+        .column_as(order::Column::SeasonNumber.max(), "last")
+        .filter(order::Column::Season.eq(season))
+        .into_tuple()
+        .one(tx)
+        .await?;
+    Ok(last.flatten().unwrap_or_default() + 1)
+}
+
+/// Numbers every order that doesn't have a display number yet, in the
+/// season it was placed in and in the order they were placed.
+async fn assign_season_numbers(
+    db: &(impl ConnectionTrait + TransactionTrait),
+) -> Result<(), sea_orm::DbErr> {
+    db.transaction(|tx| {
+        Box::pin(async move {
+            let unnumbered = order::Entity::find()
+                .filter(order::Column::SeasonNumber.is_null())
+                .order_by_asc(order::Column::Id)
+                .all(tx)
+                .await?;
+            if unnumbered.is_empty() {
+                return Ok(());
+            }
+            let placed: HashMap<_, _> = order_status::Entity::find()
+                .filter(order_status::Column::Status.eq(order_status::Status::New))
+                .all(tx)
+                .await?
+                .into_iter()
+                .map(|model| (model.order_id, model.date))
+                .collect();
+            let mut unnumbered: Vec<_> = unnumbered
+                .into_iter()
+                .map(|model| {
+                    let date = placed
+                        .get(&model.id)
+                        .copied()
+                        .unwrap_or_else(|| Local::now().naive_local());
+                    (date, model.id)
+                })
+                .collect();
+            unnumbered.sort();
+
+            let mut last = HashMap::<u16, i64>::new();
+            for (date, id) in unnumbered {
+                let season = date.year() as u16;
+                let number = match last.entry(season) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(next_season_number(tx, season).await? - 1),
+                };
+                *number += 1;
+                order::Entity::update_many()
+                    .col_expr(order::Column::Season, Expr::value(season))
+                    .col_expr(order::Column::SeasonNumber, Expr::value(*number))
+                    .filter(order::Column::Id.eq(id))
+                    .exec(tx)
+                    .await?;
+            }
+            Result::<_, sea_orm::DbErr>::Ok(())
+        })
+    })
+    .await
+    .map_err(|e| match e {
+        sea_orm::TransactionError::Connection(e) => e,
+        sea_orm::TransactionError::Transaction(e) => e,
+    })
+}
+
+
+/// Refers to an order by its id or its display number, eg. USR-2025-0042
+#[derive(Deserialize, Clone, ToSchema)]
+#[serde(untagged)]
+pub enum OrderRef {
+    Id(u32),
+    Number(String),
+}
+
+impl OrderRef {
+    /// The id this refers to without looking anything up, if it is an id
+    pub fn as_id(&self) -> Option<i64> {
+        match self {
+            OrderRef::Id(id) => Some((*id).into()),
+            // Path and query parameters always arrive as strings
+            OrderRef::Number(number) => number.trim().parse::<u32>().ok().map(i64::from),
+        }
+    }
+
+    /// Finds the id of the order this refers to, if it exists
+    pub async fn resolve(&self, db: &impl ConnectionTrait) -> Result<Option<i64>, sea_orm::DbErr> {
+        if let Some(id) = self.as_id() {
+            return Ok(order::Entity::find_by_id(id)
+                .one(db)
+                .await?
+                .map(|model| model.id));
+        }
+        let OrderRef::Number(number) = self else {
+            return Ok(None);
+        };
+        let mut parts = number.trim().splitn(3, '-');
+        let (Some(prefix), Some(season), Some(season_number)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Ok(None);
+        };
+        let (Ok(season), Ok(season_number)) = (season.parse::<u16>(), season_number.parse::<u32>())
+        else {
+            return Ok(None);
+        };
+        if !prefix.eq_ignore_ascii_case("USR") {
+            return Ok(None);
+        }
+        Ok(order::Entity::find()
+            .filter(order::Column::Season.eq(season))
+            .filter(order::Column::SeasonNumber.eq(season_number))
+            .one(db)
+            .await?
+            .map(|model| model.id))
+    }
+}
+
+/// Resolves `order` to an id, or the response to send if there is no such order
+pub async fn resolve_order(
+    db: &DatabaseConnection,
+    order: &OrderRef,
+) -> Result<i64, (StatusCode, &'static str)> {
+    match order.resolve(db).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err((StatusCode::BAD_REQUEST, "Order not found")),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, ""))
+        }
+    }
+}
+
+/// The freeze on `team`'s new orders, if there is one. A freeze on the team
+/// itself takes precedence over one on every team.
+async fn spending_freeze(
+    db: &impl ConnectionTrait,
+    team: scheduler::Team,
+) -> Result<Option<freeze::Model>, sea_orm::DbErr> {
+    let freezes = freeze::Entity::find()
+        .filter(
+            Condition::any()
+                .add(freeze::Column::Team.eq(team))
+                .add(freeze::Column::Team.is_null()),
+        )
+        .all(db)
+        .await?;
+    Ok(freezes
+        .iter()
+        .find(|model| model.team.is_some())
+        .or(freezes.first())
+        .cloned())
+}
+
+/// The season orders placed now belong to
+fn current_season() -> u16 {
+    season::current()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/manifest/new/order",
+    tag = "manifest",
+    params(DryRun, ("Idempotency-Key" = Option<String>, Header, description = "A request made again with the same key, eg. a UUID, is answered with the first response instead of being handled again")),
+    request_body = PendingOrder,
+    responses(
+        (status = 200, description = "Placed, or with `dry_run` what would be placed", body = Option<DryRunReport>),
+        (status = 400, body = ApiError),
+        (status = 403, body = ApiError),
+        (status = 409, body = ApiError),
+        (status = 422, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn new_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Query(DryRun { dry_run }): Query<DryRun>,
+    Json(mut pending_order): Json<PendingOrder>,
+) -> Response {
+    pending_order.request_as(&caller);
+    if let Err(e) = check_duplicate(state, &pending_order, None).await {
+        return e.into_response();
+    }
+    let actor = audit::Actor::new(&caller, "/new/order");
+    if dry_run {
+        return match service::preview_order(state, pending_order, &caller, &actor).await {
+            Ok(placed) => Json(DryRunReport {
+                order_id: None,
+                status: if placed.hold.is_some() {
+                    order_status::Status::OnHold
+                } else {
+                    order_status::Status::New
+                },
+                webhook: state
+                    .notifier
+                    .routes(Topic::NewOrder)
+                    .then(|| placed.webhook_msg()),
+            })
+            .into_response(),
+            Err(e) => e.into_response(),
+        };
+    }
+    match service::place_order(state, pending_order, None, Some(caller), actor).await {
+        Ok(_) => (StatusCode::OK, "").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct NewOrders {
+    /// Shared by the orders, eg. the vendor's cart or quote number
+    #[serde(default)]
+    cart_id: Option<String>,
+}
+
+/// Turns away `pending_order` if it looks like an order placed in the last 30
+/// days, naming that order so that the requester can check it, unless the
+/// request allows duplicates. `item` is its place in a cart.
+async fn check_duplicate(
+    state: &UsrState,
+    pending_order: &PendingOrder,
+    item: Option<usize>,
+) -> Result<(), ApiError> {
+    if pending_order.allow_duplicate {
+        return Ok(());
+    }
+    let Some(model) = service::find_duplicate(&state.db, pending_order).await? else {
+        return Ok(());
+    };
+    let mut message = format!(
+        "{} ({}) was already ordered in the last 30 days. Set allow_duplicate to order it again.",
+        model.number(),
+        model.name
+    );
+    if let Some(item) = item {
+        message = format!("Item {item}: {message}");
+    }
+    Err(ApiError::conflict(message).with_details(serde_json::json!({
+        "order_id": model.id,
+        "number": model.number(),
+    })))
+}
+
+/// Places every order pasted from one cart at once. Either all of them are
+/// placed or, if any is turned away, none are. Responds with the orders.
+#[utoipa::path(
+    post,
+    path = "/api/manifest/new/orders",
+    tag = "manifest",
+    params(NewOrders),
+    request_body = Vec<PendingOrder>,
+    responses(
+        (status = 200, description = "The orders placed", body = Vec<order::Model>),
+        (status = 400, body = ApiError),
+        (status = 403, body = ApiError),
+        (status = 409, body = ApiError),
+        (status = 422, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn new_orders(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Query(NewOrders { cart_id }): Query<NewOrders>,
+    Json(mut pending_orders): Json<Vec<PendingOrder>>,
+) -> Response {
+    for pending_order in &mut pending_orders {
+        pending_order.request_as(&caller);
+    }
+    for (i, pending_order) in pending_orders.iter().enumerate() {
+        if let Err(e) = check_duplicate(state, pending_order, Some(i + 1)).await {
+            return e.into_response();
+        }
+    }
+    let actor = audit::Actor::new(&caller, "/new/orders");
+    match service::place_cart(state, pending_orders, cart_id, caller, actor).await {
+        Ok(orders) => Json(orders).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CloneOrder {
+    /// Overrides the original order's count
+    #[serde(default)]
+    count: Option<u32>,
+}
+
+/// Places a new order with the same fields as `model`, responding with it.
+/// `unit_cost` is in the currency `model` was placed in, which is converted
+/// at today's rate if one is configured.
+async fn place_copy(
+    state: &'static UsrState,
+    actor: audit::Actor,
+    caller: &policy::Caller,
+    model: order::Model,
+    count: Option<u32>,
+    unit_cost: Decimal,
+) -> Response {
+    if count == Some(0) {
+        return ApiError::invalid("Count must be positive")
+            .for_field("count")
+            .into_response();
+    }
+    let fields = match custom_field::values(&state.db, model.id).await {
+        Ok(fields) => fields,
+        Err(e) => {
+            error!("Failed to get custom field values: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let mut pending_order = PendingOrder {
+        name: model.name,
+        count: count.unwrap_or(model.count as u32),
+        unit_cost,
+        store_in: model.store_in,
+        team: model.team,
+        reason: model.reason,
+        vendor: model.vendor,
+        link: model.link,
+        funding_source: model.funding_source,
+        component_id: model.component_id.map(|id| id as u32),
+        requester: model.requester,
+        currency: model.currency.unwrap_or_default(),
+        exchange_rate: None,
+        fields,
+        needed_by: None,
+        allow_duplicate: true,
+        shipping_cost: None,
+        tax: None,
+        fees: None,
+    };
+    pending_order.request_as(caller);
+    match service::place_order(
+        state,
+        pending_order,
+        model.exchange_rate,
+        Some(caller.clone()),
+        actor,
+    )
+    .await
+    {
+        Ok(model) => Json(model).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Places a new order with the same fields as an existing one, for ordering
+/// another one of something. Responds with the new order.
+#[utoipa::path(
+    post,
+    path = "/api/manifest/clone/order/{id}",
+    tag = "manifest",
+    params(("id" = OrderRef, Path), CloneOrder),
+    responses(
+        (status = 200, description = "The new order", body = order::Model),
+        (status = 400, body = ApiError),
+        (status = 403, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn clone_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Path(id): Path<OrderRef>,
+    Query(CloneOrder { count }): Query<CloneOrder>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let model = match order::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(model)) => model,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let unit_cost = model.original_unit_cost.unwrap_or(model.unit_cost);
+    let actor = audit::Actor::new(&caller, "/clone/order");
+    place_copy(state, actor, &caller, model, count, unit_cost).await
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct Reorder {
+    /// Overrides the original order's count
+    #[serde(default)]
+    count: Option<u32>,
+    /// The unit cost the requester agreed to after seeing a price change
+    #[serde(default)]
+    unit_cost: Option<Decimal>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct PriceChange {
+    previous: Decimal,
+    current: Decimal,
+    difference: Decimal,
+}
+
+/// Orders more of something that has already been put in storage, at its
+/// current price. The price is looked up on the order's link, and if it has
+/// changed, nothing is ordered and the change is responded with `409` instead.
+/// Reordering again with `?unit_cost=` set to the new price confirms it.
+#[utoipa::path(
+    post,
+    path = "/api/manifest/reorder/order/{id}",
+    tag = "manifest",
+    params(("id" = OrderRef, Path), Reorder),
+    responses(
+        (status = 200, description = "The new order", body = order::Model),
+        (status = 409, description = "The price has changed, so nothing was ordered", body = PriceChange),
+        (status = 400, body = ApiError),
+        (status = 403, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn reorder(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Path(id): Path<OrderRef>,
+    Query(Reorder { count, unit_cost }): Query<Reorder>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let (model, status) = match current::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(current)) => current.into_parts(),
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if status != order_status::Status::InStorage {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Only orders that are in storage can be reordered",
+        )
+            .into_response();
+    }
+    let actor = audit::Actor::new(&caller, "/reorder/order");
+    if let Some(unit_cost) = unit_cost {
+        if let Err(msg) = money::validate_unit_cost(unit_cost) {
+            return ApiError::invalid(msg)
+                .for_field("unit_cost")
+                .into_response();
+        }
+        return place_copy(state, actor, &caller, model, count, unit_cost).await;
+    }
+
+    // Links that don't advertise a price are reordered at the old one. Stores
+    // advertise in the currency the order was placed in.
+    let previous = model.original_unit_cost.unwrap_or(model.unit_cost);
+    match price::lookup(&model.link).await {
+        Some(current) if current != previous => (
+            StatusCode::CONFLICT,
+            Json(PriceChange {
+                previous,
+                current,
+                difference: current - previous,
+            }),
+        )
+            .into_response(),
+        _ => place_copy(state, actor, &caller, model, count, previous).await,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ChangeOrder {
+    pub id: OrderRef,
+    pub name: String,
+    pub count: u32,
+    pub unit_cost: Decimal,
+    pub store_in: String,
+    pub team: scheduler::Team,
+    pub reason: String,
+    pub vendor: String,
+    pub link: String,
+    #[serde(default)]
+    pub funding_source: funding::Source,
+    #[serde(default)]
+    pub component_id: Option<u32>,
+    /// The version of the order the change was made to. If it has changed
+    /// since, the change is turned away instead of overwriting the newer one.
+    #[serde(default)]
+    pub version: Option<u32>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/manifest/change/order",
+    tag = "manifest",
+    params(DryRun, ("Idempotency-Key" = Option<String>, Header, description = "A request made again with the same key, eg. a UUID, is answered with the first response instead of being handled again")),
+    request_body = ChangeOrder,
+    responses(
+        (status = 200, description = "Changed, or with `dry_run` what would be changed", body = Option<DryRunReport>),
+        (status = 400, body = ApiError),
+        (status = 403, body = ApiError),
+        (status = 409, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn change_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Query(DryRun { dry_run }): Query<DryRun>,
+    Json(mut change_order): Json<ChangeOrder>,
+) -> Response {
+    if change_order.count == 0 {
+        return ApiError::invalid("Count must be positive")
+            .for_field("count")
+            .into_response();
+    }
+    if let Err(msg) = money::validate_unit_cost(change_order.unit_cost) {
+        return ApiError::invalid(msg)
+            .for_field("unit_cost")
+            .into_response();
+    }
+    if let Err(msg) = service::check_link(&change_order.link) {
+        return ApiError::invalid(msg).for_field("link").into_response();
+    }
+    let id = match resolve_order(&state.db, &change_order.id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let (model, status) = match current::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(current)) => current.into_parts(),
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if let Err(response) = policy::check_owner(&caller, &model) {
+        return response.into_response();
+    }
+    if let Err(response) = policy::check_version(change_order.version, &model) {
+        return response.into_response();
+    }
+    // Every field is sent, so only the ones that differ are being changed
+    let changed = [
+        ("name", model.name != change_order.name),
+        ("count", model.count != i64::from(change_order.count)),
+        ("unit_cost", model.unit_cost != change_order.unit_cost),
+        ("store_in", model.store_in != change_order.store_in),
+        ("team", model.team != change_order.team),
+        ("reason", model.reason != change_order.reason),
+        ("vendor", model.vendor != change_order.vendor),
+        ("link", model.link != change_order.link),
+        ("funding_source", model.funding_source != change_order.funding_source),
+        ("component_id", model.component_id != change_order.component_id.map(i64::from)),
+    ];
+    if let Err(response) = changed
+        .iter()
+        .filter(|(_, changed)| *changed)
+        .try_for_each(|(field, _)| policy::check_field(caller.role, field, status))
+    {
+        return response.into_response();
+    }
+    if vendor::key(&model.vendor) != vendor::key(&change_order.vendor) {
+        if let Err(e) = service::check_vendor(&state.db, &change_order.vendor).await {
+            return e.into_response();
+        }
+    }
+    if model.store_in != change_order.store_in {
+        change_order.store_in = match location::check(&state.db, &change_order.store_in).await {
+            Ok(store_in) => store_in,
+            Err(e) => return e.into_response(),
+        };
+    }
+    let subtotal = money::subtotal(change_order.count.into(), change_order.unit_cost);
+    // Only what the change adds to a team's spending can take it over budget
+    let adds_spending = change_order.team != model.team
+        || subtotal > money::subtotal(model.count, model.unit_cost);
+    let season = model
+        .season
+        .map(|season| season as u16)
+        .unwrap_or_else(current_season);
+    if adds_spending {
+        if let Err(e) =
+            service::check_budget(state, change_order.team, season, Some(id), subtotal).await
+        {
+            return e.into_response();
+        }
+    }
+    let standing = match budget::standing(&state.db, change_order.team, season, Some(id)).await {
+        Ok(standing) => standing.map(|mut standing| {
+            standing.committed += subtotal;
+            standing.remaining -= subtotal;
+            standing
+        }),
+        Err(e) => {
+            error!("Failed to find season budget: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let number = model.number();
+    let link = permalink::line(&model);
+    if let Some(component_id) = change_order.component_id {
+        match registry::component_exists(&state.db, component_id).await {
+            Ok(true) => {}
+            Ok(false) => return (StatusCode::BAD_REQUEST, "Component not found").into_response(),
+            Err(e) => {
+                error!("Failed to find component: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+            }
+        }
+    }
+    let webhook_msg = format!(
+        "***Order Changed***\n**Order:** {}\n**Name:** {}\n**Vendor:** {}\n**Link:** {}\n**Count:** {}\n**Unit Cost:** {}\n**Subtotal:** {}{}{}\n**Team:** {}\n**Funding:** {}\n**Reason:** {}{}{}",
+        number,
+        change_order.name,
+        change_order.vendor,
+        change_order.link,
+        change_order.count,
+        money::dollars(change_order.unit_cost),
+        money::dollars(subtotal),
+        revision::line(revision::subtotal_change(
+            &model,
+            change_order.count.into(),
+            change_order.unit_cost
+        )),
+        charges_text(&order::Model {
+            count: change_order.count.into(),
+            unit_cost: change_order.unit_cost,
+            ..model.clone()
+        }),
+        change_order.team,
+        change_order.funding_source,
+        change_order.reason,
+        standing.as_ref().map(budget::Standing::line).unwrap_or_default(),
+        link
+    );
+    if dry_run {
+        return Json(DryRunReport {
+            order_id: Some(id),
+            status,
+            webhook: state.notifier.routes(Topic::NewOrder).then_some(webhook_msg),
+        })
+        .into_response();
+    }
+    let team = change_order.team;
+    let active_model = order::ActiveModel {
+        id: ActiveValue::Unchanged(id),
+        name: ActiveValue::Set(change_order.name),
+        count: ActiveValue::Set(change_order.count.into()),
+        unit_cost: ActiveValue::Set(change_order.unit_cost),
+        store_in: ActiveValue::Set(change_order.store_in),
+        team: ActiveValue::Set(team),
+        reason: ActiveValue::Set(change_order.reason),
+        vendor: ActiveValue::Set(change_order.vendor),
+        link: ActiveValue::Set(change_order.link),
+        funding_source: ActiveValue::Set(change_order.funding_source),
+        component_id: ActiveValue::Set(change_order.component_id.map(i64::from)),
+        ref_number: ActiveValue::NotSet,
+        season: ActiveValue::NotSet,
+        season_number: ActiveValue::NotSet,
+        tax_exempt: ActiveValue::NotSet,
+        payment_method: ActiveValue::NotSet,
+        requester: ActiveValue::NotSet,
+        currency: ActiveValue::NotSet,
+        original_unit_cost: ActiveValue::NotSet,
+        exchange_rate: ActiveValue::NotSet,
+        cart_id: ActiveValue::NotSet,
+        needed_by: ActiveValue::NotSet,
+        shipping_cost: ActiveValue::NotSet,
+        tax: ActiveValue::NotSet,
+        fees: ActiveValue::NotSet,
+        version: ActiveValue::NotSet,
+        competition: ActiveValue::NotSet,
+        received_count: ActiveValue::NotSet,
+    };
+    let actor = audit::Actor::new(&caller, "/change/order");
+    let stale = policy::stale_version(&model);
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let Some(after) = order::update_versioned(tx, model.version, active_model).await?
+                else {
+                    return Ok(false);
+                };
+                vendor::ensure(tx, &after.vendor).await?;
+                let audit_id = actor
+                    .record(tx, id, audit::diff(Some(&model), Some(&after)))
+                    .await?;
+                revision::record(tx, &model, &after, audit_id).await?;
+                Result::<_, sea_orm::DbErr>::Ok(true)
+            })
+        })
+        .await;
+    match result {
+        Ok(true) => {
+            clear_approval(&state.db, id).await;
+            backup_db(state);
+            // The order may have moved between teams
+            orders_changed(state, None).await;
+            publish_current(state, events::EventKind::Changed, id).await;
+            state
+                .notifier
+                .send_for(team, Topic::NewOrder, id as u32, webhook_msg);
+            (StatusCode::OK, "").into_response()
+        }
+        // Someone else changed it after it was checked
+        Ok(false) => (StatusCode::CONFLICT, stale).into_response(),
+        Err(e) => {
+            error!("Failed to change order: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Tells a missing field apart from an explicit `null`
+fn explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// The fields of an order to change. Missing fields are left as they are.
+#[derive(Deserialize)]
+struct PatchOrder {
+    name: Option<String>,
+    count: Option<u32>,
+    unit_cost: Option<Decimal>,
+    store_in: Option<String>,
+    team: Option<scheduler::Team>,
+    reason: Option<String>,
+    vendor: Option<String>,
+    link: Option<String>,
+    funding_source: Option<funding::Source>,
+    #[serde(default, deserialize_with = "explicit_null")]
+    component_id: Option<Option<u32>>,
+    tax_exempt: Option<bool>,
+    payment_method: Option<order::PaymentMethod>,
+    #[serde(default, deserialize_with = "explicit_null")]
+    needed_by: Option<Option<NaiveDate>>,
+    /// Custom fields to set, with `null` removing a field's value
+    #[serde(default)]
+    fields: HashMap<String, serde_json::Value>,
+    /// Like [`ChangeOrder::version`]
+    #[serde(default)]
+    version: Option<u32>,
+}
+
+impl PatchOrder {
+    /// The names of the fields being changed, in the order they are listed
+    fn fields(&self) -> Vec<&'static str> {
+        [
+            ("name", self.name.is_some()),
+            ("count", self.count.is_some()),
+            ("unit_cost", self.unit_cost.is_some()),
+            ("store_in", self.store_in.is_some()),
+            ("team", self.team.is_some()),
+            ("reason", self.reason.is_some()),
+            ("vendor", self.vendor.is_some()),
+            ("link", self.link.is_some()),
+            ("funding_source", self.funding_source.is_some()),
+            ("component_id", self.component_id.is_some()),
+            ("tax_exempt", self.tax_exempt.is_some()),
+            ("payment_method", self.payment_method.is_some()),
+            ("needed_by", self.needed_by.is_some()),
+            ("fields", !self.fields.is_empty()),
+        ]
+        .into_iter()
+        .filter_map(|(field, present)| present.then_some(field))
+        .collect()
+    }
+}
+
+/// Changes only the given fields of an order, leaving the rest untouched.
+#[axum::debug_handler]
+async fn patch_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Path(id): Path<OrderRef>,
+    Query(DryRun { dry_run }): Query<DryRun>,
+    Json(mut patch): Json<PatchOrder>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let (model, status) = match current::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(current)) => current.into_parts(),
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if let Err(response) = policy::check_owner(&caller, &model) {
+        return response.into_response();
+    }
+    if let Err(response) = policy::check_version(patch.version, &model) {
+        return response.into_response();
+    }
+    let fields = patch.fields();
+    if fields.is_empty() {
+        return (StatusCode::BAD_REQUEST, "No fields to change").into_response();
+    }
+    if let Err(response) = fields
+        .iter()
+        .try_for_each(|field| policy::check_field(caller.role, field, status))
+    {
+        return response.into_response();
+    }
+    if patch.count == Some(0) {
+        return ApiError::invalid("Count must be positive")
+            .for_field("count")
+            .into_response();
+    }
+    if let Some(Err(msg)) = patch.unit_cost.map(money::validate_unit_cost) {
+        return ApiError::invalid(msg)
+            .for_field("unit_cost")
+            .into_response();
+    }
+    if let Some(Err(msg)) = patch.link.as_deref().map(service::check_link) {
+        return ApiError::invalid(msg).for_field("link").into_response();
+    }
+    if let Some(Err(msg)) = patch.needed_by.flatten().map(service::check_needed_by) {
+        return ApiError::invalid(msg)
+            .for_field("needed_by")
+            .into_response();
+    }
+    if let Some(vendor) = &patch.vendor {
+        if let Err(e) = service::check_vendor(&state.db, vendor).await {
+            return e.into_response();
+        }
+    }
+    if let Some(store_in) = patch.store_in.take() {
+        patch.store_in = match location::check(&state.db, &store_in).await {
+            Ok(store_in) => Some(store_in),
+            Err(e) => return e.into_response(),
+        };
+    }
+    let custom_fields = match custom_field::check(&state.db, &patch.fields, false).await {
+        Ok(custom_fields) => custom_fields,
+        Err(e) => return e.into_response(),
+    };
+    if let Some(Some(component_id)) = patch.component_id {
+        match registry::component_exists(&state.db, component_id).await {
+            Ok(true) => {}
+            Ok(false) => return (StatusCode::BAD_REQUEST, "Component not found").into_response(),
+            Err(e) => {
+                error!("Failed to find component: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+            }
+        }
+    }
+
+    let old_team = model.team;
+    let link = permalink::line(&model);
+    let mut webhook_msg = format!("***Order Changed***\n**Order:** {}", model.number());
+    let mut active_model: order::ActiveModel = model.clone().into();
+    let mut changed = |label: &str, value: &dyn std::fmt::Display| {
+        webhook_msg.push_str(&format!("\n**{label}:** {value}"));
+    };
+    if let Some(name) = patch.name {
+        changed("Name", &name);
+        active_model.name = ActiveValue::Set(name);
+    }
+    if let Some(vendor) = patch.vendor {
+        changed("Vendor", &vendor);
+        active_model.vendor = ActiveValue::Set(vendor);
+    }
+    if let Some(link) = patch.link {
+        changed("Link", &link);
+        active_model.link = ActiveValue::Set(link);
+    }
+    if let Some(count) = patch.count {
+        changed("Count", &count);
+        active_model.count = ActiveValue::Set(count.into());
+    }
+    if let Some(unit_cost) = patch.unit_cost {
+        changed("Unit Cost", &money::dollars(unit_cost));
+        active_model.unit_cost = ActiveValue::Set(unit_cost);
+    }
+    if let Some(store_in) = patch.store_in {
+        changed("Store In", &store_in);
+        active_model.store_in = ActiveValue::Set(store_in);
+    }
+    if let Some(team) = patch.team {
+        changed("Team", &team);
+        active_model.team = ActiveValue::Set(team);
+    }
+    if let Some(funding_source) = patch.funding_source {
+        changed("Funding", &funding_source);
+        active_model.funding_source = ActiveValue::Set(funding_source);
+    }
+    if let Some(reason) = patch.reason {
+        changed("Reason", &reason);
+        active_model.reason = ActiveValue::Set(reason);
+    }
+    if let Some(component_id) = patch.component_id {
+        match component_id {
+            Some(component_id) => changed("Component", &component_id),
+            None => changed("Component", &"None"),
+        }
+        active_model.component_id = ActiveValue::Set(component_id.map(i64::from));
+    }
+    if let Some(tax_exempt) = patch.tax_exempt {
+        changed("Tax Exempt", &if tax_exempt { "Yes" } else { "No" });
+        active_model.tax_exempt = ActiveValue::Set(Some(tax_exempt));
+    }
+    if let Some(method) = patch.payment_method {
+        changed("Payment Method", &method);
+        active_model.payment_method = ActiveValue::Set(Some(method));
+    }
+    let new_deadline = patch.needed_by.is_some();
+    if let Some(needed_by) = patch.needed_by {
+        match needed_by {
+            Some(needed_by) => changed("Needed By", &needed_by),
+            None => changed("Needed By", &"None"),
+        }
+        active_model.needed_by = ActiveValue::Set(needed_by);
+    }
+    for (key, value) in &custom_fields {
+        match value {
+            Some(value) => changed(key, value),
+            None => changed(key, &"None"),
+        }
+    }
+    webhook_msg.push_str(&revision::line(revision::subtotal_change(
+        &model,
+        patch.count.map_or(model.count, i64::from),
+        patch.unit_cost.unwrap_or(model.unit_cost),
+    )));
+    webhook_msg.push_str(&link);
+
+    if dry_run {
+        return Json(DryRunReport {
+            order_id: Some(id),
+            status,
+            webhook: state.notifier.routes(Topic::NewOrder).then_some(webhook_msg),
+        })
+        .into_response();
+    }
+    let actor = audit::Actor::new(&caller, "/order/{id}");
+    let stale = policy::stale_version(&model);
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let Some(after) = order::update_versioned(tx, model.version, active_model).await?
+                else {
+                    return Ok(None);
+                };
+                vendor::ensure(tx, &after.vendor).await?;
+                if new_deadline {
+                    deadline::reset(tx, id).await?;
+                }
+                let mut diff = audit::diff(Some(&model), Some(&after));
+                diff.extend(custom_field::store(tx, id, custom_fields).await?);
+                let audit_id = actor.record(tx, id, diff).await?;
+                revision::record(tx, &model, &after, audit_id).await?;
+                Result::<_, sea_orm::DbErr>::Ok(Some(after))
+            })
+        })
+        .await;
+    match result {
+        Ok(Some(m)) => {
+            clear_approval(&state.db, id).await;
+            backup_db(state);
+            if m.team == old_team {
+                orders_changed(state, Some(m.team)).await;
+            } else {
+                orders_changed(state, None).await;
+            }
+            publish_current(state, events::EventKind::Changed, id).await;
+            state
+                .notifier
+                .send_for(m.team, Topic::NewOrder, id as u32, webhook_msg);
+            Json(m).into_response()
+        }
+        // Someone else changed it after it was checked
+        Ok(None) => (StatusCode::CONFLICT, stale).into_response(),
+        Err(e) => {
+            error!("Failed to change order: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct DeleteOrder {
+    id: OrderRef,
+    #[serde(default)]
+    force: bool,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/manifest/del/order",
+    tag = "manifest",
+    params(DryRun),
+    request_body = DeleteOrder,
+    responses(
+        (status = 200, description = "Cancelled, or with `dry_run` what would be cancelled", body = Option<DryRunReport>),
+        (status = 400, body = ApiError),
+        (status = 403, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn cancel_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Query(DryRun { dry_run }): Query<DryRun>,
+    Json(DeleteOrder { id, force }): Json<DeleteOrder>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let checked = match service::check_cancel(state, &caller, id, force).await {
+        Ok(checked) => checked,
+        Err(e) => return e.into_response(),
+    };
+    if dry_run {
+        return Json(DryRunReport {
+            order_id: Some(id),
+            status: order_status::Status::Cancelled,
+            webhook: state
+                .notifier
+                .routes(Topic::NewOrder)
+                .then_some(checked.message),
+        })
+        .into_response();
+    }
+    let actor = audit::Actor::new(&caller, "/del/order");
+    match service::cancel(state, checked, actor).await {
+        Ok(()) => (StatusCode::OK, "").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateOrder {
+    pub id: OrderRef,
+    pub status: order_status::Status,
+    /// The order's purchasing reference. Left out when the order is submitted
+    /// without one, the next number is claimed for it.
+    pub ref_number: Option<u32>,
+    /// Usually recorded when the order is submitted
+    #[serde(default)]
+    pub tax_exempt: Option<bool>,
+    /// Only accepted when the order is being submitted
+    #[serde(default)]
+    pub payment_method: Option<order::PaymentMethod>,
+    /// Required when putting the order on hold, and only accepted then
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// What was wrong with the delivery, only accepted when the order is
+    /// marked delivered
+    #[serde(default)]
+    pub discrepancies: Vec<NewDiscrepancy>,
+    /// Only accepted when the order is marked shipped. The order joins the
+    /// shipment with this tracking number, which is watched for delivery.
+    #[serde(default)]
+    pub tracking: Option<String>,
+    /// Who is carrying the shipment, eg. UPS, given with `tracking`
+    #[serde(default)]
+    pub carrier: Option<String>,
+    /// What the vendor charged on top of the units. Only accepted when the
+    /// order is marked submitted, including once it already is, as the
+    /// invoice comes in.
+    #[serde(default)]
+    pub shipping_cost: Option<Decimal>,
+    #[serde(default)]
+    pub tax: Option<Decimal>,
+    #[serde(default)]
+    pub fees: Option<Decimal>,
+    /// Like [`ChangeOrder::version`]
+    #[serde(default)]
+    pub version: Option<u32>,
+    /// Moves the order even if `status_transitions` doesn't allow it, eg. to
+    /// fix a status entered by mistake. Only admins can. Moves that would
+    /// throw off the inventory, like storing an order twice, are still
+    /// turned away.
+    #[serde(default)]
+    pub force: bool,
+    /// How many more units arrived, for orders that arrive in more than one
+    /// delivery. Only accepted when the order is marked PartiallyReceived,
+    /// which needs it, or Delivered.
+    #[serde(default)]
+    pub received: Option<u32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewDiscrepancy {
+    pub kind: discrepancy::Kind,
+    /// How many arrived, if not all of them
+    #[serde(default)]
+    pub received: Option<u32>,
+    #[serde(default)]
+    pub note: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/manifest/update/order",
+    tag = "manifest",
+    params(DryRun, ("Idempotency-Key" = Option<String>, Header, description = "A request made again with the same key, eg. a UUID, is answered with the first response instead of being handled again")),
+    request_body = UpdateOrder,
+    responses(
+        (status = 200, description = "Updated, or with `dry_run` what would be updated", body = Option<DryRunReport>),
+        (status = 400, description = "Turned away. Moves the status transitions don't allow have the statuses that are in `details.allowed`.", body = ApiError),
+        (status = 403, body = ApiError),
+        (status = 409, body = ApiError),
+        (status = 422, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn update_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Query(DryRun { dry_run }): Query<DryRun>,
+    Json(update_order): Json<UpdateOrder>,
+) -> Response {
+    let id = match resolve_order(&state.db, &update_order.id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let actor = audit::Actor::new(&caller, "/update/order");
+    apply_order_update(state, &caller, id, update_order, dry_run, actor).await
+}
+
+/// Checks and applies `update` to order `id`, or reports what it would do
+async fn apply_order_update(
+    state: &'static UsrState,
+    caller: &policy::Caller,
+    id: i64,
+    update: UpdateOrder,
+    dry_run: bool,
+    actor: audit::Actor,
+) -> Response {
+    let checked = match service::check_update(state, caller.role, id, &update).await {
+        Ok(checked) => checked,
+        Err(e) => return e.into_response(),
+    };
+    if dry_run {
+        return Json(DryRunReport {
+            order_id: Some(id),
+            status: update.status,
+            webhook: (!checked.same_status && state.notifier.routes(Topic::OrderUpdate))
+                .then_some(checked.message),
+        })
+        .into_response();
+    }
+    match service::update_status(state, &update, checked, actor).await {
+        Ok(()) => (StatusCode::OK, "").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ReceiveOrder {
+    id: OrderRef,
+    /// How many units arrived in this delivery
+    count: u32,
+    #[serde(default)]
+    version: Option<u32>,
+}
+
+/// Records that some of an order's units arrived. The order is kept
+/// PartiallyReceived until all of them have, and then marked Delivered.
+#[utoipa::path(
+    post,
+    path = "/api/manifest/receive/order",
+    tag = "manifest",
+    params(DryRun),
+    request_body = ReceiveOrder,
+    responses(
+        (status = 200, description = "Received, or with `dry_run` what would be received", body = Option<DryRunReport>),
+        (status = 400, body = ApiError),
+        (status = 403, body = ApiError),
+        (status = 409, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn receive_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Query(DryRun { dry_run }): Query<DryRun>,
+    Json(ReceiveOrder { id, count, version }): Json<ReceiveOrder>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let model = match order::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(model)) => model,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let received = model.received_count.unwrap_or_default() + i64::from(count);
+    let status = if received >= model.count {
+        order_status::Status::Delivered
+    } else {
+        order_status::Status::PartiallyReceived
+    };
+    let update = UpdateOrder {
+        id: OrderRef::Id(id as u32),
+        status,
+        ref_number: None,
+        tax_exempt: None,
+        payment_method: None,
+        reason: None,
+        discrepancies: vec![],
+        tracking: None,
+        carrier: None,
+        shipping_cost: None,
+        tax: None,
+        fees: None,
+        version,
+        force: false,
+        received: Some(count),
+    };
+    let actor = audit::Actor::new(&caller, "/receive/order");
+    apply_order_update(state, &caller, id, update, dry_run, actor).await
+}
+
+#[derive(Deserialize)]
+struct NewShipment {
+    tracking: String,
+    #[serde(default)]
+    carrier: Option<String>,
+    orders: Vec<OrderRef>,
+}
+
+/// Groups orders that arrive in one box. Orders given a tracking number that
+/// is already known are added to that shipment.
+#[axum::debug_handler]
+async fn new_shipment(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(NewShipment {
+        tracking,
+        carrier,
+        orders,
+    }): Json<NewShipment>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot group orders into shipments", caller.role),
+        )
+            .into_response();
+    }
+    let tracking = tracking.trim().to_string();
+    if tracking.is_empty() {
+        return (StatusCode::BAD_REQUEST, "A tracking number is required").into_response();
+    }
+    if orders.is_empty() {
+        return (StatusCode::BAD_REQUEST, "A shipment needs orders").into_response();
+    }
+    let existing = match shipment::Entity::find()
+        .filter(shipment::Column::Tracking.eq(&tracking))
+        .one(&state.db)
+        .await
+    {
+        Ok(existing) => existing,
+        Err(e) => {
+            error!("Failed to find shipment: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let mut ids = vec![];
+    for order in &orders {
+        let id = match resolve_order(&state.db, order).await {
+            Ok(id) => id,
+            Err(response) => return response.into_response(),
+        };
+        let current = match current::Entity::find_by_id(id).one(&state.db).await {
+            Ok(Some(current)) => current,
+            Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+            Err(e) => {
+                error!("Failed to find order: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+            }
+        };
+        let (model, status) = current.into_parts();
+        if !matches!(
+            status,
+            order_status::Status::Submitted
+                | order_status::Status::Shipped
+                | order_status::Status::PartiallyReceived
+        ) {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("{}: only orders on their way can be shipped, not {status} ones", model.number()),
+            )
+                .into_response();
+        }
+        match shipment_order::Entity::find_by_id(id).one(&state.db).await {
+            Ok(Some(member))
+                if existing.as_ref().is_none_or(|shipment| shipment.id != member.shipment_id) =>
+            {
+                return (
+                    StatusCode::CONFLICT,
+                    format!("{} is already in another shipment", model.number()),
+                )
+                    .into_response();
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => ids.push(id),
+            Err(e) => {
+                error!("Failed to find order's shipment: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+            }
+        }
+    }
+
+    let actor = audit::Actor::new(&caller, "/new/shipment");
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let shipment =
+                    service::join_shipment(tx, &tracking, carrier, actor.name()).await?;
+                for id in ids {
+                    shipment_order::ActiveModel {
+                        order_id: ActiveValue::Set(id),
+                        shipment_id: ActiveValue::Set(shipment.id),
+                    }
+                    .insert(tx)
+                    .await?;
+                    actor
+                        .record(tx, id, audit::change("shipment", (), &shipment.tracking))
+                        .await?;
+                }
+                Result::<_, sea_orm::DbErr>::Ok(shipment)
+            })
+        })
+        .await;
+    match result {
+        Ok(shipment) => {
+            backup_db(state);
+            Json(shipment).into_response()
+        }
+        Err(e) => {
+            error!("Failed to add shipment: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UpdateShipment {
+    id: u32,
+    status: order_status::Status,
+}
+
+/// Moves every order in the shipment to `status` at once, eg. when the box
+/// is delivered, and posts one message for all of them
+#[axum::debug_handler]
+async fn update_shipment(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(UpdateShipment { id, status }): Json<UpdateShipment>,
+) -> Response {
+    let shipment = match shipment::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(shipment)) => shipment,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Shipment not found").into_response(),
+        Err(e) => {
+            error!("Failed to find shipment: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let checked = match service::check_shipment(state, caller.role, shipment, status).await {
+        Ok(checked) => checked,
+        Err(e) => return e.into_response(),
+    };
+    let actor = audit::Actor::new(&caller, "/update/shipment");
+    match service::update_shipment(state, checked, actor).await {
+        Ok(()) => (StatusCode::OK, "").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteShipment {
+    id: u32,
+}
+
+/// Ungroups the shipment's orders, leaving the orders themselves as they are
+#[axum::debug_handler]
+async fn del_shipment(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(DeleteShipment { id }): Json<DeleteShipment>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot ungroup shipments", caller.role),
+        )
+            .into_response();
+    }
+    let shipment = match shipment::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(shipment)) => shipment,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Shipment not found").into_response(),
+        Err(e) => {
+            error!("Failed to find shipment: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let actor = audit::Actor::new(&caller, "/del/shipment");
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let members = shipment_order::Entity::find()
+                    .filter(shipment_order::Column::ShipmentId.eq(id))
+                    .all(tx)
+                    .await?;
+                for member in members {
+                    actor
+                        .record(tx, member.order_id, audit::change("shipment", &shipment.tracking, ()))
+                        .await?;
+                }
+                shipment_order::Entity::delete_many()
+                    .filter(shipment_order::Column::ShipmentId.eq(id))
+                    .exec(tx)
+                    .await?;
+                shipment::Entity::delete_by_id(id).exec(tx).await?;
+                Result::<_, sea_orm::DbErr>::Ok(())
+            })
+        })
+        .await;
+    match result {
+        Ok(()) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete shipment: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListShipments {
+    /// Only the ones whose orders haven't all been put in storage
+    #[serde(default)]
+    open: bool,
+}
+
+#[derive(Serialize)]
+struct ShipmentEntry {
+    #[serde(flatten)]
+    shipment: shipment::Model,
+    /// The numbers of the orders in it
+    orders: Vec<String>,
+}
+
+#[axum::debug_handler]
+async fn get_shipments(
+    State(state): State<&'static UsrState>,
+    Query(ListShipments { open }): Query<ListShipments>,
+) -> Response {
+    let mut query = shipment::Entity::find().order_by_desc(shipment::Column::Id);
+    if open {
+        query = query.filter(shipment::Column::Status.ne(order_status::Status::InStorage));
+    }
+    let (shipments, members, orders) = tokio::join!(
+        query.all(&state.db),
+        shipment_order::Entity::find().all(&state.db),
+        order::Entity::find()
+            .filter(
+                order::Column::Id.in_subquery(
+                    sea_orm::sea_query::Query::select()
+                        .column(shipment_order::Column::OrderId)
+                        .from(shipment_order::Entity)
+                        .to_owned(),
+                ),
+            )
+            .all(&state.db),
+    );
+    let (shipments, members, orders) = match (shipments, members, orders) {
+        (Ok(shipments), Ok(members), Ok(orders)) => (shipments, members, orders),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            error!("Failed to get shipments: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let numbers: HashMap<_, _> = orders
+        .into_iter()
+        .map(|order| (order.id, order.number()))
+        .collect();
+    let mut by_shipment = HashMap::<i64, Vec<String>>::new();
+    for member in members {
+        if let Some(number) = numbers.get(&member.order_id) {
+            by_shipment
+                .entry(member.shipment_id)
+                .or_default()
+                .push(number.clone());
+        }
+    }
+    let entries: Vec<_> = shipments
+        .into_iter()
+        .map(|shipment| ShipmentEntry {
+            orders: by_shipment.remove(&shipment.id).unwrap_or_default(),
+            shipment,
+        })
+        .collect();
+    Json(entries).into_response()
+}
+
+#[derive(Deserialize)]
+struct ListDiscrepancies {
+    /// Only the ones no one has resolved yet, which are the follow-up tasks
+    #[serde(default)]
+    open: bool,
+    #[serde(default)]
+    order_id: Option<OrderRef>,
+}
+
+#[axum::debug_handler]
+async fn get_discrepancies(
+    State(state): State<&'static UsrState>,
+    Query(ListDiscrepancies { open, order_id }): Query<ListDiscrepancies>,
+) -> Response {
+    let mut query = discrepancy::Entity::find().order_by_asc(discrepancy::Column::Id);
+    if open {
+        query = query.filter(discrepancy::Column::Resolved.is_null());
+    }
+    if let Some(order_id) = order_id {
+        match resolve_order(&state.db, &order_id).await {
+            Ok(id) => query = query.filter(discrepancy::Column::OrderId.eq(id)),
+            Err(response) => return response.into_response(),
+        }
+    }
+    match query.all(&state.db).await {
+        Ok(discrepancies) => Json(discrepancies).into_response(),
+        Err(e) => {
+            error!("Failed to get discrepancies: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ResolveDiscrepancy {
+    id: u32,
+    /// What was done about it, such as a replacement or refund
+    resolution: String,
+}
+
+#[axum::debug_handler]
+async fn resolve_discrepancy(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(ResolveDiscrepancy { id, resolution }): Json<ResolveDiscrepancy>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot resolve discrepancies", caller.role),
+        )
+            .into_response();
+    }
+    let resolution = resolution.trim().to_string();
+    if resolution.is_empty() {
+        return (StatusCode::BAD_REQUEST, "A resolution is required").into_response();
+    }
+    match discrepancy::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(model)) if model.resolved.is_some() => {
+            return (StatusCode::BAD_REQUEST, "Discrepancy is already resolved").into_response();
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Discrepancy not found").into_response(),
+        Err(e) => {
+            error!("Failed to find discrepancy: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    }
+    let result = discrepancy::ActiveModel {
+        id: ActiveValue::Unchanged(id.into()),
+        order_id: ActiveValue::NotSet,
+        kind: ActiveValue::NotSet,
+        expected: ActiveValue::NotSet,
+        received: ActiveValue::NotSet,
+        note: ActiveValue::NotSet,
+        reported: ActiveValue::NotSet,
+        resolved: ActiveValue::Set(Some(Local::now().naive_local())),
+        resolution: ActiveValue::Set(Some(resolution)),
+    }
+    .update(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to resolve discrepancy: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Serialize, Default)]
+struct VendorQuality {
+    vendor: String,
+    /// Orders from the vendor that have been delivered
+    delivered: u32,
+    /// Delivered orders with at least one discrepancy
+    with_discrepancies: u32,
+    /// Out of 100, the share of delivered orders with a discrepancy
+    discrepancy_rate: Decimal,
+    discrepancies: HashMap<discrepancy::Kind, u32>,
+    /// Discrepancies no one has resolved yet
+    open: u32,
+}
+
+/// How often each vendor's deliveries have had something wrong with them,
+/// worst first
+#[axum::debug_handler]
+async fn get_vendor_report(State(state): State<&'static UsrState>) -> Response {
+    let (orders, delivered, discrepancies) = tokio::join!(
+        order::Entity::find().all(&state.db),
+        order_status::Entity::find()
+            .filter(order_status::Column::Status.eq(order_status::Status::Delivered))
+            .all(&state.db),
+        discrepancy::Entity::find().all(&state.db),
+    );
+    let (orders, delivered, discrepancies) = match (orders, delivered, discrepancies) {
+        (Ok(orders), Ok(delivered), Ok(discrepancies)) => (orders, delivered, discrepancies),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            error!("Failed to get vendor report: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let delivered: HashSet<_> = delivered.into_iter().map(|x| x.order_id).collect();
+    let mut by_order = HashMap::<i64, Vec<discrepancy::Model>>::new();
+    for model in discrepancies {
+        by_order.entry(model.order_id).or_default().push(model);
+    }
+
+    let mut vendors = HashMap::<String, VendorQuality>::new();
+    for order in orders {
+        let discrepancies = by_order.remove(&order.id).unwrap_or_default();
+        if !delivered.contains(&order.id) && discrepancies.is_empty() {
+            continue;
+        }
+        let quality = vendors
+            .entry(vendor::key(&order.vendor))
+            .or_insert_with(|| VendorQuality {
+                vendor: order.vendor.trim().to_string(),
+                ..Default::default()
+            });
+        quality.delivered += 1;
+        if !discrepancies.is_empty() {
+            quality.with_discrepancies += 1;
+        }
+        for model in discrepancies {
+            *quality.discrepancies.entry(model.kind).or_default() += 1;
+            if model.resolved.is_none() {
+                quality.open += 1;
+            }
+        }
+    }
+    let mut report: Vec<_> = vendors
+        .into_values()
+        .map(|mut quality| {
+            quality.discrepancy_rate = money::round(
+                Decimal::from(quality.with_discrepancies) * Decimal::ONE_HUNDRED
+                    / Decimal::from(quality.delivered),
+            );
+            quality
+        })
+        .collect();
+    report.sort_by(|a, b| {
+        b.discrepancy_rate
+            .cmp(&a.discrepancy_rate)
+            .then(b.delivered.cmp(&a.delivered))
+    });
+    Json(report).into_response()
+}
+
+#[derive(Deserialize)]
+struct ReleaseOrder {
+    id: OrderRef,
+}
+
+/// Takes an order off hold, returning it to the status it had before.
+#[axum::debug_handler]
+async fn release_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(ReleaseOrder { id }): Json<ReleaseOrder>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot release holds", caller.role),
+        )
+            .into_response();
+    }
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let (order, statuses) = tokio::join!(
+        order::Entity::find_by_id(id).one(&state.db),
+        order_status::Entity::find()
+            .filter(order_status::Column::OrderId.eq(id))
+            .order_by_desc(order_status::Column::InstanceId)
+            .all(&state.db),
+    );
+    let (model, statuses) = match (order, statuses) {
+        (Ok(Some(model)), Ok(statuses)) => (model, statuses),
+        (Ok(None), _) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if statuses.first().map(|x| x.status) != Some(order_status::Status::OnHold) {
+        return (StatusCode::BAD_REQUEST, "Order is not on hold").into_response();
+    }
+    let Some(previous) = statuses.iter().map(|x| x.status).find(|status| {
+        !matches!(
+            status,
+            order_status::Status::OnHold | order_status::Status::Cancelled
+        )
+    }) else {
+        return (StatusCode::BAD_REQUEST, "Order has no status to return to").into_response();
+    };
+
+    let actor = audit::Actor::new(&caller, "/release/order");
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                order_status::ActiveModel {
+                    order_id: ActiveValue::Set(id),
+                    instance_id: ActiveValue::NotSet,
+                    date: ActiveValue::Set(Local::now().naive_local()),
+                    status: ActiveValue::Set(previous),
+                    reason: ActiveValue::Set(None),
+                }
+                .insert(tx)
+                .await?;
+                let diff = audit::change("status", order_status::Status::OnHold, previous);
+                actor.record(tx, id, diff).await
+            })
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to release order: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        let webhook_msg = format!(
+            "**Order Released!**\n**Order:** {}\n**Name:** {}\n**Team:** {}\n**Status:** {previous}{}",
+            model.number(),
+            model.name,
+            model.team,
+            permalink::line(&model)
+        );
+        let webhook_msg = notify_watchers(state, id, webhook_msg).await;
+        let webhook_msg = mention_requester(state, id, webhook_msg).await;
+        state
+            .notifier
+            .send_for(model.team, Topic::OrderUpdate, id as u32, webhook_msg);
+        backup_db(state);
+        orders_changed(state, Some(model.team)).await;
+        publish_event(state, events::EventKind::StatusUpdated, &model, previous).await;
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct RestoreOrder {
+    id: OrderRef,
+}
+
+/// Undoes a cancellation, returning the order to the status it had before.
+/// Like cancelling, only leads can restore an order that had already been
+/// processed.
+#[utoipa::path(
+    post,
+    path = "/api/manifest/restore/order",
+    tag = "manifest",
+    request_body = RestoreOrder,
+    responses(
+        (status = 200, description = "Restored"),
+        (status = 400, body = ApiError),
+        (status = 403, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn restore_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(RestoreOrder { id }): Json<RestoreOrder>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let (order, statuses) = tokio::join!(
+        order::Entity::find_by_id(id).one(&state.db),
+        order_status::Entity::find()
+            .filter(order_status::Column::OrderId.eq(id))
+            .order_by_desc(order_status::Column::InstanceId)
+            .all(&state.db),
+    );
+    let (model, statuses) = match (order, statuses) {
+        (Ok(Some(model)), Ok(statuses)) => (model, statuses),
+        (Ok(None), _) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if let Err(response) = policy::check_owner(&caller, &model) {
+        return response.into_response();
+    }
+    if statuses.first().map(|x| x.status) != Some(order_status::Status::Cancelled) {
+        return (StatusCode::BAD_REQUEST, "Order is not cancelled").into_response();
+    }
+    // An order held when it was cancelled goes back on hold, for the same reason
+    let Some(previous) = statuses
+        .into_iter()
+        .find(|x| x.status != order_status::Status::Cancelled)
+    else {
+        return (StatusCode::BAD_REQUEST, "Order has no status to return to").into_response();
+    };
+    if previous.status != order_status::Status::New && caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot restore orders that were processed", caller.role),
+        )
+            .into_response();
+    }
+
+    let status = previous.status;
+    let actor = audit::Actor::new(&caller, "/restore/order");
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                order_status::ActiveModel {
+                    order_id: ActiveValue::Set(id),
+                    instance_id: ActiveValue::NotSet,
+                    date: ActiveValue::Set(Local::now().naive_local()),
+                    status: ActiveValue::Set(status),
+                    reason: ActiveValue::Set(previous.reason),
+                }
+                .insert(tx)
+                .await?;
+                let diff = audit::change("status", order_status::Status::Cancelled, status);
+                actor.record(tx, id, diff).await
+            })
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to restore order: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        let webhook_msg = format!(
+            "**Order Restored!**\n**Order:** {}\n**Name:** {}\n**Team:** {}\n**Status:** {status}{}",
+            model.number(),
+            model.name,
+            model.team,
+            permalink::line(&model)
+        );
+        let webhook_msg = notify_watchers(state, id, webhook_msg).await;
+        let webhook_msg = mention_requester(state, id, webhook_msg).await;
+        state
+            .notifier
+            .send_for(model.team, Topic::OrderUpdate, id as u32, webhook_msg);
+        backup_db(state);
+        orders_changed(state, Some(model.team)).await;
+        publish_event(state, events::EventKind::Restored, &model, status).await;
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+/// Forgets the decision on order `id` after it was changed, since it was
+/// made on what the order used to be
+async fn clear_approval(db: &DatabaseConnection, id: i64) {
+    if let Err(e) = approval::Entity::delete_by_id(id).exec(db).await {
+        error!("Failed to clear order approval: {e}");
+    }
+}
+
+#[derive(Deserialize)]
+struct ApproveOrder {
+    id: OrderRef,
+}
+
+#[derive(Deserialize)]
+struct RejectOrder {
+    id: OrderRef,
+    reason: String,
+}
+
+/// Records a lead's decision on a new order and announces it
+async fn decide_order(
+    state: &'static UsrState,
+    caller: policy::Caller,
+    id: OrderRef,
+    decision: approval::Decision,
+    reason: Option<String>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot approve or reject orders", caller.role),
+        )
+            .into_response();
+    }
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let model = match current::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(current)) if current.status == order_status::Status::New => {
+            current.into_parts().0
+        }
+        Ok(Some(_)) => {
+            return (StatusCode::BAD_REQUEST, "Order has already been processed").into_response()
+        }
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let endpoint = match decision {
+        approval::Decision::Approved => "/approve/order",
+        approval::Decision::Rejected => "/reject/order",
+    };
+    let actor = audit::Actor::new(&caller, endpoint);
+    let decided_by = caller.name.unwrap_or_else(|| caller.role.to_string());
+    let active_model = approval::ActiveModel {
+        order_id: ActiveValue::Set(id),
+        decision: ActiveValue::Set(decision),
+        decided_by: ActiveValue::Set(decided_by.clone()),
+        reason: ActiveValue::Set(reason.clone()),
+        date: ActiveValue::Set(Local::now().naive_local()),
+    };
+    let mut diff = audit::change("approval", (), decision);
+    if let Some(reason) = &reason {
+        diff.extend(audit::change("approval_reason", (), reason));
+    }
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                approval::Entity::insert(active_model)
+                    .on_conflict(
+                        OnConflict::column(approval::Column::OrderId)
+                            .update_columns([
+                                approval::Column::Decision,
+                                approval::Column::DecidedBy,
+                                approval::Column::Reason,
+                                approval::Column::Date,
+                            ])
+                            .to_owned(),
+                    )
+                    .exec(tx)
+                    .await?;
+                actor.record(tx, id, diff).await
+            })
+        })
+        .await;
+    if let Err(e) = result {
+        error!("Failed to record order approval: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+    }
+
+    let title = match decision {
+        approval::Decision::Approved => "Order Approved!",
+        approval::Decision::Rejected => "Order Rejected",
+    };
+    let mut webhook_msg = format!(
+        "**{title}**\n**Order:** {}\n**Name:** {}\n**Team:** {}\n**By:** {decided_by}",
+        model.number(),
+        model.name,
+        model.team,
+    );
+    if let Some(reason) = &reason {
+        webhook_msg.push_str(&format!("\n**Reason:** {reason}"));
+    }
+    webhook_msg.push_str(&permalink::line(&model));
+    let webhook_msg = notify_watchers(state, id, webhook_msg).await;
+    let webhook_msg = mention_requester(state, id, webhook_msg).await;
+    state
+        .notifier
+        .send_for(model.team, Topic::OrderApproval, id as u32, webhook_msg);
+    backup_db(state);
+    publish_event(
+        state,
+        events::EventKind::Changed,
+        &model,
+        order_status::Status::New,
+    )
+    .await;
+    (StatusCode::OK, "").into_response()
+}
+
+/// Signs off on a new order, so that it can be bought
+#[axum::debug_handler]
+async fn approve_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(ApproveOrder { id }): Json<ApproveOrder>,
+) -> Response {
+    decide_order(state, caller, id, approval::Decision::Approved, None).await
+}
+
+/// Turns down a new order. It stays `New`, so that its requester can change
+/// it for another look, or cancel it.
+#[axum::debug_handler]
+async fn reject_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(RejectOrder { id, reason }): Json<RejectOrder>,
+) -> Response {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return (StatusCode::BAD_REQUEST, "A reason is required to reject an order").into_response();
+    }
+    decide_order(
+        state,
+        caller,
+        id,
+        approval::Decision::Rejected,
+        Some(reason.to_string()),
+    )
+    .await
+}
+
+#[derive(Deserialize)]
+struct ListApprovals {
+    #[serde(default)]
+    order_id: Option<OrderRef>,
+}
+
+#[axum::debug_handler]
+async fn get_approvals(
+    State(state): State<&'static UsrState>,
+    Query(ListApprovals { order_id }): Query<ListApprovals>,
+) -> Response {
+    let mut query = approval::Entity::find().order_by_desc(approval::Column::Date);
+    if let Some(order_id) = order_id {
+        match resolve_order(&state.db, &order_id).await {
+            Ok(id) => query = query.filter(approval::Column::OrderId.eq(id)),
+            Err(response) => return response.into_response(),
+        }
+    }
+    match query.all(&state.db).await {
+        Ok(approvals) => Json(approvals).into_response(),
+        Err(e) => {
+            error!("Failed to get order approvals: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListAudit {
+    #[serde(default)]
+    order_id: Option<OrderRef>,
+}
+
+#[derive(Serialize)]
+struct AuditEntry {
+    #[serde(flatten)]
+    entry: audit::Model,
+    /// What the change did to the order's subtotal, for changes to its count
+    /// or unit cost
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subtotal_change: Option<Decimal>,
+}
+
+/// Who changed what on orders, newest first, for leads settling disputes
+#[axum::debug_handler]
+async fn get_audit(
+    State(state): State<&'static UsrState>,
+    role: policy::Role,
+    Query(ListAudit { order_id }): Query<ListAudit>,
+) -> Response {
+    if role < policy::Role::Lead {
+        return (StatusCode::FORBIDDEN, format!("{role} cannot read the audit log")).into_response();
+    }
+    let mut query = audit::Entity::find().order_by_desc(audit::Column::Id);
+    if let Some(order_id) = order_id {
+        // Entries outlive deleted orders, so ids are taken as given
+        let id = match order_id.as_id() {
+            Some(id) => id,
+            None => match resolve_order(&state.db, &order_id).await {
+                Ok(id) => id,
+                Err(response) => return response.into_response(),
+            },
+        };
+        query = query.filter(audit::Column::OrderId.eq(id));
+    }
+    let result = async {
+        let entries = query.all(&state.db).await?;
+        let mut changes =
+            revision::by_audit_id(&state.db, entries.iter().map(|entry| entry.id)).await?;
+        Result::<_, sea_orm::DbErr>::Ok(
+            entries
+                .into_iter()
+                .map(|entry| AuditEntry {
+                    subtotal_change: changes.remove(&entry.id),
+                    entry,
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+    .await;
+    match result {
+        Ok(entries) => Json(entries).into_response(),
+        Err(e) => {
+            error!("Failed to get audit log: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DiffRevisions {
+    #[serde(default)]
+    from: Option<usize>,
+    #[serde(default)]
+    to: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct RevisionDiff {
+    order_id: i64,
+    from: usize,
+    to: usize,
+    /// The order's latest revision. Revision 0 is before it was placed, and
+    /// each change recorded in the audit log adds one.
+    latest: usize,
+    /// Each field that differs between the two revisions, as `[from, to]`
+    changes: serde_json::Map<String, serde_json::Value>,
+}
+
+/// What changed on an order between two of its revisions, for "what changed"
+/// views. `to` defaults to the latest revision and `from` to the one before
+/// it.
+#[axum::debug_handler]
+async fn get_order_diff(
+    State(state): State<&'static UsrState>,
+    Path(id): Path<OrderRef>,
+    Query(DiffRevisions { from, to }): Query<DiffRevisions>,
+) -> Response {
+    // Entries outlive deleted orders, so ids are taken as given
+    let id = match id.as_id() {
+        Some(id) => id,
+        None => match resolve_order(&state.db, &id).await {
+            Ok(id) => id,
+            Err(response) => return response.into_response(),
+        },
+    };
+    let entries = match audit::Entity::find()
+        .filter(audit::Column::OrderId.eq(id))
+        .order_by_asc(audit::Column::Id)
+        .all(&state.db)
+        .await
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to get audit log: {e}");
+            return ApiError::internal().into_response();
+        }
+    };
+    if entries.is_empty() {
+        return ApiError::new(StatusCode::NOT_FOUND, "Order has no recorded changes")
+            .into_response();
+    }
+    let latest = entries.len();
+    let to = to.unwrap_or(latest);
+    let from = from.unwrap_or(to.saturating_sub(1));
+    if to > latest {
+        return ApiError::invalid(format!("Order's latest revision is {latest}"))
+            .for_field("to")
+            .into_response();
+    }
+    if from > to {
+        return ApiError::invalid("from cannot be after to")
+            .for_field("from")
+            .into_response();
+    }
+    Json(RevisionDiff {
+        order_id: id,
+        from,
+        to,
+        latest,
+        changes: audit::combined(&entries[from..to]),
+    })
+    .into_response()
+}
+
+/// Columns that `/list/order` can be sorted by. `date` is when the order was placed.
+const ORDER_SORT: &listing::SortKeys = &[
+    ("id", || Expr::col(order::Column::Id).into()),
+    ("name", || Expr::col(order::Column::Name).into()),
+    ("vendor", || Expr::col(order::Column::Vendor).into()),
+    ("team", || Expr::col(order::Column::Team).into()),
+    ("count", || Expr::col(order::Column::Count).into()),
+    ("unit_cost", || Expr::col(order::Column::UnitCost).into()),
+    ("subtotal", || {
+        Expr::cust("CAST(\"orders\".\"count\" AS BIGINT) * \"orders\".\"unit_cost\"")
+    }),
+    ("date", placed_date),
+    ("needed_by", || Expr::col(order::Column::NeededBy).into()),
+];
+
+/// When each order in a query on `orders` was placed
+fn placed_date() -> SimpleExpr {
+    Expr::cust(
+        "(SELECT MIN(\"date\") FROM \"order_status\" WHERE \"order_status\".\"order_id\" = \"orders\".\"id\")",
+    )
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListOrders {
+    /// eg. `unit_cost.desc,date`
+    #[serde(default)]
+    sort: String,
+    #[serde(default)]
+    team: Option<scheduler::Team>,
+    /// Only orders whose latest status is this
+    #[serde(default)]
+    status: Option<order_status::Status>,
+    #[serde(default)]
+    vendor: Option<String>,
+    /// Only orders placed on or after this day
+    #[serde(default)]
+    since: Option<NaiveDate>,
+    /// Only orders placed together from this cart
+    #[serde(default)]
+    cart: Option<String>,
+    /// Only orders with this custom field value, as `key:value`
+    #[serde(default)]
+    field: Option<String>,
+    /// Whether cancelled orders are listed too. They always are when asking
+    /// for `status=Cancelled`.
+    #[serde(default)]
+    cancelled: bool,
+    /// The season whose orders are listed, the current one if left out
+    #[serde(default)]
+    season: Option<u16>,
+    /// Only orders requested by this member, set by `/list/order/mine`
+    #[serde(skip)]
+    #[param(ignore)]
+    requester: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct OrderList {
+    orders: Vec<order::Model>,
+    /// Every status of the listed orders
+    statuses: Vec<order_status::Model>,
+    fields: Vec<field_value::Model>,
+    /// How many orders match, when paged
+    total: Option<u64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/manifest/list/order",
+    tag = "manifest",
+    params(ListOrders, listing::Page),
+    responses(
+        (status = 200, body = OrderList),
+        (status = 400, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_orders(
+    State(state): State<&'static UsrState>,
+    Query(ListOrders {
+        sort,
+        team,
+        status,
+        vendor,
+        since,
+        cart,
+        field,
+        cancelled,
+        season,
+        requester,
+    }): Query<ListOrders>,
+    Query(page): Query<listing::Page>,
+) -> Response {
+    let sort = match listing::parse_sort(&sort, ORDER_SORT) {
+        Ok(x) => x,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let current = current_season();
+    let season = season.unwrap_or(current);
+    // Orders imported since startup aren't numbered into a season yet
+    let mut query = order::Entity::find().filter(if season == current {
+        order::Column::Season.eq(season).or(order::Column::Season.is_null())
+    } else {
+        order::Column::Season.eq(season)
+    });
+    if let Some(team) = team {
+        query = query.filter(order::Column::Team.eq(team));
+    }
+    if let Some(status) = status {
+        query = query.filter(
+            order::Column::Id.in_subquery(
+                sea_orm::sea_query::Query::select()
+                    .column(current::Column::Id)
+                    .from(current::Entity)
+                    .and_where(current::Column::Status.eq(status))
+                    .to_owned(),
+            ),
+        );
+    } else if !cancelled {
+        query = query.filter(
+            order::Column::Id.not_in_subquery(
+                sea_orm::sea_query::Query::select()
+                    .column(current::Column::Id)
+                    .from(current::Entity)
+                    .and_where(current::Column::Status.eq(order_status::Status::Cancelled))
+                    .to_owned(),
+            ),
+        );
+    }
+    if let Some(vendor) = vendor {
+        query = query.filter(order::Column::Vendor.eq(vendor.trim()));
+    }
+    if let Some(since) = since {
+        query = query.filter(Expr::expr(placed_date()).gte(since.and_time(NaiveTime::MIN)));
+    }
+    if let Some(cart) = cart {
+        query = query.filter(order::Column::CartId.eq(cart.trim()));
+    }
+    if let Some(requester) = requester {
+        query = query.filter(order::Column::Requester.eq(requester));
+    }
+    if let Some(field) = field {
+        let Some((key, value)) = field.split_once(':') else {
+            return (StatusCode::BAD_REQUEST, "field must be given as key:value").into_response();
+        };
+        let definition = match custom_field::Entity::find_by_id(key.trim()).one(&state.db).await {
+            Ok(Some(definition)) => definition,
+            Ok(None) => {
+                return (StatusCode::BAD_REQUEST, format!("There is no field called {key}"))
+                    .into_response()
+            }
+            Err(e) => {
+                error!("Failed to find custom field: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+            }
+        };
+        let value = match definition.normalize(&serde_json::Value::String(value.to_string())) {
+            Ok(value) => value,
+            Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+        };
+        query = query.filter(
+            order::Column::Id.in_subquery(
+                sea_orm::sea_query::Query::select()
+                    .column(field_value::Column::OrderId)
+                    .from(field_value::Entity)
+                    .and_where(field_value::Column::Field.eq(definition.key))
+                    .and_where(field_value::Column::Value.eq(value.to_string()))
+                    .to_owned(),
+            ),
+        );
+    }
+    // Paged clients need to know how many pages there are
+    let total = if page.per_page.is_some() {
+        match query.clone().count(&state.db).await {
+            Ok(total) => Some(total),
+            Err(e) => {
+                error!("Failed to count orders: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+            }
+        }
+    } else {
+        None
+    };
+    let query = listing::apply_sort(query, sort, Expr::col(order::Column::Id).into());
+    let result = page.apply(query).all(&state.db).await;
+
+    match result {
+        Ok(orders) => {
+            // Only the statuses of the orders listed are needed, since
+            // orders are always listed by season
+            let statuses = order_status::Entity::find().filter(
+                order_status::Column::OrderId.is_in(orders.iter().map(|model| model.id)),
+            );
+            let fields = field_value::Entity::find().filter(
+                field_value::Column::OrderId.is_in(orders.iter().map(|model| model.id)),
+            );
+            let result = tokio::try_join!(statuses.all(&state.db), fields.all(&state.db));
+
+            match result {
+                Ok((statuses, fields)) => Json(OrderList {
+                    orders,
+                    statuses,
+                    fields,
+                    total,
+                })
+                .into_response(),
+                Err(e) => {
+                    error!("Failed to get orders: {e}");
+                    (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+                }
+            }
+        }
+        Err(e) => {
+            error!("Failed to get orders: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MyOrders {
+    /// Whose orders are listed, when not signed in
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// The orders requested by whoever is signed in, filtered and paged like
+/// `/list/order`
+#[utoipa::path(
+    get,
+    path = "/api/manifest/list/order/mine",
+    tag = "manifest",
+    params(MyOrders, ListOrders, listing::Page),
+    responses(
+        (status = 200, body = OrderList),
+        (status = 400, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_my_orders(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Query(MyOrders { name }): Query<MyOrders>,
+    Query(mut list): Query<ListOrders>,
+    page: Query<listing::Page>,
+) -> Response {
+    let Some(name) = caller.name.or(non_blank(name)) else {
+        return (StatusCode::BAD_REQUEST, "Name is required").into_response();
+    };
+    list.requester = Some(name);
+    get_orders(State(state), Query(list), page).await
+}
+
+#[derive(Deserialize)]
+struct ImportedStatus {
+    /// Identifies the order by id or, for orders tracked in spreadsheets,
+    /// by its ref number
+    #[serde(default)]
+    order_id: Option<OrderRef>,
+    #[serde(default)]
+    ref_number: Option<u32>,
+    status: order_status::Status,
+    date: NaiveDateTime,
+    /// Why the order was put on hold, for `OnHold` statuses
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ImportReport {
+    orders: usize,
+    /// The statuses imported, whether they redated one or were added
+    statuses: usize,
+    /// The orders whose histories were merged, to publish events for
+    #[serde(skip)]
+    order_ids: Vec<i64>,
+}
+
+/// Merges historical status transitions into the orders' histories, so that
+/// orders migrated from spreadsheets keep their real dates. An imported
+/// status redates the existing one of the same kind, eg. the `New` status
+/// stamped when the order was migrated, and the rest are added after the
+/// order's existing statuses. Nothing is imported if any row is invalid.
+#[axum::debug_handler]
+async fn import_statuses(
+    State(state): State<&'static UsrState>,
+    role: policy::Role,
+    Json(imported): Json<Vec<ImportedStatus>>,
+) -> Response {
+    if role < policy::Role::Lead {
+        return (StatusCode::FORBIDDEN, format!("{role} cannot import statuses")).into_response();
+    }
+    let now = Local::now().naive_local();
+    if imported.iter().any(|row| row.date > now) {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Status dates cannot be in the future",
+        )
+            .into_response();
+    }
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let mut histories = HashMap::<
+                    i64,
+                    Vec<(order_status::Status, NaiveDateTime, Option<String>)>,
+                >::new();
+                for row in imported {
+                    let order_id = match (row.order_id, row.ref_number) {
+                        (Some(order), _) => match order.resolve(tx).await? {
+                            Some(order_id) => order_id,
+                            None => return Ok(Err("Order not found")),
+                        },
+                        (None, Some(ref_number)) => {
+                            let matches = order::Entity::find()
+                                .filter(order::Column::RefNumber.eq(ref_number))
+                                .all(tx)
+                                .await?;
+                            match matches.as_slice() {
+                                [model] => model.id,
+                                [] => return Ok(Err("Order not found")),
+                                _ => return Ok(Err("Ref number matches more than one order")),
+                            }
+                        }
+                        (None, None) => {
+                            return Ok(Err("Each status needs an order_id or ref_number"))
+                        }
+                    };
+                    histories
+                        .entry(order_id)
+                        .or_default()
+                        .push((row.status, row.date, row.reason));
+                }
+
+                let orders = histories.len();
+                let order_ids = histories.keys().copied().collect();
+                let mut redated = vec![];
+                let mut added = vec![];
+                let mut imported = 0;
+                for (order_id, mut history) in histories {
+                    let mut existing = order_status::Entity::find()
+                        .filter(order_status::Column::OrderId.eq(order_id))
+                        .all(tx)
+                        .await?;
+                    history.sort_by_key(|(_, date, _)| *date);
+                    // Statuses of a kind the order already has are redated in
+                    // place, so that their instance ids, and the `/list/status`
+                    // cursors made from them, stay the same
+                    let mut missing = vec![];
+                    for (status, date, reason) in history {
+                        imported += 1;
+                        match existing.iter_mut().find(|model| model.status == status) {
+                            Some(model) => {
+                                if model.date != date || model.reason != reason {
+                                    model.date = date;
+                                    model.reason = reason.clone();
+                                    redated.push(order_status::ActiveModel {
+                                        instance_id: ActiveValue::Unchanged(model.instance_id),
+                                        date: ActiveValue::Set(date),
+                                        reason: ActiveValue::Set(reason),
+                                        ..Default::default()
+                                    });
+                                }
+                            }
+                            None => missing.push((status, date, reason)),
+                        }
+                    }
+                    // The latest status is the one with the highest instance
+                    // id, which the added ones will have
+                    let latest = existing.iter().map(|model| model.date).max();
+                    if missing.first().is_some_and(|(_, date, _)| Some(*date) < latest) {
+                        return Ok(Err(
+                            "New statuses cannot be dated before an order's existing ones",
+                        ));
+                    }
+                    added.extend(missing.into_iter().map(|(status, date, reason)| {
+                        order_status::ActiveModel {
+                            order_id: ActiveValue::Set(order_id),
+                            instance_id: ActiveValue::NotSet,
+                            date: ActiveValue::Set(date),
+                            status: ActiveValue::Set(status),
+                            reason: ActiveValue::Set(reason),
+                        }
+                    }));
+                }
+                for model in redated {
+                    model.update(tx).await?;
+                }
+                if !added.is_empty() {
+                    order_status::Entity::insert_many(added).exec(tx).await?;
+                }
+
+                Result::<_, sea_orm::DbErr>::Ok(Ok(ImportReport {
+                    orders,
+                    statuses: imported,
+                    order_ids,
+                }))
+            })
+        })
+        .await;
+
+    match result {
+        Ok(Ok(report)) => {
+            backup_db(state);
+            orders_changed(state, None).await;
+            for id in &report.order_ids {
+                publish_current(state, events::EventKind::StatusUpdated, *id).await;
+            }
+            Json(report).into_response()
+        }
+        Ok(Err(msg)) => (StatusCode::BAD_REQUEST, msg).into_response(),
+        Err(e) => {
+            error!("Failed to import order statuses: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// The most statuses a single page of `/list/status` can hold
+const MAX_STATUS_PAGE: u64 = 5000;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListStatuses {
+    /// The `next` cursor of the previous page, as `order_id.instance_id`
+    #[serde(default)]
+    after: Option<String>,
+    #[serde(default = "default_status_limit")]
+    limit: u64,
+}
+
+fn default_status_limit() -> u64 {
+    1000
+}
+
+#[derive(Serialize, ToSchema)]
+struct StatusPage {
+    statuses: Vec<order_status::Model>,
+    /// Pass as `after` to get the next page, missing on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+}
+
+/// Pages through every status ordered by `(order_id, instance_id)`. Keyed on
+/// a cursor rather than an offset so that statuses added while paging
+/// neither shift nor repeat rows.
+#[utoipa::path(
+    get,
+    path = "/api/manifest/list/status",
+    tag = "manifest",
+    params(ListStatuses),
+    responses(
+        (status = 200, body = StatusPage),
+        (status = 400, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+async fn get_statuses(
+    State(state): State<&'static UsrState>,
+    Query(ListStatuses { after, limit }): Query<ListStatuses>,
+) -> Response {
+    let mut query = order_status::Entity::find()
+        .order_by_asc(order_status::Column::OrderId)
+        .order_by_asc(order_status::Column::InstanceId);
+    if let Some(after) = after {
+        let Some((order_id, instance_id)) = after
+            .split_once('.')
+            .and_then(|(a, b)| Some((a.parse::<u32>().ok()?, b.parse::<u32>().ok()?)))
+        else {
+            return (StatusCode::BAD_REQUEST, "Invalid cursor").into_response();
+        };
+        query = query.filter(
+            Condition::any()
+                .add(order_status::Column::OrderId.gt(order_id))
+                .add(
+                    Condition::all()
+                        .add(order_status::Column::OrderId.eq(order_id))
+                        .add(order_status::Column::InstanceId.gt(instance_id)),
+                ),
+        );
+    }
+    let limit = limit.clamp(1, MAX_STATUS_PAGE);
+
+    match query.limit(limit + 1).all(&state.db).await {
+        Ok(mut statuses) => {
+            let next = if statuses.len() as u64 > limit {
+                statuses.truncate(limit as usize);
+                statuses
+                    .last()
+                    .map(|model| format!("{}.{}", model.order_id, model.instance_id))
+            } else {
+                None
+            };
+            Json(StatusPage { statuses, next }).into_response()
+        }
+        Err(e) => {
+            error!("Failed to get order statuses: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn new_wishlist(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(mut pending_order): Json<PendingOrder>,
+) -> (StatusCode, &'static str) {
+    pending_order.request_as(&caller);
+    if let Err(msg) = money::validate_unit_cost(pending_order.unit_cost) {
+        return (StatusCode::BAD_REQUEST, msg);
+    }
+    // Wishlist items are kept in dollars
+    if let Err(msg) = pending_order.capture_rate(state, None) {
+        return (StatusCode::BAD_REQUEST, msg);
+    }
+    let unit_cost = pending_order.dollar_unit_cost();
+    let active_model = wishlist::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(pending_order.name),
+        count: ActiveValue::Set(pending_order.count.into()),
+        unit_cost: ActiveValue::Set(unit_cost),
+        store_in: ActiveValue::Set(pending_order.store_in),
+        team: ActiveValue::Set(pending_order.team),
+        reason: ActiveValue::Set(pending_order.reason),
+        vendor: ActiveValue::Set(pending_order.vendor),
+        link: ActiveValue::Set(pending_order.link),
+        funding_source: ActiveValue::Set(pending_order.funding_source),
+        date: ActiveValue::Set(Local::now().naive_local()),
+        requester: ActiveValue::Set(pending_order.requester),
+    };
+
+    if let Err(e) = active_model.insert(&state.db).await {
+        error!("Failed to add wishlist item: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "")
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "")
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteWishlist {
+    id: u32,
+}
+
+#[axum::debug_handler]
+async fn del_wishlist(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(DeleteWishlist { id }): Json<DeleteWishlist>,
+) -> Response {
+    let model = match wishlist::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(model)) => model,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Wishlist item not found").into_response(),
+        Err(e) => {
+            error!("Failed to find wishlist item: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if caller.role < policy::Role::Lead
+        && (caller.name.is_none() || caller.name != model.requester)
+    {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} can only delete their own wishlist items", caller.role),
+        )
+            .into_response();
+    }
+    match wishlist::Entity::delete_by_id(id).exec(&state.db).await {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Wishlist item not found").into_response()
+        }
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete wishlist item: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PromoteWishlist {
+    id: u32,
+    /// Overrides the wishlisted count, since the original guess is often stale
+    #[serde(default)]
+    count: Option<u32>,
+    /// Values for custom fields, which wishlist items don't have
+    #[serde(default)]
+    fields: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    needed_by: Option<NaiveDate>,
+}
+
+#[axum::debug_handler]
+async fn promote_wishlist(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(PromoteWishlist {
+        id,
+        count,
+        fields,
+        needed_by,
+    }): Json<PromoteWishlist>,
+) -> Response {
+    let actor = audit::Actor::new(&caller, "/promote/wishlist");
+    let model = match wishlist::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(model)) => model,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Wishlist item not found").into_response(),
+        Err(e) => {
+            error!("Failed to find wishlist item: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let mut pending_order = PendingOrder {
+        name: model.name,
+        count: count.unwrap_or(model.count as u32),
+        unit_cost: model.unit_cost,
+        store_in: model.store_in,
+        team: model.team,
+        reason: model.reason,
+        vendor: model.vendor,
+        link: model.link,
+        funding_source: model.funding_source,
+        component_id: None,
+        requester: caller.name.clone(),
+        currency: order::Currency::Usd,
+        exchange_rate: None,
+        fields,
+        needed_by,
+        allow_duplicate: false,
+        shipping_cost: None,
+        tax: None,
+        fees: None,
+    };
+    if let Err(e) = service::check_new_order(state, &mut pending_order, None).await {
+        return e.into_response();
+    }
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let pending_orders = std::slice::from_ref(&pending_order);
+                if let Err(e) = quota::check(tx, state, &caller, pending_orders).await? {
+                    return Ok(Err(e));
+                }
+                let placed = service::insert_order(state, tx, pending_order, &actor).await?;
+                wishlist::Entity::delete_by_id(id).exec(tx).await?;
+                Result::<_, sea_orm::DbErr>::Ok(Ok(placed))
+            })
+        })
+        .await;
+
+    match result {
+        Ok(Ok(placed)) => {
+            service::announce_placed(state, placed).await;
+            (StatusCode::OK, "").into_response()
+        }
+        Ok(Err(e)) => e.into_response(),
+        Err(e) => {
+            error!("Failed to promote wishlist item: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn get_wishlist(State(state): State<&'static UsrState>) -> Response {
+    match wishlist::Entity::find().all(&state.db).await {
+        Ok(items) => Json(items).into_response(),
+        Err(e) => {
+            error!("Failed to get wishlist: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Sums the shares of every order, grouped by team.
+pub async fn team_spend(
+    db: &DatabaseConnection,
+) -> Result<HashMap<scheduler::Team, Decimal>, sea_orm::DbErr> {
+    let splits = budget::all_splits(db).await?;
+    let unspent = budget::unspent_cancelled(db).await?;
+    let mut out = HashMap::<scheduler::Team, Decimal>::new();
+    for model in order::Entity::find().all(db).await? {
+        if unspent.contains(&model.id) {
+            continue;
+        }
+        let splits = splits.get(&model.id).map(Vec::as_slice).unwrap_or_default();
+        for share in budget::shares(&model, splits) {
+            *out.entry(share.team).or_default() += share.amount;
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Deserialize)]
+struct SetFunding {
+    source: funding::Source,
+    allocated: Decimal,
+}
+
+#[axum::debug_handler]
+async fn set_funding(
+    State(state): State<&'static UsrState>,
+    role: policy::Role,
+    Json(SetFunding { source, allocated }): Json<SetFunding>,
+) -> Response {
+    if role < policy::Role::Lead {
+        return (StatusCode::FORBIDDEN, format!("{role} cannot reallocate funding")).into_response();
+    }
+    if allocated.is_sign_negative() {
+        return (StatusCode::BAD_REQUEST, "Allocation cannot be negative").into_response();
+    }
+    let result = funding::Entity::insert(funding::ActiveModel {
+        source: ActiveValue::Set(source),
+        allocated: ActiveValue::Set(allocated),
+    })
+    .on_conflict(
+        OnConflict::column(funding::Column::Source)
+            .update_column(funding::Column::Allocated)
+            .to_owned(),
+    )
+    .exec(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to set funding: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct SetVendor {
+    name: String,
+    #[serde(default)]
+    website: Option<String>,
+    #[serde(default)]
+    shipping_estimate: Option<String>,
+    #[serde(default)]
+    tax_exempt_notes: Option<String>,
+    #[serde(default)]
+    shipping_account: Option<String>,
+    #[serde(default)]
+    login_hint: Option<String>,
+    #[serde(default)]
+    minimum_order: Option<Decimal>,
+    #[serde(default)]
+    free_shipping_threshold: Option<Decimal>,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    rma_contact: Option<String>,
+    #[serde(default)]
+    rma_instructions: Option<String>,
+    #[serde(default)]
+    return_window_days: Option<u32>,
+}
+
+/// Blank text is the same as leaving it out
+fn non_blank(text: Option<String>) -> Option<String> {
+    text.map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// Records what the purchaser needs to know about a vendor, replacing
+/// anything recorded before
+#[axum::debug_handler]
+async fn set_vendor(
+    State(state): State<&'static UsrState>,
+    role: policy::Role,
+    Json(set_vendor): Json<SetVendor>,
+) -> Response {
+    if role < policy::Role::Lead {
+        return (StatusCode::FORBIDDEN, format!("{role} cannot change vendors")).into_response();
+    }
+    let name = set_vendor.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Vendor name is required").into_response();
+    }
+    if [set_vendor.minimum_order, set_vendor.free_shipping_threshold]
+        .into_iter()
+        .flatten()
+        .any(|amount| amount.is_sign_negative())
+    {
+        return (StatusCode::BAD_REQUEST, "Amounts cannot be negative").into_response();
+    }
+    let result = vendor::Entity::insert(vendor::ActiveModel {
+        key: ActiveValue::Set(vendor::key(&name)),
+        name: ActiveValue::Set(name),
+        website: ActiveValue::Set(non_blank(set_vendor.website)),
+        shipping_estimate: ActiveValue::Set(non_blank(set_vendor.shipping_estimate)),
+        tax_exempt_notes: ActiveValue::Set(non_blank(set_vendor.tax_exempt_notes)),
+        shipping_account: ActiveValue::Set(non_blank(set_vendor.shipping_account)),
+        login_hint: ActiveValue::Set(non_blank(set_vendor.login_hint)),
+        minimum_order: ActiveValue::Set(set_vendor.minimum_order.map(money::round)),
+        free_shipping_threshold: ActiveValue::Set(
+            set_vendor.free_shipping_threshold.map(money::round),
+        ),
+        notes: ActiveValue::Set(set_vendor.notes.trim().to_string()),
+        rma_contact: ActiveValue::Set(non_blank(set_vendor.rma_contact)),
+        rma_instructions: ActiveValue::Set(non_blank(set_vendor.rma_instructions)),
+        return_window_days: ActiveValue::Set(set_vendor.return_window_days.map(i64::from)),
+    })
+    .on_conflict(
+        OnConflict::column(vendor::Column::Key)
+            .update_columns([
+                vendor::Column::Name,
+                vendor::Column::Website,
+                vendor::Column::ShippingEstimate,
+                vendor::Column::TaxExemptNotes,
+                vendor::Column::ShippingAccount,
+                vendor::Column::LoginHint,
+                vendor::Column::MinimumOrder,
+                vendor::Column::FreeShippingThreshold,
+                vendor::Column::Notes,
+                vendor::Column::RmaContact,
+                vendor::Column::RmaInstructions,
+                vendor::Column::ReturnWindowDays,
+            ])
+            .to_owned(),
+    )
+    .exec(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to set vendor: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        state.typeahead.invalidate();
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteVendor {
+    name: String,
+}
+
+#[axum::debug_handler]
+async fn del_vendor(
+    State(state): State<&'static UsrState>,
+    role: policy::Role,
+    Json(DeleteVendor { name }): Json<DeleteVendor>,
+) -> Response {
+    if role < policy::Role::Lead {
+        return (StatusCode::FORBIDDEN, format!("{role} cannot delete vendors")).into_response();
+    }
+    match vendor::Entity::delete_by_id(vendor::key(&name))
+        .exec(&state.db)
+        .await
+    {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Vendor not found").into_response()
+        }
+        Ok(_) => {
+            state.typeahead.invalidate();
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete vendor: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SetField {
+    key: String,
+    label: String,
+    kind: custom_field::Kind,
+    #[serde(default)]
+    required: bool,
+}
+
+/// Adds a custom field to every order, or changes how one is labelled and
+/// whether it is required. A field's kind can't be changed once it has
+/// values, since they would no longer be of that kind.
+#[axum::debug_handler]
+async fn set_field(
+    State(state): State<&'static UsrState>,
+    Json(set_field): Json<SetField>,
+) -> (StatusCode, &'static str) {
+    let key = set_field.key.trim().to_string();
+    if !custom_field::valid_key(&key) {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Field keys must be 1 to 32 lowercase letters, digits or underscores",
+        );
+    }
+    let label = set_field.label.trim().to_string();
+    if label.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Field label is required");
+    }
+    let existing = match custom_field::Entity::find_by_id(key.clone())
+        .one(&state.db)
+        .await
+    {
+        Ok(existing) => existing,
+        Err(e) => {
+            error!("Failed to find custom field: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "");
+        }
+    };
+    let result = match existing {
+        Some(existing) => {
+            if existing.kind != set_field.kind {
+                match field_value::Entity::find()
+                    .filter(field_value::Column::Field.eq(&key))
+                    .count(&state.db)
+                    .await
+                {
+                    Ok(0) => {}
+                    Ok(_) => {
+                        return (
+                            StatusCode::CONFLICT,
+                            "The field's kind can't be changed while orders have values for it",
+                        )
+                    }
+                    Err(e) => {
+                        error!("Failed to count custom field values: {e}");
+                        return (StatusCode::INTERNAL_SERVER_ERROR, "");
+                    }
+                }
+            }
+            let mut active_model: custom_field::ActiveModel = existing.into();
+            active_model.label = ActiveValue::Set(label);
+            active_model.kind = ActiveValue::Set(set_field.kind);
+            active_model.required = ActiveValue::Set(set_field.required);
+            active_model.update(&state.db).await.map(|_| ())
+        }
+        None => custom_field::ActiveModel {
+            key: ActiveValue::Set(key),
+            label: ActiveValue::Set(label),
+            kind: ActiveValue::Set(set_field.kind),
+            required: ActiveValue::Set(set_field.required),
+            created: ActiveValue::Set(Local::now().naive_local()),
+        }
+        .insert(&state.db)
+        .await
+        .map(|_| ()),
+    };
+    if let Err(e) = result {
+        error!("Failed to set custom field: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "")
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "")
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteField {
+    key: String,
+}
+
+/// Removes a custom field, along with every order's value for it
+#[axum::debug_handler]
+async fn del_field(
+    State(state): State<&'static UsrState>,
+    Json(DeleteField { key }): Json<DeleteField>,
+) -> (StatusCode, &'static str) {
+    let key = key.trim().to_string();
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                field_value::Entity::delete_many()
+                    .filter(field_value::Column::Field.eq(&key))
+                    .exec(tx)
+                    .await?;
+                custom_field::Entity::delete_by_id(key).exec(tx).await
+            })
+        })
+        .await;
+    match result {
+        Ok(result) if result.rows_affected == 0 => (StatusCode::BAD_REQUEST, "Field not found"),
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "")
+        }
+        Err(e) => {
+            error!("Failed to delete custom field: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
+        }
+    }
+}
+
+/// The custom fields orders have, oldest first
+#[axum::debug_handler]
+async fn get_fields(State(state): State<&'static UsrState>) -> Response {
+    match custom_field::Entity::find()
+        .order_by_asc(custom_field::Column::Created)
+        .all(&state.db)
+        .await
+    {
+        Ok(fields) => Json(fields).into_response(),
+        Err(e) => {
+            error!("Failed to get custom fields: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct VendorEntry {
+    #[serde(flatten)]
+    vendor: vendor::Model,
+    orders: u32,
+    /// What orders that have been submitted came to
+    spent: Decimal,
+    /// From submitted to delivered, over the orders that have arrived
+    #[serde(skip_serializing_if = "Option::is_none")]
+    average_lead_days: Option<f64>,
+}
+
+/// Every vendor, with how much has been bought from it and how long it takes
+#[axum::debug_handler]
+async fn get_vendors(State(state): State<&'static UsrState>) -> Response {
+    let (vendors, orders, lead_times) = tokio::join!(
+        vendor::Entity::find()
+            .order_by_asc(vendor::Column::Key)
+            .all(&state.db),
+        current::Entity::find().all(&state.db),
+        vendor_lead_times(&state.db),
+    );
+    let (vendors, orders, lead_times) = match (vendors, orders, lead_times) {
+        (Ok(vendors), Ok(orders), Ok(lead_times)) => (vendors, orders, lead_times),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            error!("Failed to get vendors: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let mut totals = HashMap::<String, (u32, Decimal)>::new();
+    for model in orders {
+        let total = totals.entry(vendor::key(&model.vendor)).or_default();
+        total.0 += 1;
+        if model.status != order_status::Status::New {
+            total.1 += money::subtotal(model.count, model.unit_cost);
+        }
+    }
+    let entries: Vec<_> = vendors
+        .into_iter()
+        .map(|vendor| {
+            let (orders, spent) = totals.remove(&vendor.key).unwrap_or_default();
+            VendorEntry {
+                orders,
+                spent,
+                average_lead_days: lead_times
+                    .get(&vendor.key)
+                    .map(|lead_time| (lead_time.mean_days * 10.0).round() / 10.0),
+                vendor,
+            }
+        })
+        .collect();
+    Json(entries).into_response()
+}
+
+#[derive(Deserialize)]
+struct SetVendorPolicy {
+    vendor: String,
+    standing: vendor_policy::Standing,
+    #[serde(default)]
+    reason: Option<String>,
+    /// Turns away orders from a blocked vendor instead of warning about them
+    #[serde(default)]
+    reject: bool,
+}
+
+/// Marks a vendor as preferred, allowed or blocked, replacing its policy
+#[axum::debug_handler]
+async fn set_vendor_policy(
+    State(state): State<&'static UsrState>,
+    Json(set_policy): Json<SetVendorPolicy>,
+) -> Response {
+    let vendor = set_policy.vendor.trim().to_string();
+    if vendor.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Vendor name is required").into_response();
+    }
+    let reason = non_blank(set_policy.reason);
+    if set_policy.standing == vendor_policy::Standing::Blocked && reason.is_none() {
+        return (StatusCode::BAD_REQUEST, "Blocking a vendor needs a reason").into_response();
+    }
+    if set_policy.reject && set_policy.standing != vendor_policy::Standing::Blocked {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Only orders from blocked vendors are rejected",
+        )
+            .into_response();
+    }
+    let active_model = vendor_policy::ActiveModel {
+        key: ActiveValue::Set(vendor::key(&vendor)),
+        vendor: ActiveValue::Set(vendor),
+        standing: ActiveValue::Set(set_policy.standing),
+        reason: ActiveValue::Set(reason),
+        reject: ActiveValue::Set(set_policy.reject),
+        since: ActiveValue::Set(Local::now().naive_local()),
+    };
+    let result = vendor_policy::Entity::insert(active_model)
+        .on_conflict(
+            OnConflict::column(vendor_policy::Column::Key)
+                .update_columns([
+                    vendor_policy::Column::Vendor,
+                    vendor_policy::Column::Standing,
+                    vendor_policy::Column::Reason,
+                    vendor_policy::Column::Reject,
+                    vendor_policy::Column::Since,
+                ])
+                .to_owned(),
+        )
+        .exec(&state.db)
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to set vendor policy: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+/// Forgets a vendor's policy, so that it is allowed like any other
+#[axum::debug_handler]
+async fn del_vendor_policy(
+    State(state): State<&'static UsrState>,
+    Json(DeleteVendor { name }): Json<DeleteVendor>,
+) -> (StatusCode, &'static str) {
+    match vendor_policy::Entity::delete_by_id(vendor::key(&name))
+        .exec(&state.db)
+        .await
+    {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Vendor has no policy")
+        }
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "")
+        }
+        Err(e) => {
+            error!("Failed to delete vendor policy: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn get_vendor_policies(State(state): State<&'static UsrState>) -> Response {
+    match vendor_policy::Entity::find()
+        .order_by_asc(vendor_policy::Column::Key)
+        .all(&state.db)
+        .await
+    {
+        Ok(policies) => Json(policies).into_response(),
+        Err(e) => {
+            error!("Failed to get vendor policies: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// New orders grouped by vendor, for placing each vendor's orders together
+#[axum::debug_handler]
+async fn get_batches(State(state): State<&'static UsrState>) -> Response {
+    match batch::batches(&state.db).await {
+        Ok(batches) => Json(batches).into_response(),
+        Err(e) => {
+            error!("Failed to get purchase batches: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// One vendor's new orders, with what the purchaser needs to know about the
+/// vendor
+#[axum::debug_handler]
+async fn get_batch(
+    State(state): State<&'static UsrState>,
+    Path(vendor): Path<String>,
+) -> Response {
+    let key = vendor::key(&vendor);
+    match batch::batches(&state.db).await {
+        Ok(batches) => match batches
+            .into_iter()
+            .find(|batch| vendor::key(&batch.vendor) == key)
+        {
+            Some(batch) => Json(batch).into_response(),
+            None => (StatusCode::NOT_FOUND, "No new orders from that vendor").into_response(),
+        },
+        Err(e) => {
+            error!("Failed to get purchase batch: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NewSplit {
+    team: scheduler::Team,
+    /// Defaults to the order's own funding source
+    #[serde(default)]
+    funding_source: Option<funding::Source>,
+    #[serde(default)]
+    percent: Option<Decimal>,
+    #[serde(default)]
+    amount: Option<Decimal>,
+}
+
+#[derive(Deserialize)]
+struct SetSplits {
+    id: OrderRef,
+    splits: Vec<NewSplit>,
+}
+
+/// Replaces how an order's cost is split. An empty list puts the whole cost
+/// back on the order's own team and funding source.
+#[axum::debug_handler]
+async fn set_splits(
+    State(state): State<&'static UsrState>,
+    role: policy::Role,
+    Json(SetSplits { id, splits }): Json<SetSplits>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let (model, status) = match current::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(current)) => current.into_parts(),
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if let Err(response) = policy::check_field(role, "splits", status) {
+        return response.into_response();
+    }
+
+    let subtotal = money::subtotal(model.count, model.unit_cost);
+    let mut total = Decimal::ZERO;
+    for split in &splits {
+        match (split.percent, split.amount) {
+            (Some(percent), None) => {
+                if percent <= Decimal::ZERO || percent > Decimal::ONE_HUNDRED {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        "Percentages must be above 0 and at most 100",
+                    )
+                        .into_response();
+                }
+                total += money::round(subtotal * percent / Decimal::ONE_HUNDRED);
+            }
+            (None, Some(amount)) => {
+                if amount <= Decimal::ZERO {
+                    return (StatusCode::BAD_REQUEST, "Amounts must be positive").into_response();
+                }
+                total += money::round(amount);
+            }
+            _ => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    "Each split needs exactly one of percent and amount",
+                )
+                    .into_response();
+            }
+        }
+    }
+    if total > subtotal {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Splits cannot add up to more than the order's subtotal",
+        )
+            .into_response();
+    }
+
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                cost_split::Entity::delete_many()
+                    .filter(cost_split::Column::OrderId.eq(id))
+                    .exec(tx)
+                    .await?;
+                for split in splits {
+                    cost_split::ActiveModel {
+                        id: ActiveValue::NotSet,
+                        order_id: ActiveValue::Set(id),
+                        team: ActiveValue::Set(split.team),
+                        funding_source: ActiveValue::Set(
+                            split.funding_source.unwrap_or(model.funding_source),
+                        ),
+                        percent: ActiveValue::Set(split.percent),
+                        amount: ActiveValue::Set(split.amount.map(money::round)),
+                    }
+                    .insert(tx)
+                    .await?;
+                }
+                Result::<_, sea_orm::DbErr>::Ok(())
+            })
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to set cost splits: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        orders_changed(state, None).await;
+        publish_current(state, events::EventKind::Changed, id).await;
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct ListSplits {
+    order_id: OrderRef,
+}
+
+#[derive(Serialize)]
+struct SplitSummary {
+    splits: Vec<cost_split::Model>,
+    /// What each team and funding source ends up paying, the order's own
+    /// team and source last
+    shares: Vec<budget::Share>,
+}
+
+#[axum::debug_handler]
+async fn get_splits(
+    State(state): State<&'static UsrState>,
+    Query(ListSplits { order_id }): Query<ListSplits>,
+) -> Response {
+    let id = match resolve_order(&state.db, &order_id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let (model, splits) = tokio::join!(
+        order::Entity::find_by_id(id).one(&state.db),
+        cost_split::Entity::find()
+            .filter(cost_split::Column::OrderId.eq(id))
+            .order_by_asc(cost_split::Column::Id)
+            .all(&state.db),
+    );
+    match (model, splits) {
+        (Ok(Some(model)), Ok(splits)) => Json(SplitSummary {
+            shares: budget::shares(&model, &splits),
+            splits,
+        })
+        .into_response(),
+        (Ok(None), _) => (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get cost splits: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TransferItem {
+    id: OrderRef,
+    count: u32,
+}
+
+#[derive(Deserialize)]
+struct TransferInventory {
+    from: String,
+    to: String,
+    items: Vec<TransferItem>,
+    /// Posted to the order updates webhook along with what moved, if given
+    #[serde(default)]
+    note: Option<String>,
+}
+
+/// Moves each item in turn, stopping at the first that can't be moved
+async fn move_stock(
+    db: &DatabaseConnection,
+    from: &str,
+    to: &str,
+    items: &[(i64, u32)],
+    note: Option<String>,
+) -> Result<Result<Vec<(order::Model, transfer::Model)>, String>, sea_orm::DbErr> {
+    let now = Local::now().naive_local();
+    let tx = db.begin().await?;
+    let mut out = vec![];
+    for &(id, count) in items {
+        let Some(current) = current::Entity::find_by_id(id).one(&tx).await? else {
+            return Ok(Err("Order not found".to_string()));
+        };
+        let (model, status) = current.into_parts();
+        if status != order_status::Status::InStorage {
+            return Ok(Err(format!("Order {} is not in storage", model.number())));
+        }
+        match inventory::transfer(&tx, &model, count.into(), from, to, note.clone(), now).await? {
+            Ok(transfer) => out.push((model, transfer)),
+            Err(reason) => return Ok(Err(reason)),
+        }
+    }
+    tx.commit().await?;
+    Ok(Ok(out))
+}
+
+/// Moves stored stock from one location to another, such as into the
+/// competition trailer. Either every item is moved or none are. Responds with
+/// the ledger entries.
+#[axum::debug_handler]
+async fn transfer_inventory(
+    State(state): State<&'static UsrState>,
+    role: policy::Role,
+    Json(TransferInventory {
+        from,
+        to,
+        items,
+        note,
+    }): Json<TransferInventory>,
+) -> Response {
+    if role < policy::Role::Lead {
+        return (StatusCode::FORBIDDEN, format!("{role} cannot transfer inventory")).into_response();
+    }
+    let from = from.trim();
+    let to = to.trim();
+    if from.is_empty() || to.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Both locations are required").into_response();
+    }
+    let to = match location::check(&state.db, to).await {
+        Ok(to) => to,
+        Err(e) => return e.into_response(),
+    };
+    let to = to.as_str();
+    if from == to {
+        return (StatusCode::BAD_REQUEST, "Stock is already there").into_response();
+    }
+    if items.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Nothing to transfer").into_response();
+    }
+    if items.iter().any(|item| item.count == 0) {
+        return ApiError::invalid("Count must be positive")
+            .for_field("count")
+            .into_response();
+    }
+    let note = note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+    let mut resolved = Vec::with_capacity(items.len());
+    for item in &items {
+        match resolve_order(&state.db, &item.id).await {
+            Ok(id) => resolved.push((id, item.count)),
+            Err(response) => return response.into_response(),
+        }
+    }
+
+    let moved = match move_stock(&state.db, from, to, &resolved, note.clone()).await {
+        Ok(Ok(moved)) => moved,
+        Ok(Err(reason)) => return (StatusCode::CONFLICT, reason).into_response(),
+        Err(e) => {
+            error!("Failed to transfer inventory: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    backup_db(state);
+    orders_changed(state, None).await;
+
+    if let Some(note) = note {
+        let mut msg = format!("**Inventory Moved!**\n**From:** {from}\n**To:** {to}");
+        for (model, transfer) in &moved {
+            msg.push_str(&format!(
+                "\n- {} x {} ({})",
+                transfer.count,
+                model.name,
+                model.number()
+            ));
+        }
+        msg.push_str(&format!("\n**Note:** {note}"));
+        // Keyed apart from the order ids that order updates use
+        state
+            .notifier
+            .send(Topic::OrderUpdate, u32::MAX / 4 + moved[0].1.id as u32, msg);
+    }
+    Json(
+        moved
+            .into_iter()
+            .map(|(_, transfer)| transfer)
+            .collect::<Vec<_>>(),
+    )
+    .into_response()
+}
+
+/// Tells everyone watching order `id` that it moved along, returning `msg`
+/// with the watchers to mention added. Watchers who chose direct messages are
+/// sent `msg` instead, unless they can't be DMed.
+async fn notify_watchers(state: &'static UsrState, id: i64, mut msg: String) -> String {
+    let watchers = match watch::Entity::find()
+        .filter(watch::Column::OrderId.eq(id))
+        .all(&state.db)
+        .await
+    {
+        Ok(watchers) => watchers,
+        Err(e) => {
+            error!("Failed to find order watchers: {e}");
+            return msg;
+        }
+    };
+    let mut mentions = vec![];
+    for watcher in watchers {
+        let discord_id = match dm::discord_id(&state.db, &watcher.name).await {
+            Ok(Some(discord_id)) => discord_id,
+            // There's no way to reach them
+            Ok(None) => continue,
+            Err(e) => {
+                error!("Failed to find Discord id: {e}");
+                continue;
+            }
+        };
+        if watcher.channel == watch::Channel::Dm && dm::enabled(state) {
+            let content = msg.clone();
+            tokio::spawn(async move {
+                if let Err(e) = dm::send_dm(state, &watcher.name, &content).await {
+                    warn!("Failed to DM order update to {}: {e}", watcher.name);
+                }
+            });
+        } else {
+            mentions.push(format!("<@{discord_id}>"));
+        }
+    }
+    if !mentions.is_empty() {
+        msg.push_str(&format!("\n**Watching:** {}", mentions.join(" ")));
+    }
+    msg
+}
+
+/// Mentions whoever requested order `id` in `msg`, so that they hear about
+/// their order moving along without having to watch it. Requesters who watch
+/// the order already hear about it that way.
+async fn mention_requester(state: &'static UsrState, id: i64, mut msg: String) -> String {
+    let found = async {
+        let Some(requester) = order::Entity::find_by_id(id)
+            .one(&state.db)
+            .await?
+            .and_then(|order| order.requester)
+        else {
+            return Ok(None);
+        };
+        if watch::Entity::find_by_id((id, requester.clone()))
+            .one(&state.db)
+            .await?
+            .is_some()
+        {
+            return Ok(None);
+        }
+        dm::discord_id(&state.db, &requester).await
+    }
+    .await;
+    match found {
+        Ok(Some(discord_id)) => msg.push_str(&format!("\n**Requested By:** <@{discord_id}>")),
+        Ok(None) => {}
+        Err(e) => error!("Failed to find order requester: {e}"),
+    }
+    msg
+}
+
+#[derive(Deserialize)]
+struct WatchOrder {
+    id: OrderRef,
+    /// Who is watching, when not signed in
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    channel: watch::Channel,
+}
+
+/// The name of whoever is (un)watching an order. Signed in users can only
+/// (un)watch for themselves.
+fn watcher_name(
+    caller: policy::Caller,
+    name: Option<String>,
+) -> Result<String, (StatusCode, &'static str)> {
+    caller
+        .name
+        .or(non_blank(name))
+        .ok_or((StatusCode::BAD_REQUEST, "Name is required"))
+}
+
+/// Follows an order, or changes how it is followed
+#[axum::debug_handler]
+async fn watch_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(WatchOrder { id, name, channel }): Json<WatchOrder>,
+) -> Response {
+    let name = match watcher_name(caller, name) {
+        Ok(name) => name,
+        Err(response) => return response.into_response(),
+    };
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let result = watch::Entity::insert(watch::ActiveModel {
+        order_id: ActiveValue::Set(id),
+        name: ActiveValue::Set(name),
+        channel: ActiveValue::Set(channel),
+    })
+    .on_conflict(
+        OnConflict::columns([watch::Column::OrderId, watch::Column::Name])
+            .update_column(watch::Column::Channel)
+            .to_owned(),
+    )
+    .exec(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to watch order: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct UnwatchOrder {
+    id: OrderRef,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[axum::debug_handler]
+async fn unwatch_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(UnwatchOrder { id, name }): Json<UnwatchOrder>,
+) -> Response {
+    let name = match watcher_name(caller, name) {
+        Ok(name) => name,
+        Err(response) => return response.into_response(),
+    };
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    match watch::Entity::delete_by_id((id, name)).exec(&state.db).await {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Not watching order").into_response()
+        }
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to unwatch order: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListWatches {
+    #[serde(default)]
+    order_id: Option<OrderRef>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[axum::debug_handler]
+async fn get_watches(
+    State(state): State<&'static UsrState>,
+    Query(ListWatches { order_id, name }): Query<ListWatches>,
+) -> Response {
+    let mut query = watch::Entity::find().order_by_asc(watch::Column::OrderId);
+    if let Some(order_id) = order_id {
+        match resolve_order(&state.db, &order_id).await {
+            Ok(id) => query = query.filter(watch::Column::OrderId.eq(id)),
+            Err(response) => return response.into_response(),
+        }
+    }
+    if let Some(name) = name {
+        query = query.filter(watch::Column::Name.eq(name));
+    }
+    match query.all(&state.db).await {
+        Ok(watches) => Json(watches).into_response(),
+        Err(e) => {
+            error!("Failed to get order watchers: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListTransfers {
+    #[serde(default)]
+    order_id: Option<OrderRef>,
+}
+
+/// The inventory transfer ledger, newest first
+#[axum::debug_handler]
+async fn get_transfers(
+    State(state): State<&'static UsrState>,
+    Query(ListTransfers { order_id }): Query<ListTransfers>,
+) -> Response {
+    let mut query = transfer::Entity::find().order_by_desc(transfer::Column::Id);
+    if let Some(order_id) = order_id {
+        match resolve_order(&state.db, &order_id).await {
+            Ok(id) => query = query.filter(transfer::Column::OrderId.eq(id)),
+            Err(response) => return response.into_response(),
+        }
+    }
+    match query.all(&state.db).await {
+        Ok(transfers) => Json(transfers).into_response(),
+        Err(e) => {
+            error!("Failed to get inventory transfers: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListInventory {
+    #[serde(default)]
+    location: Option<String>,
+}
+
+#[derive(Serialize)]
+struct StoredOrder {
+    #[serde(flatten)]
+    order: order::Model,
+    /// Where the order's stock is, its `store_in` first
+    placements: Vec<inventory::Placement>,
+}
+
+/// Orders in storage with where their stock is, only those with some of it in
+/// `location` if given
+#[axum::debug_handler]
+async fn get_inventory(
+    State(state): State<&'static UsrState>,
+    Query(ListInventory { location }): Query<ListInventory>,
+) -> Response {
+    let (stored, moved) = tokio::join!(
+        current::Entity::find()
+            .filter(current::Column::Status.eq(order_status::Status::InStorage))
+            .order_by_asc(current::Column::Id)
+            .all(&state.db),
+        stock::Entity::find().all(&state.db),
+    );
+    let (stored, moved) = match (stored, moved) {
+        (Ok(stored), Ok(moved)) => (stored, moved),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get inventory: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let mut moved_by_order = HashMap::<i64, Vec<stock::Model>>::new();
+    for model in moved {
+        moved_by_order.entry(model.order_id).or_default().push(model);
+    }
+    let location = location.as_deref().map(str::trim);
+    let inventory: Vec<_> = stored
+        .into_iter()
+        .filter_map(|current| {
+            let (order, _) = current.into_parts();
+            let moved = moved_by_order.remove(&order.id).unwrap_or_default();
+            let placements = inventory::placements(&order, &moved);
+            if let Some(location) = location {
+                if !placements
+                    .iter()
+                    .any(|placement| placement.location == location && placement.count > 0)
+                {
+                    return None;
+                }
+            }
+            Some(StoredOrder { order, placements })
+        })
+        .collect();
+    Json(inventory).into_response()
+}
+
+#[derive(Deserialize)]
+struct CheckoutInventory {
+    id: OrderRef,
+    /// Where it is taken from, the order's `store_in` by default
+    #[serde(default)]
+    location: Option<String>,
+    count: u32,
+    #[serde(default)]
+    taken_by: Option<String>,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+async fn take_stock(
+    db: &DatabaseConnection,
+    id: i64,
+    location: Option<String>,
+    count: u32,
+    taken_by: String,
+    note: Option<String>,
+) -> Result<Result<checkout::Model, String>, sea_orm::DbErr> {
+    let tx = db.begin().await?;
+    let Some(current) = current::Entity::find_by_id(id).one(&tx).await? else {
+        return Ok(Err("Order not found".to_string()));
+    };
+    let (model, status) = current.into_parts();
+    if status != order_status::Status::InStorage {
+        return Ok(Err(format!("Order {} is not in storage", model.number())));
+    }
+    let location = location.unwrap_or_else(|| model.store_in.clone());
+    let now = Local::now().naive_local();
+    let result = inventory::checkout(&tx, &model, &location, count.into(), taken_by, note, now).await?;
+    if result.is_ok() {
+        tx.commit().await?;
+    }
+    Ok(result)
+}
+
+/// Takes stored parts out of inventory to be used. Signed in users can only
+/// take them for themselves. Responds with the checkout.
+#[axum::debug_handler]
+async fn checkout_inventory(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(CheckoutInventory {
+        id,
+        location,
+        count,
+        taken_by,
+        note,
+    }): Json<CheckoutInventory>,
+) -> Response {
+    if count == 0 {
+        return ApiError::invalid("Count must be positive")
+            .for_field("count")
+            .into_response();
+    }
+    let Some(taken_by) = caller.name.or(non_blank(taken_by)) else {
+        return (StatusCode::BAD_REQUEST, "Name is required").into_response();
+    };
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let location = non_blank(location).map(|location| location.trim().to_string());
+    match take_stock(&state.db, id, location, count, taken_by, non_blank(note)).await {
+        Ok(Ok(model)) => {
+            backup_db(state);
+            Json(model).into_response()
+        }
+        Ok(Err(reason)) => (StatusCode::CONFLICT, reason).into_response(),
+        Err(e) => {
+            error!("Failed to check out inventory: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CheckinInventory {
+    checkout_id: u32,
+    /// Everything still out by default
+    #[serde(default)]
+    count: Option<u32>,
+}
+
+async fn return_stock(
+    db: &DatabaseConnection,
+    checkout_id: u32,
+    count: Option<u32>,
+) -> Result<Result<checkout::Model, String>, sea_orm::DbErr> {
+    let tx = db.begin().await?;
+    let Some(model) = checkout::Entity::find_by_id(checkout_id).one(&tx).await? else {
+        return Ok(Err("Checkout not found".to_string()));
+    };
+    let count = count.map_or(model.count - model.returned, i64::from);
+    let result = inventory::checkin(&tx, model, count, Local::now().naive_local()).await?;
+    if result.is_ok() {
+        tx.commit().await?;
+    }
+    Ok(result)
+}
+
+/// Puts parts that weren't used back where they were taken from. Responds with
+/// the updated checkout.
+#[axum::debug_handler]
+async fn checkin_inventory(
+    State(state): State<&'static UsrState>,
+    Json(CheckinInventory { checkout_id, count }): Json<CheckinInventory>,
+) -> Response {
+    if count == Some(0) {
+        return ApiError::invalid("Count must be positive")
+            .for_field("count")
+            .into_response();
+    }
+    match return_stock(&state.db, checkout_id, count).await {
+        Ok(Ok(model)) => {
+            backup_db(state);
+            Json(model).into_response()
+        }
+        Ok(Err(reason)) => (StatusCode::CONFLICT, reason).into_response(),
+        Err(e) => {
+            error!("Failed to check in inventory: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListCheckouts {
+    #[serde(default)]
+    order_id: Option<OrderRef>,
+    #[serde(default)]
+    taken_by: Option<String>,
+    /// Only those with some of what was taken still out
+    #[serde(default)]
+    open: bool,
+}
+
+/// Who took what out of inventory, newest first
+#[axum::debug_handler]
+async fn get_checkouts(
+    State(state): State<&'static UsrState>,
+    Query(ListCheckouts {
+        order_id,
+        taken_by,
+        open,
+    }): Query<ListCheckouts>,
+) -> Response {
+    let mut query = checkout::Entity::find().order_by_desc(checkout::Column::Id);
+    if let Some(order_id) = order_id {
+        match resolve_order(&state.db, &order_id).await {
+            Ok(id) => query = query.filter(checkout::Column::OrderId.eq(id)),
+            Err(response) => return response.into_response(),
+        }
+    }
+    if let Some(taken_by) = taken_by {
+        query = query.filter(checkout::Column::TakenBy.eq(taken_by));
+    }
+    if open {
+        query = query
+            .filter(Expr::col(checkout::Column::Returned).lt(Expr::col(checkout::Column::Count)));
+    }
+    match query.all(&state.db).await {
+        Ok(checkouts) => Json(checkouts).into_response(),
+        Err(e) => {
+            error!("Failed to get inventory checkouts: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+struct FundingSummary {
+    allocated: Decimal,
+    spent: Decimal,
+    remaining: Decimal,
+    /// What was spent with each payment method, for orders that have one
+    spent_by_method: HashMap<order::PaymentMethod, Decimal>,
+}
+
+#[axum::debug_handler]
+async fn get_funding(State(state): State<&'static UsrState>) -> Response {
+    let (budgets, orders, splits, unspent) = tokio::join!(
+        funding::Entity::find().all(&state.db),
+        order::Entity::find().all(&state.db),
+        budget::all_splits(&state.db),
+        budget::unspent_cancelled(&state.db),
+    );
+
+    let budgets = match budgets {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate funding budgets: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let orders = match orders {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to get orders: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let splits = match splits {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to get cost splits: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let unspent = match unspent {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to get cancelled orders: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let mut out = HashMap::<funding::Source, FundingSummary>::new();
+    for model in budgets {
+        out.entry(model.source).or_default().allocated = model.allocated;
+    }
+    for model in orders {
+        if unspent.contains(&model.id) {
+            continue;
+        }
+        let splits = splits.get(&model.id).map(Vec::as_slice).unwrap_or_default();
+        for share in budget::shares(&model, splits) {
+            let summary = out.entry(share.funding_source).or_default();
+            summary.spent += share.amount;
+            if let Some(method) = model.payment_method {
+                *summary.spent_by_method.entry(method).or_default() += share.amount;
+            }
+        }
+    }
+    for summary in out.values_mut() {
+        summary.remaining = summary.allocated - summary.spent;
+    }
+
+    Json(out).into_response()
+}
+
+/// Each funding source has its own reporting rules, so each gets its own
+/// column layout.
+/// Each order that `source` pays some of, with how much it pays
+fn funding_lines(
+    source: funding::Source,
+    orders: impl IntoIterator<Item = order::Model>,
+    splits: &HashMap<i64, Vec<cost_split::Model>>,
+) -> Vec<(order::Model, Decimal)> {
+    orders
+        .into_iter()
+        .filter_map(|model| {
+            let splits = splits.get(&model.id).map(Vec::as_slice).unwrap_or_default();
+            let paid: Decimal = budget::shares(&model, splits)
+                .into_iter()
+                .filter(|share| share.funding_source == source)
+                .map(|share| share.amount)
+                .sum();
+            let pays = model.funding_source == source
+                || splits.iter().any(|split| split.funding_source == source);
+            pays.then_some((model, paid))
+        })
+        .collect()
+}
+
+fn write_funding_csv(
+    source: funding::Source,
+    orders: Vec<(order::Model, Decimal)>,
+    created: &HashMap<i64, NaiveDateTime>,
+) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+
+    match source {
+        funding::Source::DepartmentGrant => {
+            writer.write_record([
+                "Ref Number",
+                "Date",
+                "Vendor",
+                "Item",
+                "Quantity",
+                "Unit Cost",
+                "Total",
+                "Team",
+                "Payment Method",
+            ])?;
+        }
+        funding::Source::Sponsor => {
+            writer.write_record(["Item", "Vendor", "Total", "Team", "Reason", "Payment Method"])?;
+        }
+        funding::Source::ClubDues => {
+            writer.write_record(["Date", "Item", "Vendor", "Total", "Payment Method"])?;
+        }
+    }
+
+    for (model, total) in orders {
+        let date = created
+            .get(&model.id)
+            .map(|date| date.date().to_string())
+            .unwrap_or_default();
+        let total = total.to_string();
+        let payment_method = model
+            .payment_method
+            .map(|x| x.to_string())
+            .unwrap_or_default();
+
+        match source {
+            funding::Source::DepartmentGrant => {
+                writer.write_record([
+                    model.ref_number.map(|x| x.to_string()).unwrap_or_default(),
+                    date,
+                    model.vendor,
+                    model.name,
+                    model.count.to_string(),
+                    model.unit_cost.to_string(),
+                    total,
+                    model.team.to_string(),
+                    payment_method,
+                ])?;
+            }
+            funding::Source::Sponsor => {
+                writer.write_record([
+                    model.name,
+                    model.vendor,
+                    total,
+                    model.team.to_string(),
+                    model.reason,
+                    payment_method,
+                ])?;
+            }
+            funding::Source::ClubDues => {
+                writer.write_record([date, model.name, model.vendor, total, payment_method])?;
+            }
+        }
+    }
+
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+#[axum::debug_handler]
+async fn export_funding(
+    State(state): State<&'static UsrState>,
+    Path(source): Path<funding::Source>,
+) -> Response {
+    let (orders, statuses, splits) = tokio::join!(
+        order::Entity::find()
+            .order_by_asc(order::Column::Id)
+            .all(&state.db),
+        order_status::Entity::find()
+            .filter(order_status::Column::Status.eq(order_status::Status::New))
+            .all(&state.db),
+        budget::all_splits(&state.db),
+    );
+
+    let orders = match orders {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to get orders: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let created: HashMap<_, _> = match statuses {
+        Ok(x) => x
+            .into_iter()
+            .map(|model| (model.order_id, model.date))
+            .collect(),
+        Err(e) => {
+            error!("Failed to get order statuses: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let splits = match splits {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to get cost splits: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    match write_funding_csv(source, funding_lines(source, orders, &splits), &created) {
+        Ok(data) => (
+            [
+                (header::CONTENT_TYPE, "text/csv".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{source}.csv\""),
+                ),
+            ],
+            data,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to write funding export: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Vendors that accept the university's tax exemption, and how often it went
+/// unused with them.
+#[axum::debug_handler]
+async fn get_tax_exemptions(State(state): State<&'static UsrState>) -> Response {
+    match tax::vendor_exemptions(&state.db).await {
+        Ok(vendors) => Json(vendors).into_response(),
+        Err(e) => {
+            error!("Failed to summarize tax exemptions: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TaxBundle {
+    year: Option<i32>,
+    term: Option<tax::Term>,
+}
+
+/// The exemption certificate cover sheet for a semester, the current one by
+/// default.
+#[axum::debug_handler]
+async fn export_tax_bundle(
+    State(state): State<&'static UsrState>,
+    Query(TaxBundle { year, term }): Query<TaxBundle>,
+) -> Response {
+    let semester = match (year, term) {
+        (Some(year), Some(term)) => tax::Semester { year, term },
+        (None, None) => tax::Semester::containing(Local::now().date_naive()),
+        _ => {
+            return (StatusCode::BAD_REQUEST, "Both year and term must be given")
+                .into_response()
+        }
+    };
+    match tax::bundle(&state.db, semester).await {
+        Ok(data) => (
+            [
+                (header::CONTENT_TYPE, "text/csv".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"tax-exempt-{}.csv\"", semester.name()),
+                ),
+            ],
+            data,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to write tax exemption bundle: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// One row per status transition, with the order it belongs to, for lead
+/// time analysis outside of the app.
+fn write_status_csv(
+    statuses: Vec<order_status::Model>,
+    orders: &HashMap<i64, order::Model>,
+) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record([
+        "order_id",
+        "ref_number",
+        "name",
+        "vendor",
+        "team",
+        "status",
+        "date",
+    ])?;
+    for model in statuses {
+        let Some(order) = orders.get(&model.order_id) else {
+            continue;
+        };
+        writer.write_record([
+            model.order_id.to_string(),
+            order.ref_number.map(|x| x.to_string()).unwrap_or_default(),
+            order.name.clone(),
+            order.vendor.clone(),
+            order.team.to_string(),
+            format!("{:?}", model.status),
+            model.date.format("%Y-%m-%d %H:%M:%S").to_string(),
+        ])?;
+    }
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+/// Every export of the orders placed in `season`, named as they would be if
+/// downloaded separately, for the season archive.
+pub async fn season_reports(
+    db: &DatabaseConnection,
+    season: u16,
+) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let orders = order::Entity::find()
+        .filter(order::Column::Season.eq(season))
+        .order_by_asc(order::Column::Id)
+        .all(db)
+        .await?;
+    let orders_by_id: HashMap<_, _> = orders.iter().map(|x| (x.id, x.clone())).collect();
+    let statuses: Vec<_> = order_status::Entity::find()
+        .order_by_asc(order_status::Column::OrderId)
+        .order_by_asc(order_status::Column::InstanceId)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|x| orders_by_id.contains_key(&x.order_id))
+        .collect();
+    let created: HashMap<_, _> = statuses
+        .iter()
+        .filter(|x| x.status == order_status::Status::New)
+        .map(|x| (x.order_id, x.date))
+        .collect();
+
+    let splits = budget::all_splits(db).await?;
+
+    let mut reports = vec![];
+    for source in funding::Source::iter() {
+        let lines = funding_lines(source, orders.iter().cloned(), &splits);
+        reports.push((
+            format!("{source}.csv"),
+            write_funding_csv(source, lines, &created)?,
+        ));
+    }
+    reports.push((
+        "status-history.csv".to_string(),
+        write_status_csv(statuses, &orders_by_id)?,
+    ));
+    for term in [tax::Term::Spring, tax::Term::Fall] {
+        let semester = tax::Semester {
+            year: season.into(),
+            term,
+        };
+        reports.push((
+            format!("tax-exempt-{}.csv", semester.name()),
+            tax::bundle(db, semester).await?,
+        ));
+    }
+    Ok(reports)
+}
+
+#[axum::debug_handler]
+async fn export_statuses(State(state): State<&'static UsrState>) -> Response {
+    let (orders, statuses) = tokio::join!(
+        order::Entity::find().all(&state.db),
+        order_status::Entity::find()
+            .order_by_asc(order_status::Column::OrderId)
+            .order_by_asc(order_status::Column::InstanceId)
+            .all(&state.db),
+    );
+
+    let orders: HashMap<_, _> = match orders {
+        Ok(x) => x.into_iter().map(|model| (model.id, model)).collect(),
+        Err(e) => {
+            error!("Failed to get orders: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let statuses = match statuses {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to get order statuses: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    match write_status_csv(statuses, &orders) {
+        Ok(data) => (
+            [
+                (header::CONTENT_TYPE, "text/csv"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"statuses.csv\"",
+                ),
+            ],
+            data,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to write status export: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+}
+
+#[derive(Deserialize)]
+struct ExportOrders {
+    #[serde(default)]
+    format: ExportFormat,
+    /// Only the orders placed in this month, eg. `2026-09`
+    #[serde(default)]
+    month: Option<String>,
+    #[serde(default)]
+    team: Option<scheduler::Team>,
+}
+
+/// Each order with the date it first reached every status, in the layout the
+/// reimbursement office asks for each month
+fn order_export_rows(
+    orders: Vec<order::Model>,
+    timelines: &HashMap<i64, HashMap<order_status::Status, NaiveDateTime>>,
+) -> Vec<Vec<xlsx::Cell>> {
+    use xlsx::Cell;
+
+    let mut header: Vec<_> = [
+        "Order ID",
+        "Number",
+        "Name",
+        "Vendor",
+        "Link",
+        "Count",
+        "Unit Cost",
+        "Subtotal",
+        "Team",
+        "Ref Number",
+    ]
+    .into_iter()
+    .map(|x| Cell::Text(x.to_string()))
+    .collect();
+    header.extend(order_status::Status::iter().map(|status| Cell::Text(status.to_string())));
+
+    let mut rows = vec![header];
+    for model in orders {
+        let timeline = timelines.get(&model.id);
+        let mut row = vec![
+            Cell::Number(model.id.into()),
+            Cell::Text(model.number()),
+            Cell::Text(model.name),
+            Cell::Text(model.vendor),
+            Cell::Text(model.link),
+            Cell::Number(model.count.into()),
+            Cell::Number(model.unit_cost),
+            Cell::Number(money::subtotal(model.count, model.unit_cost)),
+            Cell::Text(model.team.to_string()),
+            match model.ref_number {
+                Some(ref_number) => Cell::Number(ref_number.into()),
+                None => Cell::Text(String::new()),
+            },
+        ];
+        row.extend(order_status::Status::iter().map(|status| {
+            Cell::Text(
+                timeline
+                    .and_then(|timeline| timeline.get(&status))
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default(),
+            )
+        }));
+        rows.push(row);
+    }
+    rows
+}
+
+fn write_order_csv(rows: Vec<Vec<xlsx::Cell>>) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for row in rows {
+        writer.write_record(row.into_iter().map(|cell| match cell {
+            xlsx::Cell::Text(text) => text,
+            xlsx::Cell::Number(number) => number.to_string(),
+        }))?;
+    }
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+/// Every order, or those placed in `month`, as a CSV or XLSX for
+/// reimbursement paperwork
+#[axum::debug_handler]
+async fn export_orders(
+    State(state): State<&'static UsrState>,
+    Query(ExportOrders {
+        format,
+        month,
+        team,
+    }): Query<ExportOrders>,
+) -> Response {
+    let month = match month {
+        Some(month) => match NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d") {
+            Ok(start) => Some(start),
+            Err(_) => {
+                return (StatusCode::BAD_REQUEST, "Month must look like 2026-09").into_response()
+            }
+        },
+        None => None,
+    };
+
+    let mut query = order::Entity::find().order_by_asc(order::Column::Id);
+    if let Some(team) = team {
+        query = query.filter(order::Column::Team.eq(team));
+    }
+    let (orders, statuses) = tokio::join!(
+        query.all(&state.db),
+        order_status::Entity::find()
+            .order_by_asc(order_status::Column::InstanceId)
+            .all(&state.db),
+    );
+    let orders = match orders {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to get orders: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let mut timelines: HashMap<i64, HashMap<_, _>> = HashMap::new();
+    match statuses {
+        Ok(statuses) => {
+            for model in statuses {
+                timelines
+                    .entry(model.order_id)
+                    .or_default()
+                    .entry(model.status)
+                    .or_insert(model.date);
+            }
+        }
+        Err(e) => {
+            error!("Failed to get order statuses: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    }
+
+    let orders: Vec<_> = match month {
+        Some(start) => orders
+            .into_iter()
+            .filter(|model| {
+                timelines
+                    .get(&model.id)
+                    .and_then(|timeline| timeline.get(&order_status::Status::New))
+                    .is_some_and(|placed| {
+                        placed.year() == start.year() && placed.month() == start.month()
+                    })
+            })
+            .collect(),
+        None => orders,
+    };
+    let name = match month {
+        Some(start) => format!("orders-{}", start.format("%Y-%m")),
+        None => "orders".to_string(),
+    };
+
+    let rows = order_export_rows(orders, &timelines);
+    let result = match format {
+        ExportFormat::Csv => write_order_csv(rows)
+            .map(|data| ("text/csv", data))
+            .map_err(anyhow::Error::from),
+        ExportFormat::Xlsx => xlsx::write("Orders", rows)
+            .map(|data| {
+                (
+                    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                    data,
+                )
+            })
+            .map_err(anyhow::Error::from),
+    };
+    let extension = match format {
+        ExportFormat::Csv => "csv",
+        ExportFormat::Xlsx => "xlsx",
+    };
+    match result {
+        Ok((content_type, data)) => (
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{name}.{extension}\""),
+                ),
+            ],
+            data,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to write order export: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+pub async fn get_order(db: &DatabaseConnection, id: i64) -> Result<Option<Order>, sea_orm::DbErr> {
+    order::Entity::find_by_id(id).one(db).await
+}
+
+/// An order's item, how many were ordered, and where it is (or will be) stored.
+pub struct OrderedItem {
+    pub name: String,
+    pub count: i64,
+    pub location: String,
+}
+
+async fn items_where(
+    db: &DatabaseConnection,
+    condition: SimpleExpr,
+) -> Result<Vec<OrderedItem>, sea_orm::DbErr> {
+    Ok(current::Entity::find()
+        .filter(condition)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| OrderedItem {
+            name: model.name,
+            count: model.count,
+            location: model.store_in,
+        })
+        .collect())
+}
+
+/// Lists every order whose latest status is `InStorage`.
+pub async fn stored_items(db: &DatabaseConnection) -> Result<Vec<OrderedItem>, sea_orm::DbErr> {
+    items_where(db, current::Column::Status.eq(order_status::Status::InStorage)).await
+}
+
+/// Lists every order that has been placed but has not reached storage yet,
+/// leaving out cancelled ones.
+pub async fn in_flight_items(db: &DatabaseConnection) -> Result<Vec<OrderedItem>, sea_orm::DbErr> {
+    items_where(
+        db,
+        current::Column::Status.is_not_in([
+            order_status::Status::InStorage,
+            order_status::Status::Returned,
+            order_status::Status::Cancelled,
+        ]),
+    )
+    .await
+}
+
+/// Sums the subtotals of every order linked to a component, along with the
+/// ids of those orders, leaving out those cancelled before they were bought.
+pub async fn component_costs(
+    db: &DatabaseConnection,
+) -> Result<HashMap<i64, (Decimal, Vec<i64>)>, sea_orm::DbErr> {
+    let unspent = budget::unspent_cancelled(db).await?;
+    let mut out = HashMap::<i64, (Decimal, Vec<i64>)>::new();
+    for model in order::Entity::find()
+        .filter(order::Column::ComponentId.is_not_null())
+        .all(db)
+        .await?
+    {
+        if unspent.contains(&model.id) {
+            continue;
+        }
+        let Some(component_id) = model.component_id else {
+            continue;
+        };
+        let (cost, orders) = out.entry(component_id).or_default();
+        *cost += money::subtotal(model.count, model.unit_cost);
+        orders.push(model.id);
+    }
+    Ok(out)
+}
+
+async fn vendor_lead_times(
+    db: &DatabaseConnection,
+) -> Result<HashMap<String, lead_time::VendorLeadTime>, sea_orm::DbErr> {
+    let (orders, statuses) = tokio::join!(
+        order::Entity::find().all(db),
+        order_status::Entity::find().all(db),
+    );
+    Ok(lead_time::compute(&orders?, &statuses?))
+}
+
+#[axum::debug_handler]
+async fn get_lead_times(State(state): State<&'static UsrState>) -> Response {
+    match vendor_lead_times(&state.db).await {
+        Ok(lead_times) => {
+            let mut lead_times: Vec<_> = lead_times.into_values().collect();
+            lead_times.sort_by(|a, b| a.vendor.cmp(&b.vendor));
+            Json(lead_times).into_response()
+        }
+        Err(e) => {
+            error!("Failed to compute vendor lead times: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Eta {
+    order_id: i64,
+    name: String,
+    vendor: String,
+    ordered: NaiveDateTime,
+    predicted: NaiveDateTime,
+    predicted_late: NaiveDateTime,
+    vendor_degraded: bool,
+}
+
+/// Predicted delivery dates for every order that has been placed but has not
+/// arrived yet.
+#[axum::debug_handler]
+async fn get_etas(State(state): State<&'static UsrState>) -> Response {
+    let (orders, statuses) = tokio::join!(
+        order::Entity::find().all(&state.db),
+        order_status::Entity::find().all(&state.db),
+    );
+
+    let (orders, statuses) = match (orders, statuses) {
+        (Ok(orders), Ok(statuses)) => (orders, statuses),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get orders: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let lead_times = lead_time::compute(&orders, &statuses);
+    let mut latest = HashMap::<i64, &order_status::Model>::new();
+    for model in &statuses {
+        match latest.entry(model.order_id) {
+            Entry::Occupied(mut occupied_entry) => {
+                if occupied_entry.get().instance_id < model.instance_id {
+                    occupied_entry.insert(model);
+                }
+            }
+            Entry::Vacant(vacant_entry) => {
+                vacant_entry.insert(model);
+            }
+        }
+    }
+
+    let etas: Vec<_> = orders
+        .into_iter()
+        .filter_map(|model| {
+            let status = latest.get(&model.id)?;
+            if !matches!(
+                status.status,
+                order_status::Status::Submitted
+                    | order_status::Status::Shipped
+                    | order_status::Status::PartiallyReceived
+            ) {
+                return None;
+            }
+            let ordered = statuses
+                .iter()
+                .filter(|x| x.order_id == model.id && x.status == order_status::Status::Submitted)
+                .map(|x| x.date)
+                .min()?;
+            let lead_time = lead_times.get(&lead_time::vendor_key(&model.vendor))?;
+            Some(Eta {
+                order_id: model.id,
+                name: model.name,
+                vendor: model.vendor,
+                ordered,
+                predicted: lead_time.predict(ordered),
+                predicted_late: lead_time.predict_late(ordered),
+                vendor_degraded: lead_time.degraded,
+            })
+        })
+        .collect();
+
+    Json(etas).into_response()
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum SuggestField {
+    Vendor,
+    Name,
+}
+
+#[derive(Deserialize)]
+struct SuggestQuery {
+    field: SuggestField,
+    q: String,
+    #[serde(default = "default_suggest_limit")]
+    limit: usize,
+}
+
+fn default_suggest_limit() -> usize {
+    10
+}
+
+#[derive(Serialize)]
+struct Suggestion {
+    value: String,
+    uses: usize,
+    score: f64,
+    /// For vendors with a policy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    standing: Option<vendor_policy::Standing>,
+}
+
+/// Scores how well `candidate` matches what the user has typed so far, with
+/// prefix and substring matches ranking above plain similarity.
+fn suggestion_score(query: &str, candidate: &str) -> f64 {
+    let similarity = strsim::jaro_winkler(query, candidate);
+    if candidate.starts_with(query) {
+        2.0 + similarity
+    } else if candidate.contains(query) {
+        1.0 + similarity
+    } else {
+        similarity
+    }
+}
+
+/// Suggests previously used vendors or item names so the frontend can
+/// autocomplete and keep spellings consistent for reporting.
+#[axum::debug_handler]
+async fn suggest(
+    State(state): State<&'static UsrState>,
+    Query(SuggestQuery { field, q, limit }): Query<SuggestQuery>,
+) -> Response {
+    let query = q.trim().to_lowercase();
+    if query.is_empty() {
+        return Json(Vec::<Suggestion>::new()).into_response();
+    }
+    let (orders, policies) = tokio::join!(
+        order::Entity::find().all(&state.db),
+        vendor_policy::Entity::find().all(&state.db),
+    );
+    let (orders, policies) = match (orders, policies) {
+        (Ok(orders), Ok(policies)) => (orders, policies),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get orders: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let standings: HashMap<_, _> = match field {
+        SuggestField::Vendor => policies
+            .into_iter()
+            .map(|model| (model.key, model.standing))
+            .collect(),
+        SuggestField::Name => HashMap::new(),
+    };
+
+    // Spellings that only differ by case or whitespace are grouped, and the
+    // most used spelling is suggested
+    let mut spellings = HashMap::<String, HashMap<String, usize>>::new();
+    for model in orders {
+        let value = match field {
+            SuggestField::Vendor => model.vendor,
+            SuggestField::Name => model.name,
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        *spellings
+            .entry(value.to_lowercase())
+            .or_default()
+            .entry(value.to_string())
+            .or_default() += 1;
+    }
+
+    let mut suggestions: Vec<_> = spellings
+        .into_iter()
+        .filter_map(|(key, spellings)| {
+            let score = suggestion_score(&query, &key);
+            if score < 0.7 {
+                return None;
+            }
+            let standing = standings.get(&key).copied();
+            // Nobody should be steered towards a blocked vendor
+            if standing == Some(vendor_policy::Standing::Blocked) {
+                return None;
+            }
+            let uses = spellings.values().sum();
+            let (value, _) = spellings.into_iter().max_by_key(|(_, uses)| *uses)?;
+            Some(Suggestion {
+                value,
+                uses,
+                score,
+                standing,
+            })
+        })
+        .collect();
+    // Preferred vendors come first, however well the others match
+    suggestions.sort_by(|a, b| {
+        let preferred = |x: &Suggestion| x.standing == Some(vendor_policy::Standing::Preferred);
+        preferred(b)
+            .cmp(&preferred(a))
+            .then(b.score.total_cmp(&a.score))
+            .then(b.uses.cmp(&a.uses))
+    });
+    suggestions.truncate(limit.min(50));
+
+    Json(suggestions).into_response()
+}
+
+/// Brings the cached views of the orders up to date after `team`'s orders
+/// changed, or every team's if `None`.
+async fn orders_changed(state: &'static UsrState, team: Option<scheduler::Team>) {
+    state.typeahead.invalidate();
+    if let Err(e) = state.rollups.refresh(&state.db, team).await {
+        error!("Failed to refresh team rollups: {e}");
+    }
+    state.dashboard.invalidate();
+}
+
+/// Tells live subscribers about `kind` of event on `order`, whose status is
+/// now `status`
+async fn publish_event(
+    state: &'static UsrState,
+    kind: events::EventKind,
+    order: &order::Model,
+    status: order_status::Status,
+) {
+    if !state.order_events.has_subscribers() {
+        return;
+    }
+    let project = match order.component_id {
+        Some(component_id) => registry::component_subsystem(&state.db, component_id)
+            .await
+            .unwrap_or_else(|e| {
+                error!("Failed to find component: {e}");
+                None
+            }),
+        None => None,
+    };
+    state.order_events.publish(events::OrderEvent {
+        kind,
+        order_id: order.id,
+        number: order.number(),
+        name: order.name.clone(),
+        team: order.team,
+        status,
+        project,
+    });
+}
+
+/// Like [`publish_event`], for order `id` as it is now
+async fn publish_current(state: &'static UsrState, kind: events::EventKind, id: i64) {
+    if !state.order_events.has_subscribers() {
+        return;
+    }
+    match current::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(current)) => {
+            let (order, status) = current.into_parts();
+            publish_event(state, kind, &order, status).await;
+        }
+        Ok(None) => {}
+        Err(e) => error!("Failed to find order: {e}"),
+    }
+}
+
+#[axum::debug_handler]
+async fn get_team_stats(State(state): State<&'static UsrState>) -> Response {
+    Json(state.rollups.get()).into_response()
+}
+
+#[derive(Deserialize)]
+struct TypeaheadQuery {
+    q: String,
+    #[serde(default = "default_typeahead_limit")]
+    limit: usize,
+}
+
+fn default_typeahead_limit() -> usize {
+    8
+}
 
-use crate::{backup::backup_db, scheduler, UsrState};
+/// Autocompletes item names on the new order form from past orders and what
+/// is in storage. Served from an in-memory index, so it is cheap to call on
+/// every keystroke.
+#[axum::debug_handler]
+async fn typeahead(
+    State(state): State<&'static UsrState>,
+    Query(TypeaheadQuery { q, limit }): Query<TypeaheadQuery>,
+) -> Response {
+    match state.typeahead.search(&state.db, &q, limit.min(20)).await {
+        Ok(matches) => Json(matches).into_response(),
+        Err(e) => {
+            error!("Failed to search orders: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
 
-mod order;
-mod order_status;
+#[derive(Serialize)]
+struct Permalink {
+    url: String,
+}
 
-#[derive(Deserialize)]
-pub struct PendingOrder {
-    pub name: String,
-    pub count: u32,
-    pub unit_cost: Decimal,
-    pub store_in: String,
-    pub team: scheduler::Team,
-    pub reason: String,
-    pub vendor: String,
-    pub link: String,
+/// The order's page in the web UI, so that the bot and emails link to orders
+/// the same way the webhooks do
+#[axum::debug_handler]
+async fn get_permalink(
+    State(state): State<&'static UsrState>,
+    Path(id): Path<OrderRef>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let model = match order::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(model)) => model,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    match permalink::url(&model) {
+        Some(url) => Json(Permalink { url }).into_response(),
+        None => (StatusCode::NOT_FOUND, "web_url is not configured").into_response(),
+    }
 }
 
+/// Regenerates the message for the order's current state and posts it again,
+/// for when the webhook was misconfigured at the time of the original event.
 #[axum::debug_handler]
-async fn new_order(
+async fn notify_order(
     State(state): State<&'static UsrState>,
-    Json(pending_order): Json<PendingOrder>,
+    Path(id): Path<OrderRef>,
 ) -> (StatusCode, &'static str) {
-    let webhook_msg = format!(
-        "**New Order!**\n**Name:** {}\n**Vendor:** {}\n**Link:** {}\n**Count:** {}\n**Unit Cost:** ${}\n**Subtotal:** ${}\n**Team:** {}\n**Reason:** {}",
-        pending_order.name,
-        pending_order.vendor,
-        pending_order.link,
-        pending_order.count,
-        pending_order.unit_cost,
-        Decimal::from(pending_order.count) * pending_order.unit_cost,
-        pending_order.team,
-        pending_order.reason
-    );
-    let active_model = order::ActiveModel {
-        id: ActiveValue::NotSet,
-        name: ActiveValue::Set(pending_order.name),
-        count: ActiveValue::Set(pending_order.count),
-        unit_cost: ActiveValue::Set(pending_order.unit_cost),
-        store_in: ActiveValue::Set(pending_order.store_in),
-        team: ActiveValue::Set(pending_order.team),
-        reason: ActiveValue::Set(pending_order.reason),
-        vendor: ActiveValue::Set(pending_order.vendor),
-        link: ActiveValue::Set(pending_order.link),
-        ref_number: ActiveValue::NotSet,
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response,
     };
-    let result = state
-        .db
-        .transaction(|tx| {
-            Box::pin(async move {
-                let model = active_model.insert(tx).await?;
+    let current = match current::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(current)) => current,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found"),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "");
+        }
+    };
+    let date = current.status_date;
+    let (order, status) = current.into_parts();
 
-                let active_model = order_status::ActiveModel {
-                    order_id: ActiveValue::Set(model.id),
-                    instance_id: ActiveValue::NotSet,
-                    date: ActiveValue::Set(Local::now().naive_local()),
-                    status: ActiveValue::Set(order_status::Status::New),
-                };
+    if status == order_status::Status::New {
+        if !state.notifier.routes(Topic::NewOrder) {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Nothing is set up to receive new orders",
+            );
+        }
+        state.notifier.send_for(
+            order.team,
+            Topic::NewOrder,
+            order.id as u32,
+            new_order_webhook_msg(&order, None, None, None),
+        );
+    } else {
+        if !state.notifier.routes(Topic::OrderUpdate) {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Nothing is set up to receive order updates",
+            );
+        }
+        let mut webhook_msg = order_update_webhook_msg(&state.db, &order, status, date).await;
+        webhook_msg.push_str(&permalink::line(&order));
+        state
+            .notifier
+            .send_for(order.team, Topic::OrderUpdate, order.id as u32, webhook_msg);
+    }
 
-                active_model.insert(tx).await?;
+    (StatusCode::OK, "")
+}
 
-                Result::<_, sea_orm::DbErr>::Ok(model)
-            })
+#[derive(Deserialize)]
+struct ClosePeriod {
+    /// Name of the period being opened, eg. `Spring 2025`
+    name: String,
+}
+
+/// Closes the open budget period and opens the next one, carrying balances
+/// over if configured to, and announces the new allocations.
+#[axum::debug_handler]
+async fn close_period(
+    State(state): State<&'static UsrState>,
+    Json(ClosePeriod { name }): Json<ClosePeriod>,
+) -> Response {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "The new period needs a name").into_response();
+    }
+    let now = Local::now().naive_local();
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(budget::roll_over(
+                tx,
+                name,
+                &state.team_budgets,
+                state.carry_over_budgets,
+                now,
+            ))
         })
         .await;
 
     match result {
-        Ok(m) => {
+        Ok(rollover) => {
             backup_db(state);
-            state
-                .new_orders_webhook
-                .as_ref()
-                .map(|x| x.enqueue(m.id, webhook_msg));
-            (StatusCode::OK, "")
+            // Counted down from the top so as not to share a key with the
+            // weekly summaries, which use the week number
+            state.notifier.send(
+                Topic::Spending,
+                u32::MAX - rollover.opened_id(),
+                rollover.message(),
+            );
+            Json(rollover).into_response()
         }
         Err(e) => {
-            error!("Failed to create new order: {e}");
-            (StatusCode::INTERNAL_SERVER_ERROR, "")
+            error!("Failed to roll over budget period: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PeriodSummary {
+    #[serde(flatten)]
+    period: budget_period::Model,
+    totals: Vec<period_total::Model>,
+}
+
+/// Every budget period, newest first, with each team's totals
+#[axum::debug_handler]
+async fn get_periods(State(state): State<&'static UsrState>) -> Response {
+    let (periods, totals) = tokio::join!(
+        budget_period::Entity::find()
+            .order_by_desc(budget_period::Column::Start)
+            .all(&state.db),
+        period_total::Entity::find()
+            .order_by_asc(period_total::Column::Team)
+            .all(&state.db),
+    );
+    let (periods, totals) = match (periods, totals) {
+        (Ok(periods), Ok(totals)) => (periods, totals),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get budget periods: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
         }
+    };
+    let mut totals_by_period = HashMap::<i64, Vec<period_total::Model>>::new();
+    for total in totals {
+        totals_by_period.entry(total.period_id).or_default().push(total);
     }
+    Json(
+        periods
+            .into_iter()
+            .map(|period| PeriodSummary {
+                totals: totals_by_period.remove(&period.id).unwrap_or_default(),
+                period,
+            })
+            .collect::<Vec<_>>(),
+    )
+    .into_response()
 }
 
 #[derive(Deserialize)]
-pub struct ChangeOrder {
-    pub id: u32,
-    pub name: String,
-    pub count: u32,
-    pub unit_cost: Decimal,
-    pub store_in: String,
-    pub team: scheduler::Team,
-    pub reason: String,
-    pub vendor: String,
-    pub link: String,
+struct SetBudget {
+    team: scheduler::Team,
+    /// Defaults to the current season
+    #[serde(default)]
+    season: Option<u16>,
+    allocated: Decimal,
+    overrun: season_budget::Overrun,
 }
 
+/// Sets what a team can commit to orders over a season
 #[axum::debug_handler]
-async fn change_order(
+async fn set_budget(
     State(state): State<&'static UsrState>,
-    Json(change_order): Json<ChangeOrder>,
+    Json(SetBudget {
+        team,
+        season,
+        allocated,
+        overrun,
+    }): Json<SetBudget>,
 ) -> (StatusCode, &'static str) {
-    match order_status::Entity::find()
-        .filter(order_status::Column::OrderId.eq(change_order.id))
-        .order_by_desc(order_status::Column::InstanceId)
-        .one(&state.db)
-        .await
-    {
-        Ok(Some(model)) => {
-            if model.status != order_status::Status::New {
-                return (StatusCode::BAD_REQUEST, "Order has already been processed");
-            }
-        }
-        Ok(None) => {
-            return (StatusCode::BAD_REQUEST, "Order not found");
-        }
-        Err(e) => {
-            error!("Failed to find order: {e}");
-            return (StatusCode::INTERNAL_SERVER_ERROR, "");
-        }
+    if allocated.is_sign_negative() {
+        return (StatusCode::BAD_REQUEST, "Budget cannot be negative");
     }
-    let webhook_msg = format!(
-        "***Order Changed***\n**Name:** {}\n**Vendor:** {}\n**Link:** {}\n**Count:** {}\n**Unit Cost:** ${}\n**Subtotal:** ${}\n**Team:** {}\n**Reason:** {}",
-        change_order.name,
-        change_order.vendor,
-        change_order.link,
-        change_order.count,
-        change_order.unit_cost,
-        Decimal::from(change_order.count) * change_order.unit_cost,
-        change_order.team,
-        change_order.reason
-    );
-    let active_model = order::ActiveModel {
-        id: ActiveValue::Unchanged(change_order.id),
-        name: ActiveValue::Set(change_order.name),
-        count: ActiveValue::Set(change_order.count),
-        unit_cost: ActiveValue::Set(change_order.unit_cost),
-        store_in: ActiveValue::Set(change_order.store_in),
-        team: ActiveValue::Set(change_order.team),
-        reason: ActiveValue::Set(change_order.reason),
-        vendor: ActiveValue::Set(change_order.vendor),
-        link: ActiveValue::Set(change_order.link),
-        ref_number: ActiveValue::NotSet,
-    };
-    if let Err(e) = active_model.update(&state.db).await {
-        error!("Failed to change order: {e}");
+    let result = season_budget::Entity::insert(season_budget::ActiveModel {
+        team: ActiveValue::Set(team),
+        season: ActiveValue::Set(season.unwrap_or_else(current_season).into()),
+        allocated: ActiveValue::Set(allocated),
+        overrun: ActiveValue::Set(overrun),
+    })
+    .on_conflict(
+        OnConflict::columns([season_budget::Column::Team, season_budget::Column::Season])
+            .update_columns([season_budget::Column::Allocated, season_budget::Column::Overrun])
+            .to_owned(),
+    )
+    .exec(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to set season budget: {e}");
         (StatusCode::INTERNAL_SERVER_ERROR, "")
     } else {
         backup_db(state);
-        state
-            .new_orders_webhook
-            .as_ref()
-            .map(|x| x.enqueue(change_order.id, webhook_msg));
         (StatusCode::OK, "")
     }
 }
 
 #[derive(Deserialize)]
-struct DeleteOrder {
-    id: u32,
+struct DeleteBudget {
+    team: scheduler::Team,
     #[serde(default)]
-    force: bool,
+    season: Option<u16>,
 }
 
 #[axum::debug_handler]
-async fn cancel_order(
+async fn del_budget(
     State(state): State<&'static UsrState>,
-    Json(DeleteOrder { id, force }): Json<DeleteOrder>,
+    Json(DeleteBudget { team, season }): Json<DeleteBudget>,
 ) -> (StatusCode, &'static str) {
-    let webhook_msg;
-
-    match order_status::Entity::find()
-        .filter(order_status::Column::OrderId.eq(id))
-        .order_by_desc(order_status::Column::InstanceId)
-        .one(&state.db)
+    let season = season.unwrap_or_else(current_season);
+    match season_budget::Entity::delete_by_id((team, season.into()))
+        .exec(&state.db)
         .await
     {
-        Ok(Some(model)) => {
-            if !force && model.status != order_status::Status::New {
-                return (StatusCode::BAD_REQUEST, "Order has already been processed");
-            }
-            let model = match order::Entity::find_by_id(id).one(&state.db).await {
-                Ok(Some(model)) => model,
-                Ok(None) => unreachable!(),
-                Err(e) => {
-                    error!("Failed to find order: {e}");
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "");
-                }
-            };
-            webhook_msg = format!(
-                "***Order Cancelled***\n**Name:** {}\n**Count:** {}\n**Team:** {}",
-                model.name, model.count, model.team,
-            );
-        }
-        Ok(None) => {
-            return (StatusCode::BAD_REQUEST, "Order not found");
+        Ok(result) if result.rows_affected == 0 => (StatusCode::BAD_REQUEST, "Budget not found"),
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "")
         }
         Err(e) => {
-            error!("Failed to find order: {e}");
-            return (StatusCode::INTERNAL_SERVER_ERROR, "");
+            error!("Failed to delete season budget: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
         }
     }
+}
 
-    if force {
-        let result = state
-            .db
-            .transaction(|tx| {
-                Box::pin(async move {
-                    order::Entity::delete_by_id(id).exec(tx).await?;
-                    order_status::Entity::delete_many()
-                        .filter(order_status::Column::OrderId.eq(id))
-                        .exec(tx)
-                        .await?;
-                    Result::<_, sea_orm::DbErr>::Ok(())
-                })
-            })
-            .await;
+#[derive(Deserialize)]
+struct ListBudgets {
+    #[serde(default)]
+    season: Option<u16>,
+}
 
-        if let Err(e) = result {
-            error!("Failed to force delete order: {e}");
-            return (StatusCode::INTERNAL_SERVER_ERROR, "");
+/// Where each team with a budget for the season stands against it
+#[axum::debug_handler]
+async fn get_budgets(
+    State(state): State<&'static UsrState>,
+    Query(ListBudgets { season }): Query<ListBudgets>,
+) -> Response {
+    let season = season.unwrap_or_else(current_season);
+    let result = async {
+        let mut standings = vec![];
+        for model in season_budget::Entity::find()
+            .filter(season_budget::Column::Season.eq(season))
+            .order_by_asc(season_budget::Column::Team)
+            .all(&state.db)
+            .await?
+        {
+            standings.extend(budget::standing(&state.db, model.team, season, None).await?);
         }
-    } else if let Err(e) = order::Entity::delete_by_id(id).exec(&state.db).await {
-        error!("Failed to delete order: {e}");
-        return (StatusCode::INTERNAL_SERVER_ERROR, "");
+        Result::<_, sea_orm::DbErr>::Ok(standings)
     }
+    .await;
 
-    state
-        .new_orders_webhook
-        .as_ref()
-        .map(|x| x.enqueue(id, webhook_msg));
-    backup_db(state);
+    match result {
+        Ok(standings) => Json(standings).into_response(),
+        Err(e) => {
+            error!("Failed to get season budgets: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
 
-    (StatusCode::OK, "")
+fn freeze_scope(team: Option<scheduler::Team>) -> SimpleExpr {
+    match team {
+        Some(team) => freeze::Column::Team.eq(team),
+        None => freeze::Column::Team.is_null(),
+    }
 }
 
 #[derive(Deserialize)]
-pub struct UpdateOrder {
-    pub id: u32,
-    pub status: order_status::Status,
-    pub ref_number: Option<u32>,
+struct SetFreeze {
+    /// Leave out to freeze every team
+    #[serde(default)]
+    team: Option<scheduler::Team>,
+    mode: freeze::Mode,
+    reason: String,
 }
 
+/// Freezes new orders for a team, or every team, replacing any freeze already
+/// on it
 #[axum::debug_handler]
-async fn update_order(
+async fn set_freeze(
     State(state): State<&'static UsrState>,
-    Json(update_order): Json<UpdateOrder>,
-) -> (StatusCode, &'static str) {
-    let webhook_msg;
-    let mut same_status = false;
-
-    match order_status::Entity::find()
-        .filter(order_status::Column::OrderId.eq(update_order.id))
-        .order_by_desc(order_status::Column::InstanceId)
-        .one(&state.db)
-        .await
-    {
-        Ok(Some(model)) => {
-            if model.status == order_status::Status::InStorage {
-                return (StatusCode::BAD_REQUEST, "Order is already in storage");
-            }
-            if model.status == update_order.status {
-                if update_order.ref_number.is_none() {
-                    return (StatusCode::BAD_REQUEST, "Order is already in that state");
-                }
-                same_status = true;
-            }
-            let model = match order::Entity::find_by_id(update_order.id)
-                .one(&state.db)
-                .await
-            {
-                Ok(Some(model)) => model,
-                Ok(None) => unreachable!(),
-                Err(e) => {
-                    error!("Failed to find order: {e}");
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "");
-                }
-            };
-            if update_order.status == order_status::Status::InStorage {
-                if model.store_in.is_empty() {
-                    webhook_msg = format!(
-                        "**Order Complete!**\n**Name:** {}\n**Team:** {}",
-                        model.name, model.team
-                    );
-                } else {
-                    webhook_msg = format!(
-                        "**Order Complete!**\n**Name:** {}\n**Team:** {}\n**Location:** {}",
-                        model.name, model.team, model.store_in
-                    );
-                }
-            } else {
-                webhook_msg = format!(
-                    "**Order Update!**\n**Name:** {}\n**Team:** {}\n**Status:** {}",
-                    model.name, model.team, update_order.status
-                );
-            }
-        }
-        Ok(None) => {
-            return (StatusCode::BAD_REQUEST, "Order not found");
-        }
-        Err(e) => {
-            error!("Failed to find order: {e}");
-            return (StatusCode::INTERNAL_SERVER_ERROR, "");
-        }
+    Json(SetFreeze { team, mode, reason }): Json<SetFreeze>,
+) -> Response {
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return (StatusCode::BAD_REQUEST, "A freeze needs a reason").into_response();
     }
-
     let result = state
         .db
         .transaction(|tx| {
             Box::pin(async move {
-                if !same_status {
-                    let active_model = order_status::ActiveModel {
-                        order_id: ActiveValue::Set(update_order.id),
-                        instance_id: ActiveValue::NotSet,
-                        date: ActiveValue::Set(Local::now().naive_local()),
-                        status: ActiveValue::Set(update_order.status),
-                    };
-    
-                    active_model.insert(tx).await?;
+                freeze::Entity::delete_many()
+                    .filter(freeze_scope(team))
+                    .exec(tx)
+                    .await?;
+                freeze::ActiveModel {
+                    id: ActiveValue::NotSet,
+                    team: ActiveValue::Set(team),
+                    mode: ActiveValue::Set(mode),
+                    reason: ActiveValue::Set(reason),
+                    since: ActiveValue::Set(Local::now().naive_local()),
                 }
-
-                let active_model = order::ActiveModel {
-                    id: ActiveValue::Unchanged(update_order.id),
-                    name: ActiveValue::NotSet,
-                    count: ActiveValue::NotSet,
-                    unit_cost: ActiveValue::NotSet,
-                    store_in: ActiveValue::NotSet,
-                    team: ActiveValue::NotSet,
-                    reason: ActiveValue::NotSet,
-                    vendor: ActiveValue::NotSet,
-                    link: ActiveValue::NotSet,
-                    ref_number: ActiveValue::Set(update_order.ref_number),
-                };
-
-                active_model.update(tx).await?;
-
-                Result::<_, sea_orm::DbErr>::Ok(())
+                .insert(tx)
+                .await
             })
         })
         .await;
 
-    if let Err(e) = result {
-        error!("Failed to update order status: {e}");
-        (StatusCode::INTERNAL_SERVER_ERROR, "")
-    } else {
-        if !same_status {
-            state
-                .order_updates_webhook
-                .as_ref()
-                .map(|x| x.enqueue(update_order.id, webhook_msg));
+    match result {
+        Ok(model) => {
+            backup_db(state);
+            Json(model).into_response()
+        }
+        Err(e) => {
+            error!("Failed to set spending freeze: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
         }
-        backup_db(state);
-        (StatusCode::OK, "")
     }
 }
 
+#[derive(Deserialize)]
+struct DeleteFreeze {
+    #[serde(default)]
+    team: Option<scheduler::Team>,
+}
+
+/// Lifts the freeze on a team, or the one on every team. Orders already put on
+/// hold by it stay on hold until a lead releases them.
 #[axum::debug_handler]
-async fn get_orders(State(state): State<&'static UsrState>) -> Response {
-    let result = order::Entity::find().all(&state.db).await;
+async fn del_freeze(
+    State(state): State<&'static UsrState>,
+    Json(DeleteFreeze { team }): Json<DeleteFreeze>,
+) -> (StatusCode, &'static str) {
+    let result = freeze::Entity::delete_many()
+        .filter(freeze_scope(team))
+        .exec(&state.db)
+        .await;
 
     match result {
-        Ok(orders) => {
-            let result = order_status::Entity::find().all(&state.db).await;
-
-            match result {
-                Ok(statuses) => Json(serde_json::json!({
-                    "orders": orders,
-                    "statuses": statuses
-                }))
-                .into_response(),
-                Err(e) => {
-                    error!("Failed to get orders: {e}");
-                    (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
-                }
-            }
+        Ok(result) if result.rows_affected == 0 => (StatusCode::BAD_REQUEST, "No such freeze"),
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "")
         }
         Err(e) => {
-            error!("Failed to get orders: {e}");
+            error!("Failed to lift spending freeze: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Summary<T> {
+    /// Freezes on new orders currently in effect
+    freezes: Vec<freeze::Model>,
+    teams: T,
+}
+
+#[axum::debug_handler]
+async fn get_summary(State(state): State<&'static UsrState>) -> Response {
+    match freeze::Entity::find()
+        .order_by_asc(freeze::Column::Id)
+        .all(&state.db)
+        .await
+    {
+        Ok(freezes) => Json(Summary {
+            freezes,
+            teams: state.rollups.get(),
+        })
+        .into_response(),
+        Err(e) => {
+            error!("Failed to get spending freezes: {e}");
             (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
         }
     }
 }
 
-pub fn router() -> Router<&'static UsrState> {
+pub fn admin_router() -> Router<&'static UsrState> {
     Router::new()
-        .route("/new/order", post(new_order))
-        .route("/change/order", post(change_order))
+        .route("/notify/order/{id}", post(notify_order))
+        .route("/close/period", post(close_period))
+        .route("/close/season", post(season::close_season))
+        .route("/set/freeze", post(set_freeze))
+        .route("/set/vendorpolicy", post(set_vendor_policy))
+        .route("/del/vendorpolicy", delete(del_vendor_policy))
+        .route("/del/freeze", delete(del_freeze))
+        .route("/set/budget", post(set_budget))
+        .route("/del/budget", delete(del_budget))
+        .route("/set/quota", post(quota::set_quota))
+        .route("/del/quota", delete(quota::del_quota))
+        .route("/set/field", post(set_field))
+        .route("/del/field", delete(del_field))
+        .route("/set/location", post(location::set_location))
+        .route("/del/location", delete(location::del_location))
+        .route(
+            "/import/orders",
+            post(import::import_orders).layer(DefaultBodyLimit::max(import::MAX_IMPORT_BYTES)),
+        )
+        .layer(axum::middleware::from_fn(api_error::json_errors))
+}
+
+pub fn router(state: &'static UsrState) -> Router<&'static UsrState> {
+    // Retried over a flaky connection, these would place or move an order twice
+    let idempotent = || axum::middleware::from_fn_with_state(state, idempotency::replay);
+    Router::new()
+        .route("/new/order", post(new_order).layer(idempotent()))
+        .route("/new/orders", post(new_orders))
+        .route("/clone/order/{id}", post(clone_order))
+        .route("/reorder/order/{id}", post(reorder))
+        .route("/change/order", post(change_order).layer(idempotent()))
+        .route("/approve/order", post(approve_order))
+        .route("/reject/order", post(reject_order))
+        .route("/list/approval", get(get_approvals))
+        .route("/list/audit", get(get_audit))
+        .route("/order/{id}", patch(patch_order))
+        .route("/order/{id}/permalink", get(get_permalink))
+        .route("/order/{id}/diff", get(get_order_diff))
+        .route(
+            "/order/{id}/attachment",
+            get(attachment::get_attachments)
+                .post(attachment::new_attachment)
+                .layer(DefaultBodyLimit::max(attachment::MAX_ATTACHMENT_BYTES + 64 * 1024)),
+        )
+        .route(
+            "/order/{id}/attachment/{attachment_id}",
+            get(attachment::get_attachment).delete(attachment::del_attachment),
+        )
+        .route(
+            "/order/{id}/comments",
+            get(comment::get_comments).post(comment::new_comment),
+        )
+        .route("/order/{id}/comments/{comment_id}", delete(comment::del_comment))
         .route("/del/order", delete(cancel_order))
-        .route("/update/order", post(update_order))
+        .route("/restore/order", post(restore_order))
+        .route("/pickup/order", post(pickup::pickup_order))
+        .route("/update/order", post(update_order).layer(idempotent()))
+        .route("/receive/order", post(receive_order))
+        .route("/new/shipment", post(new_shipment))
+        .route("/update/shipment", post(update_shipment))
+        .route("/del/shipment", delete(del_shipment))
+        .route("/list/shipment", get(get_shipments))
+        .route("/release/order", post(release_order))
+        .route("/watch/order", post(watch_order))
+        .route("/unwatch/order", delete(unwatch_order))
+        .route("/list/watch", get(get_watches))
+        .route("/list/discrepancy", get(get_discrepancies))
+        .route("/resolve/discrepancy", post(resolve_discrepancy))
+        .route("/report/vendors", get(get_vendor_report))
+        .route("/summary/spend", get(spend::get_spend_summary))
+        .route("/summary/analytics", get(analytics::get_analytics))
+        .route("/report/arrivals", get(arrivals::get_arrivals).post(arrivals::post_arrivals))
+        .route("/report/countdown", get(countdown::get_countdown))
+        .route("/report/competition", get(competition::get_competition_report))
         .route("/list/order", get(get_orders))
+        .route("/list/order/mine", get(get_my_orders))
+        .route("/search/order", get(search::search_orders))
+        .route("/events/orders", get(events::order_events))
+        .route("/list/status", get(get_statuses))
+        .route("/import/status", post(import_statuses))
+        .route("/new/wishlist", post(new_wishlist))
+        .route("/del/wishlist", delete(del_wishlist))
+        .route("/promote/wishlist", post(promote_wishlist))
+        .route("/set/recurring", post(recurring::set_recurring))
+        .route("/del/recurring", delete(recurring::del_recurring))
+        .route("/list/recurring", get(recurring::get_recurring))
+        .route("/set/template", post(template::set_template))
+        .route("/del/template", delete(template::del_template))
+        .route("/list/template", get(template::get_templates))
+        .route("/use/template/{id}", post(template::use_template))
+        .route("/set/reimbursement", post(reimbursement::set_reimbursement))
+        .route("/del/reimbursement", delete(reimbursement::del_reimbursement))
+        .route("/list/reimbursement", get(reimbursement::get_reimbursements))
+        .route("/list/wishlist", get(get_wishlist))
+        .route("/set/funding", post(set_funding))
+        .route("/list/funding", get(get_funding))
+        .route("/set/vendor", post(set_vendor))
+        .route("/del/vendor", delete(del_vendor))
+        .route("/list/vendor", get(get_vendors))
+        .route("/list/vendorpolicy", get(get_vendor_policies))
+        .route("/list/field", get(get_fields))
+        .route("/list/quota", get(quota::get_quotas))
+        .route("/list/batch", get(get_batches))
+        .route("/batch/{vendor}", get(get_batch))
+        .route("/set/split", post(set_splits))
+        .route("/list/split", get(get_splits))
+        .route("/transfer/inventory", post(transfer_inventory))
+        .route("/list/transfer", get(get_transfers))
+        .route("/list/inventory", get(get_inventory))
+        .route("/checkout/inventory", post(checkout_inventory))
+        .route("/checkin/inventory", post(checkin_inventory))
+        .route("/list/checkout", get(get_checkouts))
+        .route("/list/location", get(location::get_locations))
+        .route("/list/location/{id}/contents", get(location::get_location_contents))
+        .route("/list/period", get(get_periods))
+        .route("/list/season", get(season::get_seasons))
+        .route("/list/budget", get(get_budgets))
+        .route("/export/funding/{source}", get(export_funding))
+        .route("/export/status", get(export_statuses))
+        .route("/export/orders", get(export_orders))
+        .route("/list/taxexempt", get(get_tax_exemptions))
+        .route("/export/taxexempt", get(export_tax_bundle))
+        .route("/list/leadtime", get(get_lead_times))
+        .route("/list/eta", get(get_etas))
+        .route("/suggest", get(suggest))
+        .route("/stats/teams", get(get_team_stats))
+        .route("/stats/summary", get(get_summary))
+        .route("/typeahead", get(typeahead))
+        .layer(axum::middleware::from_fn(api_error::json_errors))
 }
 
-pub async fn reset_tables(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
-    let builder = db.get_database_backend();
-    let schema = Schema::new(builder);
-
-    db.execute(builder.build(Table::drop().table(order::Entity).if_exists()))
+/// Recreates `order_current`, whose columns are fixed when it is created and
+/// so need refreshing whenever `orders` gains a column
+async fn create_current_view(
+    db: &(impl ConnectionTrait + TransactionTrait),
+) -> Result<(), sea_orm::DbErr> {
+    db.execute_unprepared("DROP VIEW IF EXISTS order_current")
         .await?;
-    db.execute(builder.build(&schema.create_table_from_entity(order::Entity)))
-        .await?;
-    db.execute(builder.build(Table::drop().table(order_status::Entity).if_exists()))
-        .await?;
-    db.execute(builder.build(&schema.create_table_from_entity(order_status::Entity)))
-        .await?;
-
+    db.execute_unprepared(current::CREATE_VIEW).await?;
     Ok(())
 }
+
+pub async fn verify_tables(
+    db: &(impl ConnectionTrait + TransactionTrait),
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, order::Entity).await?);
+    problems.extend(schema::verify(db, order_status::Entity).await?);
+    problems.extend(schema::verify(db, wishlist::Entity).await?);
+    problems.extend(schema::verify(db, funding::Entity).await?);
+    problems.extend(schema::verify(db, budget_period::Entity).await?);
+    problems.extend(schema::verify(db, period_total::Entity).await?);
+    problems.extend(schema::verify(db, cost_split::Entity).await?);
+    problems.extend(schema::verify(db, reminder::Entity).await?);
+    problems.extend(schema::verify(db, deadline::Entity).await?);
+    problems.extend(schema::verify(db, freeze::Entity).await?);
+    problems.extend(schema::verify(db, stock::Entity).await?);
+    problems.extend(schema::verify(db, checkout::Entity).await?);
+    problems.extend(schema::verify(db, transfer::Entity).await?);
+    problems.extend(schema::verify(db, discrepancy::Entity).await?);
+    problems.extend(schema::verify(db, vendor::Entity).await?);
+    problems.extend(schema::verify(db, watch::Entity).await?);
+    problems.extend(schema::verify(db, season_budget::Entity).await?);
+    problems.extend(schema::verify(db, approval::Entity).await?);
+    problems.extend(schema::verify(db, audit::Entity).await?);
+    problems.extend(schema::verify(db, vendor_policy::Entity).await?);
+    problems.extend(schema::verify(db, attachment::Entity).await?);
+    problems.extend(schema::verify(db, comment::Entity).await?);
+    problems.extend(schema::verify(db, pickup::Entity).await?);
+    problems.extend(schema::verify(db, shipment::Entity).await?);
+    problems.extend(schema::verify(db, shipment_order::Entity).await?);
+    problems.extend(schema::verify(db, custom_field::Entity).await?);
+    problems.extend(schema::verify(db, field_value::Entity).await?);
+    problems.extend(schema::verify(db, quota::Entity).await?);
+    problems.extend(schema::verify(db, recurring::Entity).await?);
+    problems.extend(schema::verify(db, season::Entity).await?);
+    problems.extend(schema::verify(db, reimbursement::Entity).await?);
+    problems.extend(schema::verify(db, location::Entity).await?);
+    problems.extend(schema::verify(db, template::Entity).await?);
+    problems.extend(schema::verify(db, revision::Entity).await?);
+    if problems.is_empty() {
+        // The unique index can't be created over numbers that were claimed twice
+        let duplicates: Vec<(u32, i64)> = order::Entity::find()
+            .select_only()
+            .column(order::Column::RefNumber)
+            .column_as(order::Column::Id.count(), "uses")
+            .filter(order::Column::RefNumber.is_not_null())
+            .group_by(order::Column::RefNumber)
+            .having(Expr::expr(order::Column::Id.count()).gt(1))
+            .into_tuple()
+            .all(db)
+            .await?;
+        problems.extend(duplicates.into_iter().map(|(ref_number, uses)| {
+            format!("Ref number {ref_number} is used by {uses} orders")
+        }));
+    }
+    // Nearly every order lookup also looks up its statuses
+    if problems.is_empty() {
+        schema::ensure_index(db, order_status::Entity, order_status::Column::OrderId).await?;
+        schema::ensure_index(db, cost_split::Entity, cost_split::Column::OrderId).await?;
+        schema::ensure_index(db, audit::Entity, audit::Column::OrderId).await?;
+        schema::ensure_index(db, attachment::Entity, attachment::Column::OrderId).await?;
+        schema::ensure_index(db, comment::Entity, comment::Column::OrderId).await?;
+        schema::ensure_unique_index(db, shipment::Entity, shipment::Column::Tracking).await?;
+        schema::ensure_index(db, field_value::Entity, field_value::Column::Field).await?;
+        schema::ensure_unique_index(db, order::Entity, order::Column::RefNumber).await?;
+        schema::ensure_unique_index(db, reimbursement::Entity, reimbursement::Column::OrderId)
+            .await?;
+        schema::ensure_unique_index(db, location::Entity, location::Column::Label).await?;
+        schema::ensure_index(db, revision::Entity, revision::Column::AuditId).await?;
+        create_current_view(db).await?;
+    }
+    Ok(problems)
+}
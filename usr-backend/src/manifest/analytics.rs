@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{prelude::Decimal, ConnectionTrait, DatabaseBackend, DatabaseConnection, Statement};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{money, scheduler::Team, schema, UsrState};
+
+use super::{current_season, order_status::Status};
+
+/// Orders of a season that were bought, with what they cost and when they
+/// were first submitted. An order cancelled after it was bought still spent
+/// its money, so it is counted.
+const BOUGHT: &str = "WITH bought AS (
+    SELECT orders.id, orders.team, orders.vendor,
+    ROUND(CAST(orders.count AS BIGINT) * orders.unit_cost, 2) + COALESCE(orders.shipping_cost, 0)
+    + COALESCE(orders.tax, 0) + COALESCE(orders.fees, 0) AS total,
+    MIN(order_status.date) AS bought_at
+    FROM orders
+    JOIN order_status ON order_status.order_id = orders.id
+    WHERE orders.season = ? AND order_status.status IN ('S', 'F', 'P', 'D', 'I')
+    GROUP BY orders.id
+)";
+
+#[derive(Serialize, Default)]
+struct Month {
+    /// eg. 2025-09
+    month: String,
+    total: Decimal,
+    teams: HashMap<Team, Decimal>,
+}
+
+#[derive(Serialize)]
+struct VendorSpend {
+    vendor: String,
+    orders: u32,
+    total: Decimal,
+}
+
+#[derive(Serialize)]
+struct TimeToStorage {
+    /// Orders that have been put in storage
+    orders: u32,
+    /// From being placed to being put in storage, averaged over those orders
+    average_days: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct Analytics {
+    season: u16,
+    total: Decimal,
+    teams: HashMap<Team, Decimal>,
+    /// Oldest first, by the month each order was submitted in
+    months: Vec<Month>,
+    /// Most spent first
+    vendors: Vec<VendorSpend>,
+    to_storage: TimeToStorage,
+    /// Orders by their latest status
+    statuses: HashMap<Status, u32>,
+}
+
+/// SQL for the month `column` is in, eg. 2025-09
+fn month(backend: DatabaseBackend, column: &str) -> String {
+    match backend {
+        DatabaseBackend::Postgres => format!("to_char({column}, 'YYYY-MM')"),
+        _ => format!("strftime('%Y-%m', {column})"),
+    }
+}
+
+/// SQL for the days from `start` to `end`, as a fraction
+fn days_between(backend: DatabaseBackend, start: &str, end: &str) -> String {
+    match backend {
+        DatabaseBackend::Postgres => format!("EXTRACT(EPOCH FROM {end} - {start}) / 86400"),
+        _ => format!("julianday({end}) - julianday({start})"),
+    }
+}
+
+async fn analyze(db: &DatabaseConnection, season: u16) -> Result<Analytics, sea_orm::DbErr> {
+    let backend = db.get_database_backend();
+    let statement = |sql: String| {
+        Statement::from_sql_and_values(
+            backend,
+            schema::placeholders(backend, &sql),
+            [u32::from(season).into()],
+        )
+    };
+
+    let mut months = BTreeMap::<String, Month>::new();
+    let mut teams = HashMap::<Team, Decimal>::new();
+    for row in db
+        .query_all(statement(format!(
+            "{BOUGHT} SELECT {} AS month, team, SUM(total) AS total
+            FROM bought GROUP BY month, team",
+            month(backend, "bought_at")
+        )))
+        .await?
+    {
+        let month: String = row.try_get("", "month")?;
+        let team: Team = row.try_get("", "team")?;
+        let total = money::round(row.try_get("", "total")?);
+        let entry = months.entry(month.clone()).or_insert_with(|| Month {
+            month,
+            ..Default::default()
+        });
+        entry.total += total;
+        entry.teams.insert(team, total);
+        *teams.entry(team).or_default() += total;
+    }
+
+    let vendors = db
+        .query_all(statement(format!(
+            "{BOUGHT} SELECT vendor, COUNT(*) AS orders, SUM(total) AS total
+            FROM bought GROUP BY vendor ORDER BY total DESC"
+        )))
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(VendorSpend {
+                vendor: row.try_get("", "vendor")?,
+                orders: row.try_get::<i64>("", "orders")? as u32,
+                total: money::round(row.try_get("", "total")?),
+            })
+        })
+        .collect::<Result<_, sea_orm::DbErr>>()?;
+
+    let to_storage = db
+        .query_one(statement(format!(
+            "SELECT COUNT(*) AS orders,
+            CAST(AVG({}) AS DOUBLE PRECISION) AS average_days
+            FROM orders
+            JOIN (SELECT order_id, MIN(date) AS date FROM order_status WHERE status = 'N'
+                GROUP BY order_id) AS placed ON placed.order_id = orders.id
+            JOIN (SELECT order_id, MIN(date) AS date FROM order_status WHERE status = 'I'
+                GROUP BY order_id) AS stored ON stored.order_id = orders.id
+            WHERE orders.season = ?",
+            days_between(backend, "placed.date", "stored.date")
+        )))
+        .await?
+        .map(|row| {
+            Ok::<_, sea_orm::DbErr>(TimeToStorage {
+                orders: row.try_get::<i64>("", "orders")? as u32,
+                average_days: row.try_get("", "average_days")?,
+            })
+        })
+        .transpose()?
+        .unwrap_or(TimeToStorage {
+            orders: 0,
+            average_days: None,
+        });
+
+    let statuses = db
+        .query_all(statement(
+            "SELECT status, COUNT(*) AS orders FROM order_current WHERE season = ? GROUP BY status"
+                .to_string(),
+        ))
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok((
+                row.try_get("", "status")?,
+                row.try_get::<i64>("", "orders")? as u32,
+            ))
+        })
+        .collect::<Result<_, sea_orm::DbErr>>()?;
+
+    Ok(Analytics {
+        season,
+        total: teams.values().sum(),
+        teams,
+        months: months.into_values().collect(),
+        vendors,
+        to_storage,
+        statuses,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct AnalyticsQuery {
+    /// The current season if left out
+    #[serde(default)]
+    season: Option<u16>,
+}
+
+/// A season's spending by team, month and vendor, how long orders take to
+/// go from being placed to being put in storage, and how many orders are in
+/// each status, for the monthly report to the faculty advisor. Spending
+/// includes whatever shipping, tax and fees have been recorded.
+#[axum::debug_handler]
+pub async fn get_analytics(
+    State(state): State<&'static UsrState>,
+    Query(query): Query<AnalyticsQuery>,
+) -> Response {
+    let season = query.season.unwrap_or_else(current_season);
+    match analyze(&state.db, season).await {
+        Ok(analytics) => Json(analytics).into_response(),
+        Err(e) => {
+            error!("Failed to get analytics: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
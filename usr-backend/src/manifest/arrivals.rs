@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Days, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{notify::Topic, scheduler::Team, UsrState};
+
+use super::{
+    current,
+    inventory::{self, Placement},
+    order_status::{self, Status},
+    policy, stock,
+};
+
+/// An order that arrived during the report's window
+#[derive(Serialize)]
+pub struct Arrival {
+    pub order_id: i64,
+    pub number: String,
+    pub name: String,
+    pub team: Team,
+    pub vendor: String,
+    pub count: i64,
+    /// When it was first delivered or put in storage
+    pub arrived: NaiveDateTime,
+    pub status: Status,
+    /// Where it is, or where it goes if it hasn't been put away yet. Empty
+    /// if nobody said where to store it.
+    pub locations: Vec<Placement>,
+}
+
+/// Orders first delivered or put in storage on or after `since` and before
+/// `until`, by team and then in the order they arrived. Orders that came
+/// back to storage after a hold arrived the first time, not again.
+pub async fn arrivals(
+    db: &DatabaseConnection,
+    since: NaiveDateTime,
+    until: NaiveDateTime,
+) -> Result<Vec<Arrival>, sea_orm::DbErr> {
+    let statuses = order_status::Entity::find()
+        .filter(order_status::Column::Status.is_in([Status::Delivered, Status::InStorage]))
+        .order_by_asc(order_status::Column::Date)
+        .all(db)
+        .await?;
+    let mut first = HashMap::new();
+    for model in statuses {
+        first.entry(model.order_id).or_insert(model.date);
+    }
+    first.retain(|_, arrived| *arrived >= since && *arrived < until);
+
+    let (orders, stock) = tokio::join!(
+        current::Entity::find()
+            .filter(current::Column::Id.is_in(first.keys().copied()))
+            .all(db),
+        stock::Entity::find()
+            .filter(stock::Column::OrderId.is_in(first.keys().copied()))
+            .all(db),
+    );
+    let mut by_order = HashMap::<i64, Vec<stock::Model>>::new();
+    for model in stock? {
+        by_order.entry(model.order_id).or_default().push(model);
+    }
+
+    let mut out: Vec<_> = orders?
+        .into_iter()
+        .map(|model| {
+            let (order, status) = model.into_parts();
+            let locations = match by_order.get(&order.id) {
+                Some(rows) => inventory::placements(&order, rows),
+                None if order.store_in.is_empty() => vec![],
+                None => vec![Placement {
+                    location: order.store_in.clone(),
+                    count: order.count,
+                }],
+            };
+            Arrival {
+                order_id: order.id,
+                number: order.number(),
+                arrived: first[&order.id],
+                status,
+                locations,
+                name: order.name,
+                team: order.team,
+                vendor: order.vendor,
+                count: order.count,
+            }
+        })
+        .collect();
+    out.sort_by(|a, b| {
+        a.team
+            .to_string()
+            .cmp(&b.team.to_string())
+            .then(a.arrived.cmp(&b.arrived))
+    });
+    Ok(out)
+}
+
+/// The report as it is read out at the weekly meeting
+fn message(arrivals: &[Arrival], since: NaiveDate, until: NaiveDate) -> String {
+    let mut msg = format!("**Arrivals** ({since} to {until})");
+    if arrivals.is_empty() {
+        msg.push_str("\nNothing arrived");
+        return msg;
+    }
+    let mut team = None;
+    for arrival in arrivals {
+        if team != Some(arrival.team) {
+            team = Some(arrival.team);
+            msg.push_str(&format!("\n**{}**", arrival.team));
+        }
+        let locations = if arrival.locations.is_empty() {
+            "not stored yet".to_string()
+        } else {
+            arrival
+                .locations
+                .iter()
+                .map(|placement| format!("{} in {}", placement.count, placement.location))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        msg.push_str(&format!(
+            "\n- {} x {} ({}) from {}: {locations}",
+            arrival.count, arrival.name, arrival.number, arrival.vendor
+        ));
+    }
+    msg
+}
+
+#[derive(Deserialize)]
+pub struct Window {
+    /// The first day of the window, a week before `until` by default
+    #[serde(default)]
+    since: Option<NaiveDate>,
+    /// The day after the window, tomorrow by default so that today counts
+    #[serde(default)]
+    until: Option<NaiveDate>,
+}
+
+impl Window {
+    /// The first and last day of the window
+    fn days(&self) -> Result<(NaiveDate, NaiveDate), (StatusCode, &'static str)> {
+        let until = self
+            .until
+            .unwrap_or_else(|| Local::now().date_naive() + Days::new(1));
+        let since = self.since.unwrap_or(until - Days::new(7));
+        if since >= until {
+            return Err((StatusCode::BAD_REQUEST, "since must be before until"));
+        }
+        Ok((since, until - Days::new(1)))
+    }
+}
+
+async fn report(
+    state: &'static UsrState,
+    since: NaiveDate,
+    last: NaiveDate,
+) -> Result<Vec<Arrival>, (StatusCode, &'static str)> {
+    arrivals(
+        &state.db,
+        since.and_time(NaiveTime::MIN),
+        (last + Days::new(1)).and_time(NaiveTime::MIN),
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to get arrivals: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "")
+    })
+}
+
+/// What arrived during the window, with where it was put
+#[axum::debug_handler]
+pub async fn get_arrivals(
+    State(state): State<&'static UsrState>,
+    Query(window): Query<Window>,
+) -> Response {
+    let (since, last) = match window.days() {
+        Ok(days) => days,
+        Err(response) => return response.into_response(),
+    };
+    match report(state, since, last).await {
+        Ok(report) => Json(report).into_response(),
+        Err(response) => response.into_response(),
+    }
+}
+
+/// Posts what arrived during the window to the order updates webhook, for
+/// leads to read out at the weekly meeting. Responds with the report too.
+#[axum::debug_handler]
+pub async fn post_arrivals(
+    State(state): State<&'static UsrState>,
+    role: policy::Role,
+    Query(window): Query<Window>,
+) -> Response {
+    if role < policy::Role::Lead {
+        return (StatusCode::FORBIDDEN, format!("{role} cannot post reports")).into_response();
+    }
+    if !state.notifier.routes(Topic::OrderUpdate) {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Nothing is set up to receive order updates",
+        )
+            .into_response();
+    }
+    let (since, last) = match window.days() {
+        Ok(days) => days,
+        Err(response) => return response.into_response(),
+    };
+    let report = match report(state, since, last).await {
+        Ok(report) => report,
+        Err(response) => return response.into_response(),
+    };
+    // Posting the report again before it is sent replaces it
+    state.notifier.send(
+        Topic::OrderUpdate,
+        u32::MAX / 8,
+        message(&report, since, last),
+    );
+    Json(report).into_response()
+}
@@ -0,0 +1,344 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use rand::RngCore;
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection, QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+use crate::{backup::backup_db, multipart, UsrState};
+
+use super::{audit, order, policy, resolve_order, OrderRef};
+
+/// Large enough for a scanned multi-page invoice
+pub const MAX_ATTACHMENT_BYTES: usize = 20 * 1024 * 1024;
+
+/// A receipt, invoice or other file kept with an order, for reimbursements
+/// and the treasurer's records. The file itself is in `state.attachments`.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "attachments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub order_id: i64,
+    pub kind: Kind,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    /// Hex encoded, so that the same receipt uploaded twice can be spotted
+    pub sha256: String,
+    /// Where the file is in storage
+    #[serde(skip)]
+    pub key: String,
+    pub uploaded_by: String,
+    pub uploaded: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Kind {
+    #[sea_orm(string_value = "R")]
+    Receipt,
+    #[sea_orm(string_value = "I")]
+    Invoice,
+    #[sea_orm(string_value = "O")]
+    Other,
+}
+
+impl Kind {
+    fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "receipt" => Some(Self::Receipt),
+            "invoice" => Some(Self::Invoice),
+            "other" => Some(Self::Other),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps only the last path component and drops characters that would break
+/// the `Content-Disposition` header it is sent back in
+fn clean_file_name(name: &str) -> String {
+    let name: String = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .collect();
+    if name.trim().is_empty() {
+        "attachment".to_string()
+    } else {
+        name.trim().to_string()
+    }
+}
+
+async fn find_order(
+    db: &DatabaseConnection,
+    id: &OrderRef,
+) -> Result<order::Model, (StatusCode, &'static str)> {
+    let id = resolve_order(db, id).await?;
+    match order::Entity::find_by_id(id).one(db).await {
+        Ok(Some(model)) => Ok(model),
+        Ok(None) => Err((StatusCode::BAD_REQUEST, "Order not found")),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, ""))
+        }
+    }
+}
+
+async fn find_attachment(
+    db: &DatabaseConnection,
+    order_id: i64,
+    id: u32,
+) -> Result<Model, (StatusCode, &'static str)> {
+    match Entity::find_by_id(id).one(db).await {
+        Ok(Some(model)) if model.order_id == order_id => Ok(model),
+        Ok(_) => Err((StatusCode::NOT_FOUND, "Attachment not found")),
+        Err(e) => {
+            error!("Failed to find attachment: {e}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, ""))
+        }
+    }
+}
+
+/// Uploads a file as `multipart/form-data`, with the file in a `file` field
+/// and optionally `kind` as `receipt`, `invoice` or `other`. Files without a
+/// kind are taken to be receipts.
+#[axum::debug_handler]
+pub async fn new_attachment(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Path(id): Path<OrderRef>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let model = match find_order(&state.db, &id).await {
+        Ok(model) => model,
+        Err(response) => return response.into_response(),
+    };
+    if let Err(response) = policy::check_owner(&caller, &model) {
+        return response.into_response();
+    }
+    let Some(boundary) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(multipart::boundary)
+    else {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Expected a multipart/form-data body",
+        )
+            .into_response();
+    };
+    let parts = match multipart::parse(&body, boundary) {
+        Ok(parts) => parts,
+        Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+    };
+    let mut kind = Kind::Receipt;
+    if let Some(part) = parts.iter().find(|part| part.name == "kind") {
+        match std::str::from_utf8(part.data).ok().and_then(Kind::parse) {
+            Some(parsed) => kind = parsed,
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    "kind must be receipt, invoice or other",
+                )
+                    .into_response()
+            }
+        }
+    }
+    let Some(file) = parts.iter().find(|part| part.name == "file") else {
+        return (StatusCode::BAD_REQUEST, "Missing the file field").into_response();
+    };
+    if file.data.is_empty() {
+        return (StatusCode::BAD_REQUEST, "The file is empty").into_response();
+    }
+    if file.data.len() > MAX_ATTACHMENT_BYTES {
+        return (StatusCode::PAYLOAD_TOO_LARGE, "Files can be at most 20 MiB").into_response();
+    }
+
+    let file_name = clean_file_name(file.file_name.as_deref().unwrap_or_default());
+    let mut nonce = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let key = format!("orders/{}/{}", model.id, hex::encode(nonce));
+    let pending = ActiveModel {
+        id: ActiveValue::NotSet,
+        order_id: ActiveValue::Set(model.id),
+        kind: ActiveValue::Set(kind),
+        file_name: ActiveValue::Set(file_name.clone()),
+        content_type: ActiveValue::Set(
+            file.content_type
+                .clone()
+                .unwrap_or_else(|| "application/octet-stream".to_string()),
+        ),
+        size: ActiveValue::Set(file.data.len() as i64),
+        sha256: ActiveValue::Set(hex::encode(Sha256::digest(file.data))),
+        key: ActiveValue::Set(key.clone()),
+        uploaded_by: ActiveValue::Set(
+            caller
+                .name
+                .clone()
+                .unwrap_or_else(|| caller.role.to_string()),
+        ),
+        uploaded: ActiveValue::Set(chrono::Local::now().naive_local()),
+    };
+
+    if let Err(e) = state.attachments.put(&key, file.data.to_vec()).await {
+        error!("Failed to store attachment: {e:#}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+    }
+    let actor = audit::Actor::new(&caller, "/order/{id}/attachment");
+    let order_id = model.id;
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let attachment = pending.insert(tx).await?;
+                actor
+                    .record(tx, order_id, audit::change("attachment", (), file_name))
+                    .await?;
+                Result::<_, sea_orm::DbErr>::Ok(attachment)
+            })
+        })
+        .await;
+    match result {
+        Ok(attachment) => {
+            backup_db(state);
+            Json(attachment).into_response()
+        }
+        Err(e) => {
+            error!("Failed to add attachment: {e}");
+            if let Err(e) = state.attachments.delete(&key).await {
+                error!("Failed to remove unrecorded attachment {key}: {e:#}");
+            }
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// The order's attachments, oldest first
+#[axum::debug_handler]
+pub async fn get_attachments(
+    State(state): State<&'static UsrState>,
+    Path(id): Path<OrderRef>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    match Entity::find()
+        .filter(Column::OrderId.eq(id))
+        .order_by_asc(Column::Id)
+        .all(&state.db)
+        .await
+    {
+        Ok(attachments) => Json(attachments).into_response(),
+        Err(e) => {
+            error!("Failed to get attachments: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// The file itself, always as a download so that uploaded pages can't run
+/// in the web UI's origin
+#[axum::debug_handler]
+pub async fn get_attachment(
+    State(state): State<&'static UsrState>,
+    Path((id, attachment_id)): Path<(OrderRef, u32)>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let attachment = match find_attachment(&state.db, id, attachment_id).await {
+        Ok(attachment) => attachment,
+        Err(response) => return response.into_response(),
+    };
+    match state.attachments.get(&attachment.key).await {
+        Ok(data) => (
+            [
+                (header::CONTENT_TYPE, attachment.content_type),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", attachment.file_name),
+                ),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            ],
+            data,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to read attachment {}: {e:#}", attachment.key);
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[axum::debug_handler]
+pub async fn del_attachment(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Path((id, attachment_id)): Path<(OrderRef, u32)>,
+) -> (StatusCode, &'static str) {
+    let model = match find_order(&state.db, &id).await {
+        Ok(model) => model,
+        Err(response) => return response,
+    };
+    if let Err((status, _)) = policy::check_owner(&caller, &model) {
+        return (
+            status,
+            "Only leads can remove attachments from others' orders",
+        );
+    }
+    let attachment = match find_attachment(&state.db, model.id, attachment_id).await {
+        Ok(attachment) => attachment,
+        Err(response) => return response,
+    };
+    let actor = audit::Actor::new(&caller, "/order/{id}/attachment/{attachment_id}");
+    let diff = audit::change("attachment", &attachment.file_name, ());
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                Entity::delete_by_id(attachment_id).exec(tx).await?;
+                actor.record(tx, model.id, diff).await?;
+                Result::<_, sea_orm::DbErr>::Ok(())
+            })
+        })
+        .await;
+    if let Err(e) = result {
+        error!("Failed to delete attachment: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, "");
+    }
+    remove_files(state, [attachment]).await;
+    backup_db(state);
+    (StatusCode::OK, "")
+}
+
+/// Deletes the files of attachments that are no longer recorded. Sandbox
+/// instances share production's storage, so they leave the files alone.
+async fn remove_files(state: &'static UsrState, attachments: impl IntoIterator<Item = Model>) {
+    if state.sandbox {
+        return;
+    }
+    for attachment in attachments {
+        if let Err(e) = state.attachments.delete(&attachment.key).await {
+            error!("Failed to remove attachment {}: {e:#}", attachment.key);
+        }
+    }
+}
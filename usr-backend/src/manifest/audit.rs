@@ -0,0 +1,159 @@
+use sea_orm::{entity::prelude::*, ActiveValue};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::policy;
+
+/// A change someone made to an order, kept so that disputes about who
+/// changed what can be settled. Entries outlive the orders they are about.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "audit_log")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub order_id: i64,
+    /// The user's name, or their role if they didn't sign in
+    pub actor: String,
+    pub date: DateTime,
+    /// The route the change was made through, eg. `/change/order`
+    pub endpoint: String,
+    /// A JSON object of each field that changed, as `[before, after]`, with
+    /// `null` for fields the order didn't have yet or no longer has
+    #[serde(serialize_with = "as_json")]
+    pub diff: String,
+}
+
+/// Responds with the diff as JSON rather than a string of it
+fn as_json<S: serde::Serializer>(diff: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serde_json::from_str::<Value>(diff)
+        .unwrap_or_default()
+        .serialize(serializer)
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Who is changing orders, and through which route
+#[derive(Clone)]
+pub struct Actor {
+    name: String,
+    endpoint: &'static str,
+}
+
+impl Actor {
+    pub fn new(caller: &policy::Caller, endpoint: &'static str) -> Self {
+        Self {
+            name: caller
+                .name
+                .clone()
+                .unwrap_or_else(|| caller.role.to_string()),
+            endpoint,
+        }
+    }
+
+    /// Who is making the change
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// For changes nobody signed in to make, eg. orders emailed in
+    pub fn system(name: &str, endpoint: &'static str) -> Self {
+        Self {
+            name: name.to_string(),
+            endpoint,
+        }
+    }
+
+    /// Records `diff` against order `id`, meant to be written in the same
+    /// transaction as the change itself, returning the entry's id. Nothing is
+    /// recorded if nothing changed.
+    pub async fn record(
+        &self,
+        db: &impl ConnectionTrait,
+        order_id: i64,
+        diff: Map<String, Value>,
+    ) -> Result<Option<i64>, DbErr> {
+        if diff.is_empty() {
+            return Ok(None);
+        }
+        let model = ActiveModel {
+            id: ActiveValue::NotSet,
+            order_id: ActiveValue::Set(order_id),
+            actor: ActiveValue::Set(self.name.clone()),
+            date: ActiveValue::Set(chrono::Local::now().naive_local()),
+            endpoint: ActiveValue::Set(self.endpoint.to_string()),
+            diff: ActiveValue::Set(Value::Object(diff).to_string()),
+        }
+        .insert(db)
+        .await?;
+        Ok(Some(model.id))
+    }
+}
+
+fn fields(value: Option<&impl Serialize>) -> Map<String, Value> {
+    match value.map(serde_json::to_value) {
+        Some(Ok(Value::Object(fields))) => fields,
+        _ => Map::new(),
+    }
+}
+
+/// Each field that differs between `before` and `after`, where `None` is an
+/// order that doesn't exist yet or anymore. Ids aren't compared.
+pub fn diff<T: Serialize>(before: Option<&T>, after: Option<&T>) -> Map<String, Value> {
+    let (mut before, after) = (fields(before), fields(after));
+    let mut out = Map::new();
+    for (field, new) in after {
+        let old = before.remove(&field).unwrap_or(Value::Null);
+        if field != "id" && old != new {
+            out.insert(field, Value::Array(vec![old, new]));
+        }
+    }
+    for (field, old) in before {
+        if field != "id" && !old.is_null() {
+            out.insert(field, Value::Array(vec![old, Value::Null]));
+        }
+    }
+    out
+}
+
+/// A single field changing from `before` to `after`
+pub fn change(field: &str, before: impl Serialize, after: impl Serialize) -> Map<String, Value> {
+    let mut out = Map::new();
+    out.insert(
+        field.to_string(),
+        Value::Array(vec![
+            serde_json::to_value(before).unwrap_or_default(),
+            serde_json::to_value(after).unwrap_or_default(),
+        ]),
+    );
+    out
+}
+
+/// What changed over `entries`, oldest first, as each field's value before the
+/// first of them and after the last. Fields that were changed back are left
+/// out.
+pub fn combined(entries: &[Model]) -> Map<String, Value> {
+    let mut out = Map::new();
+    for entry in entries {
+        let Ok(Value::Object(diff)) = serde_json::from_str::<Value>(&entry.diff) else {
+            continue;
+        };
+        for (field, change) in diff {
+            let Value::Array(mut change) = change else {
+                continue;
+            };
+            let after = change.pop().unwrap_or_default();
+            let before = change.pop().unwrap_or_default();
+            match out.get_mut(&field) {
+                Some(Value::Array(existing)) if existing.len() == 2 => existing[1] = after,
+                _ => {
+                    out.insert(field, Value::Array(vec![before, after]));
+                }
+            }
+        }
+    }
+    out.retain(|_, change| change.get(0) != change.get(1));
+    out
+}
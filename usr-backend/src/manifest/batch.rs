@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use sea_orm::{
+    prelude::Decimal, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder,
+};
+use serde::Serialize;
+
+use crate::money;
+
+use super::{current, order, order_status, vendor, wishlist};
+
+/// The new orders for one vendor, which the purchaser places together
+#[derive(Serialize)]
+pub struct Batch {
+    pub vendor: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<vendor::Model>,
+    pub orders: Vec<order::Model>,
+    pub subtotal: Decimal,
+    /// How much more is needed to meet the vendor's minimum order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_of_minimum: Option<Decimal>,
+    /// How much more is needed for free shipping
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_of_free_shipping: Option<Decimal>,
+    /// Wishlist items from the vendor that would make up the shortfall if
+    /// they were ordered now, so they ride along with this batch
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pull_forward: Vec<wishlist::Model>,
+}
+
+/// How far `subtotal` is below `threshold`, if it is
+fn shortfall(subtotal: Decimal, threshold: Option<Decimal>) -> Option<Decimal> {
+    threshold
+        .filter(|threshold| *threshold > subtotal)
+        .map(|threshold| threshold - subtotal)
+}
+
+/// Picks wishlist items that make up `gap`: the cheapest single item that
+/// covers it if there is one, otherwise the largest items until it is covered.
+/// Picks nothing if even the whole wishlist falls short.
+fn pick_wishlist(gap: Decimal, mut items: Vec<wishlist::Model>) -> Vec<wishlist::Model> {
+    let cost = |item: &wishlist::Model| money::subtotal(item.count, item.unit_cost);
+    items.sort_by_key(cost);
+    if let Some(index) = items.iter().position(|item| cost(item) >= gap) {
+        return vec![items.swap_remove(index)];
+    }
+    let mut picked = vec![];
+    let mut covered = Decimal::ZERO;
+    while covered < gap {
+        let Some(item) = items.pop() else {
+            return vec![];
+        };
+        covered += cost(&item);
+        picked.push(item);
+    }
+    picked
+}
+
+/// New orders grouped by vendor, largest batch first
+pub async fn batches(db: &impl ConnectionTrait) -> Result<Vec<Batch>, sea_orm::DbErr> {
+    let orders = current::Entity::find()
+        .filter(current::Column::Status.eq(order_status::Status::New))
+        .order_by_asc(current::Column::Id)
+        .all(db)
+        .await?;
+    let mut vendors: HashMap<_, _> = vendor::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| (model.key.clone(), model))
+        .collect();
+    let mut wishlists = HashMap::<String, Vec<wishlist::Model>>::new();
+    for model in wishlist::Entity::find().all(db).await? {
+        wishlists
+            .entry(vendor::key(&model.vendor))
+            .or_default()
+            .push(model);
+    }
+
+    let mut batches = HashMap::<String, Batch>::new();
+    for current in orders {
+        let (order, _) = current.into_parts();
+        let key = vendor::key(&order.vendor);
+        let batch = batches.entry(key.clone()).or_insert_with(|| Batch {
+            vendor: order.vendor.trim().to_string(),
+            info: vendors.remove(&key),
+            orders: vec![],
+            subtotal: Decimal::ZERO,
+            short_of_minimum: None,
+            short_of_free_shipping: None,
+            pull_forward: vec![],
+        });
+        batch.subtotal += money::subtotal(order.count, order.unit_cost);
+        batch.orders.push(order);
+    }
+    let mut batches: Vec<_> = batches
+        .into_values()
+        .map(|mut batch| {
+            if let Some(info) = &batch.info {
+                batch.vendor = info.name.clone();
+                batch.short_of_minimum = shortfall(batch.subtotal, info.minimum_order);
+                batch.short_of_free_shipping =
+                    shortfall(batch.subtotal, info.free_shipping_threshold);
+            }
+            let gap = batch.short_of_minimum.max(batch.short_of_free_shipping);
+            if let Some(gap) = gap {
+                let items = wishlists
+                    .remove(&vendor::key(&batch.vendor))
+                    .unwrap_or_default();
+                batch.pull_forward = pick_wishlist(gap, items);
+            }
+            batch
+        })
+        .collect();
+    batches.sort_by(|a, b| b.subtotal.cmp(&a.subtotal).then(a.vendor.cmp(&b.vendor)));
+    Ok(batches)
+}
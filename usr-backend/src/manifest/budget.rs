@@ -0,0 +1,466 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDateTime;
+use sea_orm::{
+    prelude::Decimal, sea_query::OnConflict, ActiveModelTrait, ActiveValue, ColumnTrait,
+    ConnectionTrait, DatabaseTransaction, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::Serialize;
+
+use crate::{money, scheduler::Team};
+
+use super::{
+    budget_period, cost_split, current, funding, order, order_status, period_total,
+    season_budget,
+};
+
+/// Part of an order's cost and who pays for it
+#[derive(Serialize, Clone, Copy)]
+pub struct Share {
+    pub team: Team,
+    pub funding_source: funding::Source,
+    pub amount: Decimal,
+}
+
+/// Splits an order's subtotal between the teams and funding sources paying
+/// for it. Splits are taken in order, each capped at what is left so that a
+/// later price drop never has anyone paying a negative amount, and the
+/// order's own team and source pay the rest.
+pub fn shares(order: &order::Model, splits: &[cost_split::Model]) -> Vec<Share> {
+    let subtotal = money::subtotal(order.count, order.unit_cost);
+    let mut remaining = subtotal;
+    let mut out = vec![];
+    for split in splits {
+        let amount = match (split.percent, split.amount) {
+            (Some(percent), _) => subtotal * percent / Decimal::ONE_HUNDRED,
+            (None, Some(amount)) => amount,
+            (None, None) => continue,
+        };
+        let amount = money::round(amount).min(remaining);
+        remaining -= amount;
+        out.push(Share {
+            team: split.team,
+            funding_source: split.funding_source,
+            amount,
+        });
+    }
+    if out.is_empty() || !remaining.is_zero() {
+        out.push(Share {
+            team: order.team,
+            funding_source: order.funding_source,
+            amount: remaining,
+        });
+    }
+    out
+}
+
+/// The cost splits of every order that has any, by order id
+pub async fn all_splits(
+    db: &impl ConnectionTrait,
+) -> Result<HashMap<i64, Vec<cost_split::Model>>, sea_orm::DbErr> {
+    let mut out = HashMap::<i64, Vec<cost_split::Model>>::new();
+    for model in cost_split::Entity::find()
+        .order_by_asc(cost_split::Column::Id)
+        .all(db)
+        .await?
+    {
+        out.entry(model.order_id).or_default().push(model);
+    }
+    Ok(out)
+}
+
+/// Orders that were cancelled before they were bought, which never spent
+/// anything. One cancelled after it was bought still counts towards what was
+/// spent.
+pub async fn unspent_cancelled(db: &impl ConnectionTrait) -> Result<HashSet<i64>, sea_orm::DbErr> {
+    let mut cancelled: HashSet<i64> = current::Entity::find()
+        .select_only()
+        .column(current::Column::Id)
+        .filter(current::Column::Status.eq(order_status::Status::Cancelled))
+        .into_tuple::<i64>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+    if cancelled.is_empty() {
+        return Ok(cancelled);
+    }
+    for model in order_status::Entity::find()
+        .filter(order_status::Column::OrderId.is_in(cancelled.iter().copied()))
+        .filter(order_status::Column::Status.is_in([
+            order_status::Status::Submitted,
+            order_status::Status::Shipped,
+            order_status::Status::PartiallyReceived,
+            order_status::Status::Delivered,
+            order_status::Status::InStorage,
+        ]))
+        .all(db)
+        .await?
+    {
+        cancelled.remove(&model.order_id);
+    }
+    Ok(cancelled)
+}
+
+/// Sums the shares of orders placed from `start` until `end`, grouped by
+/// team
+pub async fn spend_between(
+    db: &impl ConnectionTrait,
+    start: NaiveDateTime,
+    end: Option<NaiveDateTime>,
+) -> Result<HashMap<Team, Decimal>, sea_orm::DbErr> {
+    let mut placed = order_status::Entity::find()
+        .filter(order_status::Column::Status.eq(order_status::Status::New))
+        .filter(order_status::Column::Date.gte(start));
+    if let Some(end) = end {
+        placed = placed.filter(order_status::Column::Date.lt(end));
+    }
+    let placed: HashSet<_> = placed
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| model.order_id)
+        .collect();
+
+    let splits = all_splits(db).await?;
+    let unspent = unspent_cancelled(db).await?;
+
+    let mut out = HashMap::<Team, Decimal>::new();
+    for model in order::Entity::find().all(db).await? {
+        if !placed.contains(&model.id) || unspent.contains(&model.id) {
+            continue;
+        }
+        let splits = splits.get(&model.id).map(Vec::as_slice).unwrap_or_default();
+        for share in shares(&model, splits) {
+            *out.entry(share.team).or_default() += share.amount;
+        }
+    }
+    Ok(out)
+}
+
+/// The shares `team` pays of the orders placed in `season`, leaving out the
+/// order `excluding` if given
+pub async fn committed_in_season(
+    db: &impl ConnectionTrait,
+    team: Team,
+    season: u16,
+    excluding: Option<i64>,
+) -> Result<Decimal, sea_orm::DbErr> {
+    let mut query = order::Entity::find().filter(order::Column::Season.eq(season));
+    if let Some(id) = excluding {
+        query = query.filter(order::Column::Id.ne(id));
+    }
+    let splits = all_splits(db).await?;
+    let unspent = unspent_cancelled(db).await?;
+    let mut committed = Decimal::ZERO;
+    for model in query.all(db).await? {
+        if unspent.contains(&model.id) {
+            continue;
+        }
+        let splits = splits.get(&model.id).map(Vec::as_slice).unwrap_or_default();
+        committed += shares(&model, splits)
+            .iter()
+            .filter(|share| share.team == team)
+            .map(|share| share.amount)
+            .sum::<Decimal>();
+    }
+    Ok(committed)
+}
+
+/// Where a team stands against its budget for a season
+#[derive(Serialize, Clone)]
+pub struct Standing {
+    pub team: Team,
+    pub season: u16,
+    pub allocated: Decimal,
+    pub committed: Decimal,
+    pub remaining: Decimal,
+    pub overrun: season_budget::Overrun,
+}
+
+impl Standing {
+    /// The line added to order messages
+    pub fn line(&self) -> String {
+        if self.remaining.is_sign_negative() {
+            format!(
+                "\n**Over Budget:** {} over its {} budget",
+                money::dollars(-self.remaining),
+                money::dollars(self.allocated)
+            )
+        } else {
+            format!(
+                "\n**Budget Remaining:** {} of {}",
+                money::dollars(self.remaining),
+                money::dollars(self.allocated)
+            )
+        }
+    }
+}
+
+/// Where `team` stands in `season`, if it has a budget for it. `excluding`
+/// leaves out an order, such as one about to be changed.
+pub async fn standing(
+    db: &impl ConnectionTrait,
+    team: Team,
+    season: u16,
+    excluding: Option<i64>,
+) -> Result<Option<Standing>, sea_orm::DbErr> {
+    let Some(budget) = season_budget::Entity::find_by_id((team, season.into()))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let committed = committed_in_season(db, team, season, excluding).await?;
+    Ok(Some(Standing {
+        team,
+        season,
+        allocated: budget.allocated,
+        committed,
+        remaining: budget.allocated - committed,
+        overrun: budget.overrun,
+    }))
+}
+
+/// The open budget period, if one has been opened, with each team's
+/// allocation for it
+pub async fn open_period(
+    db: &impl ConnectionTrait,
+) -> Result<Option<(budget_period::Model, HashMap<Team, Decimal>)>, sea_orm::DbErr> {
+    let Some(period) = budget_period::Entity::find()
+        .filter(budget_period::Column::End.is_null())
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let allocations = period_total::Entity::find()
+        .filter(period_total::Column::PeriodId.eq(period.id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| (model.team, model.allocated))
+        .collect();
+    Ok(Some((period, allocations)))
+}
+
+/// Announces the highest of `thresholds`, in percent of its team's budget,
+/// that placing `order` pushed the team's spending past. Runs in the
+/// transaction that inserted `order`, so concurrent orders can't both see the
+/// spending from before the other and announce the same threshold twice, or
+/// neither announce it.
+pub async fn threshold_alert(
+    db: &impl ConnectionTrait,
+    order: &order::Model,
+    budgets: &HashMap<Team, Decimal>,
+    thresholds: &[u8],
+    lead_role: Option<u64>,
+) -> Result<Option<String>, sea_orm::DbErr> {
+    let (start, budgets) = match open_period(db).await? {
+        Some((period, allocations)) => (period.start, allocations),
+        None => (NaiveDateTime::MIN, budgets.clone()),
+    };
+    let Some(budget) = budgets
+        .get(&order.team)
+        .copied()
+        .filter(|budget| budget.is_sign_positive() && !budget.is_zero())
+    else {
+        return Ok(None);
+    };
+    let after = spend_between(db, start, None)
+        .await?
+        .get(&order.team)
+        .copied()
+        .unwrap_or_default();
+    let before = after - money::subtotal(order.count, order.unit_cost);
+    let Some(threshold) = thresholds
+        .iter()
+        .copied()
+        .filter(|threshold| {
+            let line = budget * Decimal::from(*threshold) / Decimal::ONE_HUNDRED;
+            before < line && after >= line
+        })
+        .max()
+    else {
+        return Ok(None);
+    };
+
+    let mut msg = format!("**Budget Alert: {}**
+", order.team);
+    if let Some(role) = lead_role {
+        msg.push_str(&format!("<@&{role}> "));
+    }
+    msg.push_str(&format!(
+        "{} has committed {} of its {} budget ({:.0}%), passing {threshold}% with order {}",
+        order.team,
+        money::dollars(after),
+        money::dollars(budget),
+        after / budget * Decimal::ONE_HUNDRED,
+        order.number()
+    ));
+    Ok(Some(msg))
+}
+
+#[derive(Serialize)]
+pub struct Rollover {
+    closed: budget_period::Model,
+    /// What each team was given and spent in the closed period
+    totals: Vec<period_total::Model>,
+    opened: budget_period::Model,
+    allocations: Vec<period_total::Model>,
+}
+
+async fn insert_total(
+    tx: &DatabaseTransaction,
+    period_id: i64,
+    team: Team,
+    allocated: Decimal,
+    carried_over: Decimal,
+) -> Result<(), sea_orm::DbErr> {
+    period_total::ActiveModel {
+        period_id: ActiveValue::Set(period_id),
+        team: ActiveValue::Set(team),
+        allocated: ActiveValue::Set(allocated),
+        carried_over: ActiveValue::Set(carried_over),
+        spent: ActiveValue::Set(None),
+    }
+    .insert(tx)
+    .await?;
+    Ok(())
+}
+
+async fn totals_of(
+    tx: &DatabaseTransaction,
+    period_id: i64,
+) -> Result<Vec<period_total::Model>, sea_orm::DbErr> {
+    period_total::Entity::find()
+        .filter(period_total::Column::PeriodId.eq(period_id))
+        .order_by_asc(period_total::Column::Team)
+        .all(tx)
+        .await
+}
+
+/// Closes the open budget period, recording what each team spent in it, and
+/// opens `name` with `budgets` as the allocations. With `carry_over`, each
+/// team's balance, surplus or deficit, is added to its new allocation.
+pub async fn roll_over(
+    tx: &DatabaseTransaction,
+    name: String,
+    budgets: &HashMap<Team, Decimal>,
+    carry_over: bool,
+    now: NaiveDateTime,
+) -> Result<Rollover, sea_orm::DbErr> {
+    let (closing, allocations) = match open_period(tx).await? {
+        Some(open) => open,
+        None => {
+            // Before the first rollover, everything so far is one period
+            // under the configured budgets
+            let start = order_status::Entity::find()
+                .order_by_asc(order_status::Column::Date)
+                .one(tx)
+                .await?
+                .map_or(now, |model| model.date);
+            let period = budget_period::ActiveModel {
+                id: ActiveValue::NotSet,
+                name: ActiveValue::Set("Initial".to_string()),
+                start: ActiveValue::Set(start),
+                end: ActiveValue::Set(None),
+            }
+            .insert(tx)
+            .await?;
+            for (team, budget) in budgets {
+                insert_total(tx, period.id, *team, *budget, Decimal::ZERO).await?;
+            }
+            (period, budgets.clone())
+        }
+    };
+
+    let spent = spend_between(tx, closing.start, Some(now)).await?;
+    let teams: HashSet<_> = allocations.keys().chain(spent.keys()).copied().collect();
+    let mut carried = HashMap::<Team, Decimal>::new();
+    for team in teams {
+        let allocated = allocations.get(&team).copied().unwrap_or_default();
+        let spent = spent.get(&team).copied().unwrap_or_default();
+        period_total::Entity::insert(period_total::ActiveModel {
+            period_id: ActiveValue::Set(closing.id),
+            team: ActiveValue::Set(team),
+            allocated: ActiveValue::Set(allocated),
+            carried_over: ActiveValue::Set(Decimal::ZERO),
+            spent: ActiveValue::Set(Some(spent)),
+        })
+        .on_conflict(
+            OnConflict::columns([period_total::Column::PeriodId, period_total::Column::Team])
+                .update_column(period_total::Column::Spent)
+                .to_owned(),
+        )
+        .exec(tx)
+        .await?;
+        // Teams without a budget have nothing to carry
+        if carry_over && allocations.contains_key(&team) && allocated != spent {
+            carried.insert(team, allocated - spent);
+        }
+    }
+    let closed = budget_period::ActiveModel {
+        id: ActiveValue::Unchanged(closing.id),
+        name: ActiveValue::NotSet,
+        start: ActiveValue::NotSet,
+        end: ActiveValue::Set(Some(now)),
+    }
+    .update(tx)
+    .await?;
+
+    let opened = budget_period::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(name),
+        start: ActiveValue::Set(now),
+        end: ActiveValue::Set(None),
+    }
+    .insert(tx)
+    .await?;
+    let teams: HashSet<_> = budgets.keys().chain(carried.keys()).copied().collect();
+    for team in teams {
+        let carried_over = carried.get(&team).copied().unwrap_or_default();
+        let budget = budgets.get(&team).copied().unwrap_or_default();
+        insert_total(tx, opened.id, team, budget + carried_over, carried_over).await?;
+    }
+
+    Ok(Rollover {
+        totals: totals_of(tx, closed.id).await?,
+        allocations: totals_of(tx, opened.id).await?,
+        closed,
+        opened,
+    })
+}
+
+impl Rollover {
+    pub fn opened_id(&self) -> u32 {
+        self.opened.id as u32
+    }
+
+    /// Announces the new period's allocations
+    pub fn message(&self) -> String {
+        let mut msg = format!(
+            "**Budget Period Opened: {}**\n{} closed",
+            self.opened.name, self.closed.name
+        );
+        for total in &self.allocations {
+            msg.push_str(&format!(
+                "\n**{}:** {}",
+                total.team,
+                money::dollars(total.allocated)
+            ));
+            if total.carried_over.is_sign_negative() {
+                msg.push_str(&format!(
+                    " ({} overspent last period)",
+                    money::dollars(-total.carried_over)
+                ));
+            } else if !total.carried_over.is_zero() {
+                msg.push_str(&format!(
+                    " ({} carried over)",
+                    money::dollars(total.carried_over)
+                ));
+            }
+        }
+        msg
+    }
+}
@@ -0,0 +1,28 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// Stock taken out of inventory to be used, and how much of it came back
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "inventory_checkouts")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub order_id: i64,
+    /// Where it was taken from
+    pub location: String,
+    pub count: i64,
+    pub returned: i64,
+    pub taken_by: String,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub taken: DateTime,
+    #[sea_orm(nullable)]
+    pub last_returned: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
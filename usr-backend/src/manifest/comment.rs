@@ -0,0 +1,182 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, QueryOrder};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{api_error::ApiError, backup::backup_db, notify::Topic, UsrState};
+
+use super::{notify_watchers, order, permalink, policy, OrderRef};
+
+/// The longest a comment can be, so that it fits in one Discord message
+const MAX_COMMENT_CHARS: usize = 1500;
+
+/// A note left on an order, eg. the treasurer asking which variant was meant
+/// and the requester answering, so that the conversation stays with the order
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "order_comments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub order_id: i64,
+    pub author: String,
+    pub text: String,
+    pub created: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+async fn find_order(db: &DatabaseConnection, id: &OrderRef) -> Result<order::Model, ApiError> {
+    let found = match id.resolve(db).await {
+        Ok(Some(id)) => order::Entity::find_by_id(id).one(db).await,
+        Ok(None) => Ok(None),
+        Err(e) => Err(e),
+    };
+    match found {
+        Ok(Some(model)) => Ok(model),
+        Ok(None) => Err(ApiError::new(
+            axum::http::StatusCode::NOT_FOUND,
+            "Order not found",
+        )),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            Err(ApiError::internal())
+        }
+    }
+}
+
+/// The order's comments, oldest first
+#[axum::debug_handler]
+pub async fn get_comments(
+    State(state): State<&'static UsrState>,
+    Path(id): Path<OrderRef>,
+) -> Result<Json<Vec<Model>>, ApiError> {
+    let model = find_order(&state.db, &id).await?;
+    Entity::find()
+        .filter(Column::OrderId.eq(model.id))
+        .order_by_asc(Column::Id)
+        .all(&state.db)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Failed to get comments: {e}");
+            ApiError::internal()
+        })
+}
+
+#[derive(Deserialize)]
+pub struct NewComment {
+    pub text: String,
+    /// Also posts the comment to the order updates webhook, mentioning the
+    /// order's watchers
+    #[serde(default)]
+    pub notify: bool,
+}
+
+#[axum::debug_handler]
+pub async fn new_comment(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Path(id): Path<OrderRef>,
+    Json(comment): Json<NewComment>,
+) -> Response {
+    let text = comment.text.trim();
+    if text.is_empty() {
+        return ApiError::invalid("Comment cannot be empty")
+            .for_field("text")
+            .into_response();
+    }
+    if text.chars().count() > MAX_COMMENT_CHARS {
+        return ApiError::invalid(format!(
+            "Comments can be at most {MAX_COMMENT_CHARS} characters"
+        ))
+        .for_field("text")
+        .into_response();
+    }
+    let model = match find_order(&state.db, &id).await {
+        Ok(model) => model,
+        Err(e) => return e.into_response(),
+    };
+    let author = caller
+        .name
+        .clone()
+        .unwrap_or_else(|| caller.role.to_string());
+    let result = ActiveModel {
+        id: ActiveValue::NotSet,
+        order_id: ActiveValue::Set(model.id),
+        author: ActiveValue::Set(author),
+        text: ActiveValue::Set(text.to_string()),
+        created: ActiveValue::Set(chrono::Local::now().naive_local()),
+    }
+    .insert(&state.db)
+    .await;
+    let comment_model = match result {
+        Ok(comment_model) => comment_model,
+        Err(e) => {
+            error!("Failed to add comment: {e}");
+            return ApiError::internal().into_response();
+        }
+    };
+    backup_db(state);
+    if comment.notify {
+        let msg = format!(
+            "**{} commented on {}**\n**Name:** {}\n>>> {}{}",
+            comment_model.author,
+            model.number(),
+            model.name,
+            comment_model.text,
+            permalink::line(&model)
+        );
+        let msg = notify_watchers(state, model.id, msg).await;
+        // Comments are keyed apart from their order, so that one doesn't
+        // replace an update to the order waiting in the same batch
+        state.notifier.send_for(
+            model.team,
+            Topic::OrderUpdate,
+            u32::MAX / 16 * 3 + comment_model.id as u32,
+            msg,
+        );
+    }
+    Json(comment_model).into_response()
+}
+
+/// Members can only remove their own comments
+#[axum::debug_handler]
+pub async fn del_comment(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Path((id, comment_id)): Path<(OrderRef, u32)>,
+) -> Result<(), ApiError> {
+    let model = find_order(&state.db, &id).await?;
+    let comment = match Entity::find_by_id(comment_id).one(&state.db).await {
+        Ok(Some(comment)) if comment.order_id == model.id => comment,
+        Ok(_) => {
+            return Err(ApiError::new(
+                axum::http::StatusCode::NOT_FOUND,
+                "Comment not found",
+            ))
+        }
+        Err(e) => {
+            error!("Failed to find comment: {e}");
+            return Err(ApiError::internal());
+        }
+    };
+    if caller.role < policy::Role::Lead && caller.name.as_ref() != Some(&comment.author) {
+        return Err(ApiError::forbidden(format!(
+            "{} can only remove their own comments",
+            caller.role
+        )));
+    }
+    if let Err(e) = Entity::delete_by_id(comment.id).exec(&state.db).await {
+        error!("Failed to delete comment: {e}");
+        return Err(ApiError::internal());
+    }
+    backup_db(state);
+    Ok(())
+}
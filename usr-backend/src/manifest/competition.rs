@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{prelude::Decimal, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{notify::Topic, scheduler::Team, UsrState};
+
+use super::{current, current_season, order, order_status::Status};
+
+/// The runtime flag that turns competition mode on, set through `/set/flag`
+/// like any other
+pub const FLAG: &str = "competition_mode";
+
+/// How an event is run differently while competition mode is on, when
+/// orders have to go out within hours rather than days
+#[derive(Deserialize)]
+pub struct CompetitionConfig {
+    /// Orders costing up to this much in total can be bought without a
+    /// lead's approval. Left out, every order still needs one.
+    #[serde(default)]
+    pub approval_waiver: Option<Decimal>,
+    /// Discord webhook of the event's own channel, which notifications about
+    /// `topics` go to instead of their usual places
+    #[serde(default)]
+    pub webhook: Option<String>,
+    #[serde(default = "default_topics")]
+    pub topics: Vec<Topic>,
+    /// Hours between requesters' order digests, instead of once a day
+    #[serde(default = "default_digest_hours")]
+    pub digest_hours: u32,
+}
+
+fn default_topics() -> Vec<Topic> {
+    vec![
+        Topic::NewOrder,
+        Topic::OrderReminder,
+        Topic::OrderUpdate,
+        Topic::OrderApproval,
+    ]
+}
+
+fn default_digest_hours() -> u32 {
+    4
+}
+
+impl Default for CompetitionConfig {
+    fn default() -> Self {
+        Self {
+            approval_waiver: None,
+            webhook: None,
+            topics: default_topics(),
+            digest_hours: default_digest_hours(),
+        }
+    }
+}
+
+impl CompetitionConfig {
+    pub fn validate(&self, problems: &mut Vec<String>) {
+        if self
+            .approval_waiver
+            .is_some_and(|waiver| waiver.is_sign_negative())
+        {
+            problems.push("competition.approval_waiver: must not be negative".to_string());
+        }
+        if !(1..=12).contains(&self.digest_hours) {
+            problems.push("competition.digest_hours: must be between 1 and 12".to_string());
+        }
+    }
+}
+
+/// Whether competition mode is on
+pub fn active(state: &UsrState) -> bool {
+    state.flag_enabled(FLAG)
+}
+
+/// Whether `order` can be bought without a lead's approval, because
+/// competition mode is on and it costs no more than the waiver
+pub fn waives_approval(state: &UsrState, order: &order::Model) -> bool {
+    active(state)
+        && state
+            .competition
+            .approval_waiver
+            .is_some_and(|waiver| order.total() <= waiver)
+}
+
+/// Hours between requesters' order digests, if competition mode shortens them
+pub fn digest_hours(state: &UsrState) -> Option<u32> {
+    active(state).then_some(state.competition.digest_hours)
+}
+
+#[derive(Serialize, Default)]
+struct TeamReport {
+    orders: u32,
+    /// Of those, the ones that were cancelled, which aren't in `total`
+    cancelled: u32,
+    total: Decimal,
+}
+
+#[derive(Serialize)]
+struct CompetitionReport {
+    season: u16,
+    /// Whether competition mode is still on, so more orders may be added
+    active: bool,
+    teams: HashMap<Team, TeamReport>,
+    total: Decimal,
+    orders: Vec<current::Model>,
+}
+
+#[derive(Deserialize)]
+pub struct CompetitionQuery {
+    /// The current season if left out
+    #[serde(default)]
+    season: Option<u16>,
+}
+
+/// Every order placed during competition mode in a season, and what each
+/// team spent on them
+#[axum::debug_handler]
+pub async fn get_competition_report(
+    State(state): State<&'static UsrState>,
+    Query(query): Query<CompetitionQuery>,
+) -> Response {
+    let season = query.season.unwrap_or_else(current_season);
+    let orders = match current::Entity::find()
+        .filter(current::Column::Competition.eq(true))
+        .filter(current::Column::Season.eq(season))
+        .order_by_asc(current::Column::Id)
+        .all(&state.db)
+        .await
+    {
+        Ok(orders) => orders,
+        Err(e) => {
+            error!("Failed to find competition orders: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let mut teams = HashMap::<Team, TeamReport>::new();
+    let mut total = Decimal::ZERO;
+    for model in &orders {
+        let report = teams.entry(model.team).or_default();
+        report.orders += 1;
+        if model.status == Status::Cancelled {
+            report.cancelled += 1;
+            continue;
+        }
+        let cost = model.clone().into_parts().0.total();
+        report.total += cost;
+        total += cost;
+    }
+    Json(CompetitionReport {
+        season,
+        active: active(state),
+        teams,
+        total,
+        orders,
+    })
+    .into_response()
+}
@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+use crate::scheduler;
+
+use super::funding;
+
+/// Part of an order's cost that another team or funding source pays for.
+/// Whatever isn't split off is paid by the order's own team and source.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "cost_splits")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub order_id: i64,
+    pub team: scheduler::Team,
+    pub funding_source: funding::Source,
+    /// A share of the subtotal, out of 100. Exactly one of this and `amount`
+    /// is set.
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent: Option<Decimal>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Decimal>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,186 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{scheduler::Team, travel, UsrState};
+
+use super::{
+    current, lead_time,
+    order_status::{self, Status},
+    vendor_lead_times,
+};
+
+/// How sure it is that an order misses ship-out day, most sure first
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Risk {
+    /// Usually arrives after ship-out day
+    Late,
+    /// Usually arrives in time, but the vendor's slow orders don't
+    Possible,
+    /// The vendor hasn't delivered an order yet, so there's nothing to go by
+    Unknown,
+}
+
+#[derive(Serialize)]
+pub struct OpenOrder {
+    pub order_id: i64,
+    pub number: String,
+    pub name: String,
+    pub team: Team,
+    pub vendor: String,
+    pub status: Status,
+    pub risk: Risk,
+    /// When it usually would arrive, from the vendor's median lead time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicted: Option<NaiveDate>,
+    /// When it would arrive if it is as slow as the vendor's slowest tenth
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicted_late: Option<NaiveDate>,
+    /// How many days after ship-out day it would arrive, by `predicted` for
+    /// late orders and by `predicted_late` for those possibly late
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub days_late: Option<i64>,
+}
+
+/// Orders that haven't arrived and might not by `ship_out`, riskiest first.
+/// Orders that haven't been submitted are predicted as if they were today.
+pub async fn at_risk(
+    db: &DatabaseConnection,
+    ship_out: NaiveDate,
+    now: NaiveDateTime,
+) -> Result<Vec<OpenOrder>, sea_orm::DbErr> {
+    let open = [
+        Status::New,
+        Status::Submitted,
+        Status::Shipped,
+        Status::PartiallyReceived,
+        Status::OnHold,
+    ];
+    let (orders, submitted, lead_times) = tokio::join!(
+        current::Entity::find()
+            .filter(current::Column::Status.is_in(open))
+            .all(db),
+        order_status::Entity::find()
+            .filter(order_status::Column::Status.eq(Status::Submitted))
+            .all(db),
+        vendor_lead_times(db),
+    );
+    let lead_times = lead_times?;
+    let mut ordered = HashMap::new();
+    for model in submitted? {
+        ordered
+            .entry(model.order_id)
+            .and_modify(|date: &mut NaiveDateTime| *date = (*date).min(model.date))
+            .or_insert(model.date);
+    }
+
+    let today = now.date();
+    let mut out = vec![];
+    for model in orders? {
+        let (order, status) = model.into_parts();
+        let base = ordered.get(&order.id).copied().unwrap_or(now);
+        // An order that should have arrived already is at least as late as today
+        let (predicted, predicted_late, risk, days_late) =
+            match lead_times.get(&lead_time::vendor_key(&order.vendor)) {
+                Some(lead_time) => {
+                    let predicted = lead_time.predict(base).date().max(today);
+                    let late = lead_time.predict_late(base).date().max(today);
+                    let (risk, days_late) = if predicted > ship_out {
+                        (Risk::Late, predicted - ship_out)
+                    } else if late > ship_out {
+                        (Risk::Possible, late - ship_out)
+                    } else {
+                        continue;
+                    };
+                    (
+                        Some(predicted),
+                        Some(late),
+                        risk,
+                        Some(days_late.num_days()),
+                    )
+                }
+                None => (None, None, Risk::Unknown, None),
+            };
+        out.push(OpenOrder {
+            order_id: order.id,
+            number: order.number(),
+            name: order.name,
+            team: order.team,
+            vendor: order.vendor,
+            status,
+            risk,
+            predicted,
+            predicted_late,
+            days_late,
+        });
+    }
+    out.sort_by_key(|order| (order.risk, Reverse(order.days_late), order.order_id));
+    Ok(out)
+}
+
+#[derive(Deserialize)]
+pub struct CountdownQuery {
+    /// The day everything has to be packed by, the start of the next trip by
+    /// default
+    #[serde(default)]
+    ship_out: Option<NaiveDate>,
+}
+
+#[derive(Serialize)]
+pub struct Countdown {
+    pub ship_out: NaiveDate,
+    /// The trip whose start is ship-out day, if it wasn't given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip: Option<String>,
+    pub days_left: i64,
+    pub orders: Vec<OpenOrder>,
+}
+
+/// Open orders that might not arrive before the team ships out for the next
+/// competition
+#[axum::debug_handler]
+pub async fn get_countdown(
+    State(state): State<&'static UsrState>,
+    Query(CountdownQuery { ship_out }): Query<CountdownQuery>,
+) -> Response {
+    let now = Local::now().naive_local();
+    let (ship_out, trip) = match ship_out {
+        Some(ship_out) => (ship_out, None),
+        None => match travel::next_trip(&state.db, now.date()).await {
+            Ok(Some(trip)) => (trip.start_date, Some(trip.name)),
+            Ok(None) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    "No upcoming trip, so ship_out must be given",
+                )
+                    .into_response()
+            }
+            Err(e) => {
+                error!("Failed to find next trip: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+            }
+        },
+    };
+    match at_risk(&state.db, ship_out, now).await {
+        Ok(orders) => Json(Countdown {
+            ship_out,
+            trip,
+            days_left: (ship_out - now.date()).num_days(),
+            orders,
+        })
+        .into_response(),
+        Err(e) => {
+            error!("Failed to get orders at risk: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::scheduler;
+
+use super::{funding, order, order_status};
+
+/// Each order alongside its latest status. Kept as a view so that it stays
+/// in step with orders and statuses without any bookkeeping of its own.
+pub const CREATE_VIEW: &str = "CREATE VIEW order_current AS
+SELECT orders.*, order_status.status AS status, order_status.date AS status_date,
+order_status.reason AS hold_reason
+FROM orders
+JOIN order_status ON order_status.order_id = orders.id
+AND order_status.instance_id = (
+    SELECT MAX(latest.instance_id) FROM order_status AS latest WHERE latest.order_id = orders.id
+)";
+
+/// Read only, since it is backed by `order_current`, a view
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "order_current")]
+#[schema(as = CurrentOrder)]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    pub count: i64,
+    pub unit_cost: Decimal,
+    pub store_in: String,
+    pub team: scheduler::Team,
+    pub reason: String,
+    pub vendor: String,
+    pub link: String,
+    pub funding_source: funding::Source,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component_id: Option<i64>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_number: Option<i64>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub season: Option<i64>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub season_number: Option<i64>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax_exempt: Option<bool>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_method: Option<order::PaymentMethod>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requester: Option<String>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<order::Currency>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_unit_cost: Option<Decimal>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_rate: Option<Decimal>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cart_id: Option<String>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<NaiveDate>)]
+    pub needed_by: Option<Date>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shipping_cost: Option<Decimal>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax: Option<Decimal>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees: Option<Decimal>,
+    pub version: i64,
+    pub competition: bool,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub received_count: Option<i64>,
+    pub status: order_status::Status,
+    /// When the order moved into `status`
+    pub status_date: DateTime,
+    /// Why the order is on hold, if it is
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hold_reason: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Splits the order from its latest status
+    pub fn into_parts(self) -> (order::Model, order_status::Status) {
+        let order = order::Model {
+            id: self.id,
+            name: self.name,
+            count: self.count,
+            unit_cost: self.unit_cost,
+            store_in: self.store_in,
+            team: self.team,
+            reason: self.reason,
+            vendor: self.vendor,
+            link: self.link,
+            funding_source: self.funding_source,
+            component_id: self.component_id,
+            ref_number: self.ref_number,
+            season: self.season,
+            season_number: self.season_number,
+            tax_exempt: self.tax_exempt,
+            payment_method: self.payment_method,
+            requester: self.requester,
+            currency: self.currency,
+            original_unit_cost: self.original_unit_cost,
+            exchange_rate: self.exchange_rate,
+            cart_id: self.cart_id,
+            needed_by: self.needed_by,
+            shipping_cost: self.shipping_cost,
+            tax: self.tax,
+            fees: self.fees,
+            version: self.version,
+            competition: self.competition,
+            received_count: self.received_count,
+        };
+        (order, self.status)
+    }
+}
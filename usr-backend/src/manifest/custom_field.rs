@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use sea_orm::{entity::prelude::*, ActiveValue};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::error;
+
+use super::{audit, field_value, service::OrderError};
+
+/// A field that admins have added to every order, eg. an export control flag
+/// or the account code an order is charged to
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "custom_fields")]
+pub struct Model {
+    /// What the field is called in requests, eg. `account_code`
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub key: String,
+    /// What the field is called in the web UI, eg. `Account Code`
+    pub label: String,
+    pub kind: Kind,
+    /// New orders can't be placed without it
+    pub required: bool,
+    pub created: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Kind {
+    #[sea_orm(string_value = "T")]
+    Text,
+    #[sea_orm(string_value = "N")]
+    Number,
+    #[sea_orm(string_value = "B")]
+    Flag,
+    #[sea_orm(string_value = "D")]
+    Date,
+}
+
+/// Whether `key` can be used as a field's key. Keys are kept to what reads
+/// well in a query string, eg. `?field=account_code:1234`.
+pub fn valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= 32
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+impl Model {
+    /// The value as it is stored, so that equal values are stored the same
+    /// way. Strings are accepted for every kind, as query strings only have
+    /// those.
+    pub fn normalize(&self, value: &Value) -> Result<Value, String> {
+        let normalized = match (self.kind, value) {
+            (Kind::Text, Value::String(text)) => {
+                Some(Value::String(text.trim().to_string())).filter(|_| !text.trim().is_empty())
+            }
+            (Kind::Number, Value::Number(number)) => Some(Value::Number(number.clone())),
+            (Kind::Number, Value::String(text)) => text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number),
+            (Kind::Flag, Value::Bool(flag)) => Some(Value::Bool(*flag)),
+            (Kind::Flag, Value::String(text)) => text.trim().parse::<bool>().ok().map(Value::Bool),
+            (Kind::Date, Value::String(text)) => NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+                .ok()
+                .map(|date| Value::String(date.to_string())),
+            _ => None,
+        };
+        normalized.ok_or_else(|| {
+            let expected = match self.kind {
+                Kind::Text => "some text",
+                Kind::Number => "a number",
+                Kind::Flag => "true or false",
+                Kind::Date => "a date like 2026-01-31",
+            };
+            format!("{} must be {expected}", self.label)
+        })
+    }
+}
+
+/// Checks the fields given for an order against those defined, returning them
+/// as they will be stored. When `new` is set, required fields must be given.
+pub async fn check(
+    db: &impl ConnectionTrait,
+    fields: &HashMap<String, Value>,
+    new: bool,
+) -> Result<Vec<(String, Option<Value>)>, OrderError> {
+    let defined = Entity::find().all(db).await.map_err(|e| {
+        error!("Failed to get custom fields: {e}");
+        OrderError::Internal
+    })?;
+    if let Some(key) = fields
+        .keys()
+        .find(|key| !defined.iter().any(|field| &field.key == *key))
+    {
+        return Err(OrderError::Invalid(format!(
+            "There is no field called {key}"
+        )));
+    }
+    let mut checked = vec![];
+    for field in defined {
+        match fields.get(&field.key) {
+            None | Some(Value::Null) if new && field.required => {
+                return Err(OrderError::Invalid(format!("{} is required", field.label)));
+            }
+            Some(Value::Null) if field.required => {
+                return Err(OrderError::Invalid(format!(
+                    "{} is required, so it can't be removed",
+                    field.label
+                )));
+            }
+            None => {}
+            Some(Value::Null) => checked.push((field.key, None)),
+            Some(value) => {
+                let value = field.normalize(value).map_err(OrderError::Invalid)?;
+                checked.push((field.key, Some(value)));
+            }
+        }
+    }
+    Ok(checked)
+}
+
+/// The order's values, by key
+pub async fn values(
+    db: &impl ConnectionTrait,
+    order_id: i64,
+) -> Result<HashMap<String, Value>, DbErr> {
+    Ok(field_value::Entity::find()
+        .filter(field_value::Column::OrderId.eq(order_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| {
+            let value = serde_json::from_str(&model.value).unwrap_or_default();
+            (model.field, value)
+        })
+        .collect())
+}
+
+/// Sets or removes the order's fields, as returned by [check], returning what
+/// changed for the audit log
+pub async fn store(
+    db: &impl ConnectionTrait,
+    order_id: i64,
+    fields: Vec<(String, Option<Value>)>,
+) -> Result<Map<String, Value>, DbErr> {
+    let mut diff = Map::new();
+    for (key, value) in fields {
+        let before = field_value::Entity::find_by_id((order_id, key.clone()))
+            .one(db)
+            .await?
+            .map(|model| serde_json::from_str::<Value>(&model.value).unwrap_or_default());
+        if before == value {
+            continue;
+        }
+        field_value::Entity::delete_by_id((order_id, key.clone()))
+            .exec(db)
+            .await?;
+        if let Some(value) = &value {
+            field_value::ActiveModel {
+                order_id: ActiveValue::Set(order_id),
+                field: ActiveValue::Set(key.clone()),
+                value: ActiveValue::Set(value.to_string()),
+            }
+            .insert(db)
+            .await?;
+        }
+        diff.extend(audit::change(&format!("fields.{key}"), before, value));
+    }
+    Ok(diff)
+}
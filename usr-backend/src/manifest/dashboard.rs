@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use chrono::{Days, Local, NaiveDate, NaiveDateTime};
+use sea_orm::{entity::prelude::*, Iterable, QueryOrder};
+use serde::Serialize;
+
+use crate::{scheduler::Team, UsrState};
+
+use super::{
+    arrivals::{self, Arrival},
+    budget::{self, Standing},
+    current, current_season, order_status,
+    rollup::TeamRollup,
+};
+
+/// An order that hasn't been put in storage yet, as the wall dashboard
+/// lists it
+#[derive(Serialize)]
+pub struct OpenOrder {
+    pub id: i64,
+    pub number: String,
+    pub name: String,
+    pub team: Team,
+    pub vendor: String,
+    pub count: i64,
+    pub status: order_status::Status,
+    pub status_date: NaiveDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub needed_by: Option<NaiveDate>,
+}
+
+/// The manifest's part of the wall dashboard
+#[derive(Serialize)]
+pub struct Section {
+    /// Most recently moved first
+    pub orders: Vec<OpenOrder>,
+    pub teams: HashMap<Team, TeamRollup>,
+    /// Each team with a budget for the current season
+    pub budgets: Vec<Standing>,
+    /// Orders that were delivered or put in storage today
+    pub arrivals: Vec<Arrival>,
+}
+
+pub async fn section(state: &UsrState) -> Result<Section, DbErr> {
+    let db = &state.db;
+    let orders = current::Entity::find()
+        .filter(current::Column::Status.is_not_in([
+            order_status::Status::InStorage,
+            order_status::Status::Returned,
+            order_status::Status::Cancelled,
+        ]))
+        .order_by_desc(current::Column::StatusDate)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| {
+            let status_date = model.status_date;
+            let (order, status) = model.into_parts();
+            OpenOrder {
+                id: order.id,
+                number: order.number(),
+                name: order.name,
+                team: order.team,
+                vendor: order.vendor,
+                count: order.count,
+                status,
+                status_date,
+                needed_by: order.needed_by,
+            }
+        })
+        .collect();
+
+    let mut budgets = vec![];
+    for team in Team::iter() {
+        if let Some(standing) = budget::standing(db, team, current_season(), None).await? {
+            budgets.push(standing);
+        }
+    }
+
+    let today = Local::now().date_naive();
+    let arrivals = arrivals::arrivals(
+        db,
+        today.and_time(Default::default()),
+        (today + Days::new(1)).and_time(Default::default()),
+    )
+    .await?;
+
+    Ok(Section {
+        orders,
+        teams: state.rollups.get(),
+        budgets,
+        arrivals,
+    })
+}
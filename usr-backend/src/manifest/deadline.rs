@@ -0,0 +1,151 @@
+use std::{collections::HashSet, time::Duration};
+
+use crate::{
+    jobs::{self, Retry, Schedule},
+    notify::Topic,
+    UsrState,
+};
+use chrono::Local;
+use sea_orm::{
+    entity::prelude::*, sea_query::Expr, ActiveValue, Condition, DatabaseConnection, QueryOrder,
+};
+use serde::Serialize;
+
+use super::{current, order_status, permalink};
+
+/// A reminder that has been posted about an order's `needed_by` date, or its
+/// return window, so that each is only posted once
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "deadline_reminders")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub order_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub stage: Stage,
+    pub sent: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Hash)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Stage {
+    /// The order is needed within `needed_by_reminder_days`
+    #[sea_orm(string_value = "S")]
+    Soon,
+    /// The order was needed by a day that has passed
+    #[sea_orm(string_value = "O")]
+    Overdue,
+    /// The return window of an order being returned closes soon
+    #[sea_orm(string_value = "R")]
+    ReturnWindow,
+}
+
+/// Forgets the reminders posted about an order, so that a new `needed_by`
+/// date is reminded about afresh
+pub async fn reset(db: &impl ConnectionTrait, order_id: i64) -> Result<(), DbErr> {
+    Entity::delete_many()
+        .filter(Column::OrderId.eq(order_id))
+        .filter(Column::Stage.is_in([Stage::Soon, Stage::Overdue]))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Posts a reminder for each order that hasn't been bought yet and is needed
+/// within `days`, and again once it is overdue
+async fn remind(state: &'static UsrState, days: u32) -> Result<(), DbErr> {
+    let db: &DatabaseConnection = &state.db;
+    let today = Local::now().date_naive();
+    let cutoff = today + chrono::Days::new(days.into());
+    let waiting = current::Entity::find()
+        .filter(
+            Condition::any()
+                .add(current::Column::Status.eq(order_status::Status::New))
+                .add(current::Column::Status.eq(order_status::Status::OnHold)),
+        )
+        .filter(current::Column::NeededBy.is_not_null())
+        .filter(Expr::col(current::Column::NeededBy).lte(cutoff))
+        .order_by_asc(current::Column::NeededBy)
+        .all(db)
+        .await?;
+    let sent: HashSet<_> = Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| (model.order_id, model.stage))
+        .collect();
+
+    for model in waiting {
+        let Some(needed_by) = model.needed_by else {
+            continue;
+        };
+        let stage = if needed_by < today {
+            Stage::Overdue
+        } else {
+            Stage::Soon
+        };
+        if sent.contains(&(model.id, stage)) {
+            continue;
+        }
+        let (order, status) = model.into_parts();
+        let mention = state
+            .team_lead_roles
+            .get(&order.team)
+            .map(|role| format!("<@&{role}> "))
+            .unwrap_or_default();
+        let when = match (needed_by - today).num_days() {
+            ..0 => format!("was needed by {needed_by}"),
+            0 => "is needed today".to_string(),
+            1 => "is needed tomorrow".to_string(),
+            days => format!("is needed by {needed_by}, in {days} days"),
+        };
+        let title = match stage {
+            Stage::Soon => "Order Needed Soon",
+            _ => "Order Overdue",
+        };
+        state.notifier.send_for(
+            order.team,
+            Topic::OrderUpdate,
+            order.id as u32,
+            format!(
+                "**{title}**\n{mention}{} {when} and is still {status}\n**Name:** {}\n**Vendor:** {}\n**Team:** {}{}",
+                order.number(),
+                order.name,
+                order.vendor,
+                order.team,
+                permalink::line(&order)
+            ),
+        );
+        Entity::insert(ActiveModel {
+            order_id: ActiveValue::Set(order.id),
+            stage: ActiveValue::Set(stage),
+            sent: ActiveValue::Set(Local::now().naive_local()),
+        })
+        .on_conflict_do_nothing()
+        .exec(db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Periodically reminds the order updates webhook of orders that are needed
+/// soon but haven't been bought, following `needed_by_reminder_days`
+pub fn spawn(state: &'static UsrState) {
+    let Some(days) = state.needed_by_reminder_days else {
+        return;
+    };
+    if !state.notifier.routes(Topic::OrderUpdate) {
+        return;
+    }
+    jobs::spawn(
+        state,
+        "needed_by_reminders",
+        Schedule::Every(Duration::from_secs(60 * 60)),
+        Retry::times(2, Duration::from_secs(5 * 60)),
+        move |state| async move { Ok(remind(state, days).await?) },
+    );
+}
@@ -0,0 +1,61 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveDateTime;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+
+use super::{order, order_status, permalink};
+use crate::locale::Locale;
+
+/// A digest for each member whose requested orders moved since `since`, by
+/// member name, in the member's language from `locales`. Placing an order
+/// isn't news to whoever requested it, so only later statuses count.
+pub async fn requester_digests(
+    db: &impl ConnectionTrait,
+    since: NaiveDateTime,
+    locales: &HashMap<String, Locale>,
+) -> Result<BTreeMap<String, String>, sea_orm::DbErr> {
+    let mut moves = HashMap::<i64, Vec<order_status::Status>>::new();
+    for model in order_status::Entity::find()
+        .filter(order_status::Column::Date.gt(since))
+        .filter(order_status::Column::Status.ne(order_status::Status::New))
+        .order_by_asc(order_status::Column::InstanceId)
+        .all(db)
+        .await?
+    {
+        moves.entry(model.order_id).or_default().push(model.status);
+    }
+    if moves.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let orders = order::Entity::find()
+        .filter(order::Column::Id.is_in(moves.keys().copied()))
+        .filter(order::Column::Requester.is_not_null())
+        .order_by_asc(order::Column::Id)
+        .all(db)
+        .await?;
+
+    let mut digests = BTreeMap::<String, String>::new();
+    for order in orders {
+        let Some(requester) = &order.requester else {
+            continue;
+        };
+        let locale = locales.get(requester).copied().unwrap_or_default();
+        let statuses: Vec<_> = moves[&order.id]
+            .iter()
+            .map(|status| locale.status(*status))
+            .collect();
+        let digest = digests
+            .entry(requester.clone())
+            .or_insert_with(|| locale.order_digest_heading().to_string());
+        digest.push_str(&format!(
+            "\n- **{}** {}: {}",
+            order.number(),
+            order.name,
+            statuses.join(" → ")
+        ));
+        if let Some(url) = permalink::url(&order) {
+            digest.push_str(&format!(" <{url}>"));
+        }
+    }
+    Ok(digests)
+}
@@ -0,0 +1,56 @@
+use std::fmt::Display;
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Something wrong with a delivery, noted by whoever received it. Stays open
+/// as a follow-up task until someone records how it was resolved.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "order_discrepancies")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub order_id: i64,
+    pub kind: Kind,
+    /// The order's count when the discrepancy was reported
+    pub expected: i64,
+    pub received: i64,
+    pub note: String,
+    pub reported: DateTime,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<DateTime>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize, ToSchema)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+#[schema(as = DiscrepancyKind)]
+pub enum Kind {
+    #[sea_orm(string_value = "W")]
+    WrongItem,
+    /// Fewer arrived than were ordered
+    #[sea_orm(string_value = "S")]
+    ShortShipment,
+    #[sea_orm(string_value = "D")]
+    Damaged,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::WrongItem => write!(f, "Wrong Item"),
+            Kind::ShortShipment => write!(f, "Short Shipment"),
+            Kind::Damaged => write!(f, "Damaged"),
+        }
+    }
+}
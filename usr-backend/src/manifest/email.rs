@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::post,
+    Json, Router,
+};
+use sea_orm::{prelude::Decimal, TransactionTrait};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::{money, scheduler, UsrState};
+
+use super::{
+    audit, order, policy, quota,
+    service::{self, OrderError},
+    PendingOrder,
+};
+
+/// What an order email looks like, sent back when one can't be read
+const TEMPLATE: &str = "Name: 608 bearing
+Count: 10
+Unit Cost: 1.25
+Vendor: VEXpro
+Link: https://www.vexrobotics.com/608-bearings.html
+Team: Mechanical
+Store In: Bin 3
+Reason: Drive base";
+
+/// Settings for placing orders by email. The mail provider's inbound webhook
+/// posts each email to `/api/email/inbound?key=...`.
+#[derive(Deserialize)]
+pub struct EmailConfig {
+    /// Shared with the mail provider so that nobody else can post orders
+    pub key: String,
+    /// Each address orders are accepted from, and the member it belongs to
+    pub senders: HashMap<String, String>,
+    /// Where replies are posted as `{from, to, subject, text}` to be sent,
+    /// such as the mail provider's send API
+    #[serde(default)]
+    pub reply_url: Option<String>,
+    /// Sent as a bearer token with each reply
+    #[serde(default)]
+    pub reply_token: Option<String>,
+    /// The address replies come from
+    pub reply_from: String,
+}
+
+impl EmailConfig {
+    pub fn validate(&self, problems: &mut Vec<String>) {
+        if self.key.trim().is_empty() {
+            problems.push("email.key: must not be empty".to_string());
+        }
+        for address in self.senders.keys() {
+            if !address.contains('@') {
+                problems.push(format!(
+                    "email.senders: {address:?} is not an email address"
+                ));
+            }
+        }
+        if let Some(url) = &self.reply_url {
+            match url.parse::<axum::http::Uri>() {
+                Ok(uri) if matches!(uri.scheme_str(), Some("http" | "https")) => {}
+                Ok(_) => problems.push(format!("email.reply_url: {url:?} is not an http(s) url")),
+                Err(e) => {
+                    problems.push(format!("email.reply_url: {url:?} is not a valid url: {e}"))
+                }
+            }
+        }
+    }
+}
+
+/// Places orders from emails and replies to their senders
+pub struct EmailIntake {
+    config: EmailConfig,
+    client: reqwest::Client,
+}
+
+impl EmailIntake {
+    /// Replies aren't sent from sandboxes, like webhooks
+    pub fn new(mut config: EmailConfig, sandbox: bool) -> Self {
+        config.senders = config
+            .senders
+            .into_iter()
+            .map(|(address, member)| (address.trim().to_lowercase(), member))
+            .collect();
+        if sandbox {
+            config.reply_url = None;
+        }
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Sends `text` to `to` in the background, if replies are configured
+    fn reply(&'static self, to: String, subject: &str, text: String) {
+        let Some(url) = &self.config.reply_url else {
+            return;
+        };
+        let subject = if subject.to_lowercase().starts_with("re:") {
+            subject.to_string()
+        } else {
+            format!("Re: {subject}")
+        };
+        let mut request = self.client.post(url).json(&Reply {
+            from: &self.config.reply_from,
+            to: &to,
+            subject: &subject,
+            text: &text,
+        });
+        if let Some(token) = &self.config.reply_token {
+            request = request.bearer_auth(token);
+        }
+        tokio::spawn(async move {
+            match request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+            {
+                Ok(_) => {}
+                Err(e) => error!("Failed to reply to {to}: {e}"),
+            }
+        });
+    }
+}
+
+#[derive(Serialize)]
+struct Reply<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    text: &'a str,
+}
+
+/// The address in a `From` header such as `Jane Doe <jane@example.org>`
+fn address(from: &str) -> String {
+    let from = from.trim();
+    from.rsplit_once('<')
+        .and_then(|(_, rest)| rest.split_once('>'))
+        .map_or(from, |(address, _)| address)
+        .trim()
+        .to_lowercase()
+}
+
+/// Reads an enum the way it is written in JSON, eg. `Mechanical`
+fn parse_enum<T: DeserializeOwned>(value: &str) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(value.to_string())).ok()
+}
+
+/// Reads the `Key: value` lines of an order email, ignoring any others such
+/// as a greeting or signature. Returns everything wrong with it instead if it
+/// can't be read.
+fn parse_order(text: &str, requester: &str) -> Result<PendingOrder, Vec<String>> {
+    let mut fields = HashMap::new();
+    for line in text.lines() {
+        // Quoted replies repeat an earlier email
+        if line.trim_start().starts_with('>') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key: String = key
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        fields
+            .entry(key)
+            .or_insert_with(|| value.trim().to_string());
+    }
+
+    let mut problems = vec![];
+    let mut text_field = |key: &str, label: &str| match fields.get(key) {
+        Some(value) if !value.is_empty() => value.clone(),
+        _ => {
+            problems.push(format!("{label} is missing"));
+            String::new()
+        }
+    };
+    let name = text_field("name", "Name");
+    let store_in = text_field("storein", "Store In");
+    let reason = text_field("reason", "Reason");
+    let vendor = text_field("vendor", "Vendor");
+    let link = fields.get("link").cloned().unwrap_or_default();
+    let count = text_field("count", "Count");
+    let unit_cost = text_field("unitcost", "Unit Cost");
+    let team = text_field("team", "Team");
+
+    let count = match count.parse::<u32>() {
+        Ok(count) if count > 0 => count,
+        _ if count.is_empty() => 0,
+        _ => {
+            problems.push(format!("Count {count:?} is not a positive whole number"));
+            0
+        }
+    };
+    let unit_cost = match unit_cost.trim_start_matches('$').parse::<Decimal>() {
+        Ok(unit_cost) => unit_cost,
+        _ if unit_cost.is_empty() => Decimal::ZERO,
+        _ => {
+            problems.push(format!("Unit Cost {unit_cost:?} is not a number"));
+            Decimal::ZERO
+        }
+    };
+    let team = match parse_enum::<scheduler::Team>(&team) {
+        Some(team) => Some(team),
+        None if team.is_empty() => None,
+        None => {
+            problems.push(format!("Team {team:?} is not a team"));
+            None
+        }
+    };
+    let funding_source = match fields
+        .get("fundingsource")
+        .filter(|value| !value.is_empty())
+    {
+        Some(value) => parse_enum(value).unwrap_or_else(|| {
+            problems.push(format!("Funding Source {value:?} is not a funding source"));
+            Default::default()
+        }),
+        None => Default::default(),
+    };
+    let currency = match fields.get("currency").filter(|value| !value.is_empty()) {
+        Some(value) => parse_enum(&value.to_uppercase()).unwrap_or_else(|| {
+            problems.push(format!("Currency {value:?} is not a currency"));
+            order::Currency::Usd
+        }),
+        None => order::Currency::Usd,
+    };
+    if let Err(msg) = money::validate_unit_cost(unit_cost) {
+        problems.push(msg.to_string());
+    }
+
+    match team {
+        Some(team) if problems.is_empty() => Ok(PendingOrder {
+            name,
+            count,
+            unit_cost,
+            store_in,
+            team,
+            reason,
+            vendor,
+            link,
+            funding_source,
+            component_id: None,
+            requester: Some(requester.to_string()),
+            currency,
+            exchange_rate: None,
+            fields: HashMap::new(),
+            needed_by: None,
+            allow_duplicate: false,
+            shipping_cost: None,
+            tax: None,
+            fees: None,
+        }),
+        _ => Err(problems),
+    }
+}
+
+#[derive(Deserialize)]
+struct InboundKey {
+    key: String,
+}
+
+/// An email as the mail provider posts it
+#[derive(Deserialize)]
+struct InboundEmail {
+    from: String,
+    #[serde(default)]
+    subject: String,
+    text: String,
+}
+
+/// Places the order in an email, waiting for approval in `New` like any
+/// other, and replies with its number or why it couldn't be placed. Emails
+/// from unknown senders are dropped without a reply, so that spam doesn't
+/// get any.
+#[axum::debug_handler]
+async fn inbound_email(
+    State(state): State<&'static UsrState>,
+    Query(InboundKey { key }): Query<InboundKey>,
+    Json(email): Json<InboundEmail>,
+) -> (StatusCode, &'static str) {
+    let Some(intake) = &state.email_intake else {
+        return (StatusCode::NOT_FOUND, "");
+    };
+    if key != intake.config.key {
+        return (StatusCode::UNAUTHORIZED, "Invalid key");
+    }
+    let sender = address(&email.from);
+    let Some(member) = intake.config.senders.get(&sender) else {
+        warn!("Dropped an order email from unknown sender {sender}");
+        return (StatusCode::OK, "");
+    };
+
+    let rejected = |problems: Vec<String>| {
+        let mut text = "Your order couldn't be placed:".to_string();
+        for problem in problems {
+            text.push_str(&format!("\n- {problem}"));
+        }
+        text.push_str("\n\nOrders are written like this:\n\n");
+        text.push_str(TEMPLATE);
+        intake.reply(sender.clone(), &email.subject, text);
+        (StatusCode::OK, "")
+    };
+    let mut pending_order = match parse_order(&email.text, member) {
+        Ok(pending_order) => pending_order,
+        Err(problems) => return rejected(problems),
+    };
+    let actor = audit::Actor::system(member, "/email/inbound");
+    // Emailed orders are limited like the member's own
+    let caller = policy::Caller {
+        name: Some(member.to_string()),
+        role: policy::Role::Member,
+    };
+    let rejected_for = |e: OrderError| match e {
+        OrderError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, ""),
+        OrderError::Violations(violations) => {
+            rejected(violations.into_iter().map(|v| v.message).collect())
+        }
+        e => rejected(vec![e.to_string()]),
+    };
+    if let Err(e) = service::check_new_order(state, &mut pending_order, None).await {
+        return rejected_for(e);
+    }
+
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let pending_orders = std::slice::from_ref(&pending_order);
+                if let Err(e) = quota::check(tx, state, &caller, pending_orders).await? {
+                    return Ok(Err(e));
+                }
+                service::insert_order(state, tx, pending_order, &actor)
+                    .await
+                    .map(Ok)
+            })
+        })
+        .await;
+    match result {
+        Ok(Err(e)) => rejected_for(e),
+        Ok(Ok(placed)) => {
+            let hold = placed.hold.clone();
+            let order = service::announce_placed(state, placed).await;
+            let mut text = format!(
+                "Order {} ({} x {}) was placed and is waiting for a lead to approve it.",
+                order.number(),
+                order.count,
+                order.name
+            );
+            if let Some(hold) = hold {
+                text.push_str(&format!("\nIt was put on hold: {hold}"));
+            }
+            intake.reply(sender, &email.subject, text);
+            (StatusCode::OK, "")
+        }
+        Err(e) => {
+            error!("Failed to create order from email: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
+        }
+    }
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new().route("/inbound", post(inbound_email))
+}
@@ -0,0 +1,124 @@
+use std::{collections::HashSet, time::Duration};
+
+use chrono::{Local, TimeDelta};
+use sea_orm::{ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use crate::{
+    jobs::{self, Retry, Schedule},
+    notify::Topic,
+    UsrState,
+};
+
+use super::{approval, competition, current, order_status, permalink, reminder};
+
+/// Who a step of the escalation chain mentions
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Notify {
+    /// The leads of the order's team, from `team_lead_roles`
+    Lead,
+    /// A Discord role, such as the admins
+    Role(u64),
+}
+
+/// A reminder posted once an order has waited `after_hours` for approval
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Step {
+    pub after_hours: u32,
+    pub notify: Notify,
+}
+
+/// Posts the latest step of the chain that each order waiting for approval
+/// has reached, skipping any steps it passed while the server was down.
+/// Orders wait for approval in `New`, until a lead approves or rejects them.
+async fn remind(state: &'static UsrState, chain: &[Step]) -> Result<(), sea_orm::DbErr> {
+    let db: &DatabaseConnection = &state.db;
+    let now = Local::now().naive_local();
+    let waiting = current::Entity::find()
+        .filter(current::Column::Status.eq(order_status::Status::New))
+        .all(db)
+        .await?;
+    let decided: HashSet<_> = approval::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| model.order_id)
+        .collect();
+    let sent: HashSet<_> = reminder::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| (model.order_id, model.step))
+        .collect();
+
+    for model in waiting {
+        if decided.contains(&model.id) {
+            continue;
+        }
+        let waited = now - model.status_date;
+        let Some(step) = chain
+            .iter()
+            .rposition(|step| waited >= TimeDelta::hours(step.after_hours.into()))
+        else {
+            continue;
+        };
+        if sent.contains(&(model.id, step as i64)) {
+            continue;
+        }
+        let (order, _) = model.into_parts();
+        // It can be bought without anyone's approval for now
+        if competition::waives_approval(state, &order) {
+            continue;
+        }
+        let mention = match chain[step].notify {
+            Notify::Lead => state
+                .team_lead_roles
+                .get(&order.team)
+                .map(|role| format!("<@&{role}> "))
+                .unwrap_or_default(),
+            Notify::Role(role) => format!("<@&{role}> "),
+        };
+        let title = if step == 0 {
+            "Order Awaiting Approval"
+        } else {
+            "Order Approval Escalated"
+        };
+        state.notifier.send_for(
+            order.team,
+            Topic::OrderReminder,
+            order.id as u32,
+            format!(
+                "**{title}**\n{mention}{} has waited {} hours\n**Name:** {}\n**Team:** {}{}",
+                order.number(),
+                waited.num_hours(),
+                order.name,
+                order.team,
+                permalink::line(&order)
+            ),
+        );
+        reminder::Entity::insert_many((0..=step).map(|step| reminder::ActiveModel {
+            order_id: ActiveValue::Set(order.id),
+            step: ActiveValue::Set(step as i64),
+            sent: ActiveValue::Set(now),
+        }))
+        .on_conflict_do_nothing()
+        .exec(db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Periodically reminds approvers of orders that have waited too long, then
+/// escalates, following `approval_escalation`.
+pub fn spawn(state: &'static UsrState) {
+    if !state.notifier.routes(Topic::OrderReminder) || state.approval_escalation.is_empty() {
+        return;
+    }
+    jobs::spawn(
+        state,
+        "approval_reminders",
+        Schedule::Every(Duration::from_secs(60 * 60)),
+        Retry::times(2, Duration::from_secs(5 * 60)),
+        |state| async move { Ok(remind(state, &state.approval_escalation).await?) },
+    );
+}
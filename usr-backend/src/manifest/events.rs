@@ -0,0 +1,158 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    Stream, StreamExt,
+};
+
+use crate::{scheduler::Team, UsrState};
+
+use super::order_status::Status;
+
+/// How far a slow subscriber can fall behind before it starts missing events
+const BACKLOG: usize = 256;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub enum EventKind {
+    Created,
+    Changed,
+    StatusUpdated,
+    Cancelled,
+    Restored,
+}
+
+/// How far along an order is, for dashboards that don't need every status
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum StatusClass {
+    /// Waiting on the team, ie. `New` or `OnHold`
+    Pending,
+    /// Bought but not all here yet
+    InTransit,
+    /// Delivered, and maybe put away
+    Arrived,
+    /// Sent back to the vendor
+    Returned,
+    Cancelled,
+}
+
+impl StatusClass {
+    pub fn of(status: Status) -> Self {
+        match status {
+            Status::New | Status::OnHold => StatusClass::Pending,
+            Status::Submitted | Status::Shipped | Status::PartiallyReceived => {
+                StatusClass::InTransit
+            }
+            Status::Delivered | Status::InStorage => StatusClass::Arrived,
+            Status::Returned => StatusClass::Returned,
+            Status::Cancelled => StatusClass::Cancelled,
+        }
+    }
+}
+
+/// Something that happened to an order, as pushed to live subscribers
+#[derive(Clone, Debug, Serialize)]
+pub struct OrderEvent {
+    pub kind: EventKind,
+    pub order_id: i64,
+    pub number: String,
+    pub name: String,
+    pub team: Team,
+    /// Its status after the event
+    pub status: Status,
+    /// The subsystem of the component the order is for, if it is for one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+/// Fans order events out to everyone subscribed to `/events/orders`
+pub struct OrderEvents {
+    sender: broadcast::Sender<OrderEvent>,
+}
+
+impl Default for OrderEvents {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(BACKLOG).0,
+        }
+    }
+}
+
+impl OrderEvents {
+    /// Whether anyone is listening, so that events nobody will see aren't
+    /// looked up
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    pub fn publish(&self, event: OrderEvent) {
+        // Fails only when nobody is subscribed
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Narrows the stream down to what a subscriber cares about. Every given
+/// filter must match.
+#[derive(Deserialize)]
+pub struct EventFilter {
+    #[serde(default)]
+    team: Option<Team>,
+    #[serde(default)]
+    class: Option<StatusClass>,
+    #[serde(default)]
+    project: Option<String>,
+}
+
+impl EventFilter {
+    fn matches(&self, event: &OrderEvent) -> bool {
+        self.team.is_none_or(|team| team == event.team)
+            && self
+                .class
+                .is_none_or(|class| class == StatusClass::of(event.status))
+            && self
+                .project
+                .as_ref()
+                .is_none_or(|project| event.project.as_ref() == Some(project))
+    }
+}
+
+/// Pushes order events as they happen, filtered on the server so that each
+/// dashboard only gets what it shows. Subscribers that fall too far behind
+/// skip what they missed, and are sent a `resync` event so that they can
+/// fetch the orders again instead of showing stale ones.
+#[axum::debug_handler]
+pub async fn order_events(
+    State(state): State<&'static UsrState>,
+    Query(filter): Query<EventFilter>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream =
+        BroadcastStream::new(state.order_events.sender.subscribe()).filter_map(move |event| {
+            let event = match event {
+                Ok(event) => event,
+                Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                    return Some(Ok(Event::default()
+                        .event("resync")
+                        .data(format!("{{\"missed\":{missed}}}"))));
+                }
+            };
+            if !filter.matches(&event) {
+                return None;
+            }
+            let kind = match event.kind {
+                EventKind::Created => "created",
+                EventKind::Changed => "changed",
+                EventKind::StatusUpdated => "status_updated",
+                EventKind::Cancelled => "cancelled",
+                EventKind::Restored => "restored",
+            };
+            Some(Ok(Event::default()
+                .event(kind)
+                .data(serde_json::to_string(&event).unwrap_or_default())))
+        });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// An order's value for a custom field. Orders without one have no row.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "order_fields")]
+#[schema(as = FieldValue)]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub order_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub field: String,
+    /// The value as JSON, as normalized by the field's kind, so that equal
+    /// values compare equal in queries
+    #[serde(serialize_with = "as_json")]
+    #[schema(value_type = Value)]
+    pub value: String,
+}
+
+/// Responds with the value itself rather than a string of it
+fn as_json<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serde_json::from_str::<Value>(value)
+        .unwrap_or_default()
+        .serialize(serializer)
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
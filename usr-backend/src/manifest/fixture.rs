@@ -0,0 +1,327 @@
+use chrono::{Days, Duration, Local, NaiveDateTime};
+use sea_orm::{
+    prelude::Decimal, ActiveModelTrait, ActiveValue, DatabaseConnection, DatabaseTransaction,
+    EntityTrait, TransactionTrait,
+};
+
+use crate::scheduler::Team;
+
+use super::{funding, order, order_status, stock};
+
+/// Rows per INSERT, kept well under SQLite's bound parameter limit
+const CHUNK: usize = 500;
+
+/// The statuses an order goes through on its way to each status, in order
+const PATH: [order_status::Status; 4] = [
+    order_status::Status::Submitted,
+    order_status::Status::Shipped,
+    order_status::Status::Delivered,
+    order_status::Status::InStorage,
+];
+
+/// An order to insert along with its status history and, once it is in
+/// storage, its inventory, so that seeded databases look like ones orders
+/// actually went through. Anything not given is filled in with something
+/// plausible.
+#[derive(Clone, Debug)]
+pub struct OrderFixture {
+    name: String,
+    count: i64,
+    unit_cost: Decimal,
+    store_in: String,
+    team: Team,
+    reason: String,
+    vendor: String,
+    requester: Option<String>,
+    placed: NaiveDateTime,
+    /// The statuses after `New`, oldest first
+    history: Vec<(order_status::Status, NaiveDateTime)>,
+}
+
+impl OrderFixture {
+    pub fn new() -> Self {
+        Self {
+            name: "Zip ties".to_string(),
+            count: 1,
+            unit_cost: Decimal::new(999, 2),
+            store_in: "Bin 1".to_string(),
+            team: Team::Mechanical,
+            reason: "Fixture".to_string(),
+            vendor: "McMaster-Carr".to_string(),
+            requester: None,
+            placed: Local::now().naive_local(),
+            history: vec![],
+        }
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn count(mut self, count: u32) -> Self {
+        self.count = count.into();
+        self
+    }
+
+    pub fn unit_cost(mut self, unit_cost: Decimal) -> Self {
+        self.unit_cost = unit_cost;
+        self
+    }
+
+    pub fn store_in(mut self, store_in: impl Into<String>) -> Self {
+        self.store_in = store_in.into();
+        self
+    }
+
+    pub fn team(mut self, team: Team) -> Self {
+        self.team = team;
+        self
+    }
+
+    pub fn reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = reason.into();
+        self
+    }
+
+    pub fn vendor(mut self, vendor: impl Into<String>) -> Self {
+        self.vendor = vendor.into();
+        self
+    }
+
+    pub fn requester(mut self, requester: impl Into<String>) -> Self {
+        self.requester = Some(requester.into());
+        self
+    }
+
+    /// When the order was placed, which statuses given after it follow
+    pub fn placed(mut self, placed: NaiveDateTime) -> Self {
+        self.placed = placed;
+        self
+    }
+
+    /// Moves the order along to `status` a day at a time, going through each
+    /// status that comes before it. Holds and cancellations happen the day
+    /// after wherever the order is.
+    pub fn status(mut self, status: order_status::Status) -> Self {
+        let start = self
+            .history
+            .iter()
+            .rev()
+            .find_map(|(last, _)| PATH.iter().position(|x| x == last))
+            .map_or(0, |i| i + 1);
+        let steps = match PATH.iter().position(|x| *x == status) {
+            Some(end) if end >= start => &PATH[start..=end],
+            Some(_) => return self,
+            None if status == order_status::Status::New => return self,
+            None => std::slice::from_ref(&status),
+        };
+        for status in steps {
+            let date = self.last_date() + Duration::days(1);
+            self.history.push((*status, date));
+        }
+        self
+    }
+
+    /// Moves the order to `status` at exactly `date`, for histories that
+    /// don't advance a day at a time
+    pub fn status_at(mut self, status: order_status::Status, date: NaiveDateTime) -> Self {
+        self.history.push((status, date));
+        self
+    }
+
+    fn last_date(&self) -> NaiveDateTime {
+        self.history.last().map_or(self.placed, |(_, date)| *date)
+    }
+
+    fn in_storage(&self) -> bool {
+        self.history
+            .iter()
+            .any(|(status, _)| *status == order_status::Status::InStorage)
+    }
+
+    fn order(&self) -> order::ActiveModel {
+        order::ActiveModel {
+            id: ActiveValue::NotSet,
+            name: ActiveValue::Set(self.name.clone()),
+            count: ActiveValue::Set(self.count),
+            unit_cost: ActiveValue::Set(self.unit_cost),
+            store_in: ActiveValue::Set(self.store_in.clone()),
+            team: ActiveValue::Set(self.team),
+            reason: ActiveValue::Set(self.reason.clone()),
+            vendor: ActiveValue::Set(self.vendor.clone()),
+            link: ActiveValue::Set(String::new()),
+            funding_source: ActiveValue::Set(funding::Source::default()),
+            component_id: ActiveValue::Set(None),
+            ref_number: ActiveValue::Set(None),
+            season: ActiveValue::Set(None),
+            season_number: ActiveValue::Set(None),
+            tax_exempt: ActiveValue::Set(None),
+            payment_method: ActiveValue::Set(None),
+            requester: ActiveValue::Set(self.requester.clone()),
+            currency: ActiveValue::Set(None),
+            original_unit_cost: ActiveValue::Set(None),
+            exchange_rate: ActiveValue::Set(None),
+            cart_id: ActiveValue::Set(None),
+            needed_by: ActiveValue::Set(None),
+            shipping_cost: ActiveValue::Set(None),
+            tax: ActiveValue::Set(None),
+            fees: ActiveValue::Set(None),
+            version: ActiveValue::Set(0),
+            competition: ActiveValue::Set(false),
+            received_count: ActiveValue::Set(None),
+        }
+    }
+
+    fn statuses(&self, order_id: i64) -> impl Iterator<Item = order_status::ActiveModel> + '_ {
+        std::iter::once((order_status::Status::New, self.placed))
+            .chain(self.history.iter().copied())
+            .map(move |(status, date)| order_status::ActiveModel {
+                order_id: ActiveValue::Set(order_id),
+                instance_id: ActiveValue::NotSet,
+                date: ActiveValue::Set(date),
+                status: ActiveValue::Set(status),
+                reason: ActiveValue::Set(
+                    (status == order_status::Status::OnHold).then(|| "Fixture".to_string()),
+                ),
+            })
+    }
+
+    fn stock(&self, order_id: i64) -> Option<stock::ActiveModel> {
+        self.in_storage().then(|| stock::ActiveModel {
+            order_id: ActiveValue::Set(order_id),
+            location: ActiveValue::Set(self.store_in.clone()),
+            name: ActiveValue::Set(self.name.clone()),
+            quantity: ActiveValue::Set(self.count),
+        })
+    }
+
+    /// Inserts the order and everything that goes with it
+    pub async fn insert(self, db: &DatabaseConnection) -> Result<order::Model, sea_orm::DbErr> {
+        let tx = db.begin().await?;
+        let model = self.order().insert(&tx).await?;
+        order_status::Entity::insert_many(self.statuses(model.id))
+            .exec(&tx)
+            .await?;
+        if let Some(stock) = self.stock(model.id) {
+            stock.insert(&tx).await?;
+        }
+        tx.commit().await?;
+        Ok(model)
+    }
+}
+
+impl Default for OrderFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Inserts many fixtures at once, a chunk per transaction, for seeding
+/// databases large enough to benchmark against
+pub async fn insert_many(
+    db: &DatabaseConnection,
+    fixtures: Vec<OrderFixture>,
+) -> Result<(), sea_orm::DbErr> {
+    for chunk in fixtures.chunks(CHUNK) {
+        db.transaction(|tx| Box::pin(insert_chunk(tx, chunk.to_vec())))
+            .await
+            .map_err(|e| match e {
+                sea_orm::TransactionError::Connection(e) => e,
+                sea_orm::TransactionError::Transaction(e) => e,
+            })?;
+    }
+    Ok(())
+}
+
+async fn insert_chunk(
+    tx: &DatabaseTransaction,
+    chunk: Vec<OrderFixture>,
+) -> Result<(), sea_orm::DbErr> {
+    let last_id = order::Entity::insert_many(chunk.iter().map(OrderFixture::order))
+        .exec(tx)
+        .await?
+        .last_insert_id;
+    let first_id = last_id + 1 - chunk.len() as i64;
+    let ids = (first_id..).zip(&chunk);
+    let statuses: Vec<_> = ids
+        .clone()
+        .flat_map(|(id, fixture)| fixture.statuses(id))
+        .collect();
+    for statuses in statuses.chunks(CHUNK) {
+        order_status::Entity::insert_many(statuses.to_vec())
+            .exec(tx)
+            .await?;
+    }
+    let stock: Vec<_> = ids.filter_map(|(id, fixture)| fixture.stock(id)).collect();
+    if !stock.is_empty() {
+        stock::Entity::insert_many(stock).exec(tx).await?;
+    }
+    Ok(())
+}
+
+/// Inserts a handful of orders in every status, for trying the web UI
+/// against a fresh database. Not for use on a real database.
+pub async fn seed(db: &DatabaseConnection) -> Result<u32, sea_orm::DbErr> {
+    let placed = Local::now().naive_local() - Days::new(14);
+    let fixture = || {
+        OrderFixture::new()
+            .placed(placed)
+            .requester("Seed")
+            .reason("Seed data")
+    };
+    let fixtures = [
+        fixture()
+            .name("608 bearing")
+            .count(8)
+            .unit_cost(Decimal::new(125, 2))
+            .team(Team::Mechanical)
+            .vendor("McMaster-Carr"),
+        fixture()
+            .name("XT60 connector")
+            .count(10)
+            .unit_cost(Decimal::new(89, 2))
+            .team(Team::Electrical)
+            .vendor("Amazon")
+            .status(order_status::Status::Submitted),
+        fixture()
+            .name("Raspberry Pi 5")
+            .unit_cost(Decimal::new(8000, 2))
+            .team(Team::Software)
+            .vendor("DigiKey")
+            .status(order_status::Status::Shipped),
+        fixture()
+            .name("Aluminum 6061 plate")
+            .count(2)
+            .unit_cost(Decimal::new(4650, 2))
+            .team(Team::Mechanical)
+            .status(order_status::Status::Delivered),
+        fixture()
+            .name("SPARK MAX controller")
+            .count(4)
+            .unit_cost(Decimal::new(9000, 2))
+            .store_in("Cabinet A")
+            .team(Team::Electrical)
+            .vendor("REV Robotics")
+            .status(order_status::Status::InStorage),
+        fixture()
+            .name("Lithium battery 6S")
+            .unit_cost(Decimal::new(15999, 2))
+            .team(Team::Systems)
+            .vendor("AndyMark")
+            .status(order_status::Status::OnHold),
+        fixture()
+            .name("NEO brushless motor")
+            .count(2)
+            .unit_cost(Decimal::new(6000, 2))
+            .team(Team::Mechanical)
+            .vendor("REV Robotics")
+            .status(order_status::Status::Submitted)
+            .status(order_status::Status::Cancelled),
+    ];
+    let count = fixtures.len() as u32;
+    for fixture in fixtures {
+        fixture.insert(db).await?;
+    }
+    Ok(count)
+}
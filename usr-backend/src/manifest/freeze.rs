@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler;
+
+/// A freeze on new orders, such as during end-of-year account closeout
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "spending_freezes")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// The team whose orders are frozen, or `None` for every team. A team's
+    /// own freeze takes precedence over one for every team.
+    #[sea_orm(nullable)]
+    pub team: Option<scheduler::Team>,
+    pub mode: Mode,
+    pub reason: String,
+    pub since: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Mode {
+    /// New orders are turned away
+    #[sea_orm(string_value = "R")]
+    Reject,
+    /// New orders are placed, but put on hold until a lead releases them
+    #[sea_orm(string_value = "H")]
+    Hold,
+}
@@ -0,0 +1,38 @@
+use std::fmt::Display;
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "funding_budgets")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub source: Source,
+    pub allocated: Decimal,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize, Default, ToSchema)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+#[schema(as = FundingSource)]
+pub enum Source {
+    #[default]
+    #[sea_orm(string_value = "D")]
+    DepartmentGrant,
+    #[sea_orm(string_value = "S")]
+    Sponsor,
+    #[sea_orm(string_value = "C")]
+    ClubDues,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
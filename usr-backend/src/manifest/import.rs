@@ -0,0 +1,280 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Local, NaiveDateTime};
+use sea_orm::{prelude::Decimal, Iterable, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{backup::backup_db, money, scheduler::Team, UsrState};
+
+use super::{
+    order_status::Status,
+    orders_changed,
+    sheet::{self, LegacyOrder},
+};
+
+/// The largest import accepted, enough for several seasons of purchases
+pub const MAX_IMPORT_BYTES: usize = 16 * 1024 * 1024;
+
+/// A purchase from before the manifest, as exported from the spreadsheets
+/// it was tracked in
+#[derive(Deserialize)]
+struct ImportedOrder {
+    name: String,
+    #[serde(default)]
+    vendor: String,
+    #[serde(default = "default_count")]
+    count: u32,
+    unit_cost: Decimal,
+    team: Team,
+    /// Where the order ended up
+    status: Status,
+    /// When the order was placed
+    placed: NaiveDateTime,
+    /// When the order reached `status`, the same as `placed` if left out
+    #[serde(default)]
+    status_date: Option<NaiveDateTime>,
+    #[serde(default)]
+    store_in: String,
+    #[serde(default)]
+    reason: String,
+    #[serde(default)]
+    link: String,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+#[derive(Serialize)]
+struct RowError {
+    /// The line in the CSV, counting the header, or the position in the JSON
+    /// array, starting from 1
+    row: usize,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct ImportReport {
+    /// Ids of the orders that were added, in the order of their rows
+    imported: Vec<i64>,
+    /// Nothing is imported unless this is empty
+    errors: Vec<RowError>,
+}
+
+/// Each row's number with the order read from it, or why it couldn't be
+type Rows = Vec<(usize, Result<ImportedOrder, String>)>;
+
+fn parse_status(value: &str) -> Option<Status> {
+    let value: String = value.split_whitespace().collect();
+    Status::iter().find(|status| status.to_string().eq_ignore_ascii_case(&value))
+}
+
+/// Reads the rows of a CSV export, whose headers are the fields of
+/// [`ImportedOrder`], matched case-insensitively. Costs, dates and teams are
+/// read as leniently as the old manifest sheet's.
+fn read_csv(body: &[u8]) -> Result<Rows, String> {
+    let mut reader = csv::Reader::from_reader(body);
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| format!("Invalid CSV: {e}"))?
+        .iter()
+        .map(|header| header.trim().to_lowercase().replace(' ', "_"))
+        .collect();
+    for field in ["name", "unit_cost", "team", "status", "placed"] {
+        if !headers.iter().any(|header| header == field) {
+            return Err(format!("The CSV has no {field} column"));
+        }
+    }
+
+    let mut rows = vec![];
+    for (i, record) in reader.records().enumerate() {
+        let row = i + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                rows.push((row, Err(format!("Invalid CSV: {e}"))));
+                continue;
+            }
+        };
+        let get = |field: &str| {
+            headers
+                .iter()
+                .position(|header| header == field)
+                .and_then(|i| record.get(i))
+                .unwrap_or_default()
+                .trim()
+        };
+        let parsed = (|| {
+            let count = match get("count") {
+                "" => default_count(),
+                count => count
+                    .parse()
+                    .map_err(|_| format!("Invalid count {count:?}"))?,
+            };
+            let status_date = match get("status_date") {
+                "" => None,
+                date => {
+                    Some(sheet::parse_date(date).ok_or_else(|| format!("Invalid date {date:?}"))?)
+                }
+            };
+            Ok(ImportedOrder {
+                name: get("name").to_string(),
+                vendor: get("vendor").to_string(),
+                count,
+                unit_cost: sheet::parse_cost(get("unit_cost"))
+                    .ok_or_else(|| format!("Invalid unit cost {:?}", get("unit_cost")))?,
+                team: sheet::parse_team(get("team"))
+                    .ok_or_else(|| format!("Unknown team {:?}", get("team")))?,
+                status: parse_status(get("status"))
+                    .ok_or_else(|| format!("Unknown status {:?}", get("status")))?,
+                placed: sheet::parse_date(get("placed"))
+                    .ok_or_else(|| format!("Invalid date {:?}", get("placed")))?,
+                status_date,
+                store_in: get("store_in").to_string(),
+                reason: get("reason").to_string(),
+                link: get("link").to_string(),
+            })
+        })();
+        rows.push((row, parsed));
+    }
+    Ok(rows)
+}
+
+/// Checks an imported order, turning it into the order to add
+fn validate(imported: ImportedOrder, now: NaiveDateTime) -> Result<LegacyOrder, String> {
+    let name = imported.name.trim().to_string();
+    if name.is_empty() {
+        return Err("A name is required".into());
+    }
+    if imported.count == 0 {
+        return Err("Count must be positive".into());
+    }
+    money::validate_unit_cost(imported.unit_cost)?;
+    let status_date = imported.status_date.unwrap_or(imported.placed);
+    if imported.placed > now || status_date > now {
+        return Err("Dates cannot be in the future".into());
+    }
+    if status_date < imported.placed {
+        return Err(format!(
+            "The order was {} before it was placed",
+            imported.status
+        ));
+    }
+    let mut history = vec![(Status::New, imported.placed)];
+    if imported.status != Status::New {
+        history.push((imported.status, status_date));
+    }
+    Ok(LegacyOrder {
+        name,
+        count: imported.count,
+        unit_cost: imported.unit_cost,
+        store_in: imported.store_in,
+        team: imported.team,
+        reason: imported.reason,
+        vendor: imported.vendor.trim().to_string(),
+        link: imported.link,
+        history,
+    })
+}
+
+/// Adds purchases from before the manifest, such as past seasons tracked in
+/// Google Sheets, as a JSON array or, with `Content-Type: text/csv`, a CSV
+/// export. Each order is placed at its `placed` date and then reaches its
+/// final status at `status_date`, and is counted in the season it was placed
+/// in. Every row is checked first, and if any are invalid nothing is imported
+/// and each of their problems is reported.
+#[axum::debug_handler]
+pub async fn import_orders(
+    State(state): State<&'static UsrState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let is_csv = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/csv"));
+    let rows = if is_csv {
+        match read_csv(&body) {
+            Ok(rows) => rows,
+            Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+        }
+    } else {
+        match serde_json::from_slice::<Vec<serde_json::Value>>(&body) {
+            Ok(values) => values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    (
+                        i + 1,
+                        serde_json::from_value(value).map_err(|e| e.to_string()),
+                    )
+                })
+                .collect(),
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    format!("Expected an array of orders: {e}"),
+                )
+                    .into_response()
+            }
+        }
+    };
+    if rows.is_empty() {
+        return (StatusCode::BAD_REQUEST, "There are no orders to import").into_response();
+    }
+
+    let now = Local::now().naive_local();
+    let mut orders = vec![];
+    let mut errors = vec![];
+    for (row, parsed) in rows {
+        match parsed.and_then(|imported| validate(imported, now)) {
+            Ok(order) => orders.push(order),
+            Err(error) => errors.push(RowError { row, error }),
+        }
+    }
+    if !errors.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ImportReport {
+                imported: vec![],
+                errors,
+            }),
+        )
+            .into_response();
+    }
+
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let mut imported = vec![];
+                for order in orders {
+                    imported.push(sheet::insert(tx, order).await?);
+                }
+                Result::<_, sea_orm::DbErr>::Ok(imported)
+            })
+        })
+        .await;
+
+    match result {
+        Ok(imported) => {
+            backup_db(state);
+            orders_changed(state, None).await;
+            Json(ImportReport {
+                imported,
+                errors: vec![],
+            })
+            .into_response()
+        }
+        Err(e) => {
+            error!("Failed to import orders: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
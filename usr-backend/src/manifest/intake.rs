@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::scheduler;
+
+use super::{vendor, PendingOrder};
+
+/// A requirement on new orders that admins set in the config instead of code,
+/// eg. `{"name": "reason_length", "field": "reason", "min_length": 20}` or
+/// `{"name": "link_over_200", "field": "link", "required": true, "when":
+/// {"subtotal_over": 200}}`
+#[derive(Deserialize, Debug)]
+pub struct IntakeRule {
+    /// Named in violations, so that the web UI can tell rules apart
+    pub name: String,
+    /// One of name, reason, vendor, link, store_in and requester, or a
+    /// custom field's key
+    pub field: String,
+    #[serde(default)]
+    pub required: bool,
+    /// In characters, ignoring surrounding whitespace
+    #[serde(default)]
+    pub min_length: Option<usize>,
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    /// Which orders the rule applies to. Every order, if left out.
+    #[serde(default)]
+    pub when: Condition,
+    /// Told to the requester in place of the default message
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Every part that is given has to hold for a rule to apply
+#[derive(Deserialize, Debug, Default)]
+pub struct Condition {
+    /// Orders whose subtotal in dollars is more than this
+    #[serde(default)]
+    pub subtotal_over: Option<Decimal>,
+    #[serde(default)]
+    pub teams: Vec<scheduler::Team>,
+    /// Matched regardless of case
+    #[serde(default)]
+    pub vendors: Vec<String>,
+    /// Orders whose custom fields have these values, eg.
+    /// `{"kind": "physical"}`
+    #[serde(default)]
+    pub fields: HashMap<String, Value>,
+}
+
+/// A rule a new order breaks
+#[derive(Serialize, Debug, Clone)]
+pub struct Violation {
+    pub rule: String,
+    pub field: String,
+    pub message: String,
+}
+
+impl IntakeRule {
+    pub fn validate(&self, index: usize, problems: &mut Vec<String>) {
+        let at = format!("intake_rules[{index}]");
+        if self.name.trim().is_empty() {
+            problems.push(format!("{at}.name: must not be empty"));
+        }
+        if self.field.trim().is_empty() {
+            problems.push(format!("{at}.field: must not be empty"));
+        }
+        if !self.required && self.min_length.is_none() && self.max_length.is_none() {
+            problems.push(format!(
+                "{at}: needs at least one of required, min_length and max_length"
+            ));
+        }
+        if let (Some(min), Some(max)) = (self.min_length, self.max_length) {
+            if min > max {
+                problems.push(format!("{at}: min_length is more than max_length"));
+            }
+        }
+        if self
+            .when
+            .subtotal_over
+            .is_some_and(|subtotal| subtotal.is_sign_negative())
+        {
+            problems.push(format!("{at}.when.subtotal_over: must not be negative"));
+        }
+    }
+
+    fn applies_to(&self, order: &PendingOrder) -> bool {
+        let when = &self.when;
+        when.subtotal_over
+            .is_none_or(|subtotal| order.subtotal() > subtotal)
+            && (when.teams.is_empty() || when.teams.contains(&order.team))
+            && (when.vendors.is_empty()
+                || when
+                    .vendors
+                    .iter()
+                    .any(|name| vendor::key(name) == vendor::key(&order.vendor)))
+            && when
+                .fields
+                .iter()
+                .all(|(key, value)| order.fields.get(key) == Some(value))
+    }
+
+    /// Why `order` breaks this rule, if it does
+    fn check(&self, order: &PendingOrder) -> Option<String> {
+        if !self.applies_to(order) {
+            return None;
+        }
+        let text = field_text(order, &self.field);
+        let length = text.chars().count();
+        let problem = if text.is_empty() {
+            self.required.then(|| format!("{} is required", self.field))
+        } else if self.min_length.is_some_and(|min| length < min) {
+            Some(format!(
+                "{} must be at least {} characters",
+                self.field,
+                self.min_length.unwrap_or_default()
+            ))
+        } else if self.max_length.is_some_and(|max| length > max) {
+            Some(format!(
+                "{} must be at most {} characters",
+                self.field,
+                self.max_length.unwrap_or_default()
+            ))
+        } else {
+            None
+        };
+        problem.map(|problem| self.message.clone().unwrap_or(problem))
+    }
+}
+
+/// The text of `field` on `order`, trimmed, or empty if it isn't given
+fn field_text(order: &PendingOrder, field: &str) -> String {
+    let text = match field {
+        "name" => order.name.clone(),
+        "reason" => order.reason.clone(),
+        "vendor" => order.vendor.clone(),
+        "link" => order.link.clone(),
+        "store_in" => order.store_in.clone(),
+        "requester" => order.requester.clone().unwrap_or_default(),
+        key => match order.fields.get(key) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(text)) => text.clone(),
+            Some(value) => value.to_string(),
+        },
+    };
+    text.trim().to_string()
+}
+
+/// Every rule `order` breaks
+pub fn check(rules: &[IntakeRule], order: &PendingOrder) -> Vec<Violation> {
+    rules
+        .iter()
+        .filter_map(|rule| {
+            rule.check(order).map(|message| Violation {
+                rule: rule.name.clone(),
+                field: rule.field.clone(),
+                message,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,393 @@
+use std::time::Duration;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, warn};
+
+use crate::{
+    auth, dm,
+    jobs::{self, Retry, Schedule},
+    scheduler::Team,
+    UsrState,
+};
+
+use super::{approval, current, decide_order, order_status, permalink, OrderRef};
+
+/// The most orders `/order list` answers with, to stay within one message
+const MAX_LISTED: usize = 15;
+/// Makes a reply only visible to whoever used the command
+const EPHEMERAL: u32 = 1 << 6;
+
+/// Settings for answering Discord slash commands, so that orders can be
+/// looked up and approved without opening the web UI. Discord posts each
+/// use of a command to `/api/discord/interactions`, which is set as the
+/// application's interactions endpoint url.
+#[derive(Deserialize)]
+pub struct InteractionsConfig {
+    /// The application's public key, in hex, that Discord signs interactions
+    /// with
+    pub public_key: String,
+    /// The application's id, that the `/order` command is registered under
+    /// by the bot at startup
+    pub application_id: String,
+}
+
+impl InteractionsConfig {
+    pub fn validate(&self, problems: &mut Vec<String>) {
+        if !matches!(hex::decode(self.public_key.trim()), Ok(key) if key.len() == 32) {
+            problems.push("interactions.public_key: must be 64 hex digits".to_string());
+        }
+        if self.application_id.is_empty()
+            || !self.application_id.bytes().all(|b| b.is_ascii_digit())
+        {
+            problems.push("interactions.application_id: must be a Discord id".to_string());
+        }
+    }
+}
+
+/// Answers Discord slash commands
+pub struct Interactions {
+    config: InteractionsConfig,
+    public_key: Vec<u8>,
+}
+
+impl Interactions {
+    pub fn new(config: InteractionsConfig) -> Self {
+        Self {
+            public_key: hex::decode(config.public_key.trim()).unwrap_or_default(),
+            config,
+        }
+    }
+
+    /// Whether `body` was signed by Discord, as every interaction has to be
+    /// checked before it is answered
+    fn verify(&self, headers: &HeaderMap, body: &[u8]) -> bool {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let (Some(signature), Some(timestamp)) = (
+            header("X-Signature-Ed25519"),
+            header("X-Signature-Timestamp"),
+        ) else {
+            return false;
+        };
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        let mut message = timestamp.as_bytes().to_vec();
+        message.extend_from_slice(body);
+        UnparsedPublicKey::new(&ED25519, &self.public_key)
+            .verify(&message, &signature)
+            .is_ok()
+    }
+}
+
+#[derive(Deserialize)]
+struct Interaction {
+    #[serde(rename = "type")]
+    kind: u8,
+    #[serde(default)]
+    data: Option<CommandData>,
+    /// Who used the command in a server
+    #[serde(default)]
+    member: Option<GuildMember>,
+    /// Who used the command in a direct message
+    #[serde(default)]
+    user: Option<User>,
+}
+
+#[derive(Deserialize)]
+struct GuildMember {
+    user: User,
+}
+
+#[derive(Deserialize)]
+struct User {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct CommandData {
+    name: String,
+    #[serde(default)]
+    options: Vec<CommandOption>,
+}
+
+/// An option given to a command, or a subcommand and its own options
+#[derive(Deserialize)]
+struct CommandOption {
+    name: String,
+    #[serde(default)]
+    value: Option<serde_json::Value>,
+    #[serde(default)]
+    options: Vec<CommandOption>,
+}
+
+impl CommandOption {
+    /// The value of the option `name` as text
+    fn text(&self, name: &str) -> Option<String> {
+        let value = self
+            .options
+            .iter()
+            .find(|x| x.name == name)?
+            .value
+            .as_ref()?;
+        Some(match value {
+            serde_json::Value::String(value) => value.trim().to_string(),
+            value => value.to_string(),
+        })
+    }
+}
+
+/// The `/order` command, as registered with Discord
+fn order_command() -> serde_json::Value {
+    let id = json!({
+        "type": 3,
+        "name": "id",
+        "description": "The order's number, eg. USR-2025-0042",
+        "required": true,
+    });
+    let teams: Vec<_> = [
+        Team::Software,
+        Team::Mechanical,
+        Team::Electrical,
+        Team::Systems,
+        Team::Social,
+        Team::Admin,
+    ]
+    .iter()
+    .map(|team| json!({ "name": team.to_string(), "value": team.to_string() }))
+    .collect();
+    json!([{
+        "name": "order",
+        "description": "Look up and approve orders",
+        "options": [
+            {
+                "type": 1,
+                "name": "status",
+                "description": "Where an order is at",
+                "options": [id],
+            },
+            {
+                "type": 1,
+                "name": "list",
+                "description": "A team's orders that haven't been put away yet",
+                "options": [{
+                    "type": 3,
+                    "name": "team",
+                    "description": "The team",
+                    "required": true,
+                    "choices": teams,
+                }],
+            },
+            {
+                "type": 1,
+                "name": "approve",
+                "description": "Approve a new order, as a lead",
+                "options": [id],
+            },
+        ],
+    }])
+}
+
+fn reply(content: String) -> Response {
+    Json(json!({
+        "type": 4,
+        "data": { "content": content, "flags": EPHEMERAL },
+    }))
+    .into_response()
+}
+
+/// Answers a slash command, after checking that Discord sent it
+#[axum::debug_handler]
+async fn interaction(
+    State(state): State<&'static UsrState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(interactions) = &state.interactions else {
+        return (StatusCode::NOT_FOUND, "").into_response();
+    };
+    if !interactions.verify(&headers, &body) {
+        return (StatusCode::UNAUTHORIZED, "Invalid signature").into_response();
+    }
+    let interaction: Interaction = match serde_json::from_slice(&body) {
+        Ok(interaction) => interaction,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    match interaction.kind {
+        // Discord pings the endpoint when it is set, and now and then after
+        1 => Json(json!({ "type": 1 })).into_response(),
+        2 => {
+            let Some(discord_id) = interaction
+                .member
+                .map(|member| member.user)
+                .or(interaction.user)
+                .map(|user| user.id)
+            else {
+                return (StatusCode::BAD_REQUEST, "").into_response();
+            };
+            let Some(subcommand) = interaction
+                .data
+                .filter(|data| data.name == "order")
+                .and_then(|data| data.options.into_iter().next())
+            else {
+                return reply("Unknown command".to_string());
+            };
+            match run(state, &discord_id, subcommand).await {
+                Ok(content) => reply(content),
+                Err(e) => {
+                    error!("Failed to answer slash command: {e}");
+                    reply("Something went wrong, try again in a bit".to_string())
+                }
+            }
+        }
+        _ => (StatusCode::BAD_REQUEST, "").into_response(),
+    }
+}
+
+/// Runs a subcommand of `/order` for the member with Discord id
+/// `discord_id`. Only members whose Discord id belongs to a user are trusted
+/// with more than looking orders up, or with anything when sign in is
+/// required.
+async fn run(
+    state: &'static UsrState,
+    discord_id: &str,
+    subcommand: CommandOption,
+) -> Result<String, sea_orm::DbErr> {
+    let caller = match dm::member_name(&state.db, discord_id).await? {
+        Some(name) => auth::caller_named(&state.db, &name).await?,
+        None => None,
+    };
+    const UNLINKED: &str = "Your Discord account isn't linked to a user yet, ask a lead to link it";
+    if caller.is_none() && state.require_auth {
+        return Ok(UNLINKED.to_string());
+    }
+
+    match subcommand.name.as_str() {
+        "status" => {
+            let id = OrderRef::Number(subcommand.text("id").unwrap_or_default());
+            let Some(id) = id.resolve(&state.db).await? else {
+                return Ok("Order not found".to_string());
+            };
+            let Some(model) = current::Entity::find_by_id(id).one(&state.db).await? else {
+                return Ok("Order not found".to_string());
+            };
+            Ok(status_text(model))
+        }
+        "list" => {
+            let team = subcommand.text("team").unwrap_or_default();
+            let Ok(team) = serde_json::from_value::<Team>(json!(team)) else {
+                return Ok(format!("{team} is not a team"));
+            };
+            let models = current::Entity::find()
+                .filter(current::Column::Team.eq(team))
+                .filter(current::Column::Status.is_not_in([
+                    order_status::Status::InStorage,
+                    order_status::Status::Returned,
+                    order_status::Status::Cancelled,
+                ]))
+                .order_by_asc(current::Column::Id)
+                .all(&state.db)
+                .await?;
+            if models.is_empty() {
+                return Ok(format!("{team} has no orders on their way"));
+            }
+            let mut text = format!("**{team}'s Open Orders**");
+            let more = models.len().saturating_sub(MAX_LISTED);
+            for model in models.into_iter().take(MAX_LISTED) {
+                let (model, status) = model.into_parts();
+                text.push_str(&format!(
+                    "\n- **{}** {} x {}: {status}",
+                    model.number(),
+                    model.count,
+                    model.name
+                ));
+            }
+            if more > 0 {
+                text.push_str(&format!("\n…and {more} more"));
+            }
+            Ok(text)
+        }
+        "approve" => {
+            let Some(caller) = caller else {
+                return Ok(UNLINKED.to_string());
+            };
+            let number = subcommand.text("id").unwrap_or_default();
+            let id = OrderRef::Number(number.clone());
+            let response =
+                decide_order(state, caller, id, approval::Decision::Approved, None).await;
+            if response.status().is_success() {
+                return Ok(format!("Approved {number}"));
+            }
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), 64 * 1024)
+                .await
+                .unwrap_or_default();
+            let reason = String::from_utf8_lossy(&body);
+            Ok(if reason.is_empty() {
+                format!("Couldn't approve {number}: {status}")
+            } else {
+                format!("Couldn't approve {number}: {reason}")
+            })
+        }
+        _ => Ok("Unknown command".to_string()),
+    }
+}
+
+/// Where an order is at, as answered to `/order status`
+fn status_text(model: current::Model) -> String {
+    let (since, hold_reason) = (model.status_date.date(), model.hold_reason.clone());
+    let (model, status) = model.into_parts();
+    let mut text = format!(
+        "**{}** {} x {}\n**Team:** {}\n**Vendor:** {}\n**Status:** {status} since {since}",
+        model.number(),
+        model.count,
+        model.name,
+        model.team,
+        model.vendor
+    );
+    if let Some(reason) = hold_reason {
+        text.push_str(&format!("\n**Reason:** {reason}"));
+    }
+    if status == order_status::Status::InStorage && !model.store_in.is_empty() {
+        text.push_str(&format!("\n**Location:** {}", model.store_in));
+    }
+    text.push_str(&permalink::line(&model));
+    text
+}
+
+/// Registers the `/order` command with Discord through the bot, once at
+/// startup, so that it stays in step with what this version answers
+pub fn spawn(state: &'static UsrState) {
+    let Some(interactions) = &state.interactions else {
+        return;
+    };
+    if !dm::enabled(state) {
+        warn!("Slash commands can't be registered without a Discord bot");
+        return;
+    }
+    let application_id = interactions.config.application_id.as_str();
+    jobs::spawn(
+        state,
+        "register_commands",
+        Schedule::Once(Duration::ZERO),
+        Retry::times(3, Duration::from_secs(60)),
+        move |state| async move {
+            dm::register_commands(state, application_id, &order_command()).await?;
+            Ok(())
+        },
+    );
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new().route("/interactions", post(interaction))
+}
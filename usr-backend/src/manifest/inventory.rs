@@ -0,0 +1,252 @@
+use chrono::NaiveDateTime;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait,
+    DatabaseTransaction, EntityTrait, PaginatorTrait, QueryFilter,
+};
+use serde::Serialize;
+
+use super::{checkout, order, stock, transfer};
+
+/// How much of a stored order is in one location
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Placement {
+    pub location: String,
+    pub count: i64,
+}
+
+/// Where a stored order's stock is, starting with its `store_in`. `rows` are
+/// its rows of `inventory`; locations it has all been taken from are left out.
+pub fn placements(order: &order::Model, rows: &[stock::Model]) -> Vec<Placement> {
+    let mut out: Vec<_> = rows
+        .iter()
+        .filter(|model| model.quantity > 0)
+        .map(|model| Placement {
+            location: model.location.clone(),
+            count: model.quantity,
+        })
+        .collect();
+    out.sort_by_key(|placement| placement.location != order.store_in);
+    out
+}
+
+/// Adds all of `order` to its `store_in` as it is put in storage. An order
+/// that was stocked before, such as one put back in storage after a hold,
+/// isn't again, even if all of it has been used up since.
+pub async fn stock(db: &impl ConnectionTrait, order: &order::Model) -> Result<(), sea_orm::DbErr> {
+    let stocked = stock::Entity::find()
+        .filter(stock::Column::OrderId.eq(order.id))
+        .count(db)
+        .await?;
+    if stocked > 0 {
+        return Ok(());
+    }
+    stock::ActiveModel {
+        order_id: ActiveValue::Set(order.id),
+        location: ActiveValue::Set(order.store_in.clone()),
+        name: ActiveValue::Set(order.name.clone()),
+        quantity: ActiveValue::Set(order.count),
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+/// Takes whatever is left of `order` out of every location as it leaves for
+/// good, eg. going back to its vendor. Its rows are kept at zero, like used
+/// up stock.
+pub async fn clear(db: &impl ConnectionTrait, order: &order::Model) -> Result<(), sea_orm::DbErr> {
+    stock::Entity::update_many()
+        .col_expr(stock::Column::Quantity, Expr::value(0))
+        .filter(stock::Column::OrderId.eq(order.id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// How much of `order` is left in `location`
+async fn quantity_in(
+    tx: &DatabaseTransaction,
+    order: &order::Model,
+    location: &str,
+) -> Result<Option<stock::Model>, sea_orm::DbErr> {
+    stock::Entity::find_by_id((order.id, location.to_string()))
+        .one(tx)
+        .await
+}
+
+/// Moves `count` of `order` from `from` to `to`, recording it in the ledger.
+/// When everything left ends up in one place, that becomes the order's
+/// `store_in`. Returns why it can't be moved instead if there isn't enough
+/// of it at `from`.
+pub async fn transfer(
+    tx: &DatabaseTransaction,
+    order: &order::Model,
+    count: i64,
+    from: &str,
+    to: &str,
+    note: Option<String>,
+    now: NaiveDateTime,
+) -> Result<Result<transfer::Model, String>, sea_orm::DbErr> {
+    let source = quantity_in(tx, order, from).await?;
+    let available = source.as_ref().map_or(0, |model| model.quantity);
+    if available < count {
+        return Ok(Err(format!(
+            "Only {available} of order {} is in {from}",
+            order.number()
+        )));
+    }
+    let source = source.expect("some stock is at the source");
+
+    // Locations emptied by a transfer are dropped, as the stock is still
+    // accounted for wherever it went
+    if source.quantity == count {
+        stock::Entity::delete_by_id((order.id, from.to_string()))
+            .exec(tx)
+            .await?;
+    } else {
+        let quantity = source.quantity - count;
+        let mut active_model: stock::ActiveModel = source.into();
+        active_model.quantity = ActiveValue::Set(quantity);
+        active_model.update(tx).await?;
+    }
+    match quantity_in(tx, order, to).await? {
+        Some(model) => {
+            let quantity = model.quantity + count;
+            let mut active_model: stock::ActiveModel = model.into();
+            active_model.quantity = ActiveValue::Set(quantity);
+            active_model.update(tx).await?;
+        }
+        None => {
+            stock::ActiveModel {
+                order_id: ActiveValue::Set(order.id),
+                location: ActiveValue::Set(to.to_string()),
+                name: ActiveValue::Set(order.name.clone()),
+                quantity: ActiveValue::Set(count),
+            }
+            .insert(tx)
+            .await?;
+        }
+    }
+
+    // Collapse back into `store_in` once everything is in one place
+    let rows = stock::Entity::find()
+        .filter(stock::Column::OrderId.eq(order.id))
+        .filter(stock::Column::Quantity.gt(0))
+        .all(tx)
+        .await?;
+    if let [only] = rows.as_slice() {
+        if only.location != order.store_in {
+            let mut active_model: order::ActiveModel = order.clone().into();
+            active_model.store_in = ActiveValue::Set(only.location.clone());
+            active_model.version = ActiveValue::Set(order.version + 1);
+            active_model.update(tx).await?;
+        }
+    }
+
+    let model = transfer::ActiveModel {
+        id: ActiveValue::NotSet,
+        order_id: ActiveValue::Set(order.id),
+        count: ActiveValue::Set(count),
+        from_location: ActiveValue::Set(from.to_string()),
+        to_location: ActiveValue::Set(to.to_string()),
+        note: ActiveValue::Set(note),
+        date: ActiveValue::Set(now),
+    }
+    .insert(tx)
+    .await?;
+    Ok(Ok(model))
+}
+
+/// Takes `count` of `order` out of `location` for `taken_by` to use. The
+/// location is kept at zero once used up, so that the order isn't stocked
+/// again. Returns why it can't be taken instead if there isn't enough there.
+pub async fn checkout(
+    tx: &DatabaseTransaction,
+    order: &order::Model,
+    location: &str,
+    count: i64,
+    taken_by: String,
+    note: Option<String>,
+    now: NaiveDateTime,
+) -> Result<Result<checkout::Model, String>, sea_orm::DbErr> {
+    let Some(model) = quantity_in(tx, order, location)
+        .await?
+        .filter(|model| model.quantity >= count)
+    else {
+        let available = quantity_in(tx, order, location)
+            .await?
+            .map_or(0, |model| model.quantity);
+        return Ok(Err(format!(
+            "Only {available} of order {} is in {location}",
+            order.number()
+        )));
+    };
+    let quantity = model.quantity - count;
+    let mut active_model: stock::ActiveModel = model.into();
+    active_model.quantity = ActiveValue::Set(quantity);
+    active_model.update(tx).await?;
+
+    let model = checkout::ActiveModel {
+        id: ActiveValue::NotSet,
+        order_id: ActiveValue::Set(order.id),
+        location: ActiveValue::Set(location.to_string()),
+        count: ActiveValue::Set(count),
+        returned: ActiveValue::Set(0),
+        taken_by: ActiveValue::Set(taken_by),
+        note: ActiveValue::Set(note),
+        taken: ActiveValue::Set(now),
+        last_returned: ActiveValue::Set(None),
+    }
+    .insert(tx)
+    .await?;
+    Ok(Ok(model))
+}
+
+/// Puts `count` of what was taken in `checkout` back where it was taken from.
+/// Returns why it can't be returned instead if that's more than is still out.
+pub async fn checkin(
+    tx: &DatabaseTransaction,
+    checkout: checkout::Model,
+    count: i64,
+    now: NaiveDateTime,
+) -> Result<Result<checkout::Model, String>, sea_orm::DbErr> {
+    let outstanding = checkout.count - checkout.returned;
+    if count > outstanding {
+        return Ok(Err(format!(
+            "Only {outstanding} of checkout {} is still out",
+            checkout.id
+        )));
+    }
+    match stock::Entity::find_by_id((checkout.order_id, checkout.location.clone()))
+        .one(tx)
+        .await?
+    {
+        Some(model) => {
+            let quantity = model.quantity + count;
+            let mut active_model: stock::ActiveModel = model.into();
+            active_model.quantity = ActiveValue::Set(quantity);
+            active_model.update(tx).await?;
+        }
+        // The location was emptied by a transfer since
+        None => {
+            let name = order::Entity::find_by_id(checkout.order_id)
+                .one(tx)
+                .await?
+                .map(|order| order.name)
+                .unwrap_or_default();
+            stock::ActiveModel {
+                order_id: ActiveValue::Set(checkout.order_id),
+                location: ActiveValue::Set(checkout.location.clone()),
+                name: ActiveValue::Set(name),
+                quantity: ActiveValue::Set(count),
+            }
+            .insert(tx)
+            .await?;
+        }
+    }
+    let returned = checkout.returned + count;
+    let mut active_model: checkout::ActiveModel = checkout.into();
+    active_model.returned = ActiveValue::Set(returned);
+    active_model.last_returned = ActiveValue::Set(Some(now));
+    active_model.update(tx).await.map(Ok)
+}
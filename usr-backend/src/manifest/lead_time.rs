@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, TimeDelta};
+use serde::Serialize;
+
+use super::{order, order_status};
+
+/// How many of a vendor's latest orders are compared against its history
+const RECENT_SAMPLES: usize = 5;
+/// Recent lead times this many times slower than before mark a vendor as degraded
+const DEGRADED_FACTOR: f64 = 1.5;
+
+#[derive(Serialize, Clone)]
+pub struct VendorLeadTime {
+    pub vendor: String,
+    pub samples: usize,
+    pub median_days: f64,
+    pub mean_days: f64,
+    pub p90_days: f64,
+    pub recent_median_days: f64,
+    pub degraded: bool,
+}
+
+impl VendorLeadTime {
+    pub fn predict(&self, ordered: NaiveDateTime) -> NaiveDateTime {
+        ordered + days(self.median_days)
+    }
+
+    pub fn predict_late(&self, ordered: NaiveDateTime) -> NaiveDateTime {
+        ordered + days(self.p90_days)
+    }
+}
+
+fn days(days: f64) -> TimeDelta {
+    TimeDelta::seconds((days * 60.0 * 60.0 * 24.0) as i64)
+}
+
+pub fn vendor_key(vendor: &str) -> String {
+    vendor.trim().to_lowercase()
+}
+
+/// `sorted` must be non-empty and sorted ascending.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+}
+
+fn median(samples: &[f64]) -> f64 {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    percentile(&sorted, 0.5)
+}
+
+/// The time an order was placed, and how long it took to arrive.
+fn sample(statuses: &[&order_status::Model]) -> Option<(NaiveDateTime, f64)> {
+    let first = |status| {
+        statuses
+            .iter()
+            .filter(|model| model.status == status)
+            .map(|model| model.date)
+            .min()
+    };
+    let ordered = first(order_status::Status::Submitted)?;
+    let arrived = first(order_status::Status::Delivered)
+        .or_else(|| first(order_status::Status::InStorage))?;
+    let elapsed = (arrived - ordered).num_seconds();
+    (elapsed >= 0).then(|| (ordered, elapsed as f64 / (60.0 * 60.0 * 24.0)))
+}
+
+/// Learns each vendor's lead time from when orders were submitted to when they
+/// were delivered, keyed by [`vendor_key`].
+pub fn compute(
+    orders: &[order::Model],
+    statuses: &[order_status::Model],
+) -> HashMap<String, VendorLeadTime> {
+    let mut by_order = HashMap::<i64, Vec<&order_status::Model>>::new();
+    for model in statuses {
+        by_order.entry(model.order_id).or_default().push(model);
+    }
+
+    let mut by_vendor = HashMap::<String, (String, Vec<(NaiveDateTime, f64)>)>::new();
+    for model in orders {
+        if model.vendor.trim().is_empty() {
+            continue;
+        }
+        let Some(sample) = by_order.get(&model.id).and_then(|x| sample(x)) else {
+            continue;
+        };
+        by_vendor
+            .entry(vendor_key(&model.vendor))
+            .or_insert_with(|| (model.vendor.trim().to_string(), vec![]))
+            .1
+            .push(sample);
+    }
+
+    by_vendor
+        .into_iter()
+        .map(|(key, (vendor, mut samples))| {
+            samples.sort_by_key(|(ordered, _)| *ordered);
+            let days: Vec<_> = samples.iter().map(|(_, days)| *days).collect();
+            let mut sorted = days.clone();
+            sorted.sort_by(f64::total_cmp);
+
+            let split = days.len().saturating_sub(RECENT_SAMPLES);
+            let recent_median_days = median(&days[split..]);
+            let degraded =
+                split > 0 && recent_median_days > median(&days[..split]) * DEGRADED_FACTOR;
+
+            (
+                key,
+                VendorLeadTime {
+                    vendor,
+                    samples: days.len(),
+                    median_days: percentile(&sorted, 0.5),
+                    mean_days: days.iter().sum::<f64>() / days.len() as f64,
+                    p90_days: percentile(&sorted, 0.9),
+                    recent_median_days,
+                    degraded,
+                },
+            )
+        })
+        .collect()
+}
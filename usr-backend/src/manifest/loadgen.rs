@@ -0,0 +1,119 @@
+use chrono::{Days, Duration, Local, NaiveDateTime};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::SliceRandom,
+    Rng,
+};
+use sea_orm::{prelude::Decimal, DatabaseConnection};
+
+use crate::scheduler::Team;
+
+use super::{
+    fixture::{self, OrderFixture},
+    order_status,
+};
+
+/// Orders are spread over this many days before today
+const HISTORY_DAYS: u64 = 365;
+
+const VENDORS: [(&str, u32); 8] = [
+    ("McMaster-Carr", 30),
+    ("DigiKey", 25),
+    ("Amazon", 20),
+    ("AndyMark", 8),
+    ("REV Robotics", 6),
+    ("Mouser", 5),
+    ("Home Depot", 4),
+    ("goBILDA", 2),
+];
+
+const ITEMS: [&str; 16] = [
+    "M3 socket head cap screw",
+    "M4 nylock nut",
+    "608 bearing",
+    "NEO brushless motor",
+    "SPARK MAX controller",
+    "XT60 connector",
+    "22 AWG silicone wire",
+    "Aluminum 6061 plate",
+    "PLA filament",
+    "Timing belt GT2",
+    "Raspberry Pi 5",
+    "Buck converter",
+    "Heat shrink assortment",
+    "Lithium battery 6S",
+    "Limit switch",
+    "Zip ties",
+];
+
+const TEAMS: [(Team, u32); 6] = [
+    (Team::Mechanical, 35),
+    (Team::Electrical, 30),
+    (Team::Software, 15),
+    (Team::Systems, 10),
+    (Team::Admin, 6),
+    (Team::Social, 4),
+];
+
+/// Moves `fixture`, placed at `placed`, through the statuses it would have
+/// gone through by `now`
+fn status_history(
+    rng: &mut impl Rng,
+    mut fixture: OrderFixture,
+    placed: NaiveDateTime,
+    now: NaiveDateTime,
+) -> OrderFixture {
+    fixture = fixture.placed(placed);
+    // Days spent in New, Submitted, Shipped and Delivered respectively
+    let steps = [
+        (order_status::Status::Submitted, rng.gen_range(0..4)),
+        (order_status::Status::Shipped, rng.gen_range(1..4)),
+        (order_status::Status::Delivered, rng.gen_range(2..12)),
+        (order_status::Status::InStorage, rng.gen_range(0..3)),
+    ];
+    let mut date = placed;
+    for (status, days) in steps {
+        date += Duration::days(days) + Duration::minutes(rng.gen_range(0..600));
+        if date > now {
+            break;
+        }
+        fixture = fixture.status_at(status, date);
+    }
+    fixture
+}
+
+/// Inserts `count` synthetic orders, with status histories that advance the
+/// way real orders do, for benchmarking. Not for use on a real database.
+pub async fn generate(db: &DatabaseConnection, count: u32) -> Result<(), sea_orm::DbErr> {
+    let mut rng = rand::thread_rng();
+    let vendor_weights = WeightedIndex::new(VENDORS.map(|(_, weight)| weight)).unwrap();
+    let team_weights = WeightedIndex::new(TEAMS.map(|(_, weight)| weight)).unwrap();
+    let now = Local::now().naive_local();
+    let start = now - Days::new(HISTORY_DAYS);
+
+    let fixtures = (0..count)
+        .map(|_| {
+            let vendor = VENDORS[vendor_weights.sample(&mut rng)].0;
+            let name = ITEMS.choose(&mut rng).unwrap();
+            // Most orders are for a handful of cheap parts
+            let count = if rng.gen_bool(0.8) {
+                rng.gen_range(1..=4)
+            } else {
+                rng.gen_range(5..=100)
+            };
+            let cents = (10f64.powf(rng.gen_range(1.7..4.7))) as i64;
+            let placed =
+                start + Duration::minutes(rng.gen_range(0..HISTORY_DAYS as i64 * 24 * 60));
+            let fixture = OrderFixture::new()
+                .name(*name)
+                .count(count)
+                .unit_cost(Decimal::new(cents, 2))
+                .store_in(format!("Bin {}", rng.gen_range(1..=40)))
+                .team(TEAMS[team_weights.sample(&mut rng)].0)
+                .reason("Load test")
+                .vendor(vendor);
+            status_history(&mut rng, fixture, placed, now)
+        })
+        .collect();
+    fixture::insert_many(db, fixtures).await
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{entity::prelude::*, sea_query::Expr, ActiveValue, QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{backup::backup_db, scheduler::Team, UsrState};
+
+use super::{
+    checkout, current, non_blank, order, order_status::Status, orders_changed, service::OrderError,
+    stock,
+};
+
+/// Somewhere in the shop that parts are kept. Orders name where they go by
+/// the location's `label`, which is also what gets printed on the bin.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "locations")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub room: String,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shelf: Option<String>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin: Option<String>,
+    /// What `store_in` and inventory refer to the location by, eg. `EE Lab
+    /// Shelf 2 Bin 14`
+    pub label: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// `store_in` spelled as the location it names, or why it names none. Until
+/// a lead has set up any locations, anything is taken as it was before, and
+/// an order can always be left without one.
+pub async fn check(db: &impl ConnectionTrait, store_in: &str) -> Result<String, OrderError> {
+    let store_in = store_in.trim();
+    if store_in.is_empty() {
+        return Ok(String::new());
+    }
+    let locations = match Entity::find().all(db).await {
+        Ok(locations) => locations,
+        Err(e) => {
+            error!("Failed to find locations: {e}");
+            return Err(OrderError::Internal);
+        }
+    };
+    if locations.is_empty() {
+        return Ok(store_in.to_string());
+    }
+    locations
+        .into_iter()
+        .find(|location| location.label.eq_ignore_ascii_case(store_in))
+        .map(|location| location.label)
+        .ok_or_else(|| {
+            OrderError::InvalidField(
+                "store_in",
+                format!("{store_in} is not a known location, ask a lead to add it"),
+            )
+        })
+}
+
+#[derive(Deserialize)]
+pub struct SetLocation {
+    /// The location to change, or a new one if left out
+    #[serde(default)]
+    id: Option<u32>,
+    room: String,
+    #[serde(default)]
+    shelf: Option<String>,
+    #[serde(default)]
+    bin: Option<String>,
+    /// The room, shelf and bin put together if left out
+    #[serde(default)]
+    label: Option<String>,
+}
+
+/// Adds a location, or changes one. Orders and stock in a location that is
+/// relabelled move to the new label with it.
+#[axum::debug_handler]
+pub async fn set_location(
+    State(state): State<&'static UsrState>,
+    Json(set): Json<SetLocation>,
+) -> Response {
+    let id = set.id.map(i64::from);
+    let room = set.room.trim().to_string();
+    if room.is_empty() {
+        return (StatusCode::BAD_REQUEST, "A room is required").into_response();
+    }
+    let shelf = non_blank(set.shelf).map(|shelf| shelf.trim().to_string());
+    let bin = non_blank(set.bin).map(|bin| bin.trim().to_string());
+    let label = match non_blank(set.label) {
+        Some(label) => label.trim().to_string(),
+        None => [Some(&room), shelf.as_ref(), bin.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+
+    let locations = match Entity::find().all(&state.db).await {
+        Ok(locations) => locations,
+        Err(e) => {
+            error!("Failed to find locations: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if locations
+        .iter()
+        .any(|location| Some(location.id) != id && location.label.eq_ignore_ascii_case(&label))
+    {
+        return (
+            StatusCode::CONFLICT,
+            format!("There is already a location labelled {label}"),
+        )
+            .into_response();
+    }
+    let previous = match id {
+        Some(id) => match locations.into_iter().find(|location| location.id == id) {
+            Some(previous) => Some(previous.label),
+            None => return (StatusCode::BAD_REQUEST, "Location not found").into_response(),
+        },
+        None => None,
+    };
+
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let active_model = ActiveModel {
+                    id: id.map_or(ActiveValue::NotSet, ActiveValue::Unchanged),
+                    room: ActiveValue::Set(room),
+                    shelf: ActiveValue::Set(shelf),
+                    bin: ActiveValue::Set(bin),
+                    label: ActiveValue::Set(label.clone()),
+                };
+                let model = match id {
+                    Some(_) => active_model.update(tx).await?,
+                    None => active_model.insert(tx).await?,
+                };
+                if let Some(previous) = previous.filter(|previous| *previous != label) {
+                    order::Entity::update_many()
+                        .col_expr(order::Column::StoreIn, Expr::value(&label))
+                        .filter(order::Column::StoreIn.eq(&previous))
+                        .exec(tx)
+                        .await?;
+                    stock::Entity::update_many()
+                        .col_expr(stock::Column::Location, Expr::value(&label))
+                        .filter(stock::Column::Location.eq(&previous))
+                        .exec(tx)
+                        .await?;
+                    checkout::Entity::update_many()
+                        .col_expr(checkout::Column::Location, Expr::value(&label))
+                        .filter(checkout::Column::Location.eq(&previous))
+                        .exec(tx)
+                        .await?;
+                }
+                Result::<_, DbErr>::Ok(model)
+            })
+        })
+        .await;
+
+    match result {
+        Ok(model) => {
+            backup_db(state);
+            if id.is_some() {
+                orders_changed(state, None).await;
+            }
+            Json(model).into_response()
+        }
+        Err(e) => {
+            error!("Failed to set location: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteLocation {
+    id: u32,
+}
+
+/// Removes a location that nothing is kept in or on its way to anymore
+#[axum::debug_handler]
+pub async fn del_location(
+    State(state): State<&'static UsrState>,
+    Json(DeleteLocation { id }): Json<DeleteLocation>,
+) -> Response {
+    let result = async {
+        let Some(model) = Entity::find_by_id(id).one(&state.db).await? else {
+            return Ok(None);
+        };
+        let stocked = stock::Entity::find()
+            .filter(stock::Column::Location.eq(&model.label))
+            .filter(stock::Column::Quantity.gt(0))
+            .count(&state.db)
+            .await?;
+        let incoming = current::Entity::find()
+            .filter(current::Column::StoreIn.eq(&model.label))
+            .filter(current::Column::Status.is_in(INCOMING))
+            .count(&state.db)
+            .await?;
+        Result::<_, DbErr>::Ok(Some((model, stocked, incoming)))
+    }
+    .await;
+
+    let model = match result {
+        Ok(Some((model, 0, 0))) => model,
+        Ok(Some((model, stocked, incoming))) => {
+            return (
+                StatusCode::CONFLICT,
+                format!(
+                    "{} has {stocked} orders stored in it and {incoming} on their way to it, move them first",
+                    model.label
+                ),
+            )
+                .into_response()
+        }
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Location not found").into_response(),
+        Err(e) => {
+            error!("Failed to find what is in location: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    match Entity::delete_by_id(model.id).exec(&state.db).await {
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete location: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Every location, by label
+#[axum::debug_handler]
+pub async fn get_locations(State(state): State<&'static UsrState>) -> Response {
+    match Entity::find()
+        .order_by_asc(Column::Label)
+        .all(&state.db)
+        .await
+    {
+        Ok(models) => Json(models).into_response(),
+        Err(e) => {
+            error!("Failed to get locations: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Statuses of orders that are still on their way to their `store_in`
+const INCOMING: [Status; 6] = [
+    Status::New,
+    Status::Submitted,
+    Status::Shipped,
+    Status::PartiallyReceived,
+    Status::Delivered,
+    Status::OnHold,
+];
+
+#[derive(Serialize)]
+struct Stored {
+    order_id: i64,
+    /// The order's display number, eg. USR-2025-0042
+    number: String,
+    name: String,
+    team: Team,
+    quantity: i64,
+}
+
+#[derive(Serialize)]
+struct Incoming {
+    order_id: i64,
+    number: String,
+    name: String,
+    team: Team,
+    count: i64,
+    status: Status,
+}
+
+#[derive(Serialize)]
+struct Contents {
+    #[serde(flatten)]
+    location: Model,
+    /// What is kept there now, by order
+    stored: Vec<Stored>,
+    /// Orders that will be put there once they arrive
+    incoming: Vec<Incoming>,
+}
+
+/// What is in a location and on its way to it, eg. to print its bin label
+/// or to find a part
+#[axum::debug_handler]
+pub async fn get_location_contents(
+    State(state): State<&'static UsrState>,
+    Path(id): Path<u32>,
+) -> Response {
+    let result = async {
+        let Some(location) = Entity::find_by_id(id).one(&state.db).await? else {
+            return Ok(None);
+        };
+        let rows = stock::Entity::find()
+            .filter(stock::Column::Location.eq(&location.label))
+            .filter(stock::Column::Quantity.gt(0))
+            .order_by_asc(stock::Column::OrderId)
+            .all(&state.db)
+            .await?;
+        let orders: HashMap<_, _> = order::Entity::find()
+            .filter(order::Column::Id.is_in(rows.iter().map(|row| row.order_id)))
+            .all(&state.db)
+            .await?
+            .into_iter()
+            .map(|order| (order.id, order))
+            .collect();
+        let incoming = current::Entity::find()
+            .filter(current::Column::StoreIn.eq(&location.label))
+            .filter(current::Column::Status.is_in(INCOMING))
+            .order_by_asc(current::Column::Id)
+            .all(&state.db)
+            .await?;
+        Result::<_, DbErr>::Ok(Some((location, rows, orders, incoming)))
+    }
+    .await;
+
+    match result {
+        Ok(Some((location, rows, orders, incoming))) => Json(Contents {
+            location,
+            stored: rows
+                .into_iter()
+                .filter_map(|row| {
+                    let order = orders.get(&row.order_id)?;
+                    Some(Stored {
+                        order_id: order.id,
+                        number: order.number(),
+                        name: order.name.clone(),
+                        team: order.team,
+                        quantity: row.quantity,
+                    })
+                })
+                .collect(),
+            incoming: incoming
+                .into_iter()
+                .map(|current| {
+                    let (order, status) = current.into_parts();
+                    Incoming {
+                        order_id: order.id,
+                        number: order.number(),
+                        name: order.name,
+                        team: order.team,
+                        count: order.count,
+                        status,
+                    }
+                })
+                .collect(),
+        })
+        .into_response(),
+        Ok(None) => (StatusCode::BAD_REQUEST, "Location not found").into_response(),
+        Err(e) => {
+            error!("Failed to get location contents: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use axum::Router;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::UsrState;
+
+/// The OpenAPI document for the manifest's order routes, generated from the
+/// handlers and the types they take and respond with
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "USR"),
+    paths(
+        super::new_order,
+        super::new_orders,
+        super::clone_order,
+        super::reorder,
+        super::change_order,
+        super::cancel_order,
+        super::restore_order,
+        super::update_order,
+        super::receive_order,
+        super::get_orders,
+        super::get_my_orders,
+        super::get_statuses,
+        super::search::search_orders,
+    )
+)]
+struct ApiDoc;
+
+/// Swagger UI at `/api-doc`, served from assets bundled into the binary, and
+/// the document at `/api-doc/openapi.json`
+pub fn router() -> Router<&'static UsrState> {
+    SwaggerUi::new("/api-doc")
+        .url("/api-doc/openapi.json", ApiDoc::openapi())
+        .into()
+}
@@ -1,28 +1,200 @@
-use sea_orm::entity::prelude::*;
-use serde::Serialize;
+use std::fmt::Display;
 
-use crate::scheduler;
+use sea_orm::{entity::prelude::*, ActiveValue};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+use crate::{money, scheduler};
+
+use super::funding;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
 #[sea_orm(table_name = "orders")]
+#[schema(as = Order)]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u32,
+    pub id: i64,
     pub name: String,
-    pub count: u32,
+    pub count: i64,
     pub unit_cost: Decimal,
     pub store_in: String,
     pub team: scheduler::Team,
     pub reason: String,
     pub vendor: String,
     pub link: String,
+    #[sea_orm(default_value = "D")]
+    pub funding_source: funding::Source,
+    /// The robot component this order was bought for, if any
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component_id: Option<i64>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_number: Option<i64>,
+    /// The year of the season the order was placed in, which scopes `season_number`
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub season: Option<i64>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub season_number: Option<i64>,
+    /// Whether the university's tax exemption was used, once the treasurer
+    /// has recorded it
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax_exempt: Option<bool>,
+    /// How the order was paid for, recorded when it is submitted
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_method: Option<PaymentMethod>,
+    /// The member who asked for the order, who is sent a daily digest of how
+    /// it is moving along
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requester: Option<String>,
+    /// The currency the order was priced in, for orders that weren't priced
+    /// in dollars. `unit_cost` is always in dollars, converted when the order
+    /// was placed.
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<Currency>,
+    /// The unit cost in `currency`, as the order was placed
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_unit_cost: Option<Decimal>,
+    /// Dollars per unit of `currency` when the order was placed
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_rate: Option<Decimal>,
+    /// Shared by the orders placed together from one vendor's cart
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cart_id: Option<String>,
+    /// The day the order has to be bought by, eg. a competition's shipping
+    /// cutoff, which reminders are posted ahead of
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<NaiveDate>)]
+    pub needed_by: Option<Date>,
+    /// What the vendor actually charged on top of the units, once known,
+    /// for reimbursements
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shipping_cost: Option<Decimal>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax: Option<Decimal>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees: Option<Decimal>,
+    /// Bumped on every change to the order, so that an edit made to an older
+    /// copy of it can be turned away instead of overwriting the newer one
+    #[sea_orm(default_value = 0)]
+    pub version: i64,
+    /// Whether the order was placed while competition mode was on, for
+    /// reporting on what an event cost afterwards
+    #[sea_orm(default_value = false)]
+    pub competition: bool,
+    /// How many units have arrived so far, for orders that arrive in more
+    /// than one delivery. `None` if it arrived in one go or hasn't yet.
     #[sea_orm(nullable)]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ref_number: Option<u32>
+    pub received_count: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
 }
 
-impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Saves `active_model` as the next version of an order that was at
+/// `version`. Returns `None` without saving if the order has been changed
+/// since, eg. by someone else editing it at the same time.
+pub async fn update_versioned(
+    db: &impl ConnectionTrait,
+    version: i64,
+    mut active_model: ActiveModel,
+) -> Result<Option<Model>, DbErr> {
+    active_model.version = ActiveValue::Set(version + 1);
+    match Entity::update(active_model)
+        .filter(Column::Version.eq(version))
+        .exec(db)
+        .await
+    {
+        Ok(model) => Ok(Some(model)),
+        Err(DbErr::RecordNotUpdated) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl Model {
+    /// Whether any of shipping, tax or fees has been recorded
+    pub fn has_charges(&self) -> bool {
+        self.shipping_cost.is_some() || self.tax.is_some() || self.fees.is_some()
+    }
+
+    /// The units plus whatever shipping, tax and fees have been recorded
+    pub fn total(&self) -> Decimal {
+        money::subtotal(self.count, self.unit_cost)
+            + self.shipping_cost.unwrap_or_default()
+            + self.tax.unwrap_or_default()
+            + self.fees.unwrap_or_default()
+    }
+
+    /// The number shown to people, like USR-2025-0042, falling back to the
+    /// id for orders that haven't been numbered
+    pub fn number(&self) -> String {
+        match (self.season, self.season_number) {
+            (Some(season), Some(number)) => format!("USR-{season}-{number:04}"),
+            _ => format!("#{}", self.id),
+        }
+    }
+}
+
+/// Reconciliation is different for each of these, so the treasurer needs to
+/// know which was used
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize, ToSchema)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum PaymentMethod {
+    #[sea_orm(string_value = "C")]
+    PCard,
+    #[sea_orm(string_value = "P")]
+    PurchaseOrder,
+    /// Bought by a member and paid back to them
+    #[sea_orm(string_value = "R")]
+    Reimbursement,
+    /// Paid for by a sponsor directly
+    #[sea_orm(string_value = "S")]
+    SponsorDirect,
+}
+
+/// Currencies that vendors price in
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize, Default, ToSchema)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(3))")]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    #[default]
+    #[sea_orm(string_value = "USD")]
+    Usd,
+    #[sea_orm(string_value = "CAD")]
+    Cad,
+    #[sea_orm(string_value = "EUR")]
+    Eur,
+}
+
+impl Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Currency::Usd => write!(f, "USD"),
+            Currency::Cad => write!(f, "CAD"),
+            Currency::Eur => write!(f, "EUR"),
+        }
+    }
+}
+
+impl Display for PaymentMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
@@ -1,17 +1,23 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
 use sea_orm::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
 #[sea_orm(table_name = "order_status")]
+#[schema(as = OrderStatus)]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub instance_id: u32,
-    pub order_id: u32,
+    pub instance_id: i64,
+    pub order_id: i64,
     pub date: DateTime,
-    pub status: Status
+    pub status: Status,
+    /// Why the order was put on hold or returned, for `OnHold` and `Returned`
+    /// statuses
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -20,7 +26,7 @@ pub enum Relation {
 
 impl ActiveModelBehavior for ActiveModel {}
 
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize, ToSchema)]
 #[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
 pub enum Status {
     #[sea_orm(string_value = "N")]
@@ -29,14 +35,77 @@ pub enum Status {
     Submitted,
     #[sea_orm(string_value = "F")]
     Shipped,
+    /// Some of the units have arrived and the rest are still on their way,
+    /// for orders that arrive in more than one delivery
+    #[sea_orm(string_value = "P")]
+    PartiallyReceived,
     #[sea_orm(string_value = "D")]
     Delivered,
     #[sea_orm(string_value = "I")]
     InStorage,
+    /// Waiting on something outside the usual flow, such as an export-control
+    /// review. Nothing else happens to the order until a lead releases it.
+    #[sea_orm(string_value = "H")]
+    OnHold,
+    /// Sent back to the vendor, eg. because it arrived broken, within the
+    /// vendor's return window
+    #[sea_orm(string_value = "R")]
+    Returned,
+    /// The order was called off. It is kept, along with its history, so that
+    /// what was spent isn't lost and a lead can restore it.
+    #[sea_orm(string_value = "X")]
+    Cancelled,
 }
 
 impl Display for Status {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
-}
\ No newline at end of file
+}
+
+/// Which statuses an order can be moved to from each status through
+/// `/update/order`, eg. `{ "Submitted": ["Shipped", "Delivered"] }`.
+/// Statuses that are left out can't be moved out of. Cancelling and
+/// releasing holds have routes of their own, so they aren't listed.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(transparent)]
+pub struct Transitions(HashMap<Status, Vec<Status>>);
+
+impl Default for Transitions {
+    fn default() -> Self {
+        use Status::*;
+        Self(HashMap::from([
+            (New, vec![Submitted, OnHold]),
+            (
+                Submitted,
+                vec![Shipped, PartiallyReceived, Delivered, InStorage, OnHold],
+            ),
+            (Shipped, vec![PartiallyReceived, Delivered, InStorage, OnHold]),
+            (PartiallyReceived, vec![Delivered, InStorage, OnHold]),
+            (Delivered, vec![InStorage, Returned, OnHold]),
+            (InStorage, vec![Returned]),
+        ]))
+    }
+}
+
+impl Transitions {
+    /// The statuses an order in `from` can be moved to
+    pub fn allowed(&self, from: Status) -> &[Status] {
+        self.0.get(&from).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn validate(&self, problems: &mut Vec<String>) {
+        for (from, to) in &self.0 {
+            if to.contains(&Status::New) {
+                problems.push(format!(
+                    "status_transitions.{from}: orders can't be moved back to New"
+                ));
+            }
+            if to.contains(&Status::Cancelled) {
+                problems.push(format!(
+                    "status_transitions.{from}: orders are cancelled through /del/order"
+                ));
+            }
+        }
+    }
+}
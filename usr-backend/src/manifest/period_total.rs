@@ -0,0 +1,26 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+use crate::scheduler;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "budget_period_totals")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub period_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub team: scheduler::Team,
+    /// Includes `carried_over`
+    pub allocated: Decimal,
+    /// What was left over from the previous period, or overspent if negative
+    pub carried_over: Decimal,
+    /// Filled in when the period is closed
+    #[sea_orm(nullable)]
+    pub spent: Option<Decimal>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
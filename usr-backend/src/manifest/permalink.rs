@@ -0,0 +1,32 @@
+use std::sync::OnceLock;
+
+use super::order;
+
+static WEB_URL: OnceLock<String> = OnceLock::new();
+
+/// Sets the base url of the web UI that order links point to, for the rest
+/// of the process. Only the first call has any effect.
+pub fn init(web_url: &str) {
+    let _ = WEB_URL.set(web_url.trim_end_matches('/').to_string());
+}
+
+/// The order's page in the web UI, if the web UI's url is configured. Orders
+/// are linked by their display number where they have one, since that is
+/// what people read out and search for.
+pub fn url(order: &order::Model) -> Option<String> {
+    let base = WEB_URL.get()?;
+    let reference = match (order.season, order.season_number) {
+        (Some(_), Some(_)) => order.number(),
+        _ => order.id.to_string(),
+    };
+    Some(format!("{base}/manifest?order={reference}"))
+}
+
+/// The last line of every webhook message about `order`, linking to its page.
+/// Empty if the web UI's url isn't configured.
+pub fn line(order: &order::Model) -> String {
+    // Angle brackets keep Discord from embedding a preview of the page
+    url(order)
+        .map(|url| format!("\n**Order Page:** <{url}>"))
+        .unwrap_or_default()
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Days, Local, NaiveDateTime, NaiveTime};
+use sea_orm::{entity::prelude::*, ActiveValue, QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::{
+    backup::backup_db,
+    dm,
+    jobs::{self, Retry, Schedule},
+    notify::Topic,
+    UsrState,
+};
+
+use super::{
+    audit, current, events, order, order_status, permalink, policy, publish_current,
+    resolve_order, watch, OrderRef,
+};
+
+/// Who took a stored order out of storage, so that it stops being nagged about
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "order_pickups")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub order_id: i64,
+    pub picked_up_by: String,
+    pub picked_up: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Tells whoever requested order `id` that it was put away and where to pick
+/// it up, returning `msg` with them mentioned if they can't be sent a direct
+/// message. Requesters who watch the order already hear about it that way.
+pub async fn notify_requester(state: &'static UsrState, id: i64, mut msg: String) -> String {
+    let found = async {
+        let Some(order) = order::Entity::find_by_id(id).one(&state.db).await? else {
+            return Ok(None);
+        };
+        let Some(requester) = order.requester.clone() else {
+            return Ok(None);
+        };
+        if watch::Entity::find_by_id((id, requester.clone()))
+            .one(&state.db)
+            .await?
+            .is_some()
+        {
+            return Ok(None);
+        }
+        let Some(discord_id) = dm::discord_id(&state.db, &requester).await? else {
+            return Ok(None);
+        };
+        Result::<_, DbErr>::Ok(Some((order, requester, discord_id)))
+    }
+    .await;
+    let (order, requester, discord_id) = match found {
+        Ok(Some(found)) => found,
+        Ok(None) => return msg,
+        Err(e) => {
+            error!("Failed to find order requester: {e}");
+            return msg;
+        }
+    };
+
+    if dm::enabled(state) {
+        let locale = match dm::locales(&state.db).await {
+            Ok(locales) => locales.get(&requester).copied().unwrap_or_default(),
+            Err(e) => {
+                error!("Failed to find member locales: {e}");
+                Default::default()
+            }
+        };
+        let mut content = locale.pickup_ready(&order.number(), &order.name, &order.store_in);
+        content.push_str(&permalink::line(&order));
+        tokio::spawn(async move {
+            if let Err(e) = dm::send_dm(state, &requester, &content).await {
+                warn!("Failed to DM pickup notice to {requester}: {e}");
+            }
+        });
+    } else {
+        msg.push_str(&format!("\n**Pick Up:** <@{discord_id}>"));
+    }
+    msg
+}
+
+#[derive(Deserialize)]
+pub struct PickupOrder {
+    id: OrderRef,
+}
+
+/// Confirms that a stored order was taken out of storage. Members can only
+/// confirm the orders they requested.
+#[axum::debug_handler]
+pub async fn pickup_order(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(PickupOrder { id }): Json<PickupOrder>,
+) -> Response {
+    let id = match resolve_order(&state.db, &id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let (current, pickup) = tokio::join!(
+        current::Entity::find_by_id(id).one(&state.db),
+        Entity::find_by_id(id).one(&state.db),
+    );
+    let (current, pickup) = match (current, pickup) {
+        (Ok(Some(current)), Ok(pickup)) => (current, pickup),
+        (Ok(None), _) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let (model, status) = current.into_parts();
+    if let Err(response) = policy::check_owner(&caller, &model) {
+        return response.into_response();
+    }
+    if status != order_status::Status::InStorage {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Only orders in storage can be picked up, not {status} ones"),
+        )
+            .into_response();
+    }
+    if let Some(pickup) = pickup {
+        return (
+            StatusCode::CONFLICT,
+            format!(
+                "{} was already picked up by {} on {}",
+                model.number(),
+                pickup.picked_up_by,
+                pickup.picked_up.date()
+            ),
+        )
+            .into_response();
+    }
+
+    let actor = audit::Actor::new(&caller, "/pickup/order");
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let pickup = ActiveModel {
+                    order_id: ActiveValue::Set(id),
+                    picked_up_by: ActiveValue::Set(actor.name().to_string()),
+                    picked_up: ActiveValue::Set(Local::now().naive_local()),
+                }
+                .insert(tx)
+                .await?;
+                let diff = audit::change("picked_up_by", (), &pickup.picked_up_by);
+                actor.record(tx, id, diff).await?;
+                Result::<_, DbErr>::Ok(pickup)
+            })
+        })
+        .await;
+
+    match result {
+        Ok(pickup) => {
+            backup_db(state);
+            publish_current(state, events::EventKind::Changed, id).await;
+            Json(pickup).into_response()
+        }
+        Err(e) => {
+            error!("Failed to record pickup: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// When the next pickup reminders go out, each morning
+fn next_nag(now: NaiveDateTime) -> NaiveDateTime {
+    let nag = now.date().and_time(NaiveTime::from_hms_opt(10, 0, 0).unwrap());
+    if nag > now {
+        nag
+    } else {
+        nag + Days::new(1)
+    }
+}
+
+/// Reminds requesters of their orders that have sat in storage for at least
+/// `days` without being picked up. Each is sent a digest of theirs directly,
+/// and the rest are listed in one message to the order updates webhook,
+/// mentioning their requesters.
+async fn nag(state: &'static UsrState, days: u32) -> anyhow::Result<()> {
+    let db = &state.db;
+    let cutoff = Local::now().naive_local() - Days::new(days.into());
+    let picked_up: HashSet<_> = Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| model.order_id)
+        .collect();
+    let unclaimed = current::Entity::find()
+        .filter(current::Column::Status.eq(order_status::Status::InStorage))
+        .filter(current::Column::StatusDate.lte(cutoff))
+        .order_by_asc(current::Column::StatusDate)
+        .all(db)
+        .await?;
+
+    let mut by_requester = BTreeMap::<Option<String>, Vec<String>>::new();
+    for model in unclaimed {
+        if picked_up.contains(&model.id) {
+            continue;
+        }
+        let since = model.status_date.date();
+        let (order, _) = model.into_parts();
+        let mut line = format!("\n- **{}** {}", order.number(), order.name);
+        if order.store_in.is_empty() {
+            line.push_str(&format!(" ({since})"));
+        } else {
+            line.push_str(&format!(" ({}, {since})", order.store_in));
+        }
+        if let Some(url) = permalink::url(&order) {
+            line.push_str(&format!(" <{url}>"));
+        }
+        by_requester.entry(order.requester).or_default().push(line);
+    }
+    if by_requester.is_empty() {
+        return Ok(());
+    }
+
+    let locales = dm::locales(db).await?;
+    let mut leftover = String::new();
+    for (requester, lines) in by_requester {
+        let Some(requester) = requester else {
+            leftover.extend(lines);
+            continue;
+        };
+        if dm::enabled(state) {
+            let locale = locales.get(&requester).copied().unwrap_or_default();
+            let content = format!("{}{}", locale.pickup_digest_heading(), lines.concat());
+            match dm::send_dm(state, &requester, &content).await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => warn!("Failed to DM pickup reminder to {requester}: {e}"),
+            }
+        }
+        let mention = match dm::discord_id(db, &requester).await? {
+            Some(discord_id) => format!("<@{discord_id}>"),
+            None => requester,
+        };
+        for line in lines {
+            leftover.push_str(&format!("{line} {mention}"));
+        }
+    }
+    if !leftover.is_empty() {
+        state.notifier.send(
+            Topic::OrderUpdate,
+            u32::MAX / 16 * 5,
+            format!("**Waiting for Pickup**{leftover}"),
+        );
+    }
+    Ok(())
+}
+
+/// Each morning, reminds requesters of orders left in storage for longer
+/// than `pickup_reminder_days`
+pub fn spawn(state: &'static UsrState) {
+    let Some(days) = state.pickup_reminder_days else {
+        return;
+    };
+    if !dm::enabled(state) && !state.notifier.routes(Topic::OrderUpdate) {
+        return;
+    }
+    jobs::spawn(
+        state,
+        "pickup_reminders",
+        Schedule::At(|_, now| next_nag(now)),
+        Retry::times(2, Duration::from_secs(10 * 60)),
+        move |state| async move { nag(state, days).await },
+    );
+}
+
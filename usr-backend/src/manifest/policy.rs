@@ -0,0 +1,82 @@
+use super::{order, order_status::Status};
+use axum::http::StatusCode;
+
+pub use crate::auth::{Caller, Role};
+
+/// The least role that can change `field`
+fn required_role(field: &str) -> Role {
+    match field {
+        "name" | "link" | "reason" | "fields" | "needed_by" => Role::Member,
+        "unit_cost" | "count" | "team" | "splits" => Role::Lead,
+        _ => Role::Admin,
+    }
+}
+
+/// Whether `field` can still be changed on an order whose latest status is
+/// `status`. What was bought, from where and by whom is settled once the
+/// order is submitted, but how it is described, where it goes and how it
+/// was paid for, or by whom, are not. Nothing changes on a cancelled order
+/// until it is restored.
+fn editable_in(field: &str, status: Status) -> bool {
+    match field {
+        _ if status == Status::Cancelled => false,
+        "name" | "reason" | "store_in" | "component_id" | "tax_exempt" | "payment_method"
+        | "splits" | "fields" | "needed_by" => true,
+        _ => status == Status::New,
+    }
+}
+
+/// Why `role` cannot change `field` on an order in `status`, if it can't
+pub fn check_field(role: Role, field: &str, status: Status) -> Result<(), (StatusCode, String)> {
+    if role < required_role(field) {
+        Err((
+            StatusCode::FORBIDDEN,
+            format!("{role} cannot change {field}"),
+        ))
+    } else if !editable_in(field, status) {
+        Err((
+            StatusCode::BAD_REQUEST,
+            format!("{field} cannot be changed once an order is {status}"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Why `caller` cannot change `order`, if it can't. Members can only change
+/// the orders they requested.
+pub fn check_owner(caller: &Caller, order: &order::Model) -> Result<(), (StatusCode, String)> {
+    if caller.role >= Role::Lead
+        || caller.name.is_some() && caller.name == order.requester
+    {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::FORBIDDEN,
+            format!("{} can only change their own orders", caller.role),
+        ))
+    }
+}
+
+/// Why an edit made to version `expected` of `order` can't be applied, if it
+/// can't. Edits that don't say which version they were made to always can.
+pub fn check_version(
+    expected: Option<u32>,
+    order: &order::Model,
+) -> Result<(), (StatusCode, String)> {
+    match expected {
+        Some(expected) if i64::from(expected) != order.version => {
+            Err((StatusCode::CONFLICT, stale_version(order)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The message for an edit that was made to an older version of `order`
+pub fn stale_version(order: &order::Model) -> String {
+    format!(
+        "Order {} was changed by someone else and is now at version {}",
+        order.number(),
+        order.version
+    )
+}
@@ -0,0 +1,165 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use reqwest::{redirect, Url};
+use sea_orm::prelude::Decimal;
+
+/// Product pages that take longer than this are treated as having no price
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of a product page is read. Stores put the price in the page's
+/// head, well before this.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// How many redirects are followed, eg. from http to https
+const MAX_REDIRECTS: usize = 5;
+
+/// Markers that precede a price in the structured data most stores embed for
+/// search engines, most reliable first. The price is the first number after
+/// the marker.
+const MARKERS: [&str; 4] = [
+    "\"product:price:amount\"",
+    "itemprop=\"price\"",
+    "\"price\":",
+    "\"price\" :",
+];
+
+/// Pulls the first number out of `text`, skipping quotes and `content=`
+/// attributes in between. Gives up if anything else comes first.
+fn leading_number(text: &str) -> Option<Decimal> {
+    let text = text.trim_start_matches(|c: char| {
+        c.is_whitespace() || c == '"' || c == '\'' || c == '=' || c == '$'
+    });
+    let text = text
+        .strip_prefix("content=")
+        .map(|rest| rest.trim_start_matches(['"', '\'', '$']))
+        .unwrap_or(text);
+    let end = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.' && c != ',')
+        .unwrap_or(text.len());
+    text[..end].replace(',', "").parse().ok()
+}
+
+/// Finds the advertised price in a product page
+fn find_price(html: &str) -> Option<Decimal> {
+    MARKERS.iter().find_map(|marker| {
+        html.match_indices(marker)
+            .find_map(|(i, _)| leading_number(&html[i + marker.len()..]))
+            .filter(|price| *price > Decimal::ZERO)
+    })
+}
+
+/// Whether `ip` is on the public internet, rather than the server's own
+/// network, which links could otherwise be used to reach
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && b & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    // Unique local, fc00::/7
+                    || first & 0xfe00 == 0xfc00
+                    // Link local, fe80::/10
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Where `url` can be fetched from, as long as it is an http(s) link to a
+/// public address
+async fn public_addr(url: &Url) -> Option<SocketAddr> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = url.host_str()?;
+    let port = url.port_or_known_default()?;
+    let addrs: Vec<_> = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .ok()?
+        .collect();
+    // Every address has to be public, since any of them could be connected to
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_public(addr.ip())) {
+        return None;
+    }
+    addrs.into_iter().next()
+}
+
+/// Fetches `link` and looks for the current price of the product on it.
+/// `None` when the link is missing, unreachable, doesn't advertise a price,
+/// or leads anywhere but the public internet.
+pub async fn lookup(link: &str) -> Option<Decimal> {
+    tokio::time::timeout(TIMEOUT, fetch(link)).await.ok().flatten()
+}
+
+async fn fetch(link: &str) -> Option<Decimal> {
+    let mut url = Url::parse(link).ok()?;
+    for _ in 0..=MAX_REDIRECTS {
+        let addr = public_addr(&url).await?;
+        // Connects to the address that was checked, so that the name can't
+        // be resolved again to somewhere else. Redirects are followed here so
+        // that each one is checked too.
+        let client = reqwest::Client::builder()
+            .timeout(TIMEOUT)
+            .redirect(redirect::Policy::none())
+            .resolve(url.host_str()?, addr)
+            .build()
+            .ok()?;
+        let mut response = client.get(url.clone()).send().await.ok()?;
+        if response.status().is_redirection() {
+            let location = response.headers().get(reqwest::header::LOCATION)?;
+            url = url.join(location.to_str().ok()?).ok()?;
+            continue;
+        }
+        if !response.status().is_success() {
+            return None;
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.ok()? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_BODY {
+                body.truncate(MAX_BODY);
+                break;
+            }
+        }
+        return find_price(&String::from_utf8_lossy(&body));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn private_links_are_not_fetched() {
+        for link in [
+            "file:///etc/passwd",
+            "http://127.0.0.1:3000/api/admin/backup/list",
+            "http://localhost/",
+            "http://10.0.0.1/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]/",
+            "http://[::ffff:192.168.1.1]/",
+        ] {
+            assert!(public_addr(&Url::parse(link).unwrap()).await.is_none(), "{link}");
+        }
+        assert!(is_public("93.184.215.14".parse().unwrap()));
+    }
+}
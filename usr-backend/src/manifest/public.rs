@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{Datelike, Local};
+use sea_orm::{prelude::Decimal, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{api_error, scheduler::Team, UsrState};
+
+use super::{budget, current, funding, order_status};
+
+/// An order as the public sees it, without reasons, requesters or prices
+#[derive(Serialize)]
+struct PublicOrder {
+    name: String,
+    vendor: String,
+    count: i64,
+    team: Team,
+    funding_source: funding::Source,
+    status: order_status::Status,
+}
+
+/// Spending rounded to the dollar, which is as precise as sponsors need
+#[derive(Serialize, Default)]
+struct PublicTotals {
+    total: Decimal,
+    by_team: BTreeMap<String, Decimal>,
+    by_funding_source: BTreeMap<String, Decimal>,
+}
+
+#[derive(Serialize)]
+struct PublicManifest {
+    season: u16,
+    orders: Vec<PublicOrder>,
+    totals: PublicTotals,
+}
+
+#[derive(Deserialize)]
+struct PublicQuery {
+    /// Defaults to the current season
+    #[serde(default)]
+    season: Option<u16>,
+}
+
+/// What a season's orders bought, for the team's website to show sponsors.
+/// Only orders that were actually purchased are included.
+#[axum::debug_handler]
+async fn get_public_manifest(
+    State(state): State<&'static UsrState>,
+    Query(PublicQuery { season }): Query<PublicQuery>,
+) -> Response {
+    let season = season.unwrap_or(Local::now().year() as u16);
+    let (orders, splits) = tokio::join!(
+        current::Entity::find()
+            .filter(current::Column::Season.eq(season))
+            .filter(current::Column::Status.is_not_in([
+                order_status::Status::New,
+                order_status::Status::OnHold,
+                order_status::Status::Cancelled,
+            ]))
+            .order_by_asc(current::Column::Id)
+            .all(&state.db),
+        budget::all_splits(&state.db),
+    );
+    let (orders, splits) = match (orders, splits) {
+        (Ok(orders), Ok(splits)) => (orders, splits),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get public manifest: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let mut totals = PublicTotals::default();
+    let mut public_orders = Vec::with_capacity(orders.len());
+    for current in orders {
+        let (order, status) = current.into_parts();
+        let splits = splits.get(&order.id).map(Vec::as_slice).unwrap_or_default();
+        for share in budget::shares(&order, splits) {
+            totals.total += share.amount;
+            *totals.by_team.entry(share.team.to_string()).or_default() += share.amount;
+            *totals
+                .by_funding_source
+                .entry(share.funding_source.to_string())
+                .or_default() += share.amount;
+        }
+        public_orders.push(PublicOrder {
+            name: order.name,
+            vendor: order.vendor,
+            count: order.count,
+            team: order.team,
+            funding_source: order.funding_source,
+            status,
+        });
+    }
+    // Rounded after summing so that the parts still add up to about the total
+    totals.total = totals.total.round();
+    for amount in totals
+        .by_team
+        .values_mut()
+        .chain(totals.by_funding_source.values_mut())
+    {
+        *amount = amount.round();
+    }
+
+    Json(PublicManifest {
+        season,
+        orders: public_orders,
+        totals,
+    })
+    .into_response()
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/manifest", get(get_public_manifest))
+        .layer(axum::middleware::from_fn(api_error::json_errors))
+}
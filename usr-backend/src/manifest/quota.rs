@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use sea_orm::{entity::prelude::*, sea_query::OnConflict, ActiveValue, QueryOrder};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{backup::backup_db, money, UsrState};
+
+use super::{budget, order, placed_date, policy, service::OrderError, PendingOrder};
+
+/// The most a member can order in a calendar month. Either limit can be left
+/// out to not limit by it.
+#[derive(Deserialize, Serialize, Clone, Copy, Default, Debug)]
+pub struct Quota {
+    #[serde(default)]
+    pub orders: Option<i64>,
+    #[serde(default)]
+    pub dollars: Option<Decimal>,
+}
+
+impl Quota {
+    pub fn validate(&self, key: &str, problems: &mut Vec<String>) {
+        if self.orders.is_some_and(|orders| orders < 0) {
+            problems.push(format!("{key}.orders: cannot be negative"));
+        }
+        if self
+            .dollars
+            .is_some_and(|dollars| dollars.is_sign_negative())
+        {
+            problems.push(format!("{key}.dollars: cannot be negative"));
+        }
+    }
+}
+
+/// A member whose quota a lead has set in place of the configured one, eg.
+/// to let the member ordering a drivetrain's parts order all of them
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "member_quotas")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub member: String,
+    #[sea_orm(nullable)]
+    pub orders: Option<i64>,
+    #[sea_orm(nullable)]
+    pub dollars: Option<Decimal>,
+    pub set_by: String,
+    pub updated: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// The start of the month `now` is in
+fn month_start(now: NaiveDateTime) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(now.year(), now.month(), 1)
+        .unwrap_or(now.date())
+        .and_time(NaiveTime::MIN)
+}
+
+/// How many orders each member requested this month, and what they came to,
+/// leaving out those cancelled before they were bought
+async fn usage(
+    db: &impl ConnectionTrait,
+    member: Option<&str>,
+) -> Result<BTreeMap<String, (i64, Decimal)>, DbErr> {
+    let mut query = order::Entity::find()
+        .filter(order::Column::Requester.is_not_null())
+        .filter(Expr::expr(placed_date()).gte(month_start(Local::now().naive_local())));
+    if let Some(member) = member {
+        query = query.filter(order::Column::Requester.eq(member));
+    }
+    let unspent = budget::unspent_cancelled(db).await?;
+    let mut usage = BTreeMap::<String, (i64, Decimal)>::new();
+    for order in query.all(db).await? {
+        if unspent.contains(&order.id) {
+            continue;
+        }
+        let Some(requester) = order.requester else {
+            continue;
+        };
+        let used = usage.entry(requester).or_default();
+        used.0 += 1;
+        used.1 += money::subtotal(order.count, order.unit_cost);
+    }
+    Ok(usage)
+}
+
+/// Turns away orders that would take the signed in member over their quota
+/// this month, or orders no one is signed in for while quotas are on. `db` is
+/// the transaction placing the orders, so that orders placed at the same time
+/// can't each fit in what is left. Leads aren't limited, including in the
+/// orders they place on a member's behalf.
+pub async fn check(
+    db: &impl ConnectionTrait,
+    state: &UsrState,
+    caller: &policy::Caller,
+    pending_orders: &[PendingOrder],
+) -> Result<Result<(), OrderError>, DbErr> {
+    if caller.role >= policy::Role::Lead {
+        return Ok(Ok(()));
+    }
+    let Some(member) = caller.name.as_deref() else {
+        let quotas_on = state.member_quota.orders.is_some()
+            || state.member_quota.dollars.is_some()
+            || Entity::find().one(db).await?.is_some();
+        if quotas_on {
+            return Ok(Err(OrderError::Forbidden(
+                "Orders count against their requester's quota, so sign in to place them"
+                    .to_string(),
+            )));
+        }
+        return Ok(Ok(()));
+    };
+    let quota = match Entity::find_by_id(member).one(db).await? {
+        Some(model) => Quota {
+            orders: model.orders,
+            dollars: model.dollars,
+        },
+        None => state.member_quota,
+    };
+    if quota.orders.is_none() && quota.dollars.is_none() {
+        return Ok(Ok(()));
+    }
+    let orders = pending_orders.len() as i64;
+    let dollars: Decimal = pending_orders.iter().map(PendingOrder::subtotal).sum();
+    let (used_orders, used_dollars) = usage(db, Some(member))
+        .await?
+        .remove(member)
+        .unwrap_or_default();
+    if let Some(limit) = quota.orders {
+        if used_orders + orders > limit {
+            return Ok(Err(OrderError::Forbidden(format!(
+                "{member} has placed {used_orders} of their {limit} orders this month, so a lead has to place this"
+            ))));
+        }
+    }
+    if let Some(limit) = quota.dollars {
+        if used_dollars + dollars > limit {
+            return Ok(Err(OrderError::Forbidden(format!(
+                "{member} has ordered {} of their {} this month, so a lead has to place this",
+                money::dollars(used_dollars),
+                money::dollars(limit)
+            ))));
+        }
+    }
+    Ok(Ok(()))
+}
+
+#[derive(Deserialize)]
+pub struct SetQuota {
+    member: String,
+    #[serde(default)]
+    orders: Option<u32>,
+    #[serde(default)]
+    dollars: Option<Decimal>,
+}
+
+/// Gives a member a quota of their own in place of the configured one.
+/// Leaving out both limits lets them order without one.
+#[axum::debug_handler]
+pub async fn set_quota(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(set_quota): Json<SetQuota>,
+) -> (StatusCode, &'static str) {
+    let member = set_quota.member.trim().to_string();
+    if member.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Member is required");
+    }
+    if set_quota
+        .dollars
+        .is_some_and(|dollars| dollars.is_sign_negative())
+    {
+        return (StatusCode::BAD_REQUEST, "Amounts cannot be negative");
+    }
+    let result = Entity::insert(ActiveModel {
+        member: ActiveValue::Set(member),
+        orders: ActiveValue::Set(set_quota.orders.map(i64::from)),
+        dollars: ActiveValue::Set(set_quota.dollars.map(money::round)),
+        set_by: ActiveValue::Set(
+            caller
+                .name
+                .clone()
+                .unwrap_or_else(|| caller.role.to_string()),
+        ),
+        updated: ActiveValue::Set(Local::now().naive_local()),
+    })
+    .on_conflict(
+        OnConflict::column(Column::Member)
+            .update_columns([
+                Column::Orders,
+                Column::Dollars,
+                Column::SetBy,
+                Column::Updated,
+            ])
+            .to_owned(),
+    )
+    .exec(&state.db)
+    .await;
+    if let Err(e) = result {
+        error!("Failed to set member quota: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "")
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "")
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteQuota {
+    member: String,
+}
+
+/// Puts a member back on the configured quota
+#[axum::debug_handler]
+pub async fn del_quota(
+    State(state): State<&'static UsrState>,
+    Json(DeleteQuota { member }): Json<DeleteQuota>,
+) -> (StatusCode, &'static str) {
+    match Entity::delete_by_id(member.trim()).exec(&state.db).await {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Member has no quota of their own")
+        }
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "")
+        }
+        Err(e) => {
+            error!("Failed to delete member quota: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct MemberUsage {
+    member: String,
+    orders: i64,
+    dollars: Decimal,
+    quota: Quota,
+}
+
+#[derive(Serialize)]
+struct Quotas {
+    /// The configured quota, for members without one of their own
+    default: Quota,
+    overrides: Vec<Model>,
+    /// What each member who has requested orders this month has used
+    usage: Vec<MemberUsage>,
+}
+
+#[axum::debug_handler]
+pub async fn get_quotas(State(state): State<&'static UsrState>) -> Response {
+    let (overrides, usage) = tokio::join!(
+        Entity::find().order_by_asc(Column::Member).all(&state.db),
+        usage(&state.db, None),
+    );
+    let (overrides, usage) = match (overrides, usage) {
+        (Ok(overrides), Ok(usage)) => (overrides, usage),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get member quotas: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let usage = usage
+        .into_iter()
+        .map(|(member, (orders, dollars))| {
+            let quota = overrides
+                .iter()
+                .find(|model| model.member == member)
+                .map_or(state.member_quota, |model| Quota {
+                    orders: model.orders,
+                    dollars: model.dollars,
+                });
+            MemberUsage {
+                member,
+                orders,
+                dollars,
+                quota,
+            }
+        })
+        .collect();
+    Json(Quotas {
+        default: state.member_quota,
+        overrides,
+        usage,
+    })
+    .into_response()
+}
@@ -0,0 +1,313 @@
+use std::{collections::HashMap, time::Duration};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Days, Local, NaiveDate};
+use sea_orm::{entity::prelude::*, ActiveValue, QueryOrder};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    backup::backup_db,
+    jobs::{self, Retry, Schedule},
+    money,
+    notify::Topic,
+    scheduler, UsrState,
+};
+
+use super::{
+    audit, funding, non_blank, order, policy,
+    service::{self, OrderError},
+    PendingOrder,
+};
+
+/// Something bought again and again, eg. zip ties or filament, placed as a
+/// new order every `interval_days` for a lead to approve like any other
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "recurring_orders")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    pub count: i64,
+    pub unit_cost: Decimal,
+    pub store_in: String,
+    pub team: scheduler::Team,
+    pub reason: String,
+    pub vendor: String,
+    pub link: String,
+    pub funding_source: funding::Source,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requester: Option<String>,
+    pub interval_days: i64,
+    /// The day the next order is placed on
+    pub next_due: Date,
+    /// The last order placed from this, if any has been
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_order_id: Option<i64>,
+    pub created_by: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    fn pending_order(&self) -> PendingOrder {
+        PendingOrder {
+            name: self.name.clone(),
+            count: self.count as u32,
+            unit_cost: self.unit_cost,
+            store_in: self.store_in.clone(),
+            team: self.team,
+            reason: self.reason.clone(),
+            vendor: self.vendor.clone(),
+            link: self.link.clone(),
+            funding_source: self.funding_source,
+            component_id: None,
+            requester: self.requester.clone(),
+            currency: order::Currency::Usd,
+            exchange_rate: None,
+            fields: HashMap::new(),
+            needed_by: None,
+            // It was ordered last time around, on purpose
+            allow_duplicate: true,
+            shipping_cost: None,
+            tax: None,
+            fees: None,
+        }
+    }
+
+    /// The first day after `today` that an order is due, skipping any that
+    /// were missed while the server was down
+    fn due_after(&self, today: NaiveDate) -> NaiveDate {
+        let mut next_due = self.next_due;
+        while next_due <= today {
+            next_due = next_due + Days::new(self.interval_days.unsigned_abs());
+        }
+        next_due
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetRecurring {
+    /// The recurring order to change, or a new one if left out
+    #[serde(default)]
+    id: Option<u32>,
+    name: String,
+    count: u32,
+    unit_cost: Decimal,
+    store_in: String,
+    team: scheduler::Team,
+    reason: String,
+    vendor: String,
+    link: String,
+    #[serde(default)]
+    funding_source: funding::Source,
+    #[serde(default)]
+    requester: Option<String>,
+    interval_days: u32,
+    /// Today if left out
+    #[serde(default)]
+    next_due: Option<NaiveDate>,
+}
+
+/// Sets up an order to be placed on a schedule, or changes one. Only leads
+/// can, since the orders it places go out under their name.
+#[axum::debug_handler]
+pub async fn set_recurring(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(set): Json<SetRecurring>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot set up recurring orders", caller.role),
+        )
+            .into_response();
+    }
+    let name = set.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "A name is required").into_response();
+    }
+    if set.count == 0 {
+        return (StatusCode::BAD_REQUEST, "Count must be positive").into_response();
+    }
+    if let Err(msg) = money::validate_unit_cost(set.unit_cost) {
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+    if set.interval_days == 0 {
+        return (StatusCode::BAD_REQUEST, "Interval must be at least a day").into_response();
+    }
+
+    let mut active_model = ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(name),
+        count: ActiveValue::Set(set.count.into()),
+        unit_cost: ActiveValue::Set(set.unit_cost),
+        store_in: ActiveValue::Set(set.store_in),
+        team: ActiveValue::Set(set.team),
+        reason: ActiveValue::Set(set.reason),
+        vendor: ActiveValue::Set(set.vendor),
+        link: ActiveValue::Set(set.link),
+        funding_source: ActiveValue::Set(set.funding_source),
+        requester: ActiveValue::Set(non_blank(set.requester)),
+        interval_days: ActiveValue::Set(set.interval_days.into()),
+        next_due: ActiveValue::Set(set.next_due.unwrap_or_else(|| Local::now().date_naive())),
+        last_order_id: ActiveValue::NotSet,
+        created_by: ActiveValue::NotSet,
+    };
+    let result = match set.id {
+        Some(id) => match Entity::find_by_id(id).one(&state.db).await {
+            Ok(Some(_)) => {
+                active_model.id = ActiveValue::Unchanged(id.into());
+                active_model.update(&state.db).await
+            }
+            Ok(None) => {
+                return (StatusCode::BAD_REQUEST, "Recurring order not found").into_response()
+            }
+            Err(e) => Err(e),
+        },
+        None => {
+            active_model.created_by = ActiveValue::Set(
+                caller
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| caller.role.to_string()),
+            );
+            active_model.insert(&state.db).await
+        }
+    };
+
+    match result {
+        Ok(model) => {
+            backup_db(state);
+            Json(model).into_response()
+        }
+        Err(e) => {
+            error!("Failed to set recurring order: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteRecurring {
+    id: u32,
+}
+
+/// Stops placing a recurring order. The orders it already placed stay.
+#[axum::debug_handler]
+pub async fn del_recurring(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(DeleteRecurring { id }): Json<DeleteRecurring>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot delete recurring orders", caller.role),
+        )
+            .into_response();
+    }
+    match Entity::delete_by_id(id).exec(&state.db).await {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Recurring order not found").into_response()
+        }
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete recurring order: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Every recurring order, soonest due first
+#[axum::debug_handler]
+pub async fn get_recurring(State(state): State<&'static UsrState>) -> Response {
+    match Entity::find()
+        .order_by_asc(Column::NextDue)
+        .order_by_asc(Column::Id)
+        .all(&state.db)
+        .await
+    {
+        Ok(models) => Json(models).into_response(),
+        Err(e) => {
+            error!("Failed to get recurring orders: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Places an order for each recurring order that has come due. They are
+/// announced to the new orders webhook as they are placed, and wait for a
+/// lead's approval like any other. Ones that can't be placed, eg. during a
+/// spending freeze, are announced as skipped until the next time around.
+async fn place_due(state: &'static UsrState) -> anyhow::Result<()> {
+    let db = &state.db;
+    let today = Local::now().date_naive();
+    let due = Entity::find()
+        .filter(Column::NextDue.lte(today))
+        .order_by_asc(Column::Id)
+        .all(db)
+        .await?;
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    for model in due {
+        let next_due = model.due_after(today);
+        let actor = audit::Actor::system(&model.created_by, "/recurring");
+        let placed = service::place_order(state, model.pending_order(), None, None, actor).await;
+        let last_order_id = match placed {
+            Ok(order) => Some(order.id),
+            Err(OrderError::Internal) => {
+                anyhow::bail!("Failed to place recurring order {}", model.id)
+            }
+            Err(e) => {
+                state.notifier.send_for(
+                        model.team,
+                        Topic::NewOrder,
+                        u32::MAX / 16 * 7 + model.id as u32,
+                        format!(
+                            "**Recurring Order Skipped**\n{} x {} for {} couldn't be placed: {e}\n**Next Due:** {next_due}",
+                            model.count, model.name, model.team
+                        ),
+                    );
+                model.last_order_id
+            }
+        };
+        ActiveModel {
+            id: ActiveValue::Unchanged(model.id),
+            next_due: ActiveValue::Set(next_due),
+            last_order_id: ActiveValue::Set(last_order_id),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+    }
+    backup_db(state);
+    Ok(())
+}
+
+/// Hourly, places the recurring orders that have come due
+pub fn spawn(state: &'static UsrState) {
+    jobs::spawn(
+        state,
+        "recurring_orders",
+        Schedule::Every(Duration::from_secs(60 * 60)),
+        Retry::times(2, Duration::from_secs(10 * 60)),
+        place_due,
+    );
+}
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::NaiveDate;
+use sea_orm::{entity::prelude::*, ActiveValue, QueryOrder};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{backup::backup_db, UsrState};
+
+use super::{non_blank, order, policy, resolve_order, OrderRef};
+
+/// Who paid for an order out of pocket or on a card, and how far along
+/// paying them back is. Kept next to the orders so the treasurer doesn't
+/// need a spreadsheet of who is owed what.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "reimbursements")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Each order is paid back at most once
+    pub order_id: i64,
+    pub payer: String,
+    pub card: Card,
+    /// What the payer is owed, usually the order's total
+    pub amount: Decimal,
+    /// When the paperwork went to the university
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitted: Option<Date>,
+    /// The university's ticket number for the paperwork
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ticket: Option<String>,
+    /// When the payer got their money back
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repaid: Option<Date>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// What the order was paid with
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Card {
+    /// The payer's own card, so they are owed the amount
+    #[sea_orm(string_value = "P")]
+    Personal,
+    /// A team card in the payer's keeping, which only needs reconciling
+    #[sea_orm(string_value = "T")]
+    Team,
+}
+
+#[derive(Deserialize)]
+pub struct SetReimbursement {
+    /// The reimbursement to change, or a new one if left out
+    #[serde(default)]
+    id: Option<u32>,
+    order_id: OrderRef,
+    payer: String,
+    card: Card,
+    /// Left out, the order's total for a new reimbursement, or unchanged
+    #[serde(default)]
+    amount: Option<Decimal>,
+    #[serde(default)]
+    submitted: Option<NaiveDate>,
+    #[serde(default)]
+    ticket: Option<String>,
+    #[serde(default)]
+    repaid: Option<NaiveDate>,
+}
+
+/// Records who paid for an order, or updates how paying them back is going.
+/// Only leads can, as treasurers are.
+#[axum::debug_handler]
+pub async fn set_reimbursement(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(set): Json<SetReimbursement>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot record reimbursements", caller.role),
+        )
+            .into_response();
+    }
+    let payer = set.payer.trim().to_string();
+    if payer.is_empty() {
+        return (StatusCode::BAD_REQUEST, "A payer is required").into_response();
+    }
+    if set.amount.is_some_and(|amount| amount.is_sign_negative()) {
+        return (StatusCode::BAD_REQUEST, "Amount must not be negative").into_response();
+    }
+    if let (Some(submitted), Some(repaid)) = (set.submitted, set.repaid) {
+        if repaid < submitted {
+            return (StatusCode::BAD_REQUEST, "Repaid before it was submitted").into_response();
+        }
+    }
+    let order_id = match resolve_order(&state.db, &set.order_id).await {
+        Ok(id) => id,
+        Err(response) => return response.into_response(),
+    };
+    let order = match order::Entity::find_by_id(order_id).one(&state.db).await {
+        Ok(Some(order)) => order,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Order not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let existing = match Entity::find()
+        .filter(Column::OrderId.eq(order_id))
+        .one(&state.db)
+        .await
+    {
+        Ok(existing) => existing,
+        Err(e) => {
+            error!("Failed to find reimbursement: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if let Some(existing) = &existing {
+        if set.id.map(i64::from) != Some(existing.id) {
+            return (
+                StatusCode::CONFLICT,
+                format!(
+                    "{} is already being paid back to {}, change that reimbursement instead",
+                    order.number(),
+                    existing.payer
+                ),
+            )
+                .into_response();
+        }
+    }
+
+    let mut active_model = ActiveModel {
+        id: ActiveValue::NotSet,
+        order_id: ActiveValue::Set(order_id),
+        payer: ActiveValue::Set(payer),
+        card: ActiveValue::Set(set.card),
+        amount: match (set.amount, set.id) {
+            (Some(amount), _) => ActiveValue::Set(amount),
+            (None, Some(_)) => ActiveValue::NotSet,
+            (None, None) => ActiveValue::Set(order.total()),
+        },
+        submitted: ActiveValue::Set(set.submitted),
+        ticket: ActiveValue::Set(non_blank(set.ticket)),
+        repaid: ActiveValue::Set(set.repaid),
+    };
+    let result = match set.id {
+        Some(id) => match Entity::find_by_id(id).one(&state.db).await {
+            Ok(Some(_)) => {
+                active_model.id = ActiveValue::Unchanged(id.into());
+                active_model.update(&state.db).await
+            }
+            Ok(None) => {
+                return (StatusCode::BAD_REQUEST, "Reimbursement not found").into_response()
+            }
+            Err(e) => Err(e),
+        },
+        None => active_model.insert(&state.db).await,
+    };
+
+    match result {
+        Ok(model) => {
+            backup_db(state);
+            Json(model).into_response()
+        }
+        Err(e) => {
+            error!("Failed to set reimbursement: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteReimbursement {
+    id: u32,
+}
+
+#[axum::debug_handler]
+pub async fn del_reimbursement(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(DeleteReimbursement { id }): Json<DeleteReimbursement>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot delete reimbursements", caller.role),
+        )
+            .into_response();
+    }
+    match Entity::delete_by_id(id).exec(&state.db).await {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Reimbursement not found").into_response()
+        }
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete reimbursement: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListReimbursements {
+    /// Only those that haven't been repaid yet
+    #[serde(default)]
+    outstanding: bool,
+    #[serde(default)]
+    payer: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ListedReimbursement {
+    #[serde(flatten)]
+    reimbursement: Model,
+    /// The order's display number, eg. USR-2025-0042
+    number: String,
+    name: String,
+}
+
+#[derive(Serialize)]
+struct Reimbursements {
+    reimbursements: Vec<ListedReimbursement>,
+    /// What each payer who paid with their own card is still owed
+    owed: HashMap<String, Decimal>,
+}
+
+/// Reimbursements, oldest order first
+#[axum::debug_handler]
+pub async fn get_reimbursements(
+    State(state): State<&'static UsrState>,
+    Query(ListReimbursements { outstanding, payer }): Query<ListReimbursements>,
+) -> Response {
+    let mut query = Entity::find();
+    if outstanding {
+        query = query.filter(Column::Repaid.is_null());
+    }
+    if let Some(payer) = payer {
+        query = query.filter(Column::Payer.eq(payer.trim()));
+    }
+    let result = async {
+        let models = query.order_by_asc(Column::OrderId).all(&state.db).await?;
+        let orders: HashMap<_, _> = order::Entity::find()
+            .filter(order::Column::Id.is_in(models.iter().map(|model| model.order_id)))
+            .all(&state.db)
+            .await?
+            .into_iter()
+            .map(|order| (order.id, order))
+            .collect();
+        Result::<_, DbErr>::Ok((models, orders))
+    }
+    .await;
+
+    match result {
+        Ok((models, mut orders)) => {
+            let mut owed = HashMap::<String, Decimal>::new();
+            let reimbursements = models
+                .into_iter()
+                .filter_map(|reimbursement| {
+                    if reimbursement.card == Card::Personal && reimbursement.repaid.is_none() {
+                        *owed.entry(reimbursement.payer.clone()).or_default() +=
+                            reimbursement.amount;
+                    }
+                    let order = orders.remove(&reimbursement.order_id)?;
+                    Some(ListedReimbursement {
+                        number: order.number(),
+                        name: order.name,
+                        reimbursement,
+                    })
+                })
+                .collect();
+            Json(Reimbursements {
+                reimbursements,
+                owed,
+            })
+            .into_response()
+        }
+        Err(e) => {
+            error!("Failed to get reimbursements: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
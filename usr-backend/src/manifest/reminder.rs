@@ -0,0 +1,21 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// A step of the approval escalation chain that has been posted for an order,
+/// so that each step is only posted once.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "order_reminders")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub order_id: i64,
+    /// Index into the configured escalation chain
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub step: i64,
+    pub sent: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
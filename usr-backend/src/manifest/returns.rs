@@ -0,0 +1,150 @@
+use std::{collections::HashSet, time::Duration};
+
+use chrono::{Days, Local, NaiveDate};
+use sea_orm::{entity::prelude::*, ActiveValue, Condition, DatabaseConnection, QueryOrder};
+
+use crate::{
+    jobs::{self, Retry, Schedule},
+    notify::Topic,
+    UsrState,
+};
+
+use super::{
+    current,
+    deadline::{self, Stage},
+    order, order_status, permalink, vendor,
+};
+
+/// How many days before an order's return window closes the order updates
+/// webhook is reminded to get it back to the vendor
+const RETURN_WARNING_DAYS: u64 = 3;
+
+/// The last day `order` can be returned to its vendor, if the vendor takes
+/// returns for a known number of days after delivery and it was delivered
+async fn return_by(
+    db: &impl ConnectionTrait,
+    order: &order::Model,
+    vendor: &vendor::Model,
+) -> Result<Option<NaiveDate>, DbErr> {
+    let Some(days) = vendor.return_window_days else {
+        return Ok(None);
+    };
+    // Orders unpacked straight into storage were delivered then
+    let delivered = order_status::Entity::find()
+        .filter(order_status::Column::OrderId.eq(order.id))
+        .filter(
+            Condition::any()
+                .add(order_status::Column::Status.eq(order_status::Status::Delivered))
+                .add(order_status::Column::Status.eq(order_status::Status::InStorage)),
+        )
+        .order_by_asc(order_status::Column::InstanceId)
+        .one(db)
+        .await?;
+    Ok(delivered.map(|model| model.date.date() + Days::new(days.unsigned_abs())))
+}
+
+/// What the webhook message announcing that `order` is being returned adds:
+/// how to reach its vendor for a return authorization, what it wants done
+/// and until when
+pub async fn rma_lines(db: &DatabaseConnection, order: &order::Model) -> Result<String, DbErr> {
+    let Some(vendor) = vendor::find(db, &order.vendor).await? else {
+        return Ok(String::new());
+    };
+    let mut lines = String::new();
+    if let Some(contact) = &vendor.rma_contact {
+        lines.push_str(&format!("\n**RMA Contact:** {contact}"));
+    }
+    if let Some(instructions) = &vendor.rma_instructions {
+        lines.push_str(&format!("\n**RMA Instructions:** {instructions}"));
+    }
+    if let Some(date) = return_by(db, order, &vendor).await? {
+        lines.push_str(&format!("\n**Return By:** {date}"));
+    }
+    Ok(lines)
+}
+
+/// Posts a reminder for each returned order whose vendor's return window
+/// closes within `RETURN_WARNING_DAYS`, once per order
+async fn remind(state: &'static UsrState) -> Result<(), DbErr> {
+    let db = &state.db;
+    let today = Local::now().date_naive();
+    let returned = current::Entity::find()
+        .filter(current::Column::Status.eq(order_status::Status::Returned))
+        .all(db)
+        .await?;
+    if returned.is_empty() {
+        return Ok(());
+    }
+    let sent: HashSet<_> = deadline::Entity::find()
+        .filter(deadline::Column::Stage.eq(Stage::ReturnWindow))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| model.order_id)
+        .collect();
+
+    for model in returned {
+        if sent.contains(&model.id) {
+            continue;
+        }
+        let (order, _) = model.into_parts();
+        let Some(vendor) = vendor::find(db, &order.vendor).await? else {
+            continue;
+        };
+        let Some(date) = return_by(db, &order, &vendor).await? else {
+            continue;
+        };
+        if today + Days::new(RETURN_WARNING_DAYS) < date {
+            continue;
+        }
+        let mention = state
+            .team_lead_roles
+            .get(&order.team)
+            .map(|role| format!("<@&{role}> "))
+            .unwrap_or_default();
+        let when = match (date - today).num_days() {
+            ..0 => format!("closed on {date}"),
+            0 => "closes today".to_string(),
+            1 => "closes tomorrow".to_string(),
+            days => format!("closes on {date}, in {days} days"),
+        };
+        let mut msg = format!(
+            "**Return Window Closing**\n{mention}{}'s return window with {} {when}\n**Name:** {}\n**Team:** {}",
+            order.number(),
+            vendor.name,
+            order.name,
+            order.team,
+        );
+        if let Some(contact) = &vendor.rma_contact {
+            msg.push_str(&format!("\n**RMA Contact:** {contact}"));
+        }
+        msg.push_str(&permalink::line(&order));
+        state
+            .notifier
+            .send_for(order.team, Topic::OrderUpdate, order.id as u32, msg);
+        deadline::Entity::insert(deadline::ActiveModel {
+            order_id: ActiveValue::Set(order.id),
+            stage: ActiveValue::Set(Stage::ReturnWindow),
+            sent: ActiveValue::Set(Local::now().naive_local()),
+        })
+        .on_conflict_do_nothing()
+        .exec(db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Periodically reminds the order updates webhook of returns that have to
+/// reach their vendor soon
+pub fn spawn(state: &'static UsrState) {
+    if !state.notifier.routes(Topic::OrderUpdate) {
+        return;
+    }
+    jobs::spawn(
+        state,
+        "return_window_reminders",
+        Schedule::Every(Duration::from_secs(60 * 60)),
+        Retry::times(2, Duration::from_secs(5 * 60)),
+        |state| async move { Ok(remind(state).await?) },
+    );
+}
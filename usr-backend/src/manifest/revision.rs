@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use sea_orm::{entity::prelude::*, ActiveValue};
+use serde::Serialize;
+
+use crate::money;
+
+use super::order;
+
+/// An order's count or unit cost before and after a change, so that a
+/// quietly bumped quantity can be traced to what it did to the budget
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "order_revisions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub order_id: i64,
+    /// The audit log entry the change was recorded in
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audit_id: Option<i64>,
+    pub date: DateTime,
+    pub old_count: i64,
+    pub old_unit_cost: Decimal,
+    pub new_count: i64,
+    pub new_unit_cost: Decimal,
+    /// What the change did to the order's subtotal, eg. 42.50 or -10.00
+    pub subtotal_change: Decimal,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// What `before`'s subtotal changes by at `count` and `unit_cost`, or `None`
+/// if neither is changing
+pub fn subtotal_change(before: &order::Model, count: i64, unit_cost: Decimal) -> Option<Decimal> {
+    (before.count != count || before.unit_cost != unit_cost).then(|| {
+        money::subtotal(count, unit_cost) - money::subtotal(before.count, before.unit_cost)
+    })
+}
+
+/// The webhook line for a change to an order's subtotal, eg.
+/// `**Subtotal Change:** +$42.50`
+pub fn line(change: Option<Decimal>) -> String {
+    match change {
+        Some(change) if change.is_sign_positive() && !change.is_zero() => {
+            format!("\n**Subtotal Change:** +{}", money::dollars(change))
+        }
+        Some(change) => format!("\n**Subtotal Change:** {}", money::dollars(change)),
+        None => String::new(),
+    }
+}
+
+/// Records the change from `before` to `after`, meant to be written in the
+/// same transaction as the change itself. Nothing is recorded unless the
+/// count or unit cost changed.
+pub async fn record(
+    db: &impl ConnectionTrait,
+    before: &order::Model,
+    after: &order::Model,
+    audit_id: Option<i64>,
+) -> Result<(), DbErr> {
+    let Some(change) = subtotal_change(before, after.count, after.unit_cost) else {
+        return Ok(());
+    };
+    ActiveModel {
+        id: ActiveValue::NotSet,
+        order_id: ActiveValue::Set(after.id),
+        audit_id: ActiveValue::Set(audit_id),
+        date: ActiveValue::Set(chrono::Local::now().naive_local()),
+        old_count: ActiveValue::Set(before.count),
+        old_unit_cost: ActiveValue::Set(before.unit_cost),
+        new_count: ActiveValue::Set(after.count),
+        new_unit_cost: ActiveValue::Set(after.unit_cost),
+        subtotal_change: ActiveValue::Set(change),
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+/// The subtotal change recorded with each of `audit_ids`, for those that
+/// changed one
+pub async fn by_audit_id(
+    db: &impl ConnectionTrait,
+    audit_ids: impl IntoIterator<Item = i64>,
+) -> Result<HashMap<i64, Decimal>, DbErr> {
+    Ok(Entity::find()
+        .filter(Column::AuditId.is_in(audit_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|model| Some((model.audit_id?, model.subtotal_change)))
+        .collect())
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use parking_lot::RwLock;
+use sea_orm::{prelude::Decimal, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Serialize;
+
+use crate::scheduler::Team;
+
+use super::{budget, cost_split, current, order_status};
+
+#[derive(Serialize, Default, Clone, Copy)]
+pub struct TeamRollup {
+    /// Orders that haven't reached storage yet
+    open_orders: u32,
+    /// The team's share of every order that has been submitted to its vendor
+    committed_spend: Decimal,
+    /// Orders that have been delivered but not put into storage
+    awaiting_pickup: u32,
+    /// Orders waiting on a lead to release them
+    on_hold: u32,
+}
+
+/// Per-team dashboard numbers, cached so that they can be polled cheaply and
+/// refreshed only for the teams an order mutation touches.
+pub struct Rollups {
+    cache: RwLock<HashMap<Team, TeamRollup>>,
+}
+
+async fn compute(
+    db: &DatabaseConnection,
+    team: Option<Team>,
+) -> Result<HashMap<Team, TeamRollup>, sea_orm::DbErr> {
+    let mut orders = current::Entity::find();
+    if let Some(team) = team {
+        orders = orders.filter(current::Column::Team.eq(team));
+    }
+
+    let splits = budget::all_splits(db).await?;
+    let unspent = budget::unspent_cancelled(db).await?;
+
+    let mut out = HashMap::<Team, TeamRollup>::new();
+    for model in orders.all(db).await? {
+        let (model, status) = model.into_parts();
+        let rollup = out.entry(model.team).or_default();
+        if !matches!(
+            status,
+            order_status::Status::InStorage
+                | order_status::Status::Returned
+                | order_status::Status::Cancelled
+        ) {
+            rollup.open_orders += 1;
+        }
+        if status == order_status::Status::Delivered {
+            rollup.awaiting_pickup += 1;
+        }
+        if status == order_status::Status::OnHold {
+            rollup.on_hold += 1;
+        }
+        if status != order_status::Status::New && !unspent.contains(&model.id) {
+            let splits = splits.get(&model.id).map(Vec::as_slice).unwrap_or_default();
+            for share in budget::shares(&model, splits) {
+                out.entry(share.team).or_default().committed_spend += share.amount;
+            }
+        }
+    }
+    Ok(out)
+}
+
+impl Rollups {
+    pub async fn load(db: &DatabaseConnection) -> Result<Self, sea_orm::DbErr> {
+        Ok(Self {
+            cache: RwLock::new(compute(db, None).await?),
+        })
+    }
+
+    /// Recomputes the rollup of `team`, or of every team if `None`
+    pub async fn refresh(
+        &self,
+        db: &DatabaseConnection,
+        team: Option<Team>,
+    ) -> Result<(), sea_orm::DbErr> {
+        // Split orders count towards several teams, so once there are any,
+        // one team's change can move another's numbers
+        let team = match cost_split::Entity::find().one(db).await? {
+            Some(_) => None,
+            None => team,
+        };
+        let fresh = compute(db, team).await?;
+        let mut cache = self.cache.write();
+        match team {
+            Some(team) => match fresh.get(&team) {
+                Some(rollup) => {
+                    cache.insert(team, *rollup);
+                }
+                None => {
+                    cache.remove(&team);
+                }
+            },
+            None => *cache = fresh,
+        }
+        Ok(())
+    }
+
+    pub fn get(&self) -> HashMap<Team, TeamRollup> {
+        self.cache.read().clone()
+    }
+}
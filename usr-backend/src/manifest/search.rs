@@ -0,0 +1,96 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{
+    sea_query::{Expr, Func, LikeExpr},
+    Condition, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::Deserialize;
+use tracing::error;
+use utoipa::IntoParams;
+
+use crate::{api_error::ApiError, UsrState};
+
+use super::current;
+
+/// The columns searched, which hold what people remember about an order
+const COLUMNS: [current::Column; 4] = [
+    current::Column::Name,
+    current::Column::Vendor,
+    current::Column::Reason,
+    current::Column::Link,
+];
+
+/// Matches `term` anywhere in a column, taking `%` and `_` literally
+fn containing(term: &str) -> LikeExpr {
+    let mut pattern = String::from("%");
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    LikeExpr::new(pattern).escape('\\')
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Orders containing every word of this are found, ignoring case
+    q: String,
+    /// At most 200
+    #[serde(default = "default_limit")]
+    #[param(minimum = 1, maximum = 200)]
+    limit: u64,
+}
+
+fn default_limit() -> u64 {
+    50
+}
+
+/// Orders whose name, vendor, reason or link contain every word of `q`, with
+/// their latest status, newest first. Cancelled orders and those of closed
+/// seasons are searched too, since they are often what is being looked for.
+#[utoipa::path(
+    get,
+    path = "/api/manifest/search/order",
+    tag = "manifest",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "The orders found", body = Vec<current::Model>),
+        (status = 400, body = ApiError),
+    ),
+)]
+#[axum::debug_handler]
+pub async fn search_orders(
+    State(state): State<&'static UsrState>,
+    Query(SearchQuery { q, limit }): Query<SearchQuery>,
+) -> Response {
+    if q.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "Nothing to search for").into_response();
+    }
+    // Both sides are lowercased since LIKE is case sensitive on Postgres
+    let q = q.to_lowercase();
+    let condition = q.split_whitespace().fold(Condition::all(), |all, term| {
+        all.add(COLUMNS.into_iter().fold(Condition::any(), |any, column| {
+            any.add(Expr::expr(Func::lower(Expr::col(column))).like(containing(term)))
+        }))
+    });
+    match current::Entity::find()
+        .filter(condition)
+        .order_by_desc(current::Column::Id)
+        .limit(limit.clamp(1, 200))
+        .all(&state.db)
+        .await
+    {
+        Ok(orders) => Json(orders).into_response(),
+        Err(e) => {
+            error!("Failed to search orders: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::sync::atomic::{AtomicU16, Ordering};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Datelike, Local};
+use sea_orm::{entity::prelude::*, ActiveValue, QueryOrder, QuerySelect, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{backup::backup_db, money, notify::Topic, UsrState};
+
+use super::{assign_season_numbers, budget, current, order_status::Status, policy};
+
+/// The last season that was closed, or 0 if none has been
+static LAST_CLOSED: AtomicU16 = AtomicU16::new(0);
+
+/// A season that was closed, with what was ordered in it as of then. Its
+/// orders stay tagged with it and can still be listed with `?season=`.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "seasons")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub season: i64,
+    pub closed_at: DateTime,
+    pub closed_by: String,
+    /// Orders placed in the season, not counting cancelled ones
+    pub orders: i64,
+    /// Of those, the ones that hadn't been put away or returned yet
+    pub open_orders: i64,
+    /// What the season's orders cost, not counting cancelled ones
+    pub total: Decimal,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Reads which season was closed last, once at startup
+pub async fn load(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let last: Option<Option<u32>> = Entity::find()
+        .select_only()
+        .column_as(Column::Season.max(), "last")
+        .into_tuple()
+        .one(db)
+        .await?;
+    LAST_CLOSED.store(last.flatten().unwrap_or_default() as u16, Ordering::Relaxed);
+    Ok(())
+}
+
+/// The season orders placed now belong to: the calendar year, unless that
+/// season has already been closed early
+pub fn current() -> u16 {
+    (Local::now().year() as u16).max(LAST_CLOSED.load(Ordering::Relaxed) + 1)
+}
+
+#[derive(Deserialize)]
+pub struct CloseSeason {
+    /// Whether each team's budget balance is carried into the next season.
+    /// Left out, the configured default is used.
+    #[serde(default)]
+    carry_over: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct ClosedSeason {
+    #[serde(flatten)]
+    season: Model,
+    /// The season orders are placed in from now on
+    next: u16,
+    rollover: budget::Rollover,
+}
+
+/// Closes the current season: records what was ordered in it, rolls the
+/// budgets over into a period for the next one, and starts placing orders
+/// in the next one. Orders are listed by season, so the closed season's
+/// orders stop showing up by default.
+#[axum::debug_handler]
+pub async fn close_season(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(CloseSeason { carry_over }): Json<CloseSeason>,
+) -> Response {
+    let season = current();
+    let next = season + 1;
+    let now = Local::now().naive_local();
+    let closed_by = caller.name.unwrap_or_else(|| caller.role.to_string());
+    // Orders imported since they were last numbered don't have a season yet
+    if let Err(e) = assign_season_numbers(&state.db).await {
+        error!("Failed to number orders: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+    }
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let models = current::Entity::find()
+                    .filter(current::Column::Season.eq(season))
+                    .filter(current::Column::Status.ne(Status::Cancelled))
+                    .all(tx)
+                    .await?;
+                let open_orders = models
+                    .iter()
+                    .filter(|model| !matches!(model.status, Status::InStorage | Status::Returned))
+                    .count() as i64;
+                let total = models
+                    .iter()
+                    .map(|model| model.clone().into_parts().0.total())
+                    .sum();
+                let model = ActiveModel {
+                    season: ActiveValue::Set(season.into()),
+                    closed_at: ActiveValue::Set(now),
+                    closed_by: ActiveValue::Set(closed_by),
+                    orders: ActiveValue::Set(models.len() as i64),
+                    open_orders: ActiveValue::Set(open_orders),
+                    total: ActiveValue::Set(total),
+                }
+                .insert(tx)
+                .await?;
+                let rollover = budget::roll_over(
+                    tx,
+                    format!("{next} Season"),
+                    &state.team_budgets,
+                    carry_over.unwrap_or(state.carry_over_budgets),
+                    now,
+                )
+                .await?;
+                Result::<_, DbErr>::Ok(ClosedSeason {
+                    season: model,
+                    next,
+                    rollover,
+                })
+            })
+        })
+        .await;
+
+    match result {
+        Ok(closed) => {
+            LAST_CLOSED.store(season, Ordering::Relaxed);
+            backup_db(state);
+            let mut msg = format!(
+                "**Season Closed**\nThe {season} season was closed with {} orders totalling {}",
+                closed.season.orders,
+                money::dollars(closed.season.total)
+            );
+            if closed.season.open_orders > 0 {
+                msg.push_str(&format!(
+                    ", {} of which haven't been put away yet",
+                    closed.season.open_orders
+                ));
+            }
+            msg.push_str(&format!("\nOrders placed from now on belong to {next}"));
+            state
+                .notifier
+                .send(Topic::Spending, u32::MAX / 16 * 9 + u32::from(season), msg);
+            state.notifier.send(
+                Topic::Spending,
+                u32::MAX - closed.rollover.opened_id(),
+                closed.rollover.message(),
+            );
+            Json(closed).into_response()
+        }
+        Err(e) => {
+            error!("Failed to close season: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Seasons {
+    current: u16,
+    /// Most recently closed first
+    closed: Vec<Model>,
+}
+
+/// The current season and every closed one
+#[axum::debug_handler]
+pub async fn get_seasons(State(state): State<&'static UsrState>) -> Response {
+    match Entity::find()
+        .order_by_desc(Column::Season)
+        .all(&state.db)
+        .await
+    {
+        Ok(closed) => Json(Seasons {
+            current: current(),
+            closed,
+        })
+        .into_response(),
+        Err(e) => {
+            error!("Failed to get seasons: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
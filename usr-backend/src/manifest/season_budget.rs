@@ -0,0 +1,35 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler;
+
+/// What a team can commit to orders over a season, on top of the budget
+/// periods that the spending alerts follow
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "season_budgets")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub team: scheduler::Team,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub season: i64,
+    pub allocated: Decimal,
+    pub overrun: Overrun,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// What happens to an order that would take its team over budget
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Overrun {
+    /// The order is turned away
+    #[sea_orm(string_value = "R")]
+    Reject,
+    /// The order is placed, and marked as over budget when it is announced
+    #[sea_orm(string_value = "F")]
+    Flag,
+}
@@ -0,0 +1,1667 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+};
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::Datelike;
+use sea_orm::{
+    prelude::Decimal,
+    sea_query::{Expr, Func, Query},
+    sqlx::types::chrono::Local,
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, QueryFilter, QueryOrder, QuerySelect, SqlErr,
+    TransactionTrait,
+};
+use tracing::error;
+
+use crate::{
+    api_error::ApiError, assets, backup::backup_db, money, notify::Topic, registry, scheduler,
+    UsrState,
+};
+
+use super::{
+    approval, audit, budget, charges_text, competition, current, current_season, custom_field, discrepancy,
+    events, freeze, intake, inventory, location, mention_requester, new_order_webhook_msg,
+    next_season_number, non_blank, notify_watchers, order, order_status, order_update_webhook_msg, orders_changed, permalink,
+    pickup, policy, publish_current, publish_event, quota, season_budget, shipment, shipment_order,
+    spending_freeze, unit_cost_text, vendor, vendor_policy, OrderRef, PendingOrder, UpdateOrder,
+};
+
+/// Why an order couldn't be placed, updated or cancelled. Handlers answer
+/// with the matching status code, while other callers, such as the email
+/// intake, pass the message on.
+#[derive(Debug)]
+pub enum OrderError {
+    /// Something about the request is wrong
+    Invalid(String),
+    /// One field of the request is wrong, eg. `count`
+    InvalidField(&'static str, String),
+    /// The caller isn't allowed to, or the team's spending rules forbid it
+    Forbidden(String),
+    /// The order isn't in a state that allows it
+    Conflict(String),
+    /// The order can't be moved to the status asked for, along with the
+    /// statuses it can be moved to
+    Transition(String, Vec<order_status::Status>),
+    /// The order breaks the intake rules set in the config
+    Violations(Vec<intake::Violation>),
+    /// The database failed. It has already been logged.
+    Internal,
+}
+
+impl Display for OrderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderError::Invalid(msg)
+            | OrderError::InvalidField(_, msg)
+            | OrderError::Forbidden(msg)
+            | OrderError::Conflict(msg)
+            | OrderError::Transition(msg, _) => write!(f, "{msg}"),
+            OrderError::Violations(violations) => {
+                let messages: Vec<_> = violations.iter().map(|v| v.message.as_str()).collect();
+                write!(f, "{}", messages.join("; "))
+            }
+            OrderError::Internal => write!(f, "Something went wrong on our end"),
+        }
+    }
+}
+
+impl From<OrderError> for ApiError {
+    fn from(error: OrderError) -> Self {
+        match error {
+            OrderError::Invalid(msg) => ApiError::invalid(msg),
+            OrderError::InvalidField(field, msg) => ApiError::invalid(msg).for_field(field),
+            OrderError::Forbidden(msg) => ApiError::forbidden(msg),
+            OrderError::Conflict(msg) => ApiError::conflict(msg),
+            OrderError::Transition(msg, allowed) => {
+                ApiError::invalid(msg).with_details(serde_json::json!({ "allowed": allowed }))
+            }
+            OrderError::Violations(violations) => {
+                let message = match violations.as_slice() {
+                    [violation] => violation.message.clone(),
+                    _ => format!("Order breaks {} intake rules", violations.len()),
+                };
+                ApiError::invalid(message)
+                    .with_details(serde_json::json!({ "violations": violations }))
+            }
+            OrderError::Internal => ApiError::internal(),
+        }
+    }
+}
+
+impl IntoResponse for OrderError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+impl OrderError {
+    /// Says which item of a cart the error is about
+    fn for_item(self, number: usize) -> Self {
+        self.about(format!("Item {number}"))
+    }
+
+    /// Says which of several orders the error is about
+    fn about(self, what: impl Display) -> Self {
+        match self {
+            OrderError::Invalid(msg) => OrderError::Invalid(format!("{what}: {msg}")),
+            OrderError::InvalidField(field, msg) => {
+                OrderError::InvalidField(field, format!("{what}: {msg}"))
+            }
+            OrderError::Forbidden(msg) => OrderError::Forbidden(format!("{what}: {msg}")),
+            OrderError::Conflict(msg) => OrderError::Conflict(format!("{what}: {msg}")),
+            OrderError::Transition(msg, allowed) => {
+                OrderError::Transition(format!("{what}: {msg}"), allowed)
+            }
+            OrderError::Violations(mut violations) => {
+                for violation in &mut violations {
+                    violation.message = format!("{what}: {}", violation.message);
+                }
+                OrderError::Violations(violations)
+            }
+            OrderError::Internal => OrderError::Internal,
+        }
+    }
+}
+
+/// The policy checks answer with a status code
+impl From<(StatusCode, String)> for OrderError {
+    fn from((code, msg): (StatusCode, String)) -> Self {
+        match code {
+            StatusCode::FORBIDDEN => OrderError::Forbidden(msg),
+            StatusCode::CONFLICT => OrderError::Conflict(msg),
+            _ => OrderError::Invalid(msg),
+        }
+    }
+}
+
+fn invalid(msg: &str) -> OrderError {
+    OrderError::Invalid(msg.to_string())
+}
+
+/// Rejects links that aren't web addresses. Orders bought in person can be
+/// left without one.
+pub fn check_link(link: &str) -> Result<(), &'static str> {
+    let link = link.trim();
+    if link.is_empty() {
+        return Ok(());
+    }
+    match reqwest::Url::parse(link) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.host().is_some() => Ok(()),
+        _ => Err("Link must be a web address, eg. https://www.example.com/part"),
+    }
+}
+
+/// Rejects `needed_by` dates that have already passed
+pub fn check_needed_by(needed_by: chrono::NaiveDate) -> Result<(), &'static str> {
+    if needed_by < Local::now().date_naive() {
+        Err("Needed by cannot be in the past")
+    } else {
+        Ok(())
+    }
+}
+
+/// The checks on an order's own fields, which don't need the database
+fn check_order_fields(pending_order: &PendingOrder) -> Result<(), OrderError> {
+    if pending_order.count == 0 {
+        return Err(OrderError::InvalidField(
+            "count",
+            "Count must be positive".to_string(),
+        ));
+    }
+    money::validate_unit_cost(pending_order.unit_cost)
+        .map_err(|msg| OrderError::InvalidField("unit_cost", msg.to_string()))?;
+    check_link(&pending_order.link)
+        .map_err(|msg| OrderError::InvalidField("link", msg.to_string()))?;
+    if let Some(Err(msg)) = pending_order.needed_by.map(check_needed_by) {
+        return Err(OrderError::InvalidField("needed_by", msg.to_string()));
+    }
+    check_charges([
+        ("shipping_cost", pending_order.shipping_cost),
+        ("tax", pending_order.tax),
+        ("fees", pending_order.fees),
+    ])
+}
+
+/// Turns away shipping, tax or fees that are negative or finer than a cent
+fn check_charges(charges: [(&'static str, Option<Decimal>); 3]) -> Result<(), OrderError> {
+    for (field, amount) in charges {
+        let Some(amount) = amount else {
+            continue;
+        };
+        if amount.is_sign_negative() {
+            return Err(OrderError::InvalidField(
+                field,
+                format!("{field} cannot be negative"),
+            ));
+        }
+        if amount.normalize().scale() > 2 {
+            return Err(OrderError::InvalidField(
+                field,
+                format!("{field} must be in whole cents"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// How far back [find_duplicate] looks
+const DUPLICATE_DAYS: u64 = 30;
+
+/// The latest order placed in the last 30 days with the same link as
+/// `pending_order`, or the same name from the same vendor, ignoring case. A
+/// cancelled order is never one.
+pub async fn find_duplicate(
+    db: &DatabaseConnection,
+    pending_order: &PendingOrder,
+) -> Result<Option<order::Model>, OrderError> {
+    let cutoff = Local::now().naive_local() - chrono::Days::new(DUPLICATE_DAYS);
+    let mut same = Condition::any().add(
+        Condition::all()
+            .add(
+                Expr::expr(Func::lower(Expr::col(order::Column::Vendor)))
+                    .eq(vendor::key(&pending_order.vendor)),
+            )
+            .add(
+                Expr::expr(Func::lower(Expr::col(order::Column::Name)))
+                    .eq(pending_order.name.trim().to_lowercase()),
+            ),
+    );
+    let link = pending_order.link.trim();
+    if !link.is_empty() {
+        same = same.add(order::Column::Link.eq(link));
+    }
+    order::Entity::find()
+        .filter(same)
+        .filter(
+            order::Column::Id.in_subquery(
+                Query::select()
+                    .column(order_status::Column::OrderId)
+                    .from(order_status::Entity)
+                    .group_by_col(order_status::Column::OrderId)
+                    .and_having(Expr::expr(Expr::col(order_status::Column::Date).min()).gte(cutoff))
+                    .to_owned(),
+            ),
+        )
+        .filter(
+            order::Column::Id.not_in_subquery(
+                Query::select()
+                    .column(current::Column::Id)
+                    .from(current::Entity)
+                    .and_where(current::Column::Status.eq(order_status::Status::Cancelled))
+                    .to_owned(),
+            ),
+        )
+        .order_by_desc(order::Column::Id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            error!("Failed to look for duplicate orders: {e}");
+            OrderError::Internal
+        })
+}
+
+/// Turns away new orders for `team` while its spending is frozen
+pub async fn check_not_frozen(state: &UsrState, team: scheduler::Team) -> Result<(), OrderError> {
+    match spending_freeze(&state.db, team).await {
+        Ok(Some(model)) if model.mode == freeze::Mode::Reject => Err(OrderError::Forbidden(
+            format!("Spending is frozen: {}", model.reason),
+        )),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to find spending freeze: {e}");
+            Err(OrderError::Internal)
+        }
+    }
+}
+
+/// Turns away an order that adds `added` to what `team` has committed in
+/// `season`, if that takes the team over a budget that rejects overruns.
+/// `excluding` is the order being changed, if it is already placed.
+pub async fn check_budget(
+    state: &UsrState,
+    team: scheduler::Team,
+    season: u16,
+    excluding: Option<i64>,
+    added: Decimal,
+) -> Result<(), OrderError> {
+    match budget::standing(&state.db, team, season, excluding).await {
+        Ok(Some(standing))
+            if standing.overrun == season_budget::Overrun::Reject && added > standing.remaining =>
+        {
+            Err(OrderError::Forbidden(format!(
+                "Order would put {team} {} over its {season} budget",
+                money::dollars(added - standing.remaining)
+            )))
+        }
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to find season budget: {e}");
+            Err(OrderError::Internal)
+        }
+    }
+}
+
+/// Why orders from `vendor` are warned about if it is blocked, and whether
+/// they are turned away
+async fn blocked_vendor(
+    db: &impl ConnectionTrait,
+    vendor: &str,
+) -> Result<Option<(String, bool)>, sea_orm::DbErr> {
+    Ok(vendor_policy::Entity::find_by_id(vendor::key(vendor))
+        .one(db)
+        .await?
+        .filter(|model| model.standing == vendor_policy::Standing::Blocked)
+        .map(|model| {
+            let warning = match model.reason {
+                Some(reason) => format!("{} is blocked: {reason}", model.vendor),
+                None => format!("{} is blocked", model.vendor),
+            };
+            (warning, model.reject)
+        }))
+}
+
+/// Turns away orders from `vendor` if it is blocked that way
+pub async fn check_vendor(db: &DatabaseConnection, vendor: &str) -> Result<(), OrderError> {
+    match blocked_vendor(db, vendor).await {
+        Ok(Some((warning, true))) => Err(OrderError::Forbidden(warning)),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to find vendor policy: {e}");
+            Err(OrderError::Internal)
+        }
+    }
+}
+
+/// Turns away an order that a lead hasn't approved, or rejected
+pub async fn check_approved(db: &DatabaseConnection, id: i64) -> Result<(), OrderError> {
+    match approval::Entity::find_by_id(id).one(db).await {
+        Ok(Some(model)) if model.decision == approval::Decision::Approved => Ok(()),
+        Ok(Some(model)) => Err(OrderError::Conflict(format!(
+            "Order was rejected by {}: {}",
+            model.decided_by,
+            model.reason.unwrap_or_default()
+        ))),
+        Ok(None) => Err(OrderError::Conflict(
+            "Order needs a lead's approval before it can be bought".to_string(),
+        )),
+        Err(e) => {
+            error!("Failed to find order approval: {e}");
+            Err(OrderError::Internal)
+        }
+    }
+}
+
+/// An order that was just placed
+pub struct Placed {
+    pub order: order::Model,
+    /// Why the order was put on hold as soon as it was placed, if it was
+    pub hold: Option<String>,
+    /// Where the order's team stands against its season budget, if it has one
+    pub standing: Option<budget::Standing>,
+    /// The budget alert the order set off, if any
+    pub alert: Option<String>,
+    /// Why its vendor is blocked, if it is but orders from it are still placed
+    pub warning: Option<String>,
+}
+
+impl Placed {
+    /// The message announcing the order
+    pub fn webhook_msg(&self) -> String {
+        new_order_webhook_msg(
+            &self.order,
+            self.hold.as_deref(),
+            self.warning.as_deref(),
+            self.standing.as_ref(),
+        )
+    }
+}
+
+/// Inserts the order along with its initial `New` status, putting it on hold
+/// if its team's spending is frozen that way.
+pub async fn insert_order(
+    state: &UsrState,
+    tx: &DatabaseTransaction,
+    pending_order: PendingOrder,
+    actor: &audit::Actor,
+) -> Result<Placed, sea_orm::DbErr> {
+    let now = Local::now().naive_local();
+    let season = now.year() as u16;
+    let unit_cost = pending_order.dollar_unit_cost();
+    let foreign = pending_order.currency != order::Currency::Usd;
+    let fields = pending_order
+        .fields
+        .into_iter()
+        .map(|(key, value)| (key, Some(value)))
+        .collect();
+    let active_model = order::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(pending_order.name),
+        count: ActiveValue::Set(pending_order.count.into()),
+        unit_cost: ActiveValue::Set(unit_cost),
+        store_in: ActiveValue::Set(pending_order.store_in),
+        team: ActiveValue::Set(pending_order.team),
+        reason: ActiveValue::Set(pending_order.reason),
+        vendor: ActiveValue::Set(pending_order.vendor),
+        link: ActiveValue::Set(pending_order.link),
+        funding_source: ActiveValue::Set(pending_order.funding_source),
+        component_id: ActiveValue::Set(pending_order.component_id.map(i64::from)),
+        ref_number: ActiveValue::NotSet,
+        season: ActiveValue::Set(Some(season.into())),
+        season_number: ActiveValue::Set(Some(next_season_number(tx, season).await?)),
+        tax_exempt: ActiveValue::Set(None),
+        payment_method: ActiveValue::Set(None),
+        requester: ActiveValue::Set(non_blank(pending_order.requester)),
+        currency: ActiveValue::Set(Some(pending_order.currency).filter(|_| foreign)),
+        original_unit_cost: ActiveValue::Set(Some(pending_order.unit_cost).filter(|_| foreign)),
+        exchange_rate: ActiveValue::Set(pending_order.exchange_rate.filter(|_| foreign)),
+        cart_id: ActiveValue::Set(None),
+        needed_by: ActiveValue::Set(pending_order.needed_by),
+        shipping_cost: ActiveValue::Set(pending_order.shipping_cost),
+        tax: ActiveValue::Set(pending_order.tax),
+        fees: ActiveValue::Set(pending_order.fees),
+        version: ActiveValue::Set(0),
+        competition: ActiveValue::Set(competition::active(state)),
+        received_count: ActiveValue::Set(None),
+    };
+    let model = active_model.insert(tx).await?;
+    vendor::ensure(tx, &model.vendor).await?;
+
+    let active_model = order_status::ActiveModel {
+        order_id: ActiveValue::Set(model.id),
+        instance_id: ActiveValue::NotSet,
+        date: ActiveValue::Set(now),
+        status: ActiveValue::Set(order_status::Status::New),
+        reason: ActiveValue::Set(None),
+    };
+
+    active_model.insert(tx).await?;
+
+    let hold = match spending_freeze(tx, model.team).await? {
+        Some(freeze) if freeze.mode == freeze::Mode::Hold => {
+            let reason = format!("Spending freeze: {}", freeze.reason);
+            order_status::ActiveModel {
+                order_id: ActiveValue::Set(model.id),
+                instance_id: ActiveValue::NotSet,
+                date: ActiveValue::Set(now),
+                status: ActiveValue::Set(order_status::Status::OnHold),
+                reason: ActiveValue::Set(Some(reason.clone())),
+            }
+            .insert(tx)
+            .await?;
+            Some(reason)
+        }
+        _ => None,
+    };
+    let mut diff = audit::diff(None, Some(&model));
+    let status = if hold.is_some() {
+        order_status::Status::OnHold
+    } else {
+        order_status::Status::New
+    };
+    diff.extend(audit::change("status", (), status));
+    diff.extend(custom_field::store(tx, model.id, fields).await?);
+    actor.record(tx, model.id, diff).await?;
+
+    let alert = budget::threshold_alert(
+        tx,
+        &model,
+        &state.team_budgets,
+        &state.budget_thresholds,
+        state.team_lead_roles.get(&model.team).copied(),
+    )
+    .await?
+    .map(|alert| alert + &permalink::line(&model));
+    let standing = budget::standing(tx, model.team, season, None).await?;
+    let warning = blocked_vendor(tx, &model.vendor)
+        .await?
+        .map(|(warning, _)| warning);
+
+    Ok(Placed {
+        order: model,
+        hold,
+        standing,
+        alert,
+        warning,
+    })
+}
+
+/// Announces an order placed by `insert_order` once it has been committed.
+/// Budget alerts for a team replace each other while waiting to be sent, so
+/// only the highest threshold is posted.
+pub async fn announce_placed(state: &'static UsrState, placed: Placed) -> order::Model {
+    backup_db(state);
+    orders_changed(state, Some(placed.order.team)).await;
+    publish_current(state, events::EventKind::Created, placed.order.id).await;
+    if let Some(alert) = &placed.alert {
+        state.notifier.send_for(
+            placed.order.team,
+            Topic::Spending,
+            u32::MAX / 2 + placed.order.team as u32,
+            alert.clone(),
+        );
+    }
+    if state.notifier.routes(Topic::NewOrder) {
+        let order = &placed.order;
+        if state.notifier.is_urgent(order.total()) {
+            state
+                .notifier
+                .send_for(order.team, Topic::NewOrder, order.id as u32, placed.webhook_msg());
+        } else {
+            state.notifier.send_digested(
+                order.team,
+                Topic::NewOrder,
+                order.id as u32,
+                order_status::Status::New,
+                format!("{} {}", order.number(), order.name),
+                placed.webhook_msg(),
+            );
+        }
+    }
+    placed.order
+}
+
+/// Checks everything about an order before it is placed, settling its
+/// exchange rate and custom fields. `fallback_rate` is used if no rate is
+/// given or configured.
+pub async fn check_new_order(
+    state: &UsrState,
+    pending_order: &mut PendingOrder,
+    fallback_rate: Option<Decimal>,
+) -> Result<(), OrderError> {
+    check_order_fields(pending_order)?;
+    pending_order
+        .capture_rate(state, fallback_rate)
+        .map_err(invalid)?;
+    if let Some(component_id) = pending_order.component_id {
+        match registry::component_exists(&state.db, component_id).await {
+            Ok(true) => {}
+            Ok(false) => return Err(invalid("Component not found")),
+            Err(e) => {
+                error!("Failed to find component: {e}");
+                return Err(OrderError::Internal);
+            }
+        }
+    }
+    check_vendor(&state.db, &pending_order.vendor).await?;
+    pending_order.store_in = location::check(&state.db, &pending_order.store_in).await?;
+    pending_order.fields = custom_field::check(&state.db, &pending_order.fields, true)
+        .await?
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect();
+    let violations = intake::check(&state.intake_rules, pending_order);
+    if !violations.is_empty() {
+        return Err(OrderError::Violations(violations));
+    }
+    check_not_frozen(state, pending_order.team).await?;
+    check_budget(
+        state,
+        pending_order.team,
+        current_season(),
+        None,
+        pending_order.subtotal(),
+    )
+    .await
+}
+
+/// Checks and places an order, announcing it once it is committed. Orders a
+/// member places count against `caller`'s quota, while the ones placed by
+/// the server itself, such as recurring orders, have no caller.
+pub async fn place_order(
+    state: &'static UsrState,
+    mut pending_order: PendingOrder,
+    fallback_rate: Option<Decimal>,
+    caller: Option<policy::Caller>,
+    actor: audit::Actor,
+) -> Result<order::Model, OrderError> {
+    check_new_order(state, &mut pending_order, fallback_rate).await?;
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                if let Some(caller) = &caller {
+                    let pending_orders = std::slice::from_ref(&pending_order);
+                    if let Err(e) = quota::check(tx, state, caller, pending_orders).await? {
+                        return Ok(Err(e));
+                    }
+                }
+                insert_order(state, tx, pending_order, &actor).await.map(Ok)
+            })
+        })
+        .await;
+    match result {
+        Ok(Ok(placed)) => Ok(announce_placed(state, placed).await),
+        Ok(Err(e)) => Err(e),
+        Err(e) => {
+            error!("Failed to create new order: {e}");
+            Err(OrderError::Internal)
+        }
+    }
+}
+
+/// The message for orders placed together from one cart, in place of one
+/// message for each
+fn cart_webhook_msg(placed: &[Placed], cart_id: Option<&str>) -> String {
+    let mut msg = String::from("**New Cart!**");
+    if let Some(cart_id) = cart_id {
+        msg.push_str(&format!("\n**Cart:** {cart_id}"));
+    }
+    let mut total = Decimal::ZERO;
+    let mut standings = BTreeMap::new();
+    for placed in placed {
+        let order = &placed.order;
+        let subtotal = money::subtotal(order.count, order.unit_cost);
+        total += subtotal;
+        msg.push_str(&format!(
+            "\n- {} x {} from {} at {} = {} for {} ({})",
+            order.count,
+            order.name,
+            order.vendor,
+            unit_cost_text(order),
+            money::dollars(subtotal),
+            order.team,
+            order.number()
+        ));
+        if let Some(hold) = &placed.hold {
+            msg.push_str(&format!(" **On Hold:** {hold}"));
+        }
+        if let Some(warning) = &placed.warning {
+            msg.push_str(&format!(" **Warning:** {warning}"));
+        }
+        // Later orders were placed on top of the earlier ones, so the last
+        // standing of each team counts all of them
+        if let Some(standing) = &placed.standing {
+            standings.insert(order.team.to_string(), standing.line());
+        }
+    }
+    msg.push_str(&format!("\n**Subtotal:** {}", money::dollars(total)));
+    for (team, line) in standings {
+        msg.push_str(&line.replacen("\n", &format!("\n{team} "), 1));
+    }
+    msg
+}
+
+/// Checks and places every order in a cart in one transaction, so that
+/// either all of them are placed or none are, then announces them together.
+/// The orders share `cart_id` if one is given, and count against `caller`'s
+/// quota.
+pub async fn place_cart(
+    state: &'static UsrState,
+    mut pending_orders: Vec<PendingOrder>,
+    cart_id: Option<String>,
+    caller: policy::Caller,
+    actor: audit::Actor,
+) -> Result<Vec<order::Model>, OrderError> {
+    if pending_orders.is_empty() {
+        return Err(invalid("A cart needs at least one order"));
+    }
+    let mut totals: HashMap<scheduler::Team, Decimal> = HashMap::new();
+    for (i, pending_order) in pending_orders.iter_mut().enumerate() {
+        check_new_order(state, pending_order, None)
+            .await
+            .map_err(|e| e.for_item(i + 1))?;
+        *totals.entry(pending_order.team).or_default() += pending_order.subtotal();
+    }
+    // Each order can fit a budget on its own while the whole cart doesn't
+    for (team, total) in totals {
+        check_budget(state, team, current_season(), None, total).await?;
+    }
+
+    let cart_id = non_blank(cart_id);
+    let tx_cart_id = cart_id.clone();
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                if let Err(e) = quota::check(tx, state, &caller, &pending_orders).await? {
+                    return Ok(Err(e));
+                }
+                let mut placed = vec![];
+                for pending_order in pending_orders {
+                    placed.push(insert_order(state, tx, pending_order, &actor).await?);
+                }
+                if let Some(cart_id) = tx_cart_id {
+                    order::Entity::update_many()
+                        .col_expr(order::Column::CartId, Expr::value(cart_id.clone()))
+                        .filter(
+                            order::Column::Id.is_in(placed.iter().map(|placed| placed.order.id)),
+                        )
+                        .exec(tx)
+                        .await?;
+                    for placed in &mut placed {
+                        placed.order.cart_id = Some(cart_id.clone());
+                        actor
+                            .record(tx, placed.order.id, audit::change("cart_id", (), &cart_id))
+                            .await?;
+                    }
+                }
+                Result::<_, sea_orm::DbErr>::Ok(Ok(placed))
+            })
+        })
+        .await;
+    let placed = match result {
+        Ok(Ok(placed)) => placed,
+        Ok(Err(e)) => return Err(e),
+        Err(e) => {
+            error!("Failed to place cart: {e}");
+            return Err(OrderError::Internal);
+        }
+    };
+
+    backup_db(state);
+    let teams: HashSet<_> = placed.iter().map(|placed| placed.order.team).collect();
+    for &team in &teams {
+        orders_changed(state, Some(team)).await;
+    }
+    for placed in &placed {
+        publish_current(state, events::EventKind::Created, placed.order.id).await;
+        if let Some(alert) = &placed.alert {
+            state.notifier.send_for(
+                placed.order.team,
+                Topic::Spending,
+                u32::MAX / 2 + placed.order.team as u32,
+                alert.clone(),
+            );
+        }
+    }
+    // Keyed by the first order so that the cart shows up in its history
+    let key = placed[0].order.id as u32;
+    let message = cart_webhook_msg(&placed, cart_id.as_deref());
+    // A cart for more than one team goes to the global webhook rather than
+    // to one of their channels
+    match teams.into_iter().collect::<Vec<_>>()[..] {
+        [team] => state.notifier.send_for(team, Topic::NewOrder, key, message),
+        _ => state.notifier.send(Topic::NewOrder, key, message),
+    }
+    Ok(placed.into_iter().map(|placed| placed.order).collect())
+}
+
+/// What placing an order would do, inserting it and rolling back so that
+/// the database gets a say too
+pub async fn preview_order(
+    state: &'static UsrState,
+    mut pending_order: PendingOrder,
+    caller: &policy::Caller,
+    actor: &audit::Actor,
+) -> Result<Placed, OrderError> {
+    check_new_order(state, &mut pending_order, None).await?;
+    let preview = async {
+        let tx = state.db.begin().await?;
+        let pending_orders = std::slice::from_ref(&pending_order);
+        if let Err(e) = quota::check(&tx, state, caller, pending_orders).await? {
+            return Ok(Err(e));
+        }
+        let placed = insert_order(state, &tx, pending_order, actor).await?;
+        tx.rollback().await?;
+        Result::<_, sea_orm::DbErr>::Ok(Ok(placed))
+    };
+    match preview.await {
+        Ok(preview) => preview,
+        Err(e) => {
+            error!("Failed to create new order: {e}");
+            Err(OrderError::Internal)
+        }
+    }
+}
+
+/// How many times a ref number is claimed before giving up
+const REF_NUMBER_ATTEMPTS: u32 = 3;
+
+fn is_unique_violation(e: &sea_orm::DbErr) -> bool {
+    matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_)))
+}
+
+/// The number after the highest ref number so far
+async fn next_ref_number(tx: &DatabaseTransaction) -> Result<i64, sea_orm::DbErr> {
+    let last: Option<Option<i64>> = order::Entity::find()
+        .select_only()
+        .column_as(order::Column::RefNumber.max(), "last")
+        .into_tuple()
+        .one(tx)
+        .await?;
+    Ok(last.flatten().unwrap_or_default() + 1)
+}
+
+/// Records `update` in one transaction, claiming the next ref number if
+/// `claim` is set. Returns why it can't be recorded instead if the ref number
+/// it gives already belongs to another order.
+async fn apply_update(
+    state: &UsrState,
+    id: i64,
+    update: &UpdateOrder,
+    checked: &CheckedUpdate,
+    claim: bool,
+    actor: &audit::Actor,
+) -> Result<Result<(), String>, sea_orm::DbErr> {
+    let tx = state.db.begin().await?;
+    let result = record_update(state, &tx, id, update, checked, claim, actor).await?;
+    if result.is_ok() {
+        tx.commit().await?;
+    }
+    Ok(result)
+}
+
+/// Records `update` as part of `tx`
+async fn record_update(
+    state: &UsrState,
+    tx: &DatabaseTransaction,
+    id: i64,
+    update: &UpdateOrder,
+    checked: &CheckedUpdate,
+    claim: bool,
+    actor: &audit::Actor,
+) -> Result<Result<(), String>, sea_orm::DbErr> {
+    let CheckedUpdate {
+        same_status,
+        expected,
+        status,
+        ..
+    } = *checked;
+    let now = Local::now().naive_local();
+    let Some(before) = order::Entity::find_by_id(id).one(tx).await? else {
+        return Ok(Err("Order not found".to_string()));
+    };
+    if update.version.is_some_and(|version| i64::from(version) != before.version) {
+        return Ok(Err(policy::stale_version(&before)));
+    }
+    let ref_number = match update.ref_number {
+        Some(ref_number) => {
+            if let Some(other) = order::Entity::find()
+                .filter(order::Column::RefNumber.eq(ref_number))
+                .filter(order::Column::Id.ne(id))
+                .one(tx)
+                .await?
+            {
+                return Ok(Err(format!(
+                    "Ref number {ref_number} already belongs to order {}",
+                    other.number()
+                )));
+            }
+            ActiveValue::Set(Some(ref_number.into()))
+        }
+        None if claim => ActiveValue::Set(Some(next_ref_number(tx).await?)),
+        None => ActiveValue::NotSet,
+    };
+    for discrepancy in &update.discrepancies {
+        discrepancy::ActiveModel {
+            id: ActiveValue::NotSet,
+            order_id: ActiveValue::Set(id),
+            kind: ActiveValue::Set(discrepancy.kind),
+            expected: ActiveValue::Set(expected),
+            received: ActiveValue::Set(discrepancy.received.map_or(expected, i64::from)),
+            note: ActiveValue::Set(discrepancy.note.trim().to_string()),
+            reported: ActiveValue::Set(now),
+            resolved: ActiveValue::Set(None),
+            resolution: ActiveValue::Set(None),
+        }
+        .insert(tx)
+        .await?;
+    }
+    if !same_status {
+        let active_model = order_status::ActiveModel {
+            order_id: ActiveValue::Set(id),
+            instance_id: ActiveValue::NotSet,
+            date: ActiveValue::Set(now),
+            status: ActiveValue::Set(update.status),
+            reason: ActiveValue::Set(update.reason.clone()),
+        };
+
+        active_model.insert(tx).await?;
+    }
+    if !same_status && update.status == order_status::Status::Returned {
+        inventory::clear(tx, &before).await?;
+    }
+    if !same_status && update.status == order_status::Status::InStorage {
+        inventory::stock(tx, &before).await?;
+        if let Some(threshold) = state.asset_threshold {
+            assets::register(tx, &before, threshold, now).await?;
+        }
+    }
+
+    let active_model = order::ActiveModel {
+        id: ActiveValue::Unchanged(id),
+        name: ActiveValue::NotSet,
+        count: ActiveValue::NotSet,
+        unit_cost: ActiveValue::NotSet,
+        store_in: ActiveValue::NotSet,
+        team: ActiveValue::NotSet,
+        reason: ActiveValue::NotSet,
+        vendor: ActiveValue::NotSet,
+        link: ActiveValue::NotSet,
+        funding_source: ActiveValue::NotSet,
+        component_id: ActiveValue::NotSet,
+        ref_number,
+        season: ActiveValue::NotSet,
+        season_number: ActiveValue::NotSet,
+        tax_exempt: match update.tax_exempt {
+            Some(tax_exempt) => ActiveValue::Set(Some(tax_exempt)),
+            None => ActiveValue::NotSet,
+        },
+        payment_method: match update.payment_method {
+            Some(method) => ActiveValue::Set(Some(method)),
+            None => ActiveValue::NotSet,
+        },
+        requester: ActiveValue::NotSet,
+        currency: ActiveValue::NotSet,
+        original_unit_cost: ActiveValue::NotSet,
+        exchange_rate: ActiveValue::NotSet,
+        cart_id: ActiveValue::NotSet,
+        needed_by: ActiveValue::NotSet,
+        shipping_cost: match update.shipping_cost {
+            Some(shipping_cost) => ActiveValue::Set(Some(shipping_cost)),
+            None => ActiveValue::NotSet,
+        },
+        tax: match update.tax {
+            Some(tax) => ActiveValue::Set(Some(tax)),
+            None => ActiveValue::NotSet,
+        },
+        fees: match update.fees {
+            Some(fees) => ActiveValue::Set(Some(fees)),
+            None => ActiveValue::NotSet,
+        },
+        version: ActiveValue::NotSet,
+        competition: ActiveValue::NotSet,
+        received_count: match checked.received {
+            Some(received) => ActiveValue::Set(Some(received)),
+            None => ActiveValue::NotSet,
+        },
+    };
+
+    let Some(after) = order::update_versioned(tx, before.version, active_model).await? else {
+        return Ok(Err(policy::stale_version(&before)));
+    };
+    let mut diff = audit::diff(Some(&before), Some(&after));
+    if !same_status {
+        diff.extend(audit::change("status", status, update.status));
+    }
+    if checked.forced {
+        diff.extend(audit::change("forced", (), true));
+    }
+    if !update.discrepancies.is_empty() {
+        let kinds: Vec<_> = update.discrepancies.iter().map(|x| x.kind).collect();
+        diff.extend(audit::change("discrepancies", (), kinds));
+    }
+    if let Some(tracking) = &update.tracking {
+        let shipment = join_shipment(tx, tracking, update.carrier.clone(), actor.name()).await?;
+        if shipment_order::Entity::find_by_id(id)
+            .one(tx)
+            .await?
+            .is_none()
+        {
+            shipment_order::ActiveModel {
+                order_id: ActiveValue::Set(id),
+                shipment_id: ActiveValue::Set(shipment.id),
+            }
+            .insert(tx)
+            .await?;
+            diff.extend(audit::change("shipment", (), &shipment.tracking));
+        }
+    }
+    actor.record(tx, id, diff).await?;
+    Ok(Ok(()))
+}
+
+/// The shipment with tracking number `tracking`, added if it is new. A
+/// carrier given for a shipment that didn't have one is filled in.
+pub async fn join_shipment(
+    tx: &DatabaseTransaction,
+    tracking: &str,
+    carrier: Option<String>,
+    created_by: &str,
+) -> Result<shipment::Model, sea_orm::DbErr> {
+    let tracking = tracking.trim();
+    let carrier = non_blank(carrier);
+    let existing = shipment::Entity::find()
+        .filter(shipment::Column::Tracking.eq(tracking))
+        .one(tx)
+        .await?;
+    match existing {
+        Some(shipment) if shipment.carrier.is_none() && carrier.is_some() => {
+            shipment::ActiveModel {
+                id: ActiveValue::Unchanged(shipment.id),
+                carrier: ActiveValue::Set(carrier),
+                ..Default::default()
+            }
+            .update(tx)
+            .await
+        }
+        Some(shipment) => Ok(shipment),
+        None => {
+            shipment::ActiveModel {
+                id: ActiveValue::NotSet,
+                tracking: ActiveValue::Set(tracking.to_string()),
+                carrier: ActiveValue::Set(carrier),
+                status: ActiveValue::Set(order_status::Status::Shipped),
+                created_by: ActiveValue::Set(created_by.to_string()),
+                created: ActiveValue::Set(Local::now().naive_local()),
+            }
+            .insert(tx)
+            .await
+        }
+    }
+}
+
+/// A status update that passed every check, ready to be applied
+pub struct CheckedUpdate {
+    pub id: i64,
+    /// Whether only the order's details change, eg. its ref number
+    pub same_status: bool,
+    /// Whether an admin moved the order somewhere the transitions don't allow
+    forced: bool,
+    /// The message announcing the update
+    pub message: String,
+    /// How the order is listed in a digest, eg. `USR-2025-0042 Drive motors`
+    summary: String,
+    team: scheduler::Team,
+    expected: i64,
+    has_ref_number: bool,
+    /// The status the order had before
+    status: order_status::Status,
+    /// How many units have been received after the update, if some were
+    received: Option<i64>,
+}
+
+/// Checks that order `id` can be moved to `update.status` by `role`
+pub async fn check_update(
+    state: &UsrState,
+    role: policy::Role,
+    id: i64,
+    update: &UpdateOrder,
+) -> Result<CheckedUpdate, OrderError> {
+    if role < policy::Role::Lead {
+        return Err(OrderError::Forbidden(format!(
+            "{role} cannot update an order's status"
+        )));
+    }
+    if update.force && role < policy::Role::Admin {
+        return Err(OrderError::Forbidden(format!(
+            "{role} cannot force a status change"
+        )));
+    }
+    if update.status == order_status::Status::Cancelled {
+        return Err(invalid("Orders are cancelled through /del/order"));
+    }
+    if update.payment_method.is_some() && update.status != order_status::Status::Submitted {
+        return Err(invalid(
+            "Payment method is recorded when an order is submitted",
+        ));
+    }
+    let on_hold = update.status == order_status::Status::OnHold;
+    if on_hold && update.reason.as_ref().is_none_or(|x| x.trim().is_empty()) {
+        return Err(invalid("A reason is required to put an order on hold"));
+    }
+    if !on_hold && update.status != order_status::Status::Returned && update.reason.is_some() {
+        return Err(invalid(
+            "A reason is only given when putting an order on hold or returning it",
+        ));
+    }
+    if !update.discrepancies.is_empty() && update.status != order_status::Status::Delivered {
+        return Err(invalid(
+            "Discrepancies are recorded when an order is delivered",
+        ));
+    }
+    if update
+        .discrepancies
+        .iter()
+        .any(|x| x.kind == discrepancy::Kind::ShortShipment && x.received.is_none())
+    {
+        return Err(invalid("A short shipment needs how many were received"));
+    }
+    let partial = update.status == order_status::Status::PartiallyReceived;
+    if update.received.is_some() && !partial && update.status != order_status::Status::Delivered {
+        return Err(OrderError::InvalidField(
+            "received",
+            "Units are received when an order is partially received or delivered".to_string(),
+        ));
+    }
+    if partial && update.received.is_none() {
+        return Err(OrderError::InvalidField(
+            "received",
+            "How many units arrived is required to partially receive an order".to_string(),
+        ));
+    }
+    if update.received == Some(0) {
+        return Err(OrderError::InvalidField(
+            "received",
+            "Received must be positive".to_string(),
+        ));
+    }
+    let tracking = update.tracking.as_deref().map(str::trim);
+    if (tracking.is_some() || update.carrier.is_some())
+        && update.status != order_status::Status::Shipped
+    {
+        return Err(OrderError::InvalidField(
+            "tracking",
+            "A tracking number is recorded when an order is shipped".to_string(),
+        ));
+    }
+    if tracking == Some("") {
+        return Err(OrderError::InvalidField(
+            "tracking",
+            "Tracking number cannot be blank".to_string(),
+        ));
+    }
+    if update.carrier.is_some() && tracking.is_none() {
+        return Err(OrderError::InvalidField(
+            "carrier",
+            "A carrier is given along with a tracking number".to_string(),
+        ));
+    }
+    let charges = [
+        ("shipping_cost", update.shipping_cost),
+        ("tax", update.tax),
+        ("fees", update.fees),
+    ];
+    let charged = charges.iter().any(|(_, amount)| amount.is_some());
+    if charged && update.status != order_status::Status::Submitted {
+        return Err(invalid(
+            "Shipping, tax and fees are recorded when an order is submitted",
+        ));
+    }
+    check_charges(charges)?;
+
+    let current = match current::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(current)) => current,
+        Ok(None) => return Err(invalid("Order not found")),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return Err(OrderError::Internal);
+        }
+    };
+    if current.status == order_status::Status::InStorage
+        && update.status != order_status::Status::Returned
+    {
+        return Err(invalid("Order is already in storage"));
+    }
+    if current.status == order_status::Status::Returned {
+        return Err(invalid("Order has been returned to its vendor"));
+    }
+    if update.status == order_status::Status::Returned
+        && !matches!(
+            current.status,
+            order_status::Status::Delivered | order_status::Status::InStorage
+        )
+    {
+        return Err(invalid("Only orders that arrived can be returned"));
+    }
+    if current.status == order_status::Status::Cancelled {
+        return Err(OrderError::Conflict(
+            "Order has been cancelled, restore it first".to_string(),
+        ));
+    }
+    if update.version.is_some_and(|version| i64::from(version) != current.version) {
+        let (model, _) = current.into_parts();
+        return Err(OrderError::Conflict(policy::stale_version(&model)));
+    }
+    if let Some(tracking) = tracking {
+        let shipment = async {
+            let Some(member) = shipment_order::Entity::find_by_id(id)
+                .one(&state.db)
+                .await?
+            else {
+                return Ok(None);
+            };
+            shipment::Entity::find_by_id(member.shipment_id)
+                .one(&state.db)
+                .await
+        }
+        .await;
+        match shipment {
+            Ok(Some(shipment)) if shipment.tracking != tracking => {
+                return Err(OrderError::Conflict(format!(
+                    "Order is already in the shipment tracked as {}",
+                    shipment.tracking
+                )));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to find order's shipment: {e}");
+                return Err(OrderError::Internal);
+            }
+        }
+    }
+    if current.status == order_status::Status::OnHold
+        && update.status != order_status::Status::OnHold
+    {
+        return Err(OrderError::Conflict(format!(
+            "Order is on hold ({}) until a lead releases it",
+            current.hold_reason.unwrap_or_default()
+        )));
+    }
+    // Each delivery of an order that arrives in parts is a status of its own
+    let another_delivery = partial && current.status == order_status::Status::PartiallyReceived;
+    let mut same_status = false;
+    if current.status == update.status && !another_delivery {
+        if update.ref_number.is_none()
+            && update.tax_exempt.is_none()
+            && update.payment_method.is_none()
+            && update.discrepancies.is_empty()
+            && tracking.is_none()
+            && !charged
+        {
+            return Err(invalid("Order is already in that state"));
+        }
+        same_status = true;
+    }
+    let allowed = state.status_transitions.allowed(current.status);
+    let forced = !same_status && !another_delivery && !allowed.contains(&update.status);
+    if forced && !update.force {
+        let msg = match allowed {
+            [] => format!("Orders can't be moved out of {}", current.status),
+            _ => {
+                let names: Vec<_> = allowed.iter().map(ToString::to_string).collect();
+                format!(
+                    "Orders can't be moved from {} to {}, only to {}",
+                    current.status,
+                    update.status,
+                    names.join(", ")
+                )
+            }
+        };
+        return Err(OrderError::Transition(msg, allowed.to_vec()));
+    }
+    let (model, status) = current.into_parts();
+    if status == order_status::Status::New
+        && !matches!(
+            update.status,
+            order_status::Status::New | order_status::Status::OnHold
+        )
+        && !competition::waives_approval(state, &model)
+    {
+        check_approved(&state.db, id).await?;
+    }
+    if let Some(received) = update
+        .discrepancies
+        .iter()
+        .filter_map(|x| x.received)
+        .find(|received| i64::from(*received) > model.count)
+    {
+        return Err(OrderError::Invalid(format!(
+            "Received {received}, but only {} were ordered",
+            model.count
+        )));
+    }
+    let received = match update.received {
+        Some(received) => {
+            let received = model.received_count.unwrap_or_default() + i64::from(received);
+            if received > model.count {
+                return Err(OrderError::Invalid(format!(
+                    "Received {received}, but only {} were ordered",
+                    model.count
+                )));
+            }
+            if partial && received == model.count {
+                return Err(OrderError::InvalidField(
+                    "received",
+                    format!("All {received} have been received, mark the order Delivered"),
+                ));
+            }
+            Some(received)
+        }
+        None => None,
+    };
+    let mut message =
+        order_update_webhook_msg(&state.db, &model, update.status, Local::now().naive_local())
+            .await;
+    if let Some(reason) = &update.reason {
+        message.push_str(&format!("\n**Reason:** {reason}"));
+    }
+    if let Some(received) = received {
+        message.push_str(&format!("\n{received} of {} received", model.count));
+    }
+    for discrepancy in &update.discrepancies {
+        message.push_str(&format!("\n**{}:** ", discrepancy.kind));
+        if let Some(received) = discrepancy.received {
+            message.push_str(&format!("{received} of {} received ", model.count));
+        }
+        message.push_str(discrepancy.note.trim());
+    }
+    if let Some(tracking) = tracking {
+        message.push_str("\n**Tracking:** ");
+        if let Some(carrier) = non_blank(update.carrier.clone()) {
+            message.push_str(&format!("{carrier} "));
+        }
+        message.push_str(tracking);
+    }
+    if charged {
+        message.push_str(&charges_text(&order::Model {
+            shipping_cost: update.shipping_cost.or(model.shipping_cost),
+            tax: update.tax.or(model.tax),
+            fees: update.fees.or(model.fees),
+            ..model.clone()
+        }));
+    }
+    message.push_str(&permalink::line(&model));
+
+    Ok(CheckedUpdate {
+        id,
+        same_status,
+        forced,
+        message,
+        summary: format!("{} {}", model.number(), model.name),
+        team: model.team,
+        expected: model.count,
+        has_ref_number: model.ref_number.is_some(),
+        status,
+        received,
+    })
+}
+
+/// Applies a checked status update and announces it
+pub async fn update_status(
+    state: &'static UsrState,
+    update: &UpdateOrder,
+    checked: CheckedUpdate,
+    actor: audit::Actor,
+) -> Result<(), OrderError> {
+    let claim = update.status == order_status::Status::Submitted
+        && update.ref_number.is_none()
+        && !checked.has_ref_number;
+    // Another update can claim the same ref number first, in which case the
+    // unique index turns this one away and it tries again with the next one
+    let mut attempt = 1;
+    let result = loop {
+        match apply_update(state, checked.id, update, &checked, claim, &actor).await {
+            Err(e) if attempt < REF_NUMBER_ATTEMPTS && is_unique_violation(&e) => attempt += 1,
+            result => break result,
+        }
+    };
+
+    let CheckedUpdate {
+        id,
+        same_status,
+        message,
+        summary,
+        team,
+        ..
+    } = checked;
+    match result {
+        Ok(Ok(())) => {
+            if !same_status {
+                // The digest only lists the summary, so the mentions go after it
+                let len = message.len();
+                let mut message = notify_watchers(state, id, message).await;
+                message = if update.status == order_status::Status::InStorage {
+                    pickup::notify_requester(state, id, message).await
+                } else {
+                    mention_requester(state, id, message).await
+                };
+                let summary = format!("{summary}{}", &message[len..]);
+                state.notifier.send_digested(
+                    team,
+                    Topic::OrderUpdate,
+                    id as u32,
+                    update.status,
+                    summary,
+                    message,
+                );
+            }
+            backup_db(state);
+            orders_changed(state, Some(team)).await;
+            let kind = if same_status {
+                events::EventKind::Changed
+            } else {
+                events::EventKind::StatusUpdated
+            };
+            publish_current(state, kind, id).await;
+            Ok(())
+        }
+        Ok(Err(msg)) => Err(OrderError::Conflict(msg)),
+        Err(e) => {
+            error!("Failed to update order status: {e}");
+            Err(OrderError::Internal)
+        }
+    }
+}
+
+/// A shipment's status update that passed every check, ready to be applied
+pub struct CheckedShipment {
+    shipment: shipment::Model,
+    status: order_status::Status,
+    /// The orders that aren't in `status` yet
+    updates: Vec<(UpdateOrder, CheckedUpdate)>,
+    /// Each of those orders, as listed in the combined message
+    lines: Vec<String>,
+}
+
+/// Checks that every order in `shipment` can be moved to `status` by `role`,
+/// skipping those that are already there, eg. because one was unpacked early,
+/// and those that were returned or cancelled
+pub async fn check_shipment(
+    state: &UsrState,
+    role: policy::Role,
+    shipment: shipment::Model,
+    status: order_status::Status,
+) -> Result<CheckedShipment, OrderError> {
+    if !matches!(
+        status,
+        order_status::Status::Shipped
+            | order_status::Status::Delivered
+            | order_status::Status::InStorage
+    ) {
+        return Err(invalid(
+            "Shipments can only be marked Shipped, Delivered or InStorage",
+        ));
+    }
+    let orders = current::Entity::find()
+        .filter(
+            current::Column::Id.in_subquery(
+                sea_orm::sea_query::Query::select()
+                    .column(shipment_order::Column::OrderId)
+                    .from(shipment_order::Entity)
+                    .and_where(shipment_order::Column::ShipmentId.eq(shipment.id))
+                    .to_owned(),
+            ),
+        )
+        .all(&state.db)
+        .await;
+    let orders = match orders {
+        Ok(orders) => orders,
+        Err(e) => {
+            error!("Failed to find shipment's orders: {e}");
+            return Err(OrderError::Internal);
+        }
+    };
+    let mut updates = vec![];
+    let mut lines = vec![];
+    for current in orders {
+        if current.status == status
+            || matches!(
+                current.status,
+                order_status::Status::Returned | order_status::Status::Cancelled
+            )
+        {
+            continue;
+        }
+        let (model, _) = current.into_parts();
+        let update = UpdateOrder {
+            id: OrderRef::Id(model.id as u32),
+            status,
+            ref_number: None,
+            tax_exempt: None,
+            payment_method: None,
+            reason: None,
+            discrepancies: vec![],
+            tracking: None,
+            carrier: None,
+            shipping_cost: None,
+            tax: None,
+            fees: None,
+            version: None,
+            force: false,
+            received: None,
+        };
+        let checked = check_update(state, role, model.id, &update)
+            .await
+            .map_err(|e| e.about(model.number()))?;
+        lines.push(format!(
+            "\n- {} x {} for {} ({})",
+            model.count,
+            model.name,
+            model.team,
+            model.number()
+        ));
+        updates.push((update, checked));
+    }
+    if updates.is_empty() {
+        return Err(OrderError::Conflict(format!(
+            "Every order in the shipment is already {status}"
+        )));
+    }
+    Ok(CheckedShipment {
+        shipment,
+        status,
+        updates,
+        lines,
+    })
+}
+
+/// Moves every order in a checked shipment in one transaction, so that
+/// either all of them move or none do, then announces them together
+pub async fn update_shipment(
+    state: &'static UsrState,
+    checked: CheckedShipment,
+    actor: audit::Actor,
+) -> Result<(), OrderError> {
+    let CheckedShipment {
+        shipment,
+        status,
+        updates,
+        lines,
+    } = checked;
+    let result = async {
+        let tx = state.db.begin().await?;
+        for (update, checked) in &updates {
+            if let Err(msg) =
+                record_update(state, &tx, checked.id, update, checked, false, &actor).await?
+            {
+                return Ok(Err(msg));
+            }
+        }
+        shipment::ActiveModel {
+            id: ActiveValue::Unchanged(shipment.id),
+            status: ActiveValue::Set(status),
+            ..Default::default()
+        }
+        .update(&tx)
+        .await?;
+        tx.commit().await?;
+        Result::<_, sea_orm::DbErr>::Ok(Ok(()))
+    }
+    .await;
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(msg)) => return Err(OrderError::Conflict(msg)),
+        Err(e) => {
+            error!("Failed to update shipment: {e}");
+            return Err(OrderError::Internal);
+        }
+    }
+
+    let mut message = format!("***Shipment {status}***\n**Tracking:** ");
+    if let Some(carrier) = &shipment.carrier {
+        message.push_str(&format!("{carrier} "));
+    }
+    message.push_str(&shipment.tracking);
+    let mut teams = HashSet::new();
+    for ((_, checked), line) in updates.iter().zip(&lines) {
+        message.push_str(line);
+        // Watchers who chose direct messages hear about their own order, and
+        // the rest are mentioned after the order they watch
+        let len = checked.message.len();
+        let mut mentions = notify_watchers(state, checked.id, checked.message.clone())
+            .await
+            .split_off(len);
+        mentions = if status == order_status::Status::InStorage {
+            pickup::notify_requester(state, checked.id, mentions).await
+        } else {
+            mention_requester(state, checked.id, mentions).await
+        };
+        message.push_str(&mentions);
+        teams.insert(checked.team);
+    }
+    state
+        .notifier
+        .send(Topic::OrderUpdate, u32::MAX / 16 + shipment.id as u32, message);
+    backup_db(state);
+    for team in teams {
+        orders_changed(state, Some(team)).await;
+    }
+    for (_, checked) in &updates {
+        publish_current(state, events::EventKind::StatusUpdated, checked.id).await;
+    }
+    Ok(())
+}
+
+/// A cancellation that passed every check, ready to be carried out
+pub struct CheckedCancel {
+    pub id: i64,
+    /// The message announcing the cancellation
+    pub message: String,
+    model: order::Model,
+    status: order_status::Status,
+}
+
+/// Checks that `caller` can cancel order `id`. Only leads can `force` it,
+/// which cancels an order that has already been processed.
+pub async fn check_cancel(
+    state: &UsrState,
+    caller: &policy::Caller,
+    id: i64,
+    force: bool,
+) -> Result<CheckedCancel, OrderError> {
+    if force && caller.role < policy::Role::Lead {
+        return Err(OrderError::Forbidden(format!(
+            "{} cannot force cancel orders",
+            caller.role
+        )));
+    }
+    let current = match current::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(current)) => current,
+        Ok(None) => return Err(invalid("Order not found")),
+        Err(e) => {
+            error!("Failed to find order: {e}");
+            return Err(OrderError::Internal);
+        }
+    };
+    if current.status == order_status::Status::Cancelled {
+        return Err(OrderError::Conflict("Order is already cancelled".to_string()));
+    }
+    if !force && current.status != order_status::Status::New {
+        return Err(invalid("Order has already been processed"));
+    }
+    let (model, status) = current.into_parts();
+    policy::check_owner(caller, &model)?;
+    let message = format!(
+        "***Order Cancelled***\n**Order:** {}\n**Name:** {}\n**Count:** {}\n**Team:** {}",
+        model.number(),
+        model.name,
+        model.count,
+        model.team,
+    );
+    Ok(CheckedCancel {
+        id,
+        message,
+        model,
+        status,
+    })
+}
+
+/// Marks a checked order cancelled and announces it. The order and
+/// everything that hangs off of it are kept, so that it can be restored.
+pub async fn cancel(
+    state: &'static UsrState,
+    checked: CheckedCancel,
+    actor: audit::Actor,
+) -> Result<(), OrderError> {
+    let CheckedCancel {
+        id,
+        message,
+        model,
+        status,
+    } = checked;
+    let diff = audit::change("status", status, order_status::Status::Cancelled);
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                order_status::ActiveModel {
+                    order_id: ActiveValue::Set(id),
+                    instance_id: ActiveValue::NotSet,
+                    date: ActiveValue::Set(Local::now().naive_local()),
+                    status: ActiveValue::Set(order_status::Status::Cancelled),
+                    reason: ActiveValue::Set(None),
+                }
+                .insert(tx)
+                .await?;
+                actor.record(tx, id, diff).await
+            })
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to cancel order: {e}");
+        return Err(OrderError::Internal);
+    }
+
+    let message = notify_watchers(state, id, message).await;
+    let message = mention_requester(state, id, message).await;
+    state
+        .notifier
+        .send_for(model.team, Topic::NewOrder, id as u32, message);
+    backup_db(state);
+    orders_changed(state, Some(model.team)).await;
+    publish_event(
+        state,
+        events::EventKind::Cancelled,
+        &model,
+        order_status::Status::Cancelled,
+    )
+    .await;
+    Ok(())
+}
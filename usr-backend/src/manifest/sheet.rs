@@ -0,0 +1,230 @@
+use std::{collections::HashMap, io::Read};
+
+use anyhow::{anyhow, bail, Context};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use sea_orm::{
+    prelude::Decimal, ActiveModelTrait, ActiveValue, DatabaseConnection, DatabaseTransaction,
+    DbErr, EntityTrait, Iterable, TransactionTrait,
+};
+
+use crate::{money, scheduler::Team};
+
+use super::{funding, next_season_number, order, order_status, vendor};
+
+/// Headers of the old manifest sheet, with the spellings it used over the
+/// years. Matched case-insensitively.
+const COLUMNS: [(&str, &[&str]); 12] = [
+    ("timestamp", &["timestamp", "date", "requested"]),
+    ("name", &["item", "item name", "name"]),
+    ("vendor", &["vendor", "store"]),
+    ("link", &["link", "url"]),
+    ("count", &["quantity", "qty", "count"]),
+    ("unit_cost", &["unit price", "unit cost", "price"]),
+    ("team", &["team", "subteam"]),
+    ("reason", &["reason", "purpose", "notes"]),
+    ("store_in", &["store in", "location"]),
+    ("ordered", &["ordered", "ordered on"]),
+    ("received", &["received", "arrived"]),
+    ("stored", &["stored", "in storage"]),
+];
+
+const DATE_FORMATS: [&str; 4] = [
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+];
+
+pub fn parse_date(value: &str) -> Option<NaiveDateTime> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            ["%m/%d/%Y", "%Y-%m-%d"]
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+                .and_then(|date| date.and_hms_opt(12, 0, 0))
+        })
+}
+
+/// The sheet marks progress either with a date or a checkbox, so a checked
+/// box without a date falls back to `fallback`.
+fn parse_progress(value: &str, fallback: NaiveDateTime) -> Option<NaiveDateTime> {
+    match value.trim().to_lowercase().as_str() {
+        "" | "false" | "no" | "n" => None,
+        "true" | "yes" | "y" | "x" => Some(fallback),
+        value => Some(parse_date(value).unwrap_or(fallback)),
+    }
+}
+
+pub fn parse_team(value: &str) -> Option<Team> {
+    let value = value.trim().to_lowercase();
+    Team::iter().find(|team| {
+        let name = team.to_string().to_lowercase();
+        name == value || (value.len() >= 3 && name.starts_with(&value))
+    })
+}
+
+pub fn parse_cost(value: &str) -> Option<Decimal> {
+    value
+        .trim()
+        .trim_start_matches('$')
+        .replace(',', "")
+        .parse()
+        .ok()
+}
+
+/// Creates an order for every row of a CSV export of the old Google Sheets
+/// manifest, with statuses inferred from its Ordered, Received and Stored
+/// columns. Meant to be run once, since rows that were already imported are
+/// imported again.
+pub async fn import(db: &DatabaseConnection, csv: impl Read) -> anyhow::Result<usize> {
+    let mut reader = csv::Reader::from_reader(csv);
+    let headers = reader.headers()?.clone();
+    let mut columns = HashMap::<&str, usize>::new();
+    for (i, header) in headers.iter().enumerate() {
+        let header = header.trim().to_lowercase();
+        if let Some((field, _)) = COLUMNS
+            .iter()
+            .find(|(_, aliases)| aliases.contains(&header.as_str()))
+        {
+            columns.entry(field).or_insert(i);
+        }
+    }
+    for field in ["timestamp", "name", "count", "unit_cost", "team"] {
+        if !columns.contains_key(field) {
+            bail!("The sheet has no column for {field}");
+        }
+    }
+
+    let mut orders = vec![];
+    for (i, record) in reader.records().enumerate() {
+        // Counting the header, and starting from 1 like the sheet does
+        let row = i + 2;
+        let record = record?;
+        let get = |field: &str| {
+            columns
+                .get(field)
+                .and_then(|&i| record.get(i))
+                .unwrap_or_default()
+                .trim()
+        };
+        if get("name").is_empty() {
+            continue;
+        }
+
+        let placed = parse_date(get("timestamp"))
+            .ok_or_else(|| anyhow!("Row {row}: invalid timestamp {:?}", get("timestamp")))?;
+        let count = get("count")
+            .parse()
+            .with_context(|| format!("Row {row}: invalid quantity {:?}", get("count")))?;
+        let unit_cost = parse_cost(get("unit_cost"))
+            .ok_or_else(|| anyhow!("Row {row}: invalid unit cost {:?}", get("unit_cost")))?;
+        money::validate_unit_cost(unit_cost).map_err(|e| anyhow!("Row {row}: {e}"))?;
+        let team = parse_team(get("team"))
+            .ok_or_else(|| anyhow!("Row {row}: unknown team {:?}", get("team")))?;
+
+        let mut history = vec![(order_status::Status::New, placed)];
+        for (field, status) in [
+            ("ordered", order_status::Status::Submitted),
+            ("received", order_status::Status::Delivered),
+            ("stored", order_status::Status::InStorage),
+        ] {
+            let previous = history.last().unwrap().1;
+            if let Some(date) = parse_progress(get(field), previous) {
+                // Dates in the sheet were filled in by hand, so keep them in order
+                history.push((status, date.max(previous)));
+            }
+        }
+
+        orders.push(LegacyOrder {
+            name: get("name").to_string(),
+            count,
+            unit_cost,
+            store_in: get("store_in").to_string(),
+            team,
+            reason: get("reason").to_string(),
+            vendor: get("vendor").to_string(),
+            link: get("link").to_string(),
+            history,
+        });
+    }
+
+    let imported = orders.len();
+    db.transaction(|tx| {
+        Box::pin(async move {
+            for order in orders {
+                insert(tx, order).await?;
+            }
+            Result::<_, sea_orm::DbErr>::Ok(())
+        })
+    })
+    .await?;
+
+    Ok(imported)
+}
+
+/// An order bought before the manifest tracked orders, with the statuses it
+/// went through and when
+pub struct LegacyOrder {
+    pub name: String,
+    pub count: u32,
+    pub unit_cost: Decimal,
+    pub store_in: String,
+    pub team: Team,
+    pub reason: String,
+    pub vendor: String,
+    pub link: String,
+    /// Oldest first, starting with when it was placed
+    pub history: Vec<(order_status::Status, NaiveDateTime)>,
+}
+
+/// Adds a legacy order to the season it was placed in, returning its id
+pub async fn insert(tx: &DatabaseTransaction, order: LegacyOrder) -> Result<i64, DbErr> {
+    let season = order.history[0].1.year() as u16;
+    let model = order::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(order.name),
+        count: ActiveValue::Set(order.count.into()),
+        unit_cost: ActiveValue::Set(order.unit_cost),
+        store_in: ActiveValue::Set(order.store_in),
+        team: ActiveValue::Set(order.team),
+        reason: ActiveValue::Set(order.reason),
+        vendor: ActiveValue::Set(order.vendor),
+        link: ActiveValue::Set(order.link),
+        funding_source: ActiveValue::Set(funding::Source::default()),
+        component_id: ActiveValue::Set(None),
+        ref_number: ActiveValue::Set(None),
+        season: ActiveValue::Set(Some(season.into())),
+        season_number: ActiveValue::Set(Some(next_season_number(tx, season).await?)),
+        tax_exempt: ActiveValue::NotSet,
+        payment_method: ActiveValue::NotSet,
+        requester: ActiveValue::NotSet,
+        currency: ActiveValue::NotSet,
+        original_unit_cost: ActiveValue::NotSet,
+        exchange_rate: ActiveValue::NotSet,
+        cart_id: ActiveValue::NotSet,
+        needed_by: ActiveValue::NotSet,
+        shipping_cost: ActiveValue::NotSet,
+        tax: ActiveValue::NotSet,
+        fees: ActiveValue::NotSet,
+        version: ActiveValue::NotSet,
+        competition: ActiveValue::NotSet,
+        received_count: ActiveValue::NotSet,
+    }
+    .insert(tx)
+    .await?;
+    vendor::ensure(tx, &model.vendor).await?;
+    order_status::Entity::insert_many(order.history.into_iter().map(|(status, date)| {
+        order_status::ActiveModel {
+            order_id: ActiveValue::Set(model.id),
+            instance_id: ActiveValue::NotSet,
+            date: ActiveValue::Set(date),
+            status: ActiveValue::Set(status),
+            reason: ActiveValue::Set(None),
+        }
+    }))
+    .exec(tx)
+    .await?;
+    Ok(model.id)
+}
@@ -0,0 +1,27 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+use super::order_status::Status;
+
+/// A box that several orders arrive in together, under one tracking number,
+/// so that they can be marked delivered together
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "shipments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub tracking: String,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carrier: Option<String>,
+    /// The status its orders were last moved to together, `Shipped` until
+    /// then
+    pub status: Status,
+    pub created_by: String,
+    pub created: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
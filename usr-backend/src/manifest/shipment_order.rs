@@ -0,0 +1,16 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// Which shipment an order is coming in. Orders are in at most one.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "shipment_orders")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub order_id: i64,
+    pub shipment_id: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{prelude::Decimal, ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{money, scheduler::Team, UsrState};
+
+use super::{budget, current, current_season, order, order_status::Status};
+
+/// What a team's bought orders were estimated to cost and what was actually
+/// charged for them
+#[derive(Serialize, Default)]
+pub struct Spend {
+    /// Orders that have been submitted to their vendor
+    orders: u32,
+    /// Of those, the ones with shipping, tax or fees recorded
+    charged_orders: u32,
+    /// Unit cost times count, as the orders were placed
+    estimated: Decimal,
+    shipping: Decimal,
+    tax: Decimal,
+    fees: Decimal,
+    /// The estimate plus every recorded charge
+    actual: Decimal,
+}
+
+impl Spend {
+    fn add(&mut self, order: &order::Model) {
+        self.orders += 1;
+        if order.has_charges() {
+            self.charged_orders += 1;
+        }
+        self.estimated += money::subtotal(order.count, order.unit_cost);
+        self.shipping += order.shipping_cost.unwrap_or_default();
+        self.tax += order.tax.unwrap_or_default();
+        self.fees += order.fees.unwrap_or_default();
+        self.actual += order.total();
+    }
+}
+
+#[derive(Serialize)]
+struct SpendSummary {
+    season: u16,
+    teams: HashMap<Team, Spend>,
+    total: Spend,
+}
+
+#[derive(Deserialize)]
+pub struct SpendQuery {
+    /// The current season if left out
+    #[serde(default)]
+    season: Option<u16>,
+}
+
+/// Estimated against actual spending for each team in a season, counting
+/// orders once they have been submitted, even if they were cancelled after.
+/// Orders go to the team that placed them, whatever their cost splits.
+#[axum::debug_handler]
+pub async fn get_spend_summary(
+    State(state): State<&'static UsrState>,
+    Query(query): Query<SpendQuery>,
+) -> Response {
+    let season = query.season.unwrap_or_else(current_season);
+    let (orders, unspent) = tokio::join!(
+        current::Entity::find()
+            .filter(current::Column::Season.eq(season))
+            .filter(current::Column::Status.is_not_in([Status::New, Status::OnHold]))
+            .all(&state.db),
+        budget::unspent_cancelled(&state.db),
+    );
+    let (orders, unspent) = match (orders, unspent) {
+        (Ok(orders), Ok(unspent)) => (orders, unspent),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get orders: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let mut summary = SpendSummary {
+        season,
+        teams: HashMap::new(),
+        total: Spend::default(),
+    };
+    for model in orders.into_iter().filter(|model| !unspent.contains(&model.id)) {
+        let (order, _) = model.into_parts();
+        summary.teams.entry(order.team).or_default().add(&order);
+        summary.total.add(&order);
+    }
+    Json(summary).into_response()
+}
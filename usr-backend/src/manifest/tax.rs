@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use sea_orm::{prelude::Decimal, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::money;
+
+use super::{order, order_status};
+
+/// How often each vendor's orders used the tax exemption. Vendors with both
+/// exempt and taxed orders clearly accept the exemption, so their taxed
+/// orders are money that could have been saved.
+#[derive(Serialize)]
+pub struct VendorExemption {
+    vendor: String,
+    exempt_orders: u32,
+    taxed_orders: u32,
+    /// Orders whose tax exemption hasn't been recorded
+    unrecorded_orders: u32,
+    /// Subtotal of the taxed orders
+    taxed_spend: Decimal,
+    /// Whether the vendor accepts the exemption and it went unused
+    flagged: bool,
+}
+
+/// Every vendor that has accepted the exemption at least once, flagged
+/// vendors first.
+pub async fn vendor_exemptions(
+    db: &DatabaseConnection,
+) -> Result<Vec<VendorExemption>, sea_orm::DbErr> {
+    // Spellings that only differ by case or whitespace are the same vendor
+    let mut vendors = HashMap::<String, VendorExemption>::new();
+    for model in order::Entity::find().all(db).await? {
+        let vendor = model.vendor.trim();
+        if vendor.is_empty() {
+            continue;
+        }
+        let entry = vendors
+            .entry(vendor.to_lowercase())
+            .or_insert_with(|| VendorExemption {
+                vendor: vendor.to_string(),
+                exempt_orders: 0,
+                taxed_orders: 0,
+                unrecorded_orders: 0,
+                taxed_spend: Decimal::ZERO,
+                flagged: false,
+            });
+        match model.tax_exempt {
+            Some(true) => entry.exempt_orders += 1,
+            Some(false) => {
+                entry.taxed_orders += 1;
+                entry.taxed_spend += money::subtotal(model.count, model.unit_cost);
+            }
+            None => entry.unrecorded_orders += 1,
+        }
+    }
+
+    let mut out: Vec<_> = vendors
+        .into_values()
+        .filter(|x| x.exempt_orders > 0)
+        .map(|mut x| {
+            x.flagged = x.taxed_orders > 0;
+            x
+        })
+        .collect();
+    out.sort_by(|a, b| {
+        b.flagged
+            .cmp(&a.flagged)
+            .then(b.taxed_spend.cmp(&a.taxed_spend))
+            .then(a.vendor.cmp(&b.vendor))
+    });
+    Ok(out)
+}
+
+/// The finance office asks for exemption paperwork once a semester, where
+/// spring runs from January through June and fall from July through December.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Term {
+    Spring,
+    Fall,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Semester {
+    pub year: i32,
+    pub term: Term,
+}
+
+impl Semester {
+    pub fn containing(date: NaiveDate) -> Self {
+        Self {
+            year: date.year(),
+            term: if date.month() <= 6 {
+                Term::Spring
+            } else {
+                Term::Fall
+            },
+        }
+    }
+
+    /// The first moment of the semester, and of the one after it
+    fn range(&self) -> (NaiveDateTime, NaiveDateTime) {
+        let date = |year, month| {
+            NaiveDate::from_ymd_opt(year, month, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        };
+        match self.term {
+            Term::Spring => (date(self.year, 1), date(self.year, 7)),
+            Term::Fall => (date(self.year, 7), date(self.year + 1, 1)),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self.term {
+            Term::Spring => format!("{}-spring", self.year),
+            Term::Fall => format!("{}-fall", self.year),
+        }
+    }
+}
+
+/// The cover sheet for the exemption certificates of every tax-exempt order
+/// submitted in `semester`, grouped by vendor with a total for each.
+pub async fn bundle(db: &DatabaseConnection, semester: Semester) -> anyhow::Result<Vec<u8>> {
+    let (start, end) = semester.range();
+    let mut submitted = HashMap::<i64, NaiveDateTime>::new();
+    for model in order_status::Entity::find()
+        .filter(order_status::Column::Status.eq(order_status::Status::Submitted))
+        .all(db)
+        .await?
+    {
+        let date = submitted.entry(model.order_id).or_insert(model.date);
+        *date = (*date).min(model.date);
+    }
+    submitted.retain(|_, date| *date >= start && *date < end);
+
+    let mut orders = order::Entity::find()
+        .filter(order::Column::TaxExempt.eq(true))
+        .all(db)
+        .await?;
+    orders.retain(|model| submitted.contains_key(&model.id));
+    orders.sort_by(|a, b| {
+        a.vendor
+            .to_lowercase()
+            .cmp(&b.vendor.to_lowercase())
+            .then(submitted[&a.id].cmp(&submitted[&b.id]))
+    });
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record([
+        "vendor",
+        "order",
+        "ref_number",
+        "submitted",
+        "name",
+        "count",
+        "unit_cost",
+        "subtotal",
+        "team",
+    ])?;
+    let mut total = Decimal::ZERO;
+    let mut vendor_total = Decimal::ZERO;
+    for (i, model) in orders.iter().enumerate() {
+        let subtotal = money::subtotal(model.count, model.unit_cost);
+        vendor_total += subtotal;
+        total += subtotal;
+        writer.write_record([
+            model.vendor.clone(),
+            model.number(),
+            model.ref_number.map(|x| x.to_string()).unwrap_or_default(),
+            submitted[&model.id].date().to_string(),
+            model.name.clone(),
+            model.count.to_string(),
+            model.unit_cost.to_string(),
+            subtotal.to_string(),
+            model.team.to_string(),
+        ])?;
+        let last_of_vendor = orders
+            .get(i + 1)
+            .is_none_or(|next| next.vendor.to_lowercase() != model.vendor.to_lowercase());
+        if last_of_vendor {
+            let label = format!("Total for {}", model.vendor);
+            writer.write_record([
+                "",
+                "",
+                "",
+                "",
+                &label,
+                "",
+                "",
+                &vendor_total.to_string(),
+                "",
+            ])?;
+            vendor_total = Decimal::ZERO;
+        }
+    }
+    let label = format!("Total for {}", semester.name());
+    writer.write_record(["", "", "", "", &label, "", "", &total.to_string(), ""])?;
+
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
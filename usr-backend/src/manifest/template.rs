@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, QueryOrder};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{backup::backup_db, money, scheduler, UsrState};
+
+use super::{audit, funding, order, policy, service, PendingOrder};
+
+/// Something the team buys often enough, eg. the ESCs replaced every season,
+/// that it is kept ready to be ordered again without retyping it
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "order_templates")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// What orders placed from the template are called, unique among templates
+    pub name: String,
+    /// How many are usually ordered at once
+    pub count: i64,
+    pub unit_cost: Decimal,
+    pub store_in: String,
+    pub team: scheduler::Team,
+    pub reason: String,
+    pub vendor: String,
+    pub link: String,
+    pub funding_source: funding::Source,
+    pub created_by: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Deserialize)]
+pub struct SetTemplate {
+    /// The template to change, or a new one if left out
+    #[serde(default)]
+    id: Option<u32>,
+    name: String,
+    count: u32,
+    unit_cost: Decimal,
+    store_in: String,
+    team: scheduler::Team,
+    reason: String,
+    vendor: String,
+    link: String,
+    #[serde(default)]
+    funding_source: funding::Source,
+}
+
+/// Adds an order template, or changes one. Only leads can, so that the list
+/// stays short enough to be useful.
+#[axum::debug_handler]
+pub async fn set_template(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(set): Json<SetTemplate>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot set order templates", caller.role),
+        )
+            .into_response();
+    }
+    let name = set.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "A name is required").into_response();
+    }
+    if set.count == 0 {
+        return (StatusCode::BAD_REQUEST, "Count must be positive").into_response();
+    }
+    if let Err(msg) = money::validate_unit_cost(set.unit_cost) {
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+
+    let templates = match Entity::find().all(&state.db).await {
+        Ok(templates) => templates,
+        Err(e) => {
+            error!("Failed to find order templates: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    if templates
+        .iter()
+        .any(|template| Some(template.id) != set.id.map(i64::from) && template.name.eq_ignore_ascii_case(&name))
+    {
+        return (
+            StatusCode::CONFLICT,
+            format!("There is already a template named {name}"),
+        )
+            .into_response();
+    }
+
+    let mut active_model = ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(name),
+        count: ActiveValue::Set(set.count.into()),
+        unit_cost: ActiveValue::Set(set.unit_cost),
+        store_in: ActiveValue::Set(set.store_in),
+        team: ActiveValue::Set(set.team),
+        reason: ActiveValue::Set(set.reason),
+        vendor: ActiveValue::Set(set.vendor),
+        link: ActiveValue::Set(set.link),
+        funding_source: ActiveValue::Set(set.funding_source),
+        created_by: ActiveValue::NotSet,
+    };
+    let result = match set.id {
+        Some(id) => {
+            if !templates.iter().any(|template| template.id == i64::from(id)) {
+                return (StatusCode::BAD_REQUEST, "Template not found").into_response();
+            }
+            active_model.id = ActiveValue::Unchanged(id.into());
+            active_model.update(&state.db).await
+        }
+        None => {
+            active_model.created_by = ActiveValue::Set(
+                caller
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| caller.role.to_string()),
+            );
+            active_model.insert(&state.db).await
+        }
+    };
+
+    match result {
+        Ok(model) => {
+            backup_db(state);
+            Json(model).into_response()
+        }
+        Err(e) => {
+            error!("Failed to set order template: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteTemplate {
+    id: u32,
+}
+
+/// Removes an order template. Orders already placed from it stay.
+#[axum::debug_handler]
+pub async fn del_template(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Json(DeleteTemplate { id }): Json<DeleteTemplate>,
+) -> Response {
+    if caller.role < policy::Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot delete order templates", caller.role),
+        )
+            .into_response();
+    }
+    match Entity::delete_by_id(id).exec(&state.db).await {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Template not found").into_response()
+        }
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete order template: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Every order template, by name
+#[axum::debug_handler]
+pub async fn get_templates(State(state): State<&'static UsrState>) -> Response {
+    match Entity::find()
+        .order_by_asc(Column::Name)
+        .all(&state.db)
+        .await
+    {
+        Ok(models) => Json(models).into_response(),
+        Err(e) => {
+            error!("Failed to get order templates: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UseTemplate {
+    /// Overrides the template's count
+    #[serde(default)]
+    count: Option<u32>,
+    /// Overrides the template's team
+    #[serde(default)]
+    team: Option<scheduler::Team>,
+}
+
+/// Places a new order from a template, responding with it. It waits for
+/// approval like any other.
+#[axum::debug_handler]
+pub async fn use_template(
+    State(state): State<&'static UsrState>,
+    caller: policy::Caller,
+    Path(id): Path<u32>,
+    Query(UseTemplate { count, team }): Query<UseTemplate>,
+) -> Response {
+    let model = match Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(model)) => model,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Template not found").into_response(),
+        Err(e) => {
+            error!("Failed to find order template: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+    let mut pending_order = PendingOrder {
+        name: model.name,
+        count: count.unwrap_or(model.count as u32),
+        unit_cost: model.unit_cost,
+        store_in: model.store_in,
+        team: team.unwrap_or(model.team),
+        reason: model.reason,
+        vendor: model.vendor,
+        link: model.link,
+        funding_source: model.funding_source,
+        component_id: None,
+        requester: None,
+        currency: order::Currency::Usd,
+        exchange_rate: None,
+        fields: HashMap::new(),
+        needed_by: None,
+        // Templates are for things that are bought again and again
+        allow_duplicate: true,
+        shipping_cost: None,
+        tax: None,
+        fees: None,
+    };
+    pending_order.request_as(&caller);
+    let actor = audit::Actor::new(&caller, "/use/template");
+    match service::place_order(state, pending_order, None, Some(caller), actor).await {
+        Ok(model) => Json(model).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,306 @@
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    middleware, Router,
+};
+use chrono::Local;
+use sea_orm::{prelude::Decimal, ActiveModelTrait, ActiveValue, EntityTrait};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use crate::{auth, UsrState};
+
+use super::{funding, order, policy, vendor, wishlist};
+
+async fn state() -> &'static UsrState {
+    UsrState::for_tests("sqlite::memory:").await
+}
+
+/// The routes as `main` serves them, with the admin routes behind
+/// [`auth::require_lead`]
+fn app(state: &'static UsrState) -> Router {
+    super::router(state)
+        .merge(super::admin_router().layer(middleware::from_fn(auth::require_lead)))
+        .with_state(state)
+}
+
+async fn call(
+    state: &'static UsrState,
+    role: policy::Role,
+    method: Method,
+    uri: &str,
+    body: Value,
+) -> StatusCode {
+    call_by(state, Some("tester"), role, method, uri, body).await
+}
+
+async fn call_by(
+    state: &'static UsrState,
+    name: Option<&str>,
+    role: policy::Role,
+    method: Method,
+    uri: &str,
+    body: Value,
+) -> StatusCode {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .extension(policy::Caller {
+            name: name.map(str::to_string),
+            role,
+        })
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    app(state).oneshot(request).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn members_cannot_change_funding_vendors_or_inventory() {
+    let state = state().await;
+    let requests = [
+        (
+            Method::POST,
+            "/set/funding",
+            json!({ "source": "Sponsor", "allocated": "100" }),
+        ),
+        (Method::POST, "/set/vendor", json!({ "name": "McMaster" })),
+        (Method::DELETE, "/del/vendor", json!({ "name": "McMaster" })),
+        (
+            Method::POST,
+            "/set/vendorpolicy",
+            json!({ "vendor": "McMaster" }),
+        ),
+        (
+            Method::DELETE,
+            "/del/vendorpolicy",
+            json!({ "vendor": "McMaster" }),
+        ),
+        (
+            Method::POST,
+            "/transfer/inventory",
+            json!({ "from": "Shop", "to": "Trailer", "items": [] }),
+        ),
+    ];
+    for (method, uri, body) in requests {
+        assert_eq!(
+            call(state, policy::Role::Member, method, uri, body).await,
+            StatusCode::FORBIDDEN,
+            "{uri}"
+        );
+    }
+    assert!(funding::Entity::find()
+        .all(&state.db)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn leads_can_change_funding_and_vendors() {
+    let state = state().await;
+    let status = call(
+        state,
+        policy::Role::Lead,
+        Method::POST,
+        "/set/funding",
+        json!({ "source": "Sponsor", "allocated": "100" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let funding = funding::Entity::find_by_id(funding::Source::Sponsor)
+        .one(&state.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(funding.allocated, Decimal::from(100));
+
+    let status = call(
+        state,
+        policy::Role::Lead,
+        Method::POST,
+        "/set/vendor",
+        json!({ "name": "McMaster" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(vendor::Entity::find_by_id(vendor::key("McMaster"))
+        .one(&state.db)
+        .await
+        .unwrap()
+        .is_some());
+
+    let status = call(
+        state,
+        policy::Role::Lead,
+        Method::DELETE,
+        "/del/vendor",
+        json!({ "name": "McMaster" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Gets past the role check to the request's own problems
+    let status = call(
+        state,
+        policy::Role::Lead,
+        Method::POST,
+        "/transfer/inventory",
+        json!({ "from": " ", "to": "Trailer", "items": [] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn members_can_only_delete_their_own_wishlist_items() {
+    let state = state().await;
+    let item = wishlist::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set("Bearing".to_string()),
+        count: ActiveValue::Set(4),
+        unit_cost: ActiveValue::Set(Decimal::from(3)),
+        store_in: ActiveValue::Set("Shop".to_string()),
+        team: ActiveValue::Set(crate::scheduler::Team::Mechanical),
+        reason: ActiveValue::Set(String::new()),
+        vendor: ActiveValue::Set("McMaster".to_string()),
+        link: ActiveValue::Set(String::new()),
+        funding_source: ActiveValue::Set(funding::Source::DepartmentGrant),
+        date: ActiveValue::Set(Local::now().naive_local()),
+        requester: ActiveValue::Set(Some("alice".to_string())),
+    }
+    .insert(&state.db)
+    .await
+    .unwrap();
+    let body = json!({ "id": item.id });
+
+    let status = call_by(
+        state,
+        Some("bob"),
+        policy::Role::Member,
+        Method::DELETE,
+        "/del/wishlist",
+        body.clone(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let status = call_by(
+        state,
+        Some("alice"),
+        policy::Role::Member,
+        Method::DELETE,
+        "/del/wishlist",
+        body,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(wishlist::Entity::find_by_id(item.id)
+        .one(&state.db)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn members_cannot_resolve_discrepancies() {
+    let state = state().await;
+    let status = call(
+        state,
+        policy::Role::Member,
+        Method::POST,
+        "/resolve/discrepancy",
+        json!({ "id": 1, "resolution": "Refunded" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn quotas_count_against_the_signed_in_member() {
+    let state = state().await;
+    let status = call(
+        state,
+        policy::Role::Lead,
+        Method::POST,
+        "/set/quota",
+        json!({ "member": "alice", "orders": 1 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let order = |name: &str| {
+        json!({
+            "name": name,
+            "count": 1,
+            "unit_cost": "5",
+            "store_in": "Shop",
+            "team": "Mechanical",
+            "reason": "Spares",
+            "vendor": "McMaster",
+            "link": "",
+            "requester": "bob",
+        })
+    };
+
+    let requests = [
+        (Some("alice"), "Bearing", StatusCode::OK),
+        // Naming someone else as the requester doesn't get around the quota
+        (Some("alice"), "Shaft", StatusCode::FORBIDDEN),
+        // Nor does not signing in
+        (None, "Collar", StatusCode::FORBIDDEN),
+    ];
+    for (name, item, expected) in requests {
+        let status = call_by(
+            state,
+            name,
+            policy::Role::Member,
+            Method::POST,
+            "/new/order",
+            order(item),
+        )
+        .await;
+        assert_eq!(status, expected, "{item}");
+    }
+}
+
+#[tokio::test]
+async fn receiving_past_u32_max_is_invalid() {
+    let state = state().await;
+    let status = call(
+        state,
+        policy::Role::Lead,
+        Method::POST,
+        "/new/order",
+        json!({
+            "name": "Bearing",
+            "count": 2,
+            "unit_cost": "5",
+            "store_in": "Shop",
+            "team": "Mechanical",
+            "reason": "Spares",
+            "vendor": "McMaster",
+            "link": "",
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let model = order::Entity::find().one(&state.db).await.unwrap().unwrap();
+    order::ActiveModel {
+        id: ActiveValue::Unchanged(model.id),
+        received_count: ActiveValue::Set(Some(1)),
+        ..Default::default()
+    }
+    .update(&state.db)
+    .await
+    .unwrap();
+
+    let status = call(
+        state,
+        policy::Role::Lead,
+        Method::POST,
+        "/receive/order",
+        json!({ "id": model.id, "count": u32::MAX }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,321 @@
+use std::{collections::HashSet, time::Duration};
+
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::post,
+    Json, Router,
+};
+use parking_lot::Mutex;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::{
+    jobs::{self, Retry, Schedule},
+    UsrState,
+};
+
+use super::{
+    audit, order_status, policy,
+    service::{self, OrderError},
+    shipment,
+};
+
+/// How many tracking numbers are asked about in one request
+const BATCH: usize = 40;
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    Aftership,
+    SeventeenTrack,
+}
+
+impl Provider {
+    fn name(self) -> &'static str {
+        match self {
+            Provider::Aftership => "AfterShip",
+            Provider::SeventeenTrack => "17TRACK",
+        }
+    }
+}
+
+/// Settings for watching shipments for delivery. Shipments that are still
+/// `Shipped` are looked up with the provider every so often, and the
+/// provider's webhook can post updates to `/api/tracking/inbound?key=...`.
+#[derive(Deserialize)]
+pub struct TrackingConfig {
+    pub provider: Provider,
+    /// For polling the provider's API. Without one, only the webhook is used.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Shared with the provider so that nobody else can deliver shipments
+    pub webhook_key: String,
+    /// How often shipments are looked up, 0 to only use the webhook
+    #[serde(default = "default_poll_minutes")]
+    pub poll_minutes: u64,
+}
+
+fn default_poll_minutes() -> u64 {
+    60
+}
+
+impl TrackingConfig {
+    pub fn validate(&self, problems: &mut Vec<String>) {
+        if self.webhook_key.trim().is_empty() {
+            problems.push("tracking.webhook_key: must not be empty".to_string());
+        }
+        if self
+            .api_key
+            .as_ref()
+            .is_some_and(|key| key.trim().is_empty())
+        {
+            problems.push("tracking.api_key: must not be empty".to_string());
+        }
+    }
+}
+
+/// Watches shipments and marks them delivered when their carrier says so
+pub struct Tracking {
+    config: TrackingConfig,
+    client: reqwest::Client,
+    /// The tracking numbers already registered with the provider since the
+    /// server started
+    registered: Mutex<HashSet<String>>,
+}
+
+impl Tracking {
+    /// The provider isn't polled from sandboxes, like webhooks
+    pub fn new(mut config: TrackingConfig, sandbox: bool) -> Self {
+        if sandbox {
+            config.api_key = None;
+        }
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            registered: Mutex::default(),
+        }
+    }
+
+    /// The tracking numbers of `numbers` that have been delivered
+    async fn delivered(&self, api_key: &str, numbers: &[String]) -> anyhow::Result<Vec<String>> {
+        let unregistered: Vec<_> = {
+            let registered = self.registered.lock();
+            numbers
+                .iter()
+                .filter(|number| !registered.contains(*number))
+                .cloned()
+                .collect()
+        };
+        match self.config.provider {
+            Provider::Aftership => {
+                for number in &unregistered {
+                    // AfterShip answers numbers it already tracks with an
+                    // error, which is fine
+                    self.client
+                        .post("https://api.aftership.com/v4/trackings")
+                        .header("aftership-api-key", api_key)
+                        .json(&json!({ "tracking": { "tracking_number": number } }))
+                        .send()
+                        .await?;
+                }
+                let response: Value = self
+                    .client
+                    .get("https://api.aftership.com/v4/trackings")
+                    .header("aftership-api-key", api_key)
+                    .query(&[("tracking_numbers", numbers.join(","))])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                self.registered.lock().extend(unregistered);
+                let trackings = response["data"]["trackings"]
+                    .as_array()
+                    .context("AfterShip sent no trackings")?;
+                Ok(trackings
+                    .iter()
+                    .filter(|tracking| tracking["tag"] == "Delivered")
+                    .filter_map(|tracking| tracking["tracking_number"].as_str())
+                    .map(str::to_string)
+                    .collect())
+            }
+            Provider::SeventeenTrack => {
+                let body = |numbers: &[String]| {
+                    numbers
+                        .iter()
+                        .map(|number| json!({ "number": number }))
+                        .collect::<Vec<_>>()
+                };
+                if !unregistered.is_empty() {
+                    self.client
+                        .post("https://api.17track.net/track/v2.2/register")
+                        .header("17token", api_key)
+                        .json(&body(&unregistered))
+                        .send()
+                        .await?
+                        .error_for_status()?;
+                    self.registered.lock().extend(unregistered);
+                }
+                let response: Value = self
+                    .client
+                    .post("https://api.17track.net/track/v2.2/gettrackinfo")
+                    .header("17token", api_key)
+                    .json(&body(numbers))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let accepted = response["data"]["accepted"]
+                    .as_array()
+                    .context("17TRACK sent no tracking info")?;
+                Ok(accepted
+                    .iter()
+                    .filter(|info| info["track_info"]["latest_status"]["status"] == "Delivered")
+                    .filter_map(|info| info["number"].as_str())
+                    .map(str::to_string)
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Marks the shipment tracked as `tracking` delivered, if it is still on its
+/// way
+async fn deliver(
+    state: &'static UsrState,
+    provider: Provider,
+    tracking: &str,
+) -> anyhow::Result<()> {
+    let Some(shipment) = shipment::Entity::find()
+        .filter(shipment::Column::Tracking.eq(tracking))
+        .one(&state.db)
+        .await?
+    else {
+        return Ok(());
+    };
+    if shipment.status != order_status::Status::Shipped {
+        return Ok(());
+    }
+    let checked = match service::check_shipment(
+        state,
+        policy::Role::Admin,
+        shipment,
+        order_status::Status::Delivered,
+    )
+    .await
+    {
+        Ok(checked) => checked,
+        Err(OrderError::Internal) => anyhow::bail!("Failed to check shipment {tracking}"),
+        Err(e) => {
+            warn!("Couldn't mark shipment {tracking} delivered: {e:?}");
+            return Ok(());
+        }
+    };
+    let actor = audit::Actor::system(provider.name(), "/tracking");
+    match service::update_shipment(state, checked, actor).await {
+        Ok(()) => {
+            info!("{} delivered shipment {tracking}", provider.name());
+            Ok(())
+        }
+        Err(OrderError::Internal) => anyhow::bail!("Failed to deliver shipment {tracking}"),
+        Err(e) => {
+            warn!("Couldn't mark shipment {tracking} delivered: {e:?}");
+            Ok(())
+        }
+    }
+}
+
+/// Looks up every shipment that is still on its way
+async fn poll(state: &'static UsrState) -> anyhow::Result<()> {
+    let Some(tracking) = &state.tracking else {
+        return Ok(());
+    };
+    let Some(api_key) = &tracking.config.api_key else {
+        return Ok(());
+    };
+    let numbers: Vec<String> = shipment::Entity::find()
+        .filter(shipment::Column::Status.eq(order_status::Status::Shipped))
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|shipment| shipment.tracking)
+        .collect();
+    for numbers in numbers.chunks(BATCH) {
+        for number in tracking.delivered(api_key, numbers).await? {
+            deliver(state, tracking.config.provider, &number).await?;
+        }
+    }
+    Ok(())
+}
+
+pub fn spawn(state: &'static UsrState) {
+    let Some(tracking) = &state.tracking else {
+        return;
+    };
+    if tracking.config.api_key.is_none() || tracking.config.poll_minutes == 0 {
+        return;
+    }
+    jobs::spawn(
+        state,
+        "tracking",
+        Schedule::Every(Duration::from_secs(tracking.config.poll_minutes * 60)),
+        Retry::times(2, Duration::from_secs(5 * 60)),
+        poll,
+    );
+}
+
+#[derive(Deserialize)]
+struct InboundKey {
+    key: String,
+}
+
+/// The tracking number and whether it was delivered, from either AfterShip's
+/// `{msg: {tracking_number, tag}}` or 17TRACK's
+/// `{data: {number, track_info: {latest_status: {status}}}}`
+fn parse_update(body: &Value) -> Option<(&str, bool)> {
+    if let Some(number) = body["msg"]["tracking_number"].as_str() {
+        return Some((number, body["msg"]["tag"] == "Delivered"));
+    }
+    let number = body["data"]["number"].as_str()?;
+    Some((
+        number,
+        body["data"]["track_info"]["latest_status"]["status"] == "Delivered",
+    ))
+}
+
+#[axum::debug_handler]
+async fn inbound_update(
+    State(state): State<&'static UsrState>,
+    Query(InboundKey { key }): Query<InboundKey>,
+    Json(body): Json<Value>,
+) -> (StatusCode, &'static str) {
+    let Some(tracking) = &state.tracking else {
+        return (StatusCode::NOT_FOUND, "");
+    };
+    if key != tracking.config.webhook_key {
+        return (StatusCode::UNAUTHORIZED, "Invalid key");
+    }
+    let Some((number, delivered)) = parse_update(&body) else {
+        return (StatusCode::BAD_REQUEST, "Not a tracking update");
+    };
+    if !delivered {
+        return (StatusCode::OK, "");
+    }
+    match deliver(state, tracking.config.provider, number).await {
+        Ok(()) => (StatusCode::OK, ""),
+        // The provider retries updates that fail
+        Err(e) => {
+            warn!("{e:#}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
+        }
+    }
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new().route("/inbound", post(inbound_update))
+}
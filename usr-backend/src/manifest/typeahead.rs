@@ -0,0 +1,166 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use parking_lot::RwLock;
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde::Serialize;
+
+use super::{current, order_status, vendor};
+
+/// Matches containing less than this share of the query's trigrams are dropped
+const MIN_SIMILARITY: f64 = 0.5;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Order,
+    Inventory,
+    Vendor,
+}
+
+struct Entry {
+    value: String,
+    lower: String,
+    trigrams: HashSet<[char; 3]>,
+    source: Source,
+    uses: i64,
+    location: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Match {
+    value: String,
+    source: Source,
+    /// Orders with this name or from this vendor, or units in storage for
+    /// inventory
+    uses: i64,
+    location: Option<String>,
+}
+
+/// Only the start is padded, since the query is usually a partial word
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let padded: Vec<_> = "  ".chars().chain(text.chars()).collect();
+    padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// The share of the query's trigrams that are found in the candidate
+fn similarity(query: &HashSet<[char; 3]>, candidate: &HashSet<[char; 3]>) -> f64 {
+    if query.is_empty() {
+        return 0.0;
+    }
+    query.intersection(candidate).count() as f64 / query.len() as f64
+}
+
+/// An in-memory index of order names and stored items for autocomplete,
+/// rebuilt on the first search after the orders change.
+#[derive(Default)]
+pub struct Typeahead {
+    index: RwLock<Option<Arc<Vec<Entry>>>>,
+    generation: AtomicU64,
+}
+
+async fn build(db: &DatabaseConnection) -> Result<Vec<Entry>, sea_orm::DbErr> {
+    let (orders, vendors) = tokio::join!(
+        current::Entity::find().all(db),
+        vendor::Entity::find().all(db),
+    );
+
+    // Spellings that only differ by case or whitespace share an entry
+    let mut entries = HashMap::<(String, Source), Entry>::new();
+    let mut add = |value: &str, source, uses, location: Option<&str>| {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        let lower = value.to_lowercase();
+        let entry = entries
+            .entry((lower.clone(), source))
+            .or_insert_with(|| Entry {
+                value: value.to_string(),
+                trigrams: trigrams(&lower),
+                lower,
+                source,
+                uses: 0,
+                location: location.map(str::to_string),
+            });
+        entry.uses += uses;
+    };
+    // Recorded vendors are spelled the way the purchaser wants them
+    for model in vendors? {
+        add(&model.name, Source::Vendor, 0, None);
+    }
+    for model in orders? {
+        add(&model.name, Source::Order, 1, None);
+        add(&model.vendor, Source::Vendor, 1, None);
+        if model.status == order_status::Status::InStorage {
+            add(&model.name, Source::Inventory, model.count, Some(&model.store_in));
+        }
+    }
+    Ok(entries.into_values().collect())
+}
+
+impl Typeahead {
+    /// Drops the index so that the next search sees the latest orders
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        *self.index.write() = None;
+    }
+
+    async fn index(&self, db: &DatabaseConnection) -> Result<Arc<Vec<Entry>>, sea_orm::DbErr> {
+        if let Some(index) = self.index.read().clone() {
+            return Ok(index);
+        }
+        let generation = self.generation.load(Ordering::Acquire);
+        let index = Arc::new(build(db).await?);
+        // Orders that changed during the build would be missing from it
+        if self.generation.load(Ordering::Acquire) == generation {
+            *self.index.write() = Some(index.clone());
+        }
+        Ok(index)
+    }
+
+    /// Prefix matches come first, then the closest trigram matches, with ties
+    /// going to the most used.
+    pub async fn search(
+        &self,
+        db: &DatabaseConnection,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<Match>, sea_orm::DbErr> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+        let index = self.index(db).await?;
+        let query_trigrams = trigrams(&query);
+
+        let mut matches: Vec<_> = index
+            .iter()
+            .filter_map(|entry| {
+                let score = if entry.lower.starts_with(&query) {
+                    2.0
+                } else {
+                    similarity(&query_trigrams, &entry.trigrams)
+                };
+                (score >= MIN_SIMILARITY).then_some((score, entry))
+            })
+            .collect();
+        matches.sort_by(|(a, x), (b, y)| b.total_cmp(a).then(y.uses.cmp(&x.uses)));
+
+        Ok(matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, entry)| Match {
+                value: entry.value.clone(),
+                source: entry.source,
+                uses: entry.uses,
+                location: entry.location.clone(),
+            })
+            .collect())
+    }
+}
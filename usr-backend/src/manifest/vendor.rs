@@ -0,0 +1,99 @@
+use sea_orm::{entity::prelude::*, sea_query::OnConflict, ActiveValue};
+use serde::Serialize;
+
+/// What the purchaser needs to know when ordering from a vendor
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "vendors")]
+pub struct Model {
+    /// The vendor's name, trimmed and lowercased, so that orders spelling it
+    /// differently still find it
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[serde(skip)]
+    pub key: String,
+    pub name: String,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub website: Option<String>,
+    /// How long shipping usually takes, eg. `2 days` or `ships from China`
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shipping_estimate: Option<String>,
+    /// How to get the vendor to waive sales tax, eg. which form it wants
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax_exempt_notes: Option<String>,
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shipping_account: Option<String>,
+    /// Which account to log in with, never the password itself
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub login_hint: Option<String>,
+    /// The smallest subtotal the vendor will take an order for
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum_order: Option<Decimal>,
+    /// The subtotal at which shipping becomes free
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub free_shipping_threshold: Option<Decimal>,
+    /// Who to ask for a return authorization, eg. an email address
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rma_contact: Option<String>,
+    /// What the vendor wants done to send something back, eg. which form to
+    /// fill out and whether the original packaging is needed
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rma_instructions: Option<String>,
+    /// How many days after delivery the vendor still takes returns
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_window_days: Option<i64>,
+    pub notes: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// The vendor `name` is stored under, under any spelling
+pub async fn find(db: &impl ConnectionTrait, name: &str) -> Result<Option<Model>, DbErr> {
+    Entity::find_by_id(key(name)).one(db).await
+}
+
+/// The key `name` is stored under
+pub fn key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Adds a vendor called `name` with nothing else known about it, unless it is
+/// already there under any spelling
+pub async fn ensure(db: &impl ConnectionTrait, name: &str) -> Result<(), DbErr> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(());
+    }
+    Entity::insert(ActiveModel {
+        key: ActiveValue::Set(key(name)),
+        name: ActiveValue::Set(name.to_string()),
+        website: ActiveValue::Set(None),
+        shipping_estimate: ActiveValue::Set(None),
+        tax_exempt_notes: ActiveValue::Set(None),
+        shipping_account: ActiveValue::Set(None),
+        login_hint: ActiveValue::Set(None),
+        minimum_order: ActiveValue::Set(None),
+        free_shipping_threshold: ActiveValue::Set(None),
+        notes: ActiveValue::Set(String::new()),
+        rma_contact: ActiveValue::Set(None),
+        rma_instructions: ActiveValue::Set(None),
+        return_window_days: ActiveValue::Set(None),
+    })
+    .on_conflict(OnConflict::column(Column::Key).do_nothing().to_owned())
+    .do_nothing()
+    .exec(db)
+    .await?;
+    Ok(())
+}
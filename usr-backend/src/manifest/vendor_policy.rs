@@ -0,0 +1,42 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Whether the team should buy from a vendor
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "vendor_policies")]
+pub struct Model {
+    /// Matched against orders the same way as the vendor's details
+    #[sea_orm(primary_key, auto_increment = false)]
+    #[serde(skip)]
+    pub key: String,
+    pub vendor: String,
+    pub standing: Standing,
+    /// Why the vendor is preferred or blocked, shown to whoever orders from it
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Whether orders from a blocked vendor are turned away, rather than
+    /// placed with a warning
+    pub reject: bool,
+    pub since: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Vendors without a policy are allowed
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Standing {
+    /// Suggested before other vendors
+    #[sea_orm(string_value = "P")]
+    Preferred,
+    #[sea_orm(string_value = "A")]
+    Allowed,
+    /// Orders from it are warned about or turned away, and it isn't suggested
+    #[sea_orm(string_value = "B")]
+    Blocked,
+}
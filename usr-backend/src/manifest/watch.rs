@@ -0,0 +1,33 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Someone following an order, who is told each time it moves along
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "order_watchers")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub order_id: i64,
+    /// The member's name, as their Discord id is known by
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    pub channel: Channel,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// How a watcher prefers to be told
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize, Default)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Channel {
+    /// A direct message from the bot, or a mention if one can't be sent
+    #[default]
+    #[sea_orm(string_value = "D")]
+    Dm,
+    /// A mention on the order updates webhook's message
+    #[sea_orm(string_value = "M")]
+    Mention,
+}
@@ -0,0 +1,149 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{Datelike, Days, Local, NaiveDateTime, NaiveTime, TimeDelta};
+use sea_orm::{
+    prelude::Decimal, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+};
+
+use crate::{
+    jobs::{self, Retry, Schedule},
+    money,
+    notify::Topic,
+    scheduler::Team,
+    UsrState,
+};
+
+use super::{budget, current, order_status};
+
+/// Characters in a budget bar
+const BAR_WIDTH: i64 = 10;
+
+/// The next Monday at 9 AM strictly after `now`
+fn next_post(now: NaiveDateTime) -> NaiveDateTime {
+    let monday = now.date() - Days::new(now.weekday().num_days_from_monday() as u64);
+    let post = monday.and_time(NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+    if post > now {
+        post
+    } else {
+        post + Days::new(7)
+    }
+}
+
+/// Renders how much of `budget` is left as a bar of block characters
+fn budget_bar(spent: Decimal, budget: Decimal) -> String {
+    let remaining = budget - spent;
+    let filled = if budget.is_zero() {
+        0
+    } else {
+        let filled = (remaining * Decimal::from(BAR_WIDTH) / budget)
+            .round()
+            .clamp(Decimal::ZERO, Decimal::from(BAR_WIDTH));
+        i64::try_from(filled).unwrap_or_default()
+    };
+    let bar = format!(
+        "`{}{}`",
+        "█".repeat(filled as usize),
+        "░".repeat((BAR_WIDTH - filled) as usize)
+    );
+    if remaining.is_sign_negative() {
+        format!(
+            "{bar} {} over {}",
+            money::dollars(-remaining),
+            money::dollars(budget)
+        )
+    } else {
+        format!(
+            "{bar} {} of {} left",
+            money::dollars(remaining),
+            money::dollars(budget)
+        )
+    }
+}
+
+/// The weekly summary of spending per team, with the budget left for teams
+/// that have one, followed by every order that is on hold.
+async fn summary(
+    db: &DatabaseConnection,
+    budgets: &HashMap<Team, Decimal>,
+    now: NaiveDateTime,
+) -> Result<String, sea_orm::DbErr> {
+    let since = now - TimeDelta::days(7);
+    // Spending counts against the open budget period, or against the
+    // configured budgets until the first period is closed
+    let (start, budgets) = match budget::open_period(db).await? {
+        Some((period, allocations)) => (period.start, allocations),
+        None => (NaiveDateTime::MIN, budgets.clone()),
+    };
+    let (weekly, total) = tokio::join!(
+        budget::spend_between(db, since, None),
+        budget::spend_between(db, start, None)
+    );
+    let (weekly, total) = (weekly?, total?);
+
+    let mut teams: Vec<_> = weekly
+        .keys()
+        .chain(total.keys())
+        .chain(budgets.keys())
+        .copied()
+        .collect();
+    teams.sort_by_key(|team| team.to_string());
+    teams.dedup();
+
+    let mut msg = format!("**Weekly Spending** ({} to {})", since.date(), now.date());
+    for team in teams {
+        let week = weekly.get(&team).copied().unwrap_or_default();
+        let spent = total.get(&team).copied().unwrap_or_default();
+        msg.push_str(&format!(
+            "\n**{team}:** {} this week, {} total",
+            money::dollars(week),
+            money::dollars(spent)
+        ));
+        if let Some(budget) = budgets.get(&team) {
+            msg.push('\n');
+            msg.push_str(&budget_bar(spent, *budget));
+        }
+    }
+
+    let held = current::Entity::find()
+        .filter(current::Column::Status.eq(order_status::Status::OnHold))
+        .order_by_asc(current::Column::StatusDate)
+        .all(db)
+        .await?;
+    if !held.is_empty() {
+        msg.push_str("\n\n**On Hold**");
+        for model in held {
+            let (reason, since) = (model.hold_reason.clone(), model.status_date.date());
+            let (model, _) = model.into_parts();
+            msg.push_str(&format!(
+                "\n{} {} ({}) since {since}: {}",
+                model.number(),
+                model.name,
+                model.team,
+                reason.unwrap_or_default()
+            ));
+        }
+    }
+    Ok(msg)
+}
+
+/// Posts the weekly spending summary to the spending webhook every Monday
+/// morning.
+pub fn spawn(state: &'static UsrState) {
+    if !state.notifier.routes(Topic::Spending) {
+        return;
+    }
+    jobs::spawn(
+        state,
+        "weekly_spending",
+        Schedule::At(|_, now| next_post(now)),
+        Retry::times(3, Duration::from_secs(10 * 60)),
+        |state| async move {
+            let now = Local::now().naive_local();
+            let msg = summary(&state.db, &state.team_budgets, now).await?;
+            state
+                .notifier
+                .send(Topic::Spending, now.date().iso_week().week(), msg);
+            Ok(())
+        },
+    );
+}
@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+use crate::scheduler;
+
+use super::funding;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "wishlist")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    pub count: i64,
+    pub unit_cost: Decimal,
+    pub store_in: String,
+    pub team: scheduler::Team,
+    pub reason: String,
+    pub vendor: String,
+    pub link: String,
+    #[sea_orm(default_value = "D")]
+    pub funding_source: funding::Source,
+    pub date: DateTime,
+    /// Who wishlisted it, who can delete it along with the leads
+    pub requester: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
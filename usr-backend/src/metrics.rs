@@ -0,0 +1,124 @@
+use std::{collections::BTreeMap, fmt::Write, time::Duration};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+
+use crate::UsrState;
+
+/// Upper bounds of the histogram buckets, in seconds
+const BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+#[derive(Default)]
+struct Histogram {
+    /// Non-cumulative counts for each bucket in `BUCKETS`
+    counts: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|&bound| secs <= bound) {
+            self.counts[i] += 1;
+        }
+        self.count += 1;
+        self.sum += secs;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(self.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{{labels}le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{{labels}le=\"+Inf\"}} {}", self.count);
+        let labels = match labels.trim_end_matches(',') {
+            "" => String::new(),
+            labels => format!("{{{labels}}}"),
+        };
+        let _ = writeln!(out, "{name}_sum{labels} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{labels} {}", self.count);
+    }
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    /// Keyed by method and matched route
+    requests: Mutex<BTreeMap<(String, String), Histogram>>,
+    backups: Mutex<Histogram>,
+}
+
+impl Metrics {
+    pub fn observe_backup(&self, duration: Duration) {
+        self.backups.lock().observe(duration);
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP http_request_duration_seconds Time taken to handle a request\n");
+        out.push_str("# TYPE http_request_duration_seconds histogram\n");
+        for ((method, route), histogram) in self.requests.lock().iter() {
+            histogram.render(
+                &mut out,
+                "http_request_duration_seconds",
+                &format!("method=\"{method}\",route=\"{route}\","),
+            );
+        }
+
+        out.push_str(
+            "# HELP backup_duration_seconds Time taken to copy, commit and push a backup\n",
+        );
+        out.push_str("# TYPE backup_duration_seconds histogram\n");
+        self.backups
+            .lock()
+            .render(&mut out, "backup_duration_seconds", "");
+
+        out
+    }
+}
+
+/// Records how long each request took against the route it matched.
+pub async fn track_latency(
+    State(state): State<&'static UsrState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    // Unmatched requests are lumped together so that scanners can't create
+    // a histogram per path
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+
+    let start = std::time::Instant::now();
+    let response = next.run(request).await;
+    state
+        .metrics
+        .requests
+        .lock()
+        .entry((method, route))
+        .or_default()
+        .observe(start.elapsed());
+
+    response
+}
+
+#[axum::debug_handler]
+pub async fn get_metrics(State(state): State<&'static UsrState>) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+        .into_response()
+}
@@ -0,0 +1,108 @@
+use sea_orm_migration::{MigrationTrait, MigratorTrait};
+
+mod m20261015_000001_baseline;
+mod m20261015_000002_order_approvals;
+mod m20261015_000003_order_carts;
+mod m20261015_000004_role_grants;
+mod m20261015_000005_webhook_outbox;
+mod m20261015_000006_audit_log;
+mod m20261015_000007_vendor_policies;
+mod m20261015_000008_order_attachments;
+mod m20261015_000009_shipments;
+mod m20261015_000010_vendor_details;
+mod m20261015_000011_custom_fields;
+mod m20261015_000012_member_quotas;
+mod m20261015_000013_order_needed_by;
+mod m20261015_000014_job_runs;
+mod m20261015_000015_order_comments;
+mod m20261015_000016_order_charges;
+mod m20261015_000017_order_versions;
+mod m20261015_000018_order_pickups;
+mod m20261015_000019_vendor_returns;
+mod m20261015_000020_competition_orders;
+mod m20261015_000021_recurring_orders;
+mod m20261015_000022_seasons;
+mod m20261015_000023_order_received_count;
+mod m20261015_000024_reimbursements;
+mod m20261015_000025_team_webhooks;
+mod m20261015_000026_idempotency_keys;
+mod m20261015_000027_locations;
+mod m20261016_000028_order_templates;
+mod m20261016_000029_order_revisions;
+mod m20261016_000031_unversioned_changes;
+mod m20261016_000032_webhook_outbox_embeds;
+mod m20261016_000033_wishlist_requesters;
+mod m20261016_000034_unsigned_bigints;
+mod online;
+
+pub use online::{migrate, spawn_heartbeat, unapplied};
+
+/// Brings the database up to this version's schema at startup, one
+/// migration at a time. Migrations only ever add to the schema, so nothing
+/// that is stored is lost. Run it through [`migrate`],
+/// which keeps instances from migrating at the same time and holds back the
+/// ones that would break an older instance that is still serving.
+///
+/// Each change to an entity needs a migration here, appended after the
+/// others and named for the day it was written, or startup will report the
+/// mismatch. New tables should be made with
+/// [`crate::schema::create_table_from`] and new columns with
+/// [`online::add_column`], since on Postgres `u32` columns have to be
+/// range-checked `bigint`s.
+pub struct Migrator;
+
+impl MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+        vec![
+            Box::new(m20261015_000001_baseline::Migration),
+            Box::new(m20261015_000002_order_approvals::Migration),
+            Box::new(m20261015_000003_order_carts::Migration),
+            Box::new(m20261015_000004_role_grants::Migration),
+            Box::new(m20261015_000005_webhook_outbox::Migration),
+            Box::new(m20261015_000006_audit_log::Migration),
+            Box::new(m20261015_000007_vendor_policies::Migration),
+            Box::new(m20261015_000008_order_attachments::Migration),
+            Box::new(m20261015_000009_shipments::Migration),
+            Box::new(m20261015_000010_vendor_details::Migration),
+            Box::new(m20261015_000011_custom_fields::Migration),
+            Box::new(m20261015_000012_member_quotas::Migration),
+            Box::new(m20261015_000013_order_needed_by::Migration),
+            Box::new(m20261015_000014_job_runs::Migration),
+            Box::new(m20261015_000015_order_comments::Migration),
+            Box::new(m20261015_000016_order_charges::Migration),
+            Box::new(m20261015_000017_order_versions::Migration),
+            Box::new(m20261015_000018_order_pickups::Migration),
+            Box::new(m20261015_000019_vendor_returns::Migration),
+            Box::new(m20261015_000020_competition_orders::Migration),
+            Box::new(m20261015_000021_recurring_orders::Migration),
+            Box::new(m20261015_000022_seasons::Migration),
+            Box::new(m20261015_000023_order_received_count::Migration),
+            Box::new(m20261015_000024_reimbursements::Migration),
+            Box::new(m20261015_000025_team_webhooks::Migration),
+            Box::new(m20261015_000026_idempotency_keys::Migration),
+            Box::new(m20261015_000027_locations::Migration),
+            Box::new(m20261016_000028_order_templates::Migration),
+            Box::new(m20261016_000029_order_revisions::Migration),
+            Box::new(m20261016_000031_unversioned_changes::Migration),
+            Box::new(m20261016_000032_webhook_outbox_embeds::Migration),
+            Box::new(m20261016_000033_wishlist_requesters::Migration),
+            Box::new(m20261016_000034_unsigned_bigints::Migration),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UsrState;
+
+    #[tokio::test]
+    async fn new_databases_match_the_entities() {
+        // Checks the schema against the entities, like startup does
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        assert!(unapplied(&state.db).await.unwrap().is_empty());
+        // Migrating again finds nothing left to do
+        migrate(&state.db).await.unwrap();
+        assert!(crate::verify_schema(&state.db).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+use sea_orm_migration::prelude::*;
+
+/// The tables from before there was anything to migrate. A database from
+/// back then already has them, so they are only created if they are missing.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Teams::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Teams::Name).string().not_null())
+                    .col(ColumnDef::new(Teams::Team).string_len(1).not_null())
+                    .primary_key(Index::create().col(Teams::Name).col(Teams::Team))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Availabilities::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Availabilities::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(Availabilities::Time)
+                            .small_unsigned()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(Availabilities::Name)
+                            .col(Availabilities::Time),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Orders::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Orders::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Orders::Name).string().not_null())
+                    .col(ColumnDef::new(Orders::Count).integer().not_null())
+                    .col(ColumnDef::new(Orders::UnitCost).decimal().not_null())
+                    .col(ColumnDef::new(Orders::StoreIn).string().not_null())
+                    .col(ColumnDef::new(Orders::Team).string_len(1).not_null())
+                    .col(ColumnDef::new(Orders::Reason).string().not_null())
+                    .col(ColumnDef::new(Orders::Vendor).string().not_null())
+                    .col(ColumnDef::new(Orders::Link).string().not_null())
+                    .col(ColumnDef::new(Orders::RefNumber).integer().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderStatus::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderStatus::InstanceId)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderStatus::OrderId).integer().not_null())
+                    .col(ColumnDef::new(OrderStatus::Date).date_time().not_null())
+                    .col(ColumnDef::new(OrderStatus::Status).string_len(1).not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Attendance::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Attendance::Uid).integer().not_null())
+                    .col(ColumnDef::new(Attendance::Date).date_time().not_null())
+                    .primary_key(Index::create().col(Attendance::Uid).col(Attendance::Date))
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Teams {
+    Table,
+    Name,
+    Team,
+}
+
+#[derive(DeriveIden)]
+enum Availabilities {
+    Table,
+    Name,
+    Time,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+    Name,
+    Count,
+    UnitCost,
+    StoreIn,
+    Team,
+    Reason,
+    Vendor,
+    Link,
+    RefNumber,
+}
+
+#[derive(DeriveIden)]
+enum OrderStatus {
+    Table,
+    InstanceId,
+    OrderId,
+    Date,
+    Status,
+}
+
+#[derive(DeriveIden)]
+enum Attendance {
+    Table,
+    Uid,
+    Date,
+}
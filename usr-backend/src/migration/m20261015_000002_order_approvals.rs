@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+use crate::schema;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        schema::create_table_from(
+            manager.get_connection(),
+            Table::create()
+                .table(OrderApprovals::Table)
+                .if_not_exists()
+                .col(
+                    ColumnDef::new(OrderApprovals::OrderId)
+                        .unsigned()
+                        .not_null()
+                        .primary_key(),
+                )
+                .col(
+                    ColumnDef::new(OrderApprovals::Decision)
+                        .string_len(1)
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(OrderApprovals::DecidedBy)
+                        .string()
+                        .not_null(),
+                )
+                .col(ColumnDef::new(OrderApprovals::Reason).string().null())
+                .col(ColumnDef::new(OrderApprovals::Date).date_time().not_null())
+                .to_owned(),
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderApprovals {
+    Table,
+    OrderId,
+    Decision,
+    DecidedBy,
+    Reason,
+    Date,
+}
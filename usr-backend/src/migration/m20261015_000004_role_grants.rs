@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+use crate::schema;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        schema::create_table_from(
+            manager.get_connection(),
+            Table::create()
+                .table(RoleGrants::Table)
+                .if_not_exists()
+                .col(
+                    ColumnDef::new(RoleGrants::Id)
+                        .unsigned()
+                        .not_null()
+                        .auto_increment()
+                        .primary_key(),
+                )
+                .col(ColumnDef::new(RoleGrants::UserId).unsigned().not_null())
+                .col(ColumnDef::new(RoleGrants::Role).string_len(1).not_null())
+                .col(ColumnDef::new(RoleGrants::Reason).string().not_null())
+                .col(ColumnDef::new(RoleGrants::GrantedBy).string().not_null())
+                .col(ColumnDef::new(RoleGrants::Granted).date_time().not_null())
+                .col(ColumnDef::new(RoleGrants::Expires).date_time().not_null())
+                .col(ColumnDef::new(RoleGrants::Revoked).date_time().null())
+                .col(ColumnDef::new(RoleGrants::RevokedBy).string().null())
+                .to_owned(),
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum RoleGrants {
+    Table,
+    Id,
+    UserId,
+    Role,
+    Reason,
+    GrantedBy,
+    Granted,
+    Expires,
+    Revoked,
+    RevokedBy,
+}
@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+use crate::schema;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        schema::create_table_from(
+            manager.get_connection(),
+            Table::create()
+                .table(WebhookOutbox::Table)
+                .if_not_exists()
+                .col(
+                    ColumnDef::new(WebhookOutbox::Id)
+                        .unsigned()
+                        .not_null()
+                        .auto_increment()
+                        .primary_key(),
+                )
+                .col(
+                    ColumnDef::new(WebhookOutbox::Destination)
+                        .string()
+                        .not_null(),
+                )
+                .col(ColumnDef::new(WebhookOutbox::Ids).string().not_null())
+                .col(ColumnDef::new(WebhookOutbox::Content).string().not_null())
+                .col(
+                    ColumnDef::new(WebhookOutbox::Attempts)
+                        .unsigned()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(WebhookOutbox::NextAttempt)
+                        .date_time()
+                        .null(),
+                )
+                .col(ColumnDef::new(WebhookOutbox::LastError).string().null())
+                .col(
+                    ColumnDef::new(WebhookOutbox::Created)
+                        .date_time()
+                        .not_null(),
+                )
+                .to_owned(),
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum WebhookOutbox {
+    Table,
+    Id,
+    Destination,
+    Ids,
+    Content,
+    Attempts,
+    NextAttempt,
+    LastError,
+    Created,
+}
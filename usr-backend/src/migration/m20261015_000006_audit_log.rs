@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+use crate::schema;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        schema::create_table_from(
+            manager.get_connection(),
+            Table::create()
+                .table(AuditLog::Table)
+                .if_not_exists()
+                .col(
+                    ColumnDef::new(AuditLog::Id)
+                        .unsigned()
+                        .not_null()
+                        .auto_increment()
+                        .primary_key(),
+                )
+                .col(ColumnDef::new(AuditLog::OrderId).unsigned().not_null())
+                .col(ColumnDef::new(AuditLog::Actor).string().not_null())
+                .col(ColumnDef::new(AuditLog::Date).date_time().not_null())
+                .col(ColumnDef::new(AuditLog::Endpoint).string().not_null())
+                .col(ColumnDef::new(AuditLog::Diff).string().not_null())
+                .to_owned(),
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditLog {
+    Table,
+    Id,
+    OrderId,
+    Actor,
+    Date,
+    Endpoint,
+    Diff,
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(VendorPolicies::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(VendorPolicies::Key)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(VendorPolicies::Vendor).string().not_null())
+                    .col(
+                        ColumnDef::new(VendorPolicies::Standing)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(VendorPolicies::Reason).string().null())
+                    .col(ColumnDef::new(VendorPolicies::Reject).boolean().not_null())
+                    .col(ColumnDef::new(VendorPolicies::Since).date_time().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum VendorPolicies {
+    Table,
+    Key,
+    Vendor,
+    Standing,
+    Reason,
+    Reject,
+    Since,
+}
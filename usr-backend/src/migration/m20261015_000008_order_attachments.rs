@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Attachments::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Attachments::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Attachments::OrderId).integer().not_null())
+                    .col(ColumnDef::new(Attachments::Kind).string_len(1).not_null())
+                    .col(ColumnDef::new(Attachments::FileName).string().not_null())
+                    .col(ColumnDef::new(Attachments::ContentType).string().not_null())
+                    .col(ColumnDef::new(Attachments::Size).integer().not_null())
+                    .col(ColumnDef::new(Attachments::Sha256).string().not_null())
+                    .col(ColumnDef::new(Attachments::Key).string().not_null())
+                    .col(ColumnDef::new(Attachments::UploadedBy).string().not_null())
+                    .col(ColumnDef::new(Attachments::Uploaded).date_time().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Attachments {
+    Table,
+    Id,
+    OrderId,
+    Kind,
+    FileName,
+    ContentType,
+    Size,
+    Sha256,
+    Key,
+    UploadedBy,
+    Uploaded,
+}
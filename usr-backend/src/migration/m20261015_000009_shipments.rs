@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Shipments::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Shipments::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Shipments::Tracking).string().not_null())
+                    .col(ColumnDef::new(Shipments::Carrier).string().null())
+                    .col(ColumnDef::new(Shipments::Status).string_len(1).not_null())
+                    .col(ColumnDef::new(Shipments::CreatedBy).string().not_null())
+                    .col(ColumnDef::new(Shipments::Created).date_time().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(ShipmentOrders::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ShipmentOrders::OrderId)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ShipmentOrders::ShipmentId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Shipments {
+    Table,
+    Id,
+    Tracking,
+    Carrier,
+    Status,
+    CreatedBy,
+    Created,
+}
+
+#[derive(DeriveIden)]
+enum ShipmentOrders {
+    Table,
+    OrderId,
+    ShipmentId,
+}
@@ -0,0 +1,77 @@
+use sea_orm::ConnectionTrait;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A new database doesn't have vendors until the unversioned changes,
+        // which make it with these columns
+        if !manager.has_table("vendors").await? {
+            return Ok(());
+        }
+        for column in [
+            Vendors::Website,
+            Vendors::ShippingEstimate,
+            Vendors::TaxExemptNotes,
+        ] {
+            if manager.has_column("vendors", &column.to_string()).await? {
+                continue;
+            }
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Vendors::Table)
+                        .add_column(ColumnDef::new(column).string().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+        // Orders placed before now didn't record their vendors
+        fill_vendors(manager.get_connection()).await
+    }
+}
+
+/// Adds every vendor an order names that isn't there yet under any spelling
+pub(super) async fn fill_vendors(db: &impl ConnectionTrait) -> Result<(), DbErr> {
+    let backend = db.get_database_backend();
+    let names = Query::select()
+        .distinct()
+        .column(Orders::Vendor)
+        .from(Orders::Table)
+        .to_owned();
+    for row in db.query_all(backend.build(&names)).await? {
+        let name: String = row.try_get("", "vendor")?;
+        let name = name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        let insert = Query::insert()
+            .into_table(Vendors::Table)
+            .columns([Vendors::Key, Vendors::Name, Vendors::Notes])
+            .values_panic([name.to_lowercase().into(), name.into(), "".into()])
+            .on_conflict(OnConflict::column(Vendors::Key).do_nothing().to_owned())
+            .to_owned();
+        db.execute(backend.build(&insert)).await?;
+    }
+    Ok(())
+}
+
+#[derive(DeriveIden)]
+enum Vendors {
+    Table,
+    Key,
+    Name,
+    Notes,
+    Website,
+    ShippingEstimate,
+    TaxExemptNotes,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Vendor,
+}
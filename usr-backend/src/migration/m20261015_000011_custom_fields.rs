@@ -0,0 +1,62 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CustomFields::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CustomFields::Key)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CustomFields::Label).string().not_null())
+                    .col(ColumnDef::new(CustomFields::Kind).string_len(1).not_null())
+                    .col(ColumnDef::new(CustomFields::Required).boolean().not_null())
+                    .col(ColumnDef::new(CustomFields::Created).date_time().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderFields::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(OrderFields::OrderId).integer().not_null())
+                    .col(ColumnDef::new(OrderFields::Field).string().not_null())
+                    .col(ColumnDef::new(OrderFields::Value).string().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(OrderFields::OrderId)
+                            .col(OrderFields::Field),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CustomFields {
+    Table,
+    Key,
+    Label,
+    Kind,
+    Required,
+    Created,
+}
+
+#[derive(DeriveIden)]
+enum OrderFields {
+    Table,
+    OrderId,
+    Field,
+    Value,
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MemberQuotas::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MemberQuotas::Member)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(MemberQuotas::Orders).integer().null())
+                    .col(ColumnDef::new(MemberQuotas::Dollars).decimal().null())
+                    .col(ColumnDef::new(MemberQuotas::SetBy).string().not_null())
+                    .col(ColumnDef::new(MemberQuotas::Updated).date_time().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MemberQuotas {
+    Table,
+    Member,
+    Orders,
+    Dollars,
+    SetBy,
+    Updated,
+}
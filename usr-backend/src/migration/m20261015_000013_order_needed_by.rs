@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use super::online;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::NeededBy).date().null().to_owned(),
+        )
+        .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(DeadlineReminders::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DeadlineReminders::OrderId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DeadlineReminders::Stage)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DeadlineReminders::Sent)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(DeadlineReminders::OrderId)
+                            .col(DeadlineReminders::Stage),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    NeededBy,
+}
+
+#[derive(DeriveIden)]
+enum DeadlineReminders {
+    Table,
+    OrderId,
+    Stage,
+    Sent,
+}
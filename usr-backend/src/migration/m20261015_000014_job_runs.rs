@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(JobRuns::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(JobRuns::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(JobRuns::Job).string().not_null())
+                    .col(ColumnDef::new(JobRuns::Attempt).integer().not_null())
+                    .col(ColumnDef::new(JobRuns::Started).date_time().not_null())
+                    .col(ColumnDef::new(JobRuns::Finished).date_time().null())
+                    .col(ColumnDef::new(JobRuns::Ok).boolean().not_null())
+                    .col(ColumnDef::new(JobRuns::Error).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum JobRuns {
+    Table,
+    Id,
+    Job,
+    Attempt,
+    Started,
+    Finished,
+    Ok,
+    Error,
+}
@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderComments::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderComments::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderComments::OrderId).integer().not_null())
+                    .col(ColumnDef::new(OrderComments::Author).string().not_null())
+                    .col(ColumnDef::new(OrderComments::Text).string().not_null())
+                    .col(
+                        ColumnDef::new(OrderComments::Created)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderComments {
+    Table,
+    Id,
+    OrderId,
+    Author,
+    Text,
+    Created,
+}
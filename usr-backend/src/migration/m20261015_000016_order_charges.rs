@@ -0,0 +1,29 @@
+use sea_orm_migration::prelude::*;
+
+use super::online;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Orders::ShippingCost, Orders::Tax, Orders::Fees] {
+            online::add_column(
+                manager,
+                Orders::Table,
+                ColumnDef::new(column).decimal().null().to_owned(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    ShippingCost,
+    Tax,
+    Fees,
+}
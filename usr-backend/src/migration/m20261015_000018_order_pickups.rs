@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderPickups::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderPickups::OrderId)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderPickups::PickedUpBy).string().not_null())
+                    .col(
+                        ColumnDef::new(OrderPickups::PickedUp)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderPickups {
+    Table,
+    OrderId,
+    PickedUpBy,
+    PickedUp,
+}
@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+use super::online;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A new database doesn't have vendors until the unversioned changes,
+        // which make it with these columns
+        if !manager.has_table("vendors").await? {
+            return Ok(());
+        }
+        for column in [Vendors::RmaContact, Vendors::RmaInstructions] {
+            online::add_column(
+                manager,
+                Vendors::Table,
+                ColumnDef::new(column).string().null().to_owned(),
+            )
+            .await?;
+        }
+        online::add_column(
+            manager,
+            Vendors::Table,
+            ColumnDef::new(Vendors::ReturnWindowDays)
+                .unsigned()
+                .null()
+                .to_owned(),
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Vendors {
+    Table,
+    RmaContact,
+    RmaInstructions,
+    ReturnWindowDays,
+}
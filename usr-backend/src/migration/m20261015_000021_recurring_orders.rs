@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecurringOrders::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RecurringOrders::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RecurringOrders::Name).string().not_null())
+                    .col(ColumnDef::new(RecurringOrders::Count).integer().not_null())
+                    .col(
+                        ColumnDef::new(RecurringOrders::UnitCost)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecurringOrders::StoreIn).string().not_null())
+                    .col(
+                        ColumnDef::new(RecurringOrders::Team)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecurringOrders::Reason).string().not_null())
+                    .col(ColumnDef::new(RecurringOrders::Vendor).string().not_null())
+                    .col(ColumnDef::new(RecurringOrders::Link).string().not_null())
+                    .col(
+                        ColumnDef::new(RecurringOrders::FundingSource)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecurringOrders::Requester).string().null())
+                    .col(
+                        ColumnDef::new(RecurringOrders::IntervalDays)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecurringOrders::NextDue).date().not_null())
+                    .col(
+                        ColumnDef::new(RecurringOrders::LastOrderId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(RecurringOrders::CreatedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum RecurringOrders {
+    Table,
+    Id,
+    Name,
+    Count,
+    UnitCost,
+    StoreIn,
+    Team,
+    Reason,
+    Vendor,
+    Link,
+    FundingSource,
+    Requester,
+    IntervalDays,
+    NextDue,
+    LastOrderId,
+    CreatedBy,
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Seasons::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Seasons::Season)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Seasons::ClosedAt).date_time().not_null())
+                    .col(ColumnDef::new(Seasons::ClosedBy).string().not_null())
+                    .col(ColumnDef::new(Seasons::Orders).integer().not_null())
+                    .col(ColumnDef::new(Seasons::OpenOrders).integer().not_null())
+                    .col(ColumnDef::new(Seasons::Total).decimal().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Seasons {
+    Table,
+    Season,
+    ClosedAt,
+    ClosedBy,
+    Orders,
+    OpenOrders,
+    Total,
+}
@@ -0,0 +1,27 @@
+use sea_orm_migration::prelude::*;
+
+use super::online;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::ReceivedCount)
+                .integer()
+                .null()
+                .to_owned(),
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    ReceivedCount,
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Reimbursements::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Reimbursements::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Reimbursements::OrderId).integer().not_null())
+                    .col(ColumnDef::new(Reimbursements::Payer).string().not_null())
+                    .col(
+                        ColumnDef::new(Reimbursements::Card)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Reimbursements::Amount).decimal().not_null())
+                    .col(ColumnDef::new(Reimbursements::Submitted).date().null())
+                    .col(ColumnDef::new(Reimbursements::Ticket).string().null())
+                    .col(ColumnDef::new(Reimbursements::Repaid).date().null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Reimbursements {
+    Table,
+    Id,
+    OrderId,
+    Payer,
+    Card,
+    Amount,
+    Submitted,
+    Ticket,
+    Repaid,
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamWebhooks::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TeamWebhooks::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(TeamWebhooks::Team).string_len(1).not_null())
+                    .col(ColumnDef::new(TeamWebhooks::Topic).string_len(1).not_null())
+                    .col(ColumnDef::new(TeamWebhooks::Url).string().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TeamWebhooks {
+    Table,
+    Id,
+    Team,
+    Topic,
+    Url,
+}
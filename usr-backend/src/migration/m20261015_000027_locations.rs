@@ -0,0 +1,39 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Locations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Locations::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Locations::Room).string().not_null())
+                    .col(ColumnDef::new(Locations::Shelf).string().null())
+                    .col(ColumnDef::new(Locations::Bin).string().null())
+                    .col(ColumnDef::new(Locations::Label).string().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Locations {
+    Table,
+    Id,
+    Room,
+    Shelf,
+    Bin,
+    Label,
+}
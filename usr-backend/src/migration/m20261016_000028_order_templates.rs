@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderTemplates::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderTemplates::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderTemplates::Name).string().not_null())
+                    .col(ColumnDef::new(OrderTemplates::Count).integer().not_null())
+                    .col(
+                        ColumnDef::new(OrderTemplates::UnitCost)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OrderTemplates::StoreIn).string().not_null())
+                    .col(
+                        ColumnDef::new(OrderTemplates::Team)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OrderTemplates::Reason).string().not_null())
+                    .col(ColumnDef::new(OrderTemplates::Vendor).string().not_null())
+                    .col(ColumnDef::new(OrderTemplates::Link).string().not_null())
+                    .col(
+                        ColumnDef::new(OrderTemplates::FundingSource)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderTemplates::CreatedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderTemplates {
+    Table,
+    Id,
+    Name,
+    Count,
+    UnitCost,
+    StoreIn,
+    Team,
+    Reason,
+    Vendor,
+    Link,
+    FundingSource,
+    CreatedBy,
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderRevisions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderRevisions::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderRevisions::OrderId).integer().not_null())
+                    .col(ColumnDef::new(OrderRevisions::AuditId).integer().null())
+                    .col(ColumnDef::new(OrderRevisions::Date).date_time().not_null())
+                    .col(ColumnDef::new(OrderRevisions::OldCount).integer().not_null())
+                    .col(
+                        ColumnDef::new(OrderRevisions::OldUnitCost)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OrderRevisions::NewCount).integer().not_null())
+                    .col(
+                        ColumnDef::new(OrderRevisions::NewUnitCost)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderRevisions::SubtotalChange)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderRevisions {
+    Table,
+    Id,
+    OrderId,
+    AuditId,
+    Date,
+    OldCount,
+    OldUnitCost,
+    NewCount,
+    NewUnitCost,
+    SubtotalChange,
+}
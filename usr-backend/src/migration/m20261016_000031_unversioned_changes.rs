@@ -0,0 +1,1854 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, Local, NaiveDateTime};
+use sea_orm::{ConnectionTrait, Statement};
+use sea_orm_migration::prelude::*;
+
+use super::{m20261015_000010_vendor_details, online};
+
+/// Everything added after the baseline while changes were still made by
+/// `--migrate`. A database that was migrated that way already has some or
+/// all of it, so each table and column is only added if it is missing. A new
+/// database gets these tables here, after the migrations that came before
+/// this one, so they are made with what those migrations would have added.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let new_inventory = !manager.has_table("inventory").await?;
+        let new_vendors = !manager.has_table("vendors").await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Wishlist::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Wishlist::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Wishlist::Name).string().not_null())
+                    .col(ColumnDef::new(Wishlist::Count).integer().not_null())
+                    .col(ColumnDef::new(Wishlist::UnitCost).decimal().not_null())
+                    .col(ColumnDef::new(Wishlist::StoreIn).string().not_null())
+                    .col(ColumnDef::new(Wishlist::Team).string_len(1).not_null())
+                    .col(ColumnDef::new(Wishlist::Reason).string().not_null())
+                    .col(ColumnDef::new(Wishlist::Vendor).string().not_null())
+                    .col(ColumnDef::new(Wishlist::Link).string().not_null())
+                    .col(
+                        ColumnDef::new(Wishlist::FundingSource)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Wishlist::Date).date_time().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(FundingBudgets::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FundingBudgets::Source)
+                            .string_len(1)
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FundingBudgets::Allocated)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(BudgetPeriods::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BudgetPeriods::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(BudgetPeriods::Name).string().not_null())
+                    .col(ColumnDef::new(BudgetPeriods::Start).date_time().not_null())
+                    .col(ColumnDef::new(BudgetPeriods::End).date_time().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(BudgetPeriodTotals::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BudgetPeriodTotals::PeriodId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BudgetPeriodTotals::Team)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BudgetPeriodTotals::Allocated)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BudgetPeriodTotals::CarriedOver)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(BudgetPeriodTotals::Spent).decimal().null())
+                    .primary_key(
+                        Index::create()
+                            .col(BudgetPeriodTotals::PeriodId)
+                            .col(BudgetPeriodTotals::Team),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(CostSplits::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CostSplits::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CostSplits::OrderId).integer().not_null())
+                    .col(ColumnDef::new(CostSplits::Team).string_len(1).not_null())
+                    .col(
+                        ColumnDef::new(CostSplits::FundingSource)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CostSplits::Percent).decimal().null())
+                    .col(ColumnDef::new(CostSplits::Amount).decimal().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderReminders::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(OrderReminders::OrderId).integer().not_null())
+                    .col(ColumnDef::new(OrderReminders::Step).integer().not_null())
+                    .col(ColumnDef::new(OrderReminders::Sent).date_time().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(OrderReminders::OrderId)
+                            .col(OrderReminders::Step),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(SpendingFreezes::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SpendingFreezes::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SpendingFreezes::Team).string_len(1).null())
+                    .col(
+                        ColumnDef::new(SpendingFreezes::Mode)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SpendingFreezes::Reason).string().not_null())
+                    .col(
+                        ColumnDef::new(SpendingFreezes::Since)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Inventory::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Inventory::OrderId).integer().not_null())
+                    .col(ColumnDef::new(Inventory::Location).string().not_null())
+                    .col(ColumnDef::new(Inventory::Name).string().not_null())
+                    .col(ColumnDef::new(Inventory::Quantity).integer().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(Inventory::OrderId)
+                            .col(Inventory::Location),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(InventoryCheckouts::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(InventoryCheckouts::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(InventoryCheckouts::OrderId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InventoryCheckouts::Location)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InventoryCheckouts::Count)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InventoryCheckouts::Returned)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InventoryCheckouts::TakenBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(InventoryCheckouts::Note).string().null())
+                    .col(
+                        ColumnDef::new(InventoryCheckouts::Taken)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InventoryCheckouts::LastReturned)
+                            .date_time()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(InventoryTransfers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(InventoryTransfers::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(InventoryTransfers::OrderId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InventoryTransfers::Count)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InventoryTransfers::FromLocation)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InventoryTransfers::ToLocation)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(InventoryTransfers::Note).string().null())
+                    .col(
+                        ColumnDef::new(InventoryTransfers::Date)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderDiscrepancies::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderDiscrepancies::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderDiscrepancies::OrderId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderDiscrepancies::Kind)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderDiscrepancies::Expected)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderDiscrepancies::Received)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OrderDiscrepancies::Note).string().not_null())
+                    .col(
+                        ColumnDef::new(OrderDiscrepancies::Reported)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderDiscrepancies::Resolved)
+                            .date_time()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderDiscrepancies::Resolution)
+                            .string()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Vendors::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Vendors::Key)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Vendors::Name).string().not_null())
+                    .col(ColumnDef::new(Vendors::ShippingAccount).string().null())
+                    .col(ColumnDef::new(Vendors::LoginHint).string().null())
+                    .col(ColumnDef::new(Vendors::MinimumOrder).decimal().null())
+                    .col(
+                        ColumnDef::new(Vendors::FreeShippingThreshold)
+                            .decimal()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(Vendors::Notes).string().not_null())
+                    .col(ColumnDef::new(Vendors::Website).string().null())
+                    .col(ColumnDef::new(Vendors::ShippingEstimate).string().null())
+                    .col(ColumnDef::new(Vendors::TaxExemptNotes).string().null())
+                    .col(ColumnDef::new(Vendors::RmaContact).string().null())
+                    .col(ColumnDef::new(Vendors::RmaInstructions).string().null())
+                    .col(ColumnDef::new(Vendors::ReturnWindowDays).unsigned().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderWatchers::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(OrderWatchers::OrderId).integer().not_null())
+                    .col(ColumnDef::new(OrderWatchers::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(OrderWatchers::Channel)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(OrderWatchers::OrderId)
+                            .col(OrderWatchers::Name),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(SeasonBudgets::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(SeasonBudgets::Team).string_len(1).not_null())
+                    .col(
+                        ColumnDef::new(SeasonBudgets::Season)
+                            .small_unsigned()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SeasonBudgets::Allocated)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SeasonBudgets::Overrun)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(SeasonBudgets::Team)
+                            .col(SeasonBudgets::Season),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Sponsors::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Sponsors::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Sponsors::Name).string().not_null())
+                    .col(ColumnDef::new(Sponsors::Contact).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Donations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Donations::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Donations::SponsorId).integer().not_null())
+                    .col(ColumnDef::new(Donations::Kind).string_len(1).not_null())
+                    .col(ColumnDef::new(Donations::Description).string().not_null())
+                    .col(ColumnDef::new(Donations::Pledged).decimal().not_null())
+                    .col(ColumnDef::new(Donations::Received).decimal().not_null())
+                    .col(ColumnDef::new(Donations::Team).string_len(1).null())
+                    .col(ColumnDef::new(Donations::Date).date_time().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Trips::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Trips::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Trips::Name).string().not_null())
+                    .col(ColumnDef::new(Trips::Destination).string().not_null())
+                    .col(ColumnDef::new(Trips::StartDate).date().not_null())
+                    .col(ColumnDef::new(Trips::EndDate).date().not_null())
+                    .col(ColumnDef::new(Trips::Budget).decimal().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TravelExpenses::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TravelExpenses::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(TravelExpenses::TripId).integer().not_null())
+                    .col(
+                        ColumnDef::new(TravelExpenses::Category)
+                            .string_len(1)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(TravelExpenses::Member).string().null())
+                    .col(
+                        ColumnDef::new(TravelExpenses::Description)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(TravelExpenses::Amount).decimal().not_null())
+                    .col(ColumnDef::new(TravelExpenses::Date).date_time().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(PackingTemplateItems::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PackingTemplateItems::Template)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PackingTemplateItems::Item)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PackingTemplateItems::Quantity)
+                            .integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(PackingTemplateItems::Template)
+                            .col(PackingTemplateItems::Item),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(PackingLists::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PackingLists::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PackingLists::Name).string().not_null())
+                    .col(ColumnDef::new(PackingLists::Template).string().not_null())
+                    .col(ColumnDef::new(PackingLists::Date).date_time().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(PackingListItems::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PackingListItems::ListId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PackingListItems::Item).string().not_null())
+                    .col(
+                        ColumnDef::new(PackingListItems::Quantity)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PackingListItems::Available)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PackingListItems::Locations)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PackingListItems::Checked)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(PackingListItems::ListId)
+                            .col(PackingListItems::Item),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Components::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Components::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Components::Name).string().not_null())
+                    .col(ColumnDef::new(Components::Subsystem).string().not_null())
+                    .col(ColumnDef::new(Components::Revision).string().not_null())
+                    .col(ColumnDef::new(Components::Status).string_len(1).not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(BomLines::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(BomLines::Project).string().not_null())
+                    .col(ColumnDef::new(BomLines::Item).string().not_null())
+                    .col(ColumnDef::new(BomLines::Quantity).integer().not_null())
+                    .col(ColumnDef::new(BomLines::UnitCost).decimal().not_null())
+                    .primary_key(Index::create().col(BomLines::Project).col(BomLines::Item))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Equipment::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Equipment::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Equipment::Name).string().not_null())
+                    .col(ColumnDef::new(Equipment::Location).string().not_null())
+                    .col(ColumnDef::new(Equipment::IntervalDays).integer().not_null())
+                    .col(ColumnDef::new(Equipment::NextDue).date().not_null())
+                    .col(
+                        ColumnDef::new(Equipment::RequiredCertification)
+                            .string()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(Equipment::LastReminded).date().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(ServiceRecords::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ServiceRecords::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ServiceRecords::EquipmentId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ServiceRecords::Date).date_time().not_null())
+                    .col(
+                        ColumnDef::new(ServiceRecords::PerformedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ServiceRecords::Notes).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(EquipmentCheckouts::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EquipmentCheckouts::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(EquipmentCheckouts::EquipmentId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EquipmentCheckouts::Member)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EquipmentCheckouts::Date)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EquipmentCheckouts::Returned)
+                            .date_time()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(EquipmentCheckouts::OverrideBy)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(EquipmentCheckouts::OverrideReason)
+                            .string()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Certifications::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Certifications::Member).string().not_null())
+                    .col(
+                        ColumnDef::new(Certifications::Certification)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Certifications::GrantedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Certifications::Date).date_time().not_null())
+                    .col(
+                        ColumnDef::new(Certifications::Supervisor)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(Certifications::Member)
+                            .col(Certifications::Certification),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Hazards::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Hazards::Item)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Hazards::Class).string_len(1).not_null())
+                    .col(
+                        ColumnDef::new(Hazards::StorageRequirements)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Hazards::HandlingNotes).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Spools::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Spools::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Spools::Material).string().not_null())
+                    .col(ColumnDef::new(Spools::Color).string().not_null())
+                    .col(ColumnDef::new(Spools::Location).string().not_null())
+                    .col(ColumnDef::new(Spools::RemainingGrams).integer().not_null())
+                    .col(
+                        ColumnDef::new(Spools::LowThresholdGrams)
+                            .integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(PrintJobs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PrintJobs::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PrintJobs::SpoolId).integer().not_null())
+                    .col(ColumnDef::new(PrintJobs::Grams).integer().not_null())
+                    .col(ColumnDef::new(PrintJobs::Team).string_len(1).not_null())
+                    .col(ColumnDef::new(PrintJobs::Project).string().not_null())
+                    .col(ColumnDef::new(PrintJobs::Member).string().not_null())
+                    .col(ColumnDef::new(PrintJobs::Date).date_time().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookDeliveries::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::Destination)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WebhookDeliveries::Ids).string().not_null())
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::PayloadHash)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::Success)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::StatusCode)
+                            .small_unsigned()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(WebhookDeliveries::Error).string().null())
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::LatencyMs)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::Date)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(DbSizeSamples::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DbSizeSamples::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(DbSizeSamples::Date).date_time().not_null())
+                    .col(
+                        ColumnDef::new(DbSizeSamples::Bytes)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DbSizeSamples::FreedBytes)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(FeatureFlags::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FeatureFlags::Name)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(FeatureFlags::Enabled).boolean().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Assets::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Assets::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Assets::OrderId).integer().not_null())
+                    .col(ColumnDef::new(Assets::Name).string().not_null())
+                    .col(ColumnDef::new(Assets::Cost).decimal().not_null())
+                    .col(ColumnDef::new(Assets::SerialNumber).string().null())
+                    .col(ColumnDef::new(Assets::Custodian).string().null())
+                    .col(ColumnDef::new(Assets::Location).string().not_null())
+                    .col(ColumnDef::new(Assets::DepreciationNote).string().not_null())
+                    .col(ColumnDef::new(Assets::Acquired).date_time().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(AssetVerifications::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AssetVerifications::AssetId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AssetVerifications::Year)
+                            .small_unsigned()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AssetVerifications::VerifiedBy)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AssetVerifications::Location)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AssetVerifications::Note).string().not_null())
+                    .col(
+                        ColumnDef::new(AssetVerifications::Date)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(AssetVerifications::AssetId)
+                            .col(AssetVerifications::Year),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Users::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Users::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Users::Name).string().not_null().unique_key())
+                    .col(ColumnDef::new(Users::Role).string_len(1).not_null())
+                    .col(
+                        ColumnDef::new(Users::TokenHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Users::Created).date_time().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(MemberDiscordIds::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MemberDiscordIds::Name)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MemberDiscordIds::DiscordId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MemberDiscordIds::Locale)
+                            .string_len(2)
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(DmPreferences::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DmPreferences::Name)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(DmPreferences::OrderDigest)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(KioskSessions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(KioskSessions::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(KioskSessions::Name).string().not_null())
+                    .col(ColumnDef::new(KioskSessions::Opened).date_time().not_null())
+                    .col(ColumnDef::new(KioskSessions::LastSync).date_time().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(KioskMutations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(KioskMutations::SessionId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(KioskMutations::MutationId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(KioskMutations::Date).date_time().not_null())
+                    .col(
+                        ColumnDef::new(KioskMutations::Uploaded)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(KioskMutations::Conflict).string().null())
+                    .primary_key(
+                        Index::create()
+                            .col(KioskMutations::SessionId)
+                            .col(KioskMutations::MutationId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(KioskChanges::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(KioskChanges::Seq)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(KioskChanges::EquipmentId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::FundingSource)
+                .string_len(1)
+                .not_null()
+                .default("D")
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::ComponentId)
+                .integer()
+                .null()
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::Season)
+                .small_unsigned()
+                .null()
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::SeasonNumber)
+                .integer()
+                .null()
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::TaxExempt)
+                .boolean()
+                .null()
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::PaymentMethod)
+                .string_len(1)
+                .null()
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::Requester).string().null().to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::Currency)
+                .string_len(3)
+                .null()
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::OriginalUnitCost)
+                .decimal()
+                .null()
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Orders::Table,
+            ColumnDef::new(Orders::ExchangeRate)
+                .decimal()
+                .null()
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            OrderStatus::Table,
+            ColumnDef::new(OrderStatus::Reason)
+                .string()
+                .null()
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Wishlist::Table,
+            ColumnDef::new(Wishlist::FundingSource)
+                .string_len(1)
+                .not_null()
+                .default("D")
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            Equipment::Table,
+            ColumnDef::new(Equipment::RequiredCertification)
+                .string()
+                .null()
+                .to_owned(),
+        )
+        .await?;
+        online::add_column(
+            manager,
+            MemberDiscordIds::Table,
+            ColumnDef::new(MemberDiscordIds::Locale)
+                .string_len(2)
+                .null()
+                .to_owned(),
+        )
+        .await?;
+
+        let db = manager.get_connection();
+        number_orders(db).await?;
+        let legacy = manager.has_table("stock_locations").await?;
+        if new_inventory || legacy {
+            fill_inventory(db, legacy).await?;
+        }
+        if new_vendors {
+            m20261015_000010_vendor_details::fill_vendors(db).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Numbers the orders placed before display numbers, in the season each was
+/// placed in and in the order they were placed
+async fn number_orders(db: &impl ConnectionTrait) -> Result<(), DbErr> {
+    let backend = db.get_database_backend();
+    let unnumbered = Query::select()
+        .column(Orders::Id)
+        .from(Orders::Table)
+        .and_where(Expr::col(Orders::SeasonNumber).is_null())
+        .to_owned();
+    let unnumbered: Vec<i32> = db
+        .query_all(backend.build(&unnumbered))
+        .await?
+        .into_iter()
+        .map(|row| row.try_get("", "id"))
+        .collect::<Result<_, _>>()?;
+    if unnumbered.is_empty() {
+        return Ok(());
+    }
+    let placed = Query::select()
+        .columns([OrderStatus::OrderId, OrderStatus::Date])
+        .from(OrderStatus::Table)
+        .and_where(Expr::col(OrderStatus::Status).eq("N"))
+        .to_owned();
+    let mut placed_on = HashMap::<i32, NaiveDateTime>::new();
+    for row in db.query_all(backend.build(&placed)).await? {
+        placed_on.insert(row.try_get("", "order_id")?, row.try_get("", "date")?);
+    }
+    let mut unnumbered: Vec<_> = unnumbered
+        .into_iter()
+        .map(|id| {
+            let date = placed_on
+                .get(&id)
+                .copied()
+                .unwrap_or_else(|| Local::now().naive_local());
+            (date, id)
+        })
+        .collect();
+    unnumbered.sort();
+
+    let mut last = HashMap::<i32, i32>::new();
+    for (date, id) in unnumbered {
+        let season = date.year();
+        let number = match last.get_mut(&season) {
+            Some(number) => number,
+            None => {
+                let max = Query::select()
+                    .expr_as(Expr::col(Orders::SeasonNumber).max(), Alias::new("last"))
+                    .from(Orders::Table)
+                    .and_where(Expr::col(Orders::Season).eq(season))
+                    .to_owned();
+                let max: Option<i32> = match db.query_one(backend.build(&max)).await? {
+                    Some(row) => row.try_get("", "last")?,
+                    None => None,
+                };
+                last.entry(season).or_insert(max.unwrap_or_default())
+            }
+        };
+        *number += 1;
+        let update = Query::update()
+            .table(Orders::Table)
+            .values([
+                (Orders::Season, season.into()),
+                (Orders::SeasonNumber, (*number).into()),
+            ])
+            .and_where(Expr::col(Orders::Id).eq(id))
+            .to_owned();
+        db.execute(backend.build(&update)).await?;
+    }
+    Ok(())
+}
+
+/// Stocks orders that were put in storage before there was an inventory.
+/// Stock that `stock_locations` had moved out of `store_in` is carried over,
+/// and that table is dropped.
+async fn fill_inventory(db: &impl ConnectionTrait, legacy: bool) -> Result<(), DbErr> {
+    let backend = db.get_database_backend();
+    let mut moved = HashMap::<i32, Vec<(String, i32)>>::new();
+    if legacy {
+        let rows = db
+            .query_all(Statement::from_string(
+                backend,
+                "SELECT order_id, location, count FROM stock_locations",
+            ))
+            .await?;
+        for row in rows {
+            moved
+                .entry(row.try_get("", "order_id")?)
+                .or_default()
+                .push((row.try_get("", "location")?, row.try_get("", "count")?));
+        }
+    }
+    let stocked = Query::select()
+        .column(Inventory::OrderId)
+        .distinct()
+        .from(Inventory::Table)
+        .to_owned();
+    let stocked: HashSet<i32> = db
+        .query_all(backend.build(&stocked))
+        .await?
+        .into_iter()
+        .map(|row| row.try_get("", "order_id"))
+        .collect::<Result<_, _>>()?;
+    let stored = db
+        .query_all(Statement::from_string(
+            backend,
+            "SELECT orders.id, orders.name, orders.count, orders.store_in FROM orders \
+             JOIN order_status ON order_status.order_id = orders.id \
+             AND order_status.instance_id = ( \
+                 SELECT MAX(latest.instance_id) FROM order_status AS latest \
+                 WHERE latest.order_id = orders.id \
+             ) \
+             WHERE order_status.status = 'I'",
+        ))
+        .await?;
+    for row in stored {
+        let id: i32 = row.try_get("", "id")?;
+        if stocked.contains(&id) {
+            continue;
+        }
+        let name: String = row.try_get("", "name")?;
+        let count: i32 = row.try_get("", "count")?;
+        let moved = moved.remove(&id).unwrap_or_default();
+        let elsewhere: i32 = moved.iter().map(|(_, count)| count).sum();
+        let remaining = (count - elsewhere).max(0);
+        let mut rows = moved;
+        if remaining > 0 || rows.is_empty() {
+            rows.push((row.try_get("", "store_in")?, remaining));
+        }
+        for (location, quantity) in rows {
+            let insert = Query::insert()
+                .into_table(Inventory::Table)
+                .columns([
+                    Inventory::OrderId,
+                    Inventory::Location,
+                    Inventory::Name,
+                    Inventory::Quantity,
+                ])
+                .values_panic([
+                    id.into(),
+                    location.into(),
+                    name.clone().into(),
+                    quantity.into(),
+                ])
+                .to_owned();
+            db.execute(backend.build(&insert)).await?;
+        }
+    }
+    if legacy {
+        db.execute_unprepared("DROP TABLE stock_locations").await?;
+    }
+    Ok(())
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+    FundingSource,
+    ComponentId,
+    Season,
+    SeasonNumber,
+    TaxExempt,
+    PaymentMethod,
+    Requester,
+    Currency,
+    OriginalUnitCost,
+    ExchangeRate,
+}
+
+#[derive(DeriveIden)]
+enum OrderStatus {
+    Table,
+    OrderId,
+    Date,
+    Status,
+    Reason,
+}
+
+#[derive(DeriveIden)]
+enum Wishlist {
+    Table,
+    Id,
+    Name,
+    Count,
+    UnitCost,
+    StoreIn,
+    Team,
+    Reason,
+    Vendor,
+    Link,
+    FundingSource,
+    Date,
+}
+
+#[derive(DeriveIden)]
+enum FundingBudgets {
+    Table,
+    Source,
+    Allocated,
+}
+
+#[derive(DeriveIden)]
+enum BudgetPeriods {
+    Table,
+    Id,
+    Name,
+    Start,
+    End,
+}
+
+#[derive(DeriveIden)]
+enum BudgetPeriodTotals {
+    Table,
+    PeriodId,
+    Team,
+    Allocated,
+    CarriedOver,
+    Spent,
+}
+
+#[derive(DeriveIden)]
+enum CostSplits {
+    Table,
+    Id,
+    OrderId,
+    Team,
+    FundingSource,
+    Percent,
+    Amount,
+}
+
+#[derive(DeriveIden)]
+enum OrderReminders {
+    Table,
+    OrderId,
+    Step,
+    Sent,
+}
+
+#[derive(DeriveIden)]
+enum SpendingFreezes {
+    Table,
+    Id,
+    Team,
+    Mode,
+    Reason,
+    Since,
+}
+
+#[derive(DeriveIden)]
+enum Inventory {
+    Table,
+    OrderId,
+    Location,
+    Name,
+    Quantity,
+}
+
+#[derive(DeriveIden)]
+enum InventoryCheckouts {
+    Table,
+    Id,
+    OrderId,
+    Location,
+    Count,
+    Returned,
+    TakenBy,
+    Note,
+    Taken,
+    LastReturned,
+}
+
+#[derive(DeriveIden)]
+enum InventoryTransfers {
+    Table,
+    Id,
+    OrderId,
+    Count,
+    FromLocation,
+    ToLocation,
+    Note,
+    Date,
+}
+
+#[derive(DeriveIden)]
+enum OrderDiscrepancies {
+    Table,
+    Id,
+    OrderId,
+    Kind,
+    Expected,
+    Received,
+    Note,
+    Reported,
+    Resolved,
+    Resolution,
+}
+
+#[derive(DeriveIden)]
+enum Vendors {
+    Table,
+    Key,
+    Name,
+    ShippingAccount,
+    LoginHint,
+    MinimumOrder,
+    FreeShippingThreshold,
+    Notes,
+    Website,
+    ShippingEstimate,
+    TaxExemptNotes,
+    RmaContact,
+    RmaInstructions,
+    ReturnWindowDays,
+}
+
+#[derive(DeriveIden)]
+enum OrderWatchers {
+    Table,
+    OrderId,
+    Name,
+    Channel,
+}
+
+#[derive(DeriveIden)]
+enum SeasonBudgets {
+    Table,
+    Team,
+    Season,
+    Allocated,
+    Overrun,
+}
+
+#[derive(DeriveIden)]
+enum Sponsors {
+    Table,
+    Id,
+    Name,
+    Contact,
+}
+
+#[derive(DeriveIden)]
+enum Donations {
+    Table,
+    Id,
+    SponsorId,
+    Kind,
+    Description,
+    Pledged,
+    Received,
+    Team,
+    Date,
+}
+
+#[derive(DeriveIden)]
+enum Trips {
+    Table,
+    Id,
+    Name,
+    Destination,
+    StartDate,
+    EndDate,
+    Budget,
+}
+
+#[derive(DeriveIden)]
+enum TravelExpenses {
+    Table,
+    Id,
+    TripId,
+    Category,
+    Member,
+    Description,
+    Amount,
+    Date,
+}
+
+#[derive(DeriveIden)]
+enum PackingTemplateItems {
+    Table,
+    Template,
+    Item,
+    Quantity,
+}
+
+#[derive(DeriveIden)]
+enum PackingLists {
+    Table,
+    Id,
+    Name,
+    Template,
+    Date,
+}
+
+#[derive(DeriveIden)]
+enum PackingListItems {
+    Table,
+    ListId,
+    Item,
+    Quantity,
+    Available,
+    Locations,
+    Checked,
+}
+
+#[derive(DeriveIden)]
+enum Components {
+    Table,
+    Id,
+    Name,
+    Subsystem,
+    Revision,
+    Status,
+}
+
+#[derive(DeriveIden)]
+enum BomLines {
+    Table,
+    Project,
+    Item,
+    Quantity,
+    UnitCost,
+}
+
+#[derive(DeriveIden)]
+enum Equipment {
+    Table,
+    Id,
+    Name,
+    Location,
+    IntervalDays,
+    NextDue,
+    RequiredCertification,
+    LastReminded,
+}
+
+#[derive(DeriveIden)]
+enum ServiceRecords {
+    Table,
+    Id,
+    EquipmentId,
+    Date,
+    PerformedBy,
+    Notes,
+}
+
+#[derive(DeriveIden)]
+enum EquipmentCheckouts {
+    Table,
+    Id,
+    EquipmentId,
+    Member,
+    Date,
+    Returned,
+    OverrideBy,
+    OverrideReason,
+}
+
+#[derive(DeriveIden)]
+enum Certifications {
+    Table,
+    Member,
+    Certification,
+    GrantedBy,
+    Date,
+    Supervisor,
+}
+
+#[derive(DeriveIden)]
+enum Hazards {
+    Table,
+    Item,
+    Class,
+    StorageRequirements,
+    HandlingNotes,
+}
+
+#[derive(DeriveIden)]
+enum Spools {
+    Table,
+    Id,
+    Material,
+    Color,
+    Location,
+    RemainingGrams,
+    LowThresholdGrams,
+}
+
+#[derive(DeriveIden)]
+enum PrintJobs {
+    Table,
+    Id,
+    SpoolId,
+    Grams,
+    Team,
+    Project,
+    Member,
+    Date,
+}
+
+#[derive(DeriveIden)]
+enum WebhookDeliveries {
+    Table,
+    Id,
+    Destination,
+    Ids,
+    PayloadHash,
+    Success,
+    StatusCode,
+    Error,
+    LatencyMs,
+    Date,
+}
+
+#[derive(DeriveIden)]
+enum DbSizeSamples {
+    Table,
+    Id,
+    Date,
+    Bytes,
+    FreedBytes,
+}
+
+#[derive(DeriveIden)]
+enum FeatureFlags {
+    Table,
+    Name,
+    Enabled,
+}
+
+#[derive(DeriveIden)]
+enum Assets {
+    Table,
+    Id,
+    OrderId,
+    Name,
+    Cost,
+    SerialNumber,
+    Custodian,
+    Location,
+    DepreciationNote,
+    Acquired,
+}
+
+#[derive(DeriveIden)]
+enum AssetVerifications {
+    Table,
+    AssetId,
+    Year,
+    VerifiedBy,
+    Location,
+    Note,
+    Date,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+    Name,
+    Role,
+    TokenHash,
+    Created,
+}
+
+#[derive(DeriveIden)]
+enum MemberDiscordIds {
+    Table,
+    Name,
+    DiscordId,
+    Locale,
+}
+
+#[derive(DeriveIden)]
+enum DmPreferences {
+    Table,
+    Name,
+    OrderDigest,
+}
+
+#[derive(DeriveIden)]
+enum KioskSessions {
+    Table,
+    Id,
+    Name,
+    Opened,
+    LastSync,
+}
+
+#[derive(DeriveIden)]
+enum KioskMutations {
+    Table,
+    SessionId,
+    MutationId,
+    Date,
+    Uploaded,
+    Conflict,
+}
+
+#[derive(DeriveIden)]
+enum KioskChanges {
+    Table,
+    Seq,
+    EquipmentId,
+}
@@ -0,0 +1,134 @@
+use sea_orm::{ConnectionTrait, DatabaseBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+/// Every `u32` column there is, by table
+const UNSIGNED: &[(&str, &[&str])] = &[
+    ("asset_verifications", &["asset_id", "year"]),
+    ("assets", &["id", "order_id"]),
+    ("attachments", &["id", "order_id", "size"]),
+    ("attendance", &["uid"]),
+    ("audit_log", &["id", "order_id"]),
+    ("bom_lines", &["quantity"]),
+    ("budget_period_totals", &["period_id"]),
+    ("budget_periods", &["id"]),
+    ("components", &["id"]),
+    ("cost_splits", &["id", "order_id"]),
+    ("db_size_samples", &["id"]),
+    ("deadline_reminders", &["order_id"]),
+    ("donations", &["id", "sponsor_id"]),
+    ("equipment", &["id", "interval_days"]),
+    ("equipment_checkouts", &["id", "equipment_id"]),
+    ("idempotency_keys", &["status"]),
+    ("inventory", &["order_id", "quantity"]),
+    (
+        "inventory_checkouts",
+        &["id", "order_id", "count", "returned"],
+    ),
+    ("inventory_transfers", &["id", "order_id", "count"]),
+    ("job_runs", &["id", "attempt"]),
+    ("kiosk_changes", &["seq", "equipment_id"]),
+    ("kiosk_mutations", &["session_id", "mutation_id"]),
+    ("kiosk_sessions", &["id"]),
+    ("locations", &["id"]),
+    ("member_quotas", &["orders"]),
+    ("order_approvals", &["order_id"]),
+    ("order_comments", &["id", "order_id"]),
+    (
+        "order_discrepancies",
+        &["id", "order_id", "expected", "received"],
+    ),
+    ("order_fields", &["order_id"]),
+    ("order_pickups", &["order_id"]),
+    ("order_reminders", &["order_id", "step"]),
+    (
+        "order_revisions",
+        &["id", "order_id", "audit_id", "old_count", "new_count"],
+    ),
+    ("order_status", &["instance_id", "order_id"]),
+    ("order_templates", &["id", "count"]),
+    ("order_watchers", &["order_id"]),
+    (
+        "orders",
+        &[
+            "id",
+            "count",
+            "component_id",
+            "ref_number",
+            "season",
+            "season_number",
+            "version",
+            "received_count",
+        ],
+    ),
+    ("packing_list_items", &["list_id", "quantity", "available"]),
+    ("packing_lists", &["id"]),
+    ("packing_template_items", &["quantity"]),
+    ("print_jobs", &["id", "spool_id", "grams"]),
+    (
+        "recurring_orders",
+        &["id", "count", "interval_days", "last_order_id"],
+    ),
+    ("reimbursements", &["id", "order_id"]),
+    ("role_grants", &["id", "user_id"]),
+    ("season_budgets", &["season"]),
+    ("seasons", &["season", "orders", "open_orders"]),
+    ("service_records", &["id", "equipment_id"]),
+    ("shipment_orders", &["order_id", "shipment_id"]),
+    ("shipments", &["id"]),
+    ("spending_freezes", &["id"]),
+    ("sponsors", &["id"]),
+    ("spools", &["id", "remaining_grams", "low_threshold_grams"]),
+    ("team_webhooks", &["id"]),
+    ("travel_expenses", &["id", "trip_id"]),
+    ("trips", &["id"]),
+    ("users", &["id"]),
+    ("vendors", &["return_window_days"]),
+    ("webhook_deliveries", &["id", "status_code", "latency_ms"]),
+    ("webhook_outbox", &["id", "attempts"]),
+    ("wishlist", &["id", "count"]),
+];
+
+/// Turns the `u32` columns that earlier migrations made as integers on
+/// Postgres into the `bigint`s [`crate::schema::column_def`] makes now,
+/// checked to stay in range. SQLite stores both the same way, so there is
+/// nothing to do on it.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let backend = manager.get_database_backend();
+        if backend != DatabaseBackend::Postgres {
+            return Ok(());
+        }
+        let db = manager.get_connection();
+        // Postgres won't change a column a view reads, and the view is made
+        // again at startup anyway
+        db.execute_unprepared("DROP VIEW IF EXISTS order_current")
+            .await?;
+        for (table, columns) in UNSIGNED {
+            for column in *columns {
+                let found = db
+                    .query_one(Statement::from_sql_and_values(
+                        backend,
+                        "SELECT data_type FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1 AND column_name = $2",
+                        [(*table).into(), (*column).into()],
+                    ))
+                    .await?;
+                let Some(row) = found else {
+                    continue;
+                };
+                if row.try_get::<String>("", "data_type")? == "bigint" {
+                    continue;
+                }
+                db.execute_unprepared(&format!(
+                    "ALTER TABLE \"{table}\" ALTER COLUMN \"{column}\" TYPE bigint, ADD CHECK (\"{column}\" BETWEEN 0 AND {})",
+                    u32::MAX
+                ))
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
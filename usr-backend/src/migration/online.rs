@@ -0,0 +1,416 @@
+//! Migrating while the instance being replaced keeps serving.
+//!
+//! Changes are split into an expand half, which only adds to the schema and
+//! so is safe under the old version, and a contract half, which removes what
+//! the old version still reads. Expand migrations run as soon as the new
+//! version starts. Migrations listed in [`CONTRACTIONS`] wait until every
+//! instance of an older version has stopped, eg. renaming a column is
+//! [`add_column`] and [`backfill`] in one migration, then [`drop_column`] in a
+//! later contraction. Versions from before this module don't send
+//! heartbeats, so they aren't waited for.
+
+use std::{sync::LazyLock, time::Duration};
+
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr};
+use sea_orm_migration::{
+    prelude::*,
+    sea_query::{ColumnSpec, OnConflict},
+    MigratorTrait,
+};
+use tracing::{error, info};
+
+use crate::{schema, UsrState};
+
+use super::Migrator;
+
+/// Migrations that remove or rename something older versions still use, by
+/// name. They and every migration after them are held back while an older
+/// instance is running.
+const CONTRACTIONS: &[&str] = &[];
+
+/// How long the migration lock is held without being renewed, so that an
+/// instance that dies while migrating doesn't block the others for good
+const LEASE: TimeDelta = TimeDelta::minutes(5);
+/// How often a waiting instance checks whether the lock is free
+const LOCK_POLL: Duration = Duration::from_secs(2);
+/// How often each instance says it is still running
+const HEARTBEAT: Duration = Duration::from_secs(60);
+/// How long an instance can go without a heartbeat before it is assumed to
+/// have stopped
+const STALE: TimeDelta = TimeDelta::minutes(3);
+/// Rows copied per statement by [`backfill`]
+const BACKFILL_BATCH: u64 = 500;
+
+/// Tells this process apart from other instances on the same database
+static INSTANCE: LazyLock<String> =
+    LazyLock::new(|| format!("{}-{:08x}", std::process::id(), rand::random::<u32>()));
+
+/// The schema version this build knows, counted in migrations
+fn known_schema() -> u32 {
+    Migrator::migrations().len() as u32
+}
+
+/// Adds `column` to `table` unless it is already there, made like
+/// [`crate::schema::column_def`] makes it. Older instances insert rows without
+/// it, so it has to be nullable or have a default.
+pub async fn add_column(
+    manager: &SchemaManager<'_>,
+    table: impl IntoIden,
+    column: ColumnDef,
+) -> Result<(), DbErr> {
+    let table = table.into_iden();
+    let name = column.get_column_name();
+    let spec = column.get_column_spec();
+    if spec.iter().any(|spec| matches!(spec, ColumnSpec::NotNull))
+        && !spec
+            .iter()
+            .any(|spec| matches!(spec, ColumnSpec::Default(_)))
+    {
+        return Err(DbErr::Migration(format!(
+            "{}.{name} is not null without a default, which older instances can't insert",
+            table.to_string()
+        )));
+    }
+    if manager.has_column(table.to_string(), &name).await? {
+        return Ok(());
+    }
+    let mut column = schema::column_def(manager.get_database_backend(), column);
+    manager
+        .alter_table(
+            Table::alter()
+                .table(table)
+                .add_column(&mut column)
+                .to_owned(),
+        )
+        .await
+}
+
+/// Copies `from` into `to` on each row of `table` where `to` is still empty,
+/// a batch at a time so that other instances can write in between. `key` is
+/// the table's primary key. Returns how many rows were copied.
+#[allow(dead_code)] // The expand half of the next rename
+pub async fn backfill(
+    manager: &SchemaManager<'_>,
+    table: &str,
+    key: &str,
+    from: &str,
+    to: &str,
+) -> Result<u64, DbErr> {
+    let db = manager.get_connection();
+    let backend = manager.get_database_backend();
+    let mut copied = 0;
+    loop {
+        let batch = Query::select()
+            .column(Alias::new(key))
+            .from(Alias::new(table))
+            .and_where(Expr::col(Alias::new(to)).is_null())
+            .and_where(Expr::col(Alias::new(from)).is_not_null())
+            .limit(BACKFILL_BATCH)
+            .to_owned();
+        let update = Query::update()
+            .table(Alias::new(table))
+            .value(Alias::new(to), Expr::col(Alias::new(from)))
+            .and_where(Expr::col(Alias::new(key)).in_subquery(batch))
+            .to_owned();
+        let rows = db.execute(backend.build(&update)).await?.rows_affected();
+        if rows == 0 {
+            return Ok(copied);
+        }
+        copied += rows;
+        tokio::task::yield_now().await;
+    }
+}
+
+/// Removes `column` from `table` if it is still there. Only call this from a
+/// migration listed in [`CONTRACTIONS`]; it refuses to run while an older
+/// instance is still sending heartbeats in case one was left off the list.
+#[allow(dead_code)] // The contract half of the next rename
+pub async fn drop_column(
+    manager: &SchemaManager<'_>,
+    table: &str,
+    column: &str,
+) -> Result<(), DbErr> {
+    if !manager.has_column(table, column).await? {
+        return Ok(());
+    }
+    let older = older_instances(manager.get_connection()).await?;
+    if !older.is_empty() {
+        return Err(DbErr::Migration(format!(
+            "{table}.{column} can't be dropped while older instances are running: {}",
+            older.join(", ")
+        )));
+    }
+    manager
+        .alter_table(
+            Table::alter()
+                .table(Alias::new(table))
+                .drop_column(Alias::new(column))
+                .to_owned(),
+        )
+        .await
+}
+
+/// Creates the tables the lock and heartbeats are kept in. They are made
+/// here instead of in a migration because they are needed before migrating.
+async fn install(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let manager = SchemaManager::new(db);
+    manager
+        .create_table(
+            Table::create()
+                .table(SchemaLock::Table)
+                .if_not_exists()
+                .col(
+                    ColumnDef::new(SchemaLock::Id)
+                        .integer()
+                        .not_null()
+                        .primary_key(),
+                )
+                .col(ColumnDef::new(SchemaLock::Holder).string().not_null())
+                .col(ColumnDef::new(SchemaLock::Expires).date_time().not_null())
+                .to_owned(),
+        )
+        .await?;
+    manager
+        .create_table(
+            Table::create()
+                .table(SchemaInstances::Table)
+                .if_not_exists()
+                .col(
+                    ColumnDef::new(SchemaInstances::Id)
+                        .string()
+                        .not_null()
+                        .primary_key(),
+                )
+                .col(ColumnDef::new(SchemaInstances::Schema).integer().not_null())
+                .col(
+                    ColumnDef::new(SchemaInstances::Started)
+                        .date_time()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(SchemaInstances::LastSeen)
+                        .date_time()
+                        .not_null(),
+                )
+                .to_owned(),
+        )
+        .await
+}
+
+/// Takes or renews the migration lock, returning whether this instance holds
+/// it now
+async fn try_lock(db: &DatabaseConnection) -> Result<bool, DbErr> {
+    let backend = db.get_database_backend();
+    let now = Local::now().naive_local();
+    let expires = now + LEASE;
+    let insert = Query::insert()
+        .into_table(SchemaLock::Table)
+        .columns([SchemaLock::Id, SchemaLock::Holder, SchemaLock::Expires])
+        .values_panic([1.into(), INSTANCE.as_str().into(), expires.into()])
+        .on_conflict(OnConflict::column(SchemaLock::Id).do_nothing().to_owned())
+        .to_owned();
+    if db.execute(backend.build(&insert)).await?.rows_affected() == 1 {
+        return Ok(true);
+    }
+    let update = Query::update()
+        .table(SchemaLock::Table)
+        .values([
+            (SchemaLock::Holder, INSTANCE.as_str().into()),
+            (SchemaLock::Expires, expires.into()),
+        ])
+        .and_where(Expr::col(SchemaLock::Id).eq(1))
+        .and_where(
+            Expr::col(SchemaLock::Expires)
+                .lt(now)
+                .or(Expr::col(SchemaLock::Holder).eq(INSTANCE.as_str())),
+        )
+        .to_owned();
+    Ok(db.execute(backend.build(&update)).await?.rows_affected() == 1)
+}
+
+async fn unlock(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let delete = Query::delete()
+        .from_table(SchemaLock::Table)
+        .and_where(Expr::col(SchemaLock::Holder).eq(INSTANCE.as_str()))
+        .to_owned();
+    db.execute(db.get_database_backend().build(&delete)).await?;
+    Ok(())
+}
+
+async fn lock_holder(db: &DatabaseConnection) -> Result<Option<String>, DbErr> {
+    let select = Query::select()
+        .column(SchemaLock::Holder)
+        .from(SchemaLock::Table)
+        .and_where(Expr::col(SchemaLock::Id).eq(1))
+        .to_owned();
+    match db
+        .query_one(db.get_database_backend().build(&select))
+        .await?
+    {
+        Some(row) => row.try_get("", "holder").map(Some),
+        None => Ok(None),
+    }
+}
+
+/// The instances that are still running a version with fewer migrations
+async fn older_instances(db: &impl ConnectionTrait) -> Result<Vec<String>, DbErr> {
+    let select = Query::select()
+        .column(SchemaInstances::Id)
+        .from(SchemaInstances::Table)
+        .and_where(Expr::col(SchemaInstances::Schema).lt(known_schema()))
+        .and_where(Expr::col(SchemaInstances::LastSeen).gte(Local::now().naive_local() - STALE))
+        .to_owned();
+    db.query_all(db.get_database_backend().build(&select))
+        .await?
+        .into_iter()
+        .map(|row| row.try_get("", "id"))
+        .collect()
+}
+
+/// Runs the pending migrations that are safe while older instances are
+/// running, returning how many were held back
+async fn up(db: &DatabaseConnection) -> Result<usize, DbErr> {
+    let pending = Migrator::get_pending_migrations(db).await?;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+    let older = older_instances(db).await?;
+    let steps = if older.is_empty() {
+        pending.len()
+    } else {
+        pending
+            .iter()
+            .take_while(|migration| !CONTRACTIONS.contains(&migration.name()))
+            .count()
+    };
+    if steps > 0 {
+        info!("Running {steps} migration(s)");
+        Migrator::up(db, Some(steps as u32)).await?;
+    }
+    let held_back = pending.len() - steps;
+    if held_back > 0 {
+        info!(
+            "Holding back {held_back} migration(s) until older instances stop: {}",
+            older.join(", ")
+        );
+    }
+    Ok(held_back)
+}
+
+/// The migrations this version needs that haven't run, leaving out the
+/// contractions that are held back for older instances
+pub async fn unapplied(db: &DatabaseConnection) -> Result<Vec<String>, DbErr> {
+    Ok(Migrator::get_pending_migrations(db)
+        .await?
+        .iter()
+        .map(|migration| migration.name().to_string())
+        .take_while(|name| !CONTRACTIONS.contains(&name.as_str()))
+        .collect())
+}
+
+/// Brings the database up to this version's schema, waiting for any other
+/// instance that is migrating it first. The lock is renewed while migrating
+/// and released after, even if a migration fails.
+pub async fn migrate(db: &DatabaseConnection) -> Result<(), DbErr> {
+    install(db).await?;
+    let mut waiting = false;
+    while !try_lock(db).await? {
+        if !waiting {
+            waiting = true;
+            let holder = lock_holder(db).await?.unwrap_or_default();
+            info!("Waiting for instance {holder} to finish migrating");
+        }
+        tokio::time::sleep(LOCK_POLL).await;
+    }
+    let renewal = tokio::spawn({
+        let db = db.clone();
+        async move {
+            loop {
+                tokio::time::sleep((LEASE / 4).to_std().unwrap_or_default()).await;
+                if let Err(e) = try_lock(&db).await {
+                    error!("Failed to renew the migration lock: {e}");
+                }
+            }
+        }
+    });
+    let result = up(db).await;
+    renewal.abort();
+    unlock(db).await?;
+    result.map(|_| ())
+}
+
+/// Records that this instance is still running and which schema it knows
+async fn heartbeat(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let backend = db.get_database_backend();
+    let now = Local::now().naive_local();
+    let insert = Query::insert()
+        .into_table(SchemaInstances::Table)
+        .columns([
+            SchemaInstances::Id,
+            SchemaInstances::Schema,
+            SchemaInstances::Started,
+            SchemaInstances::LastSeen,
+        ])
+        .values_panic([
+            INSTANCE.as_str().into(),
+            known_schema().into(),
+            now.into(),
+            now.into(),
+        ])
+        .on_conflict(
+            OnConflict::column(SchemaInstances::Id)
+                .update_column(SchemaInstances::LastSeen)
+                .to_owned(),
+        )
+        .to_owned();
+    db.execute(backend.build(&insert)).await?;
+    let forget: NaiveDateTime = now - TimeDelta::days(1);
+    let delete = Query::delete()
+        .from_table(SchemaInstances::Table)
+        .and_where(Expr::col(SchemaInstances::LastSeen).lt(forget))
+        .to_owned();
+    db.execute(backend.build(&delete)).await?;
+    Ok(())
+}
+
+/// Keeps this instance's heartbeat up for as long as the server runs, and
+/// runs the migrations that were held back once the older instances are gone
+pub fn spawn_heartbeat(state: &'static UsrState) {
+    tokio::spawn(async move {
+        let db = &state.db;
+        loop {
+            let result = async {
+                heartbeat(db).await?;
+                if !Migrator::get_pending_migrations(db).await?.is_empty()
+                    && older_instances(db).await?.is_empty()
+                {
+                    migrate(db).await?;
+                }
+                Ok::<_, DbErr>(())
+            }
+            .await;
+            if let Err(e) = result {
+                error!("Failed to update the schema heartbeat: {e}");
+            }
+            tokio::time::sleep(HEARTBEAT).await;
+        }
+    });
+}
+
+#[derive(DeriveIden)]
+enum SchemaLock {
+    Table,
+    Id,
+    Holder,
+    Expires,
+}
+
+#[derive(DeriveIden)]
+enum SchemaInstances {
+    Table,
+    Id,
+    Schema,
+    Started,
+    LastSeen,
+}
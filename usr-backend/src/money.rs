@@ -0,0 +1,89 @@
+use std::sync::OnceLock;
+
+use rust_decimal::RoundingStrategy;
+use sea_orm::prelude::Decimal;
+use serde::Deserialize;
+
+/// Unit costs can be finer than a cent, since bulk parts are often priced
+/// by the thousand, but no finer than this
+const MAX_UNIT_COST_PLACES: u32 = 4;
+
+/// How amounts are rounded to the cent
+#[derive(Deserialize, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// Halves round away from zero, like the finance office's spreadsheets
+    #[default]
+    HalfUp,
+    /// Halves round to the even cent
+    Bankers,
+}
+
+static ROUNDING: OnceLock<Rounding> = OnceLock::new();
+
+/// Sets the rounding policy for the rest of the process. Only the first call
+/// has any effect.
+pub fn init(rounding: Rounding) {
+    let _ = ROUNDING.set(rounding);
+}
+
+/// Rounds `amount` to the cent, always keeping two decimal places
+pub fn round(amount: Decimal) -> Decimal {
+    let strategy = match ROUNDING.get().copied().unwrap_or_default() {
+        Rounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+        Rounding::Bankers => RoundingStrategy::MidpointNearestEven,
+    };
+    let mut rounded = amount.round_dp_with_strategy(2, strategy);
+    rounded.rescale(2);
+    rounded
+}
+
+/// The cost of an order line, rounded to the cent. Totals are sums of these,
+/// so that every webhook, report and export agrees to the cent.
+pub fn subtotal(count: i64, unit_cost: Decimal) -> Decimal {
+    round(Decimal::from(count) * unit_cost)
+}
+
+/// How amounts are written in messages, eg. `$1,234.50` or `-$20.00`. Unit
+/// costs finer than a cent keep their extra places, eg. `$0.0125`.
+pub fn dollars(amount: Decimal) -> String {
+    let mut amount = amount.normalize();
+    if amount.scale() < 2 {
+        amount.rescale(2);
+    }
+    let text = amount.abs().to_string();
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    let sign = if amount.is_sign_negative() && !amount.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    format!("{sign}${grouped}.{fraction}")
+}
+
+/// Rejects unit costs that are negative or finer than
+/// `MAX_UNIT_COST_PLACES` decimal places
+pub fn validate_unit_cost(unit_cost: Decimal) -> Result<(), &'static str> {
+    if unit_cost.is_sign_negative() && !unit_cost.is_zero() {
+        Err("Unit cost cannot be negative")
+    } else if unit_cost.normalize().scale() > MAX_UNIT_COST_PLACES {
+        Err("Unit cost cannot have more than 4 decimal places")
+    } else {
+        Ok(())
+    }
+}
+
+/// Converts a unit cost into dollars at `rate` dollars per unit of its
+/// currency, keeping as many decimal places as a unit cost is allowed
+pub fn convert(unit_cost: Decimal, rate: Decimal) -> Decimal {
+    (unit_cost * rate)
+        .round_dp_with_strategy(MAX_UNIT_COST_PLACES, RoundingStrategy::MidpointAwayFromZero)
+        .normalize()
+}
@@ -0,0 +1,87 @@
+/// A field of a `multipart/form-data` form, such as a file being uploaded
+pub struct Part<'a> {
+    pub name: String,
+    /// Set for files
+    pub file_name: Option<String>,
+    pub content_type: Option<String>,
+    pub data: &'a [u8],
+}
+
+/// The boundary between parts, from the request's `Content-Type`
+pub fn boundary(content_type: &str) -> Option<&str> {
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case("boundary") {
+            Some(value.trim().trim_matches('"')).filter(|value| !value.is_empty())
+        } else {
+            None
+        }
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// The value of `param` in a header such as
+/// `form-data; name="file"; filename="receipt.pdf"`
+fn param(header: &str, param: &str) -> Option<String> {
+    header.split(';').skip(1).find_map(|part| {
+        let (key, value) = part.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case(param) {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Splits `body` into its parts
+pub fn parse<'a>(body: &'a [u8], boundary: &str) -> Result<Vec<Part<'a>>, &'static str> {
+    const MALFORMED: &str = "Malformed multipart body";
+    let delimiter = format!("--{boundary}");
+    let start = find(body, delimiter.as_bytes()).ok_or(MALFORMED)?;
+    let mut rest = &body[start + delimiter.len()..];
+    // Each part ends at a line break followed by the next delimiter
+    let delimiter = format!("\r\n--{boundary}");
+    let mut parts = vec![];
+
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        rest = rest.strip_prefix(b"\r\n").ok_or(MALFORMED)?;
+        let headers_end = find(rest, b"\r\n\r\n").ok_or(MALFORMED)?;
+        let headers = std::str::from_utf8(&rest[..headers_end]).map_err(|_| MALFORMED)?;
+        rest = &rest[headers_end + 4..];
+        let end = find(rest, delimiter.as_bytes()).ok_or(MALFORMED)?;
+
+        let mut name = None;
+        let mut file_name = None;
+        let mut content_type = None;
+        for line in headers.split("\r\n") {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            if key.trim().eq_ignore_ascii_case("content-disposition") {
+                name = param(value, "name");
+                file_name = param(value, "filename");
+            } else if key.trim().eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.trim().to_string());
+            }
+        }
+        parts.push(Part {
+            name: name.ok_or(MALFORMED)?,
+            file_name,
+            content_type,
+            data: &rest[..end],
+        });
+        rest = &rest[end + delimiter.len()..];
+    }
+}
@@ -0,0 +1,606 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use axum::{extract::State, routing::get, Json, Router};
+use discord_webhook2::webhook::DiscordWebhook;
+use parking_lot::RwLock;
+use sea_orm::{
+    prelude::Decimal, sea_query::StringLen, DatabaseConnection, DeriveActiveEnum, EnumIter,
+    Iterable,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{scheduler::Team, webhook::BatchedWebhook, UsrState};
+
+mod digest;
+mod email;
+mod slack;
+mod stream;
+
+pub use digest::{spawn as spawn_digest, DigestConfig};
+pub use email::EmailNotifier;
+pub use slack::SlackWebhook;
+
+/// What a notification is about, which decides where it is sent
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Deserialize, Serialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Topic {
+    /// An order was placed, or changed or cancelled before it was bought
+    #[sea_orm(string_value = "N")]
+    NewOrder,
+    /// An order has waited too long for approval
+    #[sea_orm(string_value = "R")]
+    OrderReminder,
+    /// An order moved along, or its stock did
+    #[sea_orm(string_value = "U")]
+    OrderUpdate,
+    /// A lead approved or rejected an order
+    #[sea_orm(string_value = "A")]
+    OrderApproval,
+    /// Budget alerts, rollovers and the weekly summary
+    #[sea_orm(string_value = "S")]
+    Spending,
+    /// Equipment, safety, backups and the database
+    #[sea_orm(string_value = "M")]
+    Maintenance,
+    /// Printer filament running out
+    #[sea_orm(string_value = "L")]
+    LowStock,
+}
+
+/// Something worth telling people about
+#[derive(Clone, Debug, Serialize)]
+pub struct Notification {
+    pub topic: Topic,
+    /// Notifications with the same key that are still waiting to be sent
+    /// replace one another, eg. an order id
+    pub key: u32,
+    /// Written in Discord's markdown, which other backends adapt
+    pub text: String,
+    /// What Discord webhooks send instead of `text`, if it is laid out as an
+    /// embed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embed: Option<Embed>,
+}
+
+/// A notification laid out as a Discord embed, eg. a digest with a field for
+/// each team
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Embed {
+    /// Sent above the embed, since nobody mentioned in one is notified
+    pub content: String,
+    pub title: String,
+    pub color: u32,
+    /// Each field's name and value, in Discord's markdown
+    pub fields: Vec<(String, String)>,
+}
+
+/// Somewhere notifications can be sent, eg. a Discord webhook
+pub trait NotificationDispatcher: Send + Sync {
+    /// Recorded in the delivery history, eg. `new_orders`
+    fn name(&self) -> &str;
+
+    /// Sends or queues `notification` in the background
+    fn dispatch(&'static self, notification: &Notification);
+
+    /// The Discord webhook behind this backend, if it is one, for retrying
+    /// messages from the outbox
+    fn webhook(&self) -> Option<&BatchedWebhook> {
+        None
+    }
+}
+
+/// Where else to send notifications, besides the Discord webhooks that
+/// each have their own setting
+#[derive(Deserialize)]
+pub struct BackendConfig {
+    /// Recorded in the delivery history
+    pub name: String,
+    #[serde(flatten)]
+    pub backend: Backend,
+    /// What gets sent to it
+    pub topics: Vec<Topic>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind")]
+pub enum Backend {
+    Discord {
+        url: String,
+    },
+    /// A Slack incoming webhook
+    Slack {
+        url: String,
+    },
+    /// Posted to a mail provider's send API as `{from, to, subject, text}`,
+    /// like replies to order emails
+    Email {
+        url: String,
+        #[serde(default)]
+        token: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+}
+
+impl BackendConfig {
+    pub fn validate(&self, problems: &mut Vec<String>) {
+        let name = &self.name;
+        if name.trim().is_empty() {
+            problems.push("notifications: each backend needs a name".to_string());
+        }
+        if self.topics.is_empty() {
+            problems.push(format!("notifications.{name}: has no topics"));
+        }
+        let url = match &self.backend {
+            Backend::Discord { url } | Backend::Slack { url } => url,
+            Backend::Email { url, to, .. } => {
+                if to.is_empty() {
+                    problems.push(format!("notifications.{name}: has no recipients"));
+                }
+                url
+            }
+        };
+        match url.parse::<axum::http::Uri>() {
+            Ok(uri) if matches!(uri.scheme_str(), Some("http" | "https")) => {}
+            Ok(_) => problems.push(format!(
+                "notifications.{name}: {url:?} is not an http(s) url"
+            )),
+            Err(e) => problems.push(format!(
+                "notifications.{name}: {url:?} is not a valid url: {e}"
+            )),
+        }
+    }
+}
+
+struct Route {
+    topics: HashSet<Topic>,
+    backend: Box<dyn NotificationDispatcher>,
+}
+
+/// A backend that takes over notifications about its topics from the other
+/// backends while it is active, eg. a competition's own channel
+struct Divert {
+    topics: HashSet<Topic>,
+    backend: Box<dyn NotificationDispatcher>,
+    active: AtomicBool,
+}
+
+/// A Discord webhook that one team's notifications about a topic go to
+/// instead of the backends registered for the topic
+struct TeamRoute {
+    /// The team webhook's id in the database
+    id: i64,
+    team: Team,
+    topic: Topic,
+    backend: &'static BatchedWebhook,
+}
+
+/// What team webhooks set up at runtime are built with
+pub struct TeamWebhookSettings {
+    pub db: DatabaseConnection,
+    pub dedupe_window: Duration,
+    /// Sends them to `/dev/webhook-sink` instead, like the configured ones
+    pub sink: bool,
+}
+
+/// Sends each notification to every backend registered for its topic, and
+/// to anyone following `/api/notifications/stream`
+#[derive(Default)]
+pub struct Notifier {
+    routes: Vec<Route>,
+    divert: Option<Divert>,
+    team_routes: RwLock<Vec<TeamRoute>>,
+    /// Left out in sandbox mode, where team webhooks are stored but not sent to
+    team_settings: Option<TeamWebhookSettings>,
+    /// Left out unless order updates are sent as digests
+    digest: Option<digest::Digest>,
+    stream: stream::NotificationStream,
+}
+
+impl Notifier {
+    pub fn register(
+        &mut self,
+        topics: impl IntoIterator<Item = Topic>,
+        backend: impl NotificationDispatcher + 'static,
+    ) {
+        self.routes.push(Route {
+            topics: topics.into_iter().collect(),
+            backend: Box::new(backend),
+        });
+    }
+
+    /// Registers a backend that notifications about `topics` are sent to
+    /// instead of their usual backends while diverted
+    pub fn register_divert(
+        &mut self,
+        topics: impl IntoIterator<Item = Topic>,
+        backend: impl NotificationDispatcher + 'static,
+    ) {
+        self.divert = Some(Divert {
+            topics: topics.into_iter().collect(),
+            backend: Box::new(backend),
+            active: AtomicBool::new(false),
+        });
+    }
+
+    /// Starts or stops sending notifications to the divert backend, if one
+    /// was registered
+    pub fn set_diverted(&self, diverted: bool) {
+        if let Some(divert) = &self.divert {
+            divert.active.store(diverted, Ordering::Relaxed);
+        }
+    }
+
+    /// Registers a backend from the config. Discord backends are sent to
+    /// `sink_url` instead if it returns one.
+    pub fn register_config(
+        &mut self,
+        config: BackendConfig,
+        db: &DatabaseConnection,
+        sink_url: impl Fn(&str) -> Option<String>,
+        dedupe_window: Duration,
+    ) -> anyhow::Result<()> {
+        let BackendConfig {
+            name,
+            backend,
+            topics,
+        } = config;
+        match backend {
+            Backend::Discord { url } => {
+                let url = sink_url(&name).unwrap_or(url);
+                self.register(
+                    topics,
+                    BatchedWebhook::new(
+                        name,
+                        DiscordWebhook::new(url)?,
+                        db.clone(),
+                        dedupe_window,
+                    ),
+                );
+            }
+            Backend::Slack { url } => {
+                self.register(topics, SlackWebhook::new(name, url, db.clone()));
+            }
+            Backend::Email {
+                url,
+                token,
+                from,
+                to,
+            } => {
+                self.register(
+                    topics,
+                    EmailNotifier::new(name, url, token, from, to, db.clone()),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends order updates, and new orders that don't cost enough to be
+    /// urgent, as a digest every so often instead of one by one
+    pub fn enable_digest(&mut self, config: DigestConfig) {
+        self.digest = Some(digest::Digest::new(config));
+    }
+
+    /// Minutes between digests, if they are on
+    pub fn digest_minutes(&self) -> Option<u32> {
+        self.digest.as_ref().map(digest::Digest::every_minutes)
+    }
+
+    /// Whether a new order costing `cost` in total is announced right away,
+    /// rather than in the next digest
+    pub fn is_urgent(&self, cost: Decimal) -> bool {
+        self.digest
+            .as_ref()
+            .is_none_or(|digest| digest.is_urgent(cost))
+    }
+
+    /// Lets team webhooks be set up, which they aren't in sandbox mode
+    pub fn enable_team_webhooks(&mut self, settings: TeamWebhookSettings) {
+        self.team_settings = Some(settings);
+    }
+
+    /// Starts sending `team`'s notifications about `topic` to `url`, in place
+    /// of the team webhook with the same `id` if there was one. Each is
+    /// recorded in the delivery history as `team_{id}`.
+    pub fn set_team_webhook(
+        &self,
+        id: i64,
+        team: Team,
+        topic: Topic,
+        url: &str,
+    ) -> anyhow::Result<()> {
+        let Some(settings) = &self.team_settings else {
+            return Ok(());
+        };
+        let destination = format!("team_{id}");
+        let url = if settings.sink {
+            format!("http://127.0.0.1/dev/webhook-sink/{destination}")
+        } else {
+            url.to_string()
+        };
+        // Leaked rather than dropped when replaced, as messages batched for
+        // it may still be waiting to be sent
+        let backend: &'static BatchedWebhook = Box::leak(Box::new(BatchedWebhook::new(
+            destination,
+            DiscordWebhook::new(url)?,
+            settings.db.clone(),
+            settings.dedupe_window,
+        )));
+        let mut team_routes = self.team_routes.write();
+        team_routes.retain(|route| route.id != id);
+        team_routes.push(TeamRoute {
+            id,
+            team,
+            topic,
+            backend,
+        });
+        Ok(())
+    }
+
+    /// Stops sending to the team webhook with `id`
+    pub fn remove_team_webhook(&self, id: i64) {
+        self.team_routes.write().retain(|route| route.id != id);
+    }
+
+    /// Stops sending to every team webhook, so that they can be loaded again
+    pub fn clear_team_webhooks(&self) {
+        self.team_routes.write().clear();
+    }
+
+    /// Whether any backend is sent notifications about `topic`, so that
+    /// messages aren't put together for nobody. The stream isn't counted.
+    pub fn routes(&self, topic: Topic) -> bool {
+        self.routes
+            .iter()
+            .any(|route| route.topics.contains(&topic))
+            || self
+                .divert
+                .as_ref()
+                .is_some_and(|divert| divert.topics.contains(&topic))
+            || self
+                .team_routes
+                .read()
+                .iter()
+                .any(|route| route.topic == topic)
+    }
+
+    /// The Discord webhook registered as `name`
+    pub fn webhook(&self, name: &str) -> Option<&BatchedWebhook> {
+        self.routes
+            .iter()
+            .map(|route| &route.backend)
+            .chain(self.divert.as_ref().map(|divert| &divert.backend))
+            .find(|backend| backend.name() == name)
+            .and_then(|backend| backend.webhook())
+            .or_else(|| {
+                self.team_routes
+                    .read()
+                    .iter()
+                    .map(|route| route.backend)
+                    .find(|backend| backend.name() == name)
+            })
+    }
+
+    /// Sends every Discord webhook's batched messages right away, along with
+    /// the digest, for when the server is stopping
+    pub async fn flush(&'static self) {
+        self.post_digest();
+        let team_backends: Vec<&'static BatchedWebhook> =
+            self.team_routes.read().iter().map(|route| route.backend).collect();
+        let backends = self
+            .routes
+            .iter()
+            .map(|route| &route.backend)
+            .chain(self.divert.as_ref().map(|divert| &divert.backend))
+            .filter_map(|backend| backend.webhook())
+            .chain(team_backends);
+        for backend in backends {
+            backend.flush().await;
+        }
+    }
+
+    pub fn send(&'static self, topic: Topic, key: u32, text: String) {
+        self.deliver(
+            None,
+            Notification {
+                topic,
+                key,
+                text,
+                embed: None,
+            },
+        );
+    }
+
+    /// Sends a notification about one of `team`'s orders, to the team's own
+    /// webhooks for `topic` if it has any, and to the usual backends if not
+    pub fn send_for(&'static self, team: Team, topic: Topic, key: u32, text: String) {
+        self.deliver(
+            Some(team),
+            Notification {
+                topic,
+                key,
+                text,
+                embed: None,
+            },
+        );
+    }
+
+    /// Like [`Notifier::send_for`], but if digests are on, the backends only
+    /// hear about it in the next digest, where it is listed as `summary`
+    /// under `team` and `status`. Topics diverted while competition mode is
+    /// on are still sent right away. Anyone `text` mentions has to be mentioned
+    /// in `summary` too, or they won't hear about it. Followers of the stream
+    /// still get `text` right away.
+    pub fn send_digested(
+        &'static self,
+        team: Team,
+        topic: Topic,
+        key: u32,
+        status: impl Display,
+        summary: String,
+        text: String,
+    ) {
+        let notification = Notification {
+            topic,
+            key,
+            text,
+            embed: None,
+        };
+        match &self.digest {
+            // The event's channel needs to hear about orders right away
+            Some(digest) if !self.diverting(topic) => {
+                digest.add(topic, team, key, status.to_string(), summary);
+                self.stream.publish(notification);
+            }
+            _ => self.deliver(Some(team), notification),
+        }
+    }
+
+    /// Sends what has been held back for the digest, one message for each
+    /// topic. Teams with webhooks of their own for a topic are sent their
+    /// part of it there instead.
+    pub fn post_digest(&'static self) {
+        let Some(digest) = &self.digest else {
+            return;
+        };
+        for (topic, entries) in digest.take() {
+            let (own, shared): (Vec<_>, Vec<_>) = entries.iter().partition(|entry| {
+                self.team_routes
+                    .read()
+                    .iter()
+                    .any(|route| route.team == entry.team() && route.topic == topic)
+            });
+            for team in Team::iter() {
+                let ours: Vec<_> = own
+                    .iter()
+                    .copied()
+                    .filter(|entry| entry.team() == team)
+                    .collect();
+                if !ours.is_empty() {
+                    let notification = Notification {
+                        topic,
+                        key: digest.next_key(),
+                        text: digest::message(topic, &ours),
+                        embed: digest::embed(topic, &ours),
+                    };
+                    self.dispatch(Some(team), &notification);
+                }
+            }
+            if !shared.is_empty() {
+                let notification = Notification {
+                    topic,
+                    key: digest.next_key(),
+                    text: digest::message(topic, &shared),
+                    embed: digest::embed(topic, &shared),
+                };
+                self.dispatch(None, &notification);
+            }
+        }
+    }
+
+    fn deliver(&'static self, team: Option<Team>, notification: Notification) {
+        self.dispatch(team, &notification);
+        self.stream.publish(notification);
+    }
+
+    /// Whether notifications about `topic` are going to the divert backend
+    /// instead of their usual ones, eg. while competition mode is on
+    fn diverting(&self, topic: Topic) -> bool {
+        self.divert.as_ref().is_some_and(|divert| {
+            divert.active.load(Ordering::Relaxed) && divert.topics.contains(&topic)
+        })
+    }
+
+    /// Sends `notification` to the backends it is for, without the stream
+    fn dispatch(&'static self, team: Option<Team>, notification: &Notification) {
+        let topic = notification.topic;
+        match &self.divert {
+            Some(divert) if self.diverting(topic) => {
+                divert.backend.dispatch(notification);
+            }
+            _ => {
+                let mut sent = false;
+                if let Some(team) = team {
+                    for route in self.team_routes.read().iter() {
+                        if route.team == team && route.topic == topic {
+                            route.backend.dispatch(notification);
+                            sent = true;
+                        }
+                    }
+                }
+                if !sent {
+                    for route in &self.routes {
+                        if route.topics.contains(&topic) {
+                            route.backend.dispatch(notification);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RouteInfo<'a> {
+    name: &'a str,
+    topics: Vec<Topic>,
+    /// For the divert backend, whether it is taking over its topics
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diverting: Option<bool>,
+    /// For a team webhook, the team whose notifications it is sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    team: Option<Team>,
+}
+
+fn sorted(topics: &HashSet<Topic>) -> Vec<Topic> {
+    let mut topics: Vec<_> = topics.iter().copied().collect();
+    topics.sort_by_key(|topic| *topic as u8);
+    topics
+}
+
+/// Every registered backend and what it is sent, then the team webhooks
+#[axum::debug_handler]
+async fn get_routes(State(state): State<&'static UsrState>) -> Json<Vec<RouteInfo<'static>>> {
+    let notifier = &state.notifier;
+    let team_routes = notifier.team_routes.read();
+    Json(
+        notifier
+            .routes
+            .iter()
+            .map(|route| RouteInfo {
+                name: route.backend.name(),
+                topics: sorted(&route.topics),
+                diverting: None,
+                team: None,
+            })
+            .chain(notifier.divert.as_ref().map(|divert| RouteInfo {
+                name: divert.backend.name(),
+                topics: sorted(&divert.topics),
+                diverting: Some(divert.active.load(Ordering::Relaxed)),
+                team: None,
+            }))
+            .chain(team_routes.iter().map(|route| RouteInfo {
+                name: route.backend.name(),
+                topics: vec![route.topic],
+                diverting: None,
+                team: Some(route.team),
+            }))
+            .collect(),
+    )
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new().route("/stream", get(stream::notifications))
+}
+
+pub fn admin_router() -> Router<&'static UsrState> {
+    Router::new().route("/notifications", get(get_routes))
+}
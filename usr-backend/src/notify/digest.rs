@@ -0,0 +1,253 @@
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use sea_orm::{prelude::Decimal, Iterable};
+use serde::Deserialize;
+
+use crate::{
+    jobs::{self, Retry, Schedule},
+    scheduler::Team,
+    UsrState,
+};
+
+use super::{Embed, Topic};
+
+/// Digests are keyed from here up, apart from orders and the other reports,
+/// each by its own number so that one still waiting to be sent isn't
+/// replaced by the next
+const FIRST_KEY: u32 = u32::MAX / 8 * 3;
+/// Keys wrap around after this many digests
+const KEYS: u32 = 4096;
+/// Discord's limits on an embed and the message it is sent with, past which
+/// the digest is sent as text
+const MAX_CONTENT_LEN: usize = 2000;
+const MAX_FIELDS: usize = 25;
+const MAX_FIELD_LEN: usize = 1024;
+const MAX_EMBED_LEN: usize = 6000;
+const NEW_ORDERS_COLOR: u32 = 0x57F287;
+const ORDER_UPDATES_COLOR: u32 = 0x5865F2;
+
+/// Posts order updates as one summary every few minutes instead of a message
+/// each, for days busy enough to flood the channel. Deadline alerts and new
+/// orders that cost enough are still sent right away, as is everything the
+/// event's channel is sent while competition mode is on.
+#[derive(Deserialize)]
+pub struct DigestConfig {
+    /// How often the summary is posted
+    pub every_minutes: u32,
+    /// New orders costing at least this in total are sent right away, and
+    /// cheaper ones wait for the digest. Left out, every new order is sent
+    /// right away.
+    #[serde(default)]
+    pub urgent_cost: Option<Decimal>,
+}
+
+impl DigestConfig {
+    pub fn validate(&self, key: &str, problems: &mut Vec<String>) {
+        if self.every_minutes == 0 {
+            problems.push(format!("{key}.every_minutes: must be at least 1"));
+        }
+        if self.urgent_cost.is_some_and(|cost| cost.is_sign_negative()) {
+            problems.push(format!("{key}.urgent_cost: must not be negative"));
+        }
+    }
+}
+
+/// A notification waiting for the next digest
+pub struct Entry {
+    topic: Topic,
+    team: Team,
+    key: u32,
+    status: String,
+    /// eg. `USR-2025-0042 Drive motors`, followed by any lines mentioning
+    /// someone who should hear about it
+    summary: String,
+}
+
+impl Entry {
+    pub fn team(&self) -> Team {
+        self.team
+    }
+}
+
+pub struct Digest {
+    config: DigestConfig,
+    entries: Mutex<Vec<Entry>>,
+    posted: AtomicU32,
+}
+
+impl Digest {
+    pub fn new(config: DigestConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::default(),
+            posted: AtomicU32::new(0),
+        }
+    }
+
+    pub fn every_minutes(&self) -> u32 {
+        self.config.every_minutes
+    }
+
+    /// Whether a new order costing `cost` is sent right away
+    pub fn is_urgent(&self, cost: Decimal) -> bool {
+        self.config.urgent_cost.is_none_or(|urgent| cost >= urgent)
+    }
+
+    /// Adds the notification keyed `key` to the next digest, in place of
+    /// any it is already in, so that only an order's latest status is listed
+    pub fn add(&self, topic: Topic, team: Team, key: u32, status: String, summary: String) {
+        let mut entries = self.entries.lock();
+        entries.retain(|entry| entry.topic != topic || entry.key != key);
+        entries.push(Entry {
+            topic,
+            team,
+            key,
+            status,
+            summary,
+        });
+    }
+
+    /// Takes what has been added since the last digest, by topic
+    pub fn take(&self) -> Vec<(Topic, Vec<Entry>)> {
+        let mut entries = std::mem::take(&mut *self.entries.lock());
+        Topic::iter()
+            .filter_map(|topic| {
+                let (ours, rest) = entries.drain(..).partition(|entry| entry.topic == topic);
+                entries = rest;
+                (!ours.is_empty()).then_some((topic, ours))
+            })
+            .collect()
+    }
+
+    /// A key that no digest still waiting to be sent has
+    pub fn next_key(&self) -> u32 {
+        FIRST_KEY + self.posted.fetch_add(1, Ordering::Relaxed) % KEYS
+    }
+}
+
+fn title(topic: Topic) -> &'static str {
+    match topic {
+        Topic::NewOrder => "New Orders",
+        _ => "Order Updates",
+    }
+}
+
+/// The summaries of `team`'s entries, by status
+fn by_status<'a>(team: Team, entries: &[&'a Entry]) -> Vec<(&'a str, Vec<&'a str>)> {
+    let mut statuses: Vec<(&str, Vec<&str>)> = vec![];
+    for entry in entries.iter().filter(|entry| entry.team == team) {
+        match statuses
+            .iter_mut()
+            .find(|(status, _)| *status == entry.status)
+        {
+            Some((_, summaries)) => summaries.push(&entry.summary),
+            None => statuses.push((&entry.status, vec![&entry.summary])),
+        }
+    }
+    statuses
+}
+
+/// The digest of `entries` as text, for backends other than Discord and for
+/// digests too long for an embed, grouped by team and then by status, eg.
+///
+/// ```text
+/// **Order Updates** (3)
+/// **Mechanical: Shipped**
+/// USR-2025-0042 Drive motors
+/// USR-2025-0043 Bearings
+/// **Software: InStorage**
+/// USR-2025-0040 Jetson
+/// **Pick Up:** <@1234>
+/// ```
+pub fn message(topic: Topic, entries: &[&Entry]) -> String {
+    let mut msg = format!("**{}** ({})", title(topic), entries.len());
+    for team in Team::iter() {
+        for (status, summaries) in by_status(team, entries) {
+            msg.push_str(&format!("\n**{team}: {status}**"));
+            for summary in summaries {
+                msg.push_str(&format!("\n{summary}"));
+            }
+        }
+    }
+    msg
+}
+
+/// Everyone `text` mentions, eg. `<@1234>`
+fn mentions(mut text: &str) -> Vec<&str> {
+    let mut mentions = vec![];
+    while let Some(start) = text.find("<@") {
+        let Some(len) = text[start..].find('>') else {
+            break;
+        };
+        mentions.push(&text[start..=start + len]);
+        text = &text[start + len + 1..];
+    }
+    mentions
+}
+
+/// The digest of `entries` as an embed with a field for each team, or
+/// `None` if it is too long for one. Everyone the entries mention is
+/// mentioned above it.
+pub fn embed(topic: Topic, entries: &[&Entry]) -> Option<Embed> {
+    let mut mentioned: Vec<&str> = vec![];
+    for mention in entries.iter().flat_map(|entry| mentions(&entry.summary)) {
+        if !mentioned.contains(&mention) {
+            mentioned.push(mention);
+        }
+    }
+    let content = mentioned.join(" ");
+    let title = format!("{} ({})", title(topic), entries.len());
+    let fields: Vec<_> = Team::iter()
+        .filter_map(|team| {
+            let statuses = by_status(team, entries);
+            let value = statuses
+                .into_iter()
+                .map(|(status, summaries)| format!("**{status}**\n{}", summaries.join("\n")))
+                .collect::<Vec<_>>()
+                .join("\n");
+            (!value.is_empty()).then(|| (team.to_string(), value))
+        })
+        .collect();
+    let len = title.len()
+        + fields
+            .iter()
+            .map(|(name, value)| name.len() + value.len())
+            .sum::<usize>();
+    if fields.len() > MAX_FIELDS
+        || len > MAX_EMBED_LEN
+        || content.len() > MAX_CONTENT_LEN
+        || fields.iter().any(|(_, value)| value.len() > MAX_FIELD_LEN)
+    {
+        return None;
+    }
+    Some(Embed {
+        content,
+        title,
+        color: match topic {
+            Topic::NewOrder => NEW_ORDERS_COLOR,
+            _ => ORDER_UPDATES_COLOR,
+        },
+        fields,
+    })
+}
+
+/// Posts the digest every `every_minutes`, if digests are on
+pub fn spawn(state: &'static UsrState) {
+    let Some(every_minutes) = state.notifier.digest_minutes() else {
+        return;
+    };
+    jobs::spawn(
+        state,
+        "notification_digest",
+        Schedule::Every(Duration::from_secs(60 * u64::from(every_minutes))),
+        Retry::NEVER,
+        |state| async move {
+            state.notifier.post_digest();
+            Ok(())
+        },
+    );
+}
@@ -0,0 +1,93 @@
+use std::time::Instant;
+
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use tracing::error;
+
+use crate::webhook::record_delivery;
+
+use super::{Notification, NotificationDispatcher};
+
+/// Emails each notification as it happens, through a mail provider's send
+/// API
+pub struct EmailNotifier {
+    name: String,
+    url: String,
+    token: Option<String>,
+    from: String,
+    to: String,
+    client: reqwest::Client,
+    db: DatabaseConnection,
+}
+
+impl EmailNotifier {
+    pub fn new(
+        name: String,
+        url: String,
+        token: Option<String>,
+        from: String,
+        to: Vec<String>,
+        db: DatabaseConnection,
+    ) -> Self {
+        Self {
+            name,
+            url,
+            token,
+            from,
+            to: to.join(", "),
+            client: reqwest::Client::new(),
+            db,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Email<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    text: &'a str,
+}
+
+/// Plain text, since emails don't render Discord's markdown. The first line
+/// is the subject.
+fn to_plain(text: &str) -> (String, String) {
+    let text = text.replace("**", "");
+    let subject = text.lines().next().unwrap_or_default().to_string();
+    (subject, text)
+}
+
+impl NotificationDispatcher for EmailNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dispatch(&'static self, notification: &Notification) {
+        let (subject, text) = to_plain(&notification.text);
+        let key = notification.key;
+        tokio::spawn(async move {
+            let start = Instant::now();
+            let mut request = self.client.post(&self.url).json(&Email {
+                from: &self.from,
+                to: &self.to,
+                subject: &subject,
+                text: &text,
+            });
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token);
+            }
+            let result = request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            let result = match result {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    error!("Failed to email notification: {e}");
+                    Err((e.status().map(|s| s.as_u16()), e.to_string()))
+                }
+            };
+            record_delivery(&self.db, &self.name, &[key], &text, start, result).await;
+        });
+    }
+}
@@ -0,0 +1,67 @@
+use std::time::Instant;
+
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use tracing::error;
+
+use crate::webhook::record_delivery;
+
+use super::{Notification, NotificationDispatcher};
+
+/// Posts each notification to a Slack incoming webhook as it happens
+pub struct SlackWebhook {
+    name: String,
+    url: String,
+    client: reqwest::Client,
+    db: DatabaseConnection,
+}
+
+impl SlackWebhook {
+    pub fn new(name: String, url: String, db: DatabaseConnection) -> Self {
+        Self {
+            name,
+            url,
+            client: reqwest::Client::new(),
+            db,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SlackMessage<'a> {
+    text: &'a str,
+}
+
+/// Slack's markdown bolds with single asterisks
+fn to_mrkdwn(text: &str) -> String {
+    text.replace("**", "*")
+}
+
+impl NotificationDispatcher for SlackWebhook {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dispatch(&'static self, notification: &Notification) {
+        let text = to_mrkdwn(&notification.text);
+        let key = notification.key;
+        tokio::spawn(async move {
+            let start = Instant::now();
+            let result = self
+                .client
+                .post(&self.url)
+                .json(&SlackMessage { text: &text })
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            let result = match result {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    error!("Failed to post to Slack: {e}");
+                    Err((e.status().map(|s| s.as_u16()), e.to_string()))
+                }
+            };
+            record_delivery(&self.db, &self.name, &[key], &text, start, result).await;
+        });
+    }
+}
@@ -0,0 +1,63 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+use crate::UsrState;
+
+use super::{Notification, Topic};
+
+/// How far a slow subscriber can fall behind before it starts missing
+/// notifications
+const BACKLOG: usize = 256;
+
+/// Fans notifications out to everyone following `/api/notifications/stream`
+pub struct NotificationStream {
+    sender: broadcast::Sender<Notification>,
+}
+
+impl Default for NotificationStream {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(BACKLOG).0,
+        }
+    }
+}
+
+impl NotificationStream {
+    pub fn publish(&self, notification: Notification) {
+        // Fails only when nobody is subscribed
+        let _ = self.sender.send(notification);
+    }
+}
+
+#[derive(Deserialize)]
+pub struct StreamFilter {
+    #[serde(default)]
+    topic: Option<Topic>,
+}
+
+/// Pushes every notification as it is sent, or only those about `topic`
+#[axum::debug_handler]
+pub async fn notifications(
+    State(state): State<&'static UsrState>,
+    Query(StreamFilter { topic }): Query<StreamFilter>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.notifier.stream.sender.subscribe()).filter_map(
+        move |notification| {
+            let notification = notification.ok()?;
+            if topic.is_some_and(|topic| topic != notification.topic) {
+                return None;
+            }
+            Some(Ok(Event::default().event("notification").data(
+                serde_json::to_string(&notification).unwrap_or_default(),
+            )))
+        },
+    );
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,367 @@
+use std::collections::{BTreeSet, HashMap};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use sea_orm::{
+    sqlx::types::chrono::Local, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait,
+    EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    auth::{Caller, Role},
+    backup::backup_db,
+    manifest, schema, UsrState,
+};
+
+mod list;
+mod list_item;
+mod template_item;
+
+#[derive(Deserialize, Serialize)]
+struct TemplateItem {
+    item: String,
+    quantity: u32,
+}
+
+#[derive(Deserialize)]
+struct SetTemplate {
+    template: String,
+    items: Vec<TemplateItem>,
+}
+
+#[axum::debug_handler]
+async fn set_template(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(set_template): Json<SetTemplate>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot set packing templates", caller.role),
+        )
+            .into_response();
+    }
+    if set_template.template.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                template_item::Entity::delete_many()
+                    .filter(template_item::Column::Template.eq(set_template.template.clone()))
+                    .exec(tx)
+                    .await?;
+                for item in set_template.items {
+                    template_item::Entity::insert(template_item::ActiveModel {
+                        template: ActiveValue::Set(set_template.template.clone()),
+                        item: ActiveValue::Set(item.item),
+                        quantity: ActiveValue::Set(item.quantity.into()),
+                    })
+                    .on_conflict_do_nothing()
+                    .exec(tx)
+                    .await?;
+                }
+                Result::<_, sea_orm::DbErr>::Ok(())
+            })
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to set packing template: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[axum::debug_handler]
+async fn get_templates(State(state): State<&'static UsrState>) -> Response {
+    match template_item::Entity::find().all(&state.db).await {
+        Ok(items) => {
+            let mut out = HashMap::<String, Vec<TemplateItem>>::new();
+            for model in items {
+                out.entry(model.template).or_default().push(TemplateItem {
+                    item: model.item,
+                    quantity: model.quantity as u32,
+                });
+            }
+            Json(out).into_response()
+        }
+        Err(e) => {
+            error!("Failed to enumerate packing templates: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NewList {
+    name: String,
+    template: String,
+}
+
+/// Generates a packing list from a template, snapshotting where each item is
+/// stored and how many are on hand right now.
+#[axum::debug_handler]
+async fn new_list(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(NewList { name, template }): Json<NewList>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot start packing lists", caller.role),
+        )
+            .into_response();
+    }
+    let (template_items, stored) = tokio::join!(
+        template_item::Entity::find()
+            .filter(template_item::Column::Template.eq(template.clone()))
+            .all(&state.db),
+        manifest::stored_items(&state.db),
+    );
+
+    let template_items = match template_items {
+        Ok(x) if x.is_empty() => {
+            return (StatusCode::BAD_REQUEST, "Template not found").into_response();
+        }
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate packing template: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let stored = match stored {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate stored items: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let mut stock = HashMap::<String, (i64, BTreeSet<String>)>::new();
+    for item in stored {
+        let (count, locations) = stock.entry(item.name.trim().to_lowercase()).or_default();
+        *count += item.count;
+        if !item.location.is_empty() {
+            locations.insert(item.location);
+        }
+    }
+
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let list = list::ActiveModel {
+                    id: ActiveValue::NotSet,
+                    name: ActiveValue::Set(name),
+                    template: ActiveValue::Set(template),
+                    date: ActiveValue::Set(Local::now().naive_local()),
+                }
+                .insert(tx)
+                .await?;
+
+                for model in template_items {
+                    let (available, locations) = stock
+                        .get(&model.item.trim().to_lowercase())
+                        .map(|(count, locations)| {
+                            (
+                                *count,
+                                locations.iter().cloned().collect::<Vec<_>>().join(", "),
+                            )
+                        })
+                        .unwrap_or_default();
+                    list_item::ActiveModel {
+                        list_id: ActiveValue::Set(list.id),
+                        item: ActiveValue::Set(model.item),
+                        quantity: ActiveValue::Set(model.quantity),
+                        available: ActiveValue::Set(available),
+                        locations: ActiveValue::Set(locations),
+                        checked: ActiveValue::Set(false),
+                    }
+                    .insert(tx)
+                    .await?;
+                }
+
+                Result::<_, sea_orm::DbErr>::Ok(list)
+            })
+        })
+        .await;
+
+    match result {
+        Ok(list) => {
+            backup_db(state);
+            Json(list).into_response()
+        }
+        Err(e) => {
+            error!("Failed to create packing list: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PackingListItem {
+    #[serde(flatten)]
+    item: list_item::Model,
+    missing: bool,
+}
+
+#[derive(Serialize)]
+struct PackingList {
+    #[serde(flatten)]
+    list: list::Model,
+    items: Vec<PackingListItem>,
+}
+
+#[axum::debug_handler]
+async fn get_list(State(state): State<&'static UsrState>, Path(id): Path<u32>) -> Response {
+    let (list, items) = tokio::join!(
+        list::Entity::find_by_id(id).one(&state.db),
+        list_item::Entity::find()
+            .filter(list_item::Column::ListId.eq(id))
+            .all(&state.db),
+    );
+
+    let list = match list {
+        Ok(Some(x)) => x,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Packing list not found").into_response(),
+        Err(e) => {
+            error!("Failed to find packing list: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let items = match items {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate packing list items: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    Json(PackingList {
+        list,
+        items: items
+            .into_iter()
+            .map(|item| PackingListItem {
+                missing: !item.checked && item.available < item.quantity,
+                item,
+            })
+            .collect(),
+    })
+    .into_response()
+}
+
+#[axum::debug_handler]
+async fn get_lists(State(state): State<&'static UsrState>) -> Response {
+    match list::Entity::find().all(&state.db).await {
+        Ok(lists) => Json(lists).into_response(),
+        Err(e) => {
+            error!("Failed to enumerate packing lists: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CheckItem {
+    list_id: u32,
+    item: String,
+    checked: bool,
+}
+
+#[axum::debug_handler]
+async fn check_item(
+    State(state): State<&'static UsrState>,
+    Json(check_item): Json<CheckItem>,
+) -> (StatusCode, &'static str) {
+    let active_model = list_item::ActiveModel {
+        list_id: ActiveValue::Unchanged(check_item.list_id.into()),
+        item: ActiveValue::Unchanged(check_item.item),
+        quantity: ActiveValue::NotSet,
+        available: ActiveValue::NotSet,
+        locations: ActiveValue::NotSet,
+        checked: ActiveValue::Set(check_item.checked),
+    };
+
+    match active_model.update(&state.db).await {
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "")
+        }
+        Err(sea_orm::DbErr::RecordNotUpdated) => {
+            (StatusCode::BAD_REQUEST, "Packing list item not found")
+        }
+        Err(e) => {
+            error!("Failed to check packing list item: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
+        }
+    }
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/set/template", post(set_template))
+        .route("/list/template", get(get_templates))
+        .route("/new/list", post(new_list))
+        .route("/list/list", get(get_lists))
+        .route("/get/list/{id}", get(get_list))
+        .route("/check/list", post(check_item))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, template_item::Entity).await?);
+    problems.extend(schema::verify(db, list::Entity).await?);
+    problems.extend(schema::verify(db, list_item::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::{auth::Role, UsrState};
+
+    #[tokio::test]
+    async fn members_cannot_change_templates_or_start_lists() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [
+            (
+                Method::POST,
+                "/set/template",
+                json!({ "template": "Competition", "items": [] }),
+            ),
+            (
+                Method::POST,
+                "/new/list",
+                json!({ "name": "Regionals", "template": "Competition" }),
+            ),
+        ];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "packing_list_items")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub list_id: i64,
+    #[sea_orm(primary_key)]
+    pub item: String,
+    pub quantity: i64,
+    /// How many were in storage when the list was generated
+    pub available: i64,
+    /// Comma separated storage locations holding the item
+    pub locations: String,
+    pub checked: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Local;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ConnectionTrait, EntityTrait, QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    auth::{Caller, Role},
+    backup::backup_db,
+    notify::Topic,
+    scheduler, schema, UsrState,
+};
+
+mod print_job;
+mod spool;
+
+#[derive(Deserialize)]
+struct PendingSpool {
+    material: String,
+    #[serde(default)]
+    color: String,
+    #[serde(default)]
+    location: String,
+    grams: u32,
+    low_threshold_grams: u32,
+}
+
+#[axum::debug_handler]
+async fn new_spool(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(pending_spool): Json<PendingSpool>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot add spools", caller.role),
+        )
+            .into_response();
+    }
+    if pending_spool.material.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let active_model = spool::ActiveModel {
+        id: ActiveValue::NotSet,
+        material: ActiveValue::Set(pending_spool.material),
+        color: ActiveValue::Set(pending_spool.color),
+        location: ActiveValue::Set(pending_spool.location),
+        remaining_grams: ActiveValue::Set(pending_spool.grams.into()),
+        low_threshold_grams: ActiveValue::Set(pending_spool.low_threshold_grams.into()),
+    };
+
+    if let Err(e) = active_model.insert(&state.db).await {
+        error!("Failed to add spool: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[axum::debug_handler]
+async fn get_spools(State(state): State<&'static UsrState>) -> Response {
+    match spool::Entity::find().all(&state.db).await {
+        Ok(spools) => Json(spools).into_response(),
+        Err(e) => {
+            error!("Failed to enumerate spools: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PendingJob {
+    spool_id: u32,
+    grams: u32,
+    team: scheduler::Team,
+    #[serde(default)]
+    project: String,
+    member: String,
+}
+
+#[axum::debug_handler]
+async fn new_job(
+    State(state): State<&'static UsrState>,
+    Json(pending_job): Json<PendingJob>,
+) -> (StatusCode, &'static str) {
+    if pending_job.member.is_empty() || pending_job.grams == 0 {
+        return (StatusCode::BAD_REQUEST, "");
+    }
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                let Some(spool) = spool::Entity::find_by_id(pending_job.spool_id)
+                    .one(tx)
+                    .await?
+                else {
+                    return Ok(Err("Spool not found"));
+                };
+                let remaining_grams = spool.remaining_grams - i64::from(pending_job.grams);
+                if remaining_grams < 0 {
+                    return Ok(Err("Not enough filament on spool"));
+                }
+
+                print_job::ActiveModel {
+                    id: ActiveValue::NotSet,
+                    spool_id: ActiveValue::Set(spool.id),
+                    grams: ActiveValue::Set(pending_job.grams.into()),
+                    team: ActiveValue::Set(pending_job.team),
+                    project: ActiveValue::Set(pending_job.project),
+                    member: ActiveValue::Set(pending_job.member),
+                    date: ActiveValue::Set(Local::now().naive_local()),
+                }
+                .insert(tx)
+                .await?;
+
+                let updated = spool::ActiveModel {
+                    id: ActiveValue::Unchanged(spool.id),
+                    material: ActiveValue::NotSet,
+                    color: ActiveValue::NotSet,
+                    location: ActiveValue::NotSet,
+                    remaining_grams: ActiveValue::Set(remaining_grams),
+                    low_threshold_grams: ActiveValue::NotSet,
+                }
+                .update(tx)
+                .await?;
+
+                Result::<_, sea_orm::DbErr>::Ok(Ok((spool, updated)))
+            })
+        })
+        .await;
+
+    match result {
+        Ok(Ok((before, after))) => {
+            backup_db(state);
+            // Only alert on the job that crosses the threshold, not every job after
+            if before.remaining_grams >= before.low_threshold_grams
+                && after.remaining_grams < after.low_threshold_grams
+            {
+                state.notifier.send(
+                    Topic::LowStock,
+                    after.id as u32,
+                    format!(
+                        "**Low Filament!**\n**Spool:** {} {}\n**Location:** {}\n**Remaining:** {}g",
+                        after.color, after.material, after.location, after.remaining_grams
+                    ),
+                );
+            }
+            (StatusCode::OK, "")
+        }
+        Ok(Err(msg)) => (StatusCode::BAD_REQUEST, msg),
+        Err(e) => {
+            error!("Failed to log print job: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "")
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn get_jobs(State(state): State<&'static UsrState>) -> Response {
+    match print_job::Entity::find()
+        .order_by_desc(print_job::Column::Date)
+        .all(&state.db)
+        .await
+    {
+        Ok(jobs) => Json(jobs).into_response(),
+        Err(e) => {
+            error!("Failed to enumerate print jobs: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+struct Usage {
+    total_grams: i64,
+    by_project: HashMap<String, i64>,
+}
+
+/// Filament consumption attributed to each team, broken down by project.
+#[axum::debug_handler]
+async fn get_usage(State(state): State<&'static UsrState>) -> Response {
+    match print_job::Entity::find().all(&state.db).await {
+        Ok(jobs) => {
+            let mut out = HashMap::<scheduler::Team, Usage>::new();
+            for job in jobs {
+                let usage = out.entry(job.team).or_default();
+                usage.total_grams += job.grams;
+                *usage.by_project.entry(job.project).or_default() += job.grams;
+            }
+            Json(out).into_response()
+        }
+        Err(e) => {
+            error!("Failed to enumerate print jobs: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/new/spool", post(new_spool))
+        .route("/list/spool", get(get_spools))
+        .route("/new/job", post(new_job))
+        .route("/list/job", get(get_jobs))
+        .route("/report/usage", get(get_usage))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, spool::Entity).await?);
+    problems.extend(schema::verify(db, print_job::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::{auth::Role, UsrState};
+
+    #[tokio::test]
+    async fn members_cannot_add_spools() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [(
+            Method::POST,
+            "/new/spool",
+            json!({ "material": "PLA", "color": "Red", "location": "Shelf", "grams": 1000, "low_threshold_grams": 100 }),
+        )];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
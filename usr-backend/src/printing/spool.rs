@@ -0,0 +1,22 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "spools")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// eg. "PLA", "PETG", "TPU"
+    pub material: String,
+    pub color: String,
+    pub location: String,
+    pub remaining_grams: i64,
+    /// A low-stock alert is posted when a print job takes the spool below this
+    pub low_threshold_grams: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
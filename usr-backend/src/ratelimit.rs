@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+use crate::{api_error::ApiError, auth::Caller, UsrState};
+
+/// Clients that have been quiet long enough to be back at a full burst are
+/// forgotten once this many are remembered
+const PRUNE_AT: usize = 4096;
+
+/// How many requests that change something each client can make, so that a
+/// broken script looping on `/new/order` is turned away before the database
+/// queue backs up. Clients are told apart by their user, or by their address
+/// if they aren't signed in.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Requests a client can make at once before being limited
+    pub burst: u32,
+    /// How many of those a client gets back each minute. 0 doesn't limit.
+    pub per_minute: u32,
+    /// The largest request body accepted, in KiB. Attachments have a limit
+    /// of their own.
+    pub max_body_kib: usize,
+    /// Takes clients' addresses from `X-Forwarded-For`, for when the server
+    /// is behind a reverse proxy. Otherwise anyone could pick their own. The
+    /// last address is used, which is the one the proxy added, since clients
+    /// can send their own in front of it.
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            burst: 30,
+            per_minute: 60,
+            max_body_kib: 1024,
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self, key: &str, problems: &mut Vec<String>) {
+        if self.per_minute > 0 && self.burst == 0 {
+            problems.push(format!("{key}.burst: must be at least 1"));
+        }
+        if self.max_body_kib == 0 {
+            problems.push(format!("{key}.max_body_kib: must be at least 1"));
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::default(),
+        }
+    }
+
+    pub fn max_body_bytes(&self) -> usize {
+        self.config.max_body_kib * 1024
+    }
+
+    fn per_second(&self) -> f64 {
+        f64::from(self.config.per_minute) / 60.0
+    }
+
+    /// Takes a request from `client`'s allowance, or says how long until it
+    /// has one again
+    fn take(&self, client: String) -> Result<(), Duration> {
+        let burst = f64::from(self.config.burst);
+        let per_second = self.per_second();
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= PRUNE_AT {
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * per_second
+                    < burst
+            });
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        bucket.tokens = (bucket.tokens
+            + now.duration_since(bucket.updated).as_secs_f64() * per_second)
+            .min(burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+
+    /// Who a request is counted against
+    fn client(&self, request: &Request) -> String {
+        if let Some(name) = request
+            .extensions()
+            .get::<Caller>()
+            .and_then(|caller| caller.name.as_ref())
+        {
+            return format!("user:{name}");
+        }
+        let forwarded = self
+            .config
+            .trust_forwarded_for
+            .then(|| request.headers().get_all("x-forwarded-for").iter().next_back())
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .map(|addr| addr.trim().to_string());
+        let addr = forwarded.or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        });
+        format!("ip:{}", addr.unwrap_or_default())
+    }
+}
+
+/// Answers `429` with `Retry-After` to clients that have used up their
+/// allowance. Only requests that can change something count.
+pub async fn limit(
+    State(state): State<&'static UsrState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limiter = &state.rate_limiter;
+    if limiter.config.per_minute == 0
+        || matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+    {
+        return next.run(request).await;
+    }
+    match limiter.take(limiter.client(&request)) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            let mut response = ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                format!("Too many requests, try again in {secs} seconds"),
+            )
+            .into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    #[test]
+    fn spoofed_forwarded_for_is_still_limited() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            burst: 1,
+            trust_forwarded_for: true,
+            ..RateLimitConfig::default()
+        });
+        let request = |spoofed: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .header("x-forwarded-for", format!("{spoofed}, 203.0.113.7"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        assert!(limiter.take(limiter.client(&request("10.0.0.1"))).is_ok());
+        assert!(limiter.take(limiter.client(&request("10.0.0.2"))).is_err());
+    }
+}
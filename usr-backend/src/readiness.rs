@@ -0,0 +1,197 @@
+use std::{
+    net::TcpListener,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use axum_server::Handle;
+use chrono::{Local, TimeDelta};
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::{jobs, migration, UsrState};
+
+/// How long requests the gate is still answering get to finish once the
+/// server itself is ready
+const HANDOVER: Duration = Duration::from_secs(5);
+/// How long requests in flight get to finish once the server is told to stop
+const DRAIN: Duration = Duration::from_secs(30);
+/// How late the webhook retries can be to run before the worker is taken to
+/// be stuck
+const WEBHOOK_WORKER_GRACE: TimeDelta = TimeDelta::minutes(5);
+
+/// Set once the server has been told to stop, so that `/readyz` sends load
+/// balancers elsewhere while requests in flight finish
+static STOPPING: AtomicBool = AtomicBool::new(false);
+
+/// Answers on the server's port while the database is migrated, so that a
+/// load balancer sees this instance isn't ready yet and keeps sending
+/// requests to the one it is replacing
+pub struct Gate {
+    handle: Handle,
+    task: JoinHandle<std::io::Result<()>>,
+}
+
+async fn migrating() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, "5")],
+        "Migrating the database",
+    )
+        .into_response()
+}
+
+impl Gate {
+    pub fn open(listener: &TcpListener) -> std::io::Result<Self> {
+        let handle = Handle::new();
+        let task = tokio::spawn(crate::serve(
+            listener.try_clone()?,
+            Router::new().fallback(migrating),
+            handle.clone(),
+        ));
+        Ok(Self { handle, task })
+    }
+
+    /// Stops answering, leaving connections that come in meanwhile waiting on
+    /// the listener for the server
+    pub async fn close(self) {
+        self.handle.graceful_shutdown(Some(HANDOVER));
+        match self.task.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Migration gate failed: {e}"),
+            Err(e) => error!("Migration gate panicked: {e}"),
+        }
+    }
+}
+
+/// For systemd and load balancers, which restart the server if this doesn't
+/// answer. It doesn't touch the database, so a slow database doesn't get the
+/// server restarted too.
+pub async fn get_live() -> &'static str {
+    "OK"
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    ready: bool,
+    stopping: bool,
+    database: bool,
+    /// Migrations this version needs that haven't run
+    pending_migrations: Vec<String>,
+    /// Whether the job that sends failed webhook messages is keeping to its
+    /// schedule
+    webhook_worker: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    problems: Vec<String>,
+}
+
+fn webhook_worker_alive(state: &'static UsrState) -> bool {
+    let Some(job) = state.jobs.status("webhook_retries") else {
+        return false;
+    };
+    match job.next_run {
+        Some(next_run) => next_run + WEBHOOK_WORKER_GRACE > Local::now().naive_local(),
+        None => job.status == jobs::Status::Running,
+    }
+}
+
+/// For load balancers, which only send requests once this says 200. Checks
+/// that the database is reachable, that the schema is up to date and that
+/// webhook messages are being sent, answering what was checked. The gate
+/// answers 503 here until the database has been migrated, and so does this
+/// once the server is stopping.
+#[axum::debug_handler]
+pub async fn get_readyz(State(state): State<&'static UsrState>) -> Response {
+    let mut problems = vec![];
+    let stopping = STOPPING.load(Ordering::Relaxed);
+    if stopping {
+        problems.push("The server is stopping".to_string());
+    }
+    let database = match state.db.ping().await {
+        Ok(()) => true,
+        Err(e) => {
+            problems.push(format!("Database is unreachable: {e}"));
+            false
+        }
+    };
+    let pending_migrations = if database {
+        match migration::unapplied(&state.db).await {
+            Ok(pending) => pending,
+            Err(e) => {
+                problems.push(format!("Failed to check migrations: {e}"));
+                vec![]
+            }
+        }
+    } else {
+        vec![]
+    };
+    if !pending_migrations.is_empty() {
+        problems.push(format!(
+            "{} migration(s) haven't run",
+            pending_migrations.len()
+        ));
+    }
+    let webhook_worker = webhook_worker_alive(state);
+    if !webhook_worker {
+        problems.push("Webhook messages aren't being retried".to_string());
+    }
+
+    let readiness = Readiness {
+        ready: problems.is_empty(),
+        stopping,
+        database,
+        pending_migrations,
+        webhook_worker,
+        problems,
+    };
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness)).into_response()
+}
+
+/// Waits for SIGTERM, which systemd stops the server with, or Ctrl+C
+async fn stop_requested() {
+    let ctrl_c = tokio::signal::ctrl_c();
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = ctrl_c => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {e}");
+                let _ = ctrl_c.await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = ctrl_c.await;
+    }
+}
+
+/// Once the server is told to stop, stops taking new connections and gives
+/// requests in flight, and the transactions they are in, [`DRAIN`] to
+/// finish before `handle` lets the server return
+pub fn shut_down_on_signal(handle: Handle) {
+    tokio::spawn(async move {
+        stop_requested().await;
+        info!("Stopping, waiting for requests in flight to finish");
+        STOPPING.store(true, Ordering::Relaxed);
+        handle.graceful_shutdown(Some(DRAIN));
+    });
+}
@@ -0,0 +1,444 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use sea_orm::{
+    prelude::Decimal, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    auth::{Caller, Role},
+    backup::backup_db,
+    manifest, money, schema, UsrState,
+};
+
+mod bom_line;
+mod component;
+
+pub async fn component_exists(db: &DatabaseConnection, id: u32) -> Result<bool, sea_orm::DbErr> {
+    Ok(component::Entity::find_by_id(id).one(db).await?.is_some())
+}
+
+/// The subsystem that component `id` belongs to, if it exists
+pub async fn component_subsystem(
+    db: &DatabaseConnection,
+    id: i64,
+) -> Result<Option<String>, sea_orm::DbErr> {
+    Ok(component::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .map(|model| model.subsystem))
+}
+
+#[derive(Deserialize)]
+struct PendingComponent {
+    name: String,
+    subsystem: String,
+    revision: String,
+    status: component::Status,
+}
+
+#[axum::debug_handler]
+async fn new_component(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(pending_component): Json<PendingComponent>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot add components", caller.role),
+        )
+            .into_response();
+    }
+    if pending_component.name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let active_model = component::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(pending_component.name),
+        subsystem: ActiveValue::Set(pending_component.subsystem),
+        revision: ActiveValue::Set(pending_component.revision),
+        status: ActiveValue::Set(pending_component.status),
+    };
+
+    if let Err(e) = active_model.insert(&state.db).await {
+        error!("Failed to add component: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct UpdateComponent {
+    id: u32,
+    #[serde(default)]
+    revision: Option<String>,
+    #[serde(default)]
+    status: Option<component::Status>,
+}
+
+#[axum::debug_handler]
+async fn update_component(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(update_component): Json<UpdateComponent>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot change components", caller.role),
+        )
+            .into_response();
+    }
+    let active_model = component::ActiveModel {
+        id: ActiveValue::Unchanged(update_component.id.into()),
+        name: ActiveValue::NotSet,
+        subsystem: ActiveValue::NotSet,
+        revision: update_component
+            .revision
+            .map_or(ActiveValue::NotSet, ActiveValue::Set),
+        status: update_component
+            .status
+            .map_or(ActiveValue::NotSet, ActiveValue::Set),
+    };
+
+    match active_model.update(&state.db).await {
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(sea_orm::DbErr::RecordNotUpdated) => (StatusCode::BAD_REQUEST, "Component not found").into_response(),
+        Err(e) => {
+            error!("Failed to update component: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ComponentFilter {
+    #[serde(default)]
+    subsystem: Option<String>,
+    #[serde(default)]
+    revision: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ComponentSummary {
+    #[serde(flatten)]
+    component: component::Model,
+    cost: Decimal,
+    orders: Vec<i64>,
+}
+
+#[axum::debug_handler]
+async fn get_components(
+    State(state): State<&'static UsrState>,
+    Query(filter): Query<ComponentFilter>,
+) -> Response {
+    let mut query = component::Entity::find();
+    if let Some(subsystem) = filter.subsystem {
+        query = query.filter(component::Column::Subsystem.eq(subsystem));
+    }
+    if let Some(revision) = filter.revision {
+        query = query.filter(component::Column::Revision.eq(revision));
+    }
+
+    let (components, costs) =
+        tokio::join!(query.all(&state.db), manifest::component_costs(&state.db),);
+
+    let components = match components {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate components: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let mut costs = match costs {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to compute component costs: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    Json(
+        components
+            .into_iter()
+            .map(|component| {
+                let (cost, orders) = costs.remove(&component.id).unwrap_or_default();
+                ComponentSummary {
+                    component,
+                    cost,
+                    orders,
+                }
+            })
+            .collect::<Vec<_>>(),
+    )
+    .into_response()
+}
+
+#[derive(Deserialize, Serialize)]
+struct BomLine {
+    item: String,
+    quantity: u32,
+    unit_cost: Decimal,
+}
+
+#[derive(Deserialize)]
+struct SetBom {
+    project: String,
+    lines: Vec<BomLine>,
+}
+
+#[axum::debug_handler]
+async fn set_bom(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(set_bom): Json<SetBom>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot change BOMs", caller.role),
+        )
+            .into_response();
+    }
+    if set_bom.project.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    if let Some(Err(msg)) = set_bom
+        .lines
+        .iter()
+        .map(|line| money::validate_unit_cost(line.unit_cost))
+        .find(Result::is_err)
+    {
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                bom_line::Entity::delete_many()
+                    .filter(bom_line::Column::Project.eq(set_bom.project.clone()))
+                    .exec(tx)
+                    .await?;
+                for line in set_bom.lines {
+                    bom_line::Entity::insert(bom_line::ActiveModel {
+                        project: ActiveValue::Set(set_bom.project.clone()),
+                        item: ActiveValue::Set(line.item),
+                        quantity: ActiveValue::Set(line.quantity.into()),
+                        unit_cost: ActiveValue::Set(line.unit_cost),
+                    })
+                    .on_conflict_do_nothing()
+                    .exec(tx)
+                    .await?;
+                }
+                Result::<_, sea_orm::DbErr>::Ok(())
+            })
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to set BOM: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[axum::debug_handler]
+async fn get_boms(State(state): State<&'static UsrState>) -> Response {
+    match bom_line::Entity::find().all(&state.db).await {
+        Ok(lines) => {
+            let mut out = HashMap::<String, Vec<BomLine>>::new();
+            for model in lines {
+                out.entry(model.project).or_default().push(BomLine {
+                    item: model.item,
+                    quantity: model.quantity as u32,
+                    unit_cost: model.unit_cost,
+                });
+            }
+            Json(out).into_response()
+        }
+        Err(e) => {
+            error!("Failed to enumerate BOMs: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ShortageFilter {
+    #[serde(default)]
+    project: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Shortage {
+    item: String,
+    projects: BTreeSet<String>,
+    required: i64,
+    in_stock: i64,
+    in_flight: i64,
+    shortage: i64,
+    /// The most pessimistic estimate when projects disagree on cost
+    unit_cost: Decimal,
+    estimated_cost: Decimal,
+}
+
+#[derive(Serialize)]
+struct ShortageReport {
+    shortages: Vec<Shortage>,
+    estimated_cost: Decimal,
+}
+
+/// Diffs BOMs against what is in storage plus what is already on order.
+/// Requirements for the same item are combined across projects, since they
+/// draw from the same stock.
+#[axum::debug_handler]
+async fn get_shortages(
+    State(state): State<&'static UsrState>,
+    Query(ShortageFilter { project }): Query<ShortageFilter>,
+) -> Response {
+    let mut query = bom_line::Entity::find();
+    if let Some(project) = project {
+        query = query.filter(bom_line::Column::Project.eq(project));
+    }
+
+    let (lines, stored, in_flight) = tokio::join!(
+        query.all(&state.db),
+        manifest::stored_items(&state.db),
+        manifest::in_flight_items(&state.db),
+    );
+
+    let lines = match lines {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate BOMs: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let (stored, in_flight) = match (stored, in_flight) {
+        (Ok(stored), Ok(in_flight)) => (stored, in_flight),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to enumerate ordered items: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let mut stock = HashMap::<String, i64>::new();
+    for item in stored {
+        *stock.entry(item.name.trim().to_lowercase()).or_default() += item.count;
+    }
+    let mut on_order = HashMap::<String, i64>::new();
+    for item in in_flight {
+        *on_order.entry(item.name.trim().to_lowercase()).or_default() += item.count;
+    }
+
+    let mut shortages = BTreeMap::<String, Shortage>::new();
+    for line in lines {
+        let key = line.item.trim().to_lowercase();
+        let shortage = shortages.entry(key.clone()).or_insert_with(|| Shortage {
+            item: line.item.clone(),
+            projects: BTreeSet::new(),
+            required: 0,
+            in_stock: stock.get(&key).copied().unwrap_or_default(),
+            in_flight: on_order.get(&key).copied().unwrap_or_default(),
+            shortage: 0,
+            unit_cost: Decimal::ZERO,
+            estimated_cost: Decimal::ZERO,
+        });
+        shortage.projects.insert(line.project);
+        shortage.required += line.quantity;
+        shortage.unit_cost = shortage.unit_cost.max(line.unit_cost);
+    }
+
+    let shortages: Vec<_> = shortages
+        .into_values()
+        .filter_map(|mut x| {
+            x.shortage = (x.required - x.in_stock - x.in_flight).max(0);
+            x.estimated_cost = money::subtotal(x.shortage, x.unit_cost);
+            (x.shortage > 0).then_some(x)
+        })
+        .collect();
+    let estimated_cost = shortages.iter().map(|x| x.estimated_cost).sum();
+
+    Json(ShortageReport {
+        shortages,
+        estimated_cost,
+    })
+    .into_response()
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/new/component", post(new_component))
+        .route("/update/component", post(update_component))
+        .route("/list/component", get(get_components))
+        .route("/set/bom", post(set_bom))
+        .route("/list/bom", get(get_boms))
+        .route("/report/shortages", get(get_shortages))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, component::Entity).await?);
+    problems.extend(schema::verify(db, bom_line::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::{auth::Role, UsrState};
+
+    #[tokio::test]
+    async fn members_cannot_change_components_or_boms() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [
+            (
+                Method::POST,
+                "/new/component",
+                json!({ "name": "Arm", "subsystem": "Digger", "revision": "A", "status": "Design" }),
+            ),
+            (
+                Method::POST,
+                "/update/component",
+                json!({ "id": 1, "revision": "B", "status": null }),
+            ),
+            (
+                Method::POST,
+                "/set/bom",
+                json!({ "project": "Digger", "lines": [] }),
+            ),
+        ];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
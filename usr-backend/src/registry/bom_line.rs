@@ -0,0 +1,20 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "bom_lines")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub project: String,
+    #[sea_orm(primary_key)]
+    pub item: String,
+    pub quantity: i64,
+    /// Estimated, used to price shortages
+    pub unit_cost: Decimal,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
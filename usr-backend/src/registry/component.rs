@@ -0,0 +1,35 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "components")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub name: String,
+    pub subsystem: String,
+    /// eg. "A", "B", "C"
+    pub revision: String,
+    pub status: Status,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Status {
+    #[sea_orm(string_value = "D")]
+    Design,
+    #[sea_orm(string_value = "M")]
+    Manufacturing,
+    #[sea_orm(string_value = "A")]
+    Assembled,
+    #[sea_orm(string_value = "T")]
+    Testing,
+    #[sea_orm(string_value = "R")]
+    Retired,
+}
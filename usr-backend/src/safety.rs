@@ -0,0 +1,363 @@
+use std::collections::BTreeSet;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::Local;
+use sea_orm::{sea_query::OnConflict, ActiveValue, ConnectionTrait, EntityTrait};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    auth::{Caller, Role},
+    backup::backup_db,
+    manifest, schema, UsrState,
+};
+
+mod certification;
+mod hazard;
+
+pub use hazard::Model as Hazard;
+
+/// Whether `member` holds `certification`, optionally requiring supervisor rights.
+pub async fn is_certified(
+    db: &impl ConnectionTrait,
+    member: &str,
+    certification: &str,
+    supervisor: bool,
+) -> Result<bool, sea_orm::DbErr> {
+    let model = certification::Entity::find_by_id((member.to_string(), certification.to_string()))
+        .one(db)
+        .await?;
+    Ok(model.is_some_and(|model| !supervisor || model.supervisor))
+}
+
+fn normalize_item(item: &str) -> String {
+    item.trim().to_lowercase()
+}
+
+/// Looks up the hazard flag for an item, if it has one.
+pub async fn hazard_for(
+    db: &impl ConnectionTrait,
+    item: &str,
+) -> Result<Option<Hazard>, sea_orm::DbErr> {
+    hazard::Entity::find_by_id(normalize_item(item))
+        .one(db)
+        .await
+}
+
+#[derive(Deserialize)]
+struct SetCertification {
+    member: String,
+    certification: String,
+    granted_by: String,
+    #[serde(default)]
+    supervisor: bool,
+}
+
+#[axum::debug_handler]
+async fn set_certification(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(set_certification): Json<SetCertification>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot certify members", caller.role),
+        )
+            .into_response();
+    }
+    if set_certification.member.is_empty()
+        || set_certification.certification.is_empty()
+        || set_certification.granted_by.is_empty()
+    {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let result = certification::Entity::insert(certification::ActiveModel {
+        member: ActiveValue::Set(set_certification.member),
+        certification: ActiveValue::Set(set_certification.certification),
+        granted_by: ActiveValue::Set(set_certification.granted_by),
+        date: ActiveValue::Set(Local::now().naive_local()),
+        supervisor: ActiveValue::Set(set_certification.supervisor),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            certification::Column::Member,
+            certification::Column::Certification,
+        ])
+        .update_columns([
+            certification::Column::GrantedBy,
+            certification::Column::Date,
+            certification::Column::Supervisor,
+        ])
+        .to_owned(),
+    )
+    .exec(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to set certification: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteCertification {
+    member: String,
+    certification: String,
+}
+
+#[axum::debug_handler]
+async fn del_certification(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(DeleteCertification {
+        member,
+        certification,
+    }): Json<DeleteCertification>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot revoke certifications", caller.role),
+        )
+            .into_response();
+    }
+    match certification::Entity::delete_by_id((member, certification))
+        .exec(&state.db)
+        .await
+    {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Certification not found").into_response()
+        }
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete certification: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn get_certifications(State(state): State<&'static UsrState>) -> Response {
+    match certification::Entity::find().all(&state.db).await {
+        Ok(certifications) => Json(certifications).into_response(),
+        Err(e) => {
+            error!("Failed to enumerate certifications: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SetHazard {
+    item: String,
+    class: hazard::Class,
+    #[serde(default)]
+    storage_requirements: String,
+    #[serde(default)]
+    handling_notes: String,
+}
+
+#[axum::debug_handler]
+async fn set_hazard(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(set_hazard): Json<SetHazard>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot set hazards", caller.role),
+        )
+            .into_response();
+    }
+    let item = normalize_item(&set_hazard.item);
+    if item.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let result = hazard::Entity::insert(hazard::ActiveModel {
+        item: ActiveValue::Set(item),
+        class: ActiveValue::Set(set_hazard.class),
+        storage_requirements: ActiveValue::Set(set_hazard.storage_requirements),
+        handling_notes: ActiveValue::Set(set_hazard.handling_notes),
+    })
+    .on_conflict(
+        OnConflict::column(hazard::Column::Item)
+            .update_columns([
+                hazard::Column::Class,
+                hazard::Column::StorageRequirements,
+                hazard::Column::HandlingNotes,
+            ])
+            .to_owned(),
+    )
+    .exec(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to set hazard: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteHazard {
+    item: String,
+}
+
+#[axum::debug_handler]
+async fn del_hazard(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(DeleteHazard { item }): Json<DeleteHazard>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot delete hazards", caller.role),
+        )
+            .into_response();
+    }
+    match hazard::Entity::delete_by_id(normalize_item(&item))
+        .exec(&state.db)
+        .await
+    {
+        Ok(result) if result.rows_affected == 0 => (StatusCode::BAD_REQUEST, "Hazard not found").into_response(),
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete hazard: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct HazardousStock {
+    #[serde(flatten)]
+    hazard: Hazard,
+    quantity: i64,
+    locations: BTreeSet<String>,
+}
+
+/// Every hazardous item currently in storage and where it is, for safety
+/// inspections.
+#[axum::debug_handler]
+async fn get_hazard_report(State(state): State<&'static UsrState>) -> Response {
+    let (hazards, stored) = tokio::join!(
+        hazard::Entity::find().all(&state.db),
+        manifest::stored_items(&state.db),
+    );
+
+    let hazards = match hazards {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate hazards: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let stored = match stored {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate stored items: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let mut out: Vec<_> = hazards
+        .into_iter()
+        .map(|hazard| HazardousStock {
+            hazard,
+            quantity: 0,
+            locations: BTreeSet::new(),
+        })
+        .collect();
+
+    for item in stored {
+        let name = normalize_item(&item.name);
+        let Some(stock) = out.iter_mut().find(|x| x.hazard.item == name) else {
+            continue;
+        };
+        stock.quantity += item.count;
+        stock.locations.insert(item.location);
+    }
+    out.retain(|x| x.quantity > 0);
+
+    Json(out).into_response()
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/set/certification", post(set_certification))
+        .route("/del/certification", delete(del_certification))
+        .route("/list/certification", get(get_certifications))
+        .route("/set/hazard", post(set_hazard))
+        .route("/del/hazard", delete(del_hazard))
+        .route("/report/hazard", get(get_hazard_report))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, certification::Entity).await?);
+    problems.extend(schema::verify(db, hazard::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::{auth::Role, UsrState};
+
+    #[tokio::test]
+    async fn members_cannot_change_certifications_or_hazards() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [
+            (
+                Method::POST,
+                "/set/certification",
+                json!({ "member": "a", "certification": "Mill", "granted_by": "b", "supervisor": false }),
+            ),
+            (
+                Method::DELETE,
+                "/del/certification",
+                json!({ "member": "a", "certification": "Mill" }),
+            ),
+            (
+                Method::POST,
+                "/set/hazard",
+                json!({ "item": "LiPo", "class": "Battery", "storage_requirements": "", "handling_notes": "" }),
+            ),
+            (Method::DELETE, "/del/hazard", json!({ "item": "LiPo" })),
+        ];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "certifications")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub member: String,
+    /// eg. "mill", "lathe", "welding"
+    #[sea_orm(primary_key)]
+    pub certification: String,
+    pub granted_by: String,
+    pub date: DateTime,
+    /// Supervisors may override the gate for members lacking this certification
+    pub supervisor: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
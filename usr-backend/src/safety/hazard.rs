@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "hazards")]
+pub struct Model {
+    /// Lowercased item name, matched against stored orders and equipment
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub item: String,
+    pub class: Class,
+    pub storage_requirements: String,
+    pub handling_notes: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Class {
+    #[sea_orm(string_value = "B")]
+    Battery,
+    #[sea_orm(string_value = "F")]
+    Flammable,
+    #[sea_orm(string_value = "C")]
+    Corrosive,
+    #[sea_orm(string_value = "T")]
+    Toxic,
+    #[sea_orm(string_value = "G")]
+    CompressedGas,
+    #[sea_orm(string_value = "O")]
+    Other,
+}
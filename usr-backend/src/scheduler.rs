@@ -1,11 +1,15 @@
 use std::collections::{hash_map::Entry, HashMap, HashSet};
 
 use axum::{extract::State, http::StatusCode, response::{IntoResponse, Response}, routing::{delete, get, post}, Json, Router};
-use sea_orm::{sea_query::Table, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, Schema, TransactionTrait};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
+};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
 use serde::{Deserialize, Serialize};
 use tracing::error;
 
-use crate::{backup::backup_db, UsrState};
+use crate::{backup::backup_db, schema, UsrState};
 
 mod availability;
 mod team;
@@ -38,6 +42,7 @@ async fn add_schedule(State(state): State<&'static UsrState>, Json(pending_sched
         (StatusCode::INTERNAL_SERVER_ERROR, "")
     } else {
         backup_db(state);
+        state.dashboard.invalidate();
         (StatusCode::OK, "")
     }
 }
@@ -62,6 +67,7 @@ async fn del_schedule(State(state): State<&'static UsrState>, Json(pending_sched
         (StatusCode::INTERNAL_SERVER_ERROR, "")
     } else {
         backup_db(state);
+        state.dashboard.invalidate();
         (StatusCode::OK, "")
     }
 }
@@ -148,6 +154,57 @@ async fn get_schedule(State(state): State<&'static UsrState>) -> Response {
     }).into_response()
 }
 
+/// The schedule slot `time` falls in, if any. Slots are 15 minutes long with
+/// 40 to a day, starting at 9 AM, and days start on Monday.
+pub fn slot_at(time: NaiveDateTime) -> Option<u16> {
+    let minutes = time.time().signed_duration_since(NaiveTime::from_hms_opt(9, 0, 0)?).num_minutes();
+    if !(0..40 * 15).contains(&minutes) {
+        return None;
+    }
+    Some(time.weekday().num_days_from_monday() as u16 * 40 + minutes as u16 / 15)
+}
+
+/// Who is available during one of a day's slots
+#[derive(Serialize)]
+pub struct Slot {
+    pub start: NaiveTime,
+    pub members: Vec<String>,
+}
+
+/// Each of `date`'s slots, in order
+pub async fn day(db: &DatabaseConnection, date: NaiveDate) -> Result<Vec<Slot>, sea_orm::DbErr> {
+    let first = date.weekday().num_days_from_monday() as u16 * 40;
+    let mut slots: Vec<Slot> = (0..40u32)
+        .map(|i| Slot {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default() + chrono::TimeDelta::minutes(i as i64 * 15),
+            members: vec![],
+        })
+        .collect();
+    let available = availability::Entity::find()
+        .filter(availability::Column::Time.between(first, first + 39))
+        .order_by_asc(availability::Column::Name)
+        .all(db)
+        .await?;
+    for model in available {
+        slots[(model.time - first) as usize].members.push(model.name);
+    }
+    Ok(slots)
+}
+
+/// Members whose availability begins at `slot`, ie. they are available then
+/// but not in the slot before.
+pub async fn shifts_starting(db: &DatabaseConnection, slot: u16) -> Result<Vec<String>, sea_orm::DbErr> {
+    let available = availability::Entity::find().filter(availability::Column::Time.eq(slot)).all(db).await?;
+    if slot.is_multiple_of(40) {
+        return Ok(available.into_iter().map(|model| model.name).collect());
+    }
+    let before: HashSet<String> = availability::Entity::find().filter(availability::Column::Time.eq(slot - 1)).all(db).await?
+        .into_iter()
+        .map(|model| model.name)
+        .collect();
+    Ok(available.into_iter().map(|model| model.name).filter(|name| !before.contains(name)).collect())
+}
+
 pub fn router() -> Router<&'static UsrState> {
     Router::new()
     .route("/add/schedule", post(add_schedule))
@@ -157,14 +214,11 @@ pub fn router() -> Router<&'static UsrState> {
     // .route("/get/team/:name", get(get_teams))
 }
 
-pub async fn reset_tables(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
-    let builder = db.get_database_backend();
-    let schema = Schema::new(builder);
-
-    db.execute(builder.build(Table::drop().table(team::Entity).if_exists())).await?;
-    db.execute(builder.build(Table::drop().table(availability::Entity).if_exists())).await?;
-    db.execute(builder.build(&schema.create_table_from_entity(team::Entity))).await?;
-    db.execute(builder.build(&schema.create_table_from_entity(availability::Entity))).await?;
-
-    Ok(())
-}
\ No newline at end of file
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, team::Entity).await?);
+    problems.extend(schema::verify(db, availability::Entity).await?);
+    Ok(problems)
+}
@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+
+use sea_orm::{
+    sea_query::{
+        Alias, ColumnDef, ColumnSpec, ColumnType, Expr, Index, Table, TableCreateStatement,
+    },
+    ConnectionTrait, DatabaseBackend, DbErr, EntityTrait, IdenStatic, Iterable, Statement,
+};
+
+/// The columns `table` has in the database, none if it doesn't exist
+pub async fn live_columns(
+    db: &impl ConnectionTrait,
+    table: &str,
+) -> Result<HashSet<String>, sea_orm::DbErr> {
+    let backend = db.get_database_backend();
+    let sql = match backend {
+        DatabaseBackend::Sqlite => "SELECT name FROM pragma_table_info(?)",
+        DatabaseBackend::Postgres => {
+            "SELECT column_name AS name FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1"
+        }
+        DatabaseBackend::MySql => {
+            "SELECT column_name AS name FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = ?"
+        }
+    };
+    db.query_all(Statement::from_sql_and_values(backend, sql, [table.into()]))
+        .await?
+        .into_iter()
+        .map(|row| row.try_get("", "name"))
+        .collect()
+}
+
+/// Every table in the database, by name, apart from the database's own
+pub async fn tables(db: &impl ConnectionTrait) -> Result<Vec<String>, DbErr> {
+    let backend = db.get_database_backend();
+    let sql = match backend {
+        DatabaseBackend::Sqlite => {
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+        }
+        DatabaseBackend::Postgres => {
+            "SELECT table_name AS name FROM information_schema.tables WHERE table_schema = current_schema() AND table_type = 'BASE TABLE' ORDER BY name"
+        }
+        DatabaseBackend::MySql => {
+            "SELECT table_name AS name FROM information_schema.tables WHERE table_schema = DATABASE() AND table_type = 'BASE TABLE' ORDER BY name"
+        }
+    };
+    db.query_all(Statement::from_string(backend, sql))
+        .await?
+        .into_iter()
+        .map(|row| row.try_get("", "name"))
+        .collect()
+}
+
+/// Rewrites `sql`, written with SQLite's `?` placeholders, for `backend`.
+/// Postgres numbers its placeholders, so this is only for statements that
+/// bind a single value, however many times it is used.
+pub fn placeholders(backend: DatabaseBackend, sql: &str) -> String {
+    match backend {
+        DatabaseBackend::Postgres => sql.replace('?', "$1"),
+        _ => sql.to_string(),
+    }
+}
+
+/// `def` as a column is made on `backend`. Postgres has no unsigned
+/// integers, so `u32`s are stored there as `bigint`s checked to stay in
+/// range, auto incrementing as `bigserial`s. Entities hold them as `i64`s,
+/// which either backend can read back.
+pub fn column_def(backend: DatabaseBackend, def: ColumnDef) -> ColumnDef {
+    if backend != DatabaseBackend::Postgres
+        || !matches!(def.get_column_type(), Some(ColumnType::Unsigned))
+    {
+        return def;
+    }
+    let name = def.get_column_name();
+    let mut bigint = ColumnDef::new_with_type(Alias::new(&name), ColumnType::BigInteger);
+    for spec in def.get_column_spec() {
+        match spec {
+            ColumnSpec::Null => bigint.null(),
+            ColumnSpec::NotNull => bigint.not_null(),
+            ColumnSpec::UniqueKey => bigint.unique_key(),
+            ColumnSpec::PrimaryKey => bigint.primary_key(),
+            ColumnSpec::AutoIncrement => bigint.auto_increment(),
+            ColumnSpec::Default(value) => bigint.default(value.clone()),
+            ColumnSpec::Comment(comment) => bigint.comment(comment),
+            _ => &mut bigint,
+        };
+    }
+    bigint.check(Expr::col(Alias::new(&name)).between(0, u32::MAX));
+    bigint
+}
+
+/// Creates the table `create` makes if it doesn't exist yet, with the columns
+/// of [`column_def`]. Migrations make their tables with this so that they
+/// come out the same on every backend.
+pub async fn create_table_from(
+    db: &impl ConnectionTrait,
+    mut create: TableCreateStatement,
+) -> Result<(), DbErr> {
+    let backend = db.get_database_backend();
+    create.if_not_exists();
+    if backend != DatabaseBackend::Postgres {
+        db.execute(backend.build(&create)).await?;
+        return Ok(());
+    }
+
+    let Some(table) = create.get_table_name().cloned() else {
+        return Err(DbErr::Custom("The table to create has no name".to_string()));
+    };
+    let mut bigints = Table::create();
+    bigints.table(table).if_not_exists();
+    for def in create.get_columns() {
+        bigints.col(column_def(backend, def.clone()));
+    }
+    for index in create.get_indexes() {
+        bigints.index(&mut index.clone());
+    }
+    for foreign_key in create.get_foreign_key_create_stmts() {
+        bigints.foreign_key(&mut foreign_key.clone());
+    }
+    db.execute(backend.build(&bigints)).await?;
+    Ok(())
+}
+
+/// Checks that the table for `entity` exists with all of its columns,
+/// returning a description of each mismatch
+pub async fn verify<E: EntityTrait>(
+    db: &impl ConnectionTrait,
+    entity: E,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let table = entity.table_name();
+    let live = live_columns(db, table).await?;
+    if live.is_empty() {
+        return Ok(vec![format!("Table {table} is missing")]);
+    }
+    Ok(E::Column::iter()
+        .filter(|column| !live.contains(column.as_str()))
+        .map(|column| format!("Column {table}.{} is missing", column.as_str()))
+        .collect())
+}
+
+/// Creates an index on `column` if it doesn't exist yet. Indexes don't change
+/// what is stored, so unlike columns they are added without a migration.
+pub async fn ensure_index<E: EntityTrait>(
+    db: &impl ConnectionTrait,
+    entity: E,
+    column: E::Column,
+) -> Result<(), sea_orm::DbErr> {
+    let builder = db.get_database_backend();
+    let name = format!("idx_{}_{}", entity.table_name(), column.as_str());
+    db.execute(
+        builder.build(
+            Index::create()
+                .if_not_exists()
+                .name(&name)
+                .table(entity)
+                .col(column),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Like [`ensure_index`], but no two rows may share a value in `column`.
+/// Rows where it is null don't count.
+pub async fn ensure_unique_index<E: EntityTrait>(
+    db: &impl ConnectionTrait,
+    entity: E,
+    column: E::Column,
+) -> Result<(), sea_orm::DbErr> {
+    let builder = db.get_database_backend();
+    let name = format!("uniq_{}_{}", entity.table_name(), column.as_str());
+    db.execute(
+        builder.build(
+            Index::create()
+                .if_not_exists()
+                .unique()
+                .name(&name)
+                .table(entity)
+                .col(column),
+        ),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use sea_orm::{
+    prelude::Decimal, sqlx::types::chrono::Local, ActiveModelTrait, ActiveValue, ConnectionTrait,
+    EntityTrait,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    auth::{Caller, Role},
+    backup::backup_db,
+    manifest, scheduler, schema, UsrState,
+};
+
+mod donation;
+mod sponsor;
+
+#[derive(Deserialize)]
+struct PendingSponsor {
+    name: String,
+    #[serde(default)]
+    contact: String,
+}
+
+#[axum::debug_handler]
+async fn new_sponsor(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(pending_sponsor): Json<PendingSponsor>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot add sponsors", caller.role),
+        )
+            .into_response();
+    }
+    if pending_sponsor.name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    let active_model = sponsor::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(pending_sponsor.name),
+        contact: ActiveValue::Set(pending_sponsor.contact),
+    };
+
+    if let Err(e) = active_model.insert(&state.db).await {
+        error!("Failed to add sponsor: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Serialize)]
+struct SponsorSummary {
+    #[serde(flatten)]
+    sponsor: sponsor::Model,
+    pledged: Decimal,
+    received: Decimal,
+    in_kind: Decimal,
+}
+
+#[axum::debug_handler]
+async fn get_sponsors(State(state): State<&'static UsrState>) -> Response {
+    let (sponsors, donations) = tokio::join!(
+        sponsor::Entity::find().all(&state.db),
+        donation::Entity::find().all(&state.db),
+    );
+
+    let sponsors = match sponsors {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate sponsors: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let donations = match donations {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate donations: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let mut summaries: Vec<_> = sponsors
+        .into_iter()
+        .map(|sponsor| SponsorSummary {
+            sponsor,
+            pledged: Decimal::ZERO,
+            received: Decimal::ZERO,
+            in_kind: Decimal::ZERO,
+        })
+        .collect();
+
+    for model in donations {
+        let Some(summary) = summaries
+            .iter_mut()
+            .find(|x| x.sponsor.id == model.sponsor_id)
+        else {
+            continue;
+        };
+        match model.kind {
+            donation::Kind::Cash => {
+                summary.pledged += model.pledged;
+                summary.received += model.received;
+            }
+            donation::Kind::InKind => summary.in_kind += model.received,
+        }
+    }
+
+    Json(summaries).into_response()
+}
+
+#[derive(Deserialize)]
+struct PendingDonation {
+    sponsor_id: u32,
+    kind: donation::Kind,
+    #[serde(default)]
+    description: String,
+    pledged: Decimal,
+    #[serde(default)]
+    received: Decimal,
+    #[serde(default)]
+    team: Option<scheduler::Team>,
+}
+
+#[axum::debug_handler]
+async fn new_donation(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(pending_donation): Json<PendingDonation>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot record donations", caller.role),
+        )
+            .into_response();
+    }
+    if pending_donation.pledged.is_sign_negative() || pending_donation.received.is_sign_negative() {
+        return (StatusCode::BAD_REQUEST, "Amounts cannot be negative").into_response();
+    }
+    match sponsor::Entity::find_by_id(pending_donation.sponsor_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Sponsor not found").into_response(),
+        Err(e) => {
+            error!("Failed to find sponsor: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    }
+    let active_model = donation::ActiveModel {
+        id: ActiveValue::NotSet,
+        sponsor_id: ActiveValue::Set(pending_donation.sponsor_id.into()),
+        kind: ActiveValue::Set(pending_donation.kind),
+        description: ActiveValue::Set(pending_donation.description),
+        pledged: ActiveValue::Set(pending_donation.pledged),
+        received: ActiveValue::Set(pending_donation.received),
+        team: ActiveValue::Set(pending_donation.team),
+        date: ActiveValue::Set(Local::now().naive_local()),
+    };
+
+    if let Err(e) = active_model.insert(&state.db).await {
+        error!("Failed to add donation: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct ReceiveDonation {
+    id: u32,
+    received: Decimal,
+}
+
+#[axum::debug_handler]
+async fn receive_donation(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(ReceiveDonation { id, received }): Json<ReceiveDonation>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot receive donations", caller.role),
+        )
+            .into_response();
+    }
+    if received.is_sign_negative() {
+        return (StatusCode::BAD_REQUEST, "Amounts cannot be negative").into_response();
+    }
+    let active_model = donation::ActiveModel {
+        id: ActiveValue::Unchanged(id.into()),
+        sponsor_id: ActiveValue::NotSet,
+        kind: ActiveValue::NotSet,
+        description: ActiveValue::NotSet,
+        pledged: ActiveValue::NotSet,
+        received: ActiveValue::Set(received),
+        team: ActiveValue::NotSet,
+        date: ActiveValue::NotSet,
+    };
+
+    match active_model.update(&state.db).await {
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(sea_orm::DbErr::RecordNotUpdated) => (StatusCode::BAD_REQUEST, "Donation not found").into_response(),
+        Err(e) => {
+            error!("Failed to update donation: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn get_donations(State(state): State<&'static UsrState>) -> Response {
+    match donation::Entity::find().all(&state.db).await {
+        Ok(donations) => Json(donations).into_response(),
+        Err(e) => {
+            error!("Failed to enumerate donations: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+struct TeamOffset {
+    spend: Decimal,
+    in_kind: Decimal,
+    net_spend: Decimal,
+}
+
+/// Offsets each team's order spend by the value of the in-kind donations
+/// earmarked for it, once they have been received. Goods that were only
+/// pledged don't offset anything yet.
+#[axum::debug_handler]
+async fn get_offset_report(State(state): State<&'static UsrState>) -> Response {
+    let (spend, donations) = tokio::join!(
+        manifest::team_spend(&state.db),
+        donation::Entity::find().all(&state.db),
+    );
+
+    let spend = match spend {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to compute team spend: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let donations = match donations {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate donations: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let mut out = HashMap::<scheduler::Team, TeamOffset>::new();
+    for (team, spend) in spend {
+        out.entry(team).or_default().spend = spend;
+    }
+    for model in donations {
+        if model.kind != donation::Kind::InKind {
+            continue;
+        }
+        let Some(team) = model.team else {
+            continue;
+        };
+        out.entry(team).or_default().in_kind += model.received;
+    }
+    for offset in out.values_mut() {
+        offset.net_spend = offset.spend - offset.in_kind;
+    }
+
+    Json(out).into_response()
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/new/sponsor", post(new_sponsor))
+        .route("/list/sponsor", get(get_sponsors))
+        .route("/new/donation", post(new_donation))
+        .route("/receive/donation", post(receive_donation))
+        .route("/list/donation", get(get_donations))
+        .route("/report/offset", get(get_offset_report))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, sponsor::Entity).await?);
+    problems.extend(schema::verify(db, donation::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::{auth::Role, UsrState};
+
+    #[tokio::test]
+    async fn members_cannot_change_sponsors_or_donations() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [
+            (
+                Method::POST,
+                "/new/sponsor",
+                json!({ "name": "Acme", "contact": "" }),
+            ),
+            (
+                Method::POST,
+                "/new/donation",
+                json!({ "sponsor_id": 1, "kind": "Cash", "description": "", "pledged": "100", "received": "0", "team": null }),
+            ),
+            (
+                Method::POST,
+                "/receive/donation",
+                json!({ "id": 1, "received": "100" }),
+            ),
+        ];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
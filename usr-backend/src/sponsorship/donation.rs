@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "donations")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub sponsor_id: i64,
+    pub kind: Kind,
+    pub description: String,
+    /// For in-kind donations, this is the estimated value of the goods
+    pub pledged: Decimal,
+    /// For in-kind donations, the value of the goods once they have arrived,
+    /// which is zero until the donation is marked received
+    pub received: Decimal,
+    /// The team that benefits from the donation, if it was earmarked
+    #[sea_orm(nullable)]
+    pub team: Option<scheduler::Team>,
+    pub date: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Kind {
+    #[sea_orm(string_value = "C")]
+    Cash,
+    #[sea_orm(string_value = "K")]
+    InKind,
+}
@@ -0,0 +1,220 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Where uploaded files, such as receipts, are kept
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// A directory on this server, created if it doesn't exist
+    Disk { dir: String },
+    /// An S3 compatible bucket, eg. on AWS, Backblaze or a MinIO server
+    S3 {
+        /// eg. https://s3.us-west-2.amazonaws.com
+        endpoint: String,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+    },
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self::Disk {
+            dir: "attachments".to_string(),
+        }
+    }
+}
+
+impl StorageConfig {
+    pub fn validate(&self, key: &str, problems: &mut Vec<String>) {
+        match self {
+            Self::Disk { dir } => {
+                let path = std::path::Path::new(dir);
+                if path.exists() && !path.is_dir() {
+                    problems.push(format!("{key}.dir: {dir:?} is not a directory"));
+                }
+            }
+            Self::S3 {
+                endpoint,
+                bucket,
+                region,
+                access_key,
+                secret_key,
+            } => {
+                match reqwest::Url::parse(endpoint) {
+                    Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => {}
+                    Ok(_) => problems.push(format!(
+                        "{key}.endpoint: {endpoint:?} is not an http(s) url"
+                    )),
+                    Err(e) => problems.push(format!(
+                        "{key}.endpoint: {endpoint:?} is not a valid url: {e}"
+                    )),
+                }
+                for (field, value) in [
+                    ("bucket", bucket),
+                    ("region", region),
+                    ("access_key", access_key),
+                    ("secret_key", secret_key),
+                ] {
+                    if value.trim().is_empty() {
+                        problems.push(format!("{key}.{field}: is empty"));
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub enum Storage {
+    Disk(PathBuf),
+    S3 {
+        client: reqwest::Client,
+        endpoint: String,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+    },
+}
+
+impl Storage {
+    pub fn new(config: StorageConfig) -> Self {
+        match config {
+            StorageConfig::Disk { dir } => Self::Disk(PathBuf::from(dir)),
+            StorageConfig::S3 {
+                endpoint,
+                bucket,
+                region,
+                access_key,
+                secret_key,
+            } => Self::S3 {
+                client: reqwest::Client::new(),
+                endpoint: endpoint.trim_end_matches('/').to_string(),
+                bucket,
+                region,
+                access_key,
+                secret_key,
+            },
+        }
+    }
+
+    /// Stores `data` under `key`, which should only contain characters that
+    /// are safe in both paths and urls, replacing whatever was there
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> anyhow::Result<()> {
+        match self {
+            Self::Disk(dir) => {
+                let path = dir.join(key);
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::write(path, data).await?;
+            }
+            Self::S3 { .. } => {
+                self.s3(reqwest::Method::PUT, key, data).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Disk(dir) => Ok(tokio::fs::read(dir.join(key)).await?),
+            Self::S3 { .. } => Ok(self
+                .s3(reqwest::Method::GET, key, vec![])
+                .await?
+                .bytes()
+                .await?
+                .to_vec()),
+        }
+    }
+
+    /// Removes what is stored under `key`. Keys that don't exist are ignored.
+    pub async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Disk(dir) => match tokio::fs::remove_file(dir.join(key)).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            },
+            Self::S3 { .. } => {
+                self.s3(reqwest::Method::DELETE, key, vec![]).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Sends a request for the object `key`, signed with AWS signature
+    /// version 4
+    async fn s3(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Vec<u8>,
+    ) -> anyhow::Result<reqwest::Response> {
+        let Self::S3 {
+            client,
+            endpoint,
+            bucket,
+            region,
+            access_key,
+            secret_key,
+        } = self
+        else {
+            unreachable!("not an S3 bucket");
+        };
+        let url = reqwest::Url::parse(&format!("{endpoint}/{bucket}/{key}"))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => anyhow::bail!("{endpoint} has no host"),
+        };
+        let now = Utc::now();
+        let date = now.format("%Y%m%d").to_string();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{method}\n{}\n\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{timestamp}\n\n{signed_headers}\n{payload_hash}",
+            url.path()
+        );
+        let scope = format!("{date}/{region}/s3/aws4_request");
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{timestamp}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut signing_key = format!("AWS4{secret_key}").into_bytes();
+        for part in [date.as_str(), region, "s3", "aws4_request"] {
+            signing_key = hmac(&signing_key, part.as_bytes());
+        }
+        let signature = hex::encode(hmac(&signing_key, string_to_sign.as_bytes()));
+
+        let response = client
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", timestamp)
+            .header(
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={access_key}/{scope}, SignedHeaders={signed_headers}, Signature={signature}"
+                ),
+            )
+            .body(body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("{bucket}/{key}: {}", response.status());
+        }
+        Ok(response)
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use sea_orm::{
+    prelude::{Date, Decimal},
+    sqlx::types::chrono::Local,
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    auth::{Caller, Role},
+    backup::backup_db,
+    schema, UsrState,
+};
+
+mod expense;
+mod trip;
+
+pub use trip::Model as Trip;
+
+/// The first trip that starts after `today`, such as the next competition
+pub async fn next_trip(db: &DatabaseConnection, today: Date) -> Result<Option<Trip>, sea_orm::DbErr> {
+    trip::Entity::find()
+        .filter(trip::Column::StartDate.gt(today))
+        .order_by_asc(trip::Column::StartDate)
+        .one(db)
+        .await
+}
+
+#[derive(Deserialize)]
+struct PendingTrip {
+    name: String,
+    #[serde(default)]
+    destination: String,
+    start_date: Date,
+    end_date: Date,
+    budget: Decimal,
+}
+
+#[axum::debug_handler]
+async fn new_trip(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(pending_trip): Json<PendingTrip>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot plan trips", caller.role),
+        )
+            .into_response();
+    }
+    if pending_trip.name.is_empty() {
+        return (StatusCode::BAD_REQUEST, "").into_response();
+    }
+    if pending_trip.end_date < pending_trip.start_date {
+        return (StatusCode::BAD_REQUEST, "Trip ends before it starts").into_response();
+    }
+    if pending_trip.budget.is_sign_negative() {
+        return (StatusCode::BAD_REQUEST, "Budget cannot be negative").into_response();
+    }
+    let active_model = trip::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(pending_trip.name),
+        destination: ActiveValue::Set(pending_trip.destination),
+        start_date: ActiveValue::Set(pending_trip.start_date),
+        end_date: ActiveValue::Set(pending_trip.end_date),
+        budget: ActiveValue::Set(pending_trip.budget),
+    };
+
+    if let Err(e) = active_model.insert(&state.db).await {
+        error!("Failed to add trip: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteTrip {
+    id: u32,
+}
+
+#[axum::debug_handler]
+async fn del_trip(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(DeleteTrip { id }): Json<DeleteTrip>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot delete trips", caller.role),
+        )
+            .into_response();
+    }
+    let result = state
+        .db
+        .transaction(|tx| {
+            Box::pin(async move {
+                expense::Entity::delete_many()
+                    .filter(expense::Column::TripId.eq(id))
+                    .exec(tx)
+                    .await?;
+                let result = trip::Entity::delete_by_id(id).exec(tx).await?;
+                Result::<_, sea_orm::DbErr>::Ok(result.rows_affected)
+            })
+        })
+        .await;
+
+    match result {
+        Ok(0) => (StatusCode::BAD_REQUEST, "Trip not found").into_response(),
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete trip: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PendingExpense {
+    trip_id: u32,
+    category: expense::Category,
+    #[serde(default)]
+    member: Option<String>,
+    #[serde(default)]
+    description: String,
+    amount: Decimal,
+}
+
+#[axum::debug_handler]
+async fn new_expense(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(pending_expense): Json<PendingExpense>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot record trip expenses", caller.role),
+        )
+            .into_response();
+    }
+    if pending_expense.amount.is_sign_negative() {
+        return (StatusCode::BAD_REQUEST, "Amount cannot be negative").into_response();
+    }
+    match trip::Entity::find_by_id(pending_expense.trip_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Trip not found").into_response(),
+        Err(e) => {
+            error!("Failed to find trip: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    }
+    let active_model = expense::ActiveModel {
+        id: ActiveValue::NotSet,
+        trip_id: ActiveValue::Set(pending_expense.trip_id.into()),
+        category: ActiveValue::Set(pending_expense.category),
+        member: ActiveValue::Set(pending_expense.member.filter(|x| !x.is_empty())),
+        description: ActiveValue::Set(pending_expense.description),
+        amount: ActiveValue::Set(pending_expense.amount),
+        date: ActiveValue::Set(Local::now().naive_local()),
+    };
+
+    if let Err(e) = active_model.insert(&state.db).await {
+        error!("Failed to add travel expense: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    } else {
+        backup_db(state);
+        (StatusCode::OK, "").into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteExpense {
+    id: u32,
+}
+
+#[axum::debug_handler]
+async fn del_expense(
+    State(state): State<&'static UsrState>,
+    caller: Caller,
+    Json(DeleteExpense { id }): Json<DeleteExpense>,
+) -> Response {
+    if caller.role < Role::Lead {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("{} cannot delete trip expenses", caller.role),
+        )
+            .into_response();
+    }
+    match expense::Entity::delete_by_id(id).exec(&state.db).await {
+        Ok(result) if result.rows_affected == 0 => (StatusCode::BAD_REQUEST, "Expense not found").into_response(),
+        Ok(_) => {
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete travel expense: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn get_expenses(
+    State(state): State<&'static UsrState>,
+    Path(trip_id): Path<u32>,
+) -> Response {
+    match expense::Entity::find()
+        .filter(expense::Column::TripId.eq(trip_id))
+        .order_by_asc(expense::Column::Date)
+        .all(&state.db)
+        .await
+    {
+        Ok(expenses) => Json(expenses).into_response(),
+        Err(e) => {
+            error!("Failed to enumerate travel expenses: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TripRollup {
+    #[serde(flatten)]
+    trip: trip::Model,
+    spent: Decimal,
+    remaining: Decimal,
+    by_category: HashMap<expense::Category, Decimal>,
+    by_member: HashMap<String, Decimal>,
+}
+
+#[axum::debug_handler]
+async fn get_trips(State(state): State<&'static UsrState>) -> Response {
+    let (trips, expenses) = tokio::join!(
+        trip::Entity::find()
+            .order_by_desc(trip::Column::StartDate)
+            .all(&state.db),
+        expense::Entity::find().all(&state.db),
+    );
+
+    let trips = match trips {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate trips: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let expenses = match expenses {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to enumerate travel expenses: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    };
+
+    let mut rollups: Vec<_> = trips
+        .into_iter()
+        .map(|trip| TripRollup {
+            remaining: trip.budget,
+            trip,
+            spent: Decimal::ZERO,
+            by_category: HashMap::new(),
+            by_member: HashMap::new(),
+        })
+        .collect();
+
+    for model in expenses {
+        let Some(rollup) = rollups.iter_mut().find(|x| x.trip.id == model.trip_id) else {
+            continue;
+        };
+        rollup.spent += model.amount;
+        rollup.remaining -= model.amount;
+        *rollup.by_category.entry(model.category).or_default() += model.amount;
+        if let Some(member) = model.member {
+            *rollup.by_member.entry(member).or_default() += model.amount;
+        }
+    }
+
+    Json(rollups).into_response()
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/new/trip", post(new_trip))
+        .route("/del/trip", delete(del_trip))
+        .route("/list/trip", get(get_trips))
+        .route("/new/expense", post(new_expense))
+        .route("/del/expense", delete(del_expense))
+        .route("/list/expense/{trip_id}", get(get_expenses))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, trip::Entity).await?);
+    problems.extend(schema::verify(db, expense::Entity).await?);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::{auth::Role, UsrState};
+
+    #[tokio::test]
+    async fn members_cannot_change_trips_or_expenses() {
+        let state = UsrState::for_tests("sqlite::memory:").await;
+        let requests = [
+            (
+                Method::POST,
+                "/new/trip",
+                json!({ "name": "Regionals", "destination": "Houston", "start_date": "2026-04-01", "end_date": "2026-04-04", "budget": "1000" }),
+            ),
+            (Method::DELETE, "/del/trip", json!({ "id": 1 })),
+            (
+                Method::POST,
+                "/new/expense",
+                json!({ "trip_id": 1, "category": "Lodging", "member": null, "description": "", "amount": "100" }),
+            ),
+            (Method::DELETE, "/del/expense", json!({ "id": 1 })),
+        ];
+        for (method, uri, body) in requests {
+            assert_eq!(
+                state
+                    .call_as(super::router(), Role::Member, method, uri, body)
+                    .await,
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
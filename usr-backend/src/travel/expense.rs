@@ -0,0 +1,41 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "travel_expenses")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub trip_id: i64,
+    pub category: Category,
+    /// The member this cost is attributed to, if it is not shared by the whole trip
+    #[sea_orm(nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member: Option<String>,
+    pub description: String,
+    pub amount: Decimal,
+    pub date: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Hash, Copy, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(1))")]
+pub enum Category {
+    #[sea_orm(string_value = "T")]
+    Transportation,
+    #[sea_orm(string_value = "L")]
+    Lodging,
+    #[sea_orm(string_value = "V")]
+    VehicleRental,
+    #[sea_orm(string_value = "R")]
+    Registration,
+    #[sea_orm(string_value = "M")]
+    Meals,
+    #[sea_orm(string_value = "O")]
+    Other,
+}
@@ -1,22 +1,311 @@
-use std::{collections::HashMap, time::Instant};
+use std::{collections::HashMap, time::{Duration, Instant}};
 
-use discord_webhook2::{message::Message, webhook::DiscordWebhook};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use discord_webhook2::{error::DiscordWebhookError, message::Message, webhook::DiscordWebhook};
 use parking_lot::Mutex;
-use tracing::error;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::{debug, error, warn};
+
+use crate::{
+    jobs::{self, Retry, Schedule},
+    manifest,
+    notify::{Embed, Notification, NotificationDispatcher},
+    schema, UsrState,
+};
+
+mod delivery;
+mod outbox;
+mod sink;
+mod team;
+
+pub use sink::Sink;
+pub use team::load as load_team_webhooks;
+
+/// Discord rejects messages longer than this
+const MAX_MESSAGE_LEN: usize = 2000;
+const PREFIX: &str = ">>> ";
+/// Messages that fail this many times are left for an admin to resend
+const MAX_ATTEMPTS: i64 = 10;
+/// Waited before the first retry, and doubled before each one after
+const FIRST_BACKOFF_MINS: i64 = 1;
+
+/// Checks that `url` is a Discord webhook's, eg. https://discord.com/api/webhooks/...
+pub fn check_url(url: &str) -> Result<(), String> {
+    match url.parse::<axum::http::Uri>() {
+        Ok(uri)
+            if uri.scheme_str() == Some("https") && uri.path().starts_with("/api/webhooks/") =>
+        {
+            Ok(())
+        }
+        Ok(_) => Err(format!(
+            "{url:?} is not a Discord webhook url, eg. https://discord.com/api/webhooks/..."
+        )),
+        Err(e) => Err(format!("{url:?} is not a valid url: {e}")),
+    }
+}
+
+/// When a message that has failed `attempts` times is retried, if it is
+fn next_attempt(attempts: i64, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    (attempts < MAX_ATTEMPTS)
+        .then(|| now + TimeDelta::minutes(FIRST_BACKOFF_MINS << (attempts - 1)))
+}
+
+/// Ids as they are stored with deliveries, with leading and trailing commas
+fn join_ids(ids: &[u32]) -> String {
+    let mut joined = String::from(",");
+    for id in ids {
+        joined.push_str(&id.to_string());
+        joined.push(',');
+    }
+    joined
+}
+
+fn split_ids(joined: &str) -> Vec<u32> {
+    joined.split(',').filter_map(|id| id.parse().ok()).collect()
+}
+
+/// Splits a message into pieces no longer than `limit`, breaking between
+/// lines so that fields are kept whole. Lines that are too long on their own
+/// are cut wherever they need to be.
+fn split_message(msg: &str, limit: usize) -> Vec<String> {
+    let mut pieces = vec![];
+    let mut current = String::new();
+
+    for mut line in msg.split('\n') {
+        while line.len() > limit {
+            let mut cut = limit;
+            while !line.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            if !current.is_empty() {
+                pieces.push(std::mem::take(&mut current));
+            }
+            pieces.push(line[..cut].to_string());
+            line = &line[cut..];
+        }
+        if !current.is_empty() && current.len() + 1 + line.len() > limit {
+            pieces.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    pieces
+}
 
 struct Locked {
     queue: HashMap<u32, String>,
     deadline: Option<Instant>,
+    /// When each message was last queued, by the hash of its content
+    recent: HashMap<[u8; 32], Instant>,
 }
 
 pub struct BatchedWebhook {
     locked: Mutex<Locked>,
     discord: DiscordWebhook,
+    /// Name recorded in the delivery history, eg. `new_orders`
+    destination: String,
+    db: DatabaseConnection,
+    /// How long a message is remembered, so that the same message queued
+    /// again is dropped, eg. when an order's change is submitted twice
+    dedupe_window: Duration,
 }
 
 impl BatchedWebhook {
+    pub fn new(
+        destination: impl Into<String>,
+        discord: DiscordWebhook,
+        db: DatabaseConnection,
+        dedupe_window: Duration,
+    ) -> Self {
+        Self {
+            locked: Mutex::new(Locked {
+                queue: HashMap::new(),
+                deadline: None,
+                recent: HashMap::new(),
+            }),
+            discord,
+            destination: destination.into(),
+            db,
+            dedupe_window,
+        }
+    }
+
+    /// Sends `content`, or `embed` if there is one, and records the attempt,
+    /// whether or not it succeeded, against every id that contributed to it.
+    async fn attempt(
+        &self,
+        content: &str,
+        embed: Option<&Embed>,
+        ids: &[u32],
+    ) -> Result<(), (Option<u16>, String)> {
+        let start = Instant::now();
+        let message = match embed {
+            Some(embed) => Message::new(|message| {
+                message.content(&embed.content).embed(|builder| {
+                    let mut builder = builder.title(&embed.title).color(embed.color);
+                    for (name, value) in &embed.fields {
+                        builder = builder.field(|field| field.name(name).value(value));
+                    }
+                    builder
+                })
+            }),
+            None => Message::new(|message| message.content(content)),
+        };
+        let result = self.discord.send(&message).await;
+
+        let result = match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("Failed to trigger webhook: {e}");
+                let status_code = match &e {
+                    DiscordWebhookError::ReqwestError(e) => e.status().map(|s| s.as_u16()),
+                    _ => None,
+                };
+                Err((status_code, e.to_string()))
+            }
+        };
+        let recorded = result.clone();
+        record_delivery(&self.db, &self.destination, ids, content, start, recorded).await;
+        result
+    }
+
+    /// Stores `content` and `embed` in the outbox, then tries to send them.
+    /// They stay there to be retried if they can't be sent.
+    async fn send(&self, content: String, embed: Option<&Embed>, ids: &[u32]) {
+        let now = Local::now().naive_local();
+        let stored = outbox::ActiveModel {
+            id: ActiveValue::NotSet,
+            destination: ActiveValue::Set(self.destination.clone()),
+            ids: ActiveValue::Set(join_ids(ids)),
+            content: ActiveValue::Set(content.clone()),
+            embed: ActiveValue::Set(embed.and_then(|embed| serde_json::to_string(embed).ok())),
+            attempts: ActiveValue::Set(0),
+            // Left alone by the retries until this attempt would have failed
+            next_attempt: ActiveValue::Set(next_attempt(1, now)),
+            last_error: ActiveValue::Set(None),
+            created: ActiveValue::Set(now),
+        }
+        .insert(&self.db)
+        .await;
+        match stored {
+            Ok(model) => {
+                let _ = self.retry(model).await;
+            }
+            Err(e) => {
+                // Better sent without a safety net than not at all
+                error!("Failed to store webhook message: {e}");
+                let _ = self.attempt(&content, embed, ids).await;
+            }
+        }
+    }
+
+    /// Tries to send a message from the outbox, removing it if it was sent
+    /// and pushing its next attempt back if it wasn't
+    async fn retry(&self, model: outbox::Model) -> Result<(), String> {
+        let embed = model
+            .embed
+            .as_deref()
+            .and_then(|embed| serde_json::from_str::<Embed>(embed).ok());
+        let result = self
+            .attempt(&model.content, embed.as_ref(), &split_ids(&model.ids))
+            .await;
+        let update = match result {
+            Ok(()) => outbox::Entity::delete_by_id(model.id)
+                .exec(&self.db)
+                .await
+                .map(|_| ()),
+            Err((_, ref e)) => {
+                let attempts = model.attempts + 1;
+                let next = next_attempt(attempts, Local::now().naive_local());
+                if next.is_none() {
+                    warn!(
+                        "Giving up on webhook message {} to {} after {attempts} attempts",
+                        model.id, self.destination
+                    );
+                }
+                let mut active_model: outbox::ActiveModel = model.into();
+                active_model.attempts = ActiveValue::Set(attempts);
+                active_model.next_attempt = ActiveValue::Set(next);
+                active_model.last_error = ActiveValue::Set(Some(e.clone()));
+                active_model.update(&self.db).await.map(|_| ())
+            }
+        };
+        if let Err(e) = update {
+            error!("Failed to update webhook outbox: {e}");
+        }
+        result.map_err(|(_, e)| e)
+    }
+
+    /// Sends queued messages, packed into as few Discord messages as fit
+    async fn send_queue(&self, queue: HashMap<u32, String>) {
+        let mut running = String::from(PREFIX);
+        let mut running_ids = vec![];
+        for (id, msg) in queue {
+            for piece in split_message(&msg, MAX_MESSAGE_LEN - PREFIX.len() - 1) {
+                if running.len() + piece.len() + 1 > MAX_MESSAGE_LEN {
+                    self.send(
+                        std::mem::replace(&mut running, String::from(PREFIX)),
+                        None,
+                        &running_ids,
+                    )
+                    .await;
+                    running_ids.clear();
+                }
+                running.push_str(&piece);
+                running.push('\n');
+                if running_ids.last() != Some(&id) {
+                    running_ids.push(id);
+                }
+            }
+        }
+        if running.len() > PREFIX.len() {
+            self.send(running, None, &running_ids).await;
+        }
+    }
+
+    /// Sends whatever is queued now instead of at the end of the batch, eg.
+    /// before the server stops. The batch's task finds the queue empty when
+    /// it wakes.
+    pub async fn flush(&self) {
+        let queue = std::mem::take(&mut self.locked.lock().queue);
+        if !queue.is_empty() {
+            self.send_queue(queue).await;
+        }
+    }
+
     pub fn enqueue(&'static self, id: u32, message: String) {
         let mut guard = self.locked.lock();
+        if !self.dedupe_window.is_zero() {
+            let now = Instant::now();
+            let window = self.dedupe_window;
+            guard.recent.retain(|_, queued| now.duration_since(*queued) < window);
+            let hash: [u8; 32] = Sha256::digest(message.trim().as_bytes()).into();
+            // A repeat still replaces a different message queued under the
+            // same id, so that the latest one is what gets sent
+            let replaces = guard.queue.get(&id).is_some_and(|queued| *queued != message);
+            if guard.recent.insert(hash, now).is_some() && !replaces {
+                debug!("Dropped a repeated message to {}", self.destination);
+                return;
+            }
+        }
         guard.queue.insert(id, message);
         let was_none = guard.deadline.is_none();
         guard.deadline = Some(Instant::now() + std::time::Duration::from_secs(60 * 5));
@@ -36,28 +325,7 @@ impl BatchedWebhook {
                         let replacement = HashMap::with_capacity(guard.queue.capacity());
                         queue = std::mem::replace(&mut guard.queue, replacement);
                     }
-                    let mut running = String::from(">>> ");
-                    for (_, msg) in queue {
-                        if running.len() + msg.len() + 1 < 2000 {
-                            running.push_str(&msg);
-                            running.push_str("\n");
-                        } else {
-                            if let Err(e) = self.discord
-                                .send(&Message::new(|message| message.content(running)))
-                                .await
-                            {
-                                error!("Failed to trigger webhook: {e}");
-                            }
-                            running = String::from(">>> ");
-                            running.push_str(&msg);
-                        }
-                    }
-                    if let Err(e) = self.discord
-                        .send(&Message::new(|message| message.content(running)))
-                        .await
-                    {
-                        error!("Failed to trigger webhook: {e}");
-                    }
+                    self.send_queue(queue).await;
                     let mut guard = self.locked.lock();
                     if guard.queue.is_empty() {
                         guard.deadline = None;
@@ -69,14 +337,250 @@ impl BatchedWebhook {
     }
 }
 
-impl From<DiscordWebhook> for BatchedWebhook {
-    fn from(discord: DiscordWebhook) -> Self {
-        Self {
-            locked: Mutex::new(Locked {
-                queue: HashMap::new(),
-                deadline: None,
-            }),
-            discord,
+impl NotificationDispatcher for BatchedWebhook {
+    fn name(&self) -> &str {
+        &self.destination
+    }
+
+    /// Embeds are sent on their own rather than batched, as each is already
+    /// a summary, eg. a digest
+    fn dispatch(&'static self, notification: &Notification) {
+        match &notification.embed {
+            Some(embed) => {
+                let notification = notification.clone();
+                let embed = embed.clone();
+                tokio::spawn(async move {
+                    self.send(notification.text, Some(&embed), &[notification.key])
+                        .await;
+                });
+            }
+            None => self.enqueue(notification.key, notification.text.clone()),
+        }
+    }
+
+    fn webhook(&self) -> Option<&BatchedWebhook> {
+        Some(self)
+    }
+}
+
+/// Retries every message in the outbox whose next attempt is due, each with
+/// the webhook it was meant for
+async fn retry_due(state: &'static UsrState) -> Result<(), sea_orm::DbErr> {
+    let due = outbox::Entity::find()
+        .filter(outbox::Column::NextAttempt.lte(Local::now().naive_local()))
+        .order_by_asc(outbox::Column::Id)
+        .all(&state.db)
+        .await?;
+    for model in due {
+        match state.notifier.webhook(&model.destination) {
+            Some(webhook) => {
+                let _ = webhook.retry(model).await;
+            }
+            // Left in place in case the webhook is configured again
+            None => warn!(
+                "Webhook message {} is for {}, which is not configured",
+                model.id, model.destination
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Periodically retries webhook messages that couldn't be sent, backing off
+/// further after each failure
+pub fn spawn_retries(state: &'static UsrState) {
+    // Each message backs off on its own, so a failed sweep waits for the next
+    jobs::spawn(
+        state,
+        "webhook_retries",
+        Schedule::Every(Duration::from_secs(60)),
+        Retry::NEVER,
+        |state| async move { Ok(retry_due(state).await?) },
+    );
+}
+
+/// Records an attempt to deliver `content` in the history, against every id
+/// that contributed to it. Failures carry the status code if there was a
+/// response.
+pub async fn record_delivery(
+    db: &DatabaseConnection,
+    destination: &str,
+    ids: &[u32],
+    content: &str,
+    start: Instant,
+    result: Result<(), (Option<u16>, String)>,
+) {
+    let latency_ms = start.elapsed().as_millis().try_into().unwrap_or(i64::MAX);
+    let payload_hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+    let joined = join_ids(ids);
+    let (success, status_code, error) = match result {
+        Ok(()) => (true, Some(200), None),
+        Err((status_code, error)) => (false, status_code, Some(error)),
+    };
+
+    let active_model = delivery::ActiveModel {
+        id: ActiveValue::NotSet,
+        destination: ActiveValue::Set(destination.to_string()),
+        ids: ActiveValue::Set(joined),
+        payload_hash: ActiveValue::Set(payload_hash),
+        success: ActiveValue::Set(success),
+        status_code: ActiveValue::Set(status_code.map(i64::from)),
+        error: ActiveValue::Set(error),
+        latency_ms: ActiveValue::Set(latency_ms),
+        date: ActiveValue::Set(Local::now().naive_local()),
+    };
+    if let Err(e) = active_model.insert(db).await {
+        error!("Failed to record webhook delivery: {e}");
+    }
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    order_id: Option<manifest::OrderRef>,
+}
+
+/// Every webhook send attempt, newest first, optionally narrowed to the
+/// messages that mentioned a given order.
+#[axum::debug_handler]
+async fn get_history(
+    State(state): State<&'static UsrState>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    let mut select = delivery::Entity::find();
+    let mut order_id = None;
+    if let Some(order) = query.order_id {
+        // Deliveries outlive deleted orders, so ids are taken as given
+        let id = match order.as_id() {
+            Some(id) => id,
+            None => match manifest::resolve_order(&state.db, &order).await {
+                Ok(id) => id,
+                Err(response) => return response.into_response(),
+            },
+        };
+        // The commas around each id keep eg. 1 from matching 10 or 21
+        select = select.filter(delivery::Column::Ids.contains(format!(",{id},")));
+        order_id = Some(id);
+    }
+    match select
+        .order_by_desc(delivery::Column::Date)
+        .all(&state.db)
+        .await
+    {
+        Ok(mut deliveries) => {
+            if let Some(order_id) = order_id {
+                deliveries.retain(|delivery| split_ids(&delivery.ids).contains(&(order_id as u32)));
+            }
+            Json(deliveries).into_response()
+        }
+        Err(e) => {
+            error!("Failed to enumerate webhook deliveries: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Messages still waiting to be delivered, oldest first, including those
+/// that have run out of attempts
+#[axum::debug_handler]
+async fn get_pending(State(state): State<&'static UsrState>) -> Response {
+    match outbox::Entity::find()
+        .order_by_asc(outbox::Column::Id)
+        .all(&state.db)
+        .await
+    {
+        Ok(pending) => Json(pending).into_response(),
+        Err(e) => {
+            error!("Failed to enumerate pending webhook messages: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PendingRef {
+    id: u32,
+}
+
+async fn find_pending(
+    db: &DatabaseConnection,
+    id: u32,
+) -> Result<outbox::Model, (StatusCode, &'static str)> {
+    match outbox::Entity::find_by_id(id).one(db).await {
+        Ok(Some(model)) => Ok(model),
+        Ok(None) => Err((StatusCode::BAD_REQUEST, "Message not found")),
+        Err(e) => {
+            error!("Failed to find pending webhook message: {e}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, ""))
+        }
+    }
+}
+
+/// Sends a pending message now, such as one that ran out of attempts while
+/// Discord was down. It keeps backing off from where it was if it fails.
+#[axum::debug_handler]
+async fn resend_pending(
+    State(state): State<&'static UsrState>,
+    Json(PendingRef { id }): Json<PendingRef>,
+) -> Response {
+    let model = match find_pending(&state.db, id).await {
+        Ok(model) => model,
+        Err(response) => return response.into_response(),
+    };
+    let Some(webhook) = state.notifier.webhook(&model.destination) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("{} is not configured", model.destination),
+        )
+            .into_response();
+    };
+    match webhook.retry(model).await {
+        Ok(()) => (StatusCode::OK, "").into_response(),
+        Err(e) => (StatusCode::BAD_GATEWAY, e).into_response(),
+    }
+}
+
+/// Drops a pending message that shouldn't be sent after all
+#[axum::debug_handler]
+async fn del_pending(
+    State(state): State<&'static UsrState>,
+    Json(PendingRef { id }): Json<PendingRef>,
+) -> Response {
+    if let Err(response) = find_pending(&state.db, id).await {
+        return response.into_response();
+    }
+    match outbox::Entity::delete_by_id(id).exec(&state.db).await {
+        Ok(_) => (StatusCode::OK, "").into_response(),
+        Err(e) => {
+            error!("Failed to delete pending webhook message: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/history", get(get_history))
+        .route("/pending", get(get_pending))
+        .route("/resend", post(resend_pending))
+        .route("/del/pending", delete(del_pending))
+        .route("/set/team", post(team::set_team_webhook))
+        .route("/del/team", delete(team::del_team_webhook))
+        .route("/list/team", get(team::get_team_webhooks))
+}
+
+/// A fake Discord for development. See `webhook_sink` in the config.
+pub fn dev_router() -> Router<&'static UsrState> {
+    Router::new()
+        .route("/webhook-sink", get(sink::list))
+        .route("/webhook-sink/{destination}", post(sink::receive))
+}
+
+pub async fn verify_tables(
+    db: &impl ConnectionTrait,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let mut problems = vec![];
+    problems.extend(schema::verify(db, delivery::Entity).await?);
+    problems.extend(schema::verify(db, outbox::Entity).await?);
+    problems.extend(schema::verify(db, team::Entity).await?);
+    Ok(problems)
+}
@@ -0,0 +1,28 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "webhook_deliveries")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Which configured webhook this was sent to, eg. `new_orders`
+    pub destination: String,
+    /// Comma delimited ids (usually order ids) batched into this message,
+    /// with leading and trailing commas so that `,{id},` only matches that id
+    pub ids: String,
+    /// SHA-256 of the message content
+    pub payload_hash: String,
+    pub success: bool,
+    #[sea_orm(nullable)]
+    pub status_code: Option<i64>,
+    #[sea_orm(nullable)]
+    pub error: Option<String>,
+    pub latency_ms: i64,
+    pub date: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,34 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+/// A webhook message that hasn't been delivered yet. Messages are stored
+/// before they are sent and removed once Discord accepts them, so that none
+/// are lost while it is down or rate limiting us.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "webhook_outbox")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Which configured webhook this is for, eg. `new_orders`
+    pub destination: String,
+    /// Delimited like the ids of a delivery
+    pub ids: String,
+    /// Recorded in the delivery history, and sent unless there is an `embed`
+    pub content: String,
+    /// The embed the message is sent as instead, as JSON
+    #[sea_orm(nullable)]
+    pub embed: Option<String>,
+    pub attempts: i64,
+    /// When it is retried next. Messages that have run out of attempts are
+    /// only sent again by hand.
+    #[sea_orm(nullable)]
+    pub next_attempt: Option<DateTime>,
+    #[sea_orm(nullable)]
+    pub last_error: Option<String>,
+    pub created: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Local, NaiveDateTime};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::UsrState;
+
+/// Only the most recent messages are kept
+const CAPACITY: usize = 1000;
+
+#[derive(Serialize, Clone)]
+pub struct SinkMessage {
+    id: u64,
+    destination: String,
+    content: String,
+    received: NaiveDateTime,
+}
+
+/// Stands in for Discord during development, so that webhook messages can be
+/// inspected without spamming the real channels.
+#[derive(Default)]
+pub struct Sink {
+    received: Mutex<VecDeque<SinkMessage>>,
+}
+
+#[derive(Deserialize)]
+pub struct DiscordMessage {
+    #[serde(default)]
+    content: String,
+}
+
+/// Accepts a message the way Discord's webhook API does. With `?wait=true`
+/// Discord echoes the message back with an id, which the client requires.
+#[axum::debug_handler]
+pub async fn receive(
+    State(state): State<&'static UsrState>,
+    Path(destination): Path<String>,
+    Json(message): Json<DiscordMessage>,
+) -> Response {
+    let mut received = state.webhook_sink.received.lock();
+    let id = received.back().map_or(1, |last| last.id + 1);
+    if received.len() == CAPACITY {
+        received.pop_front();
+    }
+    received.push_back(SinkMessage {
+        id,
+        destination,
+        content: message.content.clone(),
+        received: Local::now().naive_local(),
+    });
+
+    Json(serde_json::json!({
+        "id": id.to_string(),
+        "content": message.content,
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct SinkQuery {
+    destination: Option<String>,
+}
+
+#[axum::debug_handler]
+pub async fn list(
+    State(state): State<&'static UsrState>,
+    Query(SinkQuery { destination }): Query<SinkQuery>,
+) -> Response {
+    let received: Vec<_> = state
+        .webhook_sink
+        .received
+        .lock()
+        .iter()
+        .filter(|message| {
+            destination
+                .as_ref()
+                .is_none_or(|destination| *destination == message.destination)
+        })
+        .cloned()
+        .collect();
+    Json(received).into_response()
+}
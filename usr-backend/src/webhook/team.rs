@@ -0,0 +1,164 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, QueryOrder};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{backup::backup_db, notify::Notifier, notify::Topic, scheduler::Team, UsrState};
+
+/// A Discord webhook of a team's own channel, which the team's notifications
+/// about `topic` go to instead of the global webhook for it. A team can have
+/// several for the same topic, and they are all sent to.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "team_webhooks")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub team: Team,
+    pub topic: Topic,
+    pub url: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Starts sending to every team webhook, once at startup
+pub async fn load(notifier: &Notifier, db: &DatabaseConnection) -> anyhow::Result<()> {
+    for model in Entity::find().all(db).await? {
+        notifier.set_team_webhook(model.id, model.team, model.topic, &model.url)?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct SetTeamWebhook {
+    /// The team webhook to change, or a new one if left out
+    #[serde(default)]
+    id: Option<u32>,
+    team: Team,
+    topic: Topic,
+    url: String,
+}
+
+/// Sends a team's notifications about a topic to its own channel, or changes
+/// where they go
+#[axum::debug_handler]
+pub async fn set_team_webhook(
+    State(state): State<&'static UsrState>,
+    Json(set): Json<SetTeamWebhook>,
+) -> Response {
+    let url = set.url.trim().to_string();
+    if let Err(msg) = super::check_url(&url) {
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+    let duplicate = Entity::find()
+        .filter(Column::Team.eq(set.team))
+        .filter(Column::Topic.eq(set.topic))
+        .filter(Column::Url.eq(&url))
+        .one(&state.db)
+        .await;
+    match duplicate {
+        Ok(Some(existing)) if set.id.map(i64::from) != Some(existing.id) => {
+            return (
+                StatusCode::CONFLICT,
+                format!(
+                    "{}'s {:?} notifications already go to that webhook",
+                    set.team, set.topic
+                ),
+            )
+                .into_response();
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to find team webhook: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "").into_response();
+        }
+    }
+
+    let mut active_model = ActiveModel {
+        id: ActiveValue::NotSet,
+        team: ActiveValue::Set(set.team),
+        topic: ActiveValue::Set(set.topic),
+        url: ActiveValue::Set(url),
+    };
+    let result = match set.id {
+        Some(id) => match Entity::find_by_id(id).one(&state.db).await {
+            Ok(Some(_)) => {
+                active_model.id = ActiveValue::Unchanged(id.into());
+                active_model.update(&state.db).await
+            }
+            Ok(None) => return (StatusCode::BAD_REQUEST, "Team webhook not found").into_response(),
+            Err(e) => Err(e),
+        },
+        None => active_model.insert(&state.db).await,
+    };
+
+    match result {
+        Ok(model) => {
+            if let Err(e) =
+                state
+                    .notifier
+                    .set_team_webhook(model.id, model.team, model.topic, &model.url)
+            {
+                error!("Failed to set up team webhook {}: {e}", model.id);
+            }
+            backup_db(state);
+            Json(model).into_response()
+        }
+        Err(e) => {
+            error!("Failed to set team webhook: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteTeamWebhook {
+    id: u32,
+}
+
+/// Sends a team's notifications back to the global webhook, unless it has
+/// another one for the topic
+#[axum::debug_handler]
+pub async fn del_team_webhook(
+    State(state): State<&'static UsrState>,
+    Json(DeleteTeamWebhook { id }): Json<DeleteTeamWebhook>,
+) -> Response {
+    match Entity::delete_by_id(id).exec(&state.db).await {
+        Ok(result) if result.rows_affected == 0 => {
+            (StatusCode::BAD_REQUEST, "Team webhook not found").into_response()
+        }
+        Ok(_) => {
+            state.notifier.remove_team_webhook(id.into());
+            backup_db(state);
+            (StatusCode::OK, "").into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete team webhook: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}
+
+/// Every team webhook, by team
+#[axum::debug_handler]
+pub async fn get_team_webhooks(State(state): State<&'static UsrState>) -> Response {
+    match Entity::find()
+        .order_by_asc(Column::Team)
+        .order_by_asc(Column::Id)
+        .all(&state.db)
+        .await
+    {
+        Ok(models) => Json(models).into_response(),
+        Err(e) => {
+            error!("Failed to get team webhooks: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        }
+    }
+}